@@ -1,17 +1,19 @@
 use crate::error::ContractError;
 use crate::state::{
-    read_borrower_info, read_borrowers, read_config, remove_borrower_info, store_borrower_info,
-    BorrowerInfo, Config,
+    read_borrower_info, read_borrowers, read_config, read_migration_source, read_state,
+    remove_borrower_info, remove_borrowers, remove_migration_source, store_borrower_info,
+    store_migration_source, store_state, BorrowerInfo, Config, State,
 };
 
 use cosmwasm_bignumber::Uint256;
 use cosmwasm_std::{
-    attr, to_binary, Addr, CanonicalAddr, CosmosMsg, Deps, DepsMut, MessageInfo, Response,
-    StdResult, WasmMsg,
+    attr, to_binary, Addr, CanonicalAddr, CosmosMsg, Deps, DepsMut, Env, MessageInfo, Response,
+    StdError, StdResult, WasmMsg,
 };
 use cw20::Cw20ExecuteMsg;
-use moneymarket::custody::{BorrowerResponse, BorrowersResponse};
+use moneymarket::custody::{BorrowerResponse, BorrowersResponse, Cw20HookMsg};
 use moneymarket::liquidation::Cw20HookMsg as LiquidationCw20HookMsg;
+use moneymarket::querier::query_token_balance;
 use terra_cosmwasm::TerraMsgWrapper;
 
 /// Deposit new collateral
@@ -28,7 +30,11 @@ pub fn deposit_collateral(
     borrower_info.balance += amount;
     borrower_info.spendable += amount;
 
+    let mut state: State = read_state(deps.storage)?;
+    state.total_balance += amount;
+
     store_borrower_info(deps.storage, &borrower_raw, &borrower_info)?;
+    store_state(deps.storage, &state)?;
 
     Ok(Response::new().add_attributes(vec![
         attr("action", "deposit_collateral"),
@@ -38,15 +44,24 @@ pub fn deposit_collateral(
 }
 
 /// Withdraw spendable collateral or a specified amount of collateral
-/// Executor: borrower
+/// Executor: borrower, or overseer on behalf of `borrower`
 pub fn withdraw_collateral(
     deps: DepsMut,
     info: MessageInfo,
+    borrower: Option<Addr>,
     amount: Option<Uint256>,
 ) -> Result<Response<TerraMsgWrapper>, ContractError> {
     let config: Config = read_config(deps.storage)?;
 
-    let borrower = info.sender;
+    let borrower = match borrower {
+        Some(borrower) => {
+            if deps.api.addr_canonicalize(info.sender.as_str())? != config.overseer_contract {
+                return Err(ContractError::Unauthorized {});
+            }
+            borrower
+        }
+        None => info.sender,
+    };
     let borrower_raw = deps.api.addr_canonicalize(borrower.as_str())?;
     let mut borrower_info: BorrowerInfo = read_borrower_info(deps.storage, &borrower_raw);
 
@@ -62,6 +77,10 @@ pub fn withdraw_collateral(
     borrower_info.balance = borrower_info.balance - amount;
     borrower_info.spendable = borrower_info.spendable - amount;
 
+    let mut state: State = read_state(deps.storage)?;
+    state.total_balance -= amount;
+    store_state(deps.storage, &state)?;
+
     if borrower_info.balance == Uint256::zero() {
         remove_borrower_info(deps.storage, &borrower_raw);
     } else {
@@ -175,6 +194,10 @@ pub fn liquidate_collateral(
     borrower_info.balance = borrower_info.balance - amount;
     store_borrower_info(deps.storage, &borrower_raw, &borrower_info)?;
 
+    let mut state: State = read_state(deps.storage)?;
+    state.total_balance -= amount;
+    store_state(deps.storage, &state)?;
+
     Ok(Response::new()
         .add_message(CosmosMsg::Wasm(WasmMsg::Execute {
             contract_addr: deps
@@ -188,8 +211,9 @@ pub fn liquidate_collateral(
                     .addr_humanize(&config.liquidation_contract)?
                     .to_string(),
                 amount: amount.into(),
-                msg: to_binary(&LiquidationCw20HookMsg::ExecuteBid {
+                msg: to_binary(&LiquidationCw20HookMsg::AccumulateBid {
                     liquidator: liquidator.to_string(),
+                    borrower: borrower.to_string(),
                     fee_address: Some(
                         deps.api
                             .addr_humanize(&config.overseer_contract)?
@@ -209,6 +233,152 @@ pub fn liquidate_collateral(
         ]))
 }
 
+/// Send the whole collateral token balance along with the borrower
+/// balances to the new custody when the overseer replaces this
+/// contract. The borrowers and the state are cleared here
+/// Executor: overseer
+pub fn migrate_balances(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    new_custody: Addr,
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+    if deps.api.addr_canonicalize(info.sender.as_str())? != config.overseer_contract {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let collateral_token = deps.api.addr_humanize(&config.collateral_token)?;
+    let amount = query_token_balance(
+        deps.as_ref(),
+        collateral_token.clone(),
+        env.contract.address,
+    )?;
+
+    // a shortfall has to be reconciled before the balances can be handed over
+    let state: State = read_state(deps.storage)?;
+    if state.total_balance > amount {
+        return Err(ContractError::MigrationExceedsTokenBalance(
+            state.total_balance.into(),
+        ));
+    }
+
+    let borrowers = remove_borrowers(deps.storage)?
+        .into_iter()
+        .map(|(borrower, borrower_info)| {
+            Ok(BorrowerResponse {
+                borrower: deps.api.addr_humanize(&borrower)?.to_string(),
+                balance: borrower_info.balance,
+                spendable: borrower_info.spendable,
+            })
+        })
+        .collect::<StdResult<Vec<BorrowerResponse>>>()?;
+    store_state(
+        deps.storage,
+        &State {
+            total_balance: Uint256::zero(),
+            surplus: Uint256::zero(),
+        },
+    )?;
+
+    let migrated_borrowers = borrowers.len();
+    let mut messages: Vec<CosmosMsg<TerraMsgWrapper>> = vec![];
+    if !amount.is_zero() {
+        messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: collateral_token.to_string(),
+            funds: vec![],
+            msg: to_binary(&Cw20ExecuteMsg::Send {
+                contract: new_custody.to_string(),
+                amount: amount.into(),
+                msg: to_binary(&Cw20HookMsg::MigrateBorrowers { borrowers })?,
+            })?,
+        }));
+    }
+
+    Ok(Response::new().add_messages(messages).add_attributes(vec![
+        attr("action", "migrate_balances"),
+        attr("new_custody", new_custody),
+        attr("borrowers", migrated_borrowers.to_string()),
+        attr("amount", amount),
+    ]))
+}
+
+/// Expect the balances of the replaced custody
+/// Executor: overseer
+pub fn accept_migration(
+    deps: DepsMut,
+    info: MessageInfo,
+    old_custody: Addr,
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+    if deps.api.addr_canonicalize(info.sender.as_str())? != config.overseer_contract {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    store_migration_source(
+        deps.storage,
+        &deps.api.addr_canonicalize(old_custody.as_str())?,
+    )?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "accept_migration"),
+        attr("old_custody", old_custody),
+    ]))
+}
+
+/// Credit the borrower balances sent by the replaced custody, on top of
+/// anything already deposited here. Tokens sent on top of the balances
+/// are added to the surplus
+/// Executor: bAsset token contract, sent by the replaced custody
+pub fn migrate_borrowers(
+    deps: DepsMut,
+    old_custody: Addr,
+    amount: Uint256,
+    borrowers: Vec<BorrowerResponse>,
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
+    let old_custody_raw = deps.api.addr_canonicalize(old_custody.as_str())?;
+    if read_migration_source(deps.storage)? != Some(old_custody_raw) {
+        return Err(ContractError::Unauthorized {});
+    }
+    remove_migration_source(deps.storage);
+
+    let total_balance = borrowers.iter().fold(Uint256::zero(), |amount, borrower| {
+        amount + borrower.balance
+    });
+    if total_balance > amount {
+        return Err(ContractError::MigrationExceedsTokenBalance(
+            total_balance.into(),
+        ));
+    }
+
+    for borrower in borrowers.iter() {
+        if borrower.spendable > borrower.balance {
+            return Err(ContractError::Std(StdError::generic_err(format!(
+                "Spendable amount exceeds the balance of {}",
+                borrower.borrower
+            ))));
+        }
+
+        let borrower_raw = deps.api.addr_canonicalize(&borrower.borrower)?;
+        let mut borrower_info: BorrowerInfo = read_borrower_info(deps.storage, &borrower_raw);
+        borrower_info.balance += borrower.balance;
+        borrower_info.spendable += borrower.spendable;
+        store_borrower_info(deps.storage, &borrower_raw, &borrower_info)?;
+    }
+
+    let mut state: State = read_state(deps.storage)?;
+    state.total_balance += total_balance;
+    state.surplus += amount - total_balance;
+    store_state(deps.storage, &state)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "migrate_borrowers"),
+        attr("old_custody", old_custody),
+        attr("borrowers", borrowers.len().to_string()),
+        attr("amount", amount),
+    ]))
+}
+
 pub fn query_borrower(deps: Deps, borrower: Addr) -> StdResult<BorrowerResponse> {
     let borrower_raw = deps.api.addr_canonicalize(borrower.as_str())?;
     let borrower_info: BorrowerInfo = read_borrower_info(deps.storage, &borrower_raw);
@@ -9,7 +9,7 @@ use crate::error::ContractError;
 use crate::external::handle::{RewardContractExecuteMsg, RewardContractQueryMsg};
 use crate::state::{read_config, BETHAccruedRewardsResponse, Config};
 
-use moneymarket::querier::{deduct_tax, query_all_balances, query_balance};
+use moneymarket::querier::{deduct_tax_vec, query_all_balances, query_balance};
 use terra_cosmwasm::{create_swap_msg, TerraMsgWrapper};
 
 // REWARD_THRESHOLD
@@ -70,17 +70,18 @@ pub fn distribute_hook(
         contract_addr,
         config.stable_denom.to_string(),
     )?;
+    let rewards = deduct_tax_vec(
+        deps.as_ref(),
+        vec![Coin {
+            denom: config.stable_denom,
+            amount: reward_amount.into(),
+        }],
+    )?;
     let mut messages: Vec<CosmosMsg<TerraMsgWrapper>> = vec![];
-    if !reward_amount.is_zero() {
+    if !rewards.is_empty() {
         messages.push(CosmosMsg::Bank(BankMsg::Send {
             to_address: overseer_contract.to_string(),
-            amount: vec![deduct_tax(
-                deps.as_ref(),
-                Coin {
-                    denom: config.stable_denom,
-                    amount: reward_amount.into(),
-                },
-            )?],
+            amount: rewards,
         }));
     }
 
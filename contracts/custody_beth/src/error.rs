@@ -18,12 +18,21 @@ pub enum ContractError {
     #[error("Lock amount cannot excceed the user's spendable amount: {0}")]
     LockAmountExceedsSpendable(u128),
 
+    #[error("Migrated balances exceed the collateral tokens held: {0}")]
+    MigrationExceedsTokenBalance(u128),
+
     #[error("Invalid reply ID")]
     InvalidReplyId {},
 
     #[error("Invalid request: \"deposit collateral\" message not included in request")]
     MissingDepositCollateralHook {},
 
+    #[error("Collateral shortfall exceeds the max adjustment: {0}")]
+    ShortfallExceedsMaxAdjustment(u128),
+
+    #[error("Collateral shortfall exceeds the surplus: {0}")]
+    ShortfallExceedsSurplus(u128),
+
     #[error("Unlock amount cannot exceed locked amount: {0}")]
     UnlockAmountExceedsLocked(u128),
 
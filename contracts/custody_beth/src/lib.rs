@@ -2,6 +2,8 @@ pub mod collateral;
 pub mod contract;
 pub mod distribution;
 pub mod error;
+pub mod invariant;
+pub mod msg;
 pub mod state;
 
 mod external;
@@ -1,21 +1,24 @@
 use cosmwasm_bignumber::Uint256;
 use cosmwasm_std::{
     attr, from_binary, to_binary, Api, Attribute, BankMsg, Coin, ContractResult, CosmosMsg,
-    Decimal, Reply, Response, SubMsg, SubMsgExecutionResponse, Uint128, WasmMsg,
+    Decimal, OwnedDeps, Reply, Response, SubMsg, SubMsgExecutionResponse, Uint128, WasmMsg,
 };
 
 use crate::contract::{
-    execute, instantiate, query, reply, CLAIM_REWARDS_OPERATION, SWAP_TO_STABLE_OPERATION,
+    execute, instantiate, migrate, query, reply, CLAIM_REWARDS_OPERATION, SWAP_TO_STABLE_OPERATION,
 };
 use crate::error::ContractError;
 use crate::external::handle::RewardContractExecuteMsg;
-use crate::state::{read_borrower_info, BETHAccruedRewardsResponse};
-use crate::testing::mock_querier::mock_dependencies;
+use crate::state::{
+    read_borrower_info, read_state, store_state, BETHAccruedRewardsResponse, State,
+};
+use crate::testing::mock_querier::{mock_dependencies, WasmMockQuerier};
 
-use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
+use cosmwasm_std::testing::{mock_env, mock_info, MockApi, MockStorage, MOCK_CONTRACT_ADDR};
 use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg};
 use moneymarket::custody::{
-    BAssetInfo, BorrowerResponse, ConfigResponse, Cw20HookMsg, ExecuteMsg, InstantiateMsg, QueryMsg,
+    BAssetInfo, BorrowerResponse, BorrowersResponse, ConfigResponse, Cw20HookMsg, ExecuteMsg,
+    InstantiateMsg, InvariantResponse, MigrateMsg, QueryMsg,
 };
 use moneymarket::liquidation::Cw20HookMsg as LiquidationCw20HookMsg;
 use terra_cosmwasm::create_swap_msg;
@@ -252,6 +255,7 @@ fn withdraw_collateral() {
     );
 
     let msg = ExecuteMsg::WithdrawCollateral {
+        borrower: None,
         amount: Some(Uint256::from(110u64)),
     };
 
@@ -263,6 +267,7 @@ fn withdraw_collateral() {
     }
 
     let msg = ExecuteMsg::WithdrawCollateral {
+        borrower: None,
         amount: Some(Uint256::from(50u64)),
     };
     let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
@@ -306,6 +311,7 @@ fn withdraw_collateral() {
     );
 
     let msg = ExecuteMsg::WithdrawCollateral {
+        borrower: None,
         amount: Some(Uint256::from(40u128)),
     };
     let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
@@ -327,8 +333,41 @@ fn withdraw_collateral() {
         }
     );
 
+    // only the overseer can withdraw on behalf of a borrower
+    let msg = ExecuteMsg::WithdrawCollateral {
+        borrower: Some("addr0000".to_string()),
+        amount: Some(Uint256::from(5u64)),
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0001", &[]),
+        msg.clone(),
+    );
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("overseer", &[]), msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "beth".to_string(),
+            funds: vec![],
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: "addr0000".to_string(),
+                amount: Uint128::from(5u128),
+            })
+            .unwrap(),
+        }))]
+    );
+
     //withdraw with "None" amount
-    let msg = ExecuteMsg::WithdrawCollateral { amount: None };
+    let msg = ExecuteMsg::WithdrawCollateral {
+        borrower: None,
+        amount: None,
+    };
     let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
     let query_res = query(
         deps.as_ref(),
@@ -429,6 +468,7 @@ fn lock_collateral() {
     assert_eq!(spend, Uint256::from(50u128));
 
     let msg = ExecuteMsg::WithdrawCollateral {
+        borrower: None,
         amount: Some(Uint256::from(51u64)),
     };
     let info = mock_info("addr0000", &[]);
@@ -439,6 +479,7 @@ fn lock_collateral() {
     }
 
     let msg = ExecuteMsg::WithdrawCollateral {
+        borrower: None,
         amount: Some(Uint256::from(50u64)),
     };
     let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -516,6 +557,7 @@ fn lock_collateral() {
     assert_eq!(spend, Uint256::from(30u128));
 
     let msg = ExecuteMsg::WithdrawCollateral {
+        borrower: None,
         amount: Some(Uint256::from(30u64)),
     };
     let info = mock_info("addr0000", &[]);
@@ -881,8 +923,9 @@ fn liquidate_collateral() {
             msg: to_binary(&Cw20ExecuteMsg::Send {
                 contract: "liquidation".to_string(),
                 amount: Uint128::from(10u128),
-                msg: to_binary(&LiquidationCw20HookMsg::ExecuteBid {
+                msg: to_binary(&LiquidationCw20HookMsg::AccumulateBid {
                     liquidator: "liquidator".to_string(),
+                    borrower: "addr0000".to_string(),
                     fee_address: Some("overseer".to_string()),
                     repay_address: Some("market".to_string()),
                 })
@@ -966,3 +1009,504 @@ fn proper_distribute_rewards_with_no_rewards() {
         ),]
     );
 }
+
+#[test]
+fn reconcile_invariant() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        owner: "owner".to_string(),
+        collateral_token: "beth".to_string(),
+        overseer_contract: "overseer".to_string(),
+        market_contract: "market".to_string(),
+        reward_contract: "reward".to_string(),
+        liquidation_contract: "liquidation".to_string(),
+        stable_denom: "uusd".to_string(),
+        basset_info: BAssetInfo {
+            name: "beth".to_string(),
+            symbol: "beth".to_string(),
+            decimals: 6,
+        },
+    };
+
+    let info = mock_info("owner", &[]);
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        amount: Uint128::from(100u128),
+        msg: to_binary(&Cw20HookMsg::DepositCollateral {}).unwrap(),
+    });
+    let _res = execute(deps.as_mut(), mock_env(), mock_info("beth", &[]), msg).unwrap();
+
+    let set_token_balance = |deps: &mut OwnedDeps<MockStorage, MockApi, WasmMockQuerier>,
+                             balance: u128| {
+        deps.querier.with_token_balances(&[(
+            &"beth".to_string(),
+            &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::from(balance))],
+        )]);
+    };
+    let query_invariant = |deps: &OwnedDeps<MockStorage, MockApi, WasmMockQuerier>| {
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Invariant {}).unwrap();
+        from_binary::<InvariantResponse>(&res).unwrap()
+    };
+
+    set_token_balance(&mut deps, 100);
+    assert_eq!(
+        query_invariant(&deps),
+        InvariantResponse {
+            token_balance: Uint256::from(100u64),
+            total_balance: Uint256::from(100u64),
+            surplus: Uint256::zero(),
+            excess: Uint256::zero(),
+            shortfall: Uint256::zero(),
+        }
+    );
+
+    // the custody received tokens no borrower deposited
+    set_token_balance(&mut deps, 110);
+    assert_eq!(query_invariant(&deps).excess, Uint256::from(10u64));
+
+    let msg = ExecuteMsg::ReconcileInvariant {
+        max_adjustment: Uint256::zero(),
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        msg.clone(),
+    );
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "reconcile_invariant"),
+            attr("excess", "10"),
+            attr("shortfall", "0"),
+            attr("surplus", "10"),
+        ]
+    );
+    assert_eq!(
+        query_invariant(&deps),
+        InvariantResponse {
+            token_balance: Uint256::from(110u64),
+            total_balance: Uint256::from(100u64),
+            surplus: Uint256::from(10u64),
+            excess: Uint256::zero(),
+            shortfall: Uint256::zero(),
+        }
+    );
+
+    // the custody lost tokens, covered by the surplus
+    set_token_balance(&mut deps, 103);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::ReconcileInvariant {
+            max_adjustment: Uint256::from(5u64),
+        },
+    );
+    match res {
+        Err(ContractError::ShortfallExceedsMaxAdjustment(7)) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let msg = ExecuteMsg::ReconcileInvariant {
+        max_adjustment: Uint256::from(10u64),
+    };
+    let _res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        msg.clone(),
+    )
+    .unwrap();
+    assert_eq!(
+        query_invariant(&deps),
+        InvariantResponse {
+            token_balance: Uint256::from(103u64),
+            total_balance: Uint256::from(100u64),
+            surplus: Uint256::from(3u64),
+            excess: Uint256::zero(),
+            shortfall: Uint256::zero(),
+        }
+    );
+
+    // borrower balances are never reduced to cover a shortfall
+    set_token_balance(&mut deps, 95);
+    let res = execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg);
+    match res {
+        Err(ContractError::ShortfallExceedsSurplus(8)) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+    assert_eq!(
+        read_borrower_info(
+            &deps.storage,
+            &deps.api.addr_canonicalize("addr0000").unwrap()
+        )
+        .balance,
+        Uint256::from(100u64)
+    );
+
+    // withdrawals keep the running total
+    let msg = ExecuteMsg::WithdrawCollateral {
+        borrower: None,
+        amount: Some(Uint256::from(40u64)),
+    };
+    let _res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+    set_token_balance(&mut deps, 63);
+    assert_eq!(
+        query_invariant(&deps),
+        InvariantResponse {
+            token_balance: Uint256::from(63u64),
+            total_balance: Uint256::from(60u64),
+            surplus: Uint256::from(3u64),
+            excess: Uint256::zero(),
+            shortfall: Uint256::zero(),
+        }
+    );
+}
+
+#[test]
+fn migrate_balances() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        owner: "owner".to_string(),
+        collateral_token: "beth".to_string(),
+        overseer_contract: "overseer".to_string(),
+        market_contract: "market".to_string(),
+        reward_contract: "reward".to_string(),
+        liquidation_contract: "liquidation".to_string(),
+        stable_denom: "uusd".to_string(),
+        basset_info: BAssetInfo {
+            name: "beth".to_string(),
+            symbol: "beth".to_string(),
+            decimals: 6,
+        },
+    };
+
+    let info = mock_info("owner", &[]);
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg.clone()).unwrap();
+    let instantiate_msg = msg;
+
+    // addr0000 deposits 100 and borrows against 40 of it
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        amount: Uint128::from(100u128),
+        msg: to_binary(&Cw20HookMsg::DepositCollateral {}).unwrap(),
+    });
+    let _res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("beth", &[]),
+        deposit_msg,
+    )
+    .unwrap();
+    let lock_msg = ExecuteMsg::LockCollateral {
+        borrower: "addr0000".to_string(),
+        amount: Uint256::from(40u64),
+    };
+    let _res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("overseer", &[]),
+        lock_msg,
+    )
+    .unwrap();
+
+    // the custody holds 3 tokens on top of the borrower balances,
+    // but cannot migrate while it is short
+    deps.querier.with_token_balances(&[(
+        &"beth".to_string(),
+        &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::from(90u128))],
+    )]);
+
+    let msg = ExecuteMsg::MigrateBalances {
+        new_custody: "new_custody".to_string(),
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        msg.clone(),
+    );
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("overseer", &[]),
+        msg.clone(),
+    );
+    match res {
+        Err(ContractError::MigrationExceedsTokenBalance(100)) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    deps.querier.with_token_balances(&[(
+        &"beth".to_string(),
+        &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::from(103u128))],
+    )]);
+    let res = execute(deps.as_mut(), mock_env(), mock_info("overseer", &[]), msg).unwrap();
+    let hook_msg = to_binary(&Cw20HookMsg::MigrateBorrowers {
+        borrowers: vec![BorrowerResponse {
+            borrower: "addr0000".to_string(),
+            balance: Uint256::from(100u64),
+            spendable: Uint256::from(60u64),
+        }],
+    })
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "beth".to_string(),
+            funds: vec![],
+            msg: to_binary(&Cw20ExecuteMsg::Send {
+                contract: "new_custody".to_string(),
+                amount: Uint128::from(103u128),
+                msg: hook_msg.clone(),
+            })
+            .unwrap(),
+        }))]
+    );
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "migrate_balances"),
+            attr("new_custody", "new_custody"),
+            attr("borrowers", "1"),
+            attr("amount", "103"),
+        ]
+    );
+
+    // the old custody is left without borrowers
+    let borrowers: BorrowersResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::Borrowers {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(borrowers.borrowers, vec![]);
+    assert_eq!(
+        read_state(deps.as_ref().storage).unwrap(),
+        State {
+            total_balance: Uint256::zero(),
+            surplus: Uint256::zero(),
+        }
+    );
+
+    // the new custody only credits the balances of the custody it expects
+    let mut new_deps = mock_dependencies(&[]);
+    let _res = instantiate(
+        new_deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg,
+    )
+    .unwrap();
+
+    let migrate_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: MOCK_CONTRACT_ADDR.to_string(),
+        amount: Uint128::from(103u128),
+        msg: hook_msg,
+    });
+    let res = execute(
+        new_deps.as_mut(),
+        mock_env(),
+        mock_info("beth", &[]),
+        migrate_msg.clone(),
+    );
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let accept_msg = ExecuteMsg::AcceptMigration {
+        old_custody: MOCK_CONTRACT_ADDR.to_string(),
+    };
+    let res = execute(
+        new_deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        accept_msg.clone(),
+    );
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+    let res = execute(
+        new_deps.as_mut(),
+        mock_env(),
+        mock_info("overseer", &[]),
+        accept_msg,
+    )
+    .unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "accept_migration"),
+            attr("old_custody", MOCK_CONTRACT_ADDR),
+        ]
+    );
+
+    let res = execute(
+        new_deps.as_mut(),
+        mock_env(),
+        mock_info("beth", &[]),
+        migrate_msg.clone(),
+    )
+    .unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "migrate_borrowers"),
+            attr("old_custody", MOCK_CONTRACT_ADDR),
+            attr("borrowers", "1"),
+            attr("amount", "103"),
+        ]
+    );
+    assert_eq!(
+        read_state(new_deps.as_ref().storage).unwrap(),
+        State {
+            total_balance: Uint256::from(100u64),
+            surplus: Uint256::from(3u64),
+        }
+    );
+    let res: BorrowerResponse = from_binary(
+        &query(
+            new_deps.as_ref(),
+            mock_env(),
+            QueryMsg::Borrower {
+                address: "addr0000".to_string(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(res.balance, Uint256::from(100u64));
+    assert_eq!(res.spendable, Uint256::from(60u64));
+
+    // the balances cannot be credited twice
+    let res = execute(
+        new_deps.as_mut(),
+        mock_env(),
+        mock_info("beth", &[]),
+        migrate_msg,
+    );
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // the borrower repays, gets the collateral unlocked and withdraws it all
+    let unlock_msg = ExecuteMsg::UnlockCollateral {
+        borrower: "addr0000".to_string(),
+        amount: Uint256::from(40u64),
+    };
+    let _res = execute(
+        new_deps.as_mut(),
+        mock_env(),
+        mock_info("overseer", &[]),
+        unlock_msg,
+    )
+    .unwrap();
+
+    let withdraw_msg = ExecuteMsg::WithdrawCollateral {
+        borrower: None,
+        amount: None,
+    };
+    let res = execute(
+        new_deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        withdraw_msg,
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "beth".to_string(),
+            funds: vec![],
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: "addr0000".to_string(),
+                amount: Uint128::from(100u128),
+            })
+            .unwrap(),
+        }))]
+    );
+    assert_eq!(
+        read_state(new_deps.as_ref().storage).unwrap().total_balance,
+        Uint256::zero()
+    );
+}
+
+#[test]
+fn migrate_running_total() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        owner: "owner".to_string(),
+        collateral_token: "beth".to_string(),
+        overseer_contract: "overseer".to_string(),
+        market_contract: "market".to_string(),
+        reward_contract: "reward".to_string(),
+        liquidation_contract: "liquidation".to_string(),
+        stable_denom: "uusd".to_string(),
+        basset_info: BAssetInfo {
+            name: "beth".to_string(),
+            symbol: "beth".to_string(),
+            decimals: 6,
+        },
+    };
+
+    let info = mock_info("owner", &[]);
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    for (borrower, amount) in [("addr0000", 100u128), ("addr0001", 50u128)].iter() {
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: borrower.to_string(),
+            amount: Uint128::from(*amount),
+            msg: to_binary(&Cw20HookMsg::DepositCollateral {}).unwrap(),
+        });
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("beth", &[]), msg).unwrap();
+    }
+
+    // custodies stored before the running total was tracked
+    store_state(
+        deps.as_mut().storage,
+        &State {
+            total_balance: Uint256::zero(),
+            surplus: Uint256::zero(),
+        },
+    )
+    .unwrap();
+
+    let res = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![attr("action", "migrate"), attr("total_balance", "150")]
+    );
+    assert_eq!(
+        read_state(&deps.storage).unwrap(),
+        State {
+            total_balance: Uint256::from(150u64),
+            surplus: Uint256::zero(),
+        }
+    );
+}
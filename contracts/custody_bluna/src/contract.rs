@@ -6,16 +6,20 @@ use cosmwasm_std::{
 };
 
 use crate::collateral::{
-    deposit_collateral, liquidate_collateral, lock_collateral, query_borrower, query_borrowers,
-    unlock_collateral, withdraw_collateral,
+    accept_migration, deposit_collateral, liquidate_collateral, lock_collateral, migrate_balances,
+    migrate_borrowers, query_borrower, query_borrowers, unlock_collateral, withdraw_collateral,
 };
 use crate::distribution::{distribute_hook, distribute_rewards, swap_to_stable_denom};
 use crate::error::ContractError;
-use crate::state::{read_config, store_config, Config};
+use crate::invariant::{query_invariant, reconcile_invariant};
+use crate::state::{read_config, read_total_balance, store_config, store_state, Config, State};
 
+use cosmwasm_bignumber::Uint256;
 use cw20::Cw20ReceiveMsg;
 use moneymarket::common::optional_addr_validate;
-use moneymarket::custody::{ConfigResponse, Cw20HookMsg, ExecuteMsg, InstantiateMsg, QueryMsg};
+use moneymarket::custody::{
+    ConfigResponse, Cw20HookMsg, ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg,
+};
 use terra_cosmwasm::TerraMsgWrapper;
 
 pub const CLAIM_REWARDS_OPERATION: u64 = 1u64;
@@ -40,6 +44,13 @@ pub fn instantiate(
     };
 
     store_config(deps.storage, &config)?;
+    store_state(
+        deps.storage,
+        &State {
+            total_balance: Uint256::zero(),
+            surplus: Uint256::zero(),
+        },
+    )?;
 
     Ok(Response::default())
 }
@@ -74,7 +85,12 @@ pub fn execute(
             unlock_collateral(deps, info, borrower_addr, amount)
         }
         ExecuteMsg::DistributeRewards {} => distribute_rewards(deps, env, info),
-        ExecuteMsg::WithdrawCollateral { amount } => withdraw_collateral(deps, info, amount),
+        ExecuteMsg::WithdrawCollateral { borrower, amount } => {
+            let borrower = borrower
+                .map(|borrower| deps.api.addr_validate(&borrower))
+                .transpose()?;
+            withdraw_collateral(deps, info, borrower, amount)
+        }
         ExecuteMsg::LiquidateCollateral {
             liquidator,
             borrower,
@@ -84,6 +100,17 @@ pub fn execute(
             let borrower_addr = deps.api.addr_validate(&borrower)?;
             liquidate_collateral(deps, info, liquidator_addr, borrower_addr, amount)
         }
+        ExecuteMsg::MigrateBalances { new_custody } => {
+            let new_custody_addr = deps.api.addr_validate(&new_custody)?;
+            migrate_balances(deps, env, info, new_custody_addr)
+        }
+        ExecuteMsg::AcceptMigration { old_custody } => {
+            let old_custody_addr = deps.api.addr_validate(&old_custody)?;
+            accept_migration(deps, info, old_custody_addr)
+        }
+        ExecuteMsg::ReconcileInvariant { max_adjustment } => {
+            reconcile_invariant(deps, env, info, max_adjustment)
+        }
     }
 }
 
@@ -102,6 +129,25 @@ pub fn reply(
     }
 }
 
+/// Initializes the running total of the borrower balances
+/// for custodies instantiated before it was tracked
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> StdResult<Response> {
+    let total_balance = read_total_balance(deps.storage)?;
+    store_state(
+        deps.storage,
+        &State {
+            total_balance,
+            surplus: Uint256::zero(),
+        },
+    )?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "migrate"),
+        attr("total_balance", total_balance),
+    ]))
+}
+
 pub fn receive_cw20(
     deps: DepsMut,
     info: MessageInfo,
@@ -109,18 +155,25 @@ pub fn receive_cw20(
 ) -> Result<Response<TerraMsgWrapper>, ContractError> {
     let contract_addr = info.sender;
 
-    match from_binary(&cw20_msg.msg) {
-        Ok(Cw20HookMsg::DepositCollateral {}) => {
-            // only asset contract can execute this message
-            let config: Config = read_config(deps.storage)?;
-            if deps.api.addr_canonicalize(contract_addr.as_str())? != config.collateral_token {
-                return Err(ContractError::Unauthorized {});
-            }
+    let hook_msg: Cw20HookMsg = match from_binary(&cw20_msg.msg) {
+        Ok(hook_msg) => hook_msg,
+        _ => return Err(ContractError::MissingDepositCollateralHook {}),
+    };
 
-            let cw20_sender_addr = deps.api.addr_validate(&cw20_msg.sender)?;
+    // only asset contract can execute this message
+    let config: Config = read_config(deps.storage)?;
+    if deps.api.addr_canonicalize(contract_addr.as_str())? != config.collateral_token {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let cw20_sender_addr = deps.api.addr_validate(&cw20_msg.sender)?;
+    match hook_msg {
+        Cw20HookMsg::DepositCollateral {} => {
             deposit_collateral(deps, cw20_sender_addr, cw20_msg.amount.into())
         }
-        _ => Err(ContractError::MissingDepositCollateralHook {}),
+        Cw20HookMsg::MigrateBorrowers { borrowers } => {
+            migrate_borrowers(deps, cw20_sender_addr, cw20_msg.amount.into(), borrowers)
+        }
     }
 }
 
@@ -149,7 +202,7 @@ pub fn update_config(
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::Config {} => to_binary(&query_config(deps)?),
         QueryMsg::Borrower { address } => {
@@ -161,6 +214,7 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
             optional_addr_validate(deps.api, start_after)?,
             limit,
         )?),
+        QueryMsg::Invariant {} => to_binary(&query_invariant(deps, env)?),
     }
 }
 
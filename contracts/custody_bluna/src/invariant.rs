@@ -0,0 +1,78 @@
+use cosmwasm_bignumber::Uint256;
+use cosmwasm_std::{attr, Deps, DepsMut, Env, MessageInfo, Response, StdResult};
+use moneymarket::custody::InvariantResponse;
+use moneymarket::querier::query_token_balance;
+use terra_cosmwasm::TerraMsgWrapper;
+
+use crate::error::ContractError;
+use crate::state::{read_config, read_state, store_state, Config, State};
+
+/// Reconcile the collateral token balance with the recorded balances
+/// Executor: owner
+pub fn reconcile_invariant(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    max_adjustment: Uint256,
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+    if deps.api.addr_canonicalize(info.sender.as_str())? != config.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let invariant = query_invariant(deps.as_ref(), env)?;
+    let mut state: State = read_state(deps.storage)?;
+    if !invariant.shortfall.is_zero() {
+        if invariant.shortfall > max_adjustment {
+            return Err(ContractError::ShortfallExceedsMaxAdjustment(
+                invariant.shortfall.into(),
+            ));
+        }
+
+        // borrower balances are never reduced
+        if invariant.shortfall > state.surplus {
+            return Err(ContractError::ShortfallExceedsSurplus(
+                invariant.shortfall.into(),
+            ));
+        }
+
+        state.surplus -= invariant.shortfall;
+    } else {
+        state.surplus += invariant.excess;
+    }
+
+    store_state(deps.storage, &state)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "reconcile_invariant"),
+        attr("excess", invariant.excess),
+        attr("shortfall", invariant.shortfall),
+        attr("surplus", state.surplus),
+    ]))
+}
+
+pub fn query_invariant(deps: Deps, env: Env) -> StdResult<InvariantResponse> {
+    let config: Config = read_config(deps.storage)?;
+    let state: State = read_state(deps.storage)?;
+
+    let token_balance = query_token_balance(
+        deps,
+        deps.api.addr_humanize(&config.collateral_token)?,
+        env.contract.address,
+    )?;
+
+    let recorded_balance = state.total_balance + state.surplus;
+    let (excess, shortfall) = if token_balance > recorded_balance {
+        (token_balance - recorded_balance, Uint256::zero())
+    } else {
+        (Uint256::zero(), recorded_balance - token_balance)
+    };
+
+    Ok(InvariantResponse {
+        token_balance,
+        total_balance: state.total_balance,
+        surplus: state.surplus,
+        excess,
+        shortfall,
+    })
+}
@@ -5,6 +5,7 @@ use cosmwasm_bignumber::Uint256;
 use cosmwasm_std::{CanonicalAddr, Deps, Order, StdResult, Storage, Uint128};
 use cosmwasm_storage::{Bucket, ReadonlyBucket, ReadonlySingleton, Singleton};
 use moneymarket::custody::{BAssetInfo, BorrowerResponse};
+use moneymarket::pagination::{calc_range_start_canonical, clamp_limit, DEFAULT_LIMIT, MAX_LIMIT};
 
 //BLunaAccruedRewardsResponse the struct that shows the result of accrued_rewards query
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
@@ -13,6 +14,8 @@ pub struct BLunaAccruedRewardsResponse {
 }
 
 const KEY_CONFIG: &[u8] = b"config";
+const KEY_STATE: &[u8] = b"state";
+const KEY_MIGRATION_SOURCE: &[u8] = b"migration_source";
 const PREFIX_BORROWER: &[u8] = b"borrower";
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -27,6 +30,14 @@ pub struct Config {
     pub basset_info: BAssetInfo,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct State {
+    /// Sum of the borrower balances
+    pub total_balance: Uint256,
+    /// Collateral tokens held on top of the borrower balances
+    pub surplus: Uint256,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct BorrowerInfo {
     pub balance: Uint256,
@@ -41,6 +52,26 @@ pub fn read_config(storage: &dyn Storage) -> StdResult<Config> {
     ReadonlySingleton::new(storage, KEY_CONFIG).load()
 }
 
+pub fn store_state(storage: &mut dyn Storage, data: &State) -> StdResult<()> {
+    Singleton::new(storage, KEY_STATE).save(data)
+}
+
+pub fn read_state(storage: &dyn Storage) -> StdResult<State> {
+    ReadonlySingleton::new(storage, KEY_STATE).load()
+}
+
+pub fn store_migration_source(storage: &mut dyn Storage, data: &CanonicalAddr) -> StdResult<()> {
+    Singleton::new(storage, KEY_MIGRATION_SOURCE).save(data)
+}
+
+pub fn read_migration_source(storage: &dyn Storage) -> StdResult<Option<CanonicalAddr>> {
+    ReadonlySingleton::new(storage, KEY_MIGRATION_SOURCE).may_load()
+}
+
+pub fn remove_migration_source(storage: &mut dyn Storage) {
+    Singleton::<CanonicalAddr>::new(storage, KEY_MIGRATION_SOURCE).remove()
+}
+
 pub fn store_borrower_info(
     storage: &mut dyn Storage,
     borrower: &CanonicalAddr,
@@ -69,9 +100,40 @@ pub fn read_borrower_info(storage: &dyn Storage, borrower: &CanonicalAddr) -> Bo
     }
 }
 
-// settings for pagination
-const MAX_LIMIT: u32 = 30;
-const DEFAULT_LIMIT: u32 = 10;
+/// Sums the balances of every borrower; only used to
+/// initialize the state of custodies stored without it
+pub fn read_total_balance(storage: &dyn Storage) -> StdResult<Uint256> {
+    let borrower_bucket: ReadonlyBucket<BorrowerInfo> =
+        ReadonlyBucket::new(storage, PREFIX_BORROWER);
+
+    let mut total_balance = Uint256::zero();
+    for item in borrower_bucket.range(None, None, Order::Ascending) {
+        let (_, v) = item?;
+        total_balance += v.balance;
+    }
+
+    Ok(total_balance)
+}
+
+/// Removes every borrower, returning their balances
+pub fn remove_borrowers(
+    storage: &mut dyn Storage,
+) -> StdResult<Vec<(CanonicalAddr, BorrowerInfo)>> {
+    let borrowers = ReadonlyBucket::<BorrowerInfo>::new(storage, PREFIX_BORROWER)
+        .range(None, None, Order::Ascending)
+        .map(|item| {
+            let (k, v) = item?;
+            Ok((CanonicalAddr::from(k), v))
+        })
+        .collect::<StdResult<Vec<(CanonicalAddr, BorrowerInfo)>>>()?;
+
+    for (borrower, _) in borrowers.iter() {
+        remove_borrower_info(storage, borrower);
+    }
+
+    Ok(borrowers)
+}
+
 pub fn read_borrowers(
     deps: Deps,
     start_after: Option<CanonicalAddr>,
@@ -80,8 +142,8 @@ pub fn read_borrowers(
     let position_bucket: ReadonlyBucket<BorrowerInfo> =
         ReadonlyBucket::new(deps.storage, PREFIX_BORROWER);
 
-    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
-    let start = calc_range_start(start_after);
+    let limit = clamp_limit(limit, DEFAULT_LIMIT, MAX_LIMIT);
+    let start = calc_range_start_canonical(start_after);
 
     position_bucket
         .range(start.as_deref(), None, Order::Ascending)
@@ -97,12 +159,3 @@ pub fn read_borrowers(
         })
         .collect()
 }
-
-// this will set the first key after the provided key, by appending a 1 byte
-fn calc_range_start(start_after: Option<CanonicalAddr>) -> Option<Vec<u8>> {
-    start_after.map(|addr| {
-        let mut v = addr.as_slice().to_vec();
-        v.push(1);
-        v
-    })
-}
@@ -1,31 +1,57 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
-use cosmwasm_std::{to_binary, Addr, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult};
+use cosmwasm_std::{
+    attr, to_binary, Addr, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult,
+};
 
 use crate::error::ContractError;
-use crate::state::{read_config, store_config, Config};
+use crate::state::{
+    read_config, read_emission_history, read_emission_history_heights, read_state,
+    remove_emission_record, store_config, store_emission_history_heights, store_emission_record,
+    store_state, Config, EmissionRecord, State,
+};
 
 use cosmwasm_bignumber::Decimal256;
 use moneymarket::common::optional_addr_validate;
 use moneymarket::distribution_model::{
-    AncEmissionRateResponse, ConfigResponse, ExecuteMsg, InstantiateMsg, QueryMsg,
+    AncEmissionRateResponse, ConfigResponse, DepositRateBoundsResponse, EmissionHistoryResponse,
+    ExecuteMsg, InstantiateMsg, QueryMsg, StateResponse,
 };
 
+/// Number of emission rate updates retained, older ones are pruned
+const MAX_EMISSION_HISTORY: usize = 100;
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     deps: DepsMut,
     _env: Env,
     _info: MessageInfo,
     msg: InstantiateMsg,
-) -> StdResult<Response> {
+) -> Result<Response, ContractError> {
+    assert_deposit_rate_bounds(msg.deposit_rate_floor, msg.deposit_rate_ceiling)?;
+
+    let risk_admin = msg
+        .risk_admin
+        .map(|risk_admin| deps.api.addr_canonicalize(&risk_admin))
+        .transpose()?;
+    let overseer_contract = msg
+        .overseer_contract
+        .map(|overseer_contract| deps.api.addr_canonicalize(&overseer_contract))
+        .transpose()?;
+
     store_config(
         deps.storage,
         &Config {
             owner: deps.api.addr_canonicalize(&msg.owner)?,
+            risk_admin,
             emission_cap: msg.emission_cap,
             emission_floor: msg.emission_floor,
             increment_multiplier: msg.increment_multiplier,
             decrement_multiplier: msg.decrement_multiplier,
+            max_rate_change_per_epoch: msg.max_rate_change_per_epoch,
+            deposit_rate_floor: msg.deposit_rate_floor,
+            deposit_rate_ceiling: msg.deposit_rate_ceiling,
+            overseer_contract,
         },
     )?;
 
@@ -35,50 +61,105 @@ pub fn instantiate(
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
     match msg {
         ExecuteMsg::UpdateConfig {
             owner,
+            risk_admin,
             emission_cap,
             emission_floor,
             increment_multiplier,
             decrement_multiplier,
+            max_rate_change_per_epoch,
+            deposit_rate_floor,
+            deposit_rate_ceiling,
+            overseer_contract,
         } => {
             let api = deps.api;
             update_config(
                 deps,
                 info,
                 optional_addr_validate(api, owner)?,
+                optional_addr_validate(api, risk_admin)?,
                 emission_cap,
                 emission_floor,
                 increment_multiplier,
                 decrement_multiplier,
+                max_rate_change_per_epoch,
+                deposit_rate_floor,
+                deposit_rate_ceiling,
+                optional_addr_validate(api, overseer_contract)?,
             )
         }
+        ExecuteMsg::UpdateEmissionRate {
+            emission_rate,
+            deposit_rate,
+            target_deposit_rate,
+            threshold_deposit_rate,
+        } => update_emission_rate(
+            deps,
+            env,
+            info,
+            EmissionRecord {
+                emission_rate,
+                deposit_rate,
+                target_deposit_rate,
+                threshold_deposit_rate,
+            },
+        ),
+    }
+}
+
+fn assert_deposit_rate_bounds(
+    deposit_rate_floor: Decimal256,
+    deposit_rate_ceiling: Decimal256,
+) -> Result<(), ContractError> {
+    if deposit_rate_floor > deposit_rate_ceiling {
+        return Err(ContractError::InvalidDepositRateBounds {});
     }
+
+    Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn update_config(
     deps: DepsMut,
     info: MessageInfo,
     owner: Option<Addr>,
+    risk_admin: Option<Addr>,
     emission_cap: Option<Decimal256>,
     emission_floor: Option<Decimal256>,
     increment_multiplier: Option<Decimal256>,
     decrement_multiplier: Option<Decimal256>,
+    max_rate_change_per_epoch: Option<Decimal256>,
+    deposit_rate_floor: Option<Decimal256>,
+    deposit_rate_ceiling: Option<Decimal256>,
+    overseer_contract: Option<Addr>,
 ) -> Result<Response, ContractError> {
     let mut config: Config = read_config(deps.storage)?;
-    if deps.api.addr_canonicalize(info.sender.as_str())? != config.owner {
-        return Err(ContractError::Unauthorized {});
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if sender_raw != config.owner {
+        // the risk admin can only tune the rate parameters
+        if config.risk_admin != Some(sender_raw)
+            || owner.is_some()
+            || risk_admin.is_some()
+            || overseer_contract.is_some()
+        {
+            return Err(ContractError::Unauthorized {});
+        }
     }
 
     if let Some(owner) = owner {
         config.owner = deps.api.addr_canonicalize(owner.as_str())?;
     }
 
+    if let Some(risk_admin) = risk_admin {
+        config.risk_admin = Some(deps.api.addr_canonicalize(risk_admin.as_str())?);
+    }
+
     if let Some(emission_cap) = emission_cap {
         config.emission_cap = emission_cap;
     }
@@ -95,10 +176,66 @@ pub fn update_config(
         config.decrement_multiplier = decrement_multiplier;
     }
 
+    if let Some(max_rate_change_per_epoch) = max_rate_change_per_epoch {
+        config.max_rate_change_per_epoch = max_rate_change_per_epoch;
+    }
+
+    if let Some(deposit_rate_floor) = deposit_rate_floor {
+        config.deposit_rate_floor = deposit_rate_floor;
+    }
+
+    if let Some(deposit_rate_ceiling) = deposit_rate_ceiling {
+        config.deposit_rate_ceiling = deposit_rate_ceiling;
+    }
+
+    if let Some(overseer_contract) = overseer_contract {
+        config.overseer_contract = Some(deps.api.addr_canonicalize(overseer_contract.as_str())?);
+    }
+
+    assert_deposit_rate_bounds(config.deposit_rate_floor, config.deposit_rate_ceiling)?;
+
     store_config(deps.storage, &config)?;
     Ok(Response::default())
 }
 
+pub fn update_emission_rate(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    record: EmissionRecord,
+) -> Result<Response, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if config.overseer_contract != Some(sender_raw) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let height = env.block.height;
+    store_state(
+        deps.storage,
+        &State {
+            emission_rate: record.emission_rate,
+            last_updated: height,
+        },
+    )?;
+    store_emission_record(deps.storage, height, &record)?;
+
+    let mut heights = read_emission_history_heights(deps.storage)?;
+    if !heights.contains(&height) {
+        heights.push(height);
+    }
+    while heights.len() > MAX_EMISSION_HISTORY {
+        remove_emission_record(deps.storage, heights.remove(0));
+    }
+    store_emission_history_heights(deps.storage, &heights)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "update_emission_rate"),
+        attr("emission_rate", record.emission_rate.to_string()),
+        attr("height", height.to_string()),
+    ]))
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
@@ -115,6 +252,11 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
             threshold_deposit_rate,
             current_emission_rate,
         )?),
+        QueryMsg::DepositRateBounds {} => to_binary(&query_deposit_rate_bounds(deps)?),
+        QueryMsg::State {} => to_binary(&query_state(deps)?),
+        QueryMsg::EmissionHistory { start_after, limit } => {
+            to_binary(&query_emission_history(deps, start_after, limit)?)
+        }
     }
 }
 
@@ -122,15 +264,54 @@ fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
     let state = read_config(deps.storage)?;
     let resp = ConfigResponse {
         owner: deps.api.addr_humanize(&state.owner)?.to_string(),
+        risk_admin: state
+            .risk_admin
+            .map(|risk_admin| deps.api.addr_humanize(&risk_admin))
+            .transpose()?
+            .map(|risk_admin| risk_admin.to_string()),
         emission_cap: state.emission_cap,
         emission_floor: state.emission_floor,
         increment_multiplier: state.increment_multiplier,
         decrement_multiplier: state.decrement_multiplier,
+        max_rate_change_per_epoch: state.max_rate_change_per_epoch,
+        deposit_rate_floor: state.deposit_rate_floor,
+        deposit_rate_ceiling: state.deposit_rate_ceiling,
+        overseer_contract: state
+            .overseer_contract
+            .map(|overseer_contract| deps.api.addr_humanize(&overseer_contract))
+            .transpose()?
+            .map(|overseer_contract| overseer_contract.to_string()),
     };
 
     Ok(resp)
 }
 
+fn query_state(deps: Deps) -> StdResult<StateResponse> {
+    let state: State = read_state(deps.storage)?;
+    Ok(StateResponse {
+        emission_rate: state.emission_rate,
+        last_updated: state.last_updated,
+    })
+}
+
+fn query_emission_history(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<EmissionHistoryResponse> {
+    Ok(EmissionHistoryResponse {
+        history: read_emission_history(deps.storage, start_after, limit)?,
+    })
+}
+
+fn query_deposit_rate_bounds(deps: Deps) -> StdResult<DepositRateBoundsResponse> {
+    let config: Config = read_config(deps.storage)?;
+    Ok(DepositRateBoundsResponse {
+        deposit_rate_floor: config.deposit_rate_floor,
+        deposit_rate_ceiling: config.deposit_rate_ceiling,
+    })
+}
+
 fn query_anc_emission_rate(
     deps: Deps,
     deposit_rate: Decimal256,
@@ -153,7 +334,7 @@ fn query_anc_emission_rate(
         current_emission_rate
     };
 
-    let emission_rate = if emission_rate > config.emission_cap {
+    let unclamped_emission_rate = if emission_rate > config.emission_cap {
         config.emission_cap
     } else if emission_rate < config.emission_floor {
         config.emission_floor
@@ -161,5 +342,35 @@ fn query_anc_emission_rate(
         emission_rate
     };
 
-    Ok(AncEmissionRateResponse { emission_rate })
+    // a current rate outside [floor, cap] moves to the bound at once;
+    // the relative step would keep a zero rate at zero forever
+    if current_emission_rate < config.emission_floor || current_emission_rate > config.emission_cap
+    {
+        return Ok(AncEmissionRateResponse {
+            emission_rate: unclamped_emission_rate,
+            unclamped_emission_rate,
+        });
+    }
+
+    // limit the change from the current rate, in both directions
+    let max_rate_change = current_emission_rate * config.max_rate_change_per_epoch;
+    let max_emission_rate = current_emission_rate + max_rate_change;
+    let min_emission_rate = if max_rate_change > current_emission_rate {
+        Decimal256::zero()
+    } else {
+        current_emission_rate - max_rate_change
+    };
+
+    let emission_rate = if unclamped_emission_rate > max_emission_rate {
+        max_emission_rate
+    } else if unclamped_emission_rate < min_emission_rate {
+        min_emission_rate
+    } else {
+        unclamped_emission_rate
+    };
+
+    Ok(AncEmissionRateResponse {
+        emission_rate,
+        unclamped_emission_rate,
+    })
 }
@@ -11,4 +11,7 @@ pub enum ContractError {
 
     #[error("Unauthorized")]
     Unauthorized {},
+
+    #[error("Deposit rate floor cannot exceed the ceiling")]
+    InvalidDepositRateBounds {},
 }
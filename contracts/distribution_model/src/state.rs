@@ -2,18 +2,44 @@ use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use cosmwasm_bignumber::Decimal256;
-use cosmwasm_std::{CanonicalAddr, StdResult, Storage};
-use cosmwasm_storage::{singleton, singleton_read};
+use cosmwasm_std::{CanonicalAddr, Order, StdResult, Storage};
+use cosmwasm_storage::{singleton, singleton_read, Bucket, ReadonlyBucket};
+use moneymarket::distribution_model::EmissionHistoryElem;
+use moneymarket::pagination::{clamp_limit, DEFAULT_LIMIT, MAX_LIMIT};
 
 static KEY_CONFIG: &[u8] = b"config";
+static KEY_STATE: &[u8] = b"state";
+static KEY_EMISSION_HISTORY_HEIGHTS: &[u8] = b"emission_history_heights";
+
+static PREFIX_EMISSION_HISTORY: &[u8] = b"emission_history";
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Config {
     pub owner: CanonicalAddr,
+    pub risk_admin: Option<CanonicalAddr>,
     pub emission_cap: Decimal256,
     pub emission_floor: Decimal256,
     pub increment_multiplier: Decimal256,
     pub decrement_multiplier: Decimal256,
+    pub max_rate_change_per_epoch: Decimal256,
+    pub deposit_rate_floor: Decimal256,
+    pub deposit_rate_ceiling: Decimal256,
+    pub overseer_contract: Option<CanonicalAddr>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct State {
+    pub emission_rate: Decimal256,
+    pub last_updated: u64,
+}
+
+/// Emission rate update recorded at a height
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct EmissionRecord {
+    pub emission_rate: Decimal256,
+    pub deposit_rate: Decimal256,
+    pub target_deposit_rate: Decimal256,
+    pub threshold_deposit_rate: Decimal256,
 }
 
 pub fn store_config(storage: &mut dyn Storage, config: &Config) -> StdResult<()> {
@@ -23,3 +49,76 @@ pub fn store_config(storage: &mut dyn Storage, config: &Config) -> StdResult<()>
 pub fn read_config(storage: &dyn Storage) -> StdResult<Config> {
     singleton_read(storage, KEY_CONFIG).load()
 }
+
+pub fn store_state(storage: &mut dyn Storage, state: &State) -> StdResult<()> {
+    singleton(storage, KEY_STATE).save(state)
+}
+
+pub fn read_state(storage: &dyn Storage) -> StdResult<State> {
+    Ok(singleton_read(storage, KEY_STATE)
+        .may_load()?
+        .unwrap_or_default())
+}
+
+pub fn store_emission_record(
+    storage: &mut dyn Storage,
+    height: u64,
+    record: &EmissionRecord,
+) -> StdResult<()> {
+    let mut history_bucket: Bucket<EmissionRecord> = Bucket::new(storage, PREFIX_EMISSION_HISTORY);
+    history_bucket.save(&height.to_be_bytes(), record)
+}
+
+pub fn remove_emission_record(storage: &mut dyn Storage, height: u64) {
+    let mut history_bucket: Bucket<EmissionRecord> = Bucket::new(storage, PREFIX_EMISSION_HISTORY);
+    history_bucket.remove(&height.to_be_bytes())
+}
+
+/// Heights of the retained records, oldest first
+pub fn store_emission_history_heights(storage: &mut dyn Storage, heights: &[u64]) -> StdResult<()> {
+    singleton(storage, KEY_EMISSION_HISTORY_HEIGHTS).save(&heights.to_vec())
+}
+
+pub fn read_emission_history_heights(storage: &dyn Storage) -> StdResult<Vec<u64>> {
+    Ok(singleton_read(storage, KEY_EMISSION_HISTORY_HEIGHTS)
+        .may_load()?
+        .unwrap_or_default())
+}
+
+pub fn read_emission_history(
+    storage: &dyn Storage,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Vec<EmissionHistoryElem>> {
+    let history_bucket: ReadonlyBucket<EmissionRecord> =
+        ReadonlyBucket::new(storage, PREFIX_EMISSION_HISTORY);
+
+    let limit = clamp_limit(limit, DEFAULT_LIMIT, MAX_LIMIT);
+    let start = calc_range_start(start_after);
+
+    history_bucket
+        .range(start.as_deref(), None, Order::Ascending)
+        .take(limit)
+        .map(|elem| {
+            let (k, v) = elem?;
+            let mut height = [0u8; 8];
+            height.copy_from_slice(&k);
+            Ok(EmissionHistoryElem {
+                height: u64::from_be_bytes(height),
+                emission_rate: v.emission_rate,
+                deposit_rate: v.deposit_rate,
+                target_deposit_rate: v.target_deposit_rate,
+                threshold_deposit_rate: v.threshold_deposit_rate,
+            })
+        })
+        .collect()
+}
+
+// this will set the first key after the provided key, by appending a 1 byte
+fn calc_range_start(start_after: Option<u64>) -> Option<Vec<u8>> {
+    start_after.map(|height| {
+        let mut v = height.to_be_bytes().to_vec();
+        v.push(1);
+        v
+    })
+}
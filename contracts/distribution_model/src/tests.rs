@@ -1,10 +1,11 @@
 use crate::contract::{execute, instantiate, query};
 use crate::error::ContractError;
 use cosmwasm_bignumber::Decimal256;
-use cosmwasm_std::from_binary;
 use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+use cosmwasm_std::{attr, from_binary, Deps};
 use moneymarket::distribution_model::{
-    AncEmissionRateResponse, ConfigResponse, ExecuteMsg, InstantiateMsg, QueryMsg,
+    AncEmissionRateResponse, ConfigResponse, DepositRateBoundsResponse, EmissionHistoryElem,
+    EmissionHistoryResponse, ExecuteMsg, InstantiateMsg, QueryMsg, StateResponse,
 };
 
 #[test]
@@ -13,10 +14,15 @@ fn proper_initialization() {
 
     let msg = InstantiateMsg {
         owner: "owner0000".to_string(),
+        risk_admin: None,
         emission_cap: Decimal256::from_uint256(100u64),
         emission_floor: Decimal256::from_uint256(10u64),
         increment_multiplier: Decimal256::percent(110),
         decrement_multiplier: Decimal256::percent(90),
+        max_rate_change_per_epoch: Decimal256::one(),
+        deposit_rate_floor: Decimal256::zero(),
+        deposit_rate_ceiling: Decimal256::one(),
+        overseer_contract: None,
     };
 
     let info = mock_info("addr0000", &[]);
@@ -33,6 +39,7 @@ fn proper_initialization() {
     assert_eq!("10", &value.emission_floor.to_string());
     assert_eq!("1.1", &value.increment_multiplier.to_string());
     assert_eq!("0.9", &value.decrement_multiplier.to_string());
+    assert_eq!("1", &value.max_rate_change_per_epoch.to_string());
 }
 
 #[test]
@@ -41,10 +48,15 @@ fn update_config() {
 
     let msg = InstantiateMsg {
         owner: "owner0000".to_string(),
+        risk_admin: None,
         emission_cap: Decimal256::from_uint256(100u64),
         emission_floor: Decimal256::from_uint256(10u64),
         increment_multiplier: Decimal256::percent(110),
         decrement_multiplier: Decimal256::percent(90),
+        max_rate_change_per_epoch: Decimal256::one(),
+        deposit_rate_floor: Decimal256::zero(),
+        deposit_rate_ceiling: Decimal256::one(),
+        overseer_contract: None,
     };
 
     let info = mock_info("addr0000", &[]);
@@ -54,10 +66,15 @@ fn update_config() {
     let info = mock_info("owner0000", &[]);
     let msg = ExecuteMsg::UpdateConfig {
         owner: Some("owner0001".to_string()),
+        risk_admin: None,
         emission_cap: None,
         emission_floor: None,
         increment_multiplier: None,
         decrement_multiplier: None,
+        max_rate_change_per_epoch: None,
+        deposit_rate_floor: None,
+        deposit_rate_ceiling: None,
+        overseer_contract: None,
     };
 
     let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -71,15 +88,21 @@ fn update_config() {
     assert_eq!("10", &value.emission_floor.to_string());
     assert_eq!("1.1", &value.increment_multiplier.to_string());
     assert_eq!("0.9", &value.decrement_multiplier.to_string());
+    assert_eq!("1", &value.max_rate_change_per_epoch.to_string());
 
     // Unauthorized err
     let info = mock_info("owner0000", &[]);
     let msg = ExecuteMsg::UpdateConfig {
         owner: None,
+        risk_admin: None,
         emission_cap: Some(Decimal256::from_uint256(100u64)),
         emission_floor: Some(Decimal256::from_uint256(10u64)),
         increment_multiplier: Some(Decimal256::percent(110)),
         decrement_multiplier: Some(Decimal256::percent(90)),
+        max_rate_change_per_epoch: Some(Decimal256::one()),
+        deposit_rate_floor: None,
+        deposit_rate_ceiling: None,
+        overseer_contract: None,
     };
 
     let res = execute(deps.as_mut(), mock_env(), info, msg);
@@ -95,10 +118,15 @@ fn proper_emission_rate() {
 
     let msg = InstantiateMsg {
         owner: "owner0000".to_string(),
+        risk_admin: None,
         emission_cap: Decimal256::from_uint256(100u64),
         emission_floor: Decimal256::from_uint256(10u64),
         increment_multiplier: Decimal256::percent(110),
         decrement_multiplier: Decimal256::percent(90),
+        max_rate_change_per_epoch: Decimal256::one(),
+        deposit_rate_floor: Decimal256::zero(),
+        deposit_rate_ceiling: Decimal256::one(),
+        overseer_contract: None,
     };
 
     let info = mock_info("addr0000", &[]);
@@ -161,3 +189,381 @@ fn proper_emission_rate() {
     let value: AncEmissionRateResponse = from_binary(&res).unwrap();
     assert_eq!("10", &value.emission_rate.to_string());
 }
+
+#[test]
+fn max_rate_change_per_epoch() {
+    let mut deps = mock_dependencies(&[]);
+
+    // a target miss multiplies the rate by 5 or by 0.1 per epoch
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        risk_admin: None,
+        emission_cap: Decimal256::from_uint256(100u64),
+        emission_floor: Decimal256::one(),
+        increment_multiplier: Decimal256::from_uint256(5u64),
+        decrement_multiplier: Decimal256::percent(10),
+        max_rate_change_per_epoch: Decimal256::percent(50),
+        deposit_rate_floor: Decimal256::zero(),
+        deposit_rate_ceiling: Decimal256::one(),
+        overseer_contract: None,
+    };
+
+    let info = mock_info("addr0000", &[]);
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let emission_rate = |deposit_rate: Decimal256, current_emission_rate: Decimal256| {
+        let query_msg = QueryMsg::AncEmissionRate {
+            deposit_rate,
+            target_deposit_rate: Decimal256::percent(10),
+            threshold_deposit_rate: Decimal256::percent(5),
+            current_emission_rate,
+        };
+        let res = query(deps.as_ref(), mock_env(), query_msg).unwrap();
+        let value: AncEmissionRateResponse = from_binary(&res).unwrap();
+        value
+    };
+
+    // deposit rate far below the target; the rate grows by
+    // at most 50% per epoch until it reaches the cap
+    let mut current_emission_rate = Decimal256::from_uint256(10u64);
+    let value = emission_rate(Decimal256::zero(), current_emission_rate);
+    assert_eq!("50", &value.unclamped_emission_rate.to_string());
+    assert_eq!("15", &value.emission_rate.to_string());
+
+    let mut epochs = vec![];
+    while current_emission_rate != Decimal256::from_uint256(100u64) {
+        current_emission_rate =
+            emission_rate(Decimal256::zero(), current_emission_rate).emission_rate;
+        epochs.push(current_emission_rate.to_string());
+    }
+    assert_eq!(
+        epochs,
+        vec!["15", "22.5", "33.75", "50.625", "75.9375", "100"]
+    );
+
+    // deposit rate far above the target; the rate shrinks by
+    // at most 50% per epoch until it reaches the floor
+    let value = emission_rate(Decimal256::one(), current_emission_rate);
+    assert_eq!("10", &value.unclamped_emission_rate.to_string());
+    assert_eq!("50", &value.emission_rate.to_string());
+
+    let mut epochs = vec![];
+    while current_emission_rate != Decimal256::one() {
+        current_emission_rate =
+            emission_rate(Decimal256::one(), current_emission_rate).emission_rate;
+        epochs.push(current_emission_rate.to_string());
+    }
+    assert_eq!(
+        epochs,
+        vec!["50", "25", "12.5", "6.25", "3.125", "1.5625", "1"]
+    );
+
+    // within the limit the rate is not clamped
+    let value = emission_rate(Decimal256::one(), Decimal256::from_uint256(2u64));
+    assert_eq!("1", &value.unclamped_emission_rate.to_string());
+    assert_eq!("1", &value.emission_rate.to_string());
+
+    // a zero rate jumps to the floor, then grows by the step limit
+    let value = emission_rate(Decimal256::percent(7), Decimal256::zero());
+    assert_eq!("1", &value.emission_rate.to_string());
+
+    let mut current_emission_rate = Decimal256::zero();
+    let mut epochs = vec![];
+    while current_emission_rate < Decimal256::from_uint256(5u64) {
+        current_emission_rate =
+            emission_rate(Decimal256::zero(), current_emission_rate).emission_rate;
+        epochs.push(current_emission_rate.to_string());
+    }
+    assert_eq!(epochs, vec!["1", "1.5", "2.25", "3.375", "5.0625"]);
+
+    // a rate above the cap drops to it at once
+    let value = emission_rate(Decimal256::percent(7), Decimal256::from_uint256(400u64));
+    assert_eq!("100", &value.emission_rate.to_string());
+}
+
+#[test]
+fn deposit_rate_bounds() {
+    let mut deps = mock_dependencies(&[]);
+
+    let mut msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        risk_admin: None,
+        emission_cap: Decimal256::from_uint256(100u64),
+        emission_floor: Decimal256::from_uint256(10u64),
+        increment_multiplier: Decimal256::percent(110),
+        decrement_multiplier: Decimal256::percent(90),
+        max_rate_change_per_epoch: Decimal256::one(),
+        deposit_rate_floor: Decimal256::from_ratio(2u64, 1000000u64),
+        deposit_rate_ceiling: Decimal256::from_ratio(1u64, 1000000u64),
+        overseer_contract: None,
+    };
+
+    let info = mock_info("addr0000", &[]);
+    let res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg.clone());
+    match res {
+        Err(ContractError::InvalidDepositRateBounds {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    msg.deposit_rate_floor = Decimal256::zero();
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::DepositRateBounds {}).unwrap();
+    let value: DepositRateBoundsResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        value,
+        DepositRateBoundsResponse {
+            deposit_rate_floor: Decimal256::zero(),
+            deposit_rate_ceiling: Decimal256::from_ratio(1u64, 1000000u64),
+        }
+    );
+
+    // the floor cannot be raised above the ceiling
+    let msg = ExecuteMsg::UpdateConfig {
+        owner: None,
+        risk_admin: None,
+        emission_cap: None,
+        emission_floor: None,
+        increment_multiplier: None,
+        decrement_multiplier: None,
+        max_rate_change_per_epoch: None,
+        deposit_rate_floor: Some(Decimal256::from_ratio(2u64, 1000000u64)),
+        deposit_rate_ceiling: None,
+        overseer_contract: None,
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg);
+    match res {
+        Err(ContractError::InvalidDepositRateBounds {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // unless the ceiling is raised along
+    let msg = ExecuteMsg::UpdateConfig {
+        owner: None,
+        risk_admin: None,
+        emission_cap: None,
+        emission_floor: None,
+        increment_multiplier: None,
+        decrement_multiplier: None,
+        max_rate_change_per_epoch: None,
+        deposit_rate_floor: Some(Decimal256::from_ratio(2u64, 1000000u64)),
+        deposit_rate_ceiling: Some(Decimal256::from_ratio(3u64, 1000000u64)),
+        overseer_contract: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    let value: ConfigResponse = from_binary(&res).unwrap();
+    assert_eq!("0.000002", &value.deposit_rate_floor.to_string());
+    assert_eq!("0.000003", &value.deposit_rate_ceiling.to_string());
+}
+
+#[test]
+fn risk_admin_permissions() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        risk_admin: Some("risk0000".to_string()),
+        emission_cap: Decimal256::from_uint256(100u64),
+        emission_floor: Decimal256::from_uint256(10u64),
+        increment_multiplier: Decimal256::percent(110),
+        decrement_multiplier: Decimal256::percent(90),
+        max_rate_change_per_epoch: Decimal256::one(),
+        deposit_rate_floor: Decimal256::zero(),
+        deposit_rate_ceiling: Decimal256::one(),
+        overseer_contract: None,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    let value: ConfigResponse = from_binary(&res).unwrap();
+    assert_eq!("owner0000", value.owner.as_str());
+    assert_eq!(Some("risk0000".to_string()), value.risk_admin);
+
+    let update_config =
+        |owner: Option<&str>, risk_admin: Option<&str>, emission_cap: Option<u64>| {
+            ExecuteMsg::UpdateConfig {
+                owner: owner.map(|owner| owner.to_string()),
+                risk_admin: risk_admin.map(|risk_admin| risk_admin.to_string()),
+                emission_cap: emission_cap.map(Decimal256::from_uint256),
+                emission_floor: None,
+                increment_multiplier: None,
+                decrement_multiplier: None,
+                max_rate_change_per_epoch: None,
+                deposit_rate_floor: None,
+                deposit_rate_ceiling: None,
+                overseer_contract: None,
+            }
+        };
+
+    // the risk admin tunes the rate parameters
+    let msg = update_config(None, None, Some(200u64));
+    execute(deps.as_mut(), mock_env(), mock_info("risk0000", &[]), msg).unwrap();
+
+    // but cannot touch the roles
+    for msg in [
+        update_config(Some("risk0000"), None, None),
+        update_config(None, Some("risk0001"), None),
+        update_config(Some("risk0000"), None, Some(300u64)),
+    ] {
+        let res = execute(deps.as_mut(), mock_env(), mock_info("risk0000", &[]), msg);
+        match res {
+            Err(ContractError::Unauthorized {}) => (),
+            _ => panic!("DO NOT ENTER HERE"),
+        }
+    }
+
+    // other senders cannot update anything
+    let msg = update_config(None, None, Some(300u64));
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg);
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // the owner can do everything, including replacing the risk admin
+    let msg = update_config(None, Some("risk0001"), Some(300u64));
+    execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg).unwrap();
+
+    let msg = update_config(None, None, Some(400u64));
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("risk0000", &[]),
+        msg.clone(),
+    );
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+    execute(deps.as_mut(), mock_env(), mock_info("risk0001", &[]), msg).unwrap();
+
+    let msg = update_config(Some("owner0001"), None, None);
+    execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    let value: ConfigResponse = from_binary(&res).unwrap();
+    assert_eq!("owner0001", value.owner.as_str());
+    assert_eq!(Some("risk0001".to_string()), value.risk_admin);
+    assert_eq!("400", &value.emission_cap.to_string());
+}
+
+#[test]
+fn emission_history() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        risk_admin: None,
+        emission_cap: Decimal256::from_uint256(100u64),
+        emission_floor: Decimal256::from_uint256(10u64),
+        increment_multiplier: Decimal256::percent(110),
+        decrement_multiplier: Decimal256::percent(90),
+        max_rate_change_per_epoch: Decimal256::one(),
+        deposit_rate_floor: Decimal256::zero(),
+        deposit_rate_ceiling: Decimal256::one(),
+        overseer_contract: Some("overseer0000".to_string()),
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap();
+    let value: StateResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        value,
+        StateResponse {
+            emission_rate: Decimal256::zero(),
+            last_updated: 0u64,
+        }
+    );
+
+    let update_emission_rate = |emission_rate: u64| ExecuteMsg::UpdateEmissionRate {
+        emission_rate: Decimal256::from_uint256(emission_rate),
+        deposit_rate: Decimal256::from_ratio(1u64, 1000000u64),
+        target_deposit_rate: Decimal256::from_ratio(2u64, 1000000u64),
+        threshold_deposit_rate: Decimal256::from_ratio(1u64, 1000000u64),
+    };
+
+    // only the overseer records the emission rate
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner0000", &[]),
+        update_emission_rate(50u64),
+    );
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let mut env = mock_env();
+    for emission_rate in [50u64, 55u64, 60u64] {
+        env.block.height += 100;
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("overseer0000", &[]),
+            update_emission_rate(emission_rate),
+        )
+        .unwrap();
+        assert_eq!(
+            res.attributes,
+            vec![
+                attr("action", "update_emission_rate"),
+                attr("emission_rate", emission_rate.to_string()),
+                attr("height", env.block.height.to_string()),
+            ]
+        );
+    }
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap();
+    let value: StateResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        value,
+        StateResponse {
+            emission_rate: Decimal256::from_uint256(60u64),
+            last_updated: env.block.height,
+        }
+    );
+
+    let emission_history = |deps: Deps, start_after: Option<u64>, limit: Option<u32>| {
+        let res = query(
+            deps,
+            mock_env(),
+            QueryMsg::EmissionHistory { start_after, limit },
+        )
+        .unwrap();
+        from_binary::<EmissionHistoryResponse>(&res)
+            .unwrap()
+            .history
+    };
+    let height = mock_env().block.height;
+
+    let history = emission_history(deps.as_ref(), None, Some(2u32));
+    assert_eq!(
+        history,
+        vec![
+            EmissionHistoryElem {
+                height: height + 100,
+                emission_rate: Decimal256::from_uint256(50u64),
+                deposit_rate: Decimal256::from_ratio(1u64, 1000000u64),
+                target_deposit_rate: Decimal256::from_ratio(2u64, 1000000u64),
+                threshold_deposit_rate: Decimal256::from_ratio(1u64, 1000000u64),
+            },
+            EmissionHistoryElem {
+                height: height + 200,
+                emission_rate: Decimal256::from_uint256(55u64),
+                deposit_rate: Decimal256::from_ratio(1u64, 1000000u64),
+                target_deposit_rate: Decimal256::from_ratio(2u64, 1000000u64),
+                threshold_deposit_rate: Decimal256::from_ratio(1u64, 1000000u64),
+            },
+        ]
+    );
+
+    let history = emission_history(deps.as_ref(), Some(height + 200), None);
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].height, height + 300);
+    assert_eq!(history[0].emission_rate, Decimal256::from_uint256(60u64));
+
+    assert!(emission_history(deps.as_ref(), Some(height + 300), None).is_empty());
+}
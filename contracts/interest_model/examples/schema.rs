@@ -4,7 +4,8 @@ use std::fs::create_dir_all;
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 
 use moneymarket::interest_model::{
-    BorrowRateResponse, ConfigResponse, ExecuteMsg, InstantiateMsg, QueryMsg,
+    BorrowRateResponse, ConfigResponse, ExecuteMsg, InstantiateMsg, ModelInfoResponse,
+    ProjectedRatesResponse, QueryMsg, SimulateBorrowRateResponse,
 };
 
 fn main() {
@@ -17,5 +18,8 @@ fn main() {
     export_schema(&schema_for!(ExecuteMsg), &out_dir);
     export_schema(&schema_for!(QueryMsg), &out_dir);
     export_schema(&schema_for!(ConfigResponse), &out_dir);
+    export_schema(&schema_for!(ModelInfoResponse), &out_dir);
     export_schema(&schema_for!(BorrowRateResponse), &out_dir);
+    export_schema(&schema_for!(SimulateBorrowRateResponse), &out_dir);
+    export_schema(&schema_for!(ProjectedRatesResponse), &out_dir);
 }
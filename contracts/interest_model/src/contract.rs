@@ -1,15 +1,28 @@
 use crate::error::ContractError;
-use crate::state::{read_config, store_config, Config};
+use crate::state::{
+    read_config, read_ema_utilization, store_config, store_ema_utilization, Config,
+};
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 
 use cosmwasm_bignumber::Decimal256;
 use cosmwasm_bignumber::Uint256;
-use cosmwasm_std::{to_binary, Addr, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult};
+use cosmwasm_std::{
+    attr, to_binary, Addr, Attribute, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdError,
+    StdResult,
+};
 use moneymarket::common::optional_addr_validate;
 use moneymarket::interest_model::{
-    BorrowRateResponse, ConfigResponse, ExecuteMsg, InstantiateMsg, QueryMsg,
+    BorrowRateResponse, ConfigOverride, ConfigResponse, ExecuteMsg, InstantiateMsg,
+    ModelInfoResponse, ProjectedRatesResponse, QueryMsg, RateMode, SimulateBorrowRateResponse,
+    UtilizationMode, MODEL_TYPE_FIXED_V1, MODEL_TYPE_KINKED_V1,
 };
+use moneymarket::rates::{
+    apr_to_per_block, compute_utilization_ratio, per_block_to_apr, per_block_to_apy,
+};
+
+/// Absolute ceiling of max_borrow_rate, a per-block rate of 100%
+const MAX_BORROW_RATE_CEILING_PERCENT: u64 = 100;
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
@@ -17,15 +30,30 @@ pub fn instantiate(
     _env: Env,
     _info: MessageInfo,
     msg: InstantiateMsg,
-) -> StdResult<Response> {
-    store_config(
-        deps.storage,
-        &Config {
-            owner: deps.api.addr_canonicalize(&msg.owner)?,
-            base_rate: msg.base_rate,
-            interest_multiplier: msg.interest_multiplier,
-        },
-    )?;
+) -> Result<Response, ContractError> {
+    let config = Config {
+        owner: deps.api.addr_canonicalize(&msg.owner)?,
+        risk_admin: msg
+            .risk_admin
+            .map(|risk_admin| deps.api.addr_canonicalize(&risk_admin))
+            .transpose()?,
+        base_rate: msg.base_rate,
+        optimal_utilization: msg.optimal_utilization,
+        slope_1: msg.slope_1,
+        slope_2: msg.slope_2,
+        max_apr: msg.max_apr,
+        blocks_per_year: msg.blocks_per_year,
+        max_borrow_rate: msg.max_borrow_rate,
+        market_contract: msg
+            .market_contract
+            .map(|market_contract| deps.api.addr_canonicalize(&market_contract))
+            .transpose()?,
+        utilization_mode: msg.utilization_mode.unwrap_or(UtilizationMode::Spot),
+        ema_smoothing_factor: msg.ema_smoothing_factor,
+        rate_mode: msg.rate_mode.unwrap_or(RateMode::Dynamic),
+    };
+    validate_config(&config)?;
+    store_config(deps.storage, &config)?;
 
     Ok(Response::default())
 }
@@ -40,53 +68,288 @@ pub fn execute(
     match msg {
         ExecuteMsg::UpdateConfig {
             owner,
+            risk_admin,
             base_rate,
-            interest_multiplier,
+            optimal_utilization,
+            slope_1,
+            slope_2,
+            max_apr,
+            blocks_per_year,
+            max_borrow_rate,
+            market_contract,
+            utilization_mode,
+            ema_smoothing_factor,
+            rate_mode,
         } => {
             let api = deps.api;
             update_config(
                 deps,
                 info,
                 optional_addr_validate(api, owner)?,
+                optional_addr_validate(api, risk_admin)?,
                 base_rate,
-                interest_multiplier,
+                optimal_utilization,
+                slope_1,
+                slope_2,
+                max_apr,
+                blocks_per_year,
+                max_borrow_rate,
+                optional_addr_validate(api, market_contract)?,
+                utilization_mode,
+                ema_smoothing_factor,
+                rate_mode,
             )
         }
+        ExecuteMsg::RecordUtilization { utilization } => {
+            record_utilization(deps, info, utilization)
+        }
     }
 }
 
+/// The kink has to leave room for both slopes, and the base rate cannot
+/// exceed max_apr spread over a year of blocks. Slopes are unsigned,
+/// so they cannot be negative. A fixed rate is bounded by max_borrow_rate.
+fn validate_config(config: &Config) -> Result<(), ContractError> {
+    if config.optimal_utilization.is_zero() || config.optimal_utilization >= Decimal256::one() {
+        return Err(ContractError::InvalidOptimalUtilization {});
+    }
+
+    if config.blocks_per_year == 0 {
+        return Err(ContractError::InvalidBlocksPerYear {});
+    }
+
+    let max_base_rate = apr_to_per_block(config.max_apr, config.blocks_per_year)?;
+    if config.base_rate > max_base_rate {
+        return Err(ContractError::BaseRateExceedsMax(max_base_rate.to_string()));
+    }
+
+    let max_borrow_rate_ceiling = Decimal256::percent(MAX_BORROW_RATE_CEILING_PERCENT);
+    if config.max_borrow_rate > max_borrow_rate_ceiling {
+        return Err(ContractError::MaxBorrowRateExceedsCeiling(
+            max_borrow_rate_ceiling.to_string(),
+        ));
+    }
+
+    if config.ema_smoothing_factor.is_zero() || config.ema_smoothing_factor > Decimal256::one() {
+        return Err(ContractError::InvalidSmoothingFactor {});
+    }
+
+    if let RateMode::Fixed { rate } = config.rate_mode {
+        if rate > config.max_borrow_rate {
+            return Err(ContractError::FixedRateExceedsMax(
+                config.max_borrow_rate.to_string(),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Logs the old and the new value of an updated parameter
+fn change_attributes(key: &str, old: String, new: String) -> Vec<Attribute> {
+    vec![
+        attr(format!("old_{}", key), old),
+        attr(format!("new_{}", key), new),
+    ]
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn update_config(
     deps: DepsMut,
     info: MessageInfo,
     owner: Option<Addr>,
+    risk_admin: Option<Addr>,
     base_rate: Option<Decimal256>,
-    interest_multiplier: Option<Decimal256>,
+    optimal_utilization: Option<Decimal256>,
+    slope_1: Option<Decimal256>,
+    slope_2: Option<Decimal256>,
+    max_apr: Option<Decimal256>,
+    blocks_per_year: Option<u64>,
+    max_borrow_rate: Option<Decimal256>,
+    market_contract: Option<Addr>,
+    utilization_mode: Option<UtilizationMode>,
+    ema_smoothing_factor: Option<Decimal256>,
+    rate_mode: Option<RateMode>,
 ) -> Result<Response, ContractError> {
     let mut config: Config = read_config(deps.storage)?;
-    if deps.api.addr_canonicalize(info.sender.as_str())? != config.owner {
-        return Err(ContractError::Unauthorized {});
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if sender_raw != config.owner {
+        // the risk admin can only tune the rate parameters
+        if config.risk_admin != Some(sender_raw)
+            || owner.is_some()
+            || risk_admin.is_some()
+            || market_contract.is_some()
+        {
+            return Err(ContractError::Unauthorized {});
+        }
     }
 
+    let mut attributes: Vec<Attribute> = vec![attr("action", "update_config")];
     if let Some(owner) = owner {
+        attributes.extend(change_attributes(
+            "owner",
+            deps.api.addr_humanize(&config.owner)?.to_string(),
+            owner.to_string(),
+        ));
         config.owner = deps.api.addr_canonicalize(owner.as_str())?;
     }
 
+    if let Some(risk_admin) = risk_admin {
+        let old_risk_admin = match &config.risk_admin {
+            Some(old_risk_admin) => deps.api.addr_humanize(old_risk_admin)?.to_string(),
+            None => "".to_string(),
+        };
+        attributes.extend(change_attributes(
+            "risk_admin",
+            old_risk_admin,
+            risk_admin.to_string(),
+        ));
+        config.risk_admin = Some(deps.api.addr_canonicalize(risk_admin.as_str())?);
+    }
+
     if let Some(base_rate) = base_rate {
+        attributes.extend(change_attributes(
+            "base_rate",
+            config.base_rate.to_string(),
+            base_rate.to_string(),
+        ));
         config.base_rate = base_rate;
     }
 
-    if let Some(interest_multiplier) = interest_multiplier {
-        config.interest_multiplier = interest_multiplier;
+    if let Some(optimal_utilization) = optimal_utilization {
+        attributes.extend(change_attributes(
+            "optimal_utilization",
+            config.optimal_utilization.to_string(),
+            optimal_utilization.to_string(),
+        ));
+        config.optimal_utilization = optimal_utilization;
     }
 
+    if let Some(slope_1) = slope_1 {
+        attributes.extend(change_attributes(
+            "slope_1",
+            config.slope_1.to_string(),
+            slope_1.to_string(),
+        ));
+        config.slope_1 = slope_1;
+    }
+
+    if let Some(slope_2) = slope_2 {
+        attributes.extend(change_attributes(
+            "slope_2",
+            config.slope_2.to_string(),
+            slope_2.to_string(),
+        ));
+        config.slope_2 = slope_2;
+    }
+
+    if let Some(max_apr) = max_apr {
+        attributes.extend(change_attributes(
+            "max_apr",
+            config.max_apr.to_string(),
+            max_apr.to_string(),
+        ));
+        config.max_apr = max_apr;
+    }
+
+    if let Some(blocks_per_year) = blocks_per_year {
+        attributes.extend(change_attributes(
+            "blocks_per_year",
+            config.blocks_per_year.to_string(),
+            blocks_per_year.to_string(),
+        ));
+        config.blocks_per_year = blocks_per_year;
+    }
+
+    if let Some(max_borrow_rate) = max_borrow_rate {
+        attributes.extend(change_attributes(
+            "max_borrow_rate",
+            config.max_borrow_rate.to_string(),
+            max_borrow_rate.to_string(),
+        ));
+        config.max_borrow_rate = max_borrow_rate;
+    }
+
+    if let Some(market_contract) = market_contract {
+        let old_market_contract = match &config.market_contract {
+            Some(old_market_contract) => deps.api.addr_humanize(old_market_contract)?.to_string(),
+            None => "".to_string(),
+        };
+        attributes.extend(change_attributes(
+            "market_contract",
+            old_market_contract,
+            market_contract.to_string(),
+        ));
+        config.market_contract = Some(deps.api.addr_canonicalize(market_contract.as_str())?);
+    }
+
+    if let Some(utilization_mode) = utilization_mode {
+        attributes.extend(change_attributes(
+            "utilization_mode",
+            config.utilization_mode.to_string(),
+            utilization_mode.to_string(),
+        ));
+        config.utilization_mode = utilization_mode;
+    }
+
+    if let Some(ema_smoothing_factor) = ema_smoothing_factor {
+        attributes.extend(change_attributes(
+            "ema_smoothing_factor",
+            config.ema_smoothing_factor.to_string(),
+            ema_smoothing_factor.to_string(),
+        ));
+        config.ema_smoothing_factor = ema_smoothing_factor;
+    }
+
+    if let Some(rate_mode) = rate_mode {
+        attributes.extend(change_attributes(
+            "rate_mode",
+            config.rate_mode.to_string(),
+            rate_mode.to_string(),
+        ));
+        config.rate_mode = rate_mode;
+    }
+
+    // the bounds depend on each other, so they are
+    // checked once every field is updated
+    validate_config(&config)?;
     store_config(deps.storage, &config)?;
-    Ok(Response::default())
+    Ok(Response::new().add_attributes(attributes))
+}
+
+pub fn record_utilization(
+    deps: DepsMut,
+    info: MessageInfo,
+    utilization: Decimal256,
+) -> Result<Response, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if config.market_contract != Some(sender_raw) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    // the first record seeds the average
+    let ema_utilization = match read_ema_utilization(deps.storage)? {
+        Some(ema_utilization) => {
+            utilization * config.ema_smoothing_factor
+                + ema_utilization * (Decimal256::one() - config.ema_smoothing_factor)
+        }
+        None => utilization,
+    };
+    store_ema_utilization(deps.storage, &ema_utilization)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "record_utilization"),
+        attr("utilization", utilization.to_string()),
+        attr("ema_utilization", ema_utilization.to_string()),
+    ]))
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::Config {} => to_binary(&query_config(deps)?),
+        QueryMsg::ModelInfo {} => to_binary(&query_model_info(deps)?),
         QueryMsg::BorrowRate {
             market_balance,
             total_liabilities,
@@ -97,6 +360,32 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
             total_liabilities,
             total_reserves,
         )?),
+        QueryMsg::SimulateBorrowRate {
+            market_balance,
+            total_liabilities,
+            total_reserves,
+            params,
+        } => to_binary(&query_simulate_borrow_rate(
+            deps,
+            market_balance,
+            total_liabilities,
+            total_reserves,
+            params.unwrap_or_default(),
+        )?),
+        QueryMsg::ProjectedRates {
+            market_balance,
+            total_liabilities,
+            total_reserves,
+            reserve_factor,
+            blocks_per_year,
+        } => to_binary(&query_projected_rates(
+            deps,
+            market_balance,
+            total_liabilities,
+            total_reserves,
+            reserve_factor,
+            blocks_per_year,
+        )?),
     }
 }
 
@@ -104,13 +393,46 @@ fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
     let state = read_config(deps.storage)?;
     let resp = ConfigResponse {
         owner: deps.api.addr_humanize(&state.owner)?.to_string(),
+        risk_admin: state
+            .risk_admin
+            .map(|risk_admin| deps.api.addr_humanize(&risk_admin))
+            .transpose()?
+            .map(|risk_admin| risk_admin.to_string()),
         base_rate: state.base_rate,
-        interest_multiplier: state.interest_multiplier,
+        optimal_utilization: state.optimal_utilization,
+        slope_1: state.slope_1,
+        slope_2: state.slope_2,
+        max_apr: state.max_apr,
+        blocks_per_year: state.blocks_per_year,
+        max_borrow_rate: state.max_borrow_rate,
+        market_contract: state
+            .market_contract
+            .map(|market_contract| deps.api.addr_humanize(&market_contract))
+            .transpose()?
+            .map(|market_contract| market_contract.to_string()),
+        utilization_mode: state.utilization_mode,
+        ema_smoothing_factor: state.ema_smoothing_factor,
+        ema_utilization: read_ema_utilization(deps.storage)?,
+        rate_mode: state.rate_mode,
     };
 
     Ok(resp)
 }
 
+fn query_model_info(deps: Deps) -> StdResult<ModelInfoResponse> {
+    let config: Config = read_config(deps.storage)?;
+    let model_type = match config.rate_mode {
+        RateMode::Dynamic => MODEL_TYPE_KINKED_V1,
+        RateMode::Fixed { .. } => MODEL_TYPE_FIXED_V1,
+    };
+
+    Ok(ModelInfoResponse {
+        model_type: model_type.to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        params: query_config(deps)?,
+    })
+}
+
 fn query_borrow_rate(
     deps: Deps,
     market_balance: Uint256,
@@ -118,18 +440,134 @@ fn query_borrow_rate(
     total_reserves: Decimal256,
 ) -> StdResult<BorrowRateResponse> {
     let config: Config = read_config(deps.storage)?;
+    let utilization_ratio = effective_utilization_ratio(
+        deps,
+        &config,
+        compute_utilization_ratio(market_balance, total_liabilities, total_reserves),
+    )?;
 
-    // ignore decimal parts
-    let total_value_in_market =
-        Decimal256::from_uint256(market_balance) + total_liabilities - total_reserves;
+    Ok(compute_borrow_rate(&config, utilization_ratio))
+}
 
-    let utilization_ratio = if total_value_in_market.is_zero() {
-        Decimal256::zero()
-    } else {
-        total_liabilities / total_value_in_market
+fn query_simulate_borrow_rate(
+    deps: Deps,
+    market_balance: Uint256,
+    total_liabilities: Decimal256,
+    total_reserves: Decimal256,
+    params: ConfigOverride,
+) -> StdResult<SimulateBorrowRateResponse> {
+    let config: Config = read_config(deps.storage)?;
+    let utilization_ratio = effective_utilization_ratio(
+        deps,
+        &config,
+        compute_utilization_ratio(market_balance, total_liabilities, total_reserves),
+    )?;
+    let current_rate = compute_borrow_rate(&config, utilization_ratio).rate;
+
+    let simulated_config = Config {
+        base_rate: params.base_rate.unwrap_or(config.base_rate),
+        optimal_utilization: params
+            .optimal_utilization
+            .unwrap_or(config.optimal_utilization),
+        slope_1: params.slope_1.unwrap_or(config.slope_1),
+        slope_2: params.slope_2.unwrap_or(config.slope_2),
+        max_borrow_rate: params.max_borrow_rate.unwrap_or(config.max_borrow_rate),
+        ..config
     };
 
-    Ok(BorrowRateResponse {
-        rate: utilization_ratio * config.interest_multiplier + config.base_rate,
+    Ok(SimulateBorrowRateResponse {
+        current_rate,
+        simulated_rate: compute_borrow_rate(&simulated_config, utilization_ratio).rate,
+    })
+}
+
+/// Depositors earn the borrow interest on the utilized share
+/// of the market, minus the reserve share. Rates are annualized
+/// both without compounding and compounded every block.
+fn query_projected_rates(
+    deps: Deps,
+    market_balance: Uint256,
+    total_liabilities: Decimal256,
+    total_reserves: Decimal256,
+    reserve_factor: Decimal256,
+    blocks_per_year: Option<u64>,
+) -> StdResult<ProjectedRatesResponse> {
+    if reserve_factor > Decimal256::one() {
+        return Err(StdError::generic_err("reserve_factor cannot exceed 1"));
+    }
+
+    let config: Config = read_config(deps.storage)?;
+    let utilization_ratio =
+        compute_utilization_ratio(market_balance, total_liabilities, total_reserves);
+    let borrow_rate = compute_borrow_rate(
+        &config,
+        effective_utilization_ratio(deps, &config, utilization_ratio)?,
+    )
+    .rate;
+    let deposit_rate = borrow_rate * utilization_ratio * (Decimal256::one() - reserve_factor);
+
+    let blocks_per_year = blocks_per_year.unwrap_or(config.blocks_per_year);
+    Ok(ProjectedRatesResponse {
+        borrow_rate,
+        deposit_rate,
+        borrow_apr: per_block_to_apr(borrow_rate, blocks_per_year),
+        deposit_apr: per_block_to_apr(deposit_rate, blocks_per_year),
+        borrow_apy: per_block_to_apy(borrow_rate, blocks_per_year)?,
+        deposit_apy: per_block_to_apy(deposit_rate, blocks_per_year)?,
+    })
+}
+
+/// Smooths the spot utilization with the recorded EMA, which
+/// falls back to the spot one until the market records any
+fn effective_utilization_ratio(
+    deps: Deps,
+    config: &Config,
+    spot_utilization_ratio: Decimal256,
+) -> StdResult<Decimal256> {
+    let ema_utilization_ratio =
+        read_ema_utilization(deps.storage)?.unwrap_or(spot_utilization_ratio);
+
+    Ok(match config.utilization_mode {
+        UtilizationMode::Spot => spot_utilization_ratio,
+        UtilizationMode::MaxSpotEma => {
+            if ema_utilization_ratio > spot_utilization_ratio {
+                ema_utilization_ratio
+            } else {
+                spot_utilization_ratio
+            }
+        }
+        UtilizationMode::Ema => ema_utilization_ratio,
     })
 }
+
+fn compute_borrow_rate(config: &Config, utilization_ratio: Decimal256) -> BorrowRateResponse {
+    // a fixed rate is within max_borrow_rate, and ignores the utilization
+    if let RateMode::Fixed { rate } = config.rate_mode {
+        return BorrowRateResponse {
+            rate,
+            clamped: false,
+        };
+    }
+
+    // slope_2 only applies to the utilization above the kink,
+    // so the rate is continuous at optimal_utilization
+    let utilization_rate = if utilization_ratio <= config.optimal_utilization {
+        utilization_ratio * config.slope_1
+    } else {
+        config.optimal_utilization * config.slope_1
+            + (utilization_ratio - config.optimal_utilization) * config.slope_2
+    };
+
+    let rate = utilization_rate + config.base_rate;
+    if rate > config.max_borrow_rate {
+        BorrowRateResponse {
+            rate: config.max_borrow_rate,
+            clamped: true,
+        }
+    } else {
+        BorrowRateResponse {
+            rate,
+            clamped: false,
+        }
+    }
+}
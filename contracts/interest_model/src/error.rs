@@ -11,4 +11,22 @@ pub enum ContractError {
 
     #[error("Unauthorized")]
     Unauthorized {},
+
+    #[error("Optimal utilization must be greater than 0 and less than 1")]
+    InvalidOptimalUtilization {},
+
+    #[error("Blocks per year must be greater than 0")]
+    InvalidBlocksPerYear {},
+
+    #[error("Base rate cannot exceed the max base rate: {0}")]
+    BaseRateExceedsMax(String),
+
+    #[error("Max borrow rate cannot exceed the ceiling: {0}")]
+    MaxBorrowRateExceedsCeiling(String),
+
+    #[error("Fixed rate cannot exceed the max borrow rate: {0}")]
+    FixedRateExceedsMax(String),
+
+    #[error("EMA smoothing factor must be within (0, 1]")]
+    InvalidSmoothingFactor {},
 }
@@ -0,0 +1,4 @@
+//! Messages and query responses of the contract, defined in the
+//! `moneymarket` package so clients do not need the contract crate
+
+pub use moneymarket::interest_model::*;
@@ -4,14 +4,26 @@ use serde::{Deserialize, Serialize};
 use cosmwasm_bignumber::Decimal256;
 use cosmwasm_std::{CanonicalAddr, StdResult, Storage};
 use cosmwasm_storage::{singleton, singleton_read};
+use moneymarket::interest_model::{RateMode, UtilizationMode};
 
 static KEY_CONFIG: &[u8] = b"config";
+static KEY_EMA_UTILIZATION: &[u8] = b"ema_utilization";
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Config {
     pub owner: CanonicalAddr,
+    pub risk_admin: Option<CanonicalAddr>,
     pub base_rate: Decimal256,
-    pub interest_multiplier: Decimal256,
+    pub optimal_utilization: Decimal256,
+    pub slope_1: Decimal256,
+    pub slope_2: Decimal256,
+    pub max_apr: Decimal256,
+    pub blocks_per_year: u64,
+    pub max_borrow_rate: Decimal256,
+    pub market_contract: Option<CanonicalAddr>,
+    pub utilization_mode: UtilizationMode,
+    pub ema_smoothing_factor: Decimal256,
+    pub rate_mode: RateMode,
 }
 
 pub fn store_config(storage: &mut dyn Storage, config: &Config) -> StdResult<()> {
@@ -21,3 +33,14 @@ pub fn store_config(storage: &mut dyn Storage, config: &Config) -> StdResult<()>
 pub fn read_config(storage: &dyn Storage) -> StdResult<Config> {
     singleton_read(storage, KEY_CONFIG).load()
 }
+
+pub fn store_ema_utilization(
+    storage: &mut dyn Storage,
+    ema_utilization: &Decimal256,
+) -> StdResult<()> {
+    singleton(storage, KEY_EMA_UTILIZATION).save(ema_utilization)
+}
+
+pub fn read_ema_utilization(storage: &dyn Storage) -> StdResult<Option<Decimal256>> {
+    singleton_read(storage, KEY_EMA_UTILIZATION).may_load()
+}
@@ -1,11 +1,14 @@
 use crate::contract::{execute, instantiate, query};
 use crate::error::ContractError;
 use cosmwasm_bignumber::{Decimal256, Uint256};
-use cosmwasm_std::from_binary;
 use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+use cosmwasm_std::{attr, from_binary, Deps, StdError};
 use moneymarket::interest_model::{
-    BorrowRateResponse, ConfigResponse, ExecuteMsg, InstantiateMsg, QueryMsg,
+    BorrowRateResponse, ConfigOverride, ConfigResponse, ExecuteMsg, InstantiateMsg,
+    ModelInfoResponse, ProjectedRatesResponse, QueryMsg, RateMode, SimulateBorrowRateResponse,
+    UtilizationMode, MODEL_TYPE_FIXED_V1, MODEL_TYPE_KINKED_V1,
 };
+use std::str::FromStr;
 
 #[test]
 fn proper_initialization() {
@@ -13,8 +16,18 @@ fn proper_initialization() {
 
     let msg = InstantiateMsg {
         owner: "owner0000".to_string(),
+        risk_admin: None,
         base_rate: Decimal256::percent(10),
-        interest_multiplier: Decimal256::percent(10),
+        optimal_utilization: Decimal256::percent(80),
+        slope_1: Decimal256::percent(10),
+        slope_2: Decimal256::one(),
+        max_apr: Decimal256::one(),
+        blocks_per_year: 10,
+        max_borrow_rate: Decimal256::one(),
+        market_contract: None,
+        utilization_mode: None,
+        ema_smoothing_factor: Decimal256::one(),
+        rate_mode: None,
     };
 
     let info = mock_info("addr0000", &[]);
@@ -28,7 +41,12 @@ fn proper_initialization() {
     let value: ConfigResponse = from_binary(&res).unwrap();
     assert_eq!("owner0000", value.owner.as_str());
     assert_eq!("0.1", &value.base_rate.to_string());
-    assert_eq!("0.1", &value.interest_multiplier.to_string());
+    assert_eq!("0.8", &value.optimal_utilization.to_string());
+    assert_eq!("0.1", &value.slope_1.to_string());
+    assert_eq!("1", &value.slope_2.to_string());
+    assert_eq!("1", &value.max_apr.to_string());
+    assert_eq!(10, value.blocks_per_year);
+    assert_eq!("1", &value.max_borrow_rate.to_string());
 
     let query_msg = QueryMsg::BorrowRate {
         market_balance: Uint256::from(1000000u128),
@@ -57,8 +75,18 @@ fn update_config() {
 
     let msg = InstantiateMsg {
         owner: "owner0000".to_string(),
+        risk_admin: None,
         base_rate: Decimal256::percent(10),
-        interest_multiplier: Decimal256::percent(10),
+        optimal_utilization: Decimal256::percent(80),
+        slope_1: Decimal256::percent(10),
+        slope_2: Decimal256::one(),
+        max_apr: Decimal256::one(),
+        blocks_per_year: 10,
+        max_borrow_rate: Decimal256::one(),
+        market_contract: None,
+        utilization_mode: None,
+        ema_smoothing_factor: Decimal256::one(),
+        rate_mode: None,
     };
 
     let info = mock_info("addr0000", &[]);
@@ -68,8 +96,18 @@ fn update_config() {
     let info = mock_info("owner0000", &[]);
     let msg = ExecuteMsg::UpdateConfig {
         owner: Some("owner0001".to_string()),
+        risk_admin: None,
         base_rate: None,
-        interest_multiplier: None,
+        optimal_utilization: None,
+        slope_1: None,
+        slope_2: None,
+        max_apr: None,
+        blocks_per_year: None,
+        max_borrow_rate: None,
+        market_contract: None,
+        utilization_mode: None,
+        ema_smoothing_factor: None,
+        rate_mode: None,
     };
 
     let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -80,14 +118,28 @@ fn update_config() {
     let value: ConfigResponse = from_binary(&res).unwrap();
     assert_eq!("owner0001", value.owner.as_str());
     assert_eq!("0.1", &value.base_rate.to_string());
-    assert_eq!("0.1", &value.interest_multiplier.to_string());
+    assert_eq!("0.8", &value.optimal_utilization.to_string());
+    assert_eq!("0.1", &value.slope_1.to_string());
+    assert_eq!("1", &value.slope_2.to_string());
+    assert_eq!("1", &value.max_apr.to_string());
+    assert_eq!(10, value.blocks_per_year);
 
     // Unauthorized err
     let info = mock_info("owner0000", &[]);
     let msg = ExecuteMsg::UpdateConfig {
         owner: None,
+        risk_admin: None,
         base_rate: Some(Decimal256::percent(1)),
-        interest_multiplier: Some(Decimal256::percent(1)),
+        optimal_utilization: None,
+        slope_1: Some(Decimal256::percent(1)),
+        slope_2: None,
+        max_apr: None,
+        blocks_per_year: None,
+        max_borrow_rate: None,
+        market_contract: None,
+        utilization_mode: None,
+        ema_smoothing_factor: None,
+        rate_mode: None,
     };
 
     let res = execute(deps.as_mut(), mock_env(), info, msg);
@@ -96,3 +148,982 @@ fn update_config() {
         _ => panic!("Must return unauthorized error"),
     }
 }
+
+#[test]
+fn kinked_borrow_rate() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        risk_admin: None,
+        base_rate: Decimal256::percent(2),
+        optimal_utilization: Decimal256::percent(80),
+        slope_1: Decimal256::percent(10),
+        slope_2: Decimal256::one(),
+        max_apr: Decimal256::one(),
+        blocks_per_year: 10,
+        max_borrow_rate: Decimal256::one(),
+        market_contract: None,
+        utilization_mode: None,
+        ema_smoothing_factor: Decimal256::one(),
+        rate_mode: None,
+    };
+
+    let info = mock_info("addr0000", &[]);
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // a market of one unit with the reserves matching the
+    // liabilities has a utilization ratio of the liabilities
+    let borrow_rate = |deps: Deps, utilization_ratio: &str| -> String {
+        let utilization_ratio = Decimal256::from_str(utilization_ratio).unwrap();
+        let query_msg = QueryMsg::BorrowRate {
+            market_balance: Uint256::one(),
+            total_liabilities: utilization_ratio,
+            total_reserves: utilization_ratio,
+        };
+        let res = query(deps, mock_env(), query_msg).unwrap();
+        let value: BorrowRateResponse = from_binary(&res).unwrap();
+        value.rate.to_string()
+    };
+
+    // borrow_rate = 0.02 + 0.1 * utilization_ratio up to the kink
+    assert_eq!(borrow_rate(deps.as_ref(), "0"), "0.02");
+    assert_eq!(borrow_rate(deps.as_ref(), "0.4"), "0.06");
+    assert_eq!(borrow_rate(deps.as_ref(), "0.8"), "0.1");
+
+    // borrow_rate = 0.1 + 1 * (utilization_ratio - 0.8) above it
+    assert_eq!(borrow_rate(deps.as_ref(), "0.9"), "0.2");
+    assert_eq!(borrow_rate(deps.as_ref(), "1"), "0.3");
+
+    // the slopes meet at the kink, down to the last decimal place
+    assert_eq!(
+        borrow_rate(deps.as_ref(), "0.799999999999999999"),
+        "0.099999999999999999"
+    );
+    assert_eq!(
+        borrow_rate(deps.as_ref(), "0.800000000000000001"),
+        "0.100000000000000001"
+    );
+
+    // the kink has to be within (0, 1)
+    for optimal_utilization in [Decimal256::zero(), Decimal256::one()] {
+        let msg = ExecuteMsg::UpdateConfig {
+            owner: None,
+            risk_admin: None,
+            base_rate: None,
+            optimal_utilization: Some(optimal_utilization),
+            slope_1: None,
+            slope_2: None,
+            max_apr: None,
+            blocks_per_year: None,
+            max_borrow_rate: None,
+            market_contract: None,
+            utilization_mode: None,
+            ema_smoothing_factor: None,
+            rate_mode: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg);
+        match res {
+            Err(ContractError::InvalidOptimalUtilization {}) => (),
+            _ => panic!("DO NOT ENTER HERE"),
+        }
+    }
+
+    // a steeper slope_2 only changes the rates above the kink
+    let msg = ExecuteMsg::UpdateConfig {
+        owner: None,
+        risk_admin: None,
+        base_rate: None,
+        optimal_utilization: Some(Decimal256::percent(50)),
+        slope_1: None,
+        slope_2: Some(Decimal256::from_uint256(2u64)),
+        max_apr: None,
+        blocks_per_year: None,
+        max_borrow_rate: None,
+        market_contract: None,
+        utilization_mode: None,
+        ema_smoothing_factor: None,
+        rate_mode: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg).unwrap();
+    assert_eq!(borrow_rate(deps.as_ref(), "0.4"), "0.06");
+    assert_eq!(borrow_rate(deps.as_ref(), "0.5"), "0.07");
+    assert_eq!(borrow_rate(deps.as_ref(), "0.95"), "0.97");
+}
+
+#[test]
+fn validate_config_bounds() {
+    let mut deps = mock_dependencies(&[]);
+
+    // base_rate cannot exceed max_apr / blocks_per_year = 0.1
+    let mut msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        risk_admin: None,
+        base_rate: Decimal256::from_str("0.100000000000000001").unwrap(),
+        optimal_utilization: Decimal256::percent(80),
+        slope_1: Decimal256::percent(10),
+        slope_2: Decimal256::one(),
+        max_apr: Decimal256::one(),
+        blocks_per_year: 10,
+        max_borrow_rate: Decimal256::one(),
+        market_contract: None,
+        utilization_mode: None,
+        ema_smoothing_factor: Decimal256::one(),
+        rate_mode: None,
+    };
+    let res = instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        msg.clone(),
+    );
+    match res {
+        Err(ContractError::BaseRateExceedsMax(max_base_rate)) => assert_eq!(max_base_rate, "0.1"),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    msg.blocks_per_year = 0;
+    let res = instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        msg.clone(),
+    );
+    match res {
+        Err(ContractError::InvalidBlocksPerYear {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    msg.base_rate = Decimal256::percent(10);
+    msg.blocks_per_year = 10;
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+
+    let update_config =
+        |base_rate: Option<Decimal256>,
+         max_apr: Option<Decimal256>,
+         blocks_per_year: Option<u64>| ExecuteMsg::UpdateConfig {
+            owner: None,
+            risk_admin: None,
+            base_rate,
+            optimal_utilization: None,
+            slope_1: None,
+            slope_2: None,
+            max_apr,
+            blocks_per_year,
+            max_borrow_rate: None,
+            market_contract: None,
+            utilization_mode: None,
+            ema_smoothing_factor: None,
+            rate_mode: None,
+        };
+
+    // lowering the ceiling below the stored base rate is rejected
+    let msg = update_config(None, Some(Decimal256::percent(99)), None);
+    let res = execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg);
+    match res {
+        Err(ContractError::BaseRateExceedsMax(max_base_rate)) => assert_eq!(max_base_rate, "0.099"),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let msg = update_config(None, None, Some(11));
+    let res = execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg);
+    match res {
+        Err(ContractError::BaseRateExceedsMax(_)) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let msg = update_config(None, None, Some(0));
+    let res = execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg);
+    match res {
+        Err(ContractError::InvalidBlocksPerYear {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // the bounds are checked against the updated values together
+    let msg = update_config(
+        Some(Decimal256::percent(5)),
+        Some(Decimal256::percent(50)),
+        None,
+    );
+    let res = execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "update_config"),
+            attr("old_base_rate", "0.1"),
+            attr("new_base_rate", "0.05"),
+            attr("old_max_apr", "1"),
+            attr("new_max_apr", "0.5"),
+        ]
+    );
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    let value: ConfigResponse = from_binary(&res).unwrap();
+    assert_eq!("0.05", &value.base_rate.to_string());
+    assert_eq!("0.5", &value.max_apr.to_string());
+    assert_eq!(10, value.blocks_per_year);
+}
+
+#[test]
+fn simulate_borrow_rate() {
+    let instantiate_msg = |base_rate: Decimal256,
+                           optimal_utilization: Decimal256,
+                           slope_1: Decimal256,
+                           slope_2: Decimal256| InstantiateMsg {
+        owner: "owner0000".to_string(),
+        risk_admin: None,
+        base_rate,
+        optimal_utilization,
+        slope_1,
+        slope_2,
+        max_apr: Decimal256::one(),
+        blocks_per_year: 10,
+        max_borrow_rate: Decimal256::one(),
+        market_contract: None,
+        utilization_mode: None,
+        ema_smoothing_factor: Decimal256::one(),
+        rate_mode: None,
+    };
+
+    let mut deps = mock_dependencies(&[]);
+    let msg = instantiate_msg(
+        Decimal256::percent(2),
+        Decimal256::percent(80),
+        Decimal256::percent(10),
+        Decimal256::one(),
+    );
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+
+    // a model configured with the overridden params
+    let mut proposed_deps = mock_dependencies(&[]);
+    let msg = instantiate_msg(
+        Decimal256::percent(2),
+        Decimal256::percent(50),
+        Decimal256::percent(20),
+        Decimal256::from_uint256(3u64),
+    );
+    instantiate(
+        proposed_deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        msg,
+    )
+    .unwrap();
+
+    let params = ConfigOverride {
+        base_rate: None,
+        optimal_utilization: Some(Decimal256::percent(50)),
+        slope_1: Some(Decimal256::percent(20)),
+        slope_2: Some(Decimal256::from_uint256(3u64)),
+        max_borrow_rate: None,
+    };
+
+    for utilization_ratio in ["0", "0.3", "0.5", "0.8", "0.95", "1"] {
+        let utilization_ratio = Decimal256::from_str(utilization_ratio).unwrap();
+        let borrow_rate = |deps: Deps| -> Decimal256 {
+            let query_msg = QueryMsg::BorrowRate {
+                market_balance: Uint256::one(),
+                total_liabilities: utilization_ratio,
+                total_reserves: utilization_ratio,
+            };
+            let res = query(deps, mock_env(), query_msg).unwrap();
+            let value: BorrowRateResponse = from_binary(&res).unwrap();
+            value.rate
+        };
+        let simulate_borrow_rate = |params: Option<ConfigOverride>| {
+            let query_msg = QueryMsg::SimulateBorrowRate {
+                market_balance: Uint256::one(),
+                total_liabilities: utilization_ratio,
+                total_reserves: utilization_ratio,
+                params,
+            };
+            let res = query(deps.as_ref(), mock_env(), query_msg).unwrap();
+            let value: SimulateBorrowRateResponse = from_binary(&res).unwrap();
+            value
+        };
+
+        let value = simulate_borrow_rate(Some(params.clone()));
+        assert_eq!(value.current_rate, borrow_rate(deps.as_ref()));
+        assert_eq!(value.simulated_rate, borrow_rate(proposed_deps.as_ref()));
+
+        // without overrides both rates are the current one
+        let value = simulate_borrow_rate(None);
+        assert_eq!(value.current_rate, borrow_rate(deps.as_ref()));
+        assert_eq!(value.simulated_rate, value.current_rate);
+    }
+
+    // the simulation does not touch the stored config
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    let value: ConfigResponse = from_binary(&res).unwrap();
+    assert_eq!("0.8", &value.optimal_utilization.to_string());
+    assert_eq!("0.1", &value.slope_1.to_string());
+    assert_eq!("1", &value.slope_2.to_string());
+}
+
+#[test]
+fn clamp_borrow_rate() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        risk_admin: None,
+        base_rate: Decimal256::percent(2),
+        optimal_utilization: Decimal256::percent(80),
+        slope_1: Decimal256::percent(10),
+        slope_2: Decimal256::from_uint256(1000u64),
+        max_apr: Decimal256::one(),
+        blocks_per_year: 10,
+        max_borrow_rate: Decimal256::percent(50),
+        market_contract: None,
+        utilization_mode: None,
+        ema_smoothing_factor: Decimal256::one(),
+        rate_mode: None,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+
+    let borrow_rate = |deps: Deps, total_liabilities: u128| -> BorrowRateResponse {
+        let query_msg = QueryMsg::BorrowRate {
+            market_balance: Uint256::from(1000000u128 - total_liabilities),
+            total_liabilities: Decimal256::from_uint256(total_liabilities),
+            total_reserves: Decimal256::zero(),
+        };
+        let res = query(deps, mock_env(), query_msg).unwrap();
+        from_binary(&res).unwrap()
+    };
+
+    // below the ceiling the curve is returned as is
+    let value = borrow_rate(deps.as_ref(), 500000u128);
+    assert_eq!("0.07", &value.rate.to_string());
+    assert!(!value.clamped);
+
+    // at 100% utilization the steep slope_2 is capped
+    let value = borrow_rate(deps.as_ref(), 1000000u128);
+    assert_eq!("0.5", &value.rate.to_string());
+    assert!(value.clamped);
+
+    // the ceiling applies to simulated params as well
+    let query_msg = QueryMsg::SimulateBorrowRate {
+        market_balance: Uint256::zero(),
+        total_liabilities: Decimal256::from_uint256(1000000u128),
+        total_reserves: Decimal256::zero(),
+        params: Some(ConfigOverride {
+            slope_2: Some(Decimal256::from_uint256(1000000u64)),
+            max_borrow_rate: Some(Decimal256::one()),
+            ..ConfigOverride::default()
+        }),
+    };
+    let res = query(deps.as_ref(), mock_env(), query_msg).unwrap();
+    let value: SimulateBorrowRateResponse = from_binary(&res).unwrap();
+    assert_eq!("0.5", &value.current_rate.to_string());
+    assert_eq!("1", &value.simulated_rate.to_string());
+
+    // max_borrow_rate cannot be set above the ceiling
+    let msg = ExecuteMsg::UpdateConfig {
+        owner: None,
+        risk_admin: None,
+        base_rate: None,
+        optimal_utilization: None,
+        slope_1: None,
+        slope_2: None,
+        max_apr: None,
+        blocks_per_year: None,
+        max_borrow_rate: Some(Decimal256::from_str("1.000000000000000001").unwrap()),
+        market_contract: None,
+        utilization_mode: None,
+        ema_smoothing_factor: None,
+        rate_mode: None,
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg);
+    match res {
+        Err(ContractError::MaxBorrowRateExceedsCeiling(ceiling)) => assert_eq!(ceiling, "1"),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let msg = ExecuteMsg::UpdateConfig {
+        owner: None,
+        risk_admin: None,
+        base_rate: None,
+        optimal_utilization: None,
+        slope_1: None,
+        slope_2: None,
+        max_apr: None,
+        blocks_per_year: None,
+        max_borrow_rate: Some(Decimal256::one()),
+        market_contract: None,
+        utilization_mode: None,
+        ema_smoothing_factor: None,
+        rate_mode: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg).unwrap();
+    let value = borrow_rate(deps.as_ref(), 1000000u128);
+    assert_eq!("1", &value.rate.to_string());
+    assert!(value.clamped);
+}
+
+#[test]
+fn utilization_ema() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        risk_admin: None,
+        base_rate: Decimal256::percent(2),
+        optimal_utilization: Decimal256::percent(80),
+        slope_1: Decimal256::percent(10),
+        slope_2: Decimal256::one(),
+        max_apr: Decimal256::one(),
+        blocks_per_year: 10,
+        max_borrow_rate: Decimal256::one(),
+        market_contract: Some("market0000".to_string()),
+        utilization_mode: Some(UtilizationMode::Ema),
+        ema_smoothing_factor: Decimal256::percent(20),
+        rate_mode: None,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+
+    let borrow_rate = |deps: Deps, utilization_ratio: &str| -> String {
+        let utilization_ratio = Decimal256::from_str(utilization_ratio).unwrap();
+        let query_msg = QueryMsg::BorrowRate {
+            market_balance: Uint256::one(),
+            total_liabilities: utilization_ratio,
+            total_reserves: utilization_ratio,
+        };
+        let res = query(deps, mock_env(), query_msg).unwrap();
+        let value: BorrowRateResponse = from_binary(&res).unwrap();
+        value.rate.to_string()
+    };
+    let record_utilization = |utilization: &str| ExecuteMsg::RecordUtilization {
+        utilization: Decimal256::from_str(utilization).unwrap(),
+    };
+
+    // the spot utilization is used until the market records any
+    assert_eq!(borrow_rate(deps.as_ref(), "0.5"), "0.07");
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        record_utilization("0.5"),
+    );
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("Must return unauthorized error"),
+    }
+
+    // the first record seeds the EMA
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("market0000", &[]),
+        record_utilization("0.5"),
+    )
+    .unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "record_utilization"),
+            attr("utilization", "0.5"),
+            attr("ema_utilization", "0.5"),
+        ]
+    );
+
+    // ema = 0.2 * 1 + 0.8 * 0.5 = 0.6
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("market0000", &[]),
+        record_utilization("1"),
+    )
+    .unwrap();
+
+    // the spike moves the rate along the smoothed utilization
+    // borrow_rate = 0.02 + 0.1 * 0.6 instead of 0.02 + 0.08 + 0.2
+    assert_eq!(borrow_rate(deps.as_ref(), "1"), "0.08");
+
+    // ema = 0.2 * 0.5 + 0.8 * 0.6 = 0.58
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("market0000", &[]),
+        record_utilization("0.5"),
+    )
+    .unwrap();
+    assert_eq!(borrow_rate(deps.as_ref(), "0.5"), "0.078");
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    let value: ConfigResponse = from_binary(&res).unwrap();
+    assert_eq!(value.market_contract, Some("market0000".to_string()));
+    assert_eq!(value.utilization_mode, UtilizationMode::Ema);
+    assert_eq!(
+        value.ema_utilization,
+        Some(Decimal256::from_str("0.58").unwrap())
+    );
+
+    let update_config = |utilization_mode: Option<UtilizationMode>,
+                         ema_smoothing_factor: Option<Decimal256>| {
+        ExecuteMsg::UpdateConfig {
+            owner: None,
+            risk_admin: None,
+            base_rate: None,
+            optimal_utilization: None,
+            slope_1: None,
+            slope_2: None,
+            max_apr: None,
+            blocks_per_year: None,
+            max_borrow_rate: None,
+            market_contract: None,
+            utilization_mode,
+            ema_smoothing_factor,
+            rate_mode: None,
+        }
+    };
+
+    // the larger of spot and EMA follows spikes up but not down
+    let msg = update_config(Some(UtilizationMode::MaxSpotEma), None);
+    let res = execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "update_config"),
+            attr("old_utilization_mode", "ema"),
+            attr("new_utilization_mode", "max_spot_ema"),
+        ]
+    );
+    assert_eq!(borrow_rate(deps.as_ref(), "0.5"), "0.078");
+    assert_eq!(borrow_rate(deps.as_ref(), "1"), "0.3");
+
+    for ema_smoothing_factor in [
+        Decimal256::zero(),
+        Decimal256::from_str("1.000000000000000001").unwrap(),
+    ] {
+        let msg = update_config(None, Some(ema_smoothing_factor));
+        let res = execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg);
+        match res {
+            Err(ContractError::InvalidSmoothingFactor {}) => (),
+            _ => panic!("DO NOT ENTER HERE"),
+        }
+    }
+}
+
+#[test]
+fn model_info() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        risk_admin: None,
+        base_rate: Decimal256::percent(2),
+        optimal_utilization: Decimal256::percent(80),
+        slope_1: Decimal256::percent(10),
+        slope_2: Decimal256::one(),
+        max_apr: Decimal256::one(),
+        blocks_per_year: 10,
+        max_borrow_rate: Decimal256::one(),
+        market_contract: None,
+        utilization_mode: None,
+        ema_smoothing_factor: Decimal256::one(),
+        rate_mode: None,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::ModelInfo {}).unwrap();
+    let value: ModelInfoResponse = from_binary(&res).unwrap();
+    assert_eq!(value.model_type, MODEL_TYPE_KINKED_V1);
+    assert_ne!(value.model_type, "linear_v1");
+    assert_eq!(value.version, env!("CARGO_PKG_VERSION"));
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    let config: ConfigResponse = from_binary(&res).unwrap();
+    assert_eq!(value.params, config);
+}
+
+#[test]
+fn degenerate_market_borrow_rate() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        risk_admin: None,
+        base_rate: Decimal256::percent(2),
+        optimal_utilization: Decimal256::percent(80),
+        slope_1: Decimal256::percent(10),
+        slope_2: Decimal256::one(),
+        max_apr: Decimal256::one(),
+        blocks_per_year: 10,
+        max_borrow_rate: Decimal256::one(),
+        market_contract: None,
+        utilization_mode: None,
+        ema_smoothing_factor: Decimal256::one(),
+        rate_mode: None,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+
+    let borrow_rate = |market_balance: u128, total_liabilities: u128, total_reserves: u128| {
+        let query_msg = QueryMsg::BorrowRate {
+            market_balance: Uint256::from(market_balance),
+            total_liabilities: Decimal256::from_uint256(total_liabilities),
+            total_reserves: Decimal256::from_uint256(total_reserves),
+        };
+        let res = query(deps.as_ref(), mock_env(), query_msg).unwrap();
+        let value: BorrowRateResponse = from_binary(&res).unwrap();
+        value.rate.to_string()
+    };
+
+    // (market_balance, total_liabilities, total_reserves, borrow_rate)
+    let cases = [
+        // freshly deployed market
+        (0u128, 0u128, 0u128, "0.02"),
+        // no liabilities
+        (100, 0, 0, "0.02"),
+        (100, 0, 50, "0.02"),
+        (0, 0, 100, "0.02"),
+        // balance plus liabilities equal to the reserves
+        (50, 50, 100, "0.02"),
+        (0, 100, 100, "0.02"),
+        // reserves exceeding balance plus liabilities
+        (50, 50, 200, "0.02"),
+        (0, 100, 200, "0.02"),
+        // fully borrowed market; 0.02 + 0.08 + 0.2
+        (0, 100, 0, "0.3"),
+        // reserves exceeding the balance only; utilization = 100 / 50
+        // and 0.02 + 0.08 + 1.2 is clamped to max_borrow_rate
+        (50, 100, 100, "1"),
+    ];
+    for (market_balance, total_liabilities, total_reserves, rate) in cases {
+        assert_eq!(
+            borrow_rate(market_balance, total_liabilities, total_reserves),
+            rate,
+            "market_balance: {}, total_liabilities: {}, total_reserves: {}",
+            market_balance,
+            total_liabilities,
+            total_reserves
+        );
+    }
+}
+
+#[test]
+fn fixed_rate_mode() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        risk_admin: None,
+        base_rate: Decimal256::percent(2),
+        optimal_utilization: Decimal256::percent(80),
+        slope_1: Decimal256::percent(10),
+        slope_2: Decimal256::one(),
+        max_apr: Decimal256::one(),
+        blocks_per_year: 10,
+        max_borrow_rate: Decimal256::one(),
+        market_contract: None,
+        utilization_mode: None,
+        ema_smoothing_factor: Decimal256::one(),
+        rate_mode: None,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+
+    let borrow_rate = |deps: Deps, utilization_ratio: &str| -> BorrowRateResponse {
+        let utilization_ratio = Decimal256::from_str(utilization_ratio).unwrap();
+        let query_msg = QueryMsg::BorrowRate {
+            market_balance: Uint256::one(),
+            total_liabilities: utilization_ratio,
+            total_reserves: utilization_ratio,
+        };
+        let res = query(deps, mock_env(), query_msg).unwrap();
+        from_binary(&res).unwrap()
+    };
+    let model_info = |deps: Deps| -> ModelInfoResponse {
+        let res = query(deps, mock_env(), QueryMsg::ModelInfo {}).unwrap();
+        from_binary(&res).unwrap()
+    };
+    let update_config = |max_borrow_rate: Option<Decimal256>, rate_mode: Option<RateMode>| {
+        ExecuteMsg::UpdateConfig {
+            owner: None,
+            risk_admin: None,
+            base_rate: None,
+            optimal_utilization: None,
+            slope_1: None,
+            slope_2: None,
+            max_apr: None,
+            blocks_per_year: None,
+            max_borrow_rate,
+            market_contract: None,
+            utilization_mode: None,
+            ema_smoothing_factor: None,
+            rate_mode,
+        }
+    };
+
+    // defaults to the dynamic rate
+    assert_eq!(borrow_rate(deps.as_ref(), "0.5").rate.to_string(), "0.07");
+    let value = model_info(deps.as_ref());
+    assert_eq!(value.model_type, MODEL_TYPE_KINKED_V1);
+    assert_eq!(value.params.rate_mode, RateMode::Dynamic);
+
+    // the fixed rate ignores the utilization
+    let fixed_rate = RateMode::Fixed {
+        rate: Decimal256::percent(5),
+    };
+    let msg = update_config(None, Some(fixed_rate.clone()));
+    let res = execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "update_config"),
+            attr("old_rate_mode", "dynamic"),
+            attr("new_rate_mode", "fixed(0.05)"),
+        ]
+    );
+    for utilization_ratio in ["0", "0.5", "0.9", "1"] {
+        let value = borrow_rate(deps.as_ref(), utilization_ratio);
+        assert_eq!(value.rate.to_string(), "0.05");
+        assert!(!value.clamped);
+    }
+    let value = model_info(deps.as_ref());
+    assert_eq!(value.model_type, MODEL_TYPE_FIXED_V1);
+    assert_eq!(value.params.rate_mode, fixed_rate);
+
+    // the fixed rate is bounded by max_borrow_rate
+    let msg = update_config(
+        None,
+        Some(RateMode::Fixed {
+            rate: Decimal256::percent(150),
+        }),
+    );
+    let res = execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg);
+    match res {
+        Err(ContractError::FixedRateExceedsMax(max_borrow_rate)) => {
+            assert_eq!(max_borrow_rate, "1")
+        }
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let msg = update_config(Some(Decimal256::percent(4)), None);
+    let res = execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg);
+    match res {
+        Err(ContractError::FixedRateExceedsMax(max_borrow_rate)) => {
+            assert_eq!(max_borrow_rate, "0.04")
+        }
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // switching back follows the curve again
+    let msg = update_config(None, Some(RateMode::Dynamic));
+    execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg).unwrap();
+    assert_eq!(borrow_rate(deps.as_ref(), "0.5").rate.to_string(), "0.07");
+    assert_eq!(borrow_rate(deps.as_ref(), "1").rate.to_string(), "0.3");
+    assert_eq!(model_info(deps.as_ref()).model_type, MODEL_TYPE_KINKED_V1);
+}
+
+#[test]
+fn risk_admin_permissions() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        risk_admin: Some("risk0000".to_string()),
+        base_rate: Decimal256::percent(2),
+        optimal_utilization: Decimal256::percent(80),
+        slope_1: Decimal256::percent(10),
+        slope_2: Decimal256::one(),
+        max_apr: Decimal256::one(),
+        blocks_per_year: 10,
+        max_borrow_rate: Decimal256::one(),
+        market_contract: Some("market0000".to_string()),
+        utilization_mode: None,
+        ema_smoothing_factor: Decimal256::one(),
+        rate_mode: None,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    let value: ConfigResponse = from_binary(&res).unwrap();
+    assert_eq!("owner0000", value.owner.as_str());
+    assert_eq!(Some("risk0000".to_string()), value.risk_admin);
+
+    let update_config = |owner: Option<&str>,
+                         risk_admin: Option<&str>,
+                         market_contract: Option<&str>,
+                         base_rate: Option<Decimal256>| {
+        ExecuteMsg::UpdateConfig {
+            owner: owner.map(|owner| owner.to_string()),
+            risk_admin: risk_admin.map(|risk_admin| risk_admin.to_string()),
+            base_rate,
+            optimal_utilization: None,
+            slope_1: None,
+            slope_2: None,
+            max_apr: None,
+            blocks_per_year: None,
+            max_borrow_rate: None,
+            market_contract: market_contract.map(|market_contract| market_contract.to_string()),
+            utilization_mode: None,
+            ema_smoothing_factor: None,
+            rate_mode: None,
+        }
+    };
+
+    // the risk admin tunes the rate parameters
+    let msg = update_config(None, None, None, Some(Decimal256::percent(3)));
+    execute(deps.as_mut(), mock_env(), mock_info("risk0000", &[]), msg).unwrap();
+
+    // but cannot touch the roles or the market
+    for msg in [
+        update_config(Some("risk0000"), None, None, None),
+        update_config(None, Some("risk0001"), None, None),
+        update_config(None, None, Some("market0001"), None),
+        update_config(Some("risk0000"), None, None, Some(Decimal256::percent(4))),
+    ] {
+        let res = execute(deps.as_mut(), mock_env(), mock_info("risk0000", &[]), msg);
+        match res {
+            Err(ContractError::Unauthorized {}) => (),
+            _ => panic!("DO NOT ENTER HERE"),
+        }
+    }
+
+    // other senders cannot update anything
+    let msg = update_config(None, None, None, Some(Decimal256::percent(4)));
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg);
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // the owner can do everything, including replacing the risk admin
+    let msg = update_config(
+        None,
+        Some("risk0001"),
+        Some("market0001"),
+        Some(Decimal256::percent(4)),
+    );
+    execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg).unwrap();
+
+    let msg = update_config(None, None, None, Some(Decimal256::percent(5)));
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("risk0000", &[]),
+        msg.clone(),
+    );
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+    execute(deps.as_mut(), mock_env(), mock_info("risk0001", &[]), msg).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    let value: ConfigResponse = from_binary(&res).unwrap();
+    assert_eq!(Some("risk0001".to_string()), value.risk_admin);
+    assert_eq!(Some("market0001".to_string()), value.market_contract);
+    assert_eq!("0.05", &value.base_rate.to_string());
+}
+
+#[test]
+fn projected_rates() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        risk_admin: None,
+        base_rate: Decimal256::percent(2),
+        optimal_utilization: Decimal256::percent(80),
+        slope_1: Decimal256::percent(10),
+        slope_2: Decimal256::one(),
+        max_apr: Decimal256::one(),
+        blocks_per_year: 10,
+        max_borrow_rate: Decimal256::one(),
+        market_contract: None,
+        utilization_mode: None,
+        ema_smoothing_factor: Decimal256::one(),
+        rate_mode: None,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+
+    let projected_rates = |deps: Deps,
+                           total_liabilities: u64,
+                           reserve_factor: Decimal256,
+                           blocks_per_year: Option<u64>| {
+        query(
+            deps,
+            mock_env(),
+            QueryMsg::ProjectedRates {
+                market_balance: Uint256::from(50u64),
+                total_liabilities: Decimal256::from_uint256(total_liabilities),
+                total_reserves: Decimal256::zero(),
+                reserve_factor,
+                blocks_per_year,
+            },
+        )
+        .map(|res| from_binary::<ProjectedRatesResponse>(&res).unwrap())
+    };
+
+    // utilization = 50 / (50 + 50) = 0.5
+    // borrow_rate = 0.02 + 0.5 * 0.1 = 0.07
+    // deposit_rate = 0.07 * 0.5 * (1 - 0.1) = 0.0315
+    // annualized over the configured 10 blocks
+    let value = projected_rates(deps.as_ref(), 50, Decimal256::percent(10), None).unwrap();
+    assert_eq!(
+        value,
+        ProjectedRatesResponse {
+            borrow_rate: Decimal256::from_str("0.07").unwrap(),
+            deposit_rate: Decimal256::from_str("0.0315").unwrap(),
+            borrow_apr: Decimal256::from_str("0.7").unwrap(),
+            deposit_apr: Decimal256::from_str("0.315").unwrap(),
+            // 1.07^10 - 1 and 1.0315^10 - 1, rounded down
+            borrow_apy: Decimal256::from_str("0.967151357289565322").unwrap(),
+            deposit_apy: Decimal256::from_str("0.363616736942864539").unwrap(),
+        }
+    );
+
+    // without the reserve haircut, over 100 blocks
+    let value = projected_rates(deps.as_ref(), 50, Decimal256::zero(), Some(100)).unwrap();
+    assert_eq!(
+        value,
+        ProjectedRatesResponse {
+            borrow_rate: Decimal256::from_str("0.07").unwrap(),
+            deposit_rate: Decimal256::from_str("0.035").unwrap(),
+            borrow_apr: Decimal256::from_str("7").unwrap(),
+            deposit_apr: Decimal256::from_str("3.5").unwrap(),
+            borrow_apy: Decimal256::from_str("866.716325566411945395").unwrap(),
+            deposit_apy: Decimal256::from_str("30.19140798310965307").unwrap(),
+        }
+    );
+
+    // depositors earn nothing without borrowers
+    let value = projected_rates(deps.as_ref(), 0, Decimal256::percent(10), None).unwrap();
+    assert_eq!(value.borrow_apr.to_string(), "0.2");
+    assert_eq!(value.deposit_rate, Decimal256::zero());
+
+    // the whole interest goes to reserves
+    let value = projected_rates(deps.as_ref(), 50, Decimal256::one(), None).unwrap();
+    assert_eq!(value.deposit_apr, Decimal256::zero());
+    assert_eq!(value.deposit_apy, Decimal256::zero());
+
+    let res = projected_rates(deps.as_ref(), 50, Decimal256::percent(101), None);
+    match res {
+        Err(StdError::GenericErr { msg, .. }) => {
+            assert_eq!(msg, "reserve_factor cannot exceed 1")
+        }
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+}
+
+#[test]
+fn per_block_compounding_precision() {
+    // 100,000 blocks at a ~14% APR per-block rate
+    let blocks = 100_000u64;
+    let rate = Decimal256::from_str("0.00000003").unwrap();
+
+    let mut index = Decimal256::one();
+    for _ in 0..blocks {
+        index = index * (Decimal256::one() + rate);
+    }
+
+    // (1 + rate) ^ blocks, computed with 60 digits
+    let exact = Decimal256::from_str("1.003004504458241824").unwrap();
+
+    // each step truncates below 1e-18, so the error is bounded by
+    // blocks * 1e-18 * index, i.e. below 1e-11 over a year of blocks
+    let bound = Decimal256::from_str("0.000000000000102").unwrap();
+    assert!(index <= exact);
+    assert!(exact - index <= bound);
+}
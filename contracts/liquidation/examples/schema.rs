@@ -4,8 +4,10 @@ use std::fs::create_dir_all;
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 
 use moneymarket::liquidation::{
-    BidResponse, BidsResponse, ConfigResponse, Cw20HookMsg, ExecuteMsg, InstantiateMsg,
-    LiquidationAmountResponse, QueryMsg,
+    BidPoolResponse, BidPoolsResponse, BidResponse, BidsResponse, CollateralInfoResponse,
+    ConfigResponse, Cw20HookMsg, EscrowHealthResponse, ExecuteMsg, InstantiateMsg,
+    LiquidationAmountResponse, LiquidityDepthResponse, MigrateMsg, QueryMsg,
+    SimulateExecuteResponse,
 };
 
 fn main() {
@@ -18,8 +20,15 @@ fn main() {
     export_schema(&schema_for!(ExecuteMsg), &out_dir);
     export_schema(&schema_for!(Cw20HookMsg), &out_dir);
     export_schema(&schema_for!(QueryMsg), &out_dir);
+    export_schema(&schema_for!(MigrateMsg), &out_dir);
     export_schema(&schema_for!(ConfigResponse), &out_dir);
     export_schema(&schema_for!(BidResponse), &out_dir);
     export_schema(&schema_for!(BidsResponse), &out_dir);
+    export_schema(&schema_for!(BidPoolResponse), &out_dir);
+    export_schema(&schema_for!(BidPoolsResponse), &out_dir);
+    export_schema(&schema_for!(CollateralInfoResponse), &out_dir);
     export_schema(&schema_for!(LiquidationAmountResponse), &out_dir);
+    export_schema(&schema_for!(SimulateExecuteResponse), &out_dir);
+    export_schema(&schema_for!(LiquidityDepthResponse), &out_dir);
+    export_schema(&schema_for!(EscrowHealthResponse), &out_dir);
 }
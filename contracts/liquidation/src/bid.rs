@@ -1,38 +1,63 @@
+use crate::bid_pool::{accrue_bid, exit_bid_pool, fill_bid_pool, join_bid_pool, leave_bid_pool};
 use crate::error::ContractError;
 use crate::state::{
-    read_bid, read_bids_by_collateral, read_bids_by_user, read_config, remove_bid, store_bid, Bid,
-    Config,
+    bid_response, pop_bid_idx, read_all_bid_pools, read_bid, read_bid_pool, read_bid_pools,
+    read_bids_by_collateral, read_bids_by_user, read_collateral_bids, read_collateral_info,
+    read_config, read_escrow, read_liquidation_accumulator, read_sweep_cursor, read_user_bids,
+    remove_bid, remove_liquidation_accumulator, store_bid, store_escrow,
+    store_liquidation_accumulator, store_sweep_cursor, AccumulatedCollateral, Bid, BidPool,
+    CollateralInfo, Config, Escrow, LiquidationAccumulator,
 };
 
 use cosmwasm_bignumber::{Decimal256, Uint256};
 use cosmwasm_std::{
-    attr, to_binary, Addr, BankMsg, Coin, CosmosMsg, Deps, DepsMut, Env, MessageInfo, Response,
-    StdResult, WasmMsg,
+    attr, to_binary, Addr, Attribute, BankMsg, Coin, CosmosMsg, Deps, DepsMut, Env, MessageInfo,
+    Response, StdError, StdResult, Storage, WasmMsg,
 };
 use cw20::Cw20ExecuteMsg;
-use moneymarket::liquidation::{BidResponse, BidsResponse};
+use moneymarket::liquidation::{
+    BidPoolResponse, BidPoolsResponse, BidResponse, BidsResponse, CollateralInfoResponse,
+    LiquidityDepthResponse, SimulateExecuteResponse, SlotDepthResponse, FILL_COLLATERAL_AMOUNT,
+    FILL_LIQUIDATION_FEE, FILL_PREMIUM_AMOUNT, FILL_PREMIUM_RATE, FILL_PREMIUM_SLOT,
+    FILL_REPAY_AMOUNT,
+};
+use moneymarket::market::ExecuteMsg as MarketExecuteMsg;
+use moneymarket::math::value_of;
 use moneymarket::oracle::PriceResponse;
+use moneymarket::pagination::{clamp_limit, DEFAULT_LIMIT, MAX_LIMIT};
 use moneymarket::querier::{deduct_tax, query_price, TimeConstraints};
 
 pub fn submit_bid(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     collateral_token: Addr,
-    premium_rate: Decimal256,
+    premium_slot: u8,
 ) -> Result<Response, ContractError> {
     let collateral_token_raw = deps.api.addr_canonicalize(collateral_token.as_str())?;
     let bidder_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
-    if read_bid(deps.storage, &bidder_raw, &collateral_token_raw).is_ok() {
-        return Err(ContractError::AlreadyBidForCollateral(collateral_token));
+    let collateral_info = match read_collateral_info(deps.storage, &collateral_token_raw)? {
+        Some(collateral_info) => collateral_info,
+        None => return Err(ContractError::CollateralNotWhitelisted(collateral_token)),
+    };
+
+    if !collateral_info.bids_enabled {
+        return Err(ContractError::CollateralBidsDisabled(collateral_token));
     }
 
-    let config: Config = read_config(deps.storage)?;
-    if config.max_premium_rate < premium_rate {
-        return Err(ContractError::PremiumExceedsMaxPremium(
-            config.max_premium_rate.to_string(),
+    let mut escrow: Escrow = read_escrow(deps.storage)?;
+    if escrow.bids_paused {
+        return Err(ContractError::BidsPaused {});
+    }
+
+    if premium_slot > collateral_info.max_slot {
+        return Err(ContractError::PremiumSlotExceedsMaxSlot(
+            collateral_info.max_slot,
         ));
     }
 
+    let config: Config = read_config(deps.storage)?;
+
     let amount: Uint256 = Uint256::from(
         info.funds
             .iter()
@@ -41,53 +66,185 @@ pub fn submit_bid(
             .ok_or(ContractError::AssetNotProvided(config.stable_denom))?,
     );
 
-    store_bid(
-        deps.storage,
-        &bidder_raw,
-        &collateral_token_raw,
-        Bid {
-            amount,
-            premium_rate,
-        },
-    )?;
+    if amount < config.min_bid_amount {
+        return Err(ContractError::BidAmountTooSmall(
+            config.min_bid_amount.into(),
+        ));
+    }
+
+    // New bids wait bid_activation_delay before joining their pool,
+    // so they cannot jump ahead of a liquidation already in flight
+    let activation_time = if config.bid_activation_delay > 0 {
+        Some(env.block.time.seconds() + config.bid_activation_delay)
+    } else {
+        None
+    };
+
+    // The stable funds stay escrowed in this contract until
+    // the bid is executed or retracted
+    let bid_idx = pop_bid_idx(deps.storage)?;
+    let bid = Bid {
+        idx: bid_idx,
+        collateral_token: collateral_token_raw,
+        bidder: bidder_raw,
+        amount,
+        premium_slot,
+        product_snapshot: Decimal256::one(),
+        sum_snapshot: Decimal256::zero(),
+        epoch_snapshot: 0,
+        scale_snapshot: 0,
+        pending_liquidated_collateral: Uint256::zero(),
+        wait_end: None,
+        activation_time,
+        last_updated: env.block.time.seconds(),
+    };
+    join_bid_pool(deps.storage, bid)?;
+
+    escrow.total_escrowed += amount;
+    store_escrow(deps.storage, &escrow)?;
 
     Ok(Response::new().add_attributes(vec![
         attr("action", "submit_bid"),
+        attr("bid_idx", bid_idx.to_string()),
         attr("collateral_token", collateral_token),
+        attr("premium_slot", premium_slot.to_string()),
         attr("amount", amount),
     ]))
 }
 
-pub fn retract_bid(
+/// Moves pending bids into their pools once the activation delay is over
+pub fn activate_bids(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     collateral_token: Addr,
-    amount: Option<Uint256>,
+    bid_idxs: Option<Vec<u64>>,
 ) -> Result<Response, ContractError> {
-    let config: Config = read_config(deps.storage)?;
     let collateral_token_raw = deps.api.addr_canonicalize(collateral_token.as_str())?;
     let bidder_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
-    let bid: Bid = read_bid(deps.storage, &bidder_raw, &collateral_token_raw)?;
+    let block_time = env.block.time.seconds();
 
-    let amount = amount.unwrap_or(bid.amount);
-    if amount > bid.amount {
-        return Err(ContractError::RetractExceedsBid(bid.amount.into()));
+    let bids: Vec<Bid> = match bid_idxs {
+        Some(bid_idxs) => {
+            let mut bids: Vec<Bid> = vec![];
+            for bid_idx in bid_idxs {
+                let bid: Bid = read_bid(deps.storage, bid_idx)?;
+                if bid.bidder != bidder_raw {
+                    return Err(ContractError::Unauthorized {});
+                }
+
+                if bid.collateral_token != collateral_token_raw {
+                    return Err(ContractError::BidCollateralMismatch(bid_idx));
+                }
+
+                match bid.activation_time {
+                    None => return Err(ContractError::BidNotPending(bid_idx)),
+                    Some(activation_time) if block_time < activation_time => {
+                        return Err(ContractError::BidActivationNotReady(activation_time))
+                    }
+                    _ => bids.push(bid),
+                }
+            }
+
+            bids
+        }
+        None => read_user_bids(deps.storage, &bidder_raw, &collateral_token_raw)?
+            .into_iter()
+            .filter(|bid| matches!(bid.activation_time, Some(t) if t <= block_time))
+            .collect(),
+    };
+
+    if bids.is_empty() {
+        return Err(ContractError::NoBidExists {});
     }
 
-    if amount == bid.amount {
-        remove_bid(deps.storage, &bidder_raw, &collateral_token_raw);
-    } else {
-        store_bid(
+    let mut activated_amount = Uint256::zero();
+    for bid in bids.iter() {
+        exit_bid_pool(deps.storage, bid)?;
+        join_bid_pool(
             deps.storage,
-            &bidder_raw,
-            &collateral_token_raw,
             Bid {
-                amount: bid.amount - amount,
-                ..bid
+                activation_time: None,
+                last_updated: block_time,
+                ..bid.clone()
             },
         )?;
+        activated_amount += bid.amount;
+    }
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "activate_bids"),
+        attr("collateral_token", collateral_token),
+        attr(
+            "bid_idxs",
+            bids.iter()
+                .map(|bid| bid.idx.to_string())
+                .collect::<Vec<String>>()
+                .join(","),
+        ),
+        attr("amount", activated_amount),
+    ]))
+}
+
+/// Retraction is done in two steps: the first call starts the waiting period,
+/// during which the bid can still be executed, and a call after the waiting
+/// period releases the funds. Pending bids cannot be executed, so they are
+/// retracted right away.
+pub fn retract_bid(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    bid_idx: u64,
+    amount: Option<Uint256>,
+) -> Result<Response, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+    let bid: Bid = accrue_bid(deps.storage, read_bid(deps.storage, bid_idx)?)?;
+    if bid.bidder != deps.api.addr_canonicalize(info.sender.as_str())? {
+        return Err(ContractError::Unauthorized {});
     }
 
+    let amount = amount.unwrap_or(bid.amount);
+    if amount > bid.amount {
+        return Err(ContractError::RetractExceedsBid(bid.amount.into()));
+    }
+
+    let block_time = env.block.time.seconds();
+    match bid.wait_end {
+        None if config.bid_retract_wait > 0 && bid.activation_time.is_none() => {
+            let wait_end = block_time + config.bid_retract_wait;
+            store_bid(
+                deps.storage,
+                &Bid {
+                    wait_end: Some(wait_end),
+                    last_updated: block_time,
+                    ..bid
+                },
+            )?;
+
+            return Ok(Response::new().add_attributes(vec![
+                attr("action", "retract_bid"),
+                attr("bid_idx", bid_idx.to_string()),
+                attr("wait_end", wait_end.to_string()),
+            ]));
+        }
+        Some(wait_end) if block_time < wait_end => {
+            return Err(ContractError::RetractWaitNotOver(wait_end));
+        }
+        _ => {}
+    }
+
+    // a partially retracted bid needs a new waiting period for the rest
+    withdraw_from_bid(
+        deps.storage,
+        Bid {
+            wait_end: None,
+            last_updated: block_time,
+            ..bid.clone()
+        },
+        amount,
+    )?;
+    release_escrow(deps.storage, amount)?;
+
     Ok(Response::new()
         .add_message(CosmosMsg::Bank(BankMsg::Send {
             to_address: info.sender.to_string(),
@@ -101,29 +258,122 @@ pub fn retract_bid(
         }))
         .add_attributes(vec![
             attr("action", "retract_bid"),
-            attr("collateral_token", collateral_token),
+            attr("bid_idx", bid_idx.to_string()),
+            attr(
+                "collateral_token",
+                deps.api.addr_humanize(&bid.collateral_token)?,
+            ),
             attr("bidder", info.sender),
             attr("amount", amount),
         ]))
 }
 
-pub fn execute_bid(
+/// Moves the remaining amount of a bid to another premium slot of its
+/// collateral. The bid is pending in the new slot like a new bid, so a
+/// bidder cannot undercut the others right before a liquidation.
+pub fn update_bid_premium(
     deps: DepsMut,
     env: Env,
-    liquidator: Addr,
-    repay_address: Addr,
-    fee_address: Addr,
-    collateral_token: Addr,
-    amount: Uint256,
+    info: MessageInfo,
+    bid_idx: u64,
+    premium_slot: u8,
 ) -> Result<Response, ContractError> {
     let config: Config = read_config(deps.storage)?;
+    let bid: Bid = accrue_bid(deps.storage, read_bid(deps.storage, bid_idx)?)?;
+    if bid.bidder != deps.api.addr_canonicalize(info.sender.as_str())? {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let collateral_token = deps.api.addr_humanize(&bid.collateral_token)?;
+    let collateral_info = match read_collateral_info(deps.storage, &bid.collateral_token)? {
+        Some(collateral_info) => collateral_info,
+        None => return Err(ContractError::CollateralNotWhitelisted(collateral_token)),
+    };
+
+    if premium_slot > collateral_info.max_slot {
+        return Err(ContractError::PremiumSlotExceedsMaxSlot(
+            collateral_info.max_slot,
+        ));
+    }
+
+    if bid.amount < config.min_bid_amount {
+        return Err(ContractError::BidAmountTooSmall(
+            config.min_bid_amount.into(),
+        ));
+    }
+
+    exit_bid_pool(deps.storage, &bid)?;
+
+    let block_time = env.block.time.seconds();
+    let activation_time = if config.bid_activation_delay > 0 {
+        Some(block_time + config.bid_activation_delay)
+    } else {
+        None
+    };
+
+    let amount = bid.amount;
+    let bid = Bid {
+        premium_slot,
+        activation_time,
+        last_updated: block_time,
+        ..bid
+    };
+    join_bid_pool(deps.storage, bid)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "update_bid_premium"),
+        attr("bid_idx", bid_idx.to_string()),
+        attr("collateral_token", collateral_token),
+        attr("premium_slot", premium_slot.to_string()),
+        attr("amount", amount),
+    ]))
+}
+
+/// Stable paid for a collateral sold to the bid pools
+struct CollateralSale {
+    /// Paid by the bidders net of the liquidation fee, before the bid fee
+    repay_amount: Uint256,
+    liquidation_fee: Uint256,
+    /// Collateral the bids could not absorb
+    returned_amount: Uint256,
+    filled_bid_pools: u32,
+    fill_attributes: Vec<Attribute>,
+}
+
+/// Part of a collateral sale taken by one bid pool
+struct PoolFill {
+    bid_pool: BidPool,
+    premium_rate: Decimal256,
+    collateral_amount: Uint256,
+    /// Oracle value of the collateral
+    value: Uint256,
+    /// Paid by the bidders, including the liquidation fee
+    paid: Uint256,
+}
+
+/// Splits the collateral between the bid pools, lowest premium slot first,
+/// without changing them. Execution and simulation share it, so that they
+/// always agree.
+fn plan_fills(
+    deps: Deps,
+    env: &Env,
+    config: &Config,
+    collateral_token: &Addr,
+    amount: Uint256,
+) -> Result<Vec<PoolFill>, ContractError> {
     let collateral_token_raw = deps.api.addr_canonicalize(collateral_token.as_str())?;
-    let bidder_raw = deps.api.addr_canonicalize(liquidator.as_str())?;
-    let bid: Bid = read_bid(deps.storage, &bidder_raw, &collateral_token_raw)?;
+    let collateral_info = match read_collateral_info(deps.storage, &collateral_token_raw)? {
+        Some(collateral_info) => collateral_info,
+        None => {
+            return Err(ContractError::CollateralNotWhitelisted(
+                collateral_token.clone(),
+            ))
+        }
+    };
 
     let oracle_contract = deps.api.addr_humanize(&config.oracle_contract)?;
     let price: PriceResponse = query_price(
-        deps.as_ref(),
+        deps,
         oracle_contract,
         collateral_token.to_string(),
         config.stable_denom.clone(),
@@ -133,107 +383,724 @@ pub fn execute_bid(
         }),
     )?;
 
-    let collateral_value = amount * price.rate;
-    let required_stable = collateral_value
-        * (Decimal256::one() - std::cmp::min(bid.premium_rate, config.max_premium_rate));
-    if required_stable > bid.amount {
-        return Err(ContractError::InsufficientBidBalance(
-            required_stable.into(),
-        ));
+    let mut fills: Vec<PoolFill> = vec![];
+    let mut remaining_collateral = amount;
+    for premium_slot in 0..=collateral_info.max_slot {
+        if remaining_collateral.is_zero() {
+            break;
+        }
+
+        let bid_pool: BidPool = read_bid_pool(deps.storage, &collateral_token_raw, premium_slot)?;
+        if bid_pool.total_bid_amount.is_zero() {
+            continue;
+        }
+
+        let premium_rate = std::cmp::min(
+            collateral_info.premium_rate(premium_slot),
+            config.max_premium_rate,
+        );
+        let discounted_price = price.rate * (Decimal256::one() - premium_rate);
+        if discounted_price.is_zero() {
+            return Err(ContractError::ZeroPrice {});
+        }
+
+        // oracle value of the collateral and the part of it paid by the bidder
+        let fill_value = |collateral: Uint256| {
+            let value = value_of(collateral, price.rate, Decimal256::one());
+            (value, value - value * premium_rate)
+        };
+
+        let mut filled_collateral = remaining_collateral;
+        let (mut value, mut paid) = fill_value(filled_collateral);
+        if paid > bid_pool.total_bid_amount {
+            filled_collateral = bid_pool.total_bid_amount / discounted_price;
+            let (partial_value, partial_paid) = fill_value(filled_collateral);
+            value = partial_value;
+            // rounding can exceed the pool by a unit, which goes to the premium
+            paid = std::cmp::min(partial_paid, bid_pool.total_bid_amount);
+        } else if bid_pool.total_bid_amount - paid < config.dust_threshold {
+            // a pool left with dust could not fill anything, so the
+            // dust is taken with this fill and the pool closed
+            paid = bid_pool.total_bid_amount;
+        }
+
+        if filled_collateral.is_zero() || paid.is_zero() {
+            continue;
+        }
+
+        remaining_collateral -= filled_collateral;
+        fills.push(PoolFill {
+            bid_pool,
+            premium_rate,
+            collateral_amount: filled_collateral,
+            value,
+            paid,
+        });
     }
 
-    // Update bid
-    if bid.amount == required_stable {
-        remove_bid(deps.storage, &bidder_raw, &collateral_token_raw);
-    } else {
-        store_bid(
+    Ok(fills)
+}
+
+/// Totals and fill attributes of the planned fills
+fn collateral_sale(config: &Config, fills: &[PoolFill], amount: Uint256) -> CollateralSale {
+    let mut sale = CollateralSale {
+        repay_amount: Uint256::zero(),
+        liquidation_fee: Uint256::zero(),
+        returned_amount: amount,
+        filled_bid_pools: fills.len() as u32,
+        fill_attributes: vec![],
+    };
+
+    for fill in fills {
+        let liquidation_fee = fill.paid * config.liquidation_fee;
+        let repay_amount = fill.paid - liquidation_fee;
+        sale.returned_amount -= fill.collateral_amount;
+        sale.repay_amount += repay_amount;
+        sale.liquidation_fee += liquidation_fee;
+        sale.fill_attributes.extend(vec![
+            attr(FILL_PREMIUM_SLOT, fill.bid_pool.premium_slot.to_string()),
+            attr(FILL_PREMIUM_RATE, fill.premium_rate.to_string()),
+            attr(FILL_COLLATERAL_AMOUNT, fill.collateral_amount),
+            attr(FILL_REPAY_AMOUNT, repay_amount),
+            attr(
+                FILL_PREMIUM_AMOUNT,
+                if fill.value > fill.paid {
+                    fill.value - fill.paid
+                } else {
+                    Uint256::zero()
+                },
+            ),
+            attr(FILL_LIQUIDATION_FEE, liquidation_fee),
+        ]);
+    }
+
+    sale
+}
+
+/// Sells the collateral to the bid pools, lowest premium slot first. The bids
+/// of a pool share each fill in proportion to their amounts, and claim the
+/// collateral they bought with ClaimLiquidations.
+///
+/// The oracle value of each fill is split between the bidder's premium, the
+/// protocol liquidation fee and the repayment, which takes the rounding dust.
+fn sell_collateral(
+    deps: DepsMut,
+    env: &Env,
+    config: &Config,
+    collateral_token: &Addr,
+    amount: Uint256,
+) -> Result<CollateralSale, ContractError> {
+    let collateral_token_raw = deps.api.addr_canonicalize(collateral_token.as_str())?;
+    let fills = plan_fills(deps.as_ref(), env, config, collateral_token, amount)?;
+    let sale = collateral_sale(config, &fills, amount);
+    if sale.repay_amount.is_zero() {
+        return Err(ContractError::NoBidExists {});
+    }
+
+    // the collateral stays in this contract until the bidders claim it
+    for fill in fills {
+        fill_bid_pool(
             deps.storage,
-            &bidder_raw,
             &collateral_token_raw,
-            Bid {
-                amount: bid.amount - required_stable,
-                ..bid
-            },
+            fill.bid_pool,
+            fill.collateral_amount,
+            fill.paid,
         )?;
     }
 
-    let bid_fee = required_stable * config.bid_fee;
-    let repay_amount = required_stable - bid_fee;
+    // the stable paid leaves the escrow, either sent right away
+    // or held for settlement by the caller
+    release_escrow(deps.storage, sale.repay_amount + sale.liquidation_fee)?;
 
-    let mut messages: Vec<CosmosMsg> = vec![
-        CosmosMsg::Wasm(WasmMsg::Execute {
-            contract_addr: collateral_token.to_string(),
-            funds: vec![],
-            msg: to_binary(&Cw20ExecuteMsg::Transfer {
-                recipient: liquidator.to_string(),
-                amount: amount.into(),
-            })?,
+    Ok(sale)
+}
+
+/// Sells the collateral and sends the stable paid to the repay address, less
+/// the bid fee, the liquidator's caller fee and the liquidation fee. Collateral left once the bids are
+/// exhausted goes back to the borrower.
+#[allow(clippy::too_many_arguments)]
+pub fn execute_bid(
+    mut deps: DepsMut,
+    env: Env,
+    liquidator: Addr,
+    borrower: Addr,
+    repay_address: Addr,
+    fee_address: Addr,
+    collateral_token: Addr,
+    amount: Uint256,
+) -> Result<Response, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+    let sale = sell_collateral(deps.branch(), &env, &config, &collateral_token, amount)?;
+
+    let (mut messages, split) = settlement_messages(
+        deps.as_ref(),
+        &config,
+        &repay_address,
+        &fee_address,
+        &liquidator,
+        &borrower,
+        sale.repay_amount,
+        sale.liquidation_fee,
+    )?;
+
+    if !sale.returned_amount.is_zero() {
+        messages.push(return_collateral_message(
+            &collateral_token,
+            &borrower,
+            sale.returned_amount,
+        )?);
+    }
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attributes(vec![
+            attr("action", "execute_bid"),
+            attr("liquidator", liquidator),
+            attr("borrower", borrower),
+            attr("stable_denom", config.stable_denom),
+            attr("repay_amount", split.repay_amount),
+            attr("bid_fee", split.bid_fee),
+            attr("caller_fee", split.caller_fee),
+            attr("liquidation_fee", sale.liquidation_fee),
+            attr("collateral_token", collateral_token),
+            attr("collateral_amount", amount - sale.returned_amount),
+            attr("returned_collateral_amount", sale.returned_amount),
+        ])
+        .add_attributes(sale.fill_attributes))
+}
+
+/// Sells the collateral like execute_bid, but keeps the stable paid in the
+/// borrower's liquidation accumulator until SettleLiquidation, so that the
+/// collaterals liquidated in one transaction are repaid at once
+#[allow(clippy::too_many_arguments)]
+pub fn accumulate_bid(
+    mut deps: DepsMut,
+    env: Env,
+    liquidator: Addr,
+    borrower: Addr,
+    repay_address: Addr,
+    fee_address: Addr,
+    collateral_token: Addr,
+    amount: Uint256,
+) -> Result<Response, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+    let borrower_raw = deps.api.addr_canonicalize(borrower.as_str())?;
+    let repay_address_raw = deps.api.addr_canonicalize(repay_address.as_str())?;
+    let fee_address_raw = deps.api.addr_canonicalize(fee_address.as_str())?;
+    let liquidator_raw = deps.api.addr_canonicalize(liquidator.as_str())?;
+    let collateral_token_raw = deps.api.addr_canonicalize(collateral_token.as_str())?;
+    let block_height = env.block.height;
+
+    let mut accumulator =
+        match read_liquidation_accumulator(deps.storage, &borrower_raw, &repay_address_raw)? {
+            Some(accumulator) => {
+                if accumulator.fee_address != fee_address_raw
+                    || accumulator.liquidator != liquidator_raw
+                {
+                    return Err(ContractError::AccumulatorMismatch {});
+                }
+
+                accumulator
+            }
+            None => LiquidationAccumulator {
+                height: block_height,
+                fee_address: fee_address_raw,
+                liquidator: liquidator_raw,
+                repay_amount: Uint256::zero(),
+                liquidation_fee: Uint256::zero(),
+                collaterals: vec![],
+            },
+        };
+
+    let sale = sell_collateral(deps.branch(), &env, &config, &collateral_token, amount)?;
+
+    // proceeds left unsettled by an earlier transaction are kept, but only
+    // the collaterals of this one are checked on settlement
+    if accumulator.height != block_height {
+        accumulator.height = block_height;
+        accumulator.collaterals = vec![];
+    }
+
+    accumulator.repay_amount += sale.repay_amount;
+    accumulator.liquidation_fee += sale.liquidation_fee;
+    match accumulator
+        .collaterals
+        .iter_mut()
+        .find(|c| c.collateral_token == collateral_token_raw)
+    {
+        Some(collateral) => {
+            collateral.amount += amount;
+            collateral.returned_amount += sale.returned_amount;
+            collateral.repay_amount += sale.repay_amount;
+        }
+        None => accumulator.collaterals.push(AccumulatedCollateral {
+            collateral_token: collateral_token_raw,
+            amount,
+            returned_amount: sale.returned_amount,
+            repay_amount: sale.repay_amount,
         }),
-        CosmosMsg::Bank(BankMsg::Send {
-            to_address: repay_address.to_string(),
+    }
+
+    store_liquidation_accumulator(
+        deps.storage,
+        &borrower_raw,
+        &repay_address_raw,
+        &accumulator,
+    )?;
+
+    let mut escrow: Escrow = read_escrow(deps.storage)?;
+    escrow.undistributed += sale.repay_amount + sale.liquidation_fee;
+    store_escrow(deps.storage, &escrow)?;
+
+    let mut messages: Vec<CosmosMsg> = vec![];
+    if !sale.returned_amount.is_zero() {
+        messages.push(return_collateral_message(
+            &collateral_token,
+            &borrower,
+            sale.returned_amount,
+        )?);
+    }
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attributes(vec![
+            attr("action", "accumulate_bid"),
+            attr("liquidator", liquidator),
+            attr("borrower", borrower),
+            attr("repay_amount", sale.repay_amount),
+            attr("liquidation_fee", sale.liquidation_fee),
+            attr("collateral_token", collateral_token),
+            attr("collateral_amount", amount - sale.returned_amount),
+            attr("returned_collateral_amount", sale.returned_amount),
+        ])
+        .add_attributes(sale.fill_attributes))
+}
+
+/// Repays the stable accumulated for the borrower with a single message.
+/// The collaterals must match the ones accumulated in this transaction,
+/// so a settlement cannot miss a collateral transfer.
+pub fn settle_liquidation(
+    deps: DepsMut,
+    env: Env,
+    borrower: Addr,
+    repay_address: Addr,
+    collaterals: Vec<(Addr, Uint256)>,
+) -> Result<Response, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+    let borrower_raw = deps.api.addr_canonicalize(borrower.as_str())?;
+    let repay_address_raw = deps.api.addr_canonicalize(repay_address.as_str())?;
+    let accumulator: LiquidationAccumulator =
+        match read_liquidation_accumulator(deps.storage, &borrower_raw, &repay_address_raw)? {
+            Some(accumulator) => accumulator,
+            None => return Err(ContractError::NoLiquidationToSettle {}),
+        };
+
+    let accumulated_collaterals: Vec<AccumulatedCollateral> =
+        if accumulator.height == env.block.height {
+            accumulator.collaterals.clone()
+        } else {
+            vec![]
+        };
+
+    if collaterals.len() != accumulated_collaterals.len() {
+        return Err(ContractError::LiquidationMismatch {});
+    }
+
+    for (collateral_token, amount) in collaterals.iter() {
+        let collateral_token_raw = deps.api.addr_canonicalize(collateral_token.as_str())?;
+        if !accumulated_collaterals
+            .iter()
+            .any(|c| c.collateral_token == collateral_token_raw && c.amount == *amount)
+        {
+            return Err(ContractError::LiquidationMismatch {});
+        }
+    }
+
+    remove_liquidation_accumulator(deps.storage, &borrower_raw, &repay_address_raw);
+
+    let mut escrow: Escrow = read_escrow(deps.storage)?;
+    escrow.undistributed -= accumulator.repay_amount + accumulator.liquidation_fee;
+    store_escrow(deps.storage, &escrow)?;
+
+    let fee_address = deps.api.addr_humanize(&accumulator.fee_address)?;
+    let liquidator = deps.api.addr_humanize(&accumulator.liquidator)?;
+    let (messages, split) = settlement_messages(
+        deps.as_ref(),
+        &config,
+        &repay_address,
+        &fee_address,
+        &liquidator,
+        &borrower,
+        accumulator.repay_amount,
+        accumulator.liquidation_fee,
+    )?;
+
+    let mut collateral_attributes: Vec<Attribute> = vec![];
+    for collateral in accumulated_collaterals {
+        collateral_attributes.extend(vec![
+            attr(
+                "collateral_token",
+                deps.api.addr_humanize(&collateral.collateral_token)?,
+            ),
+            attr(
+                "collateral_amount",
+                collateral.amount - collateral.returned_amount,
+            ),
+            attr("returned_collateral_amount", collateral.returned_amount),
+            attr("collateral_repay_amount", collateral.repay_amount),
+        ]);
+    }
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attributes(vec![
+            attr("action", "settle_liquidation"),
+            attr("borrower", borrower),
+            attr("stable_denom", config.stable_denom),
+            attr("repay_amount", split.repay_amount),
+            attr("bid_fee", split.bid_fee),
+            attr("caller_fee", split.caller_fee),
+            attr("liquidation_fee", accumulator.liquidation_fee),
+        ])
+        .add_attributes(collateral_attributes))
+}
+
+/// Stable repaid for a liquidation, less the fees taken out of it
+struct RepaySplit {
+    repay_amount: Uint256,
+    bid_fee: Uint256,
+    /// Paid to the liquidator who triggered the liquidation
+    caller_fee: Uint256,
+}
+
+fn split_repay(config: &Config, total_repay: Uint256) -> RepaySplit {
+    let bid_fee = total_repay * config.bid_fee;
+    let caller_fee = total_repay * config.caller_fee;
+    RepaySplit {
+        repay_amount: total_repay - bid_fee - caller_fee,
+        bid_fee,
+        caller_fee,
+    }
+}
+
+/// Messages paying out the stable of a liquidation: the repayment, the bid
+/// and caller fees, which are taken from it, and the protocol liquidation
+/// fee. Returns them with the split of the repayment.
+#[allow(clippy::too_many_arguments)]
+fn settlement_messages(
+    deps: Deps,
+    config: &Config,
+    repay_address: &Addr,
+    fee_address: &Addr,
+    liquidator: &Addr,
+    borrower: &Addr,
+    total_repay: Uint256,
+    liquidation_fee: Uint256,
+) -> StdResult<(Vec<CosmosMsg>, RepaySplit)> {
+    let split = split_repay(config, total_repay);
+
+    let mut messages: Vec<CosmosMsg> = vec![repay_message(
+        deps,
+        config,
+        repay_address,
+        borrower,
+        split.repay_amount,
+    )?];
+
+    if !split.bid_fee.is_zero() {
+        messages.push(CosmosMsg::Bank(BankMsg::Send {
+            to_address: fee_address.to_string(),
             amount: vec![deduct_tax(
-                deps.as_ref(),
+                deps,
                 Coin {
                     denom: config.stable_denom.clone(),
-                    amount: repay_amount.into(),
+                    amount: split.bid_fee.into(),
                 },
             )?],
-        }),
-    ];
+        }));
+    }
 
-    if !bid_fee.is_zero() {
+    if !split.caller_fee.is_zero() {
         messages.push(CosmosMsg::Bank(BankMsg::Send {
-            to_address: fee_address.to_string(),
+            to_address: liquidator.to_string(),
             amount: vec![deduct_tax(
-                deps.as_ref(),
+                deps,
                 Coin {
                     denom: config.stable_denom.clone(),
-                    amount: bid_fee.into(),
+                    amount: split.caller_fee.into(),
                 },
             )?],
         }));
     }
 
-    Ok(Response::new().add_messages(messages).add_attributes(vec![
-        attr("action", "execute_bid"),
-        attr("stable_denom", config.stable_denom),
-        attr("repay_amount", repay_amount),
-        attr("bid_fee", bid_fee),
-        attr("collateral_token", collateral_token),
-        attr("collateral_amount", amount),
-    ]))
+    if !liquidation_fee.is_zero() {
+        messages.push(CosmosMsg::Bank(BankMsg::Send {
+            to_address: deps.api.addr_humanize(&config.fee_address)?.to_string(),
+            amount: vec![deduct_tax(
+                deps,
+                Coin {
+                    denom: config.stable_denom.clone(),
+                    amount: liquidation_fee.into(),
+                },
+            )?],
+        }));
+    }
+
+    Ok((messages, split))
 }
 
-pub fn query_bid(deps: Deps, collateral_token: Addr, bidder: Addr) -> StdResult<BidResponse> {
-    let bid: Bid = read_bid(
-        deps.storage,
-        &deps.api.addr_canonicalize(bidder.as_str())?,
-        &deps.api.addr_canonicalize(collateral_token.as_str())?,
+/// Repays the borrower's loan when the repay address is the market,
+/// otherwise sends the stable to the repay address
+fn repay_message(
+    deps: Deps,
+    config: &Config,
+    repay_address: &Addr,
+    borrower: &Addr,
+    repay_amount: Uint256,
+) -> StdResult<CosmosMsg> {
+    let repay_coin = deduct_tax(
+        deps,
+        Coin {
+            denom: config.stable_denom.clone(),
+            amount: repay_amount.into(),
+        },
     )?;
 
-    Ok(BidResponse {
-        collateral_token: collateral_token.to_string(),
-        bidder: bidder.to_string(),
-        amount: bid.amount,
-        premium_rate: bid.premium_rate,
-    })
+    match &config.market_contract {
+        Some(market_contract)
+            if *market_contract == deps.api.addr_canonicalize(repay_address.as_str())? =>
+        {
+            Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: repay_address.to_string(),
+                funds: vec![repay_coin],
+                msg: to_binary(&MarketExecuteMsg::RepayStableFromLiquidation {
+                    borrower: borrower.to_string(),
+                })?,
+            }))
+        }
+        _ => Ok(CosmosMsg::Bank(BankMsg::Send {
+            to_address: repay_address.to_string(),
+            amount: vec![repay_coin],
+        })),
+    }
+}
+
+fn return_collateral_message(
+    collateral_token: &Addr,
+    borrower: &Addr,
+    amount: Uint256,
+) -> StdResult<CosmosMsg> {
+    Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: collateral_token.to_string(),
+        funds: vec![],
+        msg: to_binary(&Cw20ExecuteMsg::Transfer {
+            recipient: borrower.to_string(),
+            amount: amount.into(),
+        })?,
+    }))
+}
+
+/// Sends the sender the collateral bought by their bids for the collateral.
+/// Bids left with nothing to execute or claim are removed.
+pub fn claim_liquidations(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    collateral_token: Addr,
+    bids_idx: Option<Vec<u64>>,
+) -> Result<Response, ContractError> {
+    let collateral_token_raw = deps.api.addr_canonicalize(collateral_token.as_str())?;
+    let bidder_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+
+    let bids: Vec<Bid> = match bids_idx {
+        Some(bids_idx) => {
+            let mut bids: Vec<Bid> = vec![];
+            for bid_idx in bids_idx {
+                let bid: Bid = read_bid(deps.storage, bid_idx)?;
+                if bid.bidder != bidder_raw {
+                    return Err(ContractError::Unauthorized {});
+                }
+
+                if bid.collateral_token != collateral_token_raw {
+                    return Err(ContractError::BidCollateralMismatch(bid_idx));
+                }
+
+                bids.push(bid);
+            }
+
+            bids
+        }
+        None => read_user_bids(deps.storage, &bidder_raw, &collateral_token_raw)?,
+    };
+
+    let mut claim_amount = Uint256::zero();
+    for bid in bids {
+        let bid: Bid = accrue_bid(deps.storage, bid)?;
+        claim_amount += bid.pending_liquidated_collateral;
+
+        let bid = Bid {
+            pending_liquidated_collateral: Uint256::zero(),
+            last_updated: env.block.time.seconds(),
+            ..bid
+        };
+        if bid.amount.is_zero() {
+            exit_bid_pool(deps.storage, &bid)?;
+            remove_bid(deps.storage, &bid);
+        } else {
+            store_bid(deps.storage, &bid)?;
+        }
+    }
+
+    if claim_amount.is_zero() {
+        return Err(ContractError::NothingToClaim {});
+    }
+
+    Ok(Response::new()
+        .add_message(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: collateral_token.to_string(),
+            funds: vec![],
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: info.sender.to_string(),
+                amount: claim_amount.into(),
+            })?,
+        }))
+        .add_attributes(vec![
+            attr("action", "claim_liquidations"),
+            attr("collateral_token", collateral_token),
+            attr("bidder", info.sender),
+            attr("collateral_amount", claim_amount),
+        ]))
+}
+
+/// Removes the bids of the collateral with less than dust_threshold left or
+/// untouched by their bidder for inactive_bid_ttl, sending the bidders their
+/// remaining stable and unclaimed collateral. Checks up to limit bids from
+/// where the previous sweep stopped, starting over once the last bid is reached.
+pub fn sweep_bids(
+    deps: DepsMut,
+    env: Env,
+    collateral_token: Addr,
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+    let collateral_token_raw = deps.api.addr_canonicalize(collateral_token.as_str())?;
+    let block_time = env.block.time.seconds();
+
+    let limit = clamp_limit(limit, DEFAULT_LIMIT, MAX_LIMIT);
+    let cursor = read_sweep_cursor(deps.storage, &collateral_token_raw)?;
+    let bids: Vec<Bid> = read_collateral_bids(deps.storage, &collateral_token_raw, cursor, limit)?;
+    let next_cursor = if bids.len() < limit {
+        None
+    } else {
+        bids.last().map(|bid| bid.idx)
+    };
+    store_sweep_cursor(deps.storage, &collateral_token_raw, next_cursor)?;
+
+    let mut messages: Vec<CosmosMsg> = vec![];
+    let mut swept_attributes: Vec<Attribute> = vec![];
+    for bid in bids {
+        let bid: Bid = accrue_bid(deps.storage, bid)?;
+        let is_dust = bid.amount < config.dust_threshold;
+        let is_inactive =
+            matches!(config.inactive_bid_ttl, Some(ttl) if block_time >= bid.last_updated + ttl);
+        if !is_dust && !is_inactive {
+            continue;
+        }
+
+        let bidder = deps.api.addr_humanize(&bid.bidder)?;
+        let refund = deduct_tax(
+            deps.as_ref(),
+            Coin {
+                denom: config.stable_denom.clone(),
+                amount: bid.amount.into(),
+            },
+        )?;
+        if !refund.amount.is_zero() {
+            messages.push(CosmosMsg::Bank(BankMsg::Send {
+                to_address: bidder.to_string(),
+                amount: vec![refund],
+            }));
+        }
+
+        if !bid.pending_liquidated_collateral.is_zero() {
+            messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: collateral_token.to_string(),
+                funds: vec![],
+                msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: bidder.to_string(),
+                    amount: bid.pending_liquidated_collateral.into(),
+                })?,
+            }));
+        }
+
+        swept_attributes.push(attr("swept_bid_idx", bid.idx.to_string()));
+        let amount = bid.amount;
+        withdraw_from_bid(
+            deps.storage,
+            Bid {
+                pending_liquidated_collateral: Uint256::zero(),
+                ..bid
+            },
+            amount,
+        )?;
+        release_escrow(deps.storage, amount)?;
+    }
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attributes(vec![
+            attr("action", "sweep_bids"),
+            attr("collateral_token", collateral_token),
+        ])
+        .add_attributes(swept_attributes))
+}
+
+/// Takes the amount out of an accrued bid and its pool, removing the bid once
+/// it has nothing left to execute or claim
+fn withdraw_from_bid(storage: &mut dyn Storage, bid: Bid, amount: Uint256) -> StdResult<()> {
+    if amount == bid.amount && bid.pending_liquidated_collateral.is_zero() {
+        exit_bid_pool(storage, &bid)?;
+        remove_bid(storage, &bid);
+        return Ok(());
+    }
+
+    leave_bid_pool(storage, &bid, amount)?;
+    store_bid(
+        storage,
+        &Bid {
+            amount: bid.amount - amount,
+            ..bid
+        },
+    )
+}
+
+/// Takes the stable leaving the bids out of the escrowed total
+fn release_escrow(storage: &mut dyn Storage, amount: Uint256) -> StdResult<()> {
+    let mut escrow: Escrow = read_escrow(storage)?;
+    escrow.total_escrowed -= amount;
+    store_escrow(storage, &escrow)
+}
+
+pub fn query_bid(deps: Deps, bid_idx: u64) -> StdResult<BidResponse> {
+    let bid: Bid = read_bid(deps.storage, bid_idx)?;
+    bid_response(deps, bid)
 }
 
 pub fn query_bids_by_user(
     deps: Deps,
+    collateral_token: Option<Addr>,
     bidder: Addr,
-    start_after: Option<Addr>,
+    start_after: Option<u64>,
     limit: Option<u32>,
 ) -> StdResult<BidsResponse> {
-    let start_after = if let Some(start_after) = start_after {
-        Some(deps.api.addr_canonicalize(start_after.as_str())?)
-    } else {
-        None
+    let collateral_token_raw = match collateral_token {
+        Some(collateral_token) => Some(deps.api.addr_canonicalize(collateral_token.as_str())?),
+        None => None,
     };
-
     let bids: Vec<BidResponse> = read_bids_by_user(
         deps,
         &deps.api.addr_canonicalize(bidder.as_str())?,
+        collateral_token_raw,
         start_after,
         limit,
     )?;
@@ -244,15 +1111,9 @@ pub fn query_bids_by_user(
 pub fn query_bids_by_collateral(
     deps: Deps,
     collateral_token: Addr,
-    start_after: Option<Addr>,
+    start_after: Option<u64>,
     limit: Option<u32>,
 ) -> StdResult<BidsResponse> {
-    let start_after = if let Some(start_after) = start_after {
-        Some(deps.api.addr_canonicalize(start_after.as_str())?)
-    } else {
-        None
-    };
-
     let bids: Vec<BidResponse> = read_bids_by_collateral(
         deps,
         &deps.api.addr_canonicalize(collateral_token.as_str())?,
@@ -262,3 +1123,154 @@ pub fn query_bids_by_collateral(
 
     Ok(BidsResponse { bids })
 }
+
+pub fn query_bid_pool(
+    deps: Deps,
+    collateral_token: Addr,
+    premium_slot: u8,
+) -> StdResult<BidPoolResponse> {
+    let collateral_info = load_collateral_info(deps, &collateral_token)?;
+    let bid_pool: BidPool = read_bid_pool(
+        deps.storage,
+        &collateral_info.collateral_token,
+        premium_slot,
+    )?;
+
+    Ok(BidPoolResponse {
+        collateral_token: collateral_token.to_string(),
+        premium_slot: bid_pool.premium_slot,
+        premium_rate: collateral_info.premium_rate(premium_slot),
+        total_bid_amount: bid_pool.total_bid_amount,
+    })
+}
+
+pub fn query_bid_pools_by_collateral(
+    deps: Deps,
+    collateral_token: Addr,
+    start_after: Option<u8>,
+    limit: Option<u32>,
+) -> StdResult<BidPoolsResponse> {
+    let collateral_info = load_collateral_info(deps, &collateral_token)?;
+    let bid_pools: Vec<BidPoolResponse> = read_bid_pools(
+        deps.storage,
+        &collateral_info.collateral_token,
+        start_after,
+        limit,
+    )?
+    .into_iter()
+    .map(|bid_pool| BidPoolResponse {
+        collateral_token: collateral_token.to_string(),
+        premium_slot: bid_pool.premium_slot,
+        premium_rate: collateral_info.premium_rate(bid_pool.premium_slot),
+        total_bid_amount: bid_pool.total_bid_amount,
+    })
+    .collect();
+
+    Ok(BidPoolsResponse { bid_pools })
+}
+
+pub fn query_collateral_info(
+    deps: Deps,
+    collateral_token: Addr,
+) -> StdResult<CollateralInfoResponse> {
+    let collateral_info = load_collateral_info(deps, &collateral_token)?;
+
+    Ok(CollateralInfoResponse {
+        collateral_token: collateral_token.to_string(),
+        max_slot: collateral_info.max_slot,
+        premium_rate_per_slot: collateral_info.premium_rate_per_slot,
+        bids_enabled: collateral_info.bids_enabled,
+    })
+}
+
+/// Runs the bid execution for the collateral against the current bid pools
+/// and price without changing them. Amounts are before taxes, as in the
+/// attributes of the execution.
+pub fn query_simulate_execute(
+    deps: Deps,
+    env: Env,
+    collateral_token: Addr,
+    collateral_amount: Uint256,
+) -> StdResult<SimulateExecuteResponse> {
+    let config: Config = read_config(deps.storage)?;
+    let fills = match plan_fills(deps, &env, &config, &collateral_token, collateral_amount) {
+        Ok(fills) => fills,
+        Err(ContractError::Std(err)) => return Err(err),
+        Err(err) => return Err(StdError::generic_err(err.to_string())),
+    };
+
+    let sale = collateral_sale(&config, &fills, collateral_amount);
+    let split = split_repay(&config, sale.repay_amount);
+
+    Ok(SimulateExecuteResponse {
+        collateral_amount: collateral_amount - sale.returned_amount,
+        returned_collateral_amount: sale.returned_amount,
+        repay_amount: split.repay_amount,
+        bid_fee: split.bid_fee,
+        caller_fee: split.caller_fee,
+        liquidation_fee: sale.liquidation_fee,
+        filled_bid_pools: sale.filled_bid_pools,
+    })
+}
+
+/// Escrowed stable of the collateral by premium slot. The tracked escrow
+/// is checked against the stable balance of the contract, as in EscrowHealth.
+pub fn query_liquidity_depth(
+    deps: Deps,
+    env: Env,
+    collateral_token: Addr,
+) -> StdResult<LiquidityDepthResponse> {
+    let config: Config = read_config(deps.storage)?;
+    let collateral_info = load_collateral_info(deps, &collateral_token)?;
+
+    let mut total_bid_amount = Uint256::zero();
+    let mut active_bids = 0u64;
+    let mut pending_bids = 0u64;
+    let mut slots: Vec<SlotDepthResponse> = vec![];
+    for bid_pool in read_all_bid_pools(deps.storage, &collateral_info.collateral_token)? {
+        if bid_pool.active_bids + bid_pool.pending_bids == 0 {
+            continue;
+        }
+
+        total_bid_amount += bid_pool.total_bid_amount + bid_pool.pending_bid_amount;
+        active_bids += bid_pool.active_bids;
+        pending_bids += bid_pool.pending_bids;
+        slots.push(SlotDepthResponse {
+            premium_slot: bid_pool.premium_slot,
+            premium_rate: collateral_info.premium_rate(bid_pool.premium_slot),
+            active_bid_amount: bid_pool.total_bid_amount,
+            pending_bid_amount: bid_pool.pending_bid_amount,
+            active_bids: bid_pool.active_bids,
+            pending_bids: bid_pool.pending_bids,
+        });
+    }
+
+    let escrow: Escrow = read_escrow(deps.storage)?;
+    let balance: Uint256 = deps
+        .querier
+        .query_balance(env.contract.address, config.stable_denom)?
+        .amount
+        .into();
+
+    Ok(LiquidityDepthResponse {
+        collateral_token: collateral_token.to_string(),
+        total_bid_amount,
+        active_bids,
+        pending_bids,
+        slots,
+        balance_covers_escrow: escrow.is_covered_by(balance),
+    })
+}
+
+fn load_collateral_info(deps: Deps, collateral_token: &Addr) -> StdResult<CollateralInfo> {
+    read_collateral_info(
+        deps.storage,
+        &deps.api.addr_canonicalize(collateral_token.as_str())?,
+    )?
+    .ok_or_else(|| {
+        StdError::generic_err(format!(
+            "Collateral is not whitelisted: {}",
+            collateral_token
+        ))
+    })
+}
@@ -0,0 +1,180 @@
+//! Bid pools share each fill between their bids through two running values,
+//! so a fill never iterates the bids of the pool:
+//!
+//! - `product_snapshot` (P) is the fraction of a bid placed at the start of
+//!   the epoch that is still unfilled
+//! - `sum_snapshot` (S) is the collateral received per unit of bid placed at
+//!   the start of the epoch
+//!
+//! A bid snapshots P and S when it joins its pool, and its current amount
+//! and filled collateral follow from the values at that time and now. A fill
+//! that consumes the whole pool starts a new epoch, and P is scaled up by
+//! SCALE_FACTOR whenever it gets too small to keep its precision.
+
+use crate::state::{
+    read_bid_pool, read_epoch_scale_sum, store_bid, store_bid_pool, store_epoch_scale_sum, Bid,
+    BidPool,
+};
+
+use cosmwasm_bignumber::{Decimal256, Uint256};
+use cosmwasm_std::{CanonicalAddr, StdResult, Storage};
+
+const SCALE_FACTOR: u64 = 1_000_000_000u64;
+
+fn scale_factor() -> Decimal256 {
+    Decimal256::from_uint256(SCALE_FACTOR)
+}
+
+/// Sells the collateral to the pool for the stable amount, which cannot
+/// exceed the total bid amount of the pool
+pub fn fill_bid_pool(
+    storage: &mut dyn Storage,
+    collateral_token: &CanonicalAddr,
+    mut bid_pool: BidPool,
+    collateral_amount: Uint256,
+    stable_amount: Uint256,
+) -> StdResult<()> {
+    let total_bid_amount = bid_pool.total_bid_amount;
+    bid_pool.sum_snapshot +=
+        bid_pool.product_snapshot * Decimal256::from_ratio(collateral_amount, total_bid_amount);
+    store_epoch_scale_sum(
+        storage,
+        collateral_token,
+        bid_pool.premium_slot,
+        bid_pool.current_epoch,
+        bid_pool.current_scale,
+        &bid_pool.sum_snapshot,
+    )?;
+
+    // the unfilled fraction is rounded down, so bids never
+    // add up to more than the pool
+    let unfilled_ratio = if stable_amount >= total_bid_amount {
+        Decimal256::zero()
+    } else {
+        Decimal256::from_ratio(total_bid_amount - stable_amount, total_bid_amount)
+    };
+
+    if unfilled_ratio.is_zero() {
+        bid_pool.current_epoch += 1;
+        bid_pool.current_scale = 0;
+        bid_pool.product_snapshot = Decimal256::one();
+        bid_pool.sum_snapshot = Decimal256::zero();
+        bid_pool.total_bid_amount = Uint256::zero();
+    } else {
+        let product = bid_pool.product_snapshot * unfilled_ratio;
+        if product < Decimal256::one() / scale_factor() {
+            bid_pool.product_snapshot = bid_pool.product_snapshot * scale_factor() * unfilled_ratio;
+            bid_pool.current_scale += 1;
+            bid_pool.sum_snapshot = Decimal256::zero();
+        } else {
+            bid_pool.product_snapshot = product;
+        }
+
+        bid_pool.total_bid_amount = total_bid_amount - stable_amount;
+    }
+
+    store_bid_pool(storage, collateral_token, &bid_pool)
+}
+
+/// Brings the bid up to date with its pool: its amount is reduced by the
+/// fills since its snapshot, and the collateral they bought is added to its
+/// pending_liquidated_collateral. Pending bids are returned as they are.
+pub fn accrue_bid(storage: &dyn Storage, bid: Bid) -> StdResult<Bid> {
+    if bid.activation_time.is_some() {
+        return Ok(bid);
+    }
+
+    let bid_pool: BidPool = read_bid_pool(storage, &bid.collateral_token, bid.premium_slot)?;
+
+    // fills after the next scale are too small to count
+    let first_portion = read_epoch_scale_sum(
+        storage,
+        &bid.collateral_token,
+        bid.premium_slot,
+        bid.epoch_snapshot,
+        bid.scale_snapshot,
+    )? - bid.sum_snapshot;
+    let second_portion = read_epoch_scale_sum(
+        storage,
+        &bid.collateral_token,
+        bid.premium_slot,
+        bid.epoch_snapshot,
+        bid.scale_snapshot + 1,
+    )? / scale_factor();
+    let liquidated_collateral =
+        bid.amount * ((first_portion + second_portion) / bid.product_snapshot);
+
+    let amount = if bid.epoch_snapshot < bid_pool.current_epoch {
+        Uint256::zero()
+    } else {
+        match bid_pool.current_scale - bid.scale_snapshot {
+            0 => bid.amount * (bid_pool.product_snapshot / bid.product_snapshot),
+            1 => bid.amount * (bid_pool.product_snapshot / bid.product_snapshot / scale_factor()),
+            _ => Uint256::zero(),
+        }
+    };
+
+    Ok(Bid {
+        amount,
+        pending_liquidated_collateral: bid.pending_liquidated_collateral + liquidated_collateral,
+        ..snapshot_bid(bid, &bid_pool)
+    })
+}
+
+/// Snapshots the bid and adds it to its pool. Pending bids are only
+/// counted in the escrow of their slot until they are activated.
+pub fn join_bid_pool(storage: &mut dyn Storage, bid: Bid) -> StdResult<()> {
+    let mut bid_pool: BidPool = read_bid_pool(storage, &bid.collateral_token, bid.premium_slot)?;
+    if bid.activation_time.is_some() {
+        bid_pool.pending_bid_amount += bid.amount;
+        bid_pool.pending_bids += 1;
+        store_bid_pool(storage, &bid.collateral_token, &bid_pool)?;
+
+        return store_bid(storage, &bid);
+    }
+
+    bid_pool.total_bid_amount += bid.amount;
+    bid_pool.active_bids += 1;
+    store_bid_pool(storage, &bid.collateral_token, &bid_pool)?;
+
+    store_bid(storage, &snapshot_bid(bid, &bid_pool))
+}
+
+/// Takes the amount of an accrued bid out of its slot
+pub fn leave_bid_pool(storage: &mut dyn Storage, bid: &Bid, amount: Uint256) -> StdResult<()> {
+    let mut bid_pool: BidPool = read_bid_pool(storage, &bid.collateral_token, bid.premium_slot)?;
+    if bid.activation_time.is_some() {
+        bid_pool.pending_bid_amount -= amount;
+    } else {
+        // the snapshots round bids down, but never take more than the pool holds
+        bid_pool.total_bid_amount -= std::cmp::min(amount, bid_pool.total_bid_amount);
+    }
+
+    store_bid_pool(storage, &bid.collateral_token, &bid_pool)
+}
+
+/// Takes an accrued bid out of its slot altogether, before it is
+/// removed or moved to another slot
+pub fn exit_bid_pool(storage: &mut dyn Storage, bid: &Bid) -> StdResult<()> {
+    leave_bid_pool(storage, bid, bid.amount)?;
+
+    let mut bid_pool: BidPool = read_bid_pool(storage, &bid.collateral_token, bid.premium_slot)?;
+    if bid.activation_time.is_some() {
+        bid_pool.pending_bids -= 1;
+    } else {
+        bid_pool.active_bids -= 1;
+    }
+
+    store_bid_pool(storage, &bid.collateral_token, &bid_pool)
+}
+
+/// Sets the snapshots of a bid joining its pool or brought up to date
+fn snapshot_bid(bid: Bid, bid_pool: &BidPool) -> Bid {
+    Bid {
+        product_snapshot: bid_pool.product_snapshot,
+        sum_snapshot: bid_pool.sum_snapshot,
+        epoch_snapshot: bid_pool.current_epoch,
+        scale_snapshot: bid_pool.current_scale,
+        ..bid
+    }
+}
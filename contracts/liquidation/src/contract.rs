@@ -2,22 +2,89 @@
 use cosmwasm_std::entry_point;
 
 use crate::bid::{
-    execute_bid, query_bid, query_bids_by_collateral, query_bids_by_user, retract_bid, submit_bid,
+    accumulate_bid, activate_bids, claim_liquidations, execute_bid, query_bid, query_bid_pool,
+    query_bid_pools_by_collateral, query_bids_by_collateral, query_bids_by_user,
+    query_collateral_info, query_liquidity_depth, query_simulate_execute, retract_bid,
+    settle_liquidation, submit_bid, sweep_bids, update_bid_premium,
 };
 use crate::error::ContractError;
-use crate::state::{read_config, store_config, Config};
+use crate::escrow::{check_escrow_health, query_escrow_health};
+use crate::liquidation_amount::query_liquidation_amount;
+use crate::querier::query_whitelist;
+use crate::state::{
+    read_all_bid_pools, read_collateral_info, read_collateral_infos, read_config, read_escrow,
+    read_liquidation_accumulators, store_collateral_info, store_config, store_escrow,
+    CollateralInfo, Config, Escrow,
+};
 
 use cosmwasm_bignumber::{Decimal256, Uint256};
 use cosmwasm_std::{
-    from_binary, to_binary, Addr, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult,
+    attr, from_binary, to_binary, Addr, Attribute, Binary, Deps, DepsMut, Env, MessageInfo,
+    Response, StdResult,
 };
 use cw20::Cw20ReceiveMsg;
 use moneymarket::common::optional_addr_validate;
 use moneymarket::liquidation::{
-    ConfigResponse, Cw20HookMsg, ExecuteMsg, InstantiateMsg, LiquidationAmountResponse, QueryMsg,
+    ConfigResponse, Cw20HookMsg, ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg,
 };
-use moneymarket::querier::query_tax_rate;
-use moneymarket::tokens::TokensHuman;
+
+/// Upper bound for the protocol liquidation fee
+fn max_liquidation_fee() -> Decimal256 {
+    Decimal256::percent(10)
+}
+
+/// Upper bound for the share of the repayment paid to the liquidator
+fn max_caller_fee() -> Decimal256 {
+    Decimal256::percent(5)
+}
+
+fn assert_caller_fee(caller_fee: Decimal256) -> Result<(), ContractError> {
+    if caller_fee > max_caller_fee() {
+        return Err(ContractError::CallerFeeExceedsMax(
+            max_caller_fee().to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Both fees are taken out of the repayment, so together they must leave
+/// part of it to the borrower
+fn assert_repay_fees(bid_fee: Decimal256, caller_fee: Decimal256) -> Result<(), ContractError> {
+    if bid_fee + caller_fee >= Decimal256::one() {
+        return Err(ContractError::InvalidRepayFees {});
+    }
+
+    Ok(())
+}
+
+/// The safe ratio is the share of the borrow limit a liquidated loan is
+/// brought back to, so it has to be in (0, 1]
+fn assert_safe_ratio(safe_ratio: Decimal256) -> Result<(), ContractError> {
+    if safe_ratio.is_zero() || safe_ratio > Decimal256::one() {
+        return Err(ContractError::InvalidSafeRatio {});
+    }
+
+    Ok(())
+}
+
+fn assert_liquidation_fee(liquidation_fee: Decimal256) -> Result<(), ContractError> {
+    if liquidation_fee > max_liquidation_fee() {
+        return Err(ContractError::LiquidationFeeExceedsMax(
+            max_liquidation_fee().to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Premium slot increment for collaterals added by SyncWhitelist
+fn default_premium_rate_per_slot() -> Decimal256 {
+    Decimal256::percent(1)
+}
+
+/// Page size used to read the overseer whitelist
+const WHITELIST_PAGE_LIMIT: u32 = 30;
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
@@ -25,7 +92,22 @@ pub fn instantiate(
     _env: Env,
     _info: MessageInfo,
     msg: InstantiateMsg,
-) -> StdResult<Response> {
+) -> Result<Response, ContractError> {
+    assert_safe_ratio(msg.safe_ratio)?;
+    assert_liquidation_fee(msg.liquidation_fee)?;
+    let caller_fee = msg.caller_fee.unwrap_or_else(Decimal256::zero);
+    assert_caller_fee(caller_fee)?;
+    assert_repay_fees(msg.bid_fee, caller_fee)?;
+
+    let overseer_contract = match msg.overseer_contract {
+        Some(overseer_contract) => Some(deps.api.addr_canonicalize(&overseer_contract)?),
+        None => None,
+    };
+    let market_contract = match msg.market_contract {
+        Some(market_contract) => Some(deps.api.addr_canonicalize(&market_contract)?),
+        None => None,
+    };
+
     store_config(
         deps.storage,
         &Config {
@@ -37,9 +119,21 @@ pub fn instantiate(
             max_premium_rate: msg.max_premium_rate,
             liquidation_threshold: msg.liquidation_threshold,
             price_timeframe: msg.price_timeframe,
+            bid_retract_wait: msg.bid_retract_wait,
+            liquidation_fee: msg.liquidation_fee,
+            fee_address: deps.api.addr_canonicalize(&msg.fee_address)?,
+            bid_activation_delay: msg.bid_activation_delay,
+            inactive_bid_ttl: msg.inactive_bid_ttl,
+            min_bid_amount: msg.min_bid_amount,
+            dust_threshold: msg.dust_threshold,
+            overseer_contract,
+            caller_fee,
+            market_contract,
         },
     )?;
 
+    store_escrow(deps.storage, &Escrow::default())?;
+
     Ok(Response::default())
 }
 
@@ -61,6 +155,16 @@ pub fn execute(
             max_premium_rate,
             liquidation_threshold,
             price_timeframe,
+            bid_retract_wait,
+            liquidation_fee,
+            fee_address,
+            bid_activation_delay,
+            inactive_bid_ttl,
+            min_bid_amount,
+            dust_threshold,
+            overseer_contract,
+            caller_fee,
+            market_contract,
         } => {
             let api = deps.api;
             update_config(
@@ -74,30 +178,156 @@ pub fn execute(
                 max_premium_rate,
                 liquidation_threshold,
                 price_timeframe,
+                bid_retract_wait,
+                liquidation_fee,
+                optional_addr_validate(api, fee_address)?,
+                bid_activation_delay,
+                inactive_bid_ttl,
+                min_bid_amount,
+                dust_threshold,
+                optional_addr_validate(api, overseer_contract)?,
+                caller_fee,
+                optional_addr_validate(api, market_contract)?,
+            )
+        }
+        ExecuteMsg::WhitelistCollateral {
+            collateral_token,
+            max_slot,
+            premium_rate_per_slot,
+        } => {
+            let api = deps.api;
+            whitelist_collateral(
+                deps,
+                info,
+                api.addr_validate(&collateral_token)?,
+                max_slot,
+                premium_rate_per_slot,
             )
         }
         ExecuteMsg::SubmitBid {
             collateral_token,
-            premium_rate,
+            premium_slot,
         } => {
             let api = deps.api;
             submit_bid(
                 deps,
+                env,
                 info,
                 api.addr_validate(&collateral_token)?,
-                premium_rate,
+                premium_slot,
             )
         }
-        ExecuteMsg::RetractBid {
+        ExecuteMsg::RetractBid { bid_idx, amount } => retract_bid(deps, env, info, bid_idx, amount),
+        ExecuteMsg::UpdateBidPremium {
+            bid_idx,
+            premium_slot,
+        } => update_bid_premium(deps, env, info, bid_idx, premium_slot),
+        ExecuteMsg::SyncWhitelist { collateral_tokens } => {
+            let api = deps.api;
+            let collateral_tokens = collateral_tokens
+                .map(|collateral_tokens| {
+                    collateral_tokens
+                        .iter()
+                        .map(|collateral_token| api.addr_validate(collateral_token))
+                        .collect::<StdResult<Vec<Addr>>>()
+                })
+                .transpose()?;
+            sync_whitelist(deps, collateral_tokens)
+        }
+        ExecuteMsg::ActivateBids {
             collateral_token,
-            amount,
+            bid_idxs,
         } => {
             let api = deps.api;
-            retract_bid(deps, info, api.addr_validate(&collateral_token)?, amount)
+            activate_bids(
+                deps,
+                env,
+                info,
+                api.addr_validate(&collateral_token)?,
+                bid_idxs,
+            )
         }
+        ExecuteMsg::ClaimLiquidations {
+            collateral_token,
+            bids_idx,
+        } => {
+            let api = deps.api;
+            claim_liquidations(
+                deps,
+                env,
+                info,
+                api.addr_validate(&collateral_token)?,
+                bids_idx,
+            )
+        }
+        ExecuteMsg::SweepBids {
+            collateral_token,
+            limit,
+        } => {
+            let api = deps.api;
+            sweep_bids(deps, env, api.addr_validate(&collateral_token)?, limit)
+        }
+        ExecuteMsg::SettleLiquidation {
+            borrower,
+            repay_address,
+            collaterals,
+        } => {
+            let api = deps.api;
+            let repay_address = match repay_address {
+                Some(repay_address) => api.addr_validate(&repay_address)?,
+                None => info.sender,
+            };
+            let collaterals = collaterals
+                .iter()
+                .map(|(collateral_token, amount)| {
+                    Ok((api.addr_validate(collateral_token)?, *amount))
+                })
+                .collect::<StdResult<Vec<(Addr, Uint256)>>>()?;
+            settle_liquidation(
+                deps,
+                env,
+                api.addr_validate(&borrower)?,
+                repay_address,
+                collaterals,
+            )
+        }
+        ExecuteMsg::CheckEscrowHealth {} => check_escrow_health(deps, env, info),
     }
 }
 
+/// Initializes the escrowed total from the bid pools and the unsettled
+/// liquidations, for contracts instantiated before it was tracked
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> StdResult<Response> {
+    let mut total_escrowed = Uint256::zero();
+    for collateral_info in read_collateral_infos(deps.storage)? {
+        for bid_pool in read_all_bid_pools(deps.storage, &collateral_info.collateral_token)? {
+            total_escrowed += bid_pool.total_bid_amount + bid_pool.pending_bid_amount;
+        }
+    }
+
+    let mut undistributed = Uint256::zero();
+    for accumulator in read_liquidation_accumulators(deps.storage)? {
+        undistributed += accumulator.repay_amount + accumulator.liquidation_fee;
+    }
+
+    let escrow: Escrow = read_escrow(deps.storage)?;
+    store_escrow(
+        deps.storage,
+        &Escrow {
+            total_escrowed,
+            undistributed,
+            ..escrow
+        },
+    )?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "migrate"),
+        attr("total_escrowed", total_escrowed),
+        attr("undistributed", undistributed),
+    ]))
+}
+
 pub fn receive_cw20(
     deps: DepsMut,
     env: Env,
@@ -108,6 +338,7 @@ pub fn receive_cw20(
     match from_binary(&cw20_msg.msg) {
         Ok(Cw20HookMsg::ExecuteBid {
             liquidator,
+            borrower,
             repay_address,
             fee_address,
         }) => {
@@ -121,6 +352,30 @@ pub fn receive_cw20(
                 deps,
                 env,
                 api.addr_validate(&liquidator)?,
+                api.addr_validate(&borrower)?,
+                api.addr_validate(&repay_address)?,
+                api.addr_validate(&fee_address)?,
+                api.addr_validate(&collateral_token)?,
+                cw20_msg.amount.into(),
+            )
+        }
+        Ok(Cw20HookMsg::AccumulateBid {
+            liquidator,
+            borrower,
+            repay_address,
+            fee_address,
+        }) => {
+            let collateral_token = contract_addr.to_string();
+            let repay_address = repay_address.unwrap_or_else(|| cw20_msg.sender.clone());
+            let fee_address = fee_address.unwrap_or_else(|| cw20_msg.sender.clone());
+
+            let api = deps.api;
+
+            accumulate_bid(
+                deps,
+                env,
+                api.addr_validate(&liquidator)?,
+                api.addr_validate(&borrower)?,
                 api.addr_validate(&repay_address)?,
                 api.addr_validate(&fee_address)?,
                 api.addr_validate(&collateral_token)?,
@@ -143,6 +398,16 @@ pub fn update_config(
     max_premium_rate: Option<Decimal256>,
     liquidation_threshold: Option<Uint256>,
     price_timeframe: Option<u64>,
+    bid_retract_wait: Option<u64>,
+    liquidation_fee: Option<Decimal256>,
+    fee_address: Option<Addr>,
+    bid_activation_delay: Option<u64>,
+    inactive_bid_ttl: Option<u64>,
+    min_bid_amount: Option<Uint256>,
+    dust_threshold: Option<Uint256>,
+    overseer_contract: Option<Addr>,
+    caller_fee: Option<Decimal256>,
+    market_contract: Option<Addr>,
 ) -> Result<Response, ContractError> {
     let mut config: Config = read_config(deps.storage)?;
     if deps.api.addr_canonicalize(info.sender.as_str())? != config.owner {
@@ -162,6 +427,7 @@ pub fn update_config(
     }
 
     if let Some(safe_ratio) = safe_ratio {
+        assert_safe_ratio(safe_ratio)?;
         config.safe_ratio = safe_ratio;
     }
 
@@ -181,12 +447,192 @@ pub fn update_config(
         config.price_timeframe = price_timeframe;
     }
 
+    if let Some(bid_retract_wait) = bid_retract_wait {
+        config.bid_retract_wait = bid_retract_wait;
+    }
+
+    if let Some(liquidation_fee) = liquidation_fee {
+        assert_liquidation_fee(liquidation_fee)?;
+        config.liquidation_fee = liquidation_fee;
+    }
+
+    if let Some(fee_address) = fee_address {
+        config.fee_address = deps.api.addr_canonicalize(fee_address.as_str())?;
+    }
+
+    if let Some(bid_activation_delay) = bid_activation_delay {
+        config.bid_activation_delay = bid_activation_delay;
+    }
+
+    if let Some(inactive_bid_ttl) = inactive_bid_ttl {
+        config.inactive_bid_ttl = if inactive_bid_ttl == 0 {
+            None
+        } else {
+            Some(inactive_bid_ttl)
+        };
+    }
+
+    if let Some(min_bid_amount) = min_bid_amount {
+        config.min_bid_amount = min_bid_amount;
+    }
+
+    if let Some(dust_threshold) = dust_threshold {
+        config.dust_threshold = dust_threshold;
+    }
+
+    if let Some(overseer_contract) = overseer_contract {
+        config.overseer_contract = Some(deps.api.addr_canonicalize(overseer_contract.as_str())?);
+    }
+
+    if let Some(caller_fee) = caller_fee {
+        assert_caller_fee(caller_fee)?;
+        config.caller_fee = caller_fee;
+    }
+
+    if let Some(market_contract) = market_contract {
+        config.market_contract = Some(deps.api.addr_canonicalize(market_contract.as_str())?);
+    }
+
+    assert_repay_fees(config.bid_fee, config.caller_fee)?;
+
     store_config(deps.storage, &config)?;
     Ok(Response::default())
 }
 
+pub fn whitelist_collateral(
+    deps: DepsMut,
+    info: MessageInfo,
+    collateral_token: Addr,
+    max_slot: u8,
+    premium_rate_per_slot: Decimal256,
+) -> Result<Response, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+    if deps.api.addr_canonicalize(info.sender.as_str())? != config.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let collateral_token_raw = deps.api.addr_canonicalize(collateral_token.as_str())?;
+    if read_collateral_info(deps.storage, &collateral_token_raw)?.is_some() {
+        return Err(ContractError::CollateralAlreadyWhitelisted(
+            collateral_token,
+        ));
+    }
+
+    // the highest slot cannot offer more than the max premium rate
+    let collateral_info = CollateralInfo {
+        collateral_token: collateral_token_raw.clone(),
+        max_slot,
+        premium_rate_per_slot,
+        bids_enabled: true,
+    };
+    if collateral_info.premium_rate(max_slot) > config.max_premium_rate {
+        return Err(ContractError::PremiumExceedsMaxPremium(
+            config.max_premium_rate.to_string(),
+        ));
+    }
+
+    store_collateral_info(deps.storage, &collateral_token_raw, &collateral_info)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "whitelist_collateral"),
+        attr("collateral_token", collateral_token),
+        attr("max_slot", max_slot.to_string()),
+        attr("premium_rate_per_slot", premium_rate_per_slot.to_string()),
+    ]))
+}
+
+/// Follows the overseer whitelist for the collaterals, or every collateral
+/// known to either contract when None. Collaterals new to this contract get
+/// slots of default_premium_rate_per_slot up to the max premium rate.
+pub fn sync_whitelist(
+    deps: DepsMut,
+    collateral_tokens: Option<Vec<Addr>>,
+) -> Result<Response, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+    let overseer_contract = match config.overseer_contract {
+        Some(overseer_contract) => deps.api.addr_humanize(&overseer_contract)?,
+        None => return Err(ContractError::OverseerNotSet {}),
+    };
+
+    let mut overseer_collaterals: Vec<Addr> = vec![];
+    let mut start_after: Option<String> = None;
+    loop {
+        let elems = query_whitelist(
+            deps.as_ref(),
+            overseer_contract.clone(),
+            start_after,
+            Some(WHITELIST_PAGE_LIMIT),
+        )?
+        .elems;
+        for elem in elems.iter() {
+            overseer_collaterals.push(deps.api.addr_validate(&elem.collateral_token)?);
+        }
+
+        if elems.len() < WHITELIST_PAGE_LIMIT as usize {
+            break;
+        }
+
+        start_after = elems.last().map(|elem| elem.collateral_token.clone());
+    }
+
+    let collateral_tokens: Vec<Addr> = match collateral_tokens {
+        Some(collateral_tokens) => collateral_tokens,
+        None => {
+            let mut collateral_tokens = overseer_collaterals.clone();
+            for collateral_info in read_collateral_infos(deps.storage)? {
+                let collateral_token = deps.api.addr_humanize(&collateral_info.collateral_token)?;
+                if !collateral_tokens.contains(&collateral_token) {
+                    collateral_tokens.push(collateral_token);
+                }
+            }
+
+            collateral_tokens
+        }
+    };
+
+    let max_slot = std::cmp::min(
+        Uint256::from(100u64) * config.max_premium_rate,
+        Uint256::from(u8::MAX as u64),
+    );
+    let max_slot = u128::from(max_slot) as u8;
+
+    let mut attributes: Vec<Attribute> = vec![attr("action", "sync_whitelist")];
+    for collateral_token in collateral_tokens {
+        let collateral_token_raw = deps.api.addr_canonicalize(collateral_token.as_str())?;
+        let bids_enabled = overseer_collaterals.contains(&collateral_token);
+        let collateral_info = match read_collateral_info(deps.storage, &collateral_token_raw)? {
+            Some(collateral_info) if collateral_info.bids_enabled == bids_enabled => continue,
+            Some(collateral_info) => {
+                attributes.push(if bids_enabled {
+                    attr("enabled_collateral", collateral_token)
+                } else {
+                    attr("disabled_collateral", collateral_token)
+                });
+                CollateralInfo {
+                    bids_enabled,
+                    ..collateral_info
+                }
+            }
+            None if bids_enabled => {
+                attributes.push(attr("added_collateral", collateral_token));
+                CollateralInfo {
+                    collateral_token: collateral_token_raw.clone(),
+                    max_slot,
+                    premium_rate_per_slot: default_premium_rate_per_slot(),
+                    bids_enabled,
+                }
+            }
+            None => continue,
+        };
+
+        store_collateral_info(deps.storage, &collateral_token_raw, &collateral_info)?;
+    }
+
+    Ok(Response::new().add_attributes(attributes))
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::Config {} => to_binary(&query_config(deps)?),
         QueryMsg::LiquidationAmount {
@@ -201,25 +647,20 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
             collaterals,
             collateral_prices,
         )?),
-        QueryMsg::Bid {
-            collateral_token,
-            bidder,
-        } => {
-            let collateral_addr = deps.api.addr_validate(&collateral_token)?;
-            let bidder_addr = deps.api.addr_validate(&bidder)?;
-            to_binary(&query_bid(deps, collateral_addr, bidder_addr)?)
-        }
+        QueryMsg::Bid { bid_idx } => to_binary(&query_bid(deps, bid_idx)?),
         QueryMsg::BidsByUser {
+            collateral_token,
             bidder,
             start_after,
             limit,
         } => {
+            let collateral_addr = optional_addr_validate(deps.api, collateral_token)?;
             let bidder_addr = deps.api.addr_validate(&bidder)?;
-            let start_after_addr = optional_addr_validate(deps.api, start_after)?;
             to_binary(&query_bids_by_user(
                 deps,
+                collateral_addr,
                 bidder_addr,
-                start_after_addr,
+                start_after,
                 limit,
             )?)
         }
@@ -229,14 +670,54 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
             limit,
         } => {
             let collateral_addr = deps.api.addr_validate(&collateral_token)?;
-            let start_after_addr = optional_addr_validate(deps.api, start_after)?;
             to_binary(&query_bids_by_collateral(
                 deps,
                 collateral_addr,
-                start_after_addr,
+                start_after,
+                limit,
+            )?)
+        }
+        QueryMsg::BidPool {
+            collateral_token,
+            premium_slot,
+        } => {
+            let collateral_addr = deps.api.addr_validate(&collateral_token)?;
+            to_binary(&query_bid_pool(deps, collateral_addr, premium_slot)?)
+        }
+        QueryMsg::BidPoolsByCollateral {
+            collateral_token,
+            start_after,
+            limit,
+        } => {
+            let collateral_addr = deps.api.addr_validate(&collateral_token)?;
+            to_binary(&query_bid_pools_by_collateral(
+                deps,
+                collateral_addr,
+                start_after,
                 limit,
             )?)
         }
+        QueryMsg::CollateralInfo { collateral_token } => {
+            let collateral_addr = deps.api.addr_validate(&collateral_token)?;
+            to_binary(&query_collateral_info(deps, collateral_addr)?)
+        }
+        QueryMsg::SimulateExecute {
+            collateral_token,
+            collateral_amount,
+        } => {
+            let collateral_addr = deps.api.addr_validate(&collateral_token)?;
+            to_binary(&query_simulate_execute(
+                deps,
+                env,
+                collateral_addr,
+                collateral_amount,
+            )?)
+        }
+        QueryMsg::LiquidityDepth { collateral_token } => {
+            let collateral_addr = deps.api.addr_validate(&collateral_token)?;
+            to_binary(&query_liquidity_depth(deps, env, collateral_addr)?)
+        }
+        QueryMsg::EscrowHealth {} => to_binary(&query_escrow_health(deps, env)?),
     }
 }
 
@@ -251,71 +732,25 @@ fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
         max_premium_rate: config.max_premium_rate,
         liquidation_threshold: config.liquidation_threshold,
         price_timeframe: config.price_timeframe,
+        bid_retract_wait: config.bid_retract_wait,
+        liquidation_fee: config.liquidation_fee,
+        fee_address: deps.api.addr_humanize(&config.fee_address)?.to_string(),
+        bid_activation_delay: config.bid_activation_delay,
+        inactive_bid_ttl: config.inactive_bid_ttl,
+        min_bid_amount: config.min_bid_amount,
+        dust_threshold: config.dust_threshold,
+        overseer_contract: match config.overseer_contract {
+            Some(overseer_contract) => {
+                Some(deps.api.addr_humanize(&overseer_contract)?.to_string())
+            }
+            None => None,
+        },
+        caller_fee: config.caller_fee,
+        market_contract: match config.market_contract {
+            Some(market_contract) => Some(deps.api.addr_humanize(&market_contract)?.to_string()),
+            None => None,
+        },
     };
 
     Ok(resp)
 }
-
-fn query_liquidation_amount(
-    deps: Deps,
-    borrow_amount: Uint256,
-    borrow_limit: Uint256,
-    collaterals: TokensHuman,
-    collateral_prices: Vec<Decimal256>,
-) -> StdResult<LiquidationAmountResponse> {
-    let config: Config = read_config(deps.storage)?;
-
-    // Safely collateralized check
-    if borrow_amount <= borrow_limit {
-        return Ok(LiquidationAmountResponse {
-            collaterals: vec![],
-        });
-    }
-
-    let mut collaterals_value = Uint256::zero();
-    for c in collaterals.iter().zip(collateral_prices.iter()) {
-        let (collateral, price) = c;
-        let collateral_value = collateral.1 * *price;
-        collaterals_value += collateral_value;
-    }
-
-    let tax_rate = query_tax_rate(deps)?;
-
-    let fee_deductor = (Decimal256::one() - config.max_premium_rate)
-        * (Decimal256::one() - config.bid_fee)
-        * (Decimal256::one() - tax_rate);
-
-    // expected_repay_amount must be bigger than borrow_amount
-    // else force liquidate all collaterals
-    let expected_repay_amount = collaterals_value * fee_deductor;
-    if expected_repay_amount <= borrow_amount {
-        return Ok(LiquidationAmountResponse { collaterals });
-    }
-
-    // When collaterals_value is smaller than liquidation_threshold,
-    // liquidate all collaterals
-    let safe_borrow_amount = borrow_limit * config.safe_ratio;
-    let liquidation_ratio = if collaterals_value < config.liquidation_threshold {
-        Decimal256::from_uint256(borrow_amount) / Decimal256::from_uint256(expected_repay_amount)
-    } else {
-        Decimal256::from_uint256(borrow_amount - safe_borrow_amount)
-            / Decimal256::from_uint256(expected_repay_amount - safe_borrow_amount)
-    };
-
-    // Cap the liquidation_ratio to 1
-    let liquidation_ratio = std::cmp::min(Decimal256::one(), liquidation_ratio);
-    Ok(LiquidationAmountResponse {
-        collaterals: collaterals
-            .iter()
-            .zip(collateral_prices.iter())
-            .map(|c| {
-                let (collateral, _) = c;
-                let mut collateral = collateral.clone();
-
-                collateral.1 = collateral.1 * liquidation_ratio;
-                collateral
-            })
-            .filter(|c| c.1 > Uint256::zero())
-            .collect::<TokensHuman>(),
-    })
-}
@@ -1,4 +1,5 @@
 use cosmwasm_std::{Addr, OverflowError, StdError};
+use moneymarket::error::MoneyMarketError;
 use thiserror::Error;
 
 #[derive(Error, Debug, PartialEq)]
@@ -9,11 +10,26 @@ pub enum ContractError {
     #[error("{0}")]
     OverflowError(#[from] OverflowError),
 
+    #[error("{0}")]
+    MoneyMarket(#[from] MoneyMarketError),
+
     #[error("Unauthorized")]
     Unauthorized {},
 
-    #[error("User already has bid for specified collateral: {0}")]
-    AlreadyBidForCollateral(Addr),
+    #[error("Collateral is not whitelisted: {0}")]
+    CollateralNotWhitelisted(Addr),
+
+    #[error("Collateral does not accept new bids: {0}")]
+    CollateralBidsDisabled(Addr),
+
+    #[error("New bids are paused until the escrow matches the contract balance")]
+    BidsPaused {},
+
+    #[error("Overseer contract is not set")]
+    OverseerNotSet {},
+
+    #[error("Collateral is already whitelisted: {0}")]
+    CollateralAlreadyWhitelisted(Addr),
 
     #[error("No {0} assets have been provided")]
     AssetNotProvided(String),
@@ -21,15 +37,57 @@ pub enum ContractError {
     #[error("Premium rate cannot exceed the max premium rate: {0}")]
     PremiumExceedsMaxPremium(String),
 
+    #[error("Safe ratio must be greater than 0 and at most 1")]
+    InvalidSafeRatio {},
+
+    #[error("Caller fee cannot exceed the max caller fee: {0}")]
+    CallerFeeExceedsMax(String),
+
+    #[error("Bid fee and caller fee must sum to less than 1")]
+    InvalidRepayFees {},
+
+    #[error("Liquidation fee cannot exceed the max liquidation fee: {0}")]
+    LiquidationFeeExceedsMax(String),
+
+    #[error("Bid amount cannot be less than the min bid amount: {0}")]
+    BidAmountTooSmall(u128),
+
+    #[error("Premium slot cannot exceed the max slot: {0}")]
+    PremiumSlotExceedsMaxSlot(u8),
+
     #[error("Invalid request: \"execute bid\" message not included in request")]
     MissingExecuteBidHook {},
 
     #[error("No bids with the specified information exist")]
     NoBidExists {},
 
-    #[error("Insufficient bid balance; Required balance: {0}")]
-    InsufficientBidBalance(u128),
+    #[error("Cannot execute bids at a zero price")]
+    ZeroPrice {},
+
+    #[error("Bid is not pending activation: {0}")]
+    BidNotPending(u64),
+
+    #[error("Bid cannot be activated until: {0}")]
+    BidActivationNotReady(u64),
+
+    #[error("Bid {0} is for a different collateral")]
+    BidCollateralMismatch(u64),
+
+    #[error("Fee address or liquidator differs from the pending liquidation")]
+    AccumulatorMismatch {},
+
+    #[error("No liquidation to settle for the borrower")]
+    NoLiquidationToSettle {},
+
+    #[error("Collaterals do not match the ones liquidated in this transaction")]
+    LiquidationMismatch {},
+
+    #[error("No liquidated collateral to claim")]
+    NothingToClaim {},
 
     #[error("Retract amount cannot exceed bid balance: {0}")]
     RetractExceedsBid(u128),
+
+    #[error("Bid retraction is still in its waiting period until: {0}")]
+    RetractWaitNotOver(u64),
 }
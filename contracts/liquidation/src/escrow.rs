@@ -0,0 +1,65 @@
+use cosmwasm_bignumber::Uint256;
+use cosmwasm_std::{attr, Deps, DepsMut, Env, MessageInfo, Response, StdResult};
+use moneymarket::liquidation::EscrowHealthResponse;
+
+use crate::error::ContractError;
+use crate::state::{read_config, read_escrow, store_escrow, Config, Escrow};
+
+/// Pauses new bids when the contract balance falls short of the escrowed
+/// stable, and resumes them once it is covered again
+/// Executor: owner
+pub fn check_escrow_health(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+    if deps.api.addr_canonicalize(info.sender.as_str())? != config.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let health = query_escrow_health(deps.as_ref(), env)?;
+    let bids_paused = !health.consistent;
+    store_escrow(
+        deps.storage,
+        &Escrow {
+            total_escrowed: health.total_escrowed,
+            undistributed: health.undistributed,
+            bids_paused,
+        },
+    )?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "check_escrow_health"),
+        attr("total_escrowed", health.total_escrowed),
+        attr("undistributed", health.undistributed),
+        attr("balance", health.balance),
+        attr("surplus", health.surplus),
+        attr("bids_paused", bids_paused.to_string()),
+    ]))
+}
+
+pub fn query_escrow_health(deps: Deps, env: Env) -> StdResult<EscrowHealthResponse> {
+    let config: Config = read_config(deps.storage)?;
+    let escrow: Escrow = read_escrow(deps.storage)?;
+
+    let balance: Uint256 = deps
+        .querier
+        .query_balance(env.contract.address, config.stable_denom)?
+        .amount
+        .into();
+
+    let required = escrow.required_balance();
+    Ok(EscrowHealthResponse {
+        total_escrowed: escrow.total_escrowed,
+        balance,
+        undistributed: escrow.undistributed,
+        consistent: escrow.is_covered_by(balance),
+        surplus: if balance > required {
+            balance - required
+        } else {
+            Uint256::zero()
+        },
+        bids_paused: escrow.bids_paused,
+    })
+}
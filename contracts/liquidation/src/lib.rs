@@ -1,6 +1,11 @@
 mod bid;
+mod bid_pool;
 pub mod contract;
 pub mod error;
+mod escrow;
+mod liquidation_amount;
+pub mod msg;
+mod querier;
 pub mod state;
 
 #[cfg(test)]
@@ -0,0 +1,95 @@
+use crate::state::{read_config, Config};
+
+use cosmwasm_bignumber::{Decimal256, Uint256};
+use cosmwasm_std::{Deps, StdError, StdResult};
+use moneymarket::liquidation::LiquidationAmountResponse;
+use moneymarket::querier::query_tax_rate;
+use moneymarket::tokens::TokensHuman;
+
+pub fn query_liquidation_amount(
+    deps: Deps,
+    borrow_amount: Uint256,
+    borrow_limit: Uint256,
+    collaterals: TokensHuman,
+    collateral_prices: Vec<Decimal256>,
+) -> StdResult<LiquidationAmountResponse> {
+    let config: Config = read_config(deps.storage)?;
+    let tax_rate = query_tax_rate(deps)?;
+
+    // the bid and caller fees are both taken out of the repayment,
+    // like split_repay does on settlement
+    let fee_deductor = (Decimal256::one() - config.max_premium_rate)
+        * (Decimal256::one() - config.liquidation_fee)
+        * (Decimal256::one() - config.bid_fee - config.caller_fee)
+        * (Decimal256::one() - tax_rate);
+
+    Ok(LiquidationAmountResponse {
+        collaterals: compute_liquidation_amount(
+            borrow_amount,
+            borrow_limit,
+            collaterals,
+            &collateral_prices,
+            fee_deductor,
+            config.safe_ratio,
+            config.liquidation_threshold,
+        )?,
+    })
+}
+
+/// Returns the amount of each collateral to liquidate so that the remaining loan,
+/// once the proceeds net of `fee_deductor` are repaid, is `safe_ratio` of the
+/// remaining borrow limit.
+///
+/// Healthy positions liquidate nothing, and all collaterals are liquidated when
+/// their proceeds cannot repay the loan or their value is below `liquidation_threshold`
+/// (in which case the ratio repays the whole loan). Zero amounts are left out.
+pub fn compute_liquidation_amount(
+    borrow_amount: Uint256,
+    borrow_limit: Uint256,
+    collaterals: TokensHuman,
+    collateral_prices: &[Decimal256],
+    fee_deductor: Decimal256,
+    safe_ratio: Decimal256,
+    liquidation_threshold: Uint256,
+) -> StdResult<TokensHuman> {
+    if collaterals.len() != collateral_prices.len() {
+        return Err(StdError::generic_err(
+            "Collaterals and collateral prices must have the same length",
+        ));
+    }
+
+    // Safely collateralized check
+    if borrow_amount <= borrow_limit {
+        return Ok(vec![]);
+    }
+
+    let mut collaterals_value = Uint256::zero();
+    for (collateral, price) in collaterals.iter().zip(collateral_prices.iter()) {
+        collaterals_value += collateral.1 * *price;
+    }
+
+    // expected_repay_amount must be bigger than borrow_amount
+    // else force liquidate all collaterals
+    let expected_repay_amount = collaterals_value * fee_deductor;
+    if expected_repay_amount <= borrow_amount {
+        return Ok(collaterals);
+    }
+
+    // When collaterals_value is smaller than liquidation_threshold,
+    // liquidate all collaterals
+    let safe_borrow_amount = borrow_limit * safe_ratio;
+    let liquidation_ratio = if collaterals_value < liquidation_threshold {
+        Decimal256::from_uint256(borrow_amount) / Decimal256::from_uint256(expected_repay_amount)
+    } else {
+        Decimal256::from_uint256(borrow_amount - safe_borrow_amount)
+            / Decimal256::from_uint256(expected_repay_amount - safe_borrow_amount)
+    };
+
+    // Cap the liquidation_ratio to 1
+    let liquidation_ratio = std::cmp::min(Decimal256::one(), liquidation_ratio);
+    Ok(collaterals
+        .into_iter()
+        .map(|(collateral, amount)| (collateral, amount * liquidation_ratio))
+        .filter(|c| c.1 > Uint256::zero())
+        .collect::<TokensHuman>())
+}
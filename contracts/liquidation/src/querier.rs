@@ -0,0 +1,23 @@
+use cosmwasm_std::{to_binary, Addr, Deps, QueryRequest, StdResult, WasmQuery};
+
+use moneymarket::overseer::{QueryMsg as OverseerQueryMsg, WhitelistResponse};
+
+pub fn query_whitelist(
+    deps: Deps,
+    overseer_addr: Addr,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<WhitelistResponse> {
+    let whitelist: WhitelistResponse =
+        deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+            contract_addr: overseer_addr.to_string(),
+            msg: to_binary(&OverseerQueryMsg::Whitelist {
+                collateral_token: None,
+                start_after,
+                limit,
+                order_by: None,
+            })?,
+        }))?;
+
+    Ok(whitelist)
+}
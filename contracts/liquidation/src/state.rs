@@ -5,12 +5,23 @@ use cosmwasm_bignumber::{Decimal256, Uint256};
 use cosmwasm_std::{CanonicalAddr, Deps, Order, StdError, StdResult, Storage};
 use cosmwasm_storage::{singleton, singleton_read, Bucket, ReadonlyBucket};
 use moneymarket::liquidation::BidResponse;
+use moneymarket::pagination::{clamp_limit, DEFAULT_LIMIT, MAX_LIMIT};
+
+use crate::bid_pool::accrue_bid;
 
 static KEY_CONFIG: &[u8] = b"config";
+static KEY_BID_IDX: &[u8] = b"bid_idx";
+static KEY_ESCROW: &[u8] = b"escrow";
 
+static PREFIX_COLLATERAL_INFO: &[u8] = b"collateral_info";
 static PREFIX_BID: &[u8] = b"bid";
 static PREFIX_BID_BY_USER: &[u8] = b"bid_by_user";
+static PREFIX_BID_BY_USER_COLLATERAL: &[u8] = b"bid_by_user_collateral";
 static PREFIX_BID_BY_COLLATERAL: &[u8] = b"bid_by_collateral";
+static PREFIX_BID_POOL: &[u8] = b"bid_pool";
+static PREFIX_EPOCH_SCALE_SUM: &[u8] = b"epoch_scale_sum";
+static PREFIX_SWEEP_CURSOR: &[u8] = b"sweep_cursor";
+static PREFIX_LIQUIDATION_ACCUMULATOR: &[u8] = b"liquidation_accumulator";
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Config {
@@ -22,6 +33,18 @@ pub struct Config {
     pub max_premium_rate: Decimal256,
     pub liquidation_threshold: Uint256,
     pub price_timeframe: u64,
+    pub bid_retract_wait: u64,
+    pub liquidation_fee: Decimal256,
+    pub fee_address: CanonicalAddr,
+    pub bid_activation_delay: u64,
+    pub inactive_bid_ttl: Option<u64>,
+    pub min_bid_amount: Uint256,
+    pub dust_threshold: Uint256,
+    /// Source of the collaterals for SyncWhitelist
+    pub overseer_contract: Option<CanonicalAddr>,
+    pub caller_fee: Decimal256,
+    /// Repaid on behalf of the borrower rather than sent the stable
+    pub market_contract: Option<CanonicalAddr>,
 }
 
 pub fn store_config(storage: &mut dyn Storage, config: &Config) -> StdResult<()> {
@@ -32,75 +55,341 @@ pub fn read_config(storage: &dyn Storage) -> StdResult<Config> {
     singleton_read(storage, KEY_CONFIG).load()
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct Escrow {
+    /// Stable of the pending and active bids, updated by every
+    /// submission, retraction, sweep and fill
+    pub total_escrowed: Uint256,
+    /// Stable paid by the bids of accumulated liquidations, held until settled
+    pub undistributed: Uint256,
+    /// New bids are rejected while set
+    pub bids_paused: bool,
+}
+
+impl Escrow {
+    /// Stable the contract must hold for every bid and pending settlement
+    pub fn required_balance(&self) -> Uint256 {
+        self.total_escrowed + self.undistributed
+    }
+
+    /// A surplus (e.g. a plain transfer to the contract) is harmless,
+    /// only a shortfall means the accounting is broken
+    pub fn is_covered_by(&self, balance: Uint256) -> bool {
+        balance >= self.required_balance()
+    }
+}
+
+pub fn store_escrow(storage: &mut dyn Storage, escrow: &Escrow) -> StdResult<()> {
+    singleton(storage, KEY_ESCROW).save(escrow)
+}
+
+pub fn read_escrow(storage: &dyn Storage) -> StdResult<Escrow> {
+    Ok(singleton_read(storage, KEY_ESCROW)
+        .may_load()?
+        .unwrap_or_default())
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
-pub struct Bid {
-    pub amount: Uint256,
-    pub premium_rate: Decimal256,
+pub struct CollateralInfo {
+    pub collateral_token: CanonicalAddr,
+    pub max_slot: u8,
+    pub premium_rate_per_slot: Decimal256,
+    /// New bids are only accepted when enabled
+    pub bids_enabled: bool,
 }
 
-pub fn store_bid(
+impl CollateralInfo {
+    pub fn premium_rate(&self, premium_slot: u8) -> Decimal256 {
+        self.premium_rate_per_slot * Decimal256::from_uint256(premium_slot as u64)
+    }
+}
+
+pub fn store_collateral_info(
     storage: &mut dyn Storage,
-    bidder: &CanonicalAddr,
     collateral_token: &CanonicalAddr,
-    bid: Bid,
+    collateral_info: &CollateralInfo,
 ) -> StdResult<()> {
+    let mut collateral_bucket: Bucket<CollateralInfo> =
+        Bucket::new(storage, PREFIX_COLLATERAL_INFO);
+    collateral_bucket.save(collateral_token.as_slice(), collateral_info)
+}
+
+pub fn read_collateral_info(
+    storage: &dyn Storage,
+    collateral_token: &CanonicalAddr,
+) -> StdResult<Option<CollateralInfo>> {
+    let collateral_bucket: ReadonlyBucket<CollateralInfo> =
+        ReadonlyBucket::new(storage, PREFIX_COLLATERAL_INFO);
+    collateral_bucket.may_load(collateral_token.as_slice())
+}
+
+pub fn read_collateral_infos(storage: &dyn Storage) -> StdResult<Vec<CollateralInfo>> {
+    let collateral_bucket: ReadonlyBucket<CollateralInfo> =
+        ReadonlyBucket::new(storage, PREFIX_COLLATERAL_INFO);
+    collateral_bucket
+        .range(None, None, Order::Ascending)
+        .map(|elem| {
+            let (_, v) = elem?;
+            Ok(v)
+        })
+        .collect()
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Bid {
+    pub idx: u64,
+    pub collateral_token: CanonicalAddr,
+    pub bidder: CanonicalAddr,
+    pub amount: Uint256,
+    pub premium_slot: u8,
+    pub product_snapshot: Decimal256,
+    pub sum_snapshot: Decimal256,
+    pub epoch_snapshot: u64,
+    pub scale_snapshot: u64,
+    /// Collateral bought by the bid that is yet to be claimed
+    pub pending_liquidated_collateral: Uint256,
+    /// Time after which a requested retraction can be completed
+    pub wait_end: Option<u64>,
+    /// Time after which a pending bid can be activated; pending bids
+    /// are kept out of their pool until then
+    pub activation_time: Option<u64>,
+    /// Last time the bidder submitted, activated, retracted or claimed the bid
+    pub last_updated: u64,
+}
+
+/// Returns the next bid index; indexes are never reused
+pub fn pop_bid_idx(storage: &mut dyn Storage) -> StdResult<u64> {
+    let mut idx_store = singleton(storage, KEY_BID_IDX);
+    let last_idx: u64 = idx_store.may_load()?.unwrap_or_default();
+    idx_store.save(&(last_idx + 1))?;
+    Ok(last_idx + 1)
+}
+
+pub fn store_bid(storage: &mut dyn Storage, bid: &Bid) -> StdResult<()> {
     let mut bid_bucket: Bucket<Bid> = Bucket::new(storage, PREFIX_BID);
-    bid_bucket.save(
-        &[bidder.as_slice(), collateral_token.as_slice()].concat(),
-        &bid,
-    )?;
+    bid_bucket.save(&bid.idx.to_be_bytes(), bid)?;
 
     let mut bid_user_index: Bucket<bool> =
-        Bucket::multilevel(storage, &[PREFIX_BID_BY_USER, bidder.as_slice()]);
-    bid_user_index.save(collateral_token.as_slice(), &true)?;
+        Bucket::multilevel(storage, &[PREFIX_BID_BY_USER, bid.bidder.as_slice()]);
+    bid_user_index.save(&bid.idx.to_be_bytes(), &true)?;
+
+    let mut bid_user_collateral_index: Bucket<bool> = Bucket::multilevel(
+        storage,
+        &[
+            PREFIX_BID_BY_USER_COLLATERAL,
+            bid.bidder.as_slice(),
+            bid.collateral_token.as_slice(),
+        ],
+    );
+    bid_user_collateral_index.save(&bid.idx.to_be_bytes(), &true)?;
 
     let mut bid_collateral_index: Bucket<bool> = Bucket::multilevel(
         storage,
-        &[PREFIX_BID_BY_COLLATERAL, collateral_token.as_slice()],
+        &[PREFIX_BID_BY_COLLATERAL, bid.collateral_token.as_slice()],
     );
-    bid_collateral_index.save(bidder.as_slice(), &true)?;
+    bid_collateral_index.save(&bid.idx.to_be_bytes(), &true)?;
 
     Ok(())
 }
 
-pub fn remove_bid(
-    storage: &mut dyn Storage,
-    bidder: &CanonicalAddr,
-    collateral_token: &CanonicalAddr,
-) {
+pub fn remove_bid(storage: &mut dyn Storage, bid: &Bid) {
     let mut bid_bucket: Bucket<Bid> = Bucket::new(storage, PREFIX_BID);
-    bid_bucket.remove(&[bidder.as_slice(), collateral_token.as_slice()].concat());
+    bid_bucket.remove(&bid.idx.to_be_bytes());
 
     let mut bid_user_index: Bucket<bool> =
-        Bucket::multilevel(storage, &[PREFIX_BID_BY_USER, bidder.as_slice()]);
-    bid_user_index.remove(collateral_token.as_slice());
+        Bucket::multilevel(storage, &[PREFIX_BID_BY_USER, bid.bidder.as_slice()]);
+    bid_user_index.remove(&bid.idx.to_be_bytes());
+
+    let mut bid_user_collateral_index: Bucket<bool> = Bucket::multilevel(
+        storage,
+        &[
+            PREFIX_BID_BY_USER_COLLATERAL,
+            bid.bidder.as_slice(),
+            bid.collateral_token.as_slice(),
+        ],
+    );
+    bid_user_collateral_index.remove(&bid.idx.to_be_bytes());
 
     let mut bid_collateral_index: Bucket<bool> = Bucket::multilevel(
         storage,
-        &[PREFIX_BID_BY_COLLATERAL, collateral_token.as_slice()],
+        &[PREFIX_BID_BY_COLLATERAL, bid.collateral_token.as_slice()],
     );
-    bid_collateral_index.remove(bidder.as_slice());
+    bid_collateral_index.remove(&bid.idx.to_be_bytes());
 }
 
-pub fn read_bid<'a>(
-    storage: &'a dyn Storage,
-    bidder: &CanonicalAddr,
-    collateral_token: &CanonicalAddr,
-) -> StdResult<Bid> {
-    let bid_bucket: ReadonlyBucket<'a, Bid> = ReadonlyBucket::new(storage, PREFIX_BID);
-
+pub fn read_bid(storage: &dyn Storage, bid_idx: u64) -> StdResult<Bid> {
+    let bid_bucket: ReadonlyBucket<Bid> = ReadonlyBucket::new(storage, PREFIX_BID);
     bid_bucket
-        .load(&[bidder.as_slice(), collateral_token.as_slice()].concat())
+        .load(&bid_idx.to_be_bytes())
         .map_err(|_| StdError::generic_err("No bids with the specified information exist"))
 }
 
-// settings for pagination
-const MAX_LIMIT: u32 = 30;
-const DEFAULT_LIMIT: u32 = 10;
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BidPool {
+    pub premium_slot: u8,
+    pub total_bid_amount: Uint256,
+    pub product_snapshot: Decimal256,
+    pub sum_snapshot: Decimal256,
+    pub current_epoch: u64,
+    pub current_scale: u64,
+    /// Escrowed by the bids of the slot waiting for activation
+    pub pending_bid_amount: Uint256,
+    pub active_bids: u64,
+    pub pending_bids: u64,
+}
+
+pub fn store_bid_pool(
+    storage: &mut dyn Storage,
+    collateral_token: &CanonicalAddr,
+    bid_pool: &BidPool,
+) -> StdResult<()> {
+    let mut bid_pool_bucket: Bucket<BidPool> =
+        Bucket::multilevel(storage, &[PREFIX_BID_POOL, collateral_token.as_slice()]);
+    bid_pool_bucket.save(&[bid_pool.premium_slot], bid_pool)
+}
+
+pub fn read_bid_pool(
+    storage: &dyn Storage,
+    collateral_token: &CanonicalAddr,
+    premium_slot: u8,
+) -> StdResult<BidPool> {
+    let bid_pool_bucket: ReadonlyBucket<BidPool> =
+        ReadonlyBucket::multilevel(storage, &[PREFIX_BID_POOL, collateral_token.as_slice()]);
+    Ok(bid_pool_bucket
+        .may_load(&[premium_slot])?
+        .unwrap_or(BidPool {
+            premium_slot,
+            total_bid_amount: Uint256::zero(),
+            product_snapshot: Decimal256::one(),
+            sum_snapshot: Decimal256::zero(),
+            current_epoch: 0,
+            current_scale: 0,
+            pending_bid_amount: Uint256::zero(),
+            active_bids: 0,
+            pending_bids: 0,
+        }))
+}
+
+/// Stores the sum snapshot of a pool as of its last fill in the epoch and scale
+pub fn store_epoch_scale_sum(
+    storage: &mut dyn Storage,
+    collateral_token: &CanonicalAddr,
+    premium_slot: u8,
+    epoch: u64,
+    scale: u64,
+    sum: &Decimal256,
+) -> StdResult<()> {
+    let mut sum_bucket: Bucket<Decimal256> = Bucket::multilevel(
+        storage,
+        &[
+            PREFIX_EPOCH_SCALE_SUM,
+            collateral_token.as_slice(),
+            &[premium_slot],
+            &epoch.to_be_bytes(),
+        ],
+    );
+    sum_bucket.save(&scale.to_be_bytes(), sum)
+}
+
+pub fn read_epoch_scale_sum(
+    storage: &dyn Storage,
+    collateral_token: &CanonicalAddr,
+    premium_slot: u8,
+    epoch: u64,
+    scale: u64,
+) -> StdResult<Decimal256> {
+    let sum_bucket: ReadonlyBucket<Decimal256> = ReadonlyBucket::multilevel(
+        storage,
+        &[
+            PREFIX_EPOCH_SCALE_SUM,
+            collateral_token.as_slice(),
+            &[premium_slot],
+            &epoch.to_be_bytes(),
+        ],
+    );
+    Ok(sum_bucket
+        .may_load(&scale.to_be_bytes())?
+        .unwrap_or_else(Decimal256::zero))
+}
+
+/// Stable paid for a borrower's collaterals, waiting to be settled
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LiquidationAccumulator {
+    /// Block height of the collaterals
+    pub height: u64,
+    pub fee_address: CanonicalAddr,
+    pub liquidator: CanonicalAddr,
+    /// Paid by the bidders net of the liquidation fee, before the bid fee
+    pub repay_amount: Uint256,
+    pub liquidation_fee: Uint256,
+    pub collaterals: Vec<AccumulatedCollateral>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AccumulatedCollateral {
+    pub collateral_token: CanonicalAddr,
+    /// Received for liquidation, including the returned part
+    pub amount: Uint256,
+    pub returned_amount: Uint256,
+    pub repay_amount: Uint256,
+}
+
+pub fn store_liquidation_accumulator(
+    storage: &mut dyn Storage,
+    borrower: &CanonicalAddr,
+    repay_address: &CanonicalAddr,
+    accumulator: &LiquidationAccumulator,
+) -> StdResult<()> {
+    let mut accumulator_bucket: Bucket<LiquidationAccumulator> = Bucket::multilevel(
+        storage,
+        &[PREFIX_LIQUIDATION_ACCUMULATOR, borrower.as_slice()],
+    );
+    accumulator_bucket.save(repay_address.as_slice(), accumulator)
+}
+
+pub fn remove_liquidation_accumulator(
+    storage: &mut dyn Storage,
+    borrower: &CanonicalAddr,
+    repay_address: &CanonicalAddr,
+) {
+    let mut accumulator_bucket: Bucket<LiquidationAccumulator> = Bucket::multilevel(
+        storage,
+        &[PREFIX_LIQUIDATION_ACCUMULATOR, borrower.as_slice()],
+    );
+    accumulator_bucket.remove(repay_address.as_slice())
+}
+
+pub fn read_liquidation_accumulator(
+    storage: &dyn Storage,
+    borrower: &CanonicalAddr,
+    repay_address: &CanonicalAddr,
+) -> StdResult<Option<LiquidationAccumulator>> {
+    let accumulator_bucket: ReadonlyBucket<LiquidationAccumulator> = ReadonlyBucket::multilevel(
+        storage,
+        &[PREFIX_LIQUIDATION_ACCUMULATOR, borrower.as_slice()],
+    );
+    accumulator_bucket.may_load(repay_address.as_slice())
+}
+
+/// Liquidation accumulators of every borrower
+pub fn read_liquidation_accumulators(
+    storage: &dyn Storage,
+) -> StdResult<Vec<LiquidationAccumulator>> {
+    let accumulator_bucket: ReadonlyBucket<LiquidationAccumulator> =
+        ReadonlyBucket::new(storage, PREFIX_LIQUIDATION_ACCUMULATOR);
+    accumulator_bucket
+        .range(None, None, Order::Ascending)
+        .map(|elem| {
+            let (_, v) = elem?;
+            Ok(v)
+        })
+        .collect()
+}
+
 pub fn read_bids_by_collateral(
     deps: Deps,
     collateral_token: &CanonicalAddr,
-    start_after: Option<CanonicalAddr>,
+    start_after: Option<u64>,
     limit: Option<u32>,
 ) -> StdResult<Vec<BidResponse>> {
     let bid_bucket: ReadonlyBucket<bool> = ReadonlyBucket::multilevel(
@@ -108,7 +397,7 @@ pub fn read_bids_by_collateral(
         &[PREFIX_BID_BY_COLLATERAL, collateral_token.as_slice()],
     );
 
-    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let limit = clamp_limit(limit, DEFAULT_LIMIT, MAX_LIMIT);
     let start = calc_range_start(start_after);
 
     bid_bucket
@@ -116,20 +405,8 @@ pub fn read_bids_by_collateral(
         .take(limit)
         .map(|elem| {
             let (k, _) = elem?;
-            let bidder = CanonicalAddr::from(k);
-            let bid = read_bid(deps.storage, &bidder, collateral_token)?;
-
-            let bidder = deps.api.addr_humanize(&bidder)?.to_string();
-            let collateral_token = deps.api.addr_humanize(collateral_token)?.to_string();
-            let amount = bid.amount;
-            let premium_rate = bid.premium_rate;
-
-            Ok(BidResponse {
-                collateral_token,
-                bidder,
-                amount,
-                premium_rate,
-            })
+            let bid = read_bid(deps.storage, bid_idx_from_key(&k))?;
+            bid_response(deps, bid)
         })
         .collect()
 }
@@ -137,13 +414,23 @@ pub fn read_bids_by_collateral(
 pub fn read_bids_by_user(
     deps: Deps,
     bidder: &CanonicalAddr,
-    start_after: Option<CanonicalAddr>,
+    collateral_token: Option<CanonicalAddr>,
+    start_after: Option<u64>,
     limit: Option<u32>,
 ) -> StdResult<Vec<BidResponse>> {
-    let bid_bucket: ReadonlyBucket<bool> =
-        ReadonlyBucket::multilevel(deps.storage, &[PREFIX_BID_BY_USER, bidder.as_slice()]);
+    let bid_bucket: ReadonlyBucket<bool> = match collateral_token {
+        Some(collateral_token) => ReadonlyBucket::multilevel(
+            deps.storage,
+            &[
+                PREFIX_BID_BY_USER_COLLATERAL,
+                bidder.as_slice(),
+                collateral_token.as_slice(),
+            ],
+        ),
+        None => ReadonlyBucket::multilevel(deps.storage, &[PREFIX_BID_BY_USER, bidder.as_slice()]),
+    };
 
-    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let limit = clamp_limit(limit, DEFAULT_LIMIT, MAX_LIMIT);
     let start = calc_range_start(start_after);
 
     bid_bucket
@@ -151,28 +438,147 @@ pub fn read_bids_by_user(
         .take(limit)
         .map(|elem| {
             let (k, _) = elem?;
-            let collateral_token = CanonicalAddr::from(k);
-            let bid = read_bid(deps.storage, bidder, &collateral_token)?;
-
-            let collateral_token = deps.api.addr_humanize(&collateral_token)?.to_string();
-            let bidder = deps.api.addr_humanize(bidder)?.to_string();
-            let amount = bid.amount;
-            let premium_rate = bid.premium_rate;
-
-            Ok(BidResponse {
-                collateral_token,
-                bidder,
-                amount,
-                premium_rate,
-            })
+            let bid = read_bid(deps.storage, bid_idx_from_key(&k))?;
+            bid_response(deps, bid)
+        })
+        .collect()
+}
+
+/// All bids of the user for the collateral, oldest first
+pub fn read_user_bids(
+    storage: &dyn Storage,
+    bidder: &CanonicalAddr,
+    collateral_token: &CanonicalAddr,
+) -> StdResult<Vec<Bid>> {
+    let bid_bucket: ReadonlyBucket<bool> = ReadonlyBucket::multilevel(
+        storage,
+        &[
+            PREFIX_BID_BY_USER_COLLATERAL,
+            bidder.as_slice(),
+            collateral_token.as_slice(),
+        ],
+    );
+
+    bid_bucket
+        .range(None, None, Order::Ascending)
+        .map(|elem| {
+            let (k, _) = elem?;
+            read_bid(storage, bid_idx_from_key(&k))
         })
         .collect()
 }
 
+/// Bids of the collateral after the given index, oldest first
+pub fn read_collateral_bids(
+    storage: &dyn Storage,
+    collateral_token: &CanonicalAddr,
+    start_after: Option<u64>,
+    limit: usize,
+) -> StdResult<Vec<Bid>> {
+    let bid_bucket: ReadonlyBucket<bool> = ReadonlyBucket::multilevel(
+        storage,
+        &[PREFIX_BID_BY_COLLATERAL, collateral_token.as_slice()],
+    );
+
+    let start = calc_range_start(start_after);
+    bid_bucket
+        .range(start.as_deref(), None, Order::Ascending)
+        .take(limit)
+        .map(|elem| {
+            let (k, _) = elem?;
+            read_bid(storage, bid_idx_from_key(&k))
+        })
+        .collect()
+}
+
+/// Index of the last bid checked by SweepBids for the collateral
+pub fn store_sweep_cursor(
+    storage: &mut dyn Storage,
+    collateral_token: &CanonicalAddr,
+    cursor: Option<u64>,
+) -> StdResult<()> {
+    let mut cursor_bucket: Bucket<u64> = Bucket::new(storage, PREFIX_SWEEP_CURSOR);
+    match cursor {
+        Some(cursor) => cursor_bucket.save(collateral_token.as_slice(), &cursor),
+        None => {
+            cursor_bucket.remove(collateral_token.as_slice());
+            Ok(())
+        }
+    }
+}
+
+pub fn read_sweep_cursor(
+    storage: &dyn Storage,
+    collateral_token: &CanonicalAddr,
+) -> StdResult<Option<u64>> {
+    let cursor_bucket: ReadonlyBucket<u64> = ReadonlyBucket::new(storage, PREFIX_SWEEP_CURSOR);
+    cursor_bucket.may_load(collateral_token.as_slice())
+}
+
+/// Bid pools of the collateral, lowest premium slot first
+pub fn read_bid_pools(
+    storage: &dyn Storage,
+    collateral_token: &CanonicalAddr,
+    start_after: Option<u8>,
+    limit: Option<u32>,
+) -> StdResult<Vec<BidPool>> {
+    let bid_pool_bucket: ReadonlyBucket<BidPool> =
+        ReadonlyBucket::multilevel(storage, &[PREFIX_BID_POOL, collateral_token.as_slice()]);
+
+    let limit = clamp_limit(limit, DEFAULT_LIMIT, MAX_LIMIT);
+    let start = start_after.map(|slot| vec![slot, 1]);
+
+    bid_pool_bucket
+        .range(start.as_deref(), None, Order::Ascending)
+        .take(limit)
+        .map(|elem| {
+            let (_, v) = elem?;
+            Ok(v)
+        })
+        .collect()
+}
+
+/// Every bid pool of the collateral, lowest premium slot first
+pub fn read_all_bid_pools(
+    storage: &dyn Storage,
+    collateral_token: &CanonicalAddr,
+) -> StdResult<Vec<BidPool>> {
+    let bid_pool_bucket: ReadonlyBucket<BidPool> =
+        ReadonlyBucket::multilevel(storage, &[PREFIX_BID_POOL, collateral_token.as_slice()]);
+
+    bid_pool_bucket
+        .range(None, None, Order::Ascending)
+        .map(|elem| {
+            let (_, v) = elem?;
+            Ok(v)
+        })
+        .collect()
+}
+
+pub fn bid_response(deps: Deps, bid: Bid) -> StdResult<BidResponse> {
+    let bid = accrue_bid(deps.storage, bid)?;
+    Ok(BidResponse {
+        idx: bid.idx,
+        collateral_token: deps.api.addr_humanize(&bid.collateral_token)?.to_string(),
+        bidder: deps.api.addr_humanize(&bid.bidder)?.to_string(),
+        amount: bid.amount,
+        premium_slot: bid.premium_slot,
+        pending_liquidated_collateral: bid.pending_liquidated_collateral,
+        wait_end: bid.wait_end,
+        activation_time: bid.activation_time,
+    })
+}
+
+fn bid_idx_from_key(key: &[u8]) -> u64 {
+    let mut idx = [0u8; 8];
+    idx.copy_from_slice(key);
+    u64::from_be_bytes(idx)
+}
+
 // this will set the first key after the provided key, by appending a 1 byte
-fn calc_range_start(start_after: Option<CanonicalAddr>) -> Option<Vec<u8>> {
-    start_after.map(|addr| {
-        let mut v = addr.as_slice().to_vec();
+fn calc_range_start(start_after: Option<u64>) -> Option<Vec<u8>> {
+    start_after.map(|idx| {
+        let mut v = idx.to_be_bytes().to_vec();
         v.push(1);
         v
     })
@@ -0,0 +1,258 @@
+use crate::bid_pool::{accrue_bid, fill_bid_pool, join_bid_pool};
+use crate::state::{read_bid, read_bid_pool, store_bid, Bid};
+
+use cosmwasm_bignumber::{Decimal256, Uint256};
+use cosmwasm_std::testing::MockStorage;
+use cosmwasm_std::{CanonicalAddr, Storage};
+
+const PREMIUM_SLOT: u8 = 1;
+
+fn collateral_token() -> CanonicalAddr {
+    CanonicalAddr::from(vec![1u8; 20])
+}
+
+fn submit(storage: &mut dyn Storage, idx: u64, amount: u128) {
+    join_bid_pool(
+        storage,
+        Bid {
+            idx,
+            collateral_token: collateral_token(),
+            bidder: CanonicalAddr::from(vec![2u8; 20]),
+            amount: Uint256::from(amount),
+            premium_slot: PREMIUM_SLOT,
+            product_snapshot: Decimal256::one(),
+            sum_snapshot: Decimal256::zero(),
+            epoch_snapshot: 0,
+            scale_snapshot: 0,
+            pending_liquidated_collateral: Uint256::zero(),
+            wait_end: None,
+            activation_time: None,
+            last_updated: 0,
+        },
+    )
+    .unwrap();
+}
+
+fn fill(storage: &mut dyn Storage, collateral_amount: u128, stable_amount: u128) {
+    let bid_pool = read_bid_pool(storage, &collateral_token(), PREMIUM_SLOT).unwrap();
+    fill_bid_pool(
+        storage,
+        &collateral_token(),
+        bid_pool,
+        Uint256::from(collateral_amount),
+        Uint256::from(stable_amount),
+    )
+    .unwrap();
+}
+
+fn total_bid_amount(storage: &dyn Storage) -> u128 {
+    read_bid_pool(storage, &collateral_token(), PREMIUM_SLOT)
+        .unwrap()
+        .total_bid_amount
+        .into()
+}
+
+/// (amount, pending_liquidated_collateral) of the accrued bid
+fn bid_state(storage: &dyn Storage, idx: u64) -> (u128, u128) {
+    let bid = accrue_bid(storage, read_bid(storage, idx).unwrap()).unwrap();
+    (bid.amount.into(), bid.pending_liquidated_collateral.into())
+}
+
+/// Takes the pending collateral out of the bid, as ClaimLiquidations does
+fn claim(storage: &mut dyn Storage, idx: u64) -> u128 {
+    let bid = accrue_bid(storage, read_bid(storage, idx).unwrap()).unwrap();
+    store_bid(
+        storage,
+        &Bid {
+            pending_liquidated_collateral: Uint256::zero(),
+            ..bid.clone()
+        },
+    )
+    .unwrap();
+    bid.pending_liquidated_collateral.into()
+}
+
+/// Reference model that walks every bid on each fill
+#[derive(Default)]
+struct NaivePool {
+    // (amount, liquidated collateral) by bid
+    bids: Vec<(f64, f64)>,
+}
+
+impl NaivePool {
+    fn submit(&mut self, amount: u128) {
+        self.bids.push((amount as f64, 0f64));
+    }
+
+    fn fill(&mut self, collateral_amount: u128, stable_amount: u128) {
+        let total: f64 = self.bids.iter().map(|(amount, _)| amount).sum();
+        for (amount, collateral) in self.bids.iter_mut() {
+            *collateral += *amount * collateral_amount as f64 / total;
+            *amount -= *amount * stable_amount as f64 / total;
+        }
+    }
+}
+
+/// The pool rounds down, by no more than a unit of bid and the 1e-9 relative
+/// precision of the product snapshot per fill. A unit of bid is worth up to
+/// the highest collateral per unit of bid paid by a fill.
+fn assert_close(actual: u128, expected: f64, fills: u64, unit: f64) {
+    let tolerance = (fills as f64 + 1f64) * (unit + expected * 1e-9);
+    assert!(
+        actual as f64 <= expected + 1e-3,
+        "{} exceeds the naive {}",
+        actual,
+        expected
+    );
+    assert!(
+        expected - actual as f64 <= tolerance,
+        "{} is too far below the naive {}",
+        actual,
+        expected
+    );
+}
+
+/// Deterministic pseudo random numbers, so failures can be replayed
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self, bound: u64) -> u64 {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (self.0 >> 33) % bound
+    }
+}
+
+#[test]
+fn fills_are_shared_by_amount() {
+    let mut storage = MockStorage::new();
+    submit(&mut storage, 1, 1000000);
+    submit(&mut storage, 2, 3000000);
+
+    fill(&mut storage, 2000000, 1000000);
+    assert_eq!(bid_state(&storage, 1), (750000, 500000));
+    assert_eq!(bid_state(&storage, 2), (2250000, 1500000));
+    assert_eq!(total_bid_amount(&storage), 3000000);
+
+    // a bid joining later only takes part in the later fills
+    submit(&mut storage, 3, 1000000);
+    fill(&mut storage, 800000, 400000);
+    assert_eq!(bid_state(&storage, 1), (675000, 650000));
+    assert_eq!(bid_state(&storage, 2), (2025000, 1950000));
+    assert_eq!(bid_state(&storage, 3), (900000, 200000));
+    assert_eq!(total_bid_amount(&storage), 3600000);
+}
+
+#[test]
+fn exhausted_pool_starts_new_epoch() {
+    let mut storage = MockStorage::new();
+    submit(&mut storage, 1, 1000000);
+    submit(&mut storage, 2, 1000000);
+    fill(&mut storage, 5000000, 2000000);
+
+    let bid_pool = read_bid_pool(&storage, &collateral_token(), PREMIUM_SLOT).unwrap();
+    assert_eq!(bid_pool.current_epoch, 1);
+    assert_eq!(bid_pool.product_snapshot, Decimal256::one());
+    assert_eq!(bid_pool.total_bid_amount, Uint256::zero());
+
+    // bids of the old epoch keep their collateral and miss the new fills
+    submit(&mut storage, 3, 500000);
+    fill(&mut storage, 100000, 250000);
+    assert_eq!(bid_state(&storage, 1), (0, 2500000));
+    assert_eq!(bid_state(&storage, 2), (0, 2500000));
+    assert_eq!(bid_state(&storage, 3), (250000, 100000));
+}
+
+#[test]
+fn small_product_is_rescaled() {
+    let mut storage = MockStorage::new();
+    let mut naive = NaivePool::default();
+    for idx in 1..=2 {
+        submit(&mut storage, idx, 1000000000000000);
+        naive.submit(1000000000000000);
+    }
+
+    // each fill leaves a thousandth of the pool, so the product
+    // drops below 1e-9 on the fourth fill
+    let mut fills = 0u64;
+    while fills < 5 {
+        let total = total_bid_amount(&storage);
+        fill(&mut storage, 1000000, total - total / 1000);
+        naive.fill(1000000, total - total / 1000);
+        fills += 1;
+    }
+
+    let bid_pool = read_bid_pool(&storage, &collateral_token(), PREMIUM_SLOT).unwrap();
+    assert_eq!(bid_pool.current_epoch, 0);
+    assert_eq!(bid_pool.current_scale, 1);
+
+    for (i, (amount, collateral)) in naive.bids.iter().enumerate() {
+        let (actual_amount, actual_collateral) = bid_state(&storage, i as u64 + 1);
+        assert_close(actual_amount, *amount, fills, 1f64);
+        assert_close(actual_collateral, *collateral, fills, 1f64);
+    }
+}
+
+#[test]
+fn random_fills_match_naive_loop() {
+    let mut rng = Lcg(42);
+    for _ in 0..200 {
+        let mut storage = MockStorage::new();
+        let mut naive = NaivePool::default();
+        let mut claimed: Vec<u128> = vec![];
+        let mut fills = 0u64;
+        let mut collateral_per_unit = 1f64;
+
+        for _ in 0..(1 + rng.next(12)) {
+            match rng.next(4) {
+                0 => {
+                    let amount = 1 + rng.next(1000000000) as u128;
+                    submit(&mut storage, naive.bids.len() as u64 + 1, amount);
+                    naive.submit(amount);
+                    claimed.push(0);
+                }
+                1 if !naive.bids.is_empty() => {
+                    let idx = rng.next(naive.bids.len() as u64);
+                    claimed[idx as usize] += claim(&mut storage, idx + 1);
+                }
+                _ => {
+                    let total = total_bid_amount(&storage);
+                    if total == 0 {
+                        continue;
+                    }
+
+                    // a fifth of the fills consume the whole pool
+                    let stable_amount = if rng.next(5) == 0 {
+                        total
+                    } else {
+                        rng.next(total as u64) as u128
+                    };
+                    let collateral_amount = 1 + rng.next(2000000000) as u128;
+                    fill(&mut storage, collateral_amount, stable_amount);
+                    naive.fill(collateral_amount, stable_amount);
+                    fills += 1;
+                    collateral_per_unit =
+                        collateral_per_unit.max(collateral_amount as f64 / total as f64);
+                }
+            }
+        }
+
+        let mut total_amount = 0u128;
+        for (i, (amount, collateral)) in naive.bids.iter().enumerate() {
+            let (actual_amount, pending) = bid_state(&storage, i as u64 + 1);
+            assert_close(actual_amount, *amount, fills, 1f64);
+            assert_close(
+                claimed[i] + pending,
+                *collateral,
+                fills,
+                collateral_per_unit,
+            );
+            total_amount += actual_amount;
+        }
+
+        // the bids never add up to more than their pool
+        assert!(total_amount <= total_bid_amount(&storage));
+    }
+}
@@ -0,0 +1,197 @@
+use crate::liquidation_amount::compute_liquidation_amount;
+
+use cosmwasm_bignumber::{Decimal256, Uint256};
+use cosmwasm_std::StdError;
+
+#[test]
+fn healthy_position() {
+    for borrow_amount in [0u64, 500000u64, 1000000u64] {
+        let res = compute_liquidation_amount(
+            Uint256::from(borrow_amount),
+            Uint256::from(1000000u64),
+            vec![("token0000".to_string(), Uint256::from(2000000u64))],
+            &[Decimal256::one()],
+            Decimal256::percent(90),
+            Decimal256::percent(80),
+            Uint256::zero(),
+        )
+        .unwrap();
+        assert_eq!(res, vec![]);
+    }
+}
+
+#[test]
+fn single_collateral() {
+    // collaterals_value = 1,000,000, expected_repay_amount = 900,000
+    // safe_borrow_amount = 400,000
+    // liquidation_ratio = (600,000 - 400,000) / (900,000 - 400,000) = 0.4
+    let res = compute_liquidation_amount(
+        Uint256::from(600000u64),
+        Uint256::from(500000u64),
+        vec![("token0000".to_string(), Uint256::from(1000000u64))],
+        &[Decimal256::one()],
+        Decimal256::percent(90),
+        Decimal256::percent(80),
+        Uint256::zero(),
+    )
+    .unwrap();
+    assert_eq!(
+        res,
+        vec![("token0000".to_string(), Uint256::from(400000u64))]
+    );
+
+    // the remaining loan is back at the safe ratio of the remaining limit
+    // loan: 600,000 - 400,000 * 0.9 = 240,000
+    // limit: 500,000 * 0.6 * 0.8 = 240,000
+}
+
+#[test]
+fn multi_collateral() {
+    // collaterals_value = 1,000,000 + 1,000,000 + 0 = 2,000,000
+    // expected_repay_amount = 1,800,000, safe_borrow_amount = 800,000
+    // liquidation_ratio = (1,200,000 - 800,000) / (1,800,000 - 800,000) = 0.4
+    let res = compute_liquidation_amount(
+        Uint256::from(1200000u64),
+        Uint256::from(1000000u64),
+        vec![
+            ("token0000".to_string(), Uint256::from(1000000u64)),
+            ("token0001".to_string(), Uint256::from(500000u64)),
+            ("token0002".to_string(), Uint256::from(2u64)),
+        ],
+        &[
+            Decimal256::one(),
+            Decimal256::percent(200),
+            Decimal256::zero(),
+        ],
+        Decimal256::percent(90),
+        Decimal256::percent(80),
+        Uint256::zero(),
+    )
+    .unwrap();
+
+    // token0002 rounds down to zero and is left out
+    assert_eq!(
+        res,
+        vec![
+            ("token0000".to_string(), Uint256::from(400000u64)),
+            ("token0001".to_string(), Uint256::from(200000u64)),
+        ]
+    );
+}
+
+#[test]
+fn unrecoverable_position() {
+    let collaterals = vec![
+        ("token0000".to_string(), Uint256::from(1000000u64)),
+        ("token0001".to_string(), Uint256::from(1000000u64)),
+    ];
+
+    // expected_repay_amount = 900,000 does not exceed the loan
+    for borrow_amount in [900000u64, 950000u64, 10000000u64] {
+        let res = compute_liquidation_amount(
+            Uint256::from(borrow_amount),
+            Uint256::from(500000u64),
+            collaterals.clone(),
+            &[Decimal256::percent(50), Decimal256::percent(50)],
+            Decimal256::percent(90),
+            Decimal256::percent(80),
+            Uint256::zero(),
+        )
+        .unwrap();
+        assert_eq!(res, collaterals);
+    }
+}
+
+#[test]
+fn below_liquidation_threshold() {
+    // collaterals_value = 1,000,000 is below the threshold,
+    // so the whole loan is repaid
+    // liquidation_ratio = 600,000 / 900,000 = 0.666666666666666666
+    let res = compute_liquidation_amount(
+        Uint256::from(600000u64),
+        Uint256::from(500000u64),
+        vec![("token0000".to_string(), Uint256::from(1000000u64))],
+        &[Decimal256::one()],
+        Decimal256::percent(90),
+        Decimal256::percent(80),
+        Uint256::from(2000000u64),
+    )
+    .unwrap();
+    assert_eq!(
+        res,
+        vec![("token0000".to_string(), Uint256::from(666666u64))]
+    );
+
+    // at the threshold the safe ratio applies again
+    let res = compute_liquidation_amount(
+        Uint256::from(600000u64),
+        Uint256::from(500000u64),
+        vec![("token0000".to_string(), Uint256::from(1000000u64))],
+        &[Decimal256::one()],
+        Decimal256::percent(90),
+        Decimal256::percent(80),
+        Uint256::from(1000000u64),
+    )
+    .unwrap();
+    assert_eq!(
+        res,
+        vec![("token0000".to_string(), Uint256::from(400000u64))]
+    );
+}
+
+#[test]
+fn restores_safe_ratio() {
+    let collateral_amount = Uint256::from(1000000u64);
+    let price = Decimal256::percent(150);
+    let fee_deductor = Decimal256::permille(931);
+    let safe_ratio = Decimal256::percent(80);
+
+    // borrow limit at 50% of the collateral value
+    let borrow_limit = Uint256::from(750000u64);
+    for borrow_amount in (750001u64..1390000u64).step_by(12345) {
+        let borrow_amount = Uint256::from(borrow_amount);
+        let res = compute_liquidation_amount(
+            borrow_amount,
+            borrow_limit,
+            vec![("token0000".to_string(), collateral_amount)],
+            &[price],
+            fee_deductor,
+            safe_ratio,
+            Uint256::zero(),
+        )
+        .unwrap();
+
+        let liquidated = res[0].1;
+        let remaining_loan = borrow_amount - liquidated * price * fee_deductor;
+        let remaining_limit =
+            (collateral_amount - liquidated) * price * Decimal256::percent(50) * safe_ratio;
+
+        // each rounded product is off by at most a unit
+        assert!(remaining_loan <= remaining_limit + Uint256::from(3u64));
+        assert!(remaining_loan + Uint256::from(3u64) >= remaining_limit);
+    }
+}
+
+#[test]
+fn mismatched_prices() {
+    let res = compute_liquidation_amount(
+        Uint256::from(600000u64),
+        Uint256::from(500000u64),
+        vec![
+            ("token0000".to_string(), Uint256::from(1000000u64)),
+            ("token0001".to_string(), Uint256::from(1000000u64)),
+        ],
+        &[Decimal256::one()],
+        Decimal256::percent(90),
+        Decimal256::percent(80),
+        Uint256::zero(),
+    );
+
+    match res {
+        Err(StdError::GenericErr { msg, .. }) => assert_eq!(
+            msg,
+            "Collaterals and collateral prices must have the same length"
+        ),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+}
@@ -1,2 +1,3 @@
-mod mock_querier;
+mod bid_pool_ut;
+mod liquidation_amount_ut;
 mod tests;
@@ -1,17 +1,41 @@
-use crate::contract::{execute, instantiate, query};
+use crate::contract::{execute, instantiate, migrate, query};
 use crate::error::ContractError;
-use crate::testing::mock_querier::mock_dependencies;
+use crate::state::{store_escrow, Escrow};
 
 use cosmwasm_bignumber::{Decimal256, Uint256};
-use cosmwasm_std::testing::{mock_env, mock_info};
+use cosmwasm_std::testing::{mock_env, mock_info, MockApi, MockStorage, MOCK_CONTRACT_ADDR};
 use cosmwasm_std::{
-    from_binary, to_binary, BankMsg, Coin, CosmosMsg, Decimal, SubMsg, Uint128, WasmMsg,
+    attr, from_binary, to_binary, BankMsg, Coin, CosmosMsg, Decimal, Deps, Env, OwnedDeps,
+    StdError, SubMsg, Uint128, WasmMsg,
 };
 use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg};
+use moneymarket::error::MoneyMarketError;
 use moneymarket::liquidation::{
-    BidResponse, BidsResponse, ConfigResponse, Cw20HookMsg, ExecuteMsg, InstantiateMsg,
-    LiquidationAmountResponse, QueryMsg,
+    BidPoolResponse, BidPoolsResponse, BidResponse, BidsResponse, CollateralInfoResponse,
+    ConfigResponse, Cw20HookMsg, EscrowHealthResponse, ExecuteMsg, InstantiateMsg,
+    LiquidationAmountResponse, LiquidityDepthResponse, MigrateMsg, QueryMsg,
+    SimulateExecuteResponse, SlotDepthResponse,
 };
+use moneymarket::market::ExecuteMsg as MarketExecuteMsg;
+use moneymarket::overseer::WhitelistResponseElem;
+use moneymarket_mocks::{mock_dependencies, PriceInfo, WasmMockQuerier};
+use std::str::FromStr;
+
+// whitelist elements served by the mocked overseer
+fn whitelist_elems(collateral_tokens: &[&str]) -> Vec<WhitelistResponseElem> {
+    collateral_tokens
+        .iter()
+        .map(|collateral_token| WhitelistResponseElem {
+            name: collateral_token.to_string(),
+            symbol: collateral_token.to_string(),
+            max_ltv: Decimal256::percent(60),
+            decimals: 6,
+            paused: false,
+            custody_contract: "custody0000".to_string(),
+            collateral_token: collateral_token.to_string(),
+        })
+        .collect()
+}
 
 #[test]
 fn proper_initialization() {
@@ -26,10 +50,35 @@ fn proper_initialization() {
         max_premium_rate: Decimal256::percent(5),
         liquidation_threshold: Uint256::from(100000000u64),
         price_timeframe: 60u64,
+        bid_retract_wait: 3600u64,
+        liquidation_fee: Decimal256::percent(1),
+        fee_address: "protocol0000".to_string(),
+        bid_activation_delay: 0u64,
+        inactive_bid_ttl: None,
+        min_bid_amount: Uint256::from(1000u64),
+        dust_threshold: Uint256::from(1000u64),
+        overseer_contract: None,
+        caller_fee: None,
+        market_contract: None,
     };
 
     let info = mock_info("addr0000", &[]);
 
+    // liquidation fee above the bound
+    let res = instantiate(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        InstantiateMsg {
+            liquidation_fee: Decimal256::percent(11),
+            ..msg.clone()
+        },
+    );
+    match res {
+        Err(ContractError::LiquidationFeeExceedsMax(max)) => assert_eq!(max, "0.1"),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
     // we can just call .unwrap() to assert this was a success
     let res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
     assert_eq!(0, res.messages.len());
@@ -48,6 +97,16 @@ fn proper_initialization() {
             max_premium_rate: Decimal256::percent(5),
             liquidation_threshold: Uint256::from(100000000u64),
             price_timeframe: 60u64,
+            bid_retract_wait: 3600u64,
+            liquidation_fee: Decimal256::percent(1),
+            fee_address: "protocol0000".to_string(),
+            bid_activation_delay: 0u64,
+            inactive_bid_ttl: None,
+            min_bid_amount: Uint256::from(1000u64),
+            dust_threshold: Uint256::from(1000u64),
+            overseer_contract: None,
+            caller_fee: Decimal256::zero(),
+            market_contract: None,
         }
     );
 }
@@ -65,6 +124,16 @@ fn update_config() {
         max_premium_rate: Decimal256::percent(5),
         liquidation_threshold: Uint256::from(100000000u64),
         price_timeframe: 60u64,
+        bid_retract_wait: 3600u64,
+        liquidation_fee: Decimal256::zero(),
+        fee_address: "protocol0000".to_string(),
+        bid_activation_delay: 0u64,
+        inactive_bid_ttl: None,
+        min_bid_amount: Uint256::from(1000u64),
+        dust_threshold: Uint256::from(1000u64),
+        overseer_contract: None,
+        caller_fee: None,
+        market_contract: None,
     };
 
     let info = mock_info("addr0000", &[]);
@@ -81,6 +150,16 @@ fn update_config() {
         max_premium_rate: None,
         liquidation_threshold: None,
         price_timeframe: None,
+        bid_retract_wait: None,
+        liquidation_fee: None,
+        fee_address: None,
+        bid_activation_delay: None,
+        inactive_bid_ttl: None,
+        min_bid_amount: None,
+        dust_threshold: None,
+        overseer_contract: None,
+        caller_fee: None,
+        market_contract: None,
     };
 
     let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -100,6 +179,16 @@ fn update_config() {
             max_premium_rate: Decimal256::percent(5),
             liquidation_threshold: Uint256::from(100000000u64),
             price_timeframe: 60u64,
+            bid_retract_wait: 3600u64,
+            liquidation_fee: Decimal256::zero(),
+            fee_address: "protocol0000".to_string(),
+            bid_activation_delay: 0u64,
+            inactive_bid_ttl: None,
+            min_bid_amount: Uint256::from(1000u64),
+            dust_threshold: Uint256::from(1000u64),
+            overseer_contract: None,
+            caller_fee: Decimal256::zero(),
+            market_contract: None,
         }
     );
 
@@ -114,6 +203,16 @@ fn update_config() {
         max_premium_rate: Some(Decimal256::percent(7)),
         liquidation_threshold: Some(Uint256::from(150000000u64)),
         price_timeframe: Some(120u64),
+        bid_retract_wait: Some(7200u64),
+        liquidation_fee: Some(Decimal256::percent(2)),
+        fee_address: Some("protocol0001".to_string()),
+        bid_activation_delay: Some(60u64),
+        inactive_bid_ttl: Some(86400u64),
+        min_bid_amount: Some(Uint256::from(5000u64)),
+        dust_threshold: Some(Uint256::from(2000u64)),
+        overseer_contract: None,
+        caller_fee: None,
+        market_contract: None,
     };
 
     let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -133,9 +232,76 @@ fn update_config() {
             max_premium_rate: Decimal256::percent(7),
             liquidation_threshold: Uint256::from(150000000u64),
             price_timeframe: 120u64,
+            bid_retract_wait: 7200u64,
+            liquidation_fee: Decimal256::percent(2),
+            fee_address: "protocol0001".to_string(),
+            bid_activation_delay: 60u64,
+            inactive_bid_ttl: Some(86400u64),
+            min_bid_amount: Uint256::from(5000u64),
+            dust_threshold: Uint256::from(2000u64),
+            overseer_contract: None,
+            caller_fee: Decimal256::zero(),
+            market_contract: None,
         }
     );
 
+    // a zero ttl disables sweeping inactive bids
+    let info = mock_info("owner0001", &[]);
+    let msg = ExecuteMsg::UpdateConfig {
+        owner: None,
+        oracle_contract: None,
+        stable_denom: None,
+        safe_ratio: None,
+        bid_fee: None,
+        max_premium_rate: None,
+        liquidation_threshold: None,
+        price_timeframe: None,
+        bid_retract_wait: None,
+        liquidation_fee: None,
+        fee_address: None,
+        bid_activation_delay: None,
+        inactive_bid_ttl: Some(0u64),
+        min_bid_amount: None,
+        dust_threshold: None,
+        overseer_contract: None,
+        caller_fee: None,
+        market_contract: None,
+    };
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let value: ConfigResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap()).unwrap();
+    assert_eq!(value.inactive_bid_ttl, None);
+
+    // liquidation fee above the bound
+    let info = mock_info("owner0001", &[]);
+    let msg = ExecuteMsg::UpdateConfig {
+        owner: None,
+        oracle_contract: None,
+        stable_denom: None,
+        safe_ratio: None,
+        bid_fee: None,
+        max_premium_rate: None,
+        liquidation_threshold: None,
+        price_timeframe: None,
+        bid_retract_wait: None,
+        liquidation_fee: Some(Decimal256::percent(11)),
+        fee_address: None,
+        bid_activation_delay: None,
+        inactive_bid_ttl: None,
+        min_bid_amount: None,
+        dust_threshold: None,
+        overseer_contract: None,
+        caller_fee: None,
+        market_contract: None,
+    };
+
+    let res = execute(deps.as_mut(), mock_env(), info, msg);
+    match res {
+        Err(ContractError::LiquidationFeeExceedsMax(_)) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
     // Unauthorized err
     let info = mock_info("owner0000", &[]);
     let msg = ExecuteMsg::UpdateConfig {
@@ -147,6 +313,16 @@ fn update_config() {
         max_premium_rate: Some(Decimal256::percent(7)),
         liquidation_threshold: Some(Uint256::from(150000000u64)),
         price_timeframe: Some(100u64),
+        bid_retract_wait: Some(7200u64),
+        liquidation_fee: None,
+        fee_address: None,
+        bid_activation_delay: None,
+        inactive_bid_ttl: None,
+        min_bid_amount: None,
+        dust_threshold: None,
+        overseer_contract: None,
+        caller_fee: None,
+        market_contract: None,
     };
 
     let res = execute(deps.as_mut(), mock_env(), info, msg);
@@ -157,7 +333,7 @@ fn update_config() {
 }
 
 #[test]
-fn submit_bid() {
+fn whitelist_collateral() {
     let mut deps = mock_dependencies(&[]);
 
     let msg = InstantiateMsg {
@@ -169,67 +345,162 @@ fn submit_bid() {
         max_premium_rate: Decimal256::percent(5),
         liquidation_threshold: Uint256::from(100000000u64),
         price_timeframe: 60u64,
+        bid_retract_wait: 3600u64,
+        liquidation_fee: Decimal256::zero(),
+        fee_address: "protocol0000".to_string(),
+        bid_activation_delay: 0u64,
+        inactive_bid_ttl: None,
+        min_bid_amount: Uint256::from(1000u64),
+        dust_threshold: Uint256::from(1000u64),
+        overseer_contract: None,
+        caller_fee: None,
+        market_contract: None,
     };
 
     let info = mock_info("addr0000", &[]);
-    let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
 
-    let msg = ExecuteMsg::SubmitBid {
+    let msg = ExecuteMsg::WhitelistCollateral {
         collateral_token: "asset0000".to_string(),
-        premium_rate: Decimal256::percent(20),
+        max_slot: 5,
+        premium_rate_per_slot: Decimal256::percent(1),
     };
-    let res = execute(deps.as_mut(), mock_env(), info.clone(), msg);
-    let _premium_string = "0.05".to_string();
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        msg.clone(),
+    );
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("Must return unauthorized error"),
+    }
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner0000", &[]),
+        msg.clone(),
+    )
+    .unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "whitelist_collateral"),
+            attr("collateral_token", "asset0000"),
+            attr("max_slot", "5"),
+            attr("premium_rate_per_slot", "0.01"),
+        ]
+    );
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg);
     match res {
-        Err(ContractError::PremiumExceedsMaxPremium(_premium_string)) => (),
+        Err(ContractError::CollateralAlreadyWhitelisted(_)) => (),
         _ => panic!("DO NOT ENTER HERE"),
     }
 
-    let msg = ExecuteMsg::SubmitBid {
-        collateral_token: "asset0000".to_string(),
-        premium_rate: Decimal256::percent(1),
+    // 3 slots of 2% would exceed the max premium rate
+    let msg = ExecuteMsg::WhitelistCollateral {
+        collateral_token: "asset0001".to_string(),
+        max_slot: 3,
+        premium_rate_per_slot: Decimal256::percent(2),
     };
-    let res = execute(deps.as_mut(), mock_env(), info, msg.clone());
-    let _uusd = "uusd".to_string();
+    let res = execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg);
     match res {
-        Err(ContractError::AssetNotProvided(_uusd)) => (),
+        Err(ContractError::PremiumExceedsMaxPremium(max_premium_rate)) => {
+            assert_eq!(max_premium_rate, "0.05")
+        }
         _ => panic!("DO NOT ENTER HERE"),
     }
 
-    let info = mock_info(
-        "addr0000",
-        &[Coin {
-            denom: "uusd".to_string(),
-            amount: Uint128::from(1000000u128),
-        }],
-    );
-    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    let msg = ExecuteMsg::WhitelistCollateral {
+        collateral_token: "asset0001".to_string(),
+        max_slot: 2,
+        premium_rate_per_slot: Decimal256::percent(2),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg).unwrap();
+
+    for (collateral_token, max_slot, premium_rate_per_slot) in [
+        ("asset0000", 5u8, Decimal256::percent(1)),
+        ("asset0001", 2u8, Decimal256::percent(2)),
+    ] {
+        let collateral_info: CollateralInfoResponse = from_binary(
+            &query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::CollateralInfo {
+                    collateral_token: collateral_token.to_string(),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            collateral_info,
+            CollateralInfoResponse {
+                collateral_token: collateral_token.to_string(),
+                max_slot,
+                premium_rate_per_slot,
+                bids_enabled: true,
+            }
+        );
+
+        // bids above the highest slot are rejected
+        let info = mock_info(
+            "addr0000",
+            &[Coin {
+                denom: "uusd".to_string(),
+                amount: Uint128::from(1000000u128),
+            }],
+        );
+        let msg = ExecuteMsg::SubmitBid {
+            collateral_token: collateral_token.to_string(),
+            premium_slot: max_slot + 1,
+        };
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), msg);
+        match res {
+            Err(ContractError::PremiumSlotExceedsMaxSlot(slot)) => assert_eq!(slot, max_slot),
+            _ => panic!("DO NOT ENTER HERE"),
+        }
+
+        let msg = ExecuteMsg::SubmitBid {
+            collateral_token: collateral_token.to_string(),
+            premium_slot: max_slot,
+        };
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    }
 
-    let bid_response: BidResponse = from_binary(
+    let bid_pool: BidPoolResponse = from_binary(
         &query(
             deps.as_ref(),
             mock_env(),
-            QueryMsg::Bid {
-                bidder: "addr0000".to_string(),
-                collateral_token: "asset0000".to_string(),
+            QueryMsg::BidPool {
+                collateral_token: "asset0001".to_string(),
+                premium_slot: 2,
             },
         )
         .unwrap(),
     )
     .unwrap();
-    assert_eq!(
-        bid_response,
-        BidResponse {
-            collateral_token: "asset0000".to_string(),
-            bidder: "addr0000".to_string(),
-            amount: Uint256::from(1000000u128),
-            premium_rate: Decimal256::percent(1),
-        }
+    assert_eq!(bid_pool.premium_rate, Decimal256::percent(4));
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::CollateralInfo {
+            collateral_token: "asset0002".to_string(),
+        },
     );
+    match res {
+        Err(StdError::GenericErr { msg, .. }) => {
+            assert_eq!(msg, "Collateral is not whitelisted: asset0002")
+        }
+        _ => panic!("DO NOT ENTER HERE"),
+    }
 }
 
 #[test]
-fn retract_bid() {
+fn sync_whitelist() {
     let mut deps = mock_dependencies(&[]);
 
     let msg = InstantiateMsg {
@@ -241,14 +512,118 @@ fn retract_bid() {
         max_premium_rate: Decimal256::percent(5),
         liquidation_threshold: Uint256::from(100000000u64),
         price_timeframe: 60u64,
+        bid_retract_wait: 3600u64,
+        liquidation_fee: Decimal256::zero(),
+        fee_address: "protocol0000".to_string(),
+        bid_activation_delay: 0u64,
+        inactive_bid_ttl: None,
+        min_bid_amount: Uint256::from(1000u64),
+        dust_threshold: Uint256::from(1000u64),
+        overseer_contract: None,
+        caller_fee: None,
+        market_contract: None,
     };
 
     let info = mock_info("addr0000", &[]);
     let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
 
+    let sync_msg = |collateral_tokens: Option<Vec<&str>>| ExecuteMsg::SyncWhitelist {
+        collateral_tokens: collateral_tokens
+            .map(|tokens| tokens.iter().map(|token| token.to_string()).collect()),
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        sync_msg(None),
+    );
+    match res {
+        Err(ContractError::OverseerNotSet {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let msg = ExecuteMsg::UpdateConfig {
+        owner: None,
+        oracle_contract: None,
+        stable_denom: None,
+        safe_ratio: None,
+        bid_fee: None,
+        max_premium_rate: None,
+        liquidation_threshold: None,
+        price_timeframe: None,
+        bid_retract_wait: None,
+        liquidation_fee: None,
+        fee_address: None,
+        bid_activation_delay: None,
+        inactive_bid_ttl: None,
+        min_bid_amount: None,
+        dust_threshold: None,
+        overseer_contract: Some("overseer0000".to_string()),
+        caller_fee: None,
+        market_contract: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::WhitelistCollateral {
+        collateral_token: "asset0000".to_string(),
+        max_slot: 2,
+        premium_rate_per_slot: Decimal256::percent(2),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg).unwrap();
+
+    // more collaterals than a page of the overseer whitelist
+    let mut overseer_whitelist: Vec<String> = vec!["asset0001".to_string()];
+    overseer_whitelist.extend((1000..1031).map(|i| format!("asset{}", i)));
+    let whitelist_refs: Vec<&str> = overseer_whitelist.iter().map(|t| t.as_str()).collect();
+    deps.querier
+        .with_whitelist(&whitelist_elems(&whitelist_refs));
+
+    // anyone can sync; collaterals missing from the overseer stop taking bids
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        sync_msg(None),
+    )
+    .unwrap();
+    let mut expected_attributes = vec![attr("action", "sync_whitelist")];
+    expected_attributes.extend(
+        overseer_whitelist
+            .iter()
+            .map(|token| attr("added_collateral", token)),
+    );
+    expected_attributes.push(attr("disabled_collateral", "asset0000"));
+    assert_eq!(res.attributes, expected_attributes);
+
+    let query_collateral_info = |deps: Deps, collateral_token: &str| -> CollateralInfoResponse {
+        from_binary(
+            &query(
+                deps,
+                mock_env(),
+                QueryMsg::CollateralInfo {
+                    collateral_token: collateral_token.to_string(),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+    };
+
+    // default slots of 1% up to the max premium rate
+    assert_eq!(
+        query_collateral_info(deps.as_ref(), "asset0001"),
+        CollateralInfoResponse {
+            collateral_token: "asset0001".to_string(),
+            max_slot: 5,
+            premium_rate_per_slot: Decimal256::percent(1),
+            bids_enabled: true,
+        }
+    );
+    assert!(!query_collateral_info(deps.as_ref(), "asset0000").bids_enabled);
+
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "asset0000".to_string(),
-        premium_rate: Decimal256::percent(1),
+        premium_slot: 1,
     };
     let info = mock_info(
         "addr0000",
@@ -257,59 +632,66 @@ fn retract_bid() {
             amount: Uint128::from(1000000u128),
         }],
     );
-    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-
-    let msg = ExecuteMsg::RetractBid {
-        collateral_token: "asset0000".to_string(),
-        amount: Some(Uint256::from(1000001u64)),
-    };
-    let info = mock_info("addr0000", &[]);
-    let res = execute(deps.as_mut(), mock_env(), info.clone(), msg);
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), msg.clone());
     match res {
-        Err(ContractError::RetractExceedsBid(1000000)) => (),
+        Err(ContractError::CollateralBidsDisabled(_)) => (),
         _ => panic!("DO NOT ENTER HERE"),
     }
 
-    let msg = ExecuteMsg::RetractBid {
-        collateral_token: "asset0000".to_string(),
-        amount: Some(Uint256::from(500000u64)),
-    };
-    let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+    // listed again, the collateral keeps its own premium slots
+    let mut whitelist_refs = whitelist_refs.clone();
+    whitelist_refs.push("asset0000");
+    deps.querier
+        .with_whitelist(&whitelist_elems(&whitelist_refs));
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        sync_msg(Some(vec!["asset0000"])),
+    )
+    .unwrap();
     assert_eq!(
-        res.messages,
-        vec![SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
-            to_address: "addr0000".to_string(),
-            amount: vec![Coin {
-                denom: "uusd".to_string(),
-                amount: Uint128::from(500000u128),
-            }]
-        }))]
+        res.attributes,
+        vec![
+            attr("action", "sync_whitelist"),
+            attr("enabled_collateral", "asset0000"),
+        ]
+    );
+    assert_eq!(
+        query_collateral_info(deps.as_ref(), "asset0000"),
+        CollateralInfoResponse {
+            collateral_token: "asset0000".to_string(),
+            max_slot: 2,
+            premium_rate_per_slot: Decimal256::percent(2),
+            bids_enabled: true,
+        }
     );
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
 
-    let msg = ExecuteMsg::RetractBid {
-        collateral_token: "asset0000".to_string(),
-        amount: None,
-    };
-    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    // nothing changes for collaterals in sync or unknown to both contracts
+    whitelist_refs.retain(|token| *token != "asset0001");
+    deps.querier
+        .with_whitelist(&whitelist_elems(&whitelist_refs));
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        sync_msg(Some(vec!["asset0000", "asset0001", "asset9999"])),
+    )
+    .unwrap();
     assert_eq!(
-        res.messages,
-        vec![SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
-            to_address: "addr0000".to_string(),
-            amount: vec![Coin {
-                denom: "uusd".to_string(),
-                amount: Uint128::from(500000u128),
-            }]
-        }))]
+        res.attributes,
+        vec![
+            attr("action", "sync_whitelist"),
+            attr("disabled_collateral", "asset0001"),
+        ]
     );
 }
 
 #[test]
-fn execute_bid() {
+fn submit_bid() {
     let mut deps = mock_dependencies(&[]);
-    deps.querier.with_tax(
-        Decimal::percent(1),
-        &[(&"uusd".to_string(), &Uint128::from(1000000u128))],
-    );
+
     let msg = InstantiateMsg {
         owner: "owner0000".to_string(),
         oracle_contract: "oracle0000".to_string(),
@@ -319,25 +701,59 @@ fn execute_bid() {
         max_premium_rate: Decimal256::percent(5),
         liquidation_threshold: Uint256::from(100000000u64),
         price_timeframe: 60u64,
+        bid_retract_wait: 3600u64,
+        liquidation_fee: Decimal256::zero(),
+        fee_address: "protocol0000".to_string(),
+        bid_activation_delay: 0u64,
+        inactive_bid_ttl: None,
+        min_bid_amount: Uint256::from(1000u64),
+        dust_threshold: Uint256::from(1000u64),
+        overseer_contract: None,
+        caller_fee: None,
+        market_contract: None,
     };
 
     let info = mock_info("addr0000", &[]);
-    let env = mock_env();
-    deps.querier.with_oracle_price(&[(
-        &("asset0000".to_string(), "uusd".to_string()),
-        &(
-            Decimal256::percent(50),
-            env.block.time.seconds(),
-            env.block.time.seconds(),
-        ),
-    )]);
+    let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
 
-    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+    let msg = ExecuteMsg::SubmitBid {
+        collateral_token: "asset0000".to_string(),
+        premium_slot: 1,
+    };
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), msg);
+    match res {
+        Err(ContractError::CollateralNotWhitelisted(_)) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let msg = ExecuteMsg::WhitelistCollateral {
+        collateral_token: "asset0000".to_string(),
+        max_slot: 5,
+        premium_rate_per_slot: Decimal256::percent(1),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::SubmitBid {
+        collateral_token: "asset0000".to_string(),
+        premium_slot: 20,
+    };
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), msg);
+    match res {
+        Err(ContractError::PremiumSlotExceedsMaxSlot(5)) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
 
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "asset0000".to_string(),
-        premium_rate: Decimal256::percent(1),
+        premium_slot: 1,
     };
+    let res = execute(deps.as_mut(), mock_env(), info, msg.clone());
+    let _uusd = "uusd".to_string();
+    match res {
+        Err(ContractError::AssetNotProvided(_uusd)) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
     let info = mock_info(
         "addr0000",
         &[Coin {
@@ -345,105 +761,2682 @@ fn execute_bid() {
             amount: Uint128::from(1000000u128),
         }],
     );
-    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "submit_bid"),
+            attr("bid_idx", "1"),
+            attr("collateral_token", "asset0000"),
+            attr("premium_slot", "1"),
+            attr("amount", "1000000"),
+        ]
+    );
+
+    let bid_response: BidResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Bid { bid_idx: 1 }).unwrap())
+            .unwrap();
+    assert_eq!(
+        bid_response,
+        BidResponse {
+            idx: 1,
+            collateral_token: "asset0000".to_string(),
+            bidder: "addr0000".to_string(),
+            amount: Uint256::from(1000000u128),
+            premium_slot: 1,
+            pending_liquidated_collateral: Uint256::zero(),
+            wait_end: None,
+            activation_time: None,
+        }
+    );
+
+    // bids at different premiums land in separate pools
+    for (bidder, premium_slot, amount) in [
+        ("addr0001", 1u8, 2000000u128),
+        ("addr0002", 3u8, 3000000u128),
+    ] {
+        let msg = ExecuteMsg::SubmitBid {
+            collateral_token: "asset0000".to_string(),
+            premium_slot,
+        };
+        let info = mock_info(
+            bidder,
+            &[Coin {
+                denom: "uusd".to_string(),
+                amount: Uint128::from(amount),
+            }],
+        );
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    }
+
+    for (premium_slot, total_bid_amount) in [(1u8, 3000000u64), (3u8, 3000000u64), (2u8, 0u64)] {
+        let bid_pool: BidPoolResponse = from_binary(
+            &query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::BidPool {
+                    collateral_token: "asset0000".to_string(),
+                    premium_slot,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            bid_pool,
+            BidPoolResponse {
+                collateral_token: "asset0000".to_string(),
+                premium_slot,
+                premium_rate: Decimal256::percent(premium_slot as u64),
+                total_bid_amount: Uint256::from(total_bid_amount),
+            }
+        );
+    }
+
+    // bids start at min_bid_amount
+    let msg = ExecuteMsg::SubmitBid {
+        collateral_token: "asset0000".to_string(),
+        premium_slot: 1,
+    };
+    let bid_info = |amount: u128| {
+        mock_info(
+            "addr0003",
+            &[Coin {
+                denom: "uusd".to_string(),
+                amount: Uint128::from(amount),
+            }],
+        )
+    };
+    let res = execute(deps.as_mut(), mock_env(), bid_info(999u128), msg.clone());
+    match res {
+        Err(ContractError::BidAmountTooSmall(1000)) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let res = execute(deps.as_mut(), mock_env(), bid_info(1000u128), msg).unwrap();
+    assert_eq!(res.attributes[1], attr("bid_idx", "4"));
+    assert_eq!(res.attributes[4], attr("amount", "1000"));
+}
+
+#[test]
+fn retract_bid() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        oracle_contract: "oracle0000".to_string(),
+        stable_denom: "uusd".to_string(),
+        safe_ratio: Decimal256::percent(10),
+        bid_fee: Decimal256::percent(1),
+        max_premium_rate: Decimal256::percent(5),
+        liquidation_threshold: Uint256::from(100000000u64),
+        price_timeframe: 60u64,
+        bid_retract_wait: 3600u64,
+        liquidation_fee: Decimal256::zero(),
+        fee_address: "protocol0000".to_string(),
+        bid_activation_delay: 0u64,
+        inactive_bid_ttl: None,
+        min_bid_amount: Uint256::from(1000u64),
+        dust_threshold: Uint256::from(1000u64),
+        overseer_contract: None,
+        caller_fee: None,
+        market_contract: None,
+    };
+
+    let info = mock_info("addr0000", &[]);
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::WhitelistCollateral {
+        collateral_token: "asset0000".to_string(),
+        max_slot: 5,
+        premium_rate_per_slot: Decimal256::percent(1),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::SubmitBid {
+        collateral_token: "asset0000".to_string(),
+        premium_slot: 1,
+    };
+    let info = mock_info(
+        "addr0000",
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::from(1000000u128),
+        }],
+    );
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::RetractBid {
+        bid_idx: 1,
+        amount: Some(Uint256::from(1000001u64)),
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0001", &[]),
+        msg.clone(),
+    );
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let info = mock_info("addr0000", &[]);
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), msg);
+    match res {
+        Err(ContractError::RetractExceedsBid(1000000)) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // the first request only starts the waiting period
+    let env = mock_env();
+    let msg = ExecuteMsg::RetractBid {
+        bid_idx: 1,
+        amount: Some(Uint256::from(500000u64)),
+    };
+    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone()).unwrap();
+    assert_eq!(res.messages, vec![]);
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "retract_bid"),
+            attr("bid_idx", "1"),
+            attr("wait_end", (env.block.time.seconds() + 3600).to_string()),
+        ]
+    );
+
+    let bid_response: BidResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Bid { bid_idx: 1 }).unwrap())
+            .unwrap();
+    assert_eq!(bid_response.wait_end, Some(env.block.time.seconds() + 3600));
+
+    let mut wait_env = env.clone();
+    wait_env.block.time = wait_env.block.time.plus_seconds(3599);
+    let res = execute(deps.as_mut(), wait_env.clone(), info.clone(), msg.clone());
+    match res {
+        Err(ContractError::RetractWaitNotOver(wait_end)) => {
+            assert_eq!(wait_end, env.block.time.seconds() + 3600)
+        }
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // partial retraction after the wait
+    wait_env.block.time = wait_env.block.time.plus_seconds(1);
+    let res = execute(deps.as_mut(), wait_env.clone(), info.clone(), msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: "addr0000".to_string(),
+            amount: vec![Coin {
+                denom: "uusd".to_string(),
+                amount: Uint128::from(500000u128),
+            }]
+        }))]
+    );
+
+    let bid_response: BidResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Bid { bid_idx: 1 }).unwrap())
+            .unwrap();
+    assert_eq!(bid_response.amount, Uint256::from(500000u64));
+    assert_eq!(bid_response.wait_end, None);
+
+    let bid_pool: BidPoolResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::BidPool {
+                collateral_token: "asset0000".to_string(),
+                premium_slot: 1,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(bid_pool.total_bid_amount, Uint256::from(500000u64));
+
+    // the remainder needs its own waiting period
+    let msg = ExecuteMsg::RetractBid {
+        bid_idx: 1,
+        amount: None,
+    };
+    let res = execute(deps.as_mut(), wait_env.clone(), info.clone(), msg.clone()).unwrap();
+    assert_eq!(res.messages, vec![]);
+
+    wait_env.block.time = wait_env.block.time.plus_seconds(3600);
+    let res = execute(deps.as_mut(), wait_env, info, msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: "addr0000".to_string(),
+            amount: vec![Coin {
+                denom: "uusd".to_string(),
+                amount: Uint128::from(500000u128),
+            }]
+        }))]
+    );
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Bid { bid_idx: 1 });
+    match res {
+        Err(StdError::GenericErr { msg, .. }) => {
+            assert_eq!(msg, "No bids with the specified information exist")
+        }
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let bid_pool: BidPoolResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::BidPool {
+                collateral_token: "asset0000".to_string(),
+                premium_slot: 1,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(bid_pool.total_bid_amount, Uint256::zero());
+}
+
+#[test]
+fn execute_bid() {
+    let mut deps = mock_dependencies(&[]);
+    deps.querier.with_tax(
+        Decimal::percent(1),
+        &[(&"uusd".to_string(), &Uint128::from(1000000u128))],
+    );
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        oracle_contract: "oracle0000".to_string(),
+        stable_denom: "uusd".to_string(),
+        safe_ratio: Decimal256::percent(10),
+        bid_fee: Decimal256::percent(1),
+        max_premium_rate: Decimal256::percent(5),
+        liquidation_threshold: Uint256::from(100000000u64),
+        price_timeframe: 60u64,
+        bid_retract_wait: 3600u64,
+        liquidation_fee: Decimal256::percent(1),
+        fee_address: "protocol0000".to_string(),
+        bid_activation_delay: 0u64,
+        inactive_bid_ttl: None,
+        min_bid_amount: Uint256::from(1000u64),
+        dust_threshold: Uint256::from(1000u64),
+        overseer_contract: None,
+        caller_fee: None,
+        market_contract: None,
+    };
+
+    let info = mock_info("addr0000", &[]);
+    let env = mock_env();
+    deps.querier.with_oracle_price(&[(
+        &("asset0000".to_string(), "uusd".to_string()),
+        &PriceInfo {
+            rate: Decimal256::percent(50),
+            last_updated_base: env.block.time.seconds(),
+            last_updated_quote: env.block.time.seconds(),
+        },
+    )]);
+
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::WhitelistCollateral {
+        collateral_token: "asset0000".to_string(),
+        max_slot: 5,
+        premium_rate_per_slot: Decimal256::percent(1),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg).unwrap();
+
+    let execute_msg = |amount: u128| {
+        ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "custody0000".to_string(),
+            amount: Uint128::from(amount),
+            msg: to_binary(&Cw20HookMsg::ExecuteBid {
+                liquidator: "liquidator0000".to_string(),
+                borrower: "borrower0000".to_string(),
+                fee_address: Some("fee0000".to_string()),
+                repay_address: Some("repay0000".to_string()),
+            })
+            .unwrap(),
+        })
+    };
+
+    let info = mock_info("asset0000", &[]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        execute_msg(1000000u128),
+    );
+    match res {
+        Err(ContractError::NoBidExists {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    for (bidder, premium_slot, amount) in [
+        ("addr0000", 3u8, 2000000u128),
+        ("addr0001", 1u8, 1000000u128),
+        ("addr0002", 1u8, 500000u128),
+    ] {
+        let msg = ExecuteMsg::SubmitBid {
+            collateral_token: "asset0000".to_string(),
+            premium_slot,
+        };
+        let info = mock_info(
+            bidder,
+            &[Coin {
+                denom: "uusd".to_string(),
+                amount: Uint128::from(amount),
+            }],
+        );
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    }
+
+    // value = premium + liquidation_fee + repay for each fill
+    // 1% pool at 0.495 pays 1,500,000 for 3,030,303: 1,515,151 = 15,151 + 15,000 + 1,485,000
+    // 3% pool at 0.485 pays   470,303 for   969,697:   484,848 = 14,545 +  4,703 +   465,600
+    // total repay 1,950,600, bid_fee 19,506, repay_amount 1,931,094
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        execute_msg(4000000u128),
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: "repay0000".to_string(),
+                amount: vec![Coin {
+                    denom: "uusd".to_string(),
+                    amount: Uint128::from(1911974u128), // 1931094 / (1 + tax_rate)
+                }]
+            })),
+            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: "fee0000".to_string(),
+                amount: vec![Coin {
+                    denom: "uusd".to_string(),
+                    amount: Uint128::from(19312u128), // 19506 / (1 + tax_rate)
+                }]
+            })),
+            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: "protocol0000".to_string(),
+                amount: vec![Coin {
+                    denom: "uusd".to_string(),
+                    amount: Uint128::from(19507u128), // 19703 / (1 + tax_rate)
+                }]
+            })),
+        ]
+    );
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "execute_bid"),
+            attr("liquidator", "liquidator0000"),
+            attr("borrower", "borrower0000"),
+            attr("stable_denom", "uusd"),
+            attr("repay_amount", "1931094"),
+            attr("bid_fee", "19506"),
+            attr("caller_fee", "0"),
+            attr("liquidation_fee", "19703"),
+            attr("collateral_token", "asset0000"),
+            attr("collateral_amount", "4000000"),
+            attr("returned_collateral_amount", "0"),
+            attr("fill_premium_slot", "1"),
+            attr("fill_premium_rate", "0.01"),
+            attr("fill_collateral_amount", "3030303"),
+            attr("fill_repay_amount", "1485000"),
+            attr("fill_premium_amount", "15151"),
+            attr("fill_liquidation_fee", "15000"),
+            attr("fill_premium_slot", "3"),
+            attr("fill_premium_rate", "0.03"),
+            attr("fill_collateral_amount", "969697"),
+            attr("fill_repay_amount", "465600"),
+            attr("fill_premium_amount", "14545"),
+            attr("fill_liquidation_fee", "4703"),
+        ]
+    );
+
+    // each fill splits its oracle value exactly, the fee is rounded down
+    // so the dust stays in the repayment
+    for fill in res.attributes[11..].chunks(6) {
+        let amount = |i: usize| Uint256::from_str(&fill[i].value).unwrap();
+        assert_eq!(
+            amount(3) + amount(4) + amount(5),
+            amount(2) * Decimal256::percent(50)
+        );
+        assert_eq!(amount(5), (amount(3) + amount(5)) * Decimal256::percent(1));
+    }
+
+    // the bought collateral waits to be claimed, the partially filled bid keeps the rest
+    let bids: BidsResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::BidsByCollateral {
+                collateral_token: "asset0000".to_string(),
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        bids,
+        BidsResponse {
+            bids: vec![
+                BidResponse {
+                    idx: 1,
+                    collateral_token: "asset0000".to_string(),
+                    bidder: "addr0000".to_string(),
+                    amount: Uint256::from(1529697u64),
+                    premium_slot: 3,
+                    pending_liquidated_collateral: Uint256::from(969697u64),
+                    wait_end: None,
+                    activation_time: None,
+                },
+                BidResponse {
+                    idx: 2,
+                    collateral_token: "asset0000".to_string(),
+                    bidder: "addr0001".to_string(),
+                    amount: Uint256::zero(),
+                    premium_slot: 1,
+                    pending_liquidated_collateral: Uint256::from(2020202u64),
+                    wait_end: None,
+                    activation_time: None,
+                },
+                BidResponse {
+                    idx: 3,
+                    collateral_token: "asset0000".to_string(),
+                    bidder: "addr0002".to_string(),
+                    amount: Uint256::zero(),
+                    premium_slot: 1,
+                    pending_liquidated_collateral: Uint256::from(1010101u64),
+                    wait_end: None,
+                    activation_time: None,
+                },
+            ]
+        }
+    );
+
+    // bids run out, the unsold collateral goes back to the borrower
+    // 3% pool pays 1,529,697 for 3,154,014: 1,577,007 = 47,310 + 15,296 + 1,514,401
+    // bid_fee 15,144, repay_amount 1,499,257
+    let res = execute(deps.as_mut(), mock_env(), info, execute_msg(5000000u128)).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: "repay0000".to_string(),
+                amount: vec![Coin {
+                    denom: "uusd".to_string(),
+                    amount: Uint128::from(1484412u128), // 1499257 / (1 + tax_rate)
+                }]
+            })),
+            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: "fee0000".to_string(),
+                amount: vec![Coin {
+                    denom: "uusd".to_string(),
+                    amount: Uint128::from(14994u128), // 15144 / (1 + tax_rate)
+                }]
+            })),
+            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: "protocol0000".to_string(),
+                amount: vec![Coin {
+                    denom: "uusd".to_string(),
+                    amount: Uint128::from(15144u128), // 15296 / (1 + tax_rate)
+                }]
+            })),
+            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "asset0000".to_string(),
+                funds: vec![],
+                msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: "borrower0000".to_string(),
+                    amount: Uint128::from(1845986u128),
+                })
+                .unwrap(),
+            })),
+        ]
+    );
+
+    let bid_pool: BidPoolResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::BidPool {
+                collateral_token: "asset0000".to_string(),
+                premium_slot: 3,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(bid_pool.total_bid_amount, Uint256::zero());
+}
+
+#[test]
+fn execute_bid_price_timeframe() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        oracle_contract: "oracle0000".to_string(),
+        stable_denom: "uusd".to_string(),
+        safe_ratio: Decimal256::percent(10),
+        bid_fee: Decimal256::zero(),
+        max_premium_rate: Decimal256::percent(5),
+        liquidation_threshold: Uint256::from(100000000u64),
+        price_timeframe: 60u64,
+        bid_retract_wait: 0u64,
+        liquidation_fee: Decimal256::zero(),
+        fee_address: "protocol0000".to_string(),
+        bid_activation_delay: 0u64,
+        inactive_bid_ttl: None,
+        min_bid_amount: Uint256::from(1000u64),
+        dust_threshold: Uint256::from(1000u64),
+        overseer_contract: None,
+        caller_fee: None,
+        market_contract: None,
+    };
+
+    let info = mock_info("addr0000", &[]);
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::WhitelistCollateral {
+        collateral_token: "asset0000".to_string(),
+        max_slot: 5,
+        premium_rate_per_slot: Decimal256::percent(1),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::SubmitBid {
+        collateral_token: "asset0000".to_string(),
+        premium_slot: 1,
+    };
+    let info = mock_info(
+        "addr0000",
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::from(1000000u128),
+        }],
+    );
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let execute_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "custody0000".to_string(),
+        amount: Uint128::from(100000u128),
+        msg: to_binary(&Cw20HookMsg::ExecuteBid {
+            liquidator: "liquidator0000".to_string(),
+            borrower: "borrower0000".to_string(),
+            fee_address: None,
+            repay_address: None,
+        })
+        .unwrap(),
+    });
+
+    // a price older than price_timeframe aborts the execution
+    let env = mock_env();
+    deps.querier.with_oracle_price(&[(
+        &("asset0000".to_string(), "uusd".to_string()),
+        &PriceInfo {
+            rate: Decimal256::percent(50),
+            last_updated_base: env.block.time.seconds() - 61,
+            last_updated_quote: env.block.time.seconds(),
+        },
+    )]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("asset0000", &[]),
+        execute_msg.clone(),
+    );
+    match res {
+        Err(ContractError::MoneyMarket(MoneyMarketError::PriceStale { asset, age })) => {
+            assert_eq!(asset, "asset0000");
+            assert_eq!(age, 61);
+        }
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // the pool is left untouched
+    let bid: BidResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Bid { bid_idx: 1 }).unwrap())
+            .unwrap();
+    assert_eq!(bid.amount, Uint256::from(1000000u64));
+
+    // a price at the edge of the timeframe is still valid
+    deps.querier.with_oracle_price(&[(
+        &("asset0000".to_string(), "uusd".to_string()),
+        &PriceInfo {
+            rate: Decimal256::percent(50),
+            last_updated_base: env.block.time.seconds() - 60,
+            last_updated_quote: env.block.time.seconds() - 60,
+        },
+    )]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("asset0000", &[]),
+        execute_msg,
+    )
+    .unwrap();
+    assert_eq!(res.attributes[9], attr("collateral_amount", "100000"));
+}
+
+#[test]
+fn execute_bid_caller_fee() {
+    let mut deps = mock_dependencies(&[]);
+
+    let mut msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        oracle_contract: "oracle0000".to_string(),
+        stable_denom: "uusd".to_string(),
+        safe_ratio: Decimal256::percent(10),
+        bid_fee: Decimal256::percent(1),
+        max_premium_rate: Decimal256::percent(5),
+        liquidation_threshold: Uint256::from(100000000u64),
+        price_timeframe: 60u64,
+        bid_retract_wait: 0u64,
+        liquidation_fee: Decimal256::zero(),
+        fee_address: "protocol0000".to_string(),
+        bid_activation_delay: 0u64,
+        inactive_bid_ttl: None,
+        min_bid_amount: Uint256::from(1000u64),
+        dust_threshold: Uint256::from(1000u64),
+        overseer_contract: None,
+        caller_fee: Some(Decimal256::percent(6)),
+        market_contract: None,
+    };
+
+    // the caller fee is bounded
+    let res = instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        msg.clone(),
+    );
+    match res {
+        Err(ContractError::CallerFeeExceedsMax(max)) => assert_eq!(max, "0.05"),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // together with the bid fee it cannot take the whole repayment
+    msg.caller_fee = Some(Decimal256::percent(2));
+    msg.bid_fee = Decimal256::percent(98);
+    let res = instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        msg.clone(),
+    );
+    match res {
+        Err(ContractError::InvalidRepayFees {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    msg.bid_fee = Decimal256::percent(1);
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+
+    let update_bid_fee = |bid_fee| ExecuteMsg::UpdateConfig {
+        owner: None,
+        oracle_contract: None,
+        stable_denom: None,
+        safe_ratio: None,
+        bid_fee: Some(bid_fee),
+        max_premium_rate: None,
+        liquidation_threshold: None,
+        price_timeframe: None,
+        bid_retract_wait: None,
+        liquidation_fee: None,
+        fee_address: None,
+        bid_activation_delay: None,
+        inactive_bid_ttl: None,
+        min_bid_amount: None,
+        dust_threshold: None,
+        overseer_contract: None,
+        caller_fee: None,
+        market_contract: None,
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner0000", &[]),
+        update_bid_fee(Decimal256::percent(98)),
+    );
+    match res {
+        Err(ContractError::InvalidRepayFees {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner0000", &[]),
+        update_bid_fee(Decimal256::percent(1)),
+    )
+    .unwrap();
+
+    let msg = ExecuteMsg::WhitelistCollateral {
+        collateral_token: "asset0000".to_string(),
+        max_slot: 5,
+        premium_rate_per_slot: Decimal256::percent(1),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg).unwrap();
+
+    let env = mock_env();
+    deps.querier.with_oracle_price(&[(
+        &("asset0000".to_string(), "uusd".to_string()),
+        &PriceInfo {
+            rate: Decimal256::one(),
+            last_updated_base: env.block.time.seconds(),
+            last_updated_quote: env.block.time.seconds(),
+        },
+    )]);
+
+    let msg = ExecuteMsg::SubmitBid {
+        collateral_token: "asset0000".to_string(),
+        premium_slot: 1,
+    };
+    let info = mock_info(
+        "addr0000",
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::from(1000000u128),
+        }],
+    );
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // 99000 paid for the collateral: 990 of bid fee and 1980 to the caller
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "custody0000".to_string(),
+        amount: Uint128::from(100000u128),
+        msg: to_binary(&Cw20HookMsg::ExecuteBid {
+            liquidator: "liquidator0000".to_string(),
+            borrower: "borrower0000".to_string(),
+            fee_address: Some("fee0000".to_string()),
+            repay_address: Some("repay0000".to_string()),
+        })
+        .unwrap(),
+    });
+    let res = execute(deps.as_mut(), mock_env(), mock_info("asset0000", &[]), msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: "repay0000".to_string(),
+                amount: vec![Coin {
+                    denom: "uusd".to_string(),
+                    amount: Uint128::from(96030u128),
+                }],
+            })),
+            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: "fee0000".to_string(),
+                amount: vec![Coin {
+                    denom: "uusd".to_string(),
+                    amount: Uint128::from(990u128),
+                }],
+            })),
+            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: "liquidator0000".to_string(),
+                amount: vec![Coin {
+                    denom: "uusd".to_string(),
+                    amount: Uint128::from(1980u128),
+                }],
+            })),
+        ]
+    );
+    assert_eq!(res.attributes[4], attr("repay_amount", "96030"));
+    assert_eq!(res.attributes[6], attr("caller_fee", "1980"));
+
+    let msg = ExecuteMsg::UpdateConfig {
+        owner: None,
+        oracle_contract: None,
+        stable_denom: None,
+        safe_ratio: None,
+        bid_fee: None,
+        max_premium_rate: None,
+        liquidation_threshold: None,
+        price_timeframe: None,
+        bid_retract_wait: None,
+        liquidation_fee: None,
+        fee_address: None,
+        bid_activation_delay: None,
+        inactive_bid_ttl: None,
+        min_bid_amount: None,
+        dust_threshold: None,
+        overseer_contract: None,
+        caller_fee: Some(Decimal256::percent(6)),
+        market_contract: None,
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg);
+    match res {
+        Err(ContractError::CallerFeeExceedsMax(_)) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+}
+
+#[test]
+fn execute_bid_repays_market() {
+    let mut deps = mock_dependencies(&[]);
+    deps.querier.with_tax(
+        Decimal::percent(1),
+        &[(&"uusd".to_string(), &Uint128::from(1000000u128))],
+    );
+
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        oracle_contract: "oracle0000".to_string(),
+        stable_denom: "uusd".to_string(),
+        safe_ratio: Decimal256::percent(10),
+        bid_fee: Decimal256::zero(),
+        max_premium_rate: Decimal256::percent(5),
+        liquidation_threshold: Uint256::from(100000000u64),
+        price_timeframe: 60u64,
+        bid_retract_wait: 0u64,
+        liquidation_fee: Decimal256::zero(),
+        fee_address: "protocol0000".to_string(),
+        bid_activation_delay: 0u64,
+        inactive_bid_ttl: None,
+        min_bid_amount: Uint256::from(1000u64),
+        dust_threshold: Uint256::from(1000u64),
+        overseer_contract: None,
+        caller_fee: None,
+        market_contract: Some("market0000".to_string()),
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::WhitelistCollateral {
+        collateral_token: "asset0000".to_string(),
+        max_slot: 5,
+        premium_rate_per_slot: Decimal256::percent(1),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg).unwrap();
+
+    let env = mock_env();
+    deps.querier.with_oracle_price(&[(
+        &("asset0000".to_string(), "uusd".to_string()),
+        &PriceInfo {
+            rate: Decimal256::percent(50),
+            last_updated_base: env.block.time.seconds(),
+            last_updated_quote: env.block.time.seconds(),
+        },
+    )]);
+
+    let msg = ExecuteMsg::SubmitBid {
+        collateral_token: "asset0000".to_string(),
+        premium_slot: 0,
+    };
+    let info = mock_info(
+        "addr0000",
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::from(2000000u128),
+        }],
+    );
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let execute_msg = |repay_address: &str| {
+        ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "custody0000".to_string(),
+            amount: Uint128::from(1000000u128),
+            msg: to_binary(&Cw20HookMsg::ExecuteBid {
+                liquidator: "liquidator0000".to_string(),
+                borrower: "borrower0000".to_string(),
+                fee_address: None,
+                repay_address: Some(repay_address.to_string()),
+            })
+            .unwrap(),
+        })
+    };
+
+    // 500,000 repays the borrower's loan with the tax taken out
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("asset0000", &[]),
+        execute_msg("market0000"),
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "market0000".to_string(),
+            funds: vec![Coin {
+                denom: "uusd".to_string(),
+                amount: Uint128::from(495049u128),
+            }],
+            msg: to_binary(&MarketExecuteMsg::RepayStableFromLiquidation {
+                borrower: "borrower0000".to_string(),
+            })
+            .unwrap(),
+        }))]
+    );
+
+    // other repay addresses are sent the stable
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("asset0000", &[]),
+        execute_msg("repay0000"),
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: "repay0000".to_string(),
+            amount: vec![Coin {
+                denom: "uusd".to_string(),
+                amount: Uint128::from(495049u128),
+            }],
+        }))]
+    );
+}
+
+#[test]
+fn simulate_execute() {
+    let mut deps = mock_dependencies(&[]);
+    deps.querier.with_tax(
+        Decimal::percent(1),
+        &[(&"uusd".to_string(), &Uint128::from(1000000u128))],
+    );
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        oracle_contract: "oracle0000".to_string(),
+        stable_denom: "uusd".to_string(),
+        safe_ratio: Decimal256::percent(10),
+        bid_fee: Decimal256::percent(1),
+        max_premium_rate: Decimal256::percent(5),
+        liquidation_threshold: Uint256::from(100000000u64),
+        price_timeframe: 60u64,
+        bid_retract_wait: 3600u64,
+        liquidation_fee: Decimal256::percent(1),
+        fee_address: "protocol0000".to_string(),
+        bid_activation_delay: 0u64,
+        inactive_bid_ttl: None,
+        min_bid_amount: Uint256::from(1000u64),
+        dust_threshold: Uint256::from(1000u64),
+        overseer_contract: None,
+        caller_fee: None,
+        market_contract: None,
+    };
+
+    let info = mock_info("addr0000", &[]);
+    let env = mock_env();
+    deps.querier.with_oracle_price(&[(
+        &("asset0000".to_string(), "uusd".to_string()),
+        &PriceInfo {
+            rate: Decimal256::percent(50),
+            last_updated_base: env.block.time.seconds(),
+            last_updated_quote: env.block.time.seconds(),
+        },
+    )]);
+
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::WhitelistCollateral {
+        collateral_token: "asset0000".to_string(),
+        max_slot: 5,
+        premium_rate_per_slot: Decimal256::percent(1),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg).unwrap();
+
+    let simulate = |deps: Deps, amount: u64| -> SimulateExecuteResponse {
+        from_binary(
+            &query(
+                deps,
+                mock_env(),
+                QueryMsg::SimulateExecute {
+                    collateral_token: "asset0000".to_string(),
+                    collateral_amount: Uint256::from(amount),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+    };
+
+    // without bids all the collateral would be returned
+    assert_eq!(
+        simulate(deps.as_ref(), 1000000u64),
+        SimulateExecuteResponse {
+            collateral_amount: Uint256::zero(),
+            returned_collateral_amount: Uint256::from(1000000u64),
+            repay_amount: Uint256::zero(),
+            bid_fee: Uint256::zero(),
+            caller_fee: Uint256::zero(),
+            liquidation_fee: Uint256::zero(),
+            filled_bid_pools: 0,
+        }
+    );
+
+    for (bidder, premium_slot, amount) in [
+        ("addr0000", 3u8, 2000000u128),
+        ("addr0001", 1u8, 1000000u128),
+        ("addr0002", 1u8, 500000u128),
+    ] {
+        let msg = ExecuteMsg::SubmitBid {
+            collateral_token: "asset0000".to_string(),
+            premium_slot,
+        };
+        let info = mock_info(
+            bidder,
+            &[Coin {
+                denom: "uusd".to_string(),
+                amount: Uint128::from(amount),
+            }],
+        );
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    }
+
+    // the second execution exhausts the bids and returns collateral
+    for (amount, filled_bid_pools) in [(4000000u64, 2u32), (5000000u64, 1u32)] {
+        let simulation = simulate(deps.as_ref(), amount);
+        assert_eq!(simulation.filled_bid_pools, filled_bid_pools);
+
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "custody0000".to_string(),
+            amount: Uint128::from(amount),
+            msg: to_binary(&Cw20HookMsg::ExecuteBid {
+                liquidator: "liquidator0000".to_string(),
+                borrower: "borrower0000".to_string(),
+                fee_address: Some("fee0000".to_string()),
+                repay_address: Some("repay0000".to_string()),
+            })
+            .unwrap(),
+        });
+        let res = execute(deps.as_mut(), mock_env(), mock_info("asset0000", &[]), msg).unwrap();
+        let executed = |key: &str| {
+            let attribute = res.attributes.iter().find(|a| a.key == key).unwrap();
+            Uint256::from_str(&attribute.value).unwrap()
+        };
+        assert_eq!(
+            simulation,
+            SimulateExecuteResponse {
+                collateral_amount: executed("collateral_amount"),
+                returned_collateral_amount: executed("returned_collateral_amount"),
+                repay_amount: executed("repay_amount"),
+                bid_fee: executed("bid_fee"),
+                caller_fee: executed("caller_fee"),
+                liquidation_fee: executed("liquidation_fee"),
+                filled_bid_pools,
+            }
+        );
+    }
+    assert_eq!(
+        simulate(deps.as_ref(), 5000000u64).returned_collateral_amount,
+        Uint256::from(5000000u64)
+    );
+}
+
+#[test]
+fn execute_bid_dust() {
+    let mut deps = mock_dependencies(&[]);
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        oracle_contract: "oracle0000".to_string(),
+        stable_denom: "uusd".to_string(),
+        safe_ratio: Decimal256::percent(10),
+        bid_fee: Decimal256::zero(),
+        max_premium_rate: Decimal256::percent(5),
+        liquidation_threshold: Uint256::from(100000000u64),
+        price_timeframe: 60u64,
+        bid_retract_wait: 3600u64,
+        liquidation_fee: Decimal256::zero(),
+        fee_address: "protocol0000".to_string(),
+        bid_activation_delay: 0u64,
+        inactive_bid_ttl: None,
+        min_bid_amount: Uint256::from(1000u64),
+        dust_threshold: Uint256::from(1000u64),
+        overseer_contract: None,
+        caller_fee: None,
+        market_contract: None,
+    };
+
+    let info = mock_info("addr0000", &[]);
+    let env = mock_env();
+    deps.querier.with_oracle_price(&[(
+        &("asset0000".to_string(), "uusd".to_string()),
+        &PriceInfo {
+            rate: Decimal256::one(),
+            last_updated_base: env.block.time.seconds(),
+            last_updated_quote: env.block.time.seconds(),
+        },
+    )]);
+
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::WhitelistCollateral {
+        collateral_token: "asset0000".to_string(),
+        max_slot: 5,
+        premium_rate_per_slot: Decimal256::percent(1),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg).unwrap();
+
+    for (bidder, amount) in [("addr0000", 600000u128), ("addr0001", 400000u128)] {
+        let msg = ExecuteMsg::SubmitBid {
+            collateral_token: "asset0000".to_string(),
+            premium_slot: 0,
+        };
+        let info = mock_info(
+            bidder,
+            &[Coin {
+                denom: "uusd".to_string(),
+                amount: Uint128::from(amount),
+            }],
+        );
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    }
+
+    // 999,500 at 1 would leave 500 in the pool, below the dust threshold,
+    // so the fill takes the whole 1,000,000 and closes both bids
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "custody0000".to_string(),
+        amount: Uint128::from(999500u128),
+        msg: to_binary(&Cw20HookMsg::ExecuteBid {
+            liquidator: "liquidator0000".to_string(),
+            borrower: "borrower0000".to_string(),
+            fee_address: Some("fee0000".to_string()),
+            repay_address: Some("repay0000".to_string()),
+        })
+        .unwrap(),
+    });
+    let res = execute(deps.as_mut(), mock_env(), mock_info("asset0000", &[]), msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: "repay0000".to_string(),
+            amount: vec![Coin {
+                denom: "uusd".to_string(),
+                amount: Uint128::from(1000000u128),
+            }]
+        }))]
+    );
+    assert_eq!(
+        res.attributes[11..],
+        vec![
+            attr("fill_premium_slot", "0"),
+            attr("fill_premium_rate", "0"),
+            attr("fill_collateral_amount", "999500"),
+            attr("fill_repay_amount", "1000000"),
+            attr("fill_premium_amount", "0"),
+            attr("fill_liquidation_fee", "0"),
+        ]
+    );
+
+    for (bid_idx, pending_liquidated_collateral) in [(1u64, 599700u64), (2u64, 399800u64)] {
+        let bid: BidResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Bid { bid_idx }).unwrap())
+                .unwrap();
+        assert_eq!(bid.amount, Uint256::zero());
+        assert_eq!(
+            bid.pending_liquidated_collateral,
+            Uint256::from(pending_liquidated_collateral)
+        );
+    }
+}
+
+#[test]
+fn settle_liquidation() {
+    let mut deps = mock_dependencies(&[]);
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        oracle_contract: "oracle0000".to_string(),
+        stable_denom: "uusd".to_string(),
+        safe_ratio: Decimal256::percent(10),
+        bid_fee: Decimal256::percent(1),
+        max_premium_rate: Decimal256::percent(5),
+        liquidation_threshold: Uint256::from(100000000u64),
+        price_timeframe: 60u64,
+        bid_retract_wait: 3600u64,
+        liquidation_fee: Decimal256::percent(1),
+        fee_address: "protocol0000".to_string(),
+        bid_activation_delay: 0u64,
+        inactive_bid_ttl: None,
+        min_bid_amount: Uint256::from(1000u64),
+        dust_threshold: Uint256::from(1000u64),
+        overseer_contract: None,
+        caller_fee: None,
+        market_contract: None,
+    };
+
+    let info = mock_info("addr0000", &[]);
+    let env = mock_env();
+    deps.querier.with_oracle_price(&[
+        (
+            &("asset0000".to_string(), "uusd".to_string()),
+            &PriceInfo {
+                rate: Decimal256::percent(50),
+                last_updated_base: env.block.time.seconds(),
+                last_updated_quote: env.block.time.seconds(),
+            },
+        ),
+        (
+            &("asset0001".to_string(), "uusd".to_string()),
+            &PriceInfo {
+                rate: Decimal256::one(),
+                last_updated_base: env.block.time.seconds(),
+                last_updated_quote: env.block.time.seconds(),
+            },
+        ),
+    ]);
+
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    for (collateral_token, bidder) in [("asset0000", "addr0000"), ("asset0001", "addr0001")] {
+        let msg = ExecuteMsg::WhitelistCollateral {
+            collateral_token: collateral_token.to_string(),
+            max_slot: 5,
+            premium_rate_per_slot: Decimal256::percent(1),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg).unwrap();
+
+        let msg = ExecuteMsg::SubmitBid {
+            collateral_token: collateral_token.to_string(),
+            premium_slot: 0,
+        };
+        let info = mock_info(
+            bidder,
+            &[Coin {
+                denom: "uusd".to_string(),
+                amount: Uint128::from(1000000u128),
+            }],
+        );
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    }
+
+    let accumulate_msg = |amount: u128, fee_address: &str| {
+        ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "custody0000".to_string(),
+            amount: Uint128::from(amount),
+            msg: to_binary(&Cw20HookMsg::AccumulateBid {
+                liquidator: "liquidator0000".to_string(),
+                borrower: "borrower0000".to_string(),
+                fee_address: Some(fee_address.to_string()),
+                repay_address: Some("repay0000".to_string()),
+            })
+            .unwrap(),
+        })
+    };
+    let settle_msg = |collaterals: Vec<(&str, u64)>| ExecuteMsg::SettleLiquidation {
+        borrower: "borrower0000".to_string(),
+        repay_address: Some("repay0000".to_string()),
+        collaterals: collaterals
+            .into_iter()
+            .map(|(token, amount)| (token.to_string(), Uint256::from(amount)))
+            .collect(),
+    };
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("overseer0000", &[]),
+        settle_msg(vec![]),
+    );
+    match res {
+        Err(ContractError::NoLiquidationToSettle {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // 1,000,000 at 0.5 pays 500,000: 5,000 liquidation fee and 495,000 to repay
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("asset0000", &[]),
+        accumulate_msg(1000000u128, "fee0000"),
+    )
+    .unwrap();
+    assert_eq!(res.messages, vec![]);
+    assert_eq!(
+        res.attributes[..8],
+        vec![
+            attr("action", "accumulate_bid"),
+            attr("liquidator", "liquidator0000"),
+            attr("borrower", "borrower0000"),
+            attr("repay_amount", "495000"),
+            attr("liquidation_fee", "5000"),
+            attr("collateral_token", "asset0000"),
+            attr("collateral_amount", "1000000"),
+            attr("returned_collateral_amount", "0"),
+        ]
+    );
+
+    // the fee address is fixed by the first collateral
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("asset0001", &[]),
+        accumulate_msg(3000000u128, "fee0001"),
+    );
+    match res {
+        Err(ContractError::AccumulatorMismatch {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // the bid buys 1,000,000 at 1 for 990,000 and 10,000 liquidation fee,
+    // the other 2,000,000 go back to the borrower
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("asset0001", &[]),
+        accumulate_msg(3000000u128, "fee0000"),
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "asset0001".to_string(),
+            funds: vec![],
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: "borrower0000".to_string(),
+                amount: Uint128::from(2000000u128),
+            })
+            .unwrap(),
+        }))]
+    );
+
+    // every collateral sent for liquidation must be settled
+    for collaterals in [
+        vec![("asset0000", 1000000u64)],
+        vec![("asset0000", 1000000u64), ("asset0001", 1000000u64)],
+    ] {
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("overseer0000", &[]),
+            settle_msg(collaterals),
+        );
+        match res {
+            Err(ContractError::LiquidationMismatch {}) => (),
+            _ => panic!("DO NOT ENTER HERE"),
+        }
+    }
+
+    // total repay 1,485,000, bid_fee 14,850, repay_amount 1,470,150
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("overseer0000", &[]),
+        settle_msg(vec![("asset0001", 3000000u64), ("asset0000", 1000000u64)]),
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: "repay0000".to_string(),
+                amount: vec![Coin {
+                    denom: "uusd".to_string(),
+                    amount: Uint128::from(1470150u128),
+                }]
+            })),
+            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: "fee0000".to_string(),
+                amount: vec![Coin {
+                    denom: "uusd".to_string(),
+                    amount: Uint128::from(14850u128),
+                }]
+            })),
+            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: "protocol0000".to_string(),
+                amount: vec![Coin {
+                    denom: "uusd".to_string(),
+                    amount: Uint128::from(15000u128),
+                }]
+            })),
+        ]
+    );
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "settle_liquidation"),
+            attr("borrower", "borrower0000"),
+            attr("stable_denom", "uusd"),
+            attr("repay_amount", "1470150"),
+            attr("bid_fee", "14850"),
+            attr("caller_fee", "0"),
+            attr("liquidation_fee", "15000"),
+            attr("collateral_token", "asset0000"),
+            attr("collateral_amount", "1000000"),
+            attr("returned_collateral_amount", "0"),
+            attr("collateral_repay_amount", "495000"),
+            attr("collateral_token", "asset0001"),
+            attr("collateral_amount", "1000000"),
+            attr("returned_collateral_amount", "2000000"),
+            attr("collateral_repay_amount", "990000"),
+        ]
+    );
+
+    // the liquidation is settled only once
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("overseer0000", &[]),
+        settle_msg(vec![("asset0000", 1000000u64), ("asset0001", 3000000u64)]),
+    );
+    match res {
+        Err(ContractError::NoLiquidationToSettle {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+}
+
+#[test]
+fn claim_liquidations() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        oracle_contract: "oracle0000".to_string(),
+        stable_denom: "uusd".to_string(),
+        safe_ratio: Decimal256::percent(10),
+        bid_fee: Decimal256::zero(),
+        max_premium_rate: Decimal256::percent(5),
+        liquidation_threshold: Uint256::from(100000000u64),
+        price_timeframe: 60u64,
+        bid_retract_wait: 0u64,
+        liquidation_fee: Decimal256::zero(),
+        fee_address: "protocol0000".to_string(),
+        bid_activation_delay: 0u64,
+        inactive_bid_ttl: None,
+        min_bid_amount: Uint256::from(1000u64),
+        dust_threshold: Uint256::from(1000u64),
+        overseer_contract: None,
+        caller_fee: None,
+        market_contract: None,
+    };
+
+    let env = mock_env();
+    deps.querier.with_oracle_price(&[(
+        &("asset0000".to_string(), "uusd".to_string()),
+        &PriceInfo {
+            rate: Decimal256::percent(50),
+            last_updated_base: env.block.time.seconds(),
+            last_updated_quote: env.block.time.seconds(),
+        },
+    )]);
+
+    let info = mock_info("addr0000", &[]);
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::WhitelistCollateral {
+        collateral_token: "asset0000".to_string(),
+        max_slot: 5,
+        premium_rate_per_slot: Decimal256::percent(1),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg).unwrap();
+
+    for (bidder, amount) in [("addr0000", 1000000u128), ("addr0001", 500000u128)] {
+        let msg = ExecuteMsg::SubmitBid {
+            collateral_token: "asset0000".to_string(),
+            premium_slot: 1,
+        };
+        let info = mock_info(
+            bidder,
+            &[Coin {
+                denom: "uusd".to_string(),
+                amount: Uint128::from(amount),
+            }],
+        );
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    }
+
+    let claim_msg = |bids_idx: Option<Vec<u64>>| ExecuteMsg::ClaimLiquidations {
+        collateral_token: "asset0000".to_string(),
+        bids_idx,
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        claim_msg(None),
+    );
+    match res {
+        Err(ContractError::NothingToClaim {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let execute_msg = |amount: u128| {
+        ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "custody0000".to_string(),
+            amount: Uint128::from(amount),
+            msg: to_binary(&Cw20HookMsg::ExecuteBid {
+                liquidator: "liquidator0000".to_string(),
+                borrower: "borrower0000".to_string(),
+                fee_address: None,
+                repay_address: None,
+            })
+            .unwrap(),
+        })
+    };
+
+    // the pool pays 1,485,000 for 3,000,000, shared 2:1 between the bids
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("asset0000", &[]),
+        execute_msg(3000000u128),
+    )
+    .unwrap();
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0001", &[]),
+        claim_msg(Some(vec![1])),
+    );
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        ExecuteMsg::ClaimLiquidations {
+            collateral_token: "asset0001".to_string(),
+            bids_idx: Some(vec![1]),
+        },
+    );
+    match res {
+        Err(ContractError::BidCollateralMismatch(1)) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        claim_msg(None),
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "asset0000".to_string(),
+            funds: vec![],
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: "addr0000".to_string(),
+                amount: Uint128::from(2000000u128),
+            })
+            .unwrap(),
+        }))]
+    );
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "claim_liquidations"),
+            attr("collateral_token", "asset0000"),
+            attr("bidder", "addr0000"),
+            attr("collateral_amount", "2000000"),
+        ]
+    );
+
+    // the claimed bid keeps its unfilled amount
+    let bid: BidResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Bid { bid_idx: 1 }).unwrap())
+            .unwrap();
+    assert_eq!(bid.amount, Uint256::from(10000u64));
+    assert_eq!(bid.pending_liquidated_collateral, Uint256::zero());
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        claim_msg(None),
+    );
+    match res {
+        Err(ContractError::NothingToClaim {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // with nothing left to claim, retracting the rest removes the bid
+    let msg = ExecuteMsg::RetractBid {
+        bid_idx: 1,
+        amount: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+    assert!(query(deps.as_ref(), mock_env(), QueryMsg::Bid { bid_idx: 1 }).is_err());
+
+    // the pool pays its last 5,000 for 10,101
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("asset0000", &[]),
+        execute_msg(1000000u128),
+    )
+    .unwrap();
+
+    let bid: BidResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Bid { bid_idx: 2 }).unwrap())
+            .unwrap();
+    assert_eq!(bid.amount, Uint256::zero());
+    assert_eq!(bid.pending_liquidated_collateral, Uint256::from(1010101u64));
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0001", &[]),
+        claim_msg(Some(vec![2])),
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "asset0000".to_string(),
+            funds: vec![],
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: "addr0001".to_string(),
+                amount: Uint128::from(1010101u128),
+            })
+            .unwrap(),
+        }))]
+    );
+
+    // fully filled and claimed bids are removed
+    assert!(query(deps.as_ref(), mock_env(), QueryMsg::Bid { bid_idx: 2 }).is_err());
+}
+
+#[test]
+fn sweep_bids() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        oracle_contract: "oracle0000".to_string(),
+        stable_denom: "uusd".to_string(),
+        safe_ratio: Decimal256::percent(10),
+        bid_fee: Decimal256::zero(),
+        max_premium_rate: Decimal256::percent(5),
+        liquidation_threshold: Uint256::from(100000000u64),
+        price_timeframe: 60u64,
+        bid_retract_wait: 0u64,
+        liquidation_fee: Decimal256::zero(),
+        fee_address: "protocol0000".to_string(),
+        bid_activation_delay: 0u64,
+        inactive_bid_ttl: Some(86400u64),
+        min_bid_amount: Uint256::from(1000u64),
+        dust_threshold: Uint256::from(1000u64),
+        overseer_contract: None,
+        caller_fee: None,
+        market_contract: None,
+    };
+
+    let env = mock_env();
+    deps.querier.with_oracle_price(&[(
+        &("asset0000".to_string(), "uusd".to_string()),
+        &PriceInfo {
+            rate: Decimal256::percent(50),
+            last_updated_base: env.block.time.seconds(),
+            last_updated_quote: env.block.time.seconds(),
+        },
+    )]);
+
+    let info = mock_info("addr0000", &[]);
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::WhitelistCollateral {
+        collateral_token: "asset0000".to_string(),
+        max_slot: 5,
+        premium_rate_per_slot: Decimal256::percent(1),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg).unwrap();
+
+    let submit_bid =
+        |deps: &mut OwnedDeps<_, _, _>, env: Env, bidder: &str, premium_slot: u8, amount: u128| {
+            let msg = ExecuteMsg::SubmitBid {
+                collateral_token: "asset0000".to_string(),
+                premium_slot,
+            };
+            let info = mock_info(
+                bidder,
+                &[Coin {
+                    denom: "uusd".to_string(),
+                    amount: Uint128::from(amount),
+                }],
+            );
+            execute(deps.as_mut(), env, info, msg).unwrap();
+        };
+    submit_bid(&mut deps, mock_env(), "addr0000", 1, 1000000u128);
+    submit_bid(&mut deps, mock_env(), "addr0001", 2, 500000u128);
+
+    // slot 1 at 0.495 pays 998,501 for 2,017,173 and leaves 1,499,
+    // then the bidder retracts 500 of it leaving 999 of dust
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "custody0000".to_string(),
+        amount: Uint128::from(2017173u128),
+        msg: to_binary(&Cw20HookMsg::ExecuteBid {
+            liquidator: "liquidator0000".to_string(),
+            borrower: "borrower0000".to_string(),
+            fee_address: None,
+            repay_address: None,
+        })
+        .unwrap(),
+    });
+    execute(deps.as_mut(), mock_env(), mock_info("asset0000", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::RetractBid {
+        bid_idx: 1,
+        amount: Some(Uint256::from(500u64)),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+
+    // a day later bid 2 is inactive, while bid 3 is fresh
+    let mut sweep_env = mock_env();
+    sweep_env.block.time = sweep_env.block.time.plus_seconds(86400);
+    submit_bid(&mut deps, sweep_env.clone(), "addr0002", 3, 2000000u128);
+
+    let sweep_msg = |limit: Option<u32>| ExecuteMsg::SweepBids {
+        collateral_token: "asset0000".to_string(),
+        limit,
+    };
+
+    // the dust bid gets its stable and unclaimed collateral back
+    let res = execute(
+        deps.as_mut(),
+        sweep_env.clone(),
+        mock_info("sweeper0000", &[]),
+        sweep_msg(Some(1)),
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: "addr0000".to_string(),
+                amount: vec![Coin {
+                    denom: "uusd".to_string(),
+                    amount: Uint128::from(999u128),
+                }]
+            })),
+            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "asset0000".to_string(),
+                funds: vec![],
+                msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: "addr0000".to_string(),
+                    amount: Uint128::from(2017173u128),
+                })
+                .unwrap(),
+            })),
+        ]
+    );
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "sweep_bids"),
+            attr("collateral_token", "asset0000"),
+            attr("swept_bid_idx", "1"),
+        ]
+    );
+
+    // the next sweep continues after bid 1
+    let res = execute(
+        deps.as_mut(),
+        sweep_env.clone(),
+        mock_info("sweeper0000", &[]),
+        sweep_msg(None),
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: "addr0001".to_string(),
+            amount: vec![Coin {
+                denom: "uusd".to_string(),
+                amount: Uint128::from(500000u128),
+            }]
+        }))]
+    );
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "sweep_bids"),
+            attr("collateral_token", "asset0000"),
+            attr("swept_bid_idx", "2"),
+        ]
+    );
+
+    // healthy bids are left alone
+    let res = execute(
+        deps.as_mut(),
+        sweep_env,
+        mock_info("sweeper0000", &[]),
+        sweep_msg(None),
+    )
+    .unwrap();
+    assert_eq!(res.messages, vec![]);
+
+    let bids: BidsResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::BidsByCollateral {
+                collateral_token: "asset0000".to_string(),
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        bids.bids
+            .iter()
+            .map(|bid| (bid.idx, bid.amount))
+            .collect::<Vec<(u64, Uint256)>>(),
+        vec![(3, Uint256::from(2000000u64))]
+    );
+
+    let query_bid_pool = |premium_slot: u8| -> Uint256 {
+        let bid_pool: BidPoolResponse = from_binary(
+            &query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::BidPool {
+                    collateral_token: "asset0000".to_string(),
+                    premium_slot,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        bid_pool.total_bid_amount
+    };
+    assert_eq!(query_bid_pool(1), Uint256::zero());
+    assert_eq!(query_bid_pool(2), Uint256::zero());
+    assert_eq!(query_bid_pool(3), Uint256::from(2000000u64));
+}
+
+#[test]
+fn activate_bids() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        oracle_contract: "oracle0000".to_string(),
+        stable_denom: "uusd".to_string(),
+        safe_ratio: Decimal256::percent(10),
+        bid_fee: Decimal256::zero(),
+        max_premium_rate: Decimal256::percent(5),
+        liquidation_threshold: Uint256::from(100000000u64),
+        price_timeframe: 60u64,
+        bid_retract_wait: 3600u64,
+        liquidation_fee: Decimal256::zero(),
+        fee_address: "protocol0000".to_string(),
+        bid_activation_delay: 60u64,
+        inactive_bid_ttl: None,
+        min_bid_amount: Uint256::from(1000u64),
+        dust_threshold: Uint256::from(1000u64),
+        overseer_contract: None,
+        caller_fee: None,
+        market_contract: None,
+    };
+
+    let env = mock_env();
+    deps.querier.with_oracle_price(&[(
+        &("asset0000".to_string(), "uusd".to_string()),
+        &PriceInfo {
+            rate: Decimal256::one(),
+            last_updated_base: env.block.time.seconds() + 60,
+            last_updated_quote: env.block.time.seconds() + 60,
+        },
+    )]);
+
+    let info = mock_info("addr0000", &[]);
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::WhitelistCollateral {
+        collateral_token: "asset0000".to_string(),
+        max_slot: 5,
+        premium_rate_per_slot: Decimal256::percent(1),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg).unwrap();
+
+    for amount in [1000000u128, 2000000u128] {
+        let msg = ExecuteMsg::SubmitBid {
+            collateral_token: "asset0000".to_string(),
+            premium_slot: 1,
+        };
+        let info = mock_info(
+            "addr0000",
+            &[Coin {
+                denom: "uusd".to_string(),
+                amount: Uint128::from(amount),
+            }],
+        );
+        execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+    }
+
+    let bid_response: BidResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Bid { bid_idx: 1 }).unwrap())
+            .unwrap();
+    assert_eq!(
+        bid_response.activation_time,
+        Some(env.block.time.seconds() + 60)
+    );
+
+    // pending bids are not part of the pool
+    let query_bid_pool = |deps: Deps| -> Uint256 {
+        let bid_pool: BidPoolResponse = from_binary(
+            &query(
+                deps,
+                mock_env(),
+                QueryMsg::BidPool {
+                    collateral_token: "asset0000".to_string(),
+                    premium_slot: 1,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        bid_pool.total_bid_amount
+    };
+    assert_eq!(query_bid_pool(deps.as_ref()), Uint256::zero());
+
+    let execute_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "custody0000".to_string(),
+        amount: Uint128::from(100000u128),
+        msg: to_binary(&Cw20HookMsg::ExecuteBid {
+            liquidator: "liquidator0000".to_string(),
+            borrower: "borrower0000".to_string(),
+            fee_address: Some("fee0000".to_string()),
+            repay_address: Some("repay0000".to_string()),
+        })
+        .unwrap(),
+    });
+    let mut activation_env = env.clone();
+    activation_env.block.time = activation_env.block.time.plus_seconds(60);
+    let res = execute(
+        deps.as_mut(),
+        activation_env.clone(),
+        mock_info("asset0000", &[]),
+        execute_msg.clone(),
+    );
+    match res {
+        Err(ContractError::NoBidExists {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // too early
+    let msg = ExecuteMsg::ActivateBids {
+        collateral_token: "asset0000".to_string(),
+        bid_idxs: Some(vec![1]),
+    };
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0000", &[]),
+        msg.clone(),
+    );
+    match res {
+        Err(ContractError::BidActivationNotReady(activation_time)) => {
+            assert_eq!(activation_time, env.block.time.seconds() + 60)
+        }
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0000", &[]),
+        ExecuteMsg::ActivateBids {
+            collateral_token: "asset0000".to_string(),
+            bid_idxs: None,
+        },
+    );
+    match res {
+        Err(ContractError::NoBidExists {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let res = execute(
+        deps.as_mut(),
+        activation_env.clone(),
+        mock_info("addr0001", &[]),
+        msg.clone(),
+    );
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let res = execute(
+        deps.as_mut(),
+        activation_env.clone(),
+        mock_info("addr0000", &[]),
+        msg.clone(),
+    )
+    .unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "activate_bids"),
+            attr("collateral_token", "asset0000"),
+            attr("bid_idxs", "1"),
+            attr("amount", "1000000"),
+        ]
+    );
+    assert_eq!(query_bid_pool(deps.as_ref()), Uint256::from(1000000u64));
+
+    let res = execute(
+        deps.as_mut(),
+        activation_env.clone(),
+        mock_info("addr0000", &[]),
+        msg,
+    );
+    match res {
+        Err(ContractError::BidNotPending(1)) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // only the activated bid is matched
+    let res = execute(
+        deps.as_mut(),
+        activation_env.clone(),
+        mock_info("asset0000", &[]),
+        execute_msg,
+    )
+    .unwrap();
+    assert_eq!(
+        res.attributes[11..14],
+        vec![
+            attr("fill_premium_slot", "1"),
+            attr("fill_premium_rate", "0.01"),
+            attr("fill_collateral_amount", "100000")
+        ]
+    );
+
+    // pending bids are retracted without a waiting period
+    let res = execute(
+        deps.as_mut(),
+        env,
+        mock_info("addr0000", &[]),
+        ExecuteMsg::RetractBid {
+            bid_idx: 2,
+            amount: None,
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: "addr0000".to_string(),
+            amount: vec![Coin {
+                denom: "uusd".to_string(),
+                amount: Uint128::from(2000000u128),
+            }]
+        }))]
+    );
+    assert_eq!(query_bid_pool(deps.as_ref()), Uint256::from(901000u64));
+}
+
+#[test]
+fn update_bid_premium() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        oracle_contract: "oracle0000".to_string(),
+        stable_denom: "uusd".to_string(),
+        safe_ratio: Decimal256::percent(10),
+        bid_fee: Decimal256::zero(),
+        max_premium_rate: Decimal256::percent(5),
+        liquidation_threshold: Uint256::from(100000000u64),
+        price_timeframe: 60u64,
+        bid_retract_wait: 3600u64,
+        liquidation_fee: Decimal256::zero(),
+        fee_address: "protocol0000".to_string(),
+        bid_activation_delay: 60u64,
+        inactive_bid_ttl: None,
+        min_bid_amount: Uint256::from(1000u64),
+        dust_threshold: Uint256::from(1000u64),
+        overseer_contract: None,
+        caller_fee: None,
+        market_contract: None,
+    };
+
+    let env = mock_env();
+    let mut activation_env = env.clone();
+    activation_env.block.time = activation_env.block.time.plus_seconds(60);
+    let mut move_activation_env = env.clone();
+    move_activation_env.block.time = move_activation_env.block.time.plus_seconds(120);
+    deps.querier.with_oracle_price(&[(
+        &("asset0000".to_string(), "uusd".to_string()),
+        &PriceInfo {
+            rate: Decimal256::one(),
+            last_updated_base: env.block.time.seconds() + 60,
+            last_updated_quote: env.block.time.seconds() + 60,
+        },
+    )]);
+
+    let info = mock_info("addr0000", &[]);
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::WhitelistCollateral {
+        collateral_token: "asset0000".to_string(),
+        max_slot: 5,
+        premium_rate_per_slot: Decimal256::percent(1),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg).unwrap();
+
+    for (bidder, amount) in [("addr0000", 1000000u128), ("addr0001", 500000u128)] {
+        let msg = ExecuteMsg::SubmitBid {
+            collateral_token: "asset0000".to_string(),
+            premium_slot: 1,
+        };
+        let info = mock_info(
+            bidder,
+            &[Coin {
+                denom: "uusd".to_string(),
+                amount: Uint128::from(amount),
+            }],
+        );
+        execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        let msg = ExecuteMsg::ActivateBids {
+            collateral_token: "asset0000".to_string(),
+            bid_idxs: None,
+        };
+        execute(
+            deps.as_mut(),
+            activation_env.clone(),
+            mock_info(bidder, &[]),
+            msg,
+        )
+        .unwrap();
+    }
+
+    let query_bid_pool = |deps: Deps, premium_slot: u8| -> Uint256 {
+        let bid_pool: BidPoolResponse = from_binary(
+            &query(
+                deps,
+                mock_env(),
+                QueryMsg::BidPool {
+                    collateral_token: "asset0000".to_string(),
+                    premium_slot,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        bid_pool.total_bid_amount
+    };
+    assert_eq!(query_bid_pool(deps.as_ref(), 1), Uint256::from(1500000u64));
+
+    let msg = ExecuteMsg::UpdateBidPremium {
+        bid_idx: 1,
+        premium_slot: 3,
+    };
+    let res = execute(
+        deps.as_mut(),
+        activation_env.clone(),
+        mock_info("addr0001", &[]),
+        msg.clone(),
+    );
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let res = execute(
+        deps.as_mut(),
+        activation_env.clone(),
+        mock_info("addr0000", &[]),
+        ExecuteMsg::UpdateBidPremium {
+            bid_idx: 1,
+            premium_slot: 6,
+        },
+    );
+    match res {
+        Err(ContractError::PremiumSlotExceedsMaxSlot(5)) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let res = execute(
+        deps.as_mut(),
+        activation_env.clone(),
+        mock_info("addr0000", &[]),
+        msg,
+    )
+    .unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "update_bid_premium"),
+            attr("bid_idx", "1"),
+            attr("collateral_token", "asset0000"),
+            attr("premium_slot", "3"),
+            attr("amount", "1000000"),
+        ]
+    );
+
+    // the moved bid left its pool and waits for activation in the new slot
+    assert_eq!(query_bid_pool(deps.as_ref(), 1), Uint256::from(500000u64));
+    assert_eq!(query_bid_pool(deps.as_ref(), 3), Uint256::zero());
+    let bid_response: BidResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Bid { bid_idx: 1 }).unwrap())
+            .unwrap();
+    assert_eq!(bid_response.premium_slot, 3);
+    assert_eq!(
+        bid_response.activation_time,
+        Some(move_activation_env.block.time.seconds())
+    );
+
+    // only the other bid is matched, the rest of the collateral is returned
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "custody0000".to_string(),
+        amount: Uint128::from(1000000u128),
+        msg: to_binary(&Cw20HookMsg::ExecuteBid {
+            liquidator: "liquidator0000".to_string(),
+            borrower: "borrower0000".to_string(),
+            fee_address: Some("fee0000".to_string()),
+            repay_address: Some("repay0000".to_string()),
+        })
+        .unwrap(),
+    });
+    let res = execute(
+        deps.as_mut(),
+        activation_env,
+        mock_info("asset0000", &[]),
+        msg,
+    )
+    .unwrap();
+    assert_eq!(res.attributes[3], attr("stable_denom", "uusd"));
+    assert_eq!(res.attributes[4], attr("repay_amount", "500000"));
+    assert_eq!(
+        res.attributes[10],
+        attr("returned_collateral_amount", "494950")
+    );
+
+    let msg = ExecuteMsg::ActivateBids {
+        collateral_token: "asset0000".to_string(),
+        bid_idxs: Some(vec![1]),
+    };
+    execute(
+        deps.as_mut(),
+        move_activation_env,
+        mock_info("addr0000", &[]),
+        msg,
+    )
+    .unwrap();
+    assert_eq!(query_bid_pool(deps.as_ref(), 3), Uint256::from(1000000u64));
+}
+
+#[test]
+fn bid_book_views() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        oracle_contract: "oracle0000".to_string(),
+        stable_denom: "uusd".to_string(),
+        safe_ratio: Decimal256::percent(10),
+        bid_fee: Decimal256::zero(),
+        max_premium_rate: Decimal256::percent(5),
+        liquidation_threshold: Uint256::from(100000000u64),
+        price_timeframe: 60u64,
+        bid_retract_wait: 3600u64,
+        liquidation_fee: Decimal256::zero(),
+        fee_address: "protocol0000".to_string(),
+        bid_activation_delay: 0u64,
+        inactive_bid_ttl: None,
+        min_bid_amount: Uint256::from(1000u64),
+        dust_threshold: Uint256::from(1000u64),
+        overseer_contract: None,
+        caller_fee: None,
+        market_contract: None,
+    };
+
+    let env = mock_env();
+    deps.querier.with_oracle_price(&[(
+        &("asset0000".to_string(), "uusd".to_string()),
+        &PriceInfo {
+            rate: Decimal256::percent(50),
+            last_updated_base: env.block.time.seconds(),
+            last_updated_quote: env.block.time.seconds(),
+        },
+    )]);
+
+    let info = mock_info("addr0000", &[]);
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    for collateral_token in ["asset0000", "asset0001"] {
+        let msg = ExecuteMsg::WhitelistCollateral {
+            collateral_token: collateral_token.to_string(),
+            max_slot: 5,
+            premium_rate_per_slot: Decimal256::percent(1),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg).unwrap();
+    }
+
+    for (bidder, collateral_token, premium_slot, amount) in [
+        ("addr0000", "asset0000", 1u8, 1000000u128),
+        ("addr0001", "asset0000", 1u8, 500000u128),
+        ("addr0000", "asset0000", 2u8, 2000000u128),
+        ("addr0000", "asset0001", 0u8, 700000u128),
+    ] {
+        let msg = ExecuteMsg::SubmitBid {
+            collateral_token: collateral_token.to_string(),
+            premium_slot,
+        };
+        let info = mock_info(
+            bidder,
+            &[Coin {
+                denom: "uusd".to_string(),
+                amount: Uint128::from(amount),
+            }],
+        );
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    }
+
+    // slot 1 at 0.495 pays 1,485,000 for 3,000,000 and keeps 15,000,
+    // shared 2:1 between bids 1 and 2
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "custody0000".to_string(),
+        amount: Uint128::from(3000000u128),
+        msg: to_binary(&Cw20HookMsg::ExecuteBid {
+            liquidator: "liquidator0000".to_string(),
+            borrower: "borrower0000".to_string(),
+            fee_address: None,
+            repay_address: None,
+        })
+        .unwrap(),
+    });
+    execute(deps.as_mut(), mock_env(), mock_info("asset0000", &[]), msg).unwrap();
+
+    let query_bids_by_user =
+        |collateral_token: Option<&str>, bidder: &str| -> Vec<(u64, Uint256)> {
+            let bids: BidsResponse = from_binary(
+                &query(
+                    deps.as_ref(),
+                    mock_env(),
+                    QueryMsg::BidsByUser {
+                        collateral_token: collateral_token.map(|c| c.to_string()),
+                        bidder: bidder.to_string(),
+                        start_after: None,
+                        limit: None,
+                    },
+                )
+                .unwrap(),
+            )
+            .unwrap();
+            bids.bids.iter().map(|bid| (bid.idx, bid.amount)).collect()
+        };
+    assert_eq!(
+        query_bids_by_user(None, "addr0000"),
+        vec![
+            (1, Uint256::from(10000u64)),
+            (3, Uint256::from(2000000u64)),
+            (4, Uint256::from(700000u64))
+        ]
+    );
+    assert_eq!(
+        query_bids_by_user(Some("asset0000"), "addr0000"),
+        vec![(1, Uint256::from(10000u64)), (3, Uint256::from(2000000u64))]
+    );
+    assert_eq!(
+        query_bids_by_user(Some("asset0001"), "addr0000"),
+        vec![(4, Uint256::from(700000u64))]
+    );
+    assert_eq!(
+        query_bids_by_user(Some("asset0000"), "addr0001"),
+        vec![(2, Uint256::from(5000u64))]
+    );
+
+    let query_bid_pools = |start_after: Option<u8>, limit: Option<u32>| -> BidPoolsResponse {
+        from_binary(
+            &query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::BidPoolsByCollateral {
+                    collateral_token: "asset0000".to_string(),
+                    start_after,
+                    limit,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+    };
+    assert_eq!(
+        query_bid_pools(None, None),
+        BidPoolsResponse {
+            bid_pools: vec![
+                BidPoolResponse {
+                    collateral_token: "asset0000".to_string(),
+                    premium_slot: 1,
+                    premium_rate: Decimal256::percent(1),
+                    total_bid_amount: Uint256::from(15000u64),
+                },
+                BidPoolResponse {
+                    collateral_token: "asset0000".to_string(),
+                    premium_slot: 2,
+                    premium_rate: Decimal256::percent(2),
+                    total_bid_amount: Uint256::from(2000000u64),
+                },
+            ]
+        }
+    );
+    assert_eq!(
+        query_bid_pools(Some(1), None)
+            .bid_pools
+            .iter()
+            .map(|bid_pool| bid_pool.premium_slot)
+            .collect::<Vec<u8>>(),
+        vec![2]
+    );
+    assert_eq!(
+        query_bid_pools(None, Some(1))
+            .bid_pools
+            .iter()
+            .map(|bid_pool| bid_pool.premium_slot)
+            .collect::<Vec<u8>>(),
+        vec![1]
+    );
+
+    // pool totals match the bids of the collateral
+    let bids: BidsResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::BidsByCollateral {
+                collateral_token: "asset0000".to_string(),
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    for bid_pool in query_bid_pools(None, None).bid_pools {
+        let total = bids
+            .bids
+            .iter()
+            .filter(|bid| bid.premium_slot == bid_pool.premium_slot)
+            .fold(Uint256::zero(), |total, bid| total + bid.amount);
+        assert_eq!(total, bid_pool.total_bid_amount);
+    }
+}
+
+#[test]
+fn liquidity_depth() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        oracle_contract: "oracle0000".to_string(),
+        stable_denom: "uusd".to_string(),
+        safe_ratio: Decimal256::percent(10),
+        bid_fee: Decimal256::zero(),
+        max_premium_rate: Decimal256::percent(5),
+        liquidation_threshold: Uint256::from(100000000u64),
+        price_timeframe: 60u64,
+        bid_retract_wait: 0u64,
+        liquidation_fee: Decimal256::zero(),
+        fee_address: "protocol0000".to_string(),
+        bid_activation_delay: 0u64,
+        inactive_bid_ttl: None,
+        min_bid_amount: Uint256::from(1000u64),
+        dust_threshold: Uint256::from(1000u64),
+        overseer_contract: None,
+        caller_fee: None,
+        market_contract: None,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::WhitelistCollateral {
+        collateral_token: "asset0000".to_string(),
+        max_slot: 5,
+        premium_rate_per_slot: Decimal256::percent(1),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg).unwrap();
+
+    let submit = |deps: &mut OwnedDeps<_, _, _>, bidder: &str, premium_slot: u8, amount: u128| {
+        let msg = ExecuteMsg::SubmitBid {
+            collateral_token: "asset0000".to_string(),
+            premium_slot,
+        };
+        let info = mock_info(
+            bidder,
+            &[Coin {
+                denom: "uusd".to_string(),
+                amount: Uint128::from(amount),
+            }],
+        );
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    };
+    let retract = |deps: &mut OwnedDeps<_, _, _>, bidder: &str, bid_idx: u64, amount: u128| {
+        let msg = ExecuteMsg::RetractBid {
+            bid_idx,
+            amount: Some(Uint256::from(amount)),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(bidder, &[]), msg).unwrap();
+    };
+    // the contract balance is set to what it would hold after each step
+    let depth = |deps: &mut OwnedDeps<MockStorage, MockApi, WasmMockQuerier>,
+                 balance: u128|
+     -> LiquidityDepthResponse {
+        deps.querier.update_balance(
+            MOCK_CONTRACT_ADDR,
+            vec![Coin {
+                denom: "uusd".to_string(),
+                amount: Uint128::from(balance),
+            }],
+        );
+        let msg = QueryMsg::LiquidityDepth {
+            collateral_token: "asset0000".to_string(),
+        };
+        from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap()
+    };
+    let slot = |premium_slot: u8, amounts: (u64, u64), bids: (u64, u64)| SlotDepthResponse {
+        premium_slot,
+        premium_rate: Decimal256::percent(premium_slot as u64),
+        active_bid_amount: Uint256::from(amounts.0),
+        pending_bid_amount: Uint256::from(amounts.1),
+        active_bids: bids.0,
+        pending_bids: bids.1,
+    };
+
+    submit(&mut deps, "addr0000", 1, 1000000);
+    submit(&mut deps, "addr0001", 2, 2000000);
+
+    let msg = ExecuteMsg::UpdateConfig {
+        owner: None,
+        oracle_contract: None,
+        stable_denom: None,
+        safe_ratio: None,
+        bid_fee: None,
+        max_premium_rate: None,
+        liquidation_threshold: None,
+        price_timeframe: None,
+        bid_retract_wait: None,
+        liquidation_fee: None,
+        fee_address: None,
+        bid_activation_delay: Some(60u64),
+        inactive_bid_ttl: None,
+        min_bid_amount: None,
+        dust_threshold: None,
+        overseer_contract: None,
+        caller_fee: None,
+        market_contract: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg).unwrap();
+    submit(&mut deps, "addr0002", 2, 500000);
 
-    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
-        sender: "addr0001".to_string(),
-        amount: Uint128::from(2020206u128),
-        msg: to_binary(&Cw20HookMsg::ExecuteBid {
-            liquidator: "addr0000".to_string(),
-            fee_address: Some("fee0000".to_string()),
-            repay_address: Some("repay0000".to_string()),
-        })
-        .unwrap(),
-    });
-    let info = mock_info("asset0000", &[]);
-    let res = execute(deps.as_mut(), mock_env(), info.clone(), msg);
-    match res {
-        Err(ContractError::InsufficientBidBalance(1000001)) => (),
-        _ => panic!("DO NOT ENTER HERE"),
-    }
-    // required_stable 495,000
-    // bid_fee         4,950
-    // repay_amount    490,050
-    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
-        sender: "addr0001".to_string(),
-        amount: Uint128::from(1000000u128),
-        msg: to_binary(&Cw20HookMsg::ExecuteBid {
-            liquidator: "addr0000".to_string(),
-            fee_address: Some("fee0000".to_string()),
-            repay_address: Some("repay0000".to_string()),
-        })
-        .unwrap(),
-    });
-    let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
     assert_eq!(
-        res.messages,
-        vec![
-            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: "asset0000".to_string(),
-                funds: vec![],
-                msg: to_binary(&Cw20ExecuteMsg::Transfer {
-                    recipient: "addr0000".to_string(),
-                    amount: Uint128::from(1000000u128),
-                })
-                .unwrap(),
-            })),
-            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
-                to_address: "repay0000".to_string(),
-                amount: vec![Coin {
-                    denom: "uusd".to_string(),
-                    amount: Uint128::from(485198u128), // 490050 / (1 + tax_rate)
-                }]
-            })),
-            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
-                to_address: "fee0000".to_string(),
-                amount: vec![Coin {
-                    denom: "uusd".to_string(),
-                    amount: Uint128::from(4900u128), // 4950 / (1 + tax_rate)
-                }]
-            })),
-        ]
+        depth(&mut deps, 3500000),
+        LiquidityDepthResponse {
+            collateral_token: "asset0000".to_string(),
+            total_bid_amount: Uint256::from(3500000u64),
+            active_bids: 2,
+            pending_bids: 1,
+            slots: vec![
+                slot(1, (1000000, 0), (1, 0)),
+                slot(2, (2000000, 500000), (1, 1)),
+            ],
+            balance_covers_escrow: true,
+        }
     );
 
+    // the escrow cannot exceed what the contract holds
+    assert!(!depth(&mut deps, 3499999).balance_covers_escrow);
+    let health: EscrowHealthResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::EscrowHealth {}).unwrap()).unwrap();
+    assert!(!health.consistent);
+
+    // 500000 of collateral at 1 less 1% takes 495000 from slot 1
+    let env = mock_env();
+    deps.querier.with_oracle_price(&[(
+        &("asset0000".to_string(), "uusd".to_string()),
+        &PriceInfo {
+            rate: Decimal256::one(),
+            last_updated_base: env.block.time.seconds(),
+            last_updated_quote: env.block.time.seconds(),
+        },
+    )]);
     let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
-        sender: "addr0001".to_string(),
-        amount: Uint128::from(1000000u128),
+        sender: "custody0000".to_string(),
+        amount: Uint128::from(500000u128),
         msg: to_binary(&Cw20HookMsg::ExecuteBid {
-            liquidator: "addr0000".to_string(),
+            liquidator: "liquidator0000".to_string(),
+            borrower: "borrower0000".to_string(),
             fee_address: None,
             repay_address: None,
         })
         .unwrap(),
     });
-    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    execute(deps.as_mut(), mock_env(), mock_info("asset0000", &[]), msg).unwrap();
+
+    let res = depth(&mut deps, 3005000);
+    assert_eq!(res.total_bid_amount, Uint256::from(3005000u64));
+    assert_eq!(res.slots[0], slot(1, (505000, 0), (1, 0)));
+    assert!(res.balance_covers_escrow);
+
+    // pending bids are retracted from the pending escrow
+    retract(&mut deps, "addr0002", 3, 200000);
+    let res = depth(&mut deps, 2805000);
+    assert_eq!(res.slots[1], slot(2, (2000000, 300000), (1, 1)));
+
+    // a filled bid keeps counting until its collateral is claimed
+    retract(&mut deps, "addr0000", 1, 505000);
+    let res = depth(&mut deps, 2300000);
+    assert_eq!(res.slots[0], slot(1, (0, 0), (1, 0)));
+
+    let msg = ExecuteMsg::ClaimLiquidations {
+        collateral_token: "asset0000".to_string(),
+        bids_idx: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+    retract(&mut deps, "addr0002", 3, 300000);
     assert_eq!(
-        res.messages,
-        vec![
-            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: "asset0000".to_string(),
-                funds: vec![],
-                msg: to_binary(&Cw20ExecuteMsg::Transfer {
-                    recipient: "addr0000".to_string(),
-                    amount: Uint128::from(1000000u128),
-                })
-                .unwrap(),
-            })),
-            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
-                to_address: "addr0001".to_string(),
-                amount: vec![Coin {
-                    denom: "uusd".to_string(),
-                    amount: Uint128::from(485198u128), // 490050 / (1 + tax_rate)
-                }]
-            })),
-            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
-                to_address: "addr0001".to_string(),
-                amount: vec![Coin {
-                    denom: "uusd".to_string(),
-                    amount: Uint128::from(4900u128), // 4950 / (1 + tax_rate)
-                }]
-            })),
-        ]
+        depth(&mut deps, 2000000),
+        LiquidityDepthResponse {
+            collateral_token: "asset0000".to_string(),
+            total_bid_amount: Uint256::from(2000000u64),
+            active_bids: 1,
+            pending_bids: 0,
+            slots: vec![slot(2, (2000000, 0), (1, 0))],
+            balance_covers_escrow: true,
+        }
     );
 }
 
@@ -464,6 +3457,16 @@ fn query_liquidation_amount() {
         max_premium_rate: Decimal256::percent(5),
         liquidation_threshold: Uint256::from(100000000u64),
         price_timeframe: 60u64,
+        bid_retract_wait: 3600u64,
+        liquidation_fee: Decimal256::zero(),
+        fee_address: "protocol0000".to_string(),
+        bid_activation_delay: 0u64,
+        inactive_bid_ttl: None,
+        min_bid_amount: Uint256::from(1000u64),
+        dust_threshold: Uint256::from(1000u64),
+        overseer_contract: None,
+        caller_fee: None,
+        market_contract: None,
     };
 
     let info = mock_info("addr0000", &[]);
@@ -532,6 +3535,127 @@ fn query_liquidation_amount() {
             ],
         }
     );
+
+    // below liquidation_threshold the safe ratio is not used,
+    // so the threshold is lowered to compare safe ratios
+    let update_config = |deps: &mut OwnedDeps<_, _, _>,
+                         safe_ratio: Option<Decimal256>,
+                         liquidation_threshold: Option<Uint256>| {
+        let msg = ExecuteMsg::UpdateConfig {
+            owner: None,
+            oracle_contract: None,
+            stable_denom: None,
+            safe_ratio,
+            bid_fee: None,
+            max_premium_rate: None,
+            liquidation_threshold,
+            price_timeframe: None,
+            bid_retract_wait: None,
+            liquidation_fee: None,
+            fee_address: None,
+            bid_activation_delay: None,
+            inactive_bid_ttl: None,
+            min_bid_amount: None,
+            dust_threshold: None,
+            overseer_contract: None,
+            caller_fee: None,
+            market_contract: None,
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg)
+    };
+    update_config(&mut deps, None, Some(Uint256::from(1000000u64))).unwrap();
+
+    let query_msg = QueryMsg::LiquidationAmount {
+        borrow_amount: Uint256::from(1000000u64),
+        borrow_limit: Uint256::from(900000u64),
+        collaterals: vec![("token0000".to_string(), Uint256::from(10000000u64))],
+        collateral_prices: vec![Decimal256::one()],
+    };
+
+    // liquidation_ratio = (1000000 - 90000) / (9310950 - 90000)
+    let res = query(deps.as_ref(), mock_env(), query_msg.clone()).unwrap();
+    let res: LiquidationAmountResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        res.collaterals,
+        vec![("token0000".to_string(), Uint256::from(986883u64))]
+    );
+
+    // a higher safe ratio leaves more of the loan, so less is liquidated
+    // liquidation_ratio = (1000000 - 450000) / (9310950 - 450000)
+    update_config(&mut deps, Some(Decimal256::percent(50)), None).unwrap();
+    let res = query(deps.as_ref(), mock_env(), query_msg).unwrap();
+    let res: LiquidationAmountResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        res.collaterals,
+        vec![("token0000".to_string(), Uint256::from(620700u64))]
+    );
+
+    let res: ConfigResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap()).unwrap();
+    assert_eq!(res.safe_ratio, Decimal256::percent(50));
+
+    for safe_ratio in [Decimal256::zero(), Decimal256::percent(101)] {
+        match update_config(&mut deps, Some(safe_ratio), None) {
+            Err(ContractError::InvalidSafeRatio {}) => (),
+            _ => panic!("DO NOT ENTER HERE"),
+        }
+    }
+    update_config(&mut deps, Some(Decimal256::one()), None).unwrap();
+}
+
+#[test]
+fn query_liquidation_amount_caller_fee() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        oracle_contract: "oracle0000".to_string(),
+        stable_denom: "uusd".to_string(),
+        safe_ratio: Decimal256::percent(80),
+        bid_fee: Decimal256::percent(1),
+        max_premium_rate: Decimal256::percent(5),
+        liquidation_threshold: Uint256::from(1000000u64),
+        price_timeframe: 60u64,
+        bid_retract_wait: 3600u64,
+        liquidation_fee: Decimal256::percent(1),
+        fee_address: "protocol0000".to_string(),
+        bid_activation_delay: 0u64,
+        inactive_bid_ttl: None,
+        min_bid_amount: Uint256::from(1000u64),
+        dust_threshold: Uint256::from(1000u64),
+        overseer_contract: None,
+        caller_fee: Some(Decimal256::percent(5)),
+        market_contract: None,
+    };
+    let info = mock_info("addr0000", &[]);
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // borrow limit at 50% of the collateral value
+    let collateral_amount = Uint256::from(10000000u64);
+    let borrow_amount = Uint256::from(5500000u64);
+    let borrow_limit = Uint256::from(5000000u64);
+    let query_msg = QueryMsg::LiquidationAmount {
+        borrow_amount,
+        borrow_limit,
+        collaterals: vec![("token0000".to_string(), collateral_amount)],
+        collateral_prices: vec![Decimal256::one()],
+    };
+    let res = query(deps.as_ref(), mock_env(), query_msg).unwrap();
+    let res: LiquidationAmountResponse = from_binary(&res).unwrap();
+    let liquidated = res.collaterals[0].1;
+
+    // the repayment after the premium and the protocol fee,
+    // less the bid and caller fees of split_repay
+    let total_repay = liquidated * Decimal256::percent(95) * Decimal256::percent(99);
+    let repay_amount =
+        total_repay - total_repay * Decimal256::percent(1) - total_repay * Decimal256::percent(5);
+
+    // the loan is back to the safe ratio of the remaining borrow limit
+    let remaining_loan = borrow_amount - repay_amount;
+    let remaining_limit =
+        (collateral_amount - liquidated) * Decimal256::percent(50) * Decimal256::percent(80);
+    assert!(remaining_loan <= remaining_limit + Uint256::from(3u64));
+    assert!(remaining_loan + Uint256::from(3u64) >= remaining_limit);
 }
 
 #[test]
@@ -551,24 +3675,43 @@ fn query_bids_by_user() {
         max_premium_rate: Decimal256::percent(5),
         liquidation_threshold: Uint256::from(100000000u64),
         price_timeframe: 60u64,
+        bid_retract_wait: 3600u64,
+        liquidation_fee: Decimal256::zero(),
+        fee_address: "protocol0000".to_string(),
+        bid_activation_delay: 0u64,
+        inactive_bid_ttl: None,
+        min_bid_amount: Uint256::from(1000u64),
+        dust_threshold: Uint256::from(1000u64),
+        overseer_contract: None,
+        caller_fee: None,
+        market_contract: None,
     };
 
     let info = mock_info("addr0000", &[]);
     let env = mock_env();
     deps.querier.with_oracle_price(&[(
         &("asset0000".to_string(), "uusd".to_string()),
-        &(
-            Decimal256::percent(50),
-            env.block.time.seconds(),
-            env.block.time.seconds(),
-        ),
+        &PriceInfo {
+            rate: Decimal256::percent(50),
+            last_updated_base: env.block.time.seconds(),
+            last_updated_quote: env.block.time.seconds(),
+        },
     )]);
 
     let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
 
+    for collateral_token in ["asset0000", "asset0001", "asset0002"] {
+        let msg = ExecuteMsg::WhitelistCollateral {
+            collateral_token: collateral_token.to_string(),
+            max_slot: 5,
+            premium_rate_per_slot: Decimal256::percent(1),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg).unwrap();
+    }
+
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "asset0000".to_string(),
-        premium_rate: Decimal256::percent(1),
+        premium_slot: 1,
     };
     let info = mock_info(
         "addr0000",
@@ -581,7 +3724,7 @@ fn query_bids_by_user() {
 
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "asset0001".to_string(),
-        premium_rate: Decimal256::percent(2),
+        premium_slot: 2,
     };
     let info = mock_info(
         "addr0000",
@@ -594,7 +3737,7 @@ fn query_bids_by_user() {
 
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "asset0002".to_string(),
-        premium_rate: Decimal256::percent(3),
+        premium_slot: 3,
     };
     let info = mock_info(
         "addr0000",
@@ -610,6 +3753,7 @@ fn query_bids_by_user() {
             deps.as_ref(),
             mock_env(),
             QueryMsg::BidsByUser {
+                collateral_token: None,
                 bidder: "addr0000".to_string(),
                 start_after: None,
                 limit: None,
@@ -623,22 +3767,34 @@ fn query_bids_by_user() {
         BidsResponse {
             bids: vec![
                 BidResponse {
+                    idx: 1,
                     collateral_token: "asset0000".to_string(),
                     bidder: "addr0000".to_string(),
                     amount: Uint256::from(1000000u128),
-                    premium_rate: Decimal256::percent(1),
+                    premium_slot: 1,
+                    pending_liquidated_collateral: Uint256::zero(),
+                    wait_end: None,
+                    activation_time: None,
                 },
                 BidResponse {
+                    idx: 2,
                     collateral_token: "asset0001".to_string(),
                     bidder: "addr0000".to_string(),
                     amount: Uint256::from(2000000u128),
-                    premium_rate: Decimal256::percent(2),
+                    premium_slot: 2,
+                    pending_liquidated_collateral: Uint256::zero(),
+                    wait_end: None,
+                    activation_time: None,
                 },
                 BidResponse {
+                    idx: 3,
                     collateral_token: "asset0002".to_string(),
                     bidder: "addr0000".to_string(),
                     amount: Uint256::from(3000000u128),
-                    premium_rate: Decimal256::percent(3),
+                    premium_slot: 3,
+                    pending_liquidated_collateral: Uint256::zero(),
+                    wait_end: None,
+                    activation_time: None,
                 }
             ]
         }
@@ -649,8 +3805,9 @@ fn query_bids_by_user() {
             deps.as_ref(),
             mock_env(),
             QueryMsg::BidsByUser {
+                collateral_token: None,
                 bidder: "addr0000".to_string(),
-                start_after: Some("asset0000".to_string()),
+                start_after: Some(1u64),
                 limit: None,
             },
         )
@@ -662,16 +3819,24 @@ fn query_bids_by_user() {
         BidsResponse {
             bids: vec![
                 BidResponse {
+                    idx: 2,
                     collateral_token: "asset0001".to_string(),
                     bidder: "addr0000".to_string(),
                     amount: Uint256::from(2000000u128),
-                    premium_rate: Decimal256::percent(2),
+                    premium_slot: 2,
+                    pending_liquidated_collateral: Uint256::zero(),
+                    wait_end: None,
+                    activation_time: None,
                 },
                 BidResponse {
+                    idx: 3,
                     collateral_token: "asset0002".to_string(),
                     bidder: "addr0000".to_string(),
                     amount: Uint256::from(3000000u128),
-                    premium_rate: Decimal256::percent(3),
+                    premium_slot: 3,
+                    pending_liquidated_collateral: Uint256::zero(),
+                    wait_end: None,
+                    activation_time: None,
                 }
             ]
         }
@@ -681,6 +3846,7 @@ fn query_bids_by_user() {
             deps.as_ref(),
             mock_env(),
             QueryMsg::BidsByUser {
+                collateral_token: None,
                 bidder: "addr0000".to_string(),
                 start_after: None,
                 limit: Some(1u32),
@@ -693,10 +3859,14 @@ fn query_bids_by_user() {
         bids,
         BidsResponse {
             bids: vec![BidResponse {
+                idx: 1,
                 collateral_token: "asset0000".to_string(),
                 bidder: "addr0000".to_string(),
                 amount: Uint256::from(1000000u128),
-                premium_rate: Decimal256::percent(1),
+                premium_slot: 1,
+                pending_liquidated_collateral: Uint256::zero(),
+                wait_end: None,
+                activation_time: None,
             }]
         }
     );
@@ -711,7 +3881,11 @@ fn query_bids_by_collateral() {
     );
     deps.querier.with_oracle_price(&[(
         &("asset0000".to_string(), "uusd".to_string()),
-        &(Decimal256::percent(50), 123456u64, 123456u64),
+        &PriceInfo {
+            rate: Decimal256::percent(50),
+            last_updated_base: 123456u64,
+            last_updated_quote: 123456u64,
+        },
     )]);
 
     let msg = InstantiateMsg {
@@ -723,14 +3897,33 @@ fn query_bids_by_collateral() {
         max_premium_rate: Decimal256::percent(5),
         liquidation_threshold: Uint256::from(100000000u64),
         price_timeframe: 60u64,
+        bid_retract_wait: 3600u64,
+        liquidation_fee: Decimal256::zero(),
+        fee_address: "protocol0000".to_string(),
+        bid_activation_delay: 0u64,
+        inactive_bid_ttl: None,
+        min_bid_amount: Uint256::from(1000u64),
+        dust_threshold: Uint256::from(1000u64),
+        overseer_contract: None,
+        caller_fee: None,
+        market_contract: None,
     };
 
     let info = mock_info("addr0000", &[]);
     let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
 
+    for collateral_token in ["asset0000", "asset0001"] {
+        let msg = ExecuteMsg::WhitelistCollateral {
+            collateral_token: collateral_token.to_string(),
+            max_slot: 5,
+            premium_rate_per_slot: Decimal256::percent(1),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg).unwrap();
+    }
+
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "asset0000".to_string(),
-        premium_rate: Decimal256::percent(1),
+        premium_slot: 1,
     };
     let info = mock_info(
         "addr0000",
@@ -743,7 +3936,7 @@ fn query_bids_by_collateral() {
 
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "asset0000".to_string(),
-        premium_rate: Decimal256::percent(2),
+        premium_slot: 2,
     };
     let info = mock_info(
         "addr0001",
@@ -756,7 +3949,7 @@ fn query_bids_by_collateral() {
 
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "asset0001".to_string(),
-        premium_rate: Decimal256::percent(3),
+        premium_slot: 3,
     };
     let info = mock_info(
         "addr0000",
@@ -785,16 +3978,24 @@ fn query_bids_by_collateral() {
         BidsResponse {
             bids: vec![
                 BidResponse {
+                    idx: 1,
                     collateral_token: "asset0000".to_string(),
                     bidder: "addr0000".to_string(),
                     amount: Uint256::from(1000000u128),
-                    premium_rate: Decimal256::percent(1),
+                    premium_slot: 1,
+                    pending_liquidated_collateral: Uint256::zero(),
+                    wait_end: None,
+                    activation_time: None,
                 },
                 BidResponse {
+                    idx: 2,
                     collateral_token: "asset0000".to_string(),
                     bidder: "addr0001".to_string(),
                     amount: Uint256::from(2000000u128),
-                    premium_rate: Decimal256::percent(2),
+                    premium_slot: 2,
+                    pending_liquidated_collateral: Uint256::zero(),
+                    wait_end: None,
+                    activation_time: None,
                 }
             ]
         }
@@ -806,7 +4007,7 @@ fn query_bids_by_collateral() {
             mock_env(),
             QueryMsg::BidsByCollateral {
                 collateral_token: "asset0000".to_string(),
-                start_after: Some("addr0000".to_string()),
+                start_after: Some(1u64),
                 limit: None,
             },
         )
@@ -817,10 +4018,14 @@ fn query_bids_by_collateral() {
         bids,
         BidsResponse {
             bids: vec![BidResponse {
+                idx: 2,
                 collateral_token: "asset0000".to_string(),
                 bidder: "addr0001".to_string(),
                 amount: Uint256::from(2000000u128),
-                premium_rate: Decimal256::percent(2),
+                premium_slot: 2,
+                pending_liquidated_collateral: Uint256::zero(),
+                wait_end: None,
+                activation_time: None,
             }]
         }
     );
@@ -841,11 +4046,309 @@ fn query_bids_by_collateral() {
         bids,
         BidsResponse {
             bids: vec![BidResponse {
+                idx: 1,
                 collateral_token: "asset0000".to_string(),
                 bidder: "addr0000".to_string(),
                 amount: Uint256::from(1000000u128),
-                premium_rate: Decimal256::percent(1),
+                premium_slot: 1,
+                pending_liquidated_collateral: Uint256::zero(),
+                wait_end: None,
+                activation_time: None,
             }]
         }
     );
 }
+
+#[test]
+fn escrow_health() {
+    let mut deps = mock_dependencies(&[]);
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        oracle_contract: "oracle0000".to_string(),
+        stable_denom: "uusd".to_string(),
+        safe_ratio: Decimal256::percent(10),
+        bid_fee: Decimal256::percent(1),
+        max_premium_rate: Decimal256::percent(5),
+        liquidation_threshold: Uint256::from(100000000u64),
+        price_timeframe: 60u64,
+        bid_retract_wait: 0u64,
+        liquidation_fee: Decimal256::percent(1),
+        fee_address: "protocol0000".to_string(),
+        bid_activation_delay: 0u64,
+        inactive_bid_ttl: None,
+        min_bid_amount: Uint256::from(1000u64),
+        dust_threshold: Uint256::from(1000u64),
+        overseer_contract: None,
+        caller_fee: None,
+        market_contract: None,
+    };
+
+    let env = mock_env();
+    deps.querier.with_oracle_price(&[(
+        &("asset0000".to_string(), "uusd".to_string()),
+        &PriceInfo {
+            rate: Decimal256::percent(50),
+            last_updated_base: env.block.time.seconds(),
+            last_updated_quote: env.block.time.seconds(),
+        },
+    )]);
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::WhitelistCollateral {
+        collateral_token: "asset0000".to_string(),
+        max_slot: 5,
+        premium_rate_per_slot: Decimal256::percent(1),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg).unwrap();
+
+    let submit_msg = ExecuteMsg::SubmitBid {
+        collateral_token: "asset0000".to_string(),
+        premium_slot: 0,
+    };
+    let submit_info = mock_info(
+        "addr0000",
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::from(1000000u128),
+        }],
+    );
+    let retract_msg = ExecuteMsg::RetractBid {
+        bid_idx: 1,
+        amount: Some(Uint256::from(100000u64)),
+    };
+    let hook_msg = |amount: u128, hook: Cw20HookMsg| {
+        ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "custody0000".to_string(),
+            amount: Uint128::from(amount),
+            msg: to_binary(&hook).unwrap(),
+        })
+    };
+    // the contract balance is set to what it would hold after each step
+    let health = |deps: &mut OwnedDeps<MockStorage, MockApi, WasmMockQuerier>,
+                  balance: u128|
+     -> EscrowHealthResponse {
+        deps.querier.update_balance(
+            MOCK_CONTRACT_ADDR,
+            vec![Coin {
+                denom: "uusd".to_string(),
+                amount: Uint128::from(balance),
+            }],
+        );
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::EscrowHealth {}).unwrap()).unwrap()
+    };
+    let expected = |total_escrowed: u64, balance: u64, undistributed: u64, consistent: bool| {
+        EscrowHealthResponse {
+            total_escrowed: Uint256::from(total_escrowed),
+            balance: Uint256::from(balance),
+            undistributed: Uint256::from(undistributed),
+            consistent,
+            surplus: Uint256::from(balance.saturating_sub(total_escrowed + undistributed)),
+            bids_paused: false,
+        }
+    };
+
+    assert_eq!(health(&mut deps, 0), expected(0, 0, 0, true));
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        submit_info.clone(),
+        submit_msg.clone(),
+    )
+    .unwrap();
+    assert_eq!(
+        health(&mut deps, 1000000),
+        expected(1000000, 1000000, 0, true)
+    );
+
+    // 1,000,000 at 0.5 pays 500,000, sent out right away
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("asset0000", &[]),
+        hook_msg(
+            1000000u128,
+            Cw20HookMsg::ExecuteBid {
+                liquidator: "liquidator0000".to_string(),
+                borrower: "borrower0000".to_string(),
+                fee_address: Some("fee0000".to_string()),
+                repay_address: Some("repay0000".to_string()),
+            },
+        ),
+    )
+    .unwrap();
+    assert_eq!(health(&mut deps, 500000), expected(500000, 500000, 0, true));
+
+    // 200,000 at 0.5 pays 100,000, held until the settlement
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("asset0000", &[]),
+        hook_msg(
+            200000u128,
+            Cw20HookMsg::AccumulateBid {
+                liquidator: "liquidator0000".to_string(),
+                borrower: "borrower0000".to_string(),
+                fee_address: Some("fee0000".to_string()),
+                repay_address: Some("repay0000".to_string()),
+            },
+        ),
+    )
+    .unwrap();
+    assert_eq!(
+        health(&mut deps, 500000),
+        expected(400000, 500000, 100000, true)
+    );
+
+    let msg = ExecuteMsg::SettleLiquidation {
+        borrower: "borrower0000".to_string(),
+        repay_address: Some("repay0000".to_string()),
+        collaterals: vec![("asset0000".to_string(), Uint256::from(200000u64))],
+    };
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("overseer0000", &[]),
+        msg,
+    )
+    .unwrap();
+    assert_eq!(health(&mut deps, 400000), expected(400000, 400000, 0, true));
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        retract_msg.clone(),
+    )
+    .unwrap();
+    assert_eq!(health(&mut deps, 300000), expected(300000, 300000, 0, true));
+
+    // stable missing from the contract pauses new bids
+    assert_eq!(
+        health(&mut deps, 299000),
+        expected(300000, 299000, 0, false)
+    );
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        ExecuteMsg::CheckEscrowHealth {},
+    );
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner0000", &[]),
+        ExecuteMsg::CheckEscrowHealth {},
+    )
+    .unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "check_escrow_health"),
+            attr("total_escrowed", "300000"),
+            attr("undistributed", "0"),
+            attr("balance", "299000"),
+            attr("surplus", "0"),
+            attr("bids_paused", "true"),
+        ]
+    );
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        submit_info.clone(),
+        submit_msg.clone(),
+    );
+    match res {
+        Err(ContractError::BidsPaused {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // bidders can still exit
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        retract_msg,
+    )
+    .unwrap();
+    assert_eq!(
+        health(&mut deps, 199000),
+        EscrowHealthResponse {
+            bids_paused: true,
+            ..expected(200000, 199000, 0, false)
+        }
+    );
+
+    // bids resume once the balance matches again
+    health(&mut deps, 200000);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner0000", &[]),
+        ExecuteMsg::CheckEscrowHealth {},
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        submit_info.clone(),
+        submit_msg.clone(),
+    )
+    .unwrap();
+    assert_eq!(
+        health(&mut deps, 1200000),
+        expected(1200000, 1200000, 0, true)
+    );
+
+    // stable sent straight to the contract is reported, but does not pause bids
+    assert_eq!(
+        health(&mut deps, 1250000),
+        expected(1200000, 1250000, 0, true)
+    );
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner0000", &[]),
+        ExecuteMsg::CheckEscrowHealth {},
+    )
+    .unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "check_escrow_health"),
+            attr("total_escrowed", "1200000"),
+            attr("undistributed", "0"),
+            attr("balance", "1250000"),
+            attr("surplus", "50000"),
+            attr("bids_paused", "false"),
+        ]
+    );
+    execute(deps.as_mut(), mock_env(), submit_info, submit_msg).unwrap();
+    assert_eq!(
+        health(&mut deps, 2250000),
+        expected(2200000, 2250000, 0, true)
+    );
+
+    // the migration rebuilds the total from the bid pools
+    store_escrow(deps.as_mut().storage, &Escrow::default()).unwrap();
+    let res = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "migrate"),
+            attr("total_escrowed", "2200000"),
+            attr("undistributed", "0"),
+        ]
+    );
+    assert_eq!(
+        health(&mut deps, 2250000),
+        expected(2200000, 2250000, 0, true)
+    );
+}
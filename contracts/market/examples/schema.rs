@@ -4,10 +4,10 @@ use std::fs::create_dir_all;
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 
 use moneymarket::market::{
-    BorrowerInfoResponse, BorrowerInfosResponse, ConfigResponse, Cw20HookMsg, EpochStateResponse,
-    ExecuteMsg, InstantiateMsg, QueryMsg,
+    AccruedInterestResponse, BorrowerInfoResponse, BorrowerInfosResponse, ConfigResponse,
+    Cw20HookMsg, EpochStateResponse, ExecuteMsg, InstantiateMsg, QueryMsg, RatesResponse,
+    StateResponse,
 };
-use moneymarket_market::state::State;
 
 fn main() {
     let mut out_dir = current_dir().unwrap();
@@ -20,8 +20,10 @@ fn main() {
     export_schema(&schema_for!(Cw20HookMsg), &out_dir);
     export_schema(&schema_for!(QueryMsg), &out_dir);
     export_schema(&schema_for!(ConfigResponse), &out_dir);
-    export_schema(&schema_for!(State), &out_dir);
+    export_schema(&schema_for!(StateResponse), &out_dir);
     export_schema(&schema_for!(EpochStateResponse), &out_dir);
     export_schema(&schema_for!(BorrowerInfoResponse), &out_dir);
     export_schema(&schema_for!(BorrowerInfosResponse), &out_dir);
+    export_schema(&schema_for!(AccruedInterestResponse), &out_dir);
+    export_schema(&schema_for!(RatesResponse), &out_dir);
 }
@@ -5,13 +5,13 @@ use cosmwasm_std::{
     StdResult, WasmMsg,
 };
 use moneymarket::interest_model::BorrowRateResponse;
-use moneymarket::market::{BorrowerInfoResponse, BorrowerInfosResponse};
+use moneymarket::market::{AccruedInterestResponse, BorrowerInfoResponse, BorrowerInfosResponse};
 use moneymarket::overseer::BorrowLimitResponse;
-use moneymarket::querier::{deduct_tax, query_balance, query_supply};
+use moneymarket::querier::{deduct_tax, query_balance, query_borrow_limit, query_token_supply};
 
 use crate::deposit::compute_exchange_rate_raw;
 use crate::error::ContractError;
-use crate::querier::{query_borrow_limit, query_borrow_rate, query_target_deposit_rate};
+use crate::querier::{query_borrow_rate, query_liquidation_contract, query_target_deposit_rate};
 use crate::state::{
     read_borrower_info, read_borrower_infos, read_config, read_state, store_borrower_info,
     store_state, BorrowerInfo, Config, State,
@@ -64,6 +64,9 @@ pub fn borrow_stable(
     assert_max_borrow_factor(&config, &state, current_balance, borrow_amount)?;
 
     liability.loan_amount += borrow_amount;
+    liability.principal = liability
+        .principal
+        .map(|principal| principal + borrow_amount);
     state.total_liabilities += Decimal256::from_uint256(borrow_amount);
     store_state(deps.storage, &state)?;
     store_borrower_info(deps.storage, &borrower_raw, &liability)?;
@@ -91,27 +94,16 @@ pub fn repay_stable_from_liquidation(
     env: Env,
     info: MessageInfo,
     borrower: Addr,
-    prev_balance: Uint256,
 ) -> Result<Response, ContractError> {
     let config: Config = read_config(deps.storage)?;
-    if config.overseer_contract != deps.api.addr_canonicalize(info.sender.as_str())? {
+    let overseer_contract = deps.api.addr_humanize(&config.overseer_contract)?;
+    if query_liquidation_contract(deps.as_ref(), overseer_contract)? != info.sender {
         return Err(ContractError::Unauthorized {});
     }
 
-    let cur_balance: Uint256 = query_balance(
-        deps.as_ref(),
-        env.contract.address.clone(),
-        config.stable_denom.to_string(),
-    )?;
-
-    // override env
+    // the attached stable repays the borrower's loan
     let mut info = info;
-
     info.sender = borrower;
-    info.funds = vec![Coin {
-        denom: config.stable_denom,
-        amount: (cur_balance - prev_balance).into(),
-    }];
 
     repay_stable(deps, env, info)
 }
@@ -152,6 +144,9 @@ pub fn repay_stable(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Respon
     compute_reward(&mut state, env.block.height);
     compute_borrower_reward(&state, &mut liability);
 
+    // Repayments settle the accrued interest first
+    let accrued_interest = compute_accrued_interest(&liability);
+
     let repay_amount: Uint256;
     let mut messages: Vec<CosmosMsg> = vec![];
     if liability.loan_amount < amount {
@@ -174,6 +169,17 @@ pub fn repay_stable(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Respon
         liability.loan_amount = liability.loan_amount - repay_amount;
     }
 
+    if repay_amount > accrued_interest {
+        let principal_repaid = repay_amount - accrued_interest;
+        liability.principal = liability.principal.map(|principal| {
+            if principal > principal_repaid {
+                principal - principal_repaid
+            } else {
+                Uint256::zero()
+            }
+        });
+    }
+
     state.total_liabilities = state.total_liabilities - Decimal256::from_uint256(repay_amount);
 
     store_borrower_info(deps.storage, &borrower_raw, &liability)?;
@@ -252,7 +258,7 @@ pub fn compute_interest(
         return Ok(());
     }
 
-    let aterra_supply = query_supply(deps, deps.api.addr_humanize(&config.aterra_contract)?)?;
+    let aterra_supply = query_token_supply(deps, deps.api.addr_humanize(&config.aterra_contract)?)?;
     let balance: Uint256 = query_balance(
         deps,
         deps.api.addr_humanize(&config.contract_addr)?,
@@ -337,6 +343,16 @@ pub(crate) fn compute_borrower_interest(state: &State, liability: &mut BorrowerI
     liability.interest_index = state.global_interest_index;
 }
 
+/// Interest the liability accrued on top of its principal
+pub(crate) fn compute_accrued_interest(liability: &BorrowerInfo) -> Uint256 {
+    let principal = liability.principal.unwrap_or(liability.loan_amount);
+    if liability.loan_amount > principal {
+        liability.loan_amount - principal
+    } else {
+        Uint256::zero()
+    }
+}
+
 /// Compute distributed reward and update global index
 pub fn compute_reward(state: &mut State, block_height: u64) {
     if state.last_reward_updated >= block_height {
@@ -397,6 +413,34 @@ pub fn query_borrower_info(
     })
 }
 
+pub fn query_accrued_interest(
+    deps: Deps,
+    env: Env,
+    borrower: Addr,
+    block_height: Option<u64>,
+) -> StdResult<AccruedInterestResponse> {
+    let mut borrower_info: BorrowerInfo = read_borrower_info(
+        deps.storage,
+        &deps.api.addr_canonicalize(borrower.as_str())?,
+    );
+
+    let block_height = block_height.unwrap_or(env.block.height);
+
+    let config: Config = read_config(deps.storage)?;
+    let mut state: State = read_state(deps.storage)?;
+
+    compute_interest(deps, &config, &mut state, block_height, None)?;
+    compute_borrower_interest(&state, &mut borrower_info);
+
+    let accrued_interest = compute_accrued_interest(&borrower_info);
+    Ok(AccruedInterestResponse {
+        borrower: borrower.to_string(),
+        principal: borrower_info.loan_amount - accrued_interest,
+        total_liability: borrower_info.loan_amount,
+        accrued_interest,
+    })
+}
+
 pub fn query_borrower_infos(
     deps: Deps,
     start_after: Option<Addr>,
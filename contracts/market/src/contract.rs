@@ -3,11 +3,14 @@ use cosmwasm_std::entry_point;
 
 use crate::borrow::{
     borrow_stable, claim_rewards, compute_interest, compute_interest_raw, compute_reward,
-    query_borrower_info, query_borrower_infos, repay_stable, repay_stable_from_liquidation,
+    query_accrued_interest, query_borrower_info, query_borrower_infos, repay_stable,
+    repay_stable_from_liquidation,
 };
 use crate::deposit::{compute_exchange_rate_raw, deposit_stable, redeem_stable};
 use crate::error::ContractError;
-use crate::querier::{query_anc_emission_rate, query_borrow_rate, query_target_deposit_rate};
+use crate::querier::{
+    query_anc_emission_rate, query_borrow_rate, query_model_info, query_target_deposit_rate,
+};
 use crate::response::MsgInstantiateContractResponse;
 use crate::state::{read_config, read_state, store_config, store_state, Config, State};
 
@@ -19,17 +22,26 @@ use cosmwasm_std::{
 use cw20::{Cw20Coin, Cw20ReceiveMsg, MinterResponse};
 
 use moneymarket::common::optional_addr_validate;
-use moneymarket::interest_model::BorrowRateResponse;
+use moneymarket::interest_model::{
+    BorrowRateResponse, ExecuteMsg as InterestModelExecuteMsg, MODEL_TYPE_FIXED_V1,
+    MODEL_TYPE_KINKED_V1,
+};
 use moneymarket::market::{
     ConfigResponse, Cw20HookMsg, EpochStateResponse, ExecuteMsg, InstantiateMsg, QueryMsg,
-    StateResponse,
+    RatesResponse, StateResponse,
+};
+use moneymarket::querier::{deduct_tax, query_balance, query_token_supply};
+use moneymarket::rates::{
+    compute_utilization_ratio, per_block_to_apr, per_block_to_apy, BLOCKS_PER_YEAR,
 };
-use moneymarket::querier::{deduct_tax, query_balance, query_supply};
 use protobuf::Message;
 use terraswap::token::InstantiateMsg as TokenInstantiateMsg;
 
 pub const INITIAL_DEPOSIT_AMOUNT: u128 = 1000000;
 
+/// Interest model types the market can swap to without force
+const SUPPORTED_INTEREST_MODELS: &[&str] = &[MODEL_TYPE_KINKED_V1, MODEL_TYPE_FIXED_V1];
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     deps: DepsMut,
@@ -51,6 +63,11 @@ pub fn instantiate(
         ));
     }
 
+    let blocks_per_year = msg.blocks_per_year.unwrap_or(BLOCKS_PER_YEAR);
+    if blocks_per_year == 0 {
+        return Err(ContractError::InvalidBlocksPerYear {});
+    }
+
     store_config(
         deps.storage,
         &Config {
@@ -64,6 +81,7 @@ pub fn instantiate(
             distributor_contract: CanonicalAddr::from(vec![]),
             stable_denom: msg.stable_denom.clone(),
             max_borrow_factor: msg.max_borrow_factor,
+            blocks_per_year,
         },
     )?;
 
@@ -142,6 +160,8 @@ pub fn execute(
             interest_model,
             distribution_model,
             max_borrow_factor,
+            blocks_per_year,
+            force,
         } => {
             let api = deps.api;
             update_config(
@@ -152,6 +172,8 @@ pub fn execute(
                 optional_addr_validate(api, interest_model)?,
                 optional_addr_validate(api, distribution_model)?,
                 max_borrow_factor,
+                blocks_per_year,
+                force.unwrap_or(false),
             )
         }
         ExecuteMsg::ExecuteEpochOperations {
@@ -180,18 +202,9 @@ pub fn execute(
             )
         }
         ExecuteMsg::RepayStable {} => repay_stable(deps, env, info),
-        ExecuteMsg::RepayStableFromLiquidation {
-            borrower,
-            prev_balance,
-        } => {
+        ExecuteMsg::RepayStableFromLiquidation { borrower } => {
             let api = deps.api;
-            repay_stable_from_liquidation(
-                deps,
-                env,
-                info,
-                api.addr_validate(&borrower)?,
-                prev_balance,
-            )
+            repay_stable_from_liquidation(deps, env, info, api.addr_validate(&borrower)?)
         }
         ExecuteMsg::ClaimRewards { to } => {
             let api = deps.api;
@@ -284,6 +297,7 @@ pub fn register_contracts(
     Ok(Response::default())
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn update_config(
     deps: DepsMut,
     env: Env,
@@ -292,6 +306,8 @@ pub fn update_config(
     interest_model: Option<Addr>,
     distribution_model: Option<Addr>,
     max_borrow_factor: Option<Decimal256>,
+    blocks_per_year: Option<u64>,
+    force: bool,
 ) -> Result<Response, ContractError> {
     let mut config: Config = read_config(deps.storage)?;
 
@@ -304,6 +320,17 @@ pub fn update_config(
         config.owner_addr = deps.api.addr_canonicalize(owner_addr.as_str())?;
     }
 
+    if let Some(interest_model) = &interest_model {
+        if !force {
+            let model_info = query_model_info(deps.as_ref(), interest_model.clone())?;
+            if !SUPPORTED_INTEREST_MODELS.contains(&model_info.model_type.as_str()) {
+                return Err(ContractError::UnsupportedInterestModel(
+                    model_info.model_type,
+                ));
+            }
+        }
+    }
+
     if interest_model.is_some() {
         let mut state: State = read_state(deps.storage)?;
         compute_interest(deps.as_ref(), &config, &mut state, env.block.height, None)?;
@@ -322,6 +349,13 @@ pub fn update_config(
         config.max_borrow_factor = max_borrow_factor;
     }
 
+    if let Some(blocks_per_year) = blocks_per_year {
+        if blocks_per_year == 0 {
+            return Err(ContractError::InvalidBlocksPerYear {});
+        }
+        config.blocks_per_year = blocks_per_year;
+    }
+
     store_config(deps.storage, &config)?;
     Ok(Response::new().add_attributes(vec![attr("action", "update_config")]))
 }
@@ -343,7 +377,7 @@ pub fn execute_epoch_operations(
     let mut state: State = read_state(deps.storage)?;
 
     // Compute interest and reward before updating anc_emission_rate
-    let aterra_supply = query_supply(
+    let aterra_supply = query_token_supply(
         deps.as_ref(),
         deps.api.addr_humanize(&config.aterra_contract)?,
     )?;
@@ -361,6 +395,33 @@ pub fn execute_epoch_operations(
         state.total_reserves,
     )?;
 
+    // Let the interest model smooth the utilization over epochs. It is
+    // recorded once per epoch rather than on every interest accrual, so
+    // the smoothing factor applies per epoch and a borrower cannot move
+    // the average by transacting repeatedly. Models that do not register
+    // this market, or cannot describe themselves, are not sent the record.
+    let interest_model = deps.api.addr_humanize(&config.interest_model)?;
+    let records_utilization = query_model_info(deps.as_ref(), interest_model.clone())
+        .map(|model_info| {
+            model_info.params.market_contract == Some(env.contract.address.to_string())
+        })
+        .unwrap_or(false);
+    let record_utilization_msgs: Vec<CosmosMsg> = if records_utilization {
+        vec![CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: interest_model.to_string(),
+            funds: vec![],
+            msg: to_binary(&InterestModelExecuteMsg::RecordUtilization {
+                utilization: compute_utilization_ratio(
+                    balance,
+                    state.total_liabilities,
+                    state.total_reserves,
+                ),
+            })?,
+        })]
+    } else {
+        vec![]
+    };
+
     compute_interest_raw(
         &mut state,
         env.block.height,
@@ -413,11 +474,15 @@ pub fn execute_epoch_operations(
 
     store_state(deps.storage, &state)?;
 
-    Ok(Response::new().add_messages(messages).add_attributes(vec![
-        attr("action", "execute_epoch_operations"),
-        attr("total_reserves", total_reserves),
-        attr("anc_emission_rate", state.anc_emission_rate.to_string()),
-    ]))
+    Ok(Response::new()
+        .add_messages(record_utilization_msgs)
+        .add_messages(messages)
+        .add_attributes(vec![
+            attr("action", "execute_epoch_operations"),
+            attr("total_reserves", total_reserves),
+            attr("anc_emission_rate", state.anc_emission_rate.to_string()),
+            attr("borrow_rate_clamped", borrow_rate_res.clamped.to_string()),
+        ]))
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
@@ -447,6 +512,16 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
             optional_addr_validate(deps.api, start_after)?,
             limit,
         )?),
+        QueryMsg::AccruedInterest {
+            borrower,
+            block_height,
+        } => to_binary(&query_accrued_interest(
+            deps,
+            env,
+            deps.api.addr_validate(&borrower)?,
+            block_height,
+        )?),
+        QueryMsg::Rates {} => to_binary(&query_rates(deps)?),
     }
 }
 
@@ -474,6 +549,7 @@ pub fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
             .to_string(),
         stable_denom: config.stable_denom,
         max_borrow_factor: config.max_borrow_factor,
+        blocks_per_year: config.blocks_per_year,
     })
 }
 
@@ -519,6 +595,32 @@ pub fn query_state(deps: Deps, env: Env, block_height: Option<u64>) -> StdResult
     })
 }
 
+/// Borrow rate the interest model currently returns for the market
+pub fn query_rates(deps: Deps) -> StdResult<RatesResponse> {
+    let config: Config = read_config(deps.storage)?;
+    let state: State = read_state(deps.storage)?;
+
+    let balance = query_balance(
+        deps,
+        deps.api.addr_humanize(&config.contract_addr)?,
+        config.stable_denom.to_string(),
+    )?;
+    let borrow_rate = query_borrow_rate(
+        deps,
+        deps.api.addr_humanize(&config.interest_model)?,
+        balance,
+        state.total_liabilities,
+        state.total_reserves,
+    )?
+    .rate;
+
+    Ok(RatesResponse {
+        borrow_rate,
+        borrow_apr: per_block_to_apr(borrow_rate, config.blocks_per_year),
+        borrow_apy: per_block_to_apy(borrow_rate, config.blocks_per_year)?,
+    })
+}
+
 pub fn query_epoch_state(
     deps: Deps,
     block_height: Option<u64>,
@@ -528,7 +630,7 @@ pub fn query_epoch_state(
     let mut state: State = read_state(deps.storage)?;
 
     let distributed_interest = distributed_interest.unwrap_or_else(Uint256::zero);
-    let aterra_supply = query_supply(deps, deps.api.addr_humanize(&config.aterra_contract)?)?;
+    let aterra_supply = query_token_supply(deps, deps.api.addr_humanize(&config.aterra_contract)?)?;
     let balance = query_balance(
         deps,
         deps.api.addr_humanize(&config.contract_addr)?,
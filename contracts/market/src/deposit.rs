@@ -9,7 +9,7 @@ use crate::error::ContractError;
 use crate::state::{read_config, read_state, store_state, Config, State};
 
 use cw20::Cw20ExecuteMsg;
-use moneymarket::querier::{deduct_tax, query_balance, query_supply};
+use moneymarket::querier::{deduct_tax, query_balance, query_token_supply};
 
 pub fn deposit_stable(
     deps: DepsMut,
@@ -144,7 +144,7 @@ pub(crate) fn compute_exchange_rate(
     state: &State,
     deposit_amount: Option<Uint256>,
 ) -> StdResult<Decimal256> {
-    let aterra_supply = query_supply(deps, deps.api.addr_humanize(&config.aterra_contract)?)?;
+    let aterra_supply = query_token_supply(deps, deps.api.addr_humanize(&config.aterra_contract)?)?;
     let balance = query_balance(
         deps,
         deps.api.addr_humanize(&config.contract_addr)?,
@@ -18,6 +18,12 @@ pub enum ContractError {
     #[error("Must deposit initial funds {0}{0}")]
     InitialFundsNotDeposited(u128, String),
 
+    #[error("Unsupported interest model type: {0}")]
+    UnsupportedInterestModel(String),
+
+    #[error("Blocks per year must be greater than 0")]
+    InvalidBlocksPerYear {},
+
     #[error("Invalid reply ID")]
     InvalidReplyId {},
 
@@ -2,8 +2,10 @@ use cosmwasm_bignumber::{Decimal256, Uint256};
 use cosmwasm_std::{to_binary, Addr, Deps, QueryRequest, StdResult, WasmQuery};
 
 use moneymarket::distribution_model::{AncEmissionRateResponse, QueryMsg as DistributionQueryMsg};
-use moneymarket::interest_model::{BorrowRateResponse, QueryMsg as InterestQueryMsg};
-use moneymarket::overseer::{BorrowLimitResponse, ConfigResponse, QueryMsg as OverseerQueryMsg};
+use moneymarket::interest_model::{
+    BorrowRateResponse, ModelInfoResponse, QueryMsg as InterestQueryMsg,
+};
+use moneymarket::overseer::{ConfigResponse, QueryMsg as OverseerQueryMsg};
 
 pub fn query_borrow_rate(
     deps: Deps,
@@ -25,22 +27,14 @@ pub fn query_borrow_rate(
     Ok(borrow_rate)
 }
 
-pub fn query_borrow_limit(
-    deps: Deps,
-    overseer_addr: Addr,
-    borrower: Addr,
-    block_time: Option<u64>,
-) -> StdResult<BorrowLimitResponse> {
-    let borrow_limit: BorrowLimitResponse =
+pub fn query_model_info(deps: Deps, interest_addr: Addr) -> StdResult<ModelInfoResponse> {
+    let model_info: ModelInfoResponse =
         deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
-            contract_addr: overseer_addr.to_string(),
-            msg: to_binary(&OverseerQueryMsg::BorrowLimit {
-                borrower: borrower.to_string(),
-                block_time,
-            })?,
+            contract_addr: interest_addr.to_string(),
+            msg: to_binary(&InterestQueryMsg::ModelInfo {})?,
         }))?;
 
-    Ok(borrow_limit)
+    Ok(model_info)
 }
 
 pub fn query_anc_emission_rate(
@@ -65,6 +59,17 @@ pub fn query_anc_emission_rate(
     Ok(anc_emission_rate)
 }
 
+pub fn query_liquidation_contract(deps: Deps, overseer_contract: Addr) -> StdResult<Addr> {
+    let overseer_config: ConfigResponse =
+        deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+            contract_addr: overseer_contract.to_string(),
+            msg: to_binary(&OverseerQueryMsg::Config {})?,
+        }))?;
+
+    deps.api
+        .addr_validate(&overseer_config.liquidation_contract)
+}
+
 pub fn query_target_deposit_rate(deps: Deps, overseer_contract: Addr) -> StdResult<Decimal256> {
     let overseer_config: ConfigResponse =
         deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
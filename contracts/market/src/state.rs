@@ -6,6 +6,8 @@ use cosmwasm_std::{CanonicalAddr, Deps, Order, StdResult, Storage};
 use cosmwasm_storage::{bucket, bucket_read, ReadonlyBucket, ReadonlySingleton, Singleton};
 
 use moneymarket::market::BorrowerInfoResponse;
+use moneymarket::pagination::{calc_range_start_canonical, clamp_limit, DEFAULT_LIMIT, MAX_LIMIT};
+use moneymarket::rates::BLOCKS_PER_YEAR;
 
 pub const KEY_CONFIG: &[u8] = b"config";
 pub const KEY_STATE: &[u8] = b"state";
@@ -24,6 +26,12 @@ pub struct Config {
     pub distributor_contract: CanonicalAddr,
     pub stable_denom: String,
     pub max_borrow_factor: Decimal256,
+    #[serde(default = "default_blocks_per_year")]
+    pub blocks_per_year: u64,
+}
+
+fn default_blocks_per_year() -> u64 {
+    BLOCKS_PER_YEAR
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -45,6 +53,10 @@ pub struct BorrowerInfo {
     pub reward_index: Decimal256,
     pub loan_amount: Uint256,
     pub pending_rewards: Decimal256,
+    /// Amount borrowed and not repaid yet, excluding interest;
+    /// missing for liabilities recorded before it was tracked
+    #[serde(default)]
+    pub principal: Option<Uint256>,
 }
 
 pub fn store_config(storage: &mut dyn Storage, data: &Config) -> StdResult<()> {
@@ -73,19 +85,23 @@ pub fn store_borrower_info(
 
 pub fn read_borrower_info(storage: &dyn Storage, borrower: &CanonicalAddr) -> BorrowerInfo {
     match bucket_read(storage, PREFIX_LIABILITY).load(borrower.as_slice()) {
-        Ok(v) => v,
+        Ok(v) => {
+            let mut v: BorrowerInfo = v;
+            // the liability at the last action of untracked
+            // borrowers counts as principal
+            v.principal = v.principal.or(Some(v.loan_amount));
+            v
+        }
         _ => BorrowerInfo {
             interest_index: Decimal256::one(),
             reward_index: Decimal256::zero(),
             loan_amount: Uint256::zero(),
             pending_rewards: Decimal256::zero(),
+            principal: Some(Uint256::zero()),
         },
     }
 }
 
-// settings for pagination
-const MAX_LIMIT: u32 = 30;
-const DEFAULT_LIMIT: u32 = 10;
 pub fn read_borrower_infos(
     deps: Deps,
     start_after: Option<CanonicalAddr>,
@@ -94,8 +110,8 @@ pub fn read_borrower_infos(
     let liability_bucket: ReadonlyBucket<BorrowerInfo> =
         bucket_read(deps.storage, PREFIX_LIABILITY);
 
-    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
-    let start = calc_range_start(start_after);
+    let limit = clamp_limit(limit, DEFAULT_LIMIT, MAX_LIMIT);
+    let start = calc_range_start_canonical(start_after);
 
     liability_bucket
         .range(start.as_deref(), None, Order::Ascending)
@@ -113,12 +129,3 @@ pub fn read_borrower_infos(
         })
         .collect()
 }
-
-// this will set the first key after the provided key, by appending a 1 byte
-fn calc_range_start(start_after: Option<CanonicalAddr>) -> Option<Vec<u8>> {
-    start_after.map(|addr| {
-        let mut v = addr.as_slice().to_vec();
-        v.push(1);
-        v
-    })
-}
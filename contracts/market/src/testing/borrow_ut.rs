@@ -4,6 +4,7 @@ use crate::testing::mock_querier::mock_dependencies;
 use cosmwasm_bignumber::{Decimal256, Uint256};
 use cosmwasm_std::testing::{mock_env, MOCK_CONTRACT_ADDR};
 use cosmwasm_std::{Api, Coin, Uint128};
+use moneymarket::rates::BLOCKS_PER_YEAR;
 
 #[test]
 fn proper_compute_borrower_interest() {
@@ -24,6 +25,7 @@ fn proper_compute_borrower_interest() {
         reward_index: Decimal256::zero(),
         loan_amount: Uint256::zero(),
         pending_rewards: Decimal256::zero(),
+        principal: None,
     };
     compute_borrower_interest(&mock_state, &mut liability1);
     let liability2 = BorrowerInfo {
@@ -31,6 +33,7 @@ fn proper_compute_borrower_interest() {
         reward_index: Decimal256::zero(),
         loan_amount: Uint256::zero(),
         pending_rewards: Decimal256::zero(),
+        principal: None,
     };
     assert_eq!(liability1, liability2);
 
@@ -50,6 +53,7 @@ fn proper_compute_borrower_interest() {
         reward_index: Decimal256::zero(),
         loan_amount: Uint256::from(80u128),
         pending_rewards: Decimal256::zero(),
+        principal: None,
     };
     compute_borrower_interest(&mock_state2, &mut liability3);
     let liability4 = BorrowerInfo {
@@ -57,6 +61,7 @@ fn proper_compute_borrower_interest() {
         reward_index: Decimal256::zero(),
         loan_amount: Uint256::from(40u128),
         pending_rewards: Decimal256::zero(),
+        principal: None,
     };
     assert_eq!(liability3, liability4);
 }
@@ -86,6 +91,7 @@ fn proper_compute_interest() {
         overseer_contract: deps.api.addr_canonicalize("overseer").unwrap(),
         stable_denom: "uusd".to_string(),
         max_borrow_factor: Decimal256::one(),
+        blocks_per_year: BLOCKS_PER_YEAR,
     };
 
     deps.querier
@@ -4,6 +4,7 @@ use crate::testing::mock_querier::mock_dependencies;
 use cosmwasm_bignumber::{Decimal256, Uint256};
 use cosmwasm_std::testing::{mock_env, MOCK_CONTRACT_ADDR};
 use cosmwasm_std::{Api, Coin, Uint128};
+use moneymarket::rates::BLOCKS_PER_YEAR;
 
 #[test]
 fn proper_compute_exchange_rate() {
@@ -24,6 +25,7 @@ fn proper_compute_exchange_rate() {
         overseer_contract: deps.api.addr_canonicalize("overseer").unwrap(),
         stable_denom: "uusd".to_string(),
         max_borrow_factor: Decimal256::one(),
+        blocks_per_year: BLOCKS_PER_YEAR,
     };
     deps.querier.with_token_balances(&[(
         &"AT-uusd".to_string(),
@@ -12,7 +12,10 @@ use std::collections::HashMap;
 
 use cw20::TokenInfoResponse;
 use moneymarket::distribution_model::AncEmissionRateResponse;
-use moneymarket::interest_model::BorrowRateResponse;
+use moneymarket::interest_model::{
+    BorrowRateResponse, ConfigResponse as InterestModelConfigResponse, ModelInfoResponse, RateMode,
+    UtilizationMode,
+};
 use moneymarket::overseer::{BorrowLimitResponse, ConfigResponse};
 use terra_cosmwasm::{TaxCapResponse, TaxRateResponse, TerraQuery, TerraQueryWrapper, TerraRoute};
 
@@ -25,6 +28,8 @@ pub enum QueryMsg {
         total_liabilities: Decimal256,
         total_reserves: Decimal256,
     },
+    /// Query model info to interest model contract
+    ModelInfo {},
     /// Query borrow limit to overseer contract
     BorrowLimit {
         borrower: String,
@@ -64,6 +69,7 @@ pub struct WasmMockQuerier {
     tax_querier: TaxQuerier,
     borrow_rate_querier: BorrowRateQuerier,
     borrow_limit_querier: BorrowLimitQuerier,
+    model_type_querier: ModelTypeQuerier,
 }
 
 #[derive(Clone, Default)]
@@ -167,6 +173,28 @@ pub(crate) fn borrow_limit_to_map(
     borrow_limit_map
 }
 
+#[derive(Clone, Default)]
+pub struct ModelTypeQuerier {
+    // this lets us iterate over all pairs that match the first string
+    model_type: HashMap<String, String>,
+    // market registered to record its utilization, by interest model
+    market_contract: HashMap<String, String>,
+}
+
+impl ModelTypeQuerier {
+    pub fn new(model_type: &[(&String, &String)]) -> Self {
+        let mut model_type_map: HashMap<String, String> = HashMap::new();
+        for (interest_model, model_type) in model_type.iter() {
+            model_type_map.insert((*interest_model).clone(), (*model_type).clone());
+        }
+
+        ModelTypeQuerier {
+            model_type: model_type_map,
+            market_contract: HashMap::new(),
+        }
+    }
+}
+
 impl Querier for WasmMockQuerier {
     fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
         // MockQuerier doesn't support Custom, so we ignore it completely here
@@ -217,13 +245,48 @@ impl WasmMockQuerier {
                         market_balance: _,
                         total_liabilities: _,
                         total_reserves: _,
-                    } => {
-                        match self.borrow_rate_querier.borrower_rate.get(contract_addr) {
+                    } => match self.borrow_rate_querier.borrower_rate.get(contract_addr) {
+                        Some(v) => {
+                            SystemResult::Ok(ContractResult::from(to_binary(&BorrowRateResponse {
+                                rate: *v,
+                                clamped: false,
+                            })))
+                        }
+                        None => SystemResult::Err(SystemError::InvalidRequest {
+                            error: "No borrow rate exists".to_string(),
+                            request: msg.as_slice().into(),
+                        }),
+                    },
+                    QueryMsg::ModelInfo {} => {
+                        match self.model_type_querier.model_type.get(contract_addr) {
                             Some(v) => SystemResult::Ok(ContractResult::from(to_binary(
-                                &BorrowRateResponse { rate: *v },
+                                &ModelInfoResponse {
+                                    model_type: v.clone(),
+                                    version: "0.0.0".to_string(),
+                                    params: InterestModelConfigResponse {
+                                        owner: "".to_string(),
+                                        risk_admin: None,
+                                        base_rate: Decimal256::zero(),
+                                        optimal_utilization: Decimal256::percent(80),
+                                        slope_1: Decimal256::zero(),
+                                        slope_2: Decimal256::zero(),
+                                        max_apr: Decimal256::zero(),
+                                        blocks_per_year: 1u64,
+                                        max_borrow_rate: Decimal256::zero(),
+                                        market_contract: self
+                                            .model_type_querier
+                                            .market_contract
+                                            .get(contract_addr)
+                                            .cloned(),
+                                        utilization_mode: UtilizationMode::Spot,
+                                        ema_smoothing_factor: Decimal256::one(),
+                                        ema_utilization: None,
+                                        rate_mode: RateMode::Dynamic,
+                                    },
+                                },
                             ))),
                             None => SystemResult::Err(SystemError::InvalidRequest {
-                                error: "No borrow rate exists".to_string(),
+                                error: "No model info exists".to_string(),
                                 request: msg.as_slice().into(),
                             }),
                         }
@@ -236,6 +299,7 @@ impl WasmMockQuerier {
                             &BorrowLimitResponse {
                                 borrower,
                                 borrow_limit: *v,
+                                collaterals: vec![],
                             },
                         ))),
                         None => SystemResult::Err(SystemError::InvalidRequest {
@@ -251,6 +315,7 @@ impl WasmMockQuerier {
                     } => SystemResult::Ok(ContractResult::from(to_binary(
                         &AncEmissionRateResponse {
                             emission_rate: Decimal256::from_uint256(5u64),
+                            unclamped_emission_rate: Decimal256::from_uint256(5u64),
                         },
                     ))),
                     QueryMsg::Config {} => {
@@ -258,7 +323,7 @@ impl WasmMockQuerier {
                             owner_addr: "".to_string(),
                             oracle_contract: "".to_string(),
                             market_contract: "".to_string(),
-                            liquidation_contract: "".to_string(),
+                            liquidation_contract: "liquidation".to_string(),
                             collector_contract: "".to_string(),
                             threshold_deposit_rate: Decimal256::one(),
                             target_deposit_rate: Decimal256::from_ratio(1, 100),
@@ -267,6 +332,12 @@ impl WasmMockQuerier {
                             stable_denom: "uusd".to_string(),
                             epoch_period: 100u64,
                             price_timeframe: 100u64,
+                            max_ltv: Decimal256::percent(90),
+                            guardian: None,
+                            paused: false,
+                            executor_incentive: Uint128::zero(),
+                            max_buffer_distribution: None,
+                            custody_batch_size: 10,
                         })))
                     }
                     QueryMsg::TokenInfo {} => {
@@ -342,6 +413,7 @@ impl WasmMockQuerier {
             tax_querier: TaxQuerier::default(),
             borrow_rate_querier: BorrowRateQuerier::default(),
             borrow_limit_querier: BorrowLimitQuerier::default(),
+            model_type_querier: ModelTypeQuerier::default(),
         }
     }
 
@@ -371,4 +443,21 @@ impl WasmMockQuerier {
     pub fn with_borrow_limit(&mut self, borrow_limit: &[(&String, &Uint256)]) {
         self.borrow_limit_querier = BorrowLimitQuerier::new(borrow_limit);
     }
+
+    pub fn with_model_type(&mut self, model_type: &[(&String, &String)]) {
+        self.model_type_querier = ModelTypeQuerier {
+            market_contract: self.model_type_querier.market_contract.clone(),
+            ..ModelTypeQuerier::new(model_type)
+        };
+    }
+
+    // configure the market each interest model records the utilization of
+    pub fn with_model_market_contract(&mut self, market_contract: &[(&String, &String)]) {
+        self.model_type_querier.market_contract = market_contract
+            .iter()
+            .map(|(interest_model, market_contract)| {
+                (interest_model.to_string(), market_contract.to_string())
+            })
+            .collect();
+    }
 }
@@ -1,22 +1,28 @@
 use crate::contract::{execute, instantiate, query, reply, INITIAL_DEPOSIT_AMOUNT};
 use crate::error::ContractError;
 use crate::response::MsgInstantiateContractResponse;
-use crate::state::{read_borrower_infos, read_state, store_state, State};
-use crate::testing::mock_querier::mock_dependencies;
+use crate::state::{
+    read_borrower_infos, read_state, store_borrower_info, store_state, BorrowerInfo, State,
+};
+use crate::testing::mock_querier::{mock_dependencies, WasmMockQuerier};
 
 use anchor_token::distributor::ExecuteMsg as FaucetExecuteMsg;
 use cosmwasm_bignumber::{Decimal256, Uint256};
-use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
+use cosmwasm_std::testing::{mock_env, mock_info, MockApi, MockStorage, MOCK_CONTRACT_ADDR};
 use cosmwasm_std::{
-    attr, from_binary, to_binary, Addr, BankMsg, Coin, ContractResult, CosmosMsg, Decimal, Reply,
-    SubMsg, SubMsgExecutionResponse, Uint128, WasmMsg,
+    attr, from_binary, to_binary, Addr, Api, BankMsg, Coin, ContractResult, CosmosMsg, Decimal,
+    OwnedDeps, Reply, SubMsg, SubMsgExecutionResponse, Uint128, WasmMsg,
 };
 use cw20::{Cw20Coin, Cw20ExecuteMsg, Cw20ReceiveMsg, MinterResponse};
+use moneymarket::interest_model::{
+    ExecuteMsg as InterestModelExecuteMsg, MODEL_TYPE_FIXED_V1, MODEL_TYPE_KINKED_V1,
+};
 use moneymarket::market::{
-    BorrowerInfoResponse, ConfigResponse, Cw20HookMsg, ExecuteMsg, InstantiateMsg, QueryMsg,
-    StateResponse,
+    AccruedInterestResponse, BorrowerInfoResponse, ConfigResponse, Cw20HookMsg, ExecuteMsg,
+    InstantiateMsg, QueryMsg, RatesResponse, StateResponse,
 };
 use moneymarket::querier::deduct_tax;
+use moneymarket::rates::BLOCKS_PER_YEAR;
 use protobuf::Message;
 use std::str::FromStr;
 use terraswap::token::InstantiateMsg as TokenInstantiateMsg;
@@ -34,6 +40,7 @@ fn proper_initialization() {
         aterra_code_id: 123u64,
         anc_emission_rate: Decimal256::one(),
         max_borrow_factor: Decimal256::one(),
+        blocks_per_year: None,
     };
 
     let info = mock_info(
@@ -121,6 +128,7 @@ fn proper_initialization() {
     assert_eq!("overseer".to_string(), config_res.overseer_contract);
     assert_eq!("uusd".to_string(), config_res.stable_denom);
     assert_eq!(Decimal256::one(), config_res.max_borrow_factor);
+    assert_eq!(BLOCKS_PER_YEAR, config_res.blocks_per_year);
 
     let query_res = query(
         deps.as_ref(),
@@ -153,6 +161,7 @@ fn update_config() {
         aterra_code_id: 123u64,
         anc_emission_rate: Decimal256::one(),
         max_borrow_factor: Decimal256::one(),
+        blocks_per_year: None,
     };
 
     let info = mock_info(
@@ -196,6 +205,8 @@ fn update_config() {
         interest_model: None,
         distribution_model: None,
         max_borrow_factor: None,
+        blocks_per_year: None,
+        force: None,
     };
 
     let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -207,12 +218,18 @@ fn update_config() {
     assert_eq!("owner1".to_string(), config_res.owner_addr);
 
     // update left items
+    deps.querier.with_model_type(&[
+        (&"interest2".to_string(), &MODEL_TYPE_KINKED_V1.to_string()),
+        (&"interest3".to_string(), &"linear_v1".to_string()),
+    ]);
     let info = mock_info("owner1", &[]);
     let msg = ExecuteMsg::UpdateConfig {
         owner_addr: None,
         interest_model: Some("interest2".to_string()),
         distribution_model: Some("distribution2".to_string()),
         max_borrow_factor: Some(Decimal256::percent(100)),
+        blocks_per_year: Some(5_000_000),
+        force: None,
     };
 
     let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -225,6 +242,53 @@ fn update_config() {
     assert_eq!("interest2".to_string(), config_res.interest_model);
     assert_eq!("distribution2".to_string(), config_res.distribution_model);
     assert_eq!(Decimal256::percent(100), config_res.max_borrow_factor);
+    assert_eq!(5_000_000, config_res.blocks_per_year);
+
+    // rates cannot be annualized over zero blocks
+    let msg = ExecuteMsg::UpdateConfig {
+        owner_addr: None,
+        interest_model: None,
+        distribution_model: None,
+        max_borrow_factor: None,
+        blocks_per_year: Some(0),
+        force: None,
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("owner1", &[]), msg);
+    match res {
+        Err(ContractError::InvalidBlocksPerYear {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // unknown interest model types are rejected
+    let msg = ExecuteMsg::UpdateConfig {
+        owner_addr: None,
+        interest_model: Some("interest3".to_string()),
+        distribution_model: None,
+        max_borrow_factor: None,
+        blocks_per_year: None,
+        force: None,
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("owner1", &[]), msg);
+    match res {
+        Err(ContractError::UnsupportedInterestModel(model_type)) => {
+            assert_eq!(model_type, "linear_v1")
+        }
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // unless forced
+    let msg = ExecuteMsg::UpdateConfig {
+        owner_addr: None,
+        interest_model: Some("interest3".to_string()),
+        distribution_model: None,
+        max_borrow_factor: None,
+        blocks_per_year: None,
+        force: Some(true),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner1", &[]), msg).unwrap();
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    let config_res: ConfigResponse = from_binary(&res).unwrap();
+    assert_eq!("interest3".to_string(), config_res.interest_model);
 
     // Unauthorized err
     let info = mock_info("owner", &[]);
@@ -233,6 +297,8 @@ fn update_config() {
         interest_model: None,
         distribution_model: None,
         max_borrow_factor: None,
+        blocks_per_year: None,
+        force: None,
     };
 
     let res = execute(deps.as_mut(), mock_env(), info, msg);
@@ -255,6 +321,7 @@ fn deposit_stable_huge_amount() {
         aterra_code_id: 123u64,
         anc_emission_rate: Decimal256::one(),
         max_borrow_factor: Decimal256::one(),
+        blocks_per_year: None,
     };
 
     let info = mock_info(
@@ -411,6 +478,7 @@ fn deposit_stable() {
         aterra_code_id: 123u64,
         anc_emission_rate: Decimal256::one(),
         max_borrow_factor: Decimal256::one(),
+        blocks_per_year: None,
     };
 
     let info = mock_info(
@@ -654,6 +722,7 @@ fn redeem_stable() {
         aterra_code_id: 123u64,
         anc_emission_rate: Decimal256::one(),
         max_borrow_factor: Decimal256::one(),
+        blocks_per_year: None,
     };
 
     let info = mock_info(
@@ -846,6 +915,7 @@ fn borrow_stable() {
         aterra_code_id: 123u64,
         anc_emission_rate: Decimal256::one(),
         max_borrow_factor: Decimal256::one(),
+        blocks_per_year: None,
     };
 
     let info = mock_info(
@@ -1091,6 +1161,7 @@ fn assert_max_borrow_factor() {
         aterra_code_id: 123u64,
         anc_emission_rate: Decimal256::one(),
         max_borrow_factor: Decimal256::percent(1),
+        blocks_per_year: None,
     };
 
     let info = mock_info(
@@ -1201,6 +1272,7 @@ fn repay_stable() {
         aterra_code_id: 123u64,
         anc_emission_rate: Decimal256::one(),
         max_borrow_factor: Decimal256::one(),
+        blocks_per_year: None,
     };
 
     let info = mock_info(
@@ -1384,6 +1456,7 @@ fn repay_stable_from_liquidation() {
         aterra_code_id: 123u64,
         anc_emission_rate: Decimal256::one(),
         max_borrow_factor: Decimal256::one(),
+        blocks_per_year: None,
     };
 
     let info = mock_info(
@@ -1450,51 +1523,52 @@ fn repay_stable_from_liquidation() {
     env.block.height += 100;
     let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
 
-    // update balance to make repay
-    deps.querier.update_balance(
-        MOCK_CONTRACT_ADDR.to_string(),
-        vec![
-            Coin {
-                denom: "ukrw".to_string(),
-                amount: Uint128::from(100000u128),
-            },
-            Coin {
-                denom: "uusd".to_string(),
-                amount: Uint128::from(INITIAL_DEPOSIT_AMOUNT),
-            },
-        ],
-    );
-
     let msg = ExecuteMsg::RepayStableFromLiquidation {
         borrower: "addr0000".to_string(),
-        prev_balance: Uint256::from(INITIAL_DEPOSIT_AMOUNT),
+    };
+    let stable = |amount: u128| {
+        vec![Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::from(amount),
+        }]
     };
 
-    let res = execute(deps.as_mut(), env.clone(), info, msg.clone());
+    // only the liquidation contract set in the overseer can repay
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("overseer", &stable(100000)),
+        msg.clone(),
+    );
     match res {
         Err(ContractError::Unauthorized {}) => (),
         _ => panic!("DO NOT ENTER HERE"),
     }
 
-    let info = mock_info("overseer", &[]);
-
-    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone());
-    let _uusd_string = "uusd";
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("liquidation", &[]),
+        msg.clone(),
+    );
     match res {
-        Err(ContractError::ZeroRepay(__uusd_string)) => (),
+        Err(ContractError::ZeroRepay(denom)) => assert_eq!(denom, "uusd"),
         _ => panic!("DO NOT ENTER HERE"),
     }
 
-    // update balance to make repay
+    // the attached stable is already in the market balance
     deps.querier.update_balance(
         MOCK_CONTRACT_ADDR.to_string(),
-        vec![Coin {
-            denom: "uusd".to_string(),
-            amount: Uint128::from(INITIAL_DEPOSIT_AMOUNT + 100000u128),
-        }],
+        stable(INITIAL_DEPOSIT_AMOUNT + 100000u128),
     );
 
-    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone()).unwrap();
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("liquidation", &stable(100000)),
+        msg.clone(),
+    )
+    .unwrap();
     assert_eq!(
         res.attributes,
         vec![
@@ -1506,13 +1580,16 @@ fn repay_stable_from_liquidation() {
 
     deps.querier.update_balance(
         MOCK_CONTRACT_ADDR.to_string(),
-        vec![Coin {
-            denom: "uusd".to_string(),
-            amount: Uint128::from(INITIAL_DEPOSIT_AMOUNT + 500000u128),
-        }],
+        stable(INITIAL_DEPOSIT_AMOUNT + 600000u128),
     );
 
-    let res = execute(deps.as_mut(), env, info, msg).unwrap();
+    let res = execute(
+        deps.as_mut(),
+        env,
+        mock_info("liquidation", &stable(500000)),
+        msg,
+    )
+    .unwrap();
     assert_eq!(
         res.attributes,
         vec![
@@ -1538,6 +1615,200 @@ fn repay_stable_from_liquidation() {
     );
 }
 
+#[test]
+fn accrued_interest() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::from(INITIAL_DEPOSIT_AMOUNT),
+    }]);
+
+    let msg = InstantiateMsg {
+        owner_addr: "owner".to_string(),
+        stable_denom: "uusd".to_string(),
+        aterra_code_id: 123u64,
+        anc_emission_rate: Decimal256::one(),
+        max_borrow_factor: Decimal256::one(),
+        blocks_per_year: None,
+    };
+
+    let info = mock_info(
+        "addr0000",
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::from(INITIAL_DEPOSIT_AMOUNT),
+        }],
+    );
+
+    // we can just call .unwrap() to assert this was a success
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // Register anchor token contract
+    let mut token_inst_res = MsgInstantiateContractResponse::new();
+    token_inst_res.set_contract_address("AT-uusd".to_string());
+    let reply_msg = Reply {
+        id: 1,
+        result: ContractResult::Ok(SubMsgExecutionResponse {
+            events: vec![],
+            data: Some(token_inst_res.write_to_bytes().unwrap().into()),
+        }),
+    };
+    let _res = reply(deps.as_mut(), mock_env(), reply_msg).unwrap();
+
+    // Register overseer contract
+    let msg = ExecuteMsg::RegisterContracts {
+        overseer_contract: "overseer".to_string(),
+        interest_model: "interest".to_string(),
+        distribution_model: "distribution".to_string(),
+        collector_contract: "collector".to_string(),
+        distributor_contract: "distributor".to_string(),
+    };
+    let mut env = mock_env();
+    let mut info = mock_info("addr0000", &[]);
+    let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+    deps.querier
+        .with_borrow_rate(&[(&"interest".to_string(), &Decimal256::percent(1))]);
+    deps.querier
+        .with_borrow_limit(&[(&"addr0000".to_string(), &Uint256::from(1000000u64))]);
+
+    store_state(
+        deps.as_mut().storage,
+        &State {
+            total_liabilities: Decimal256::from_uint256(1000000u128),
+            total_reserves: Decimal256::zero(),
+            last_interest_updated: env.block.height,
+            last_reward_updated: env.block.height,
+            global_interest_index: Decimal256::one(),
+            global_reward_index: Decimal256::zero(),
+            anc_emission_rate: Decimal256::one(),
+            prev_aterra_supply: Uint256::zero(),
+            prev_exchange_rate: Decimal256::one(),
+        },
+    )
+    .unwrap();
+
+    // global_interest_index = 2
+    env.block.height += 100;
+    let msg = ExecuteMsg::BorrowStable {
+        borrow_amount: Uint256::from(500000u64),
+        to: None,
+    };
+    let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+    let query_accrued_interest = |deps: &OwnedDeps<MockStorage, MockApi, WasmMockQuerier>,
+                                  borrower: &str,
+                                  block_height: u64| {
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::AccruedInterest {
+                borrower: borrower.to_string(),
+                block_height: Some(block_height),
+            },
+        )
+        .unwrap();
+        from_binary::<AccruedInterestResponse>(&res).unwrap()
+    };
+
+    assert_eq!(
+        query_accrued_interest(&deps, "addr0000", env.block.height),
+        AccruedInterestResponse {
+            borrower: "addr0000".to_string(),
+            principal: Uint256::from(500000u64),
+            total_liability: Uint256::from(500000u64),
+            accrued_interest: Uint256::zero(),
+        }
+    );
+
+    // interest_factor = 1% * 10blocks
+    // global_interest_index = 2.2
+    env.block.height += 10;
+    assert_eq!(
+        query_accrued_interest(&deps, "addr0000", env.block.height),
+        AccruedInterestResponse {
+            borrower: "addr0000".to_string(),
+            principal: Uint256::from(500000u64),
+            total_liability: Uint256::from(550000u64),
+            accrued_interest: Uint256::from(50000u64),
+        }
+    );
+
+    // the repayment settles the interest, then 30000 of the principal
+    deps.querier.update_balance(
+        MOCK_CONTRACT_ADDR.to_string(),
+        vec![Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::from(INITIAL_DEPOSIT_AMOUNT + 80000u128),
+        }],
+    );
+    info.funds = vec![Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::from(80000u128),
+    }];
+    let _res = execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        ExecuteMsg::RepayStable {},
+    )
+    .unwrap();
+    assert_eq!(
+        query_accrued_interest(&deps, "addr0000", env.block.height),
+        AccruedInterestResponse {
+            borrower: "addr0000".to_string(),
+            principal: Uint256::from(470000u64),
+            total_liability: Uint256::from(470000u64),
+            accrued_interest: Uint256::zero(),
+        }
+    );
+
+    // liabilities recorded before the principal was
+    // tracked count their last liability as principal
+    let legacy_borrower = deps.api.addr_canonicalize("addr0001").unwrap();
+    store_borrower_info(
+        deps.as_mut().storage,
+        &legacy_borrower,
+        &BorrowerInfo {
+            interest_index: Decimal256::from_str("2.2").unwrap(),
+            reward_index: Decimal256::zero(),
+            loan_amount: Uint256::from(100000u64),
+            pending_rewards: Decimal256::zero(),
+            principal: None,
+        },
+    )
+    .unwrap();
+
+    // global_interest_index = 2.42
+    env.block.height += 10;
+    assert_eq!(
+        query_accrued_interest(&deps, "addr0000", env.block.height),
+        AccruedInterestResponse {
+            borrower: "addr0000".to_string(),
+            principal: Uint256::from(470000u64),
+            total_liability: Uint256::from(517000u64),
+            accrued_interest: Uint256::from(47000u64),
+        }
+    );
+    assert_eq!(
+        query_accrued_interest(&deps, "addr0001", env.block.height),
+        AccruedInterestResponse {
+            borrower: "addr0001".to_string(),
+            principal: Uint256::from(100000u64),
+            total_liability: Uint256::from(110000u64),
+            accrued_interest: Uint256::from(10000u64),
+        }
+    );
+    assert_eq!(
+        query_accrued_interest(&deps, "addr0002", env.block.height),
+        AccruedInterestResponse {
+            borrower: "addr0002".to_string(),
+            principal: Uint256::zero(),
+            total_liability: Uint256::zero(),
+            accrued_interest: Uint256::zero(),
+        }
+    );
+}
+
 #[test]
 fn claim_rewards() {
     let mut deps = mock_dependencies(&[Coin {
@@ -1555,6 +1826,7 @@ fn claim_rewards() {
         aterra_code_id: 123u64,
         anc_emission_rate: Decimal256::one(),
         max_borrow_factor: Decimal256::one(),
+        blocks_per_year: None,
     };
 
     let info = mock_info(
@@ -1686,6 +1958,7 @@ fn execute_epoch_operations() {
         aterra_code_id: 123u64,
         anc_emission_rate: Decimal256::one(),
         max_borrow_factor: Decimal256::one(),
+        blocks_per_year: None,
     };
 
     let info = mock_info(
@@ -1727,6 +2000,10 @@ fn execute_epoch_operations() {
         .with_borrow_rate(&[(&"interest".to_string(), &Decimal256::percent(1))]);
     deps.querier
         .with_borrow_limit(&[(&"addr0000".to_string(), &Uint256::from(1000000u64))]);
+    deps.querier
+        .with_model_type(&[(&"interest".to_string(), &MODEL_TYPE_KINKED_V1.to_string())]);
+    deps.querier
+        .with_model_market_contract(&[(&"interest".to_string(), &MOCK_CONTRACT_ADDR.to_string())]);
 
     store_state(
         deps.as_mut().storage,
@@ -1765,13 +2042,24 @@ fn execute_epoch_operations() {
     let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
     assert_eq!(
         res.messages,
-        vec![SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
-            to_address: "collector".to_string(),
-            amount: vec![Coin {
-                denom: "uusd".to_string(),
-                amount: Uint128::from(2970u128), // 1% tax
-            }],
-        }))]
+        vec![
+            // utilization = 1000000 / (1000000 + 1000000 - 3000)
+            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "interest".to_string(),
+                funds: vec![],
+                msg: to_binary(&InterestModelExecuteMsg::RecordUtilization {
+                    utilization: Decimal256::from_str("0.500751126690035052").unwrap(),
+                })
+                .unwrap(),
+            })),
+            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: "collector".to_string(),
+                amount: vec![Coin {
+                    denom: "uusd".to_string(),
+                    amount: Uint128::from(2970u128), // 1% tax
+                }],
+            }))
+        ]
     );
 
     let state = read_state(deps.as_ref().storage).unwrap();
@@ -1791,7 +2079,9 @@ fn execute_epoch_operations() {
     );
 
     // When there is not enough balance to cover reserve
-    // no message will be sent and reserve will be left as same
+    // no message will be sent and reserve will be left as same;
+    // nor is the utilization, to a model without the market registered
+    deps.querier.with_model_market_contract(&[]);
     deps.querier.update_balance(
         MOCK_CONTRACT_ADDR.to_string(),
         vec![Coin {
@@ -1828,7 +2118,7 @@ fn execute_epoch_operations() {
         distributed_interest: Uint256::zero(),
     };
 
-    let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone()).unwrap();
     assert_eq!(res.messages.len(), 0);
 
     let state = read_state(deps.as_ref().storage).unwrap();
@@ -1846,6 +2136,14 @@ fn execute_epoch_operations() {
             prev_exchange_rate: Decimal256::one(),
         }
     );
+
+    // a model forced in without model info does not get the record either
+    deps.querier.with_model_type(&[]);
+    deps.querier
+        .with_model_market_contract(&[(&"interest".to_string(), &MOCK_CONTRACT_ADDR.to_string())]);
+    env.block.height += 100;
+    let res = execute(deps.as_mut(), env, info, msg).unwrap();
+    assert_eq!(res.messages.len(), 0);
 }
 
 // #[test]
@@ -1938,3 +2236,197 @@ fn execute_epoch_operations() {
 //     // only overseer can execute this
 //     let _ = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone()).unwrap();
 // }
+
+#[test]
+fn interest_accrual_rate_modes() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::from(INITIAL_DEPOSIT_AMOUNT),
+    }]);
+
+    let msg = InstantiateMsg {
+        owner_addr: "owner".to_string(),
+        stable_denom: "uusd".to_string(),
+        aterra_code_id: 123u64,
+        anc_emission_rate: Decimal256::one(),
+        max_borrow_factor: Decimal256::one(),
+        blocks_per_year: None,
+    };
+    let info = mock_info(
+        "addr0000",
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::from(INITIAL_DEPOSIT_AMOUNT),
+        }],
+    );
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // Register anchor token contract
+    let mut token_inst_res = MsgInstantiateContractResponse::new();
+    token_inst_res.set_contract_address("AT-uusd".to_string());
+    let reply_msg = Reply {
+        id: 1,
+        result: ContractResult::Ok(SubMsgExecutionResponse {
+            events: vec![],
+            data: Some(token_inst_res.write_to_bytes().unwrap().into()),
+        }),
+    };
+    let _res = reply(deps.as_mut(), mock_env(), reply_msg).unwrap();
+
+    let msg = ExecuteMsg::RegisterContracts {
+        overseer_contract: "overseer".to_string(),
+        interest_model: "interest".to_string(),
+        distribution_model: "distribution".to_string(),
+        collector_contract: "collector".to_string(),
+        distributor_contract: "distributor".to_string(),
+    };
+    let _res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+
+    // the mocked models return their configured rate; the dynamic
+    // one for the current utilization, the fixed one for any
+    deps.querier.with_borrow_rate(&[
+        (&"interest".to_string(), &Decimal256::percent(1)),
+        (
+            &"fixed".to_string(),
+            &Decimal256::from_str("0.005").unwrap(),
+        ),
+    ]);
+    deps.querier.with_model_type(&[
+        (&"interest".to_string(), &MODEL_TYPE_KINKED_V1.to_string()),
+        (&"fixed".to_string(), &MODEL_TYPE_FIXED_V1.to_string()),
+    ]);
+
+    let env = mock_env();
+    let store_liabilities = |deps: &mut OwnedDeps<MockStorage, MockApi, WasmMockQuerier>| {
+        store_state(
+            deps.as_mut().storage,
+            &State {
+                total_liabilities: Decimal256::from_uint256(1000000u128),
+                total_reserves: Decimal256::zero(),
+                last_interest_updated: env.block.height,
+                last_reward_updated: env.block.height,
+                global_interest_index: Decimal256::one(),
+                global_reward_index: Decimal256::zero(),
+                anc_emission_rate: Decimal256::one(),
+                prev_aterra_supply: Uint256::zero(),
+                prev_exchange_rate: Decimal256::one(),
+            },
+        )
+        .unwrap();
+    };
+    let total_liabilities_after_100_blocks =
+        |deps: &OwnedDeps<MockStorage, MockApi, WasmMockQuerier>| {
+            let res = query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::State {
+                    block_height: Some(env.block.height + 100u64),
+                },
+            )
+            .unwrap();
+            let state: State = from_binary(&res).unwrap();
+            state.total_liabilities
+        };
+
+    // dynamic model; 1000000 * (1 + 100 * 0.01)
+    store_liabilities(&mut deps);
+    assert_eq!(
+        total_liabilities_after_100_blocks(&deps),
+        Decimal256::from_uint256(2000000u128)
+    );
+
+    // swap to the fixed rate model, which is a supported model type
+    let msg = ExecuteMsg::UpdateConfig {
+        owner_addr: None,
+        interest_model: Some("fixed".to_string()),
+        distribution_model: None,
+        max_borrow_factor: None,
+        blocks_per_year: None,
+        force: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+    // fixed model; 1000000 * (1 + 100 * 0.005) whatever the balance
+    for balance in [INITIAL_DEPOSIT_AMOUNT, 10 * INITIAL_DEPOSIT_AMOUNT] {
+        deps.querier.update_balance(
+            MOCK_CONTRACT_ADDR.to_string(),
+            vec![Coin {
+                denom: "uusd".to_string(),
+                amount: Uint128::from(balance),
+            }],
+        );
+        store_liabilities(&mut deps);
+        assert_eq!(
+            total_liabilities_after_100_blocks(&deps),
+            Decimal256::from_uint256(1500000u128)
+        );
+    }
+}
+
+#[test]
+fn query_rates() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::from(INITIAL_DEPOSIT_AMOUNT),
+    }]);
+    deps.querier
+        .with_borrow_rate(&[(&"interest".to_string(), &Decimal256::percent(1))]);
+
+    let msg = InstantiateMsg {
+        owner_addr: "owner".to_string(),
+        stable_denom: "uusd".to_string(),
+        aterra_code_id: 123u64,
+        anc_emission_rate: Decimal256::one(),
+        max_borrow_factor: Decimal256::one(),
+        blocks_per_year: Some(10),
+    };
+    let info = mock_info(
+        "addr0000",
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::from(INITIAL_DEPOSIT_AMOUNT),
+        }],
+    );
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::RegisterContracts {
+        overseer_contract: "overseer".to_string(),
+        interest_model: "interest".to_string(),
+        distribution_model: "distribution".to_string(),
+        collector_contract: "collector".to_string(),
+        distributor_contract: "distributor".to_string(),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Rates {}).unwrap();
+    let rates: RatesResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        rates,
+        RatesResponse {
+            borrow_rate: Decimal256::percent(1),
+            borrow_apr: Decimal256::percent(10),
+            // 1.01^10 - 1, rounded down
+            borrow_apy: Decimal256::from_str("0.10462212541120451").unwrap(),
+        }
+    );
+
+    let msg = InstantiateMsg {
+        owner_addr: "owner".to_string(),
+        stable_denom: "uusd".to_string(),
+        aterra_code_id: 123u64,
+        anc_emission_rate: Decimal256::one(),
+        max_borrow_factor: Decimal256::one(),
+        blocks_per_year: Some(0),
+    };
+    let info = mock_info(
+        "addr0000",
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::from(INITIAL_DEPOSIT_AMOUNT),
+        }],
+    );
+    match instantiate(deps.as_mut(), mock_env(), info, msg) {
+        Err(ContractError::InvalidBlocksPerYear {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+}
@@ -4,7 +4,10 @@ use std::fs::create_dir_all;
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 
 use moneymarket::oracle::{
-    ConfigResponse, ExecuteMsg, InstantiateMsg, PriceResponse, PricesResponse, QueryMsg,
+    AssetsResponse, ConfigResponse, ExecuteMsg, FeederResponse, FeederStatsResponse,
+    FeedersResponse, InstantiateMsg, MigrateMsg, PairSourceResponse, PriceResponse,
+    PricesForResponse, PricesResponse, QueryMsg, SnapshotPriceResponse, StaleFeedsResponse,
+    StalePricesResponse,
 };
 
 fn main() {
@@ -16,7 +19,17 @@ fn main() {
     export_schema(&schema_for!(InstantiateMsg), &out_dir);
     export_schema(&schema_for!(ExecuteMsg), &out_dir);
     export_schema(&schema_for!(QueryMsg), &out_dir);
+    export_schema(&schema_for!(MigrateMsg), &out_dir);
     export_schema(&schema_for!(ConfigResponse), &out_dir);
     export_schema(&schema_for!(PriceResponse), &out_dir);
     export_schema(&schema_for!(PricesResponse), &out_dir);
+    export_schema(&schema_for!(PricesForResponse), &out_dir);
+    export_schema(&schema_for!(AssetsResponse), &out_dir);
+    export_schema(&schema_for!(StaleFeedsResponse), &out_dir);
+    export_schema(&schema_for!(StalePricesResponse), &out_dir);
+    export_schema(&schema_for!(PairSourceResponse), &out_dir);
+    export_schema(&schema_for!(FeederResponse), &out_dir);
+    export_schema(&schema_for!(FeederStatsResponse), &out_dir);
+    export_schema(&schema_for!(FeedersResponse), &out_dir);
+    export_schema(&schema_for!(SnapshotPriceResponse), &out_dir);
 }
@@ -1,16 +1,39 @@
 use crate::error::ContractError;
+use crate::querier::query_pair_price;
 use crate::state::{
-    read_config, read_feeder, read_price, read_prices, store_config, store_feeder, store_price,
-    Config, PriceInfo,
+    may_read_feeder, may_read_price, migrate_legacy_feeders, migrate_legacy_prices, read_asset,
+    read_assets, read_config, read_feeder, read_feeder_key, read_feeder_registration,
+    read_feeder_registrations, read_feeder_stats, read_feeders_stats, read_pair_source, read_price,
+    read_prices, read_snapshot, read_snapshot_heights, read_stale_feeds, read_stale_prices,
+    remove_feeder_registration, remove_snapshot, store_asset, store_config, store_feeder,
+    store_feeder_key, store_feeder_registration, store_feeder_stats, store_pair_source,
+    store_price, store_snapshot, store_snapshot_heights, AssetInfo, Config, FeederRegistration,
+    PairSource, PriceInfo, Snapshot,
 };
 use cosmwasm_bignumber::Decimal256;
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
-use cosmwasm_std::{attr, to_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult};
+use cosmwasm_std::{
+    attr, to_binary, Binary, Deps, DepsMut, Env, MessageInfo, QueryRequest, Response, StdError,
+    StdResult, WasmQuery,
+};
 use moneymarket::oracle::{
-    ConfigResponse, ExecuteMsg, FeederResponse, InstantiateMsg, PriceResponse, PricesResponse,
-    PricesResponseElem, QueryMsg,
+    AssetsResponse, ConfigResponse, ExecuteMsg, FeederResponse, FeederStatsResponse,
+    FeederStatsResponseElem, FeedersResponse, FeedersResponseElem, InstantiateMsg, MigrateMsg,
+    PairSourceResponse, PriceResponse, PricesForResponse, PricesForResponseElem, PricesResponse,
+    PricesResponseElem, QueryMsg, SnapshotPriceResponse, StaleFeedsResponse, StalePricesResponse,
+    StalePricesResponseElem,
 };
+use sha2::{Digest, Sha256};
+
+/// Decimals reported for the base asset when it was not registered explicitly
+const DEFAULT_BASE_DECIMALS: u8 = 6;
+
+/// Allowed clock drift of signed price timestamps, in seconds
+const MAX_FUTURE_TIMESTAMP: u64 = 60;
+
+/// Number of price snapshots retained, older ones are pruned
+const MAX_SNAPSHOTS: usize = 30;
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
@@ -19,11 +42,25 @@ pub fn instantiate(
     _info: MessageInfo,
     msg: InstantiateMsg,
 ) -> StdResult<Response> {
+    let fallback_oracle = match msg.fallback_oracle {
+        Some(fallback_oracle) => Some(deps.api.addr_canonicalize(&fallback_oracle)?),
+        None => None,
+    };
+
+    let default_quote_denom = match msg.default_quote_denom {
+        Some(default_quote_denom) => default_quote_denom,
+        None => msg.base_asset.clone(),
+    };
+
     store_config(
         deps.storage,
         &Config {
             owner: deps.api.addr_canonicalize(&msg.owner)?,
             base_asset: msg.base_asset,
+            valid_period: msg.valid_period,
+            fallback_oracle,
+            snapshotter: None,
+            default_quote_denom,
         },
     )?;
 
@@ -38,9 +75,67 @@ pub fn execute(
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
     match msg {
-        ExecuteMsg::UpdateConfig { owner } => update_config(deps, info, owner),
-        ExecuteMsg::RegisterFeeder { asset, feeder } => register_feeder(deps, info, asset, feeder),
-        ExecuteMsg::FeedPrice { prices } => feed_prices(deps, env, info, prices),
+        ExecuteMsg::UpdateConfig {
+            owner,
+            valid_period,
+            fallback_oracle,
+            snapshotter,
+        } => update_config(
+            deps,
+            info,
+            owner,
+            valid_period,
+            fallback_oracle,
+            snapshotter,
+        ),
+        ExecuteMsg::RegisterAsset {
+            asset,
+            decimals,
+            description,
+            heartbeat,
+        } => register_asset(deps, info, asset, decimals, description, heartbeat),
+        ExecuteMsg::UpdateAssetBounds {
+            asset,
+            min_price,
+            max_price,
+        } => update_asset_bounds(deps, info, asset, min_price, max_price),
+        ExecuteMsg::RegisterFeeder {
+            asset,
+            feeder,
+            quote,
+        } => register_feeder(deps, env, info, asset, feeder, quote),
+        ExecuteMsg::FreezeAsset { asset } => update_asset_frozen(deps, info, asset, true),
+        ExecuteMsg::UnfreezeAsset { asset } => update_asset_frozen(deps, info, asset, false),
+        ExecuteMsg::DeprecateAsset { asset } => deprecate_asset(deps, info, asset),
+        ExecuteMsg::RegisterFeederKey { asset, pubkey } => {
+            register_feeder_key(deps, info, asset, pubkey)
+        }
+        ExecuteMsg::FeedPrice { prices, quote } => feed_prices(deps, env, info, prices, quote),
+        ExecuteMsg::FeedPriceSigned {
+            asset,
+            price,
+            timestamp,
+            signature,
+            pubkey,
+        } => feed_price_signed(deps, env, asset, price, timestamp, signature, pubkey),
+        ExecuteMsg::RegisterPairSource {
+            asset,
+            pair,
+            max_deviation,
+            twap_window,
+            initial_price,
+        } => register_pair_source(
+            deps,
+            env,
+            info,
+            asset,
+            pair,
+            max_deviation,
+            twap_window,
+            initial_price,
+        ),
+        ExecuteMsg::UpdatePriceFromPair { asset } => update_price_from_pair(deps, env, asset),
+        ExecuteMsg::SnapshotPrices { assets } => snapshot_prices(deps, env, info, assets),
     }
 }
 
@@ -48,6 +143,9 @@ pub fn update_config(
     deps: DepsMut,
     info: MessageInfo,
     owner: Option<String>,
+    valid_period: Option<u64>,
+    fallback_oracle: Option<String>,
+    snapshotter: Option<String>,
 ) -> Result<Response, ContractError> {
     let mut config: Config = read_config(deps.storage)?;
     if deps.api.addr_canonicalize(info.sender.as_str())? != config.owner {
@@ -58,57 +156,495 @@ pub fn update_config(
         config.owner = deps.api.addr_canonicalize(&owner)?;
     }
 
+    if let Some(valid_period) = valid_period {
+        config.valid_period = valid_period;
+    }
+
+    if let Some(fallback_oracle) = fallback_oracle {
+        config.fallback_oracle = Some(deps.api.addr_canonicalize(&fallback_oracle)?);
+    }
+
+    if let Some(snapshotter) = snapshotter {
+        config.snapshotter = Some(deps.api.addr_canonicalize(&snapshotter)?);
+    }
+
     store_config(deps.storage, &config)?;
     Ok(Response::default())
 }
 
+pub fn register_asset(
+    deps: DepsMut,
+    info: MessageInfo,
+    asset: String,
+    decimals: u8,
+    description: String,
+    heartbeat: Option<u64>,
+) -> Result<Response, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+    if deps.api.addr_canonicalize(info.sender.as_str())? != config.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    // Re-registering an asset keeps its freeze status and price bounds
+    let asset_info = match read_asset(deps.storage, &asset)? {
+        Some(asset_info) => AssetInfo {
+            decimals,
+            description,
+            heartbeat,
+            ..asset_info
+        },
+        None => AssetInfo {
+            decimals,
+            description,
+            heartbeat,
+            min_price: None,
+            max_price: None,
+            frozen: false,
+            deprecated: false,
+        },
+    };
+
+    store_asset(deps.storage, &asset, &asset_info)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "register_asset"),
+        attr("asset", asset),
+        attr("decimals", decimals.to_string()),
+    ]))
+}
+
+pub fn update_asset_frozen(
+    deps: DepsMut,
+    info: MessageInfo,
+    asset: String,
+    frozen: bool,
+) -> Result<Response, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+    if deps.api.addr_canonicalize(info.sender.as_str())? != config.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let mut asset_info = match read_asset(deps.storage, &asset)? {
+        Some(asset_info) => asset_info,
+        None => return Err(ContractError::AssetNotRegistered(asset)),
+    };
+
+    asset_info.frozen = frozen;
+    store_asset(deps.storage, &asset, &asset_info)?;
+
+    let action = if frozen {
+        "freeze_asset"
+    } else {
+        "unfreeze_asset"
+    };
+    Ok(Response::new().add_attributes(vec![attr("action", action), attr("asset", asset)]))
+}
+
+/// Deprecated assets stay listed but their price can no longer be fed or consumed
+pub fn deprecate_asset(
+    deps: DepsMut,
+    info: MessageInfo,
+    asset: String,
+) -> Result<Response, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+    if deps.api.addr_canonicalize(info.sender.as_str())? != config.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let mut asset_info = match read_asset(deps.storage, &asset)? {
+        Some(asset_info) => asset_info,
+        None => return Err(ContractError::AssetNotRegistered(asset)),
+    };
+
+    asset_info.deprecated = true;
+    store_asset(deps.storage, &asset, &asset_info)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "deprecate_asset"),
+        attr("asset", asset),
+    ]))
+}
+
+pub fn update_asset_bounds(
+    deps: DepsMut,
+    info: MessageInfo,
+    asset: String,
+    min_price: Option<Decimal256>,
+    max_price: Option<Decimal256>,
+) -> Result<Response, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+    if deps.api.addr_canonicalize(info.sender.as_str())? != config.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if let (Some(min_price), Some(max_price)) = (min_price, max_price) {
+        if min_price > max_price {
+            return Err(ContractError::InvalidPriceBounds {});
+        }
+    }
+
+    let mut asset_info = match read_asset(deps.storage, &asset)? {
+        Some(asset_info) => asset_info,
+        None => return Err(ContractError::AssetNotRegistered(asset)),
+    };
+
+    asset_info.min_price = min_price;
+    asset_info.max_price = max_price;
+    store_asset(deps.storage, &asset, &asset_info)?;
+
+    let mut attributes = vec![
+        attr("action", "update_asset_bounds"),
+        attr("asset", asset.to_string()),
+        attr("min_price", format_bound(min_price)),
+        attr("max_price", format_bound(max_price)),
+    ];
+
+    // The stored price is kept, but flag it when it falls outside the new bounds
+    if let Some(price) = may_read_price(deps.storage, &asset, &config.base_asset)? {
+        if check_price_bounds(&asset, &asset_info, price.price).is_err() {
+            attributes.push(attr("current_price_out_of_bounds", price.price.to_string()));
+        }
+    }
+
+    Ok(Response::new().add_attributes(attributes))
+}
+
+fn format_bound(bound: Option<Decimal256>) -> String {
+    match bound {
+        Some(bound) => bound.to_string(),
+        None => "unlimited".to_string(),
+    }
+}
+
+fn check_price_bounds(
+    asset: &str,
+    asset_info: &AssetInfo,
+    price: Decimal256,
+) -> Result<(), ContractError> {
+    if let Some(min_price) = asset_info.min_price {
+        if price < min_price {
+            return Err(ContractError::PriceBelowMinPrice {
+                asset: asset.to_string(),
+                min_price,
+                price,
+            });
+        }
+    }
+
+    if let Some(max_price) = asset_info.max_price {
+        if price > max_price {
+            return Err(ContractError::PriceAboveMaxPrice {
+                asset: asset.to_string(),
+                max_price,
+                price,
+            });
+        }
+    }
+
+    Ok(())
+}
+
 pub fn register_feeder(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     asset: String,
     feeder: String,
+    quote: Option<String>,
 ) -> Result<Response, ContractError> {
     let config: Config = read_config(deps.storage)?;
     if deps.api.addr_canonicalize(info.sender.as_str())? != config.owner {
         return Err(ContractError::Unauthorized {});
     }
 
-    store_feeder(deps.storage, &asset, &deps.api.addr_canonicalize(&feeder)?)?;
+    let quote = quote.unwrap_or(config.default_quote_denom);
+    let feeder_raw = deps.api.addr_canonicalize(&feeder)?;
+
+    // Drop the pair from the replaced feeder's registration
+    if let Some(prev_feeder) = may_read_feeder(deps.storage, &asset, &quote)? {
+        if let Some(mut registration) =
+            read_feeder_registration(deps.storage, &asset, &prev_feeder)?
+        {
+            registration.quotes.retain(|q| *q != quote);
+            if registration.quotes.is_empty() {
+                remove_feeder_registration(deps.storage, &asset, &prev_feeder);
+            } else {
+                store_feeder_registration(deps.storage, &asset, &prev_feeder, &registration)?;
+            }
+        }
+    }
+
+    let mut quotes = match read_feeder_registration(deps.storage, &asset, &feeder_raw)? {
+        Some(registration) => registration.quotes,
+        None => vec![],
+    };
+    quotes.push(quote.clone());
+    store_feeder_registration(
+        deps.storage,
+        &asset,
+        &feeder_raw,
+        &FeederRegistration {
+            quotes,
+            registered_at_height: env.block.height,
+        },
+    )?;
+
+    store_feeder(deps.storage, &asset, &quote, &feeder_raw)?;
 
     Ok(Response::new().add_attributes(vec![
         attr("action", "register_feeder"),
         attr("asset", asset),
+        attr("quote", quote),
         attr("feeder", feeder),
     ]))
 }
 
+pub fn register_feeder_key(
+    deps: DepsMut,
+    info: MessageInfo,
+    asset: String,
+    pubkey: Binary,
+) -> Result<Response, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+    if deps.api.addr_canonicalize(info.sender.as_str())? != config.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    store_feeder_key(deps.storage, &asset, &pubkey)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "register_feeder_key"),
+        attr("asset", asset),
+        attr("pubkey", pubkey.to_base64()),
+    ]))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn register_pair_source(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    asset: String,
+    pair: String,
+    max_deviation: Decimal256,
+    twap_window: u64,
+    initial_price: Option<Decimal256>,
+) -> Result<Response, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+    if deps.api.addr_canonicalize(info.sender.as_str())? != config.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if read_asset(deps.storage, &asset)?.is_none() {
+        return Err(ContractError::AssetNotRegistered(asset));
+    }
+
+    // (re-)registering resets the TWAP to a trusted price, so the
+    // first spot price pulled from the pair is already checked against it
+    let twap = match initial_price {
+        Some(initial_price) => initial_price,
+        None => may_read_price(deps.storage, &asset, &config.base_asset)?
+            .map(|price_info| price_info.price)
+            .unwrap_or_default(),
+    };
+    if twap.is_zero() {
+        return Err(ContractError::PairSourceNotSeeded(asset));
+    }
+    assert_feedable(deps.as_ref(), &config, &asset, &config.base_asset, twap)?;
+
+    store_pair_source(
+        deps.storage,
+        &asset,
+        &PairSource {
+            pair: deps.api.addr_canonicalize(&pair)?,
+            max_deviation,
+            twap_window,
+            twap: Some(twap),
+            twap_updated_time: env.block.time.seconds(),
+        },
+    )?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "register_pair_source"),
+        attr("asset", asset),
+        attr("pair", pair),
+        attr("max_deviation", max_deviation.to_string()),
+        attr("twap_window", twap_window.to_string()),
+        attr("twap", twap.to_string()),
+    ]))
+}
+
+pub fn snapshot_prices(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    assets: Vec<String>,
+) -> Result<Response, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+    match config.snapshotter {
+        Some(snapshotter) if deps.api.addr_canonicalize(info.sender.as_str())? == snapshotter => {}
+        _ => return Err(ContractError::Unauthorized {}),
+    }
+
+    // Assets without a servable price are left out instead of failing the snapshot
+    let mut attributes = vec![attr("action", "snapshot_prices")];
+    let mut snapshot: Snapshot = vec![];
+    for asset in assets {
+        match query_price(
+            deps.as_ref(),
+            env.clone(),
+            asset.clone(),
+            config.base_asset.clone(),
+            None,
+        ) {
+            Ok(price) => {
+                attributes.push(attr("asset", &asset));
+                attributes.push(attr("price", price.rate.to_string()));
+                snapshot.push((
+                    asset,
+                    PriceInfo {
+                        price: price.rate,
+                        last_updated_time: price.last_updated_base,
+                        source: price.source,
+                    },
+                ));
+            }
+            Err(_) => attributes.push(attr("skipped", asset)),
+        }
+    }
+
+    let height = env.block.height;
+    store_snapshot(deps.storage, height, &snapshot)?;
+
+    let mut heights = read_snapshot_heights(deps.storage)?;
+    if !heights.contains(&height) {
+        heights.push(height);
+    }
+    while heights.len() > MAX_SNAPSHOTS {
+        remove_snapshot(deps.storage, heights.remove(0));
+    }
+    store_snapshot_heights(deps.storage, &heights)?;
+
+    attributes.push(attr("height", height.to_string()));
+    Ok(Response::new().add_attributes(attributes))
+}
+
+/// Only registered assets can be fed, frozen ones keep their last price
+/// and prices must be within the asset's sanity bounds. The bounds are
+/// denominated in the base asset, so bounded assets are only fed in it.
+fn assert_feedable(
+    deps: Deps,
+    config: &Config,
+    asset: &str,
+    quote: &str,
+    price: Decimal256,
+) -> Result<(), ContractError> {
+    let asset_info = read_feedable_asset(deps, asset)?;
+    check_feed_price(config, asset, &asset_info, quote, price)
+}
+
+fn check_feed_price(
+    config: &Config,
+    asset: &str,
+    asset_info: &AssetInfo,
+    quote: &str,
+    price: Decimal256,
+) -> Result<(), ContractError> {
+    if config.base_asset == quote {
+        check_price_bounds(asset, asset_info, price)?;
+    } else if asset_info.min_price.is_some() || asset_info.max_price.is_some() {
+        return Err(ContractError::BoundedAssetQuote {
+            asset: asset.to_string(),
+            quote: quote.to_string(),
+        });
+    }
+
+    Ok(())
+}
+
+fn read_feedable_asset(deps: Deps, asset: &str) -> Result<AssetInfo, ContractError> {
+    match read_asset(deps.storage, asset)? {
+        None => Err(ContractError::AssetNotRegistered(asset.to_string())),
+        Some(asset_info) if asset_info.deprecated => {
+            Err(ContractError::AssetDeprecated(asset.to_string()))
+        }
+        Some(asset_info) if asset_info.frozen => Err(ContractError::AssetFrozen(asset.to_string())),
+        Some(asset_info) => Ok(asset_info),
+    }
+}
+
 pub fn feed_prices(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
     prices: Vec<(String, Decimal256)>,
+    quote: Option<String>,
 ) -> Result<Response, ContractError> {
-    let mut attributes = vec![attr("action", "feed_prices")];
+    let config: Config = read_config(deps.storage)?;
+    let quote = quote.unwrap_or_else(|| config.default_quote_denom.clone());
+
+    let mut attributes = vec![attr("action", "feed_prices"), attr("quote", &quote)];
     let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
     for price in prices {
         let asset: String = price.0;
         let price: Decimal256 = price.1;
 
-        // Check feeder permission
-        let feeder = read_feeder(deps.storage, &asset)?;
+        let asset_info = read_feedable_asset(deps.as_ref(), &asset)?;
+
+        // Check feeder permission for the pair
+        let feeder = read_feeder(deps.storage, &asset, &quote)?;
         if feeder != sender_raw {
             return Err(ContractError::Unauthorized {});
         }
 
+        let mut stats = read_feeder_stats(deps.storage, &asset, &sender_raw)?;
+        stats.last_submission_time = env.block.time.seconds();
+
+        // Out of bounds prices and prices older than the stored one (a signed
+        // price timestamped ahead of the block) are skipped and counted
+        // against the feeder, the rest of the batch still goes through
+        let old_price = may_read_price(deps.storage, &asset, &quote)?;
+        let checked =
+            check_feed_price(&config, &asset, &asset_info, &quote, price).and_then(|_| {
+                match &old_price {
+                    Some(old_price) if old_price.last_updated_time > env.block.time.seconds() => {
+                        Err(ContractError::PriceTimestampTooOld {})
+                    }
+                    _ => Ok(()),
+                }
+            });
+        if let Err(err) = checked {
+            stats.rejected += 1;
+            store_feeder_stats(deps.storage, &asset, &sender_raw, &stats)?;
+
+            attributes.push(attr("asset", asset.to_string()));
+            attributes.push(attr("rejected", err.to_string()));
+            continue;
+        }
+
+        stats.accepted += 1;
+        store_feeder_stats(deps.storage, &asset, &sender_raw, &stats)?;
+
+        // the previous price is only used for the logs
+        let old_price = old_price.map(|p| p.price);
+
         attributes.push(attr("asset", asset.to_string()));
+        attributes.push(attr(
+            "old_price",
+            old_price.map_or_else(|| "none".to_string(), |p| p.to_string()),
+        ));
         attributes.push(attr("price", price.to_string()));
+        attributes.push(attr("change", relative_change(old_price, price)));
+        attributes.push(attr("feeder", info.sender.to_string()));
 
         store_price(
             deps.storage,
             &asset,
+            &quote,
             &PriceInfo {
                 last_updated_time: env.block.time.seconds(),
                 price,
+                source: "primary".to_string(),
             },
         )?;
     }
@@ -116,15 +652,252 @@ pub fn feed_prices(
     Ok(Response::new().add_attributes(attributes))
 }
 
+/// Signed relative change from the previous price, "none" when there is nothing to compare to
+fn relative_change(old_price: Option<Decimal256>, new_price: Decimal256) -> String {
+    match old_price {
+        Some(old_price) if !old_price.is_zero() => {
+            if new_price >= old_price {
+                ((new_price - old_price) / old_price).to_string()
+            } else {
+                format!("-{}", (old_price - new_price) / old_price)
+            }
+        }
+        _ => "none".to_string(),
+    }
+}
+
+pub fn feed_price_signed(
+    deps: DepsMut,
+    env: Env,
+    asset: String,
+    price: Decimal256,
+    timestamp: u64,
+    signature: Binary,
+    pubkey: Binary,
+) -> Result<Response, ContractError> {
+    // Signed prices are quoted in the base asset
+    let config: Config = read_config(deps.storage)?;
+    assert_feedable(deps.as_ref(), &config, &asset, &config.base_asset, price)?;
+
+    // Check the key was registered for the asset
+    if read_feeder_key(deps.storage, &asset)? != pubkey {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    // Reject replayed and future-dated submissions
+    if let Some(stored_price) = may_read_price(deps.storage, &asset, &config.base_asset)? {
+        if timestamp <= stored_price.last_updated_time {
+            return Err(ContractError::PriceTimestampTooOld {});
+        }
+    }
+    if timestamp > env.block.time.seconds() + MAX_FUTURE_TIMESTAMP {
+        return Err(ContractError::PriceTimestampInFuture {});
+    }
+
+    let message = format!("{}|{}|{}", asset, price, timestamp);
+    let message_hash = Sha256::digest(message.as_bytes());
+    if !deps
+        .api
+        .secp256k1_verify(&message_hash, &signature, &pubkey)
+        .map_err(|_| ContractError::InvalidSignature {})?
+    {
+        return Err(ContractError::InvalidSignature {});
+    }
+
+    store_price(
+        deps.storage,
+        &asset,
+        &config.base_asset,
+        &PriceInfo {
+            last_updated_time: timestamp,
+            price,
+            source: "primary".to_string(),
+        },
+    )?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "feed_price_signed"),
+        attr("asset", asset),
+        attr("price", price.to_string()),
+        attr("timestamp", timestamp.to_string()),
+    ]))
+}
+
+pub fn update_price_from_pair(
+    deps: DepsMut,
+    env: Env,
+    asset: String,
+) -> Result<Response, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+    let mut pair_source = match read_pair_source(deps.storage, &asset)? {
+        Some(pair_source) => pair_source,
+        None => return Err(ContractError::PairSourceNotRegistered(asset)),
+    };
+
+    let price = query_pair_price(
+        deps.as_ref(),
+        deps.api.addr_humanize(&pair_source.pair)?,
+        &asset,
+        &config.base_asset,
+    )?;
+    assert_feedable(deps.as_ref(), &config, &asset, &config.base_asset, price)?;
+
+    // Reject spot prices too far from the TWAP, so a single block
+    // manipulation of the reserves cannot poison the feed
+    let current_time = env.block.time.seconds();
+    let twap = match pair_source.twap {
+        // only sources registered before the TWAP was seeded on registration
+        None => return Err(ContractError::PairSourceNotSeeded(asset)),
+        Some(twap) => {
+            let deviation = if price > twap {
+                price - twap
+            } else {
+                twap - price
+            };
+            if deviation / twap > pair_source.max_deviation {
+                return Err(ContractError::PriceDeviationTooHigh(asset));
+            }
+
+            let elapsed = current_time.saturating_sub(pair_source.twap_updated_time);
+            if elapsed >= pair_source.twap_window {
+                price
+            } else {
+                let weight = Decimal256::from_ratio(elapsed, pair_source.twap_window);
+                twap * (Decimal256::one() - weight) + price * weight
+            }
+        }
+    };
+
+    pair_source.twap = Some(twap);
+    pair_source.twap_updated_time = current_time;
+    store_pair_source(deps.storage, &asset, &pair_source)?;
+
+    store_price(
+        deps.storage,
+        &asset,
+        &config.base_asset,
+        &PriceInfo {
+            last_updated_time: current_time,
+            price,
+            source: "amm".to_string(),
+        },
+    )?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "update_price_from_pair"),
+        attr("asset", asset),
+        attr("price", price.to_string()),
+        attr("twap", twap.to_string()),
+    ]))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, env: Env, msg: MigrateMsg) -> StdResult<Response> {
+    let mut config: Config = read_config(deps.storage)?;
+    config.valid_period = msg.valid_period;
+    if config.default_quote_denom.is_empty() {
+        config.default_quote_denom = config.base_asset.clone();
+    }
+    store_config(deps.storage, &config)?;
+
+    // prices and feeders of the single quote layout were all in the base asset
+    let prices = migrate_legacy_prices(deps.storage, &config.base_asset)?;
+    let feeders = migrate_legacy_feeders(deps.storage, &config.base_asset)?;
+
+    // the first release had no asset registry, the migrated assets get the
+    // defaults of the base asset until the owner registers them
+    let mut assets: Vec<&String> = prices.iter().chain(feeders.iter().map(|f| &f.0)).collect();
+    assets.sort();
+    assets.dedup();
+    for asset in assets {
+        if read_asset(deps.storage, asset)?.is_none() {
+            store_asset(
+                deps.storage,
+                asset,
+                &AssetInfo {
+                    decimals: DEFAULT_BASE_DECIMALS,
+                    description: asset.to_string(),
+                    heartbeat: None,
+                    min_price: None,
+                    max_price: None,
+                    frozen: false,
+                    deprecated: false,
+                },
+            )?;
+        }
+    }
+
+    for (asset, feeder) in feeders.iter() {
+        store_feeder_registration(
+            deps.storage,
+            asset,
+            feeder,
+            &FeederRegistration {
+                quotes: vec![config.base_asset.clone()],
+                registered_at_height: env.block.height,
+            },
+        )?;
+    }
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "migrate"),
+        attr("prices", prices.len().to_string()),
+        attr("feeders", feeders.len().to_string()),
+    ]))
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::Config {} => to_binary(&query_config(deps)?),
-        QueryMsg::Feeder { asset } => to_binary(&query_feeder(deps, asset)?),
-        QueryMsg::Price { base, quote } => to_binary(&query_price(deps, base, quote)?),
-        QueryMsg::Prices { start_after, limit } => {
-            to_binary(&query_prices(deps, start_after, limit)?)
+        QueryMsg::Feeder { asset, quote } => to_binary(&query_feeder(deps, asset, quote)?),
+        QueryMsg::Price {
+            base,
+            quote,
+            block_time,
+        } => to_binary(&query_price(deps, env, base, quote, block_time)?),
+        QueryMsg::Prices {
+            quote,
+            start_after,
+            limit,
+        } => to_binary(&query_prices(deps, quote, start_after, limit)?),
+        QueryMsg::PricesFor {
+            assets,
+            quote,
+            block_time,
+        } => to_binary(&query_prices_for(deps, env, assets, quote, block_time)?),
+        QueryMsg::Assets { start_after, limit } => {
+            to_binary(&query_assets(deps, start_after, limit)?)
+        }
+        QueryMsg::StaleFeeds {
+            block_time,
+            start_after,
+            limit,
+        } => to_binary(&query_stale_feeds(deps, block_time, start_after, limit)?),
+        QueryMsg::StalePrices {
+            older_than,
+            block_time,
+            start_after,
+            limit,
+        } => to_binary(&query_stale_prices(
+            deps,
+            older_than,
+            block_time,
+            start_after,
+            limit,
+        )?),
+        QueryMsg::PairSource { asset } => to_binary(&query_pair_source(deps, asset)?),
+        QueryMsg::Feeders { start_after, limit } => {
+            to_binary(&query_feeders(deps, start_after, limit)?)
         }
+        QueryMsg::SnapshotPrice { asset, height } => {
+            to_binary(&query_snapshot_price(deps, asset, height)?)
+        }
+        QueryMsg::FeederStats {
+            asset,
+            start_after,
+            limit,
+        } => to_binary(&query_feeder_stats(deps, asset, start_after, limit)?),
     }
 }
 
@@ -133,53 +906,439 @@ fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
     let resp = ConfigResponse {
         owner: deps.api.addr_humanize(&state.owner)?.to_string(),
         base_asset: state.base_asset,
+        valid_period: state.valid_period,
+        fallback_oracle: match state.fallback_oracle {
+            Some(fallback_oracle) => Some(deps.api.addr_humanize(&fallback_oracle)?.to_string()),
+            None => None,
+        },
+        snapshotter: match state.snapshotter {
+            Some(snapshotter) => Some(deps.api.addr_humanize(&snapshotter)?.to_string()),
+            None => None,
+        },
+        default_quote_denom: state.default_quote_denom,
     };
 
     Ok(resp)
 }
 
-fn query_feeder(deps: Deps, asset: String) -> StdResult<FeederResponse> {
-    let feeder = read_feeder(deps.storage, &asset)?;
+fn query_feeder(deps: Deps, asset: String, quote: Option<String>) -> StdResult<FeederResponse> {
+    let quote = match quote {
+        Some(quote) => quote,
+        None => read_config(deps.storage)?.default_quote_denom,
+    };
+    let feeder = read_feeder(deps.storage, &asset, &quote)?;
     let resp = FeederResponse {
         asset,
+        quote,
         feeder: deps.api.addr_humanize(&feeder)?.to_string(),
     };
 
     Ok(resp)
 }
 
-fn query_price(deps: Deps, base: String, quote: String) -> StdResult<PriceResponse> {
+/// Returns the locally fed price, or the fallback oracle's price when the
+/// local one is missing or older than `valid_period` and a fallback oracle
+/// is configured. A frozen price is served as is, however old.
+fn query_price(
+    deps: Deps,
+    env: Env,
+    base: String,
+    quote: String,
+    block_time: Option<u64>,
+) -> StdResult<PriceResponse> {
     let config: Config = read_config(deps.storage)?;
+    for asset in [&base, &quote] {
+        if let Some(AssetInfo {
+            deprecated: true, ..
+        }) = read_asset(deps.storage, asset)?
+        {
+            return Err(StdError::generic_err("asset deprecated"));
+        }
+    }
+
+    let valid_update_time = env.block.time.seconds().saturating_sub(config.valid_period);
+    let local_price = query_local_price(deps, &config, &base, &quote).map(|price| PriceResponse {
+        age_seconds: compute_age(&price, block_time),
+        ..price
+    });
+    if let Ok(price) = &local_price {
+        if price.frozen || !is_stale(price, valid_update_time) {
+            return local_price;
+        }
+    }
+
+    match config.fallback_oracle {
+        Some(fallback_oracle) => {
+            let fallback_price: StdResult<PriceResponse> =
+                deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+                    contract_addr: deps.api.addr_humanize(&fallback_oracle)?.to_string(),
+                    msg: to_binary(&QueryMsg::Price {
+                        base,
+                        quote,
+                        block_time,
+                    })?,
+                }));
+
+            match fallback_price {
+                Ok(fallback_price) if !is_stale(&fallback_price, valid_update_time) => {
+                    Ok(PriceResponse {
+                        age_seconds: compute_age(&fallback_price, block_time),
+                        source: "fallback".to_string(),
+                        ..fallback_price
+                    })
+                }
+                // a missing local price keeps its own error
+                _ => local_price.and(Err(StdError::generic_err("Price is too old"))),
+            }
+        }
+        // without a fallback, staleness is left to the consumer
+        None => local_price,
+    }
+}
+
+fn is_stale(price: &PriceResponse, valid_update_time: u64) -> bool {
+    price.last_updated_base < valid_update_time || price.last_updated_quote < valid_update_time
+}
+
+/// Age of the oldest side of the pair, relative to the caller's block time
+fn compute_age(price: &PriceResponse, block_time: Option<u64>) -> Option<u64> {
+    let last_updated_time = std::cmp::min(price.last_updated_base, price.last_updated_quote);
+    block_time.map(|block_time| block_time.saturating_sub(last_updated_time))
+}
+
+fn query_local_price(
+    deps: Deps,
+    config: &Config,
+    base: &str,
+    quote: &str,
+) -> StdResult<PriceResponse> {
+    // a price fed directly for the pair wins over the one derived via the base asset
+    if config.base_asset != quote {
+        if let Some(pair_price) = may_read_price(deps.storage, base, quote)? {
+            let base_info = read_asset_info(deps, config, base)?;
+            let quote_info = read_asset_info(deps, config, quote)?;
+            return Ok(PriceResponse {
+                rate: pair_price.price,
+                last_updated_base: pair_price.last_updated_time,
+                last_updated_quote: 9999999999,
+                decimals_base: base_info.decimals,
+                decimals_quote: quote_info.decimals,
+                age_seconds: None,
+                feeder_count: 1,
+                derived: false,
+                frozen: base_info.frozen || quote_info.frozen,
+                source: pair_price.source,
+                inverted: false,
+            });
+        }
+    }
+
+    // pairs are stored against their quote, so the swapped orientation is the reciprocal
+    if let Some(pair_price) = may_read_price(deps.storage, quote, base)? {
+        if pair_price.price.is_zero() {
+            return Err(StdError::generic_err(format!(
+                "Cannot invert zero price of {}",
+                quote
+            )));
+        }
+
+        let base_info = read_asset_info(deps, config, base)?;
+        let quote_info = read_asset_info(deps, config, quote)?;
+        return Ok(PriceResponse {
+            rate: Decimal256::one() / pair_price.price,
+            last_updated_base: 9999999999,
+            last_updated_quote: pair_price.last_updated_time,
+            decimals_base: base_info.decimals,
+            decimals_quote: quote_info.decimals,
+            age_seconds: None,
+            feeder_count: 1,
+            derived: false,
+            frozen: base_info.frozen || quote_info.frozen,
+            source: pair_price.source,
+            inverted: true,
+        });
+    }
+
     let quote_price = if config.base_asset == quote {
         PriceInfo {
             price: Decimal256::one(),
             last_updated_time: 9999999999,
+            source: "primary".to_string(),
         }
     } else {
-        read_price(deps.storage, &quote)?
+        read_price(deps.storage, quote, &config.base_asset)?
     };
 
     let base_price = if config.base_asset == base {
         PriceInfo {
             price: Decimal256::one(),
             last_updated_time: 9999999999,
+            source: "primary".to_string(),
         }
     } else {
-        read_price(deps.storage, &base)?
+        read_price(deps.storage, base, &config.base_asset)?
+    };
+
+    let base_info = read_asset_info(deps, config, base)?;
+    let quote_info = read_asset_info(deps, config, quote)?;
+
+    // every side other than the base asset is backed by a feeder
+    let fed_sides = [base, quote]
+        .iter()
+        .filter(|asset| config.base_asset != **asset)
+        .count() as u32;
+
+    // a rate is only as trustworthy as its weakest side
+    let source = if base_price.source == "amm" || quote_price.source == "amm" {
+        "amm".to_string()
+    } else {
+        "primary".to_string()
     };
 
     Ok(PriceResponse {
         rate: base_price.price / quote_price.price,
         last_updated_base: base_price.last_updated_time,
         last_updated_quote: quote_price.last_updated_time,
+        decimals_base: base_info.decimals,
+        decimals_quote: quote_info.decimals,
+        age_seconds: None,
+        feeder_count: fed_sides,
+        derived: fed_sides == 2,
+        frozen: base_info.frozen || quote_info.frozen,
+        source,
+        inverted: false,
     })
 }
 
+fn read_asset_info(deps: Deps, config: &Config, asset: &str) -> StdResult<AssetInfo> {
+    match read_asset(deps.storage, asset)? {
+        Some(info) => Ok(info),
+        None if config.base_asset == asset => Ok(AssetInfo {
+            decimals: DEFAULT_BASE_DECIMALS,
+            description: asset.to_string(),
+            heartbeat: None,
+            min_price: None,
+            max_price: None,
+            frozen: false,
+            deprecated: false,
+        }),
+        None => Err(StdError::generic_err(format!(
+            "Asset is not registered: {}",
+            asset
+        ))),
+    }
+}
+
 fn query_prices(
     deps: Deps,
+    quote: Option<String>,
     start_after: Option<String>,
     limit: Option<u32>,
 ) -> StdResult<PricesResponse> {
-    let prices: Vec<PricesResponseElem> = read_prices(deps.storage, start_after, limit)?;
+    let quote = match quote {
+        Some(quote) => quote,
+        None => read_config(deps.storage)?.default_quote_denom,
+    };
+    let prices: Vec<PricesResponseElem> = read_prices(deps.storage, &quote, start_after, limit)?;
     Ok(PricesResponse { prices })
 }
+
+/// Prices for several assets at once, in input order; an asset that cannot be
+/// priced carries its error instead of failing the whole query
+fn query_prices_for(
+    deps: Deps,
+    env: Env,
+    assets: Vec<String>,
+    quote: String,
+    block_time: Option<u64>,
+) -> StdResult<PricesForResponse> {
+    let prices = assets
+        .into_iter()
+        .map(|asset| {
+            match query_price(deps, env.clone(), asset.clone(), quote.clone(), block_time) {
+                Ok(price) => PricesForResponseElem {
+                    asset,
+                    price: Some(price),
+                    error: None,
+                },
+                Err(err) => PricesForResponseElem {
+                    asset,
+                    price: None,
+                    error: Some(err.to_string()),
+                },
+            }
+        })
+        .collect();
+
+    Ok(PricesForResponse { prices })
+}
+
+fn query_assets(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<AssetsResponse> {
+    let assets = read_assets(deps.storage, start_after, limit)?;
+    Ok(AssetsResponse { assets })
+}
+
+fn query_stale_feeds(
+    deps: Deps,
+    block_time: u64,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<StaleFeedsResponse> {
+    let config: Config = read_config(deps.storage)?;
+    let feeds = read_stale_feeds(
+        deps.storage,
+        &config.default_quote_denom,
+        block_time,
+        config.valid_period,
+        start_after,
+        limit,
+    )?;
+    Ok(StaleFeedsResponse { feeds })
+}
+
+fn query_stale_prices(
+    deps: Deps,
+    older_than: u64,
+    block_time: u64,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<StalePricesResponse> {
+    let config: Config = read_config(deps.storage)?;
+    let (stale_prices, next_start_after) = read_stale_prices(
+        deps.storage,
+        &config.default_quote_denom,
+        older_than,
+        block_time,
+        start_after,
+        limit,
+    )?;
+
+    let prices = stale_prices
+        .into_iter()
+        .map(|(asset, last_updated_time, age)| {
+            let feeder = match may_read_feeder(deps.storage, &asset, &config.default_quote_denom)? {
+                Some(feeder) => Some(deps.api.addr_humanize(&feeder)?.to_string()),
+                None => None,
+            };
+            Ok(StalePricesResponseElem {
+                asset,
+                last_updated_time,
+                age,
+                feeder,
+            })
+        })
+        .collect::<StdResult<Vec<StalePricesResponseElem>>>()?;
+
+    Ok(StalePricesResponse {
+        prices,
+        next_start_after,
+    })
+}
+
+fn query_pair_source(deps: Deps, asset: String) -> StdResult<PairSourceResponse> {
+    let pair_source = match read_pair_source(deps.storage, &asset)? {
+        Some(pair_source) => pair_source,
+        None => {
+            return Err(StdError::generic_err(format!(
+                "No AMM pair registered for asset: {}",
+                asset
+            )))
+        }
+    };
+
+    Ok(PairSourceResponse {
+        asset,
+        pair: deps.api.addr_humanize(&pair_source.pair)?.to_string(),
+        max_deviation: pair_source.max_deviation,
+        twap_window: pair_source.twap_window,
+        twap: pair_source.twap,
+        twap_updated_time: pair_source.twap_updated_time,
+    })
+}
+
+fn query_feeder_stats(
+    deps: Deps,
+    asset: Option<String>,
+    start_after: Option<(String, String)>,
+    limit: Option<u32>,
+) -> StdResult<FeederStatsResponse> {
+    let start_after = match start_after {
+        Some((asset, feeder)) => Some((asset, deps.api.addr_canonicalize(&feeder)?)),
+        None => None,
+    };
+
+    let stats = read_feeders_stats(deps.storage, asset, start_after, limit)?
+        .into_iter()
+        .map(|(asset, feeder, stats)| {
+            Ok(FeederStatsResponseElem {
+                asset,
+                feeder: deps.api.addr_humanize(&feeder)?.to_string(),
+                accepted: stats.accepted,
+                rejected: stats.rejected,
+                last_submission_time: stats.last_submission_time,
+            })
+        })
+        .collect::<StdResult<Vec<FeederStatsResponseElem>>>()?;
+
+    Ok(FeederStatsResponse { stats })
+}
+
+fn query_snapshot_price(
+    deps: Deps,
+    asset: String,
+    height: u64,
+) -> StdResult<SnapshotPriceResponse> {
+    let snapshot = match read_snapshot(deps.storage, height)? {
+        Some(snapshot) => snapshot,
+        None => {
+            return Err(StdError::generic_err(
+                "No price snapshot at the given height",
+            ))
+        }
+    };
+
+    match snapshot
+        .into_iter()
+        .find(|(snapshot_asset, _)| *snapshot_asset == asset)
+    {
+        Some((asset, price)) => Ok(SnapshotPriceResponse {
+            asset,
+            height,
+            rate: price.price,
+            last_updated_time: price.last_updated_time,
+        }),
+        None => Err(StdError::generic_err(format!(
+            "No snapshot price for {} at height {}",
+            asset, height
+        ))),
+    }
+}
+
+fn query_feeders(
+    deps: Deps,
+    start_after: Option<(String, String)>,
+    limit: Option<u32>,
+) -> StdResult<FeedersResponse> {
+    let start_after = match start_after {
+        Some((asset, feeder)) => Some((asset, deps.api.addr_canonicalize(&feeder)?)),
+        None => None,
+    };
+
+    let feeders = read_feeder_registrations(deps.storage, start_after, limit)?
+        .into_iter()
+        .map(|(asset, feeder, registration)| {
+            Ok(FeedersResponseElem {
+                asset,
+                feeder: deps.api.addr_humanize(&feeder)?.to_string(),
+                quotes: registration.quotes,
+                registered_at_height: registration.registered_at_height,
+            })
+        })
+        .collect::<StdResult<Vec<FeedersResponseElem>>>()?;
+
+    Ok(FeedersResponse { feeders })
+}
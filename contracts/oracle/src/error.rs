@@ -1,3 +1,4 @@
+use cosmwasm_bignumber::Decimal256;
 use cosmwasm_std::{OverflowError, StdError};
 use thiserror::Error;
 
@@ -11,4 +12,51 @@ pub enum ContractError {
 
     #[error("Unauthorized")]
     Unauthorized {},
+
+    #[error("Asset is not registered: {0}")]
+    AssetNotRegistered(String),
+
+    #[error("Asset price is frozen: {0}")]
+    AssetFrozen(String),
+
+    #[error("Asset is deprecated: {0}")]
+    AssetDeprecated(String),
+
+    #[error("Price of {asset} is below min_price: {price} < {min_price}")]
+    PriceBelowMinPrice {
+        asset: String,
+        min_price: Decimal256,
+        price: Decimal256,
+    },
+
+    #[error("Price of {asset} is above max_price: {price} > {max_price}")]
+    PriceAboveMaxPrice {
+        asset: String,
+        max_price: Decimal256,
+        price: Decimal256,
+    },
+
+    #[error("Price bounds of {asset} are in the base asset, it cannot be fed in {quote}")]
+    BoundedAssetQuote { asset: String, quote: String },
+
+    #[error("min_price must not exceed max_price")]
+    InvalidPriceBounds {},
+
+    #[error("Invalid price signature")]
+    InvalidSignature {},
+
+    #[error("Price timestamp is not newer than the stored price")]
+    PriceTimestampTooOld {},
+
+    #[error("Price timestamp is too far in the future")]
+    PriceTimestampInFuture {},
+
+    #[error("No AMM pair registered for asset: {0}")]
+    PairSourceNotRegistered(String),
+
+    #[error("No initial price to seed the TWAP of asset: {0}")]
+    PairSourceNotSeeded(String),
+
+    #[error("AMM spot price deviates too much from the TWAP: {0}")]
+    PriceDeviationTooHigh(String),
 }
@@ -1,6 +1,8 @@
 pub mod contract;
 pub mod error;
+pub mod msg;
+pub mod querier;
 pub mod state;
 
 #[cfg(test)]
-mod tests;
+mod testing;
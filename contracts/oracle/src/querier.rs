@@ -0,0 +1,31 @@
+use cosmwasm_bignumber::{Decimal256, Uint256};
+use cosmwasm_std::{to_binary, Addr, Deps, QueryRequest, StdError, StdResult, WasmQuery};
+use terraswap::asset::AssetInfo;
+use terraswap::pair::{PoolResponse, QueryMsg as PairQueryMsg};
+
+/// Spot price of `asset` in `quote` implied by the pair reserves
+pub fn query_pair_price(deps: Deps, pair: Addr, asset: &str, quote: &str) -> StdResult<Decimal256> {
+    let pool: PoolResponse = deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: pair.to_string(),
+        msg: to_binary(&PairQueryMsg::Pool {})?,
+    }))?;
+
+    let reserve = |denom: &str| -> StdResult<Uint256> {
+        pool.assets
+            .iter()
+            .find(|pool_asset| match &pool_asset.info {
+                AssetInfo::NativeToken { denom: native } => native == denom,
+                AssetInfo::Token { contract_addr } => contract_addr == denom,
+            })
+            .map(|pool_asset| Uint256::from(pool_asset.amount))
+            .ok_or_else(|| StdError::generic_err(format!("Pair {} does not hold {}", pair, denom)))
+    };
+
+    let asset_reserve = reserve(asset)?;
+    let quote_reserve = reserve(quote)?;
+    if asset_reserve.is_zero() || quote_reserve.is_zero() {
+        return Err(StdError::generic_err(format!("Pair {} is empty", pair)));
+    }
+
+    Ok(Decimal256::from_uint256(quote_reserve) / Decimal256::from_uint256(asset_reserve))
+}
@@ -2,20 +2,38 @@ use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use cosmwasm_bignumber::Decimal256;
-use cosmwasm_std::{CanonicalAddr, Order, StdError, StdResult, Storage};
-use cosmwasm_storage::{singleton, singleton_read, Bucket, ReadonlyBucket};
+use cosmwasm_std::{Binary, CanonicalAddr, Order, StdError, StdResult, Storage};
+use cosmwasm_storage::{singleton, singleton_read, to_length_prefixed, Bucket, ReadonlyBucket};
 
-use moneymarket::oracle::PricesResponseElem;
+use moneymarket::oracle::{AssetResponse, PricesResponseElem, StaleFeedResponse};
+use moneymarket::pagination::{calc_range_start_human, clamp_limit, DEFAULT_LIMIT, MAX_LIMIT};
 
 static PREFIX_PRICE: &[u8] = b"price";
 static PREFIX_FEEDER: &[u8] = b"feeder";
+static PREFIX_ASSET: &[u8] = b"asset";
+static PREFIX_FEEDER_KEY: &[u8] = b"feeder_key";
+static PREFIX_PAIR_SOURCE: &[u8] = b"pair_source";
+static PREFIX_FEEDER_STATS: &[u8] = b"feeder_stats";
+static PREFIX_FEEDER_REGISTRATION: &[u8] = b"feeder_registration";
+static PREFIX_SNAPSHOT: &[u8] = b"snapshot";
 
 static KEY_CONFIG: &[u8] = b"config";
+static KEY_SNAPSHOT_HEIGHTS: &[u8] = b"snapshot_heights";
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Config {
     pub owner: CanonicalAddr,
     pub base_asset: String,
+    /// the fields below were added after the first release,
+    /// the migration fills them in for existing contracts
+    #[serde(default)]
+    pub valid_period: u64,
+    #[serde(default)]
+    pub fallback_oracle: Option<CanonicalAddr>,
+    #[serde(default)]
+    pub snapshotter: Option<CanonicalAddr>,
+    #[serde(default)]
+    pub default_quote_denom: String,
 }
 
 pub fn store_config(storage: &mut dyn Storage, config: &Config) -> StdResult<()> {
@@ -30,15 +48,40 @@ pub fn read_config(storage: &dyn Storage) -> StdResult<Config> {
 pub struct PriceInfo {
     pub price: Decimal256,
     pub last_updated_time: u64,
+    /// "primary" for fed prices, "amm" for prices read from a pair
+    #[serde(default = "default_price_source")]
+    pub source: String,
 }
 
-pub fn store_price(storage: &mut dyn Storage, asset: &str, price: &PriceInfo) -> StdResult<()> {
-    let mut price_bucket: Bucket<PriceInfo> = Bucket::new(storage, PREFIX_PRICE);
+fn default_price_source() -> String {
+    "primary".to_string()
+}
+
+// prices and feeders are keyed by quote, then by asset
+pub fn store_price(
+    storage: &mut dyn Storage,
+    asset: &str,
+    quote: &str,
+    price: &PriceInfo,
+) -> StdResult<()> {
+    let mut price_bucket: Bucket<PriceInfo> =
+        Bucket::multilevel(storage, &[PREFIX_PRICE, quote.as_bytes()]);
     price_bucket.save(asset.as_bytes(), price)
 }
 
-pub fn read_price(storage: &dyn Storage, asset: &str) -> StdResult<PriceInfo> {
-    let price_bucket: ReadonlyBucket<PriceInfo> = ReadonlyBucket::new(storage, PREFIX_PRICE);
+pub fn may_read_price(
+    storage: &dyn Storage,
+    asset: &str,
+    quote: &str,
+) -> StdResult<Option<PriceInfo>> {
+    let price_bucket: ReadonlyBucket<PriceInfo> =
+        ReadonlyBucket::multilevel(storage, &[PREFIX_PRICE, quote.as_bytes()]);
+    price_bucket.may_load(asset.as_bytes())
+}
+
+pub fn read_price(storage: &dyn Storage, asset: &str, quote: &str) -> StdResult<PriceInfo> {
+    let price_bucket: ReadonlyBucket<PriceInfo> =
+        ReadonlyBucket::multilevel(storage, &[PREFIX_PRICE, quote.as_bytes()]);
     let res = price_bucket.load(asset.as_bytes());
     match res {
         Ok(data) => Ok(data),
@@ -48,18 +91,38 @@ pub fn read_price(storage: &dyn Storage, asset: &str) -> StdResult<PriceInfo> {
     }
 }
 
-// settings for pagination
-const MAX_LIMIT: u32 = 30;
-const DEFAULT_LIMIT: u32 = 10;
+/// Moves the prices stored before the quote was part of the key under `quote`.
+/// Keys written by the per quote layout share the prefix, but start with the
+/// length of the quote, so only asset keys are moved.
+pub fn migrate_legacy_prices(storage: &mut dyn Storage, quote: &str) -> StdResult<Vec<String>> {
+    let legacy_prices: Vec<(Vec<u8>, PriceInfo)> = ReadonlyBucket::new(storage, PREFIX_PRICE)
+        .range(None, None, Order::Ascending)
+        .filter(|item| !matches!(item, Ok((k, _)) if k.first() == Some(&0)))
+        .collect::<StdResult<_>>()?;
+
+    let mut assets: Vec<String> = vec![];
+    for (k, price) in legacy_prices {
+        Bucket::<PriceInfo>::new(storage, PREFIX_PRICE).remove(&k);
+
+        let asset = std::str::from_utf8(&k).unwrap().to_string();
+        store_price(storage, &asset, quote, &price)?;
+        assets.push(asset);
+    }
+
+    Ok(assets)
+}
+
 pub fn read_prices(
     storage: &dyn Storage,
+    quote: &str,
     start_after: Option<String>,
     limit: Option<u32>,
 ) -> StdResult<Vec<PricesResponseElem>> {
-    let price_bucket: ReadonlyBucket<PriceInfo> = ReadonlyBucket::new(storage, PREFIX_PRICE);
+    let price_bucket: ReadonlyBucket<PriceInfo> =
+        ReadonlyBucket::multilevel(storage, &[PREFIX_PRICE, quote.as_bytes()]);
 
-    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
-    let start = calc_range_start(start_after);
+    let limit = clamp_limit(limit, DEFAULT_LIMIT, MAX_LIMIT);
+    let start = calc_range_start_human(start_after);
 
     price_bucket
         .range(start.as_deref(), None, Order::Ascending)
@@ -77,17 +140,176 @@ pub fn read_prices(
         .collect()
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AssetInfo {
+    pub decimals: u8,
+    pub description: String,
+    /// Maximum update interval, falls back to the config `valid_period`
+    pub heartbeat: Option<u64>,
+    /// Sanity bounds for fed prices, None means unlimited
+    #[serde(default)]
+    pub min_price: Option<Decimal256>,
+    #[serde(default)]
+    pub max_price: Option<Decimal256>,
+    #[serde(default)]
+    pub frozen: bool,
+    #[serde(default)]
+    pub deprecated: bool,
+}
+
+pub fn store_asset(storage: &mut dyn Storage, asset: &str, info: &AssetInfo) -> StdResult<()> {
+    let mut asset_bucket: Bucket<AssetInfo> = Bucket::new(storage, PREFIX_ASSET);
+    asset_bucket.save(asset.as_bytes(), info)
+}
+
+pub fn read_asset(storage: &dyn Storage, asset: &str) -> StdResult<Option<AssetInfo>> {
+    let asset_bucket: ReadonlyBucket<AssetInfo> = ReadonlyBucket::new(storage, PREFIX_ASSET);
+    asset_bucket.may_load(asset.as_bytes())
+}
+
+pub fn read_assets(
+    storage: &dyn Storage,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Vec<AssetResponse>> {
+    let asset_bucket: ReadonlyBucket<AssetInfo> = ReadonlyBucket::new(storage, PREFIX_ASSET);
+
+    let limit = clamp_limit(limit, DEFAULT_LIMIT, MAX_LIMIT);
+    let start = calc_range_start_human(start_after);
+
+    asset_bucket
+        .range(start.as_deref(), None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (k, v) = item?;
+
+            let asset = std::str::from_utf8(&k).unwrap().to_string();
+            Ok(AssetResponse {
+                asset,
+                decimals: v.decimals,
+                description: v.description,
+                heartbeat: v.heartbeat,
+                min_price: v.min_price,
+                max_price: v.max_price,
+                frozen: v.frozen,
+                deprecated: v.deprecated,
+            })
+        })
+        .collect()
+}
+
+pub fn read_stale_feeds(
+    storage: &dyn Storage,
+    quote: &str,
+    block_time: u64,
+    default_heartbeat: u64,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Vec<StaleFeedResponse>> {
+    let asset_bucket: ReadonlyBucket<AssetInfo> = ReadonlyBucket::new(storage, PREFIX_ASSET);
+    let price_bucket: ReadonlyBucket<PriceInfo> =
+        ReadonlyBucket::multilevel(storage, &[PREFIX_PRICE, quote.as_bytes()]);
+
+    let limit = clamp_limit(limit, DEFAULT_LIMIT, MAX_LIMIT);
+    let start = calc_range_start_human(start_after);
+
+    let mut stale_feeds: Vec<StaleFeedResponse> = vec![];
+    for item in asset_bucket.range(start.as_deref(), None, Order::Ascending) {
+        if stale_feeds.len() == limit {
+            break;
+        }
+
+        let (k, v) = item?;
+        // deprecated assets are not expected to be fed anymore
+        if v.deprecated {
+            continue;
+        }
+
+        let heartbeat = v.heartbeat.unwrap_or(default_heartbeat);
+        let last_updated_time = price_bucket.may_load(&k)?.map(|p| p.last_updated_time);
+        let age = last_updated_time.map(|t| block_time.saturating_sub(t));
+        let is_stale = match age {
+            Some(age) => age > heartbeat,
+            None => true,
+        };
+        if is_stale {
+            stale_feeds.push(StaleFeedResponse {
+                asset: std::str::from_utf8(&k).unwrap().to_string(),
+                heartbeat,
+                last_updated_time,
+                age,
+            });
+        }
+    }
+
+    Ok(stale_feeds)
+}
+
+/// A fed price past the staleness threshold, as (asset, last_updated_time, age)
+pub type StalePrice = (String, u64, u64);
+
+/// Prices older than `older_than` among the next `limit` fed assets,
+/// along with the last scanned asset when more remain
+pub fn read_stale_prices(
+    storage: &dyn Storage,
+    quote: &str,
+    older_than: u64,
+    block_time: u64,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<(Vec<StalePrice>, Option<String>)> {
+    let price_bucket: ReadonlyBucket<PriceInfo> =
+        ReadonlyBucket::multilevel(storage, &[PREFIX_PRICE, quote.as_bytes()]);
+
+    let limit = clamp_limit(limit, DEFAULT_LIMIT, MAX_LIMIT);
+    let start = calc_range_start_human(start_after);
+
+    let mut stale_prices = vec![];
+    let mut last_scanned: Option<String> = None;
+    for (scanned, item) in price_bucket
+        .range(start.as_deref(), None, Order::Ascending)
+        .enumerate()
+    {
+        if scanned == limit {
+            return Ok((stale_prices, last_scanned));
+        }
+
+        let (k, v) = item?;
+        let asset = std::str::from_utf8(&k).unwrap().to_string();
+        let age = block_time.saturating_sub(v.last_updated_time);
+        if age > older_than {
+            stale_prices.push((asset.clone(), v.last_updated_time, age));
+        }
+        last_scanned = Some(asset);
+    }
+
+    Ok((stale_prices, None))
+}
+
 pub fn store_feeder(
     storage: &mut dyn Storage,
     asset: &str,
+    quote: &str,
     feeder: &CanonicalAddr,
 ) -> StdResult<()> {
-    let mut price_bucket: Bucket<CanonicalAddr> = Bucket::new(storage, PREFIX_FEEDER);
+    let mut price_bucket: Bucket<CanonicalAddr> =
+        Bucket::multilevel(storage, &[PREFIX_FEEDER, quote.as_bytes()]);
     price_bucket.save(asset.as_bytes(), feeder)
 }
 
-pub fn read_feeder(storage: &dyn Storage, asset: &str) -> StdResult<CanonicalAddr> {
-    let price_bucket: ReadonlyBucket<CanonicalAddr> = ReadonlyBucket::new(storage, PREFIX_FEEDER);
+pub fn may_read_feeder(
+    storage: &dyn Storage,
+    asset: &str,
+    quote: &str,
+) -> StdResult<Option<CanonicalAddr>> {
+    let price_bucket: ReadonlyBucket<CanonicalAddr> =
+        ReadonlyBucket::multilevel(storage, &[PREFIX_FEEDER, quote.as_bytes()]);
+    price_bucket.may_load(asset.as_bytes())
+}
+
+pub fn read_feeder(storage: &dyn Storage, asset: &str, quote: &str) -> StdResult<CanonicalAddr> {
+    let price_bucket: ReadonlyBucket<CanonicalAddr> =
+        ReadonlyBucket::multilevel(storage, &[PREFIX_FEEDER, quote.as_bytes()]);
     let res = price_bucket.load(asset.as_bytes());
     match res {
         Ok(data) => Ok(data),
@@ -97,11 +319,244 @@ pub fn read_feeder(storage: &dyn Storage, asset: &str) -> StdResult<CanonicalAdd
     }
 }
 
-// this will set the first key after the provided key, by appending a 1 byte
-fn calc_range_start(start_after: Option<String>) -> Option<Vec<u8>> {
-    start_after.map(|idx| {
-        let mut v = idx.as_bytes().to_vec();
-        v.push(1);
-        v
-    })
+/// Moves the feeders registered before the quote was part of the key under `quote`
+pub fn migrate_legacy_feeders(
+    storage: &mut dyn Storage,
+    quote: &str,
+) -> StdResult<Vec<(String, CanonicalAddr)>> {
+    let legacy_feeders: Vec<(Vec<u8>, CanonicalAddr)> = ReadonlyBucket::new(storage, PREFIX_FEEDER)
+        .range(None, None, Order::Ascending)
+        .filter(|item| !matches!(item, Ok((k, _)) if k.first() == Some(&0)))
+        .collect::<StdResult<_>>()?;
+
+    let mut feeders: Vec<(String, CanonicalAddr)> = vec![];
+    for (k, feeder) in legacy_feeders {
+        Bucket::<CanonicalAddr>::new(storage, PREFIX_FEEDER).remove(&k);
+
+        let asset = std::str::from_utf8(&k).unwrap().to_string();
+        store_feeder(storage, &asset, quote, &feeder)?;
+        feeders.push((asset, feeder));
+    }
+
+    Ok(feeders)
+}
+
+pub fn store_feeder_key(storage: &mut dyn Storage, asset: &str, pubkey: &Binary) -> StdResult<()> {
+    let mut key_bucket: Bucket<Binary> = Bucket::new(storage, PREFIX_FEEDER_KEY);
+    key_bucket.save(asset.as_bytes(), pubkey)
+}
+
+pub fn read_feeder_key(storage: &dyn Storage, asset: &str) -> StdResult<Binary> {
+    let key_bucket: ReadonlyBucket<Binary> = ReadonlyBucket::new(storage, PREFIX_FEEDER_KEY);
+    let res = key_bucket.load(asset.as_bytes());
+    match res {
+        Ok(data) => Ok(data),
+        Err(_err) => Err(StdError::generic_err(
+            "No feeder key for the specified asset exist",
+        )),
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PairSource {
+    pub pair: CanonicalAddr,
+    pub max_deviation: Decimal256,
+    pub twap_window: u64,
+    pub twap: Option<Decimal256>,
+    pub twap_updated_time: u64,
+}
+
+pub fn store_pair_source(
+    storage: &mut dyn Storage,
+    asset: &str,
+    source: &PairSource,
+) -> StdResult<()> {
+    let mut source_bucket: Bucket<PairSource> = Bucket::new(storage, PREFIX_PAIR_SOURCE);
+    source_bucket.save(asset.as_bytes(), source)
+}
+
+pub fn read_pair_source(storage: &dyn Storage, asset: &str) -> StdResult<Option<PairSource>> {
+    let source_bucket: ReadonlyBucket<PairSource> =
+        ReadonlyBucket::new(storage, PREFIX_PAIR_SOURCE);
+    source_bucket.may_load(asset.as_bytes())
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct FeederStats {
+    pub accepted: u64,
+    pub rejected: u64,
+    pub last_submission_time: u64,
+}
+
+// stats are keyed by asset, then by feeder
+pub fn store_feeder_stats(
+    storage: &mut dyn Storage,
+    asset: &str,
+    feeder: &CanonicalAddr,
+    stats: &FeederStats,
+) -> StdResult<()> {
+    let mut stats_bucket: Bucket<FeederStats> =
+        Bucket::multilevel(storage, &[PREFIX_FEEDER_STATS, asset.as_bytes()]);
+    stats_bucket.save(feeder.as_slice(), stats)
+}
+
+pub fn read_feeder_stats(
+    storage: &dyn Storage,
+    asset: &str,
+    feeder: &CanonicalAddr,
+) -> StdResult<FeederStats> {
+    let stats_bucket: ReadonlyBucket<FeederStats> =
+        ReadonlyBucket::multilevel(storage, &[PREFIX_FEEDER_STATS, asset.as_bytes()]);
+    Ok(stats_bucket
+        .may_load(feeder.as_slice())?
+        .unwrap_or_default())
+}
+
+/// Stats grouped by asset, then ordered by feeder, optionally restricted to one asset
+pub fn read_feeders_stats(
+    storage: &dyn Storage,
+    asset: Option<String>,
+    start_after: Option<(String, CanonicalAddr)>,
+    limit: Option<u32>,
+) -> StdResult<Vec<(String, CanonicalAddr, FeederStats)>> {
+    // keys under the stats prefix are the length prefixed asset followed by the feeder
+    let stats_bucket: ReadonlyBucket<FeederStats> =
+        ReadonlyBucket::new(storage, PREFIX_FEEDER_STATS);
+
+    let limit = clamp_limit(limit, DEFAULT_LIMIT, MAX_LIMIT);
+    let start = match start_after {
+        Some((start_asset, feeder)) if asset.is_none() || asset == Some(start_asset.clone()) => {
+            Some(calc_composite_range_start(&start_asset, &feeder))
+        }
+        _ => asset
+            .as_ref()
+            .map(|asset| to_length_prefixed(asset.as_bytes())),
+    };
+
+    let mut stats = vec![];
+    for item in stats_bucket.range(start.as_deref(), None, Order::Ascending) {
+        let (k, v) = item?;
+        let (item_asset, feeder) = split_composite_key(&k);
+
+        if let Some(asset) = &asset {
+            if *asset != item_asset {
+                break;
+            }
+        }
+        if stats.len() == limit {
+            break;
+        }
+
+        stats.push((item_asset, feeder, v));
+    }
+
+    Ok(stats)
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FeederRegistration {
+    /// quotes the feeder is registered for
+    pub quotes: Vec<String>,
+    pub registered_at_height: u64,
+}
+
+// registrations are keyed by asset, then by feeder
+pub fn store_feeder_registration(
+    storage: &mut dyn Storage,
+    asset: &str,
+    feeder: &CanonicalAddr,
+    registration: &FeederRegistration,
+) -> StdResult<()> {
+    let mut registration_bucket: Bucket<FeederRegistration> =
+        Bucket::multilevel(storage, &[PREFIX_FEEDER_REGISTRATION, asset.as_bytes()]);
+    registration_bucket.save(feeder.as_slice(), registration)
+}
+
+pub fn remove_feeder_registration(storage: &mut dyn Storage, asset: &str, feeder: &CanonicalAddr) {
+    let mut registration_bucket: Bucket<FeederRegistration> =
+        Bucket::multilevel(storage, &[PREFIX_FEEDER_REGISTRATION, asset.as_bytes()]);
+    registration_bucket.remove(feeder.as_slice())
+}
+
+pub fn read_feeder_registration(
+    storage: &dyn Storage,
+    asset: &str,
+    feeder: &CanonicalAddr,
+) -> StdResult<Option<FeederRegistration>> {
+    let registration_bucket: ReadonlyBucket<FeederRegistration> =
+        ReadonlyBucket::multilevel(storage, &[PREFIX_FEEDER_REGISTRATION, asset.as_bytes()]);
+    registration_bucket.may_load(feeder.as_slice())
+}
+
+/// Registrations across all assets, ordered by (asset, feeder)
+pub fn read_feeder_registrations(
+    storage: &dyn Storage,
+    start_after: Option<(String, CanonicalAddr)>,
+    limit: Option<u32>,
+) -> StdResult<Vec<(String, CanonicalAddr, FeederRegistration)>> {
+    let registration_bucket: ReadonlyBucket<FeederRegistration> =
+        ReadonlyBucket::new(storage, PREFIX_FEEDER_REGISTRATION);
+
+    let limit = clamp_limit(limit, DEFAULT_LIMIT, MAX_LIMIT);
+    let start = start_after.map(|(asset, feeder)| calc_composite_range_start(&asset, &feeder));
+
+    registration_bucket
+        .range(start.as_deref(), None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (k, v) = item?;
+            let (asset, feeder) = split_composite_key(&k);
+            Ok((asset, feeder, v))
+        })
+        .collect()
+}
+
+/// Prices pinned at a block height, as (asset, price)
+pub type Snapshot = Vec<(String, PriceInfo)>;
+
+pub fn store_snapshot(
+    storage: &mut dyn Storage,
+    height: u64,
+    snapshot: &Snapshot,
+) -> StdResult<()> {
+    let mut snapshot_bucket: Bucket<Snapshot> = Bucket::new(storage, PREFIX_SNAPSHOT);
+    snapshot_bucket.save(&height.to_be_bytes(), snapshot)
+}
+
+pub fn read_snapshot(storage: &dyn Storage, height: u64) -> StdResult<Option<Snapshot>> {
+    let snapshot_bucket: ReadonlyBucket<Snapshot> = ReadonlyBucket::new(storage, PREFIX_SNAPSHOT);
+    snapshot_bucket.may_load(&height.to_be_bytes())
+}
+
+pub fn remove_snapshot(storage: &mut dyn Storage, height: u64) {
+    let mut snapshot_bucket: Bucket<Snapshot> = Bucket::new(storage, PREFIX_SNAPSHOT);
+    snapshot_bucket.remove(&height.to_be_bytes())
+}
+
+/// Heights of the retained snapshots, oldest first
+pub fn store_snapshot_heights(storage: &mut dyn Storage, heights: &[u64]) -> StdResult<()> {
+    singleton(storage, KEY_SNAPSHOT_HEIGHTS).save(&heights.to_vec())
+}
+
+pub fn read_snapshot_heights(storage: &dyn Storage) -> StdResult<Vec<u64>> {
+    Ok(singleton_read(storage, KEY_SNAPSHOT_HEIGHTS)
+        .may_load()?
+        .unwrap_or_default())
+}
+
+// keys of buckets nested by asset are the length prefixed asset followed by the address
+fn split_composite_key(key: &[u8]) -> (String, CanonicalAddr) {
+    let asset_len = u16::from_be_bytes([key[0], key[1]]) as usize;
+    let asset = std::str::from_utf8(&key[2..2 + asset_len])
+        .unwrap()
+        .to_string();
+    (asset, CanonicalAddr::from(key[2 + asset_len..].to_vec()))
+}
+
+// this will set the first key after the provided (asset, address), by appending a 1 byte
+fn calc_composite_range_start(asset: &str, addr: &CanonicalAddr) -> Vec<u8> {
+    let mut v = to_length_prefixed(asset.as_bytes());
+    v.extend_from_slice(addr.as_slice());
+    v.push(1);
+    v
 }
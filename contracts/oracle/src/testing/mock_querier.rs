@@ -0,0 +1,137 @@
+use cosmwasm_std::testing::{MockApi, MockQuerier, MockStorage, MOCK_CONTRACT_ADDR};
+use cosmwasm_std::{
+    from_binary, from_slice, to_binary, Coin, ContractResult, Empty, OwnedDeps, Querier,
+    QuerierResult, QueryRequest, SystemError, SystemResult, WasmQuery,
+};
+use std::collections::HashMap;
+
+use moneymarket::oracle::{PriceResponse, QueryMsg};
+use terraswap::pair::PoolResponse;
+
+/// mock_dependencies is a drop-in replacement for cosmwasm_std::testing::mock_dependencies
+/// this uses our CustomQuerier.
+pub fn mock_dependencies(
+    contract_balance: &[Coin],
+) -> OwnedDeps<MockStorage, MockApi, WasmMockQuerier> {
+    let custom_querier: WasmMockQuerier =
+        WasmMockQuerier::new(MockQuerier::new(&[(MOCK_CONTRACT_ADDR, contract_balance)]));
+
+    OwnedDeps {
+        storage: MockStorage::default(),
+        api: MockApi::default(),
+        querier: custom_querier,
+    }
+}
+
+pub struct WasmMockQuerier {
+    base: MockQuerier<Empty>,
+    fallback_price_querier: FallbackPriceQuerier,
+    pool_querier: PoolQuerier,
+}
+
+#[derive(Clone, Default)]
+pub struct FallbackPriceQuerier {
+    // (contract, base, quote) => price served by the secondary oracle
+    fallback_price: HashMap<(String, String, String), PriceResponse>,
+}
+
+impl FallbackPriceQuerier {
+    pub fn new(fallback_price: &[(&(String, String, String), &PriceResponse)]) -> Self {
+        let mut price_map: HashMap<(String, String, String), PriceResponse> = HashMap::new();
+        for (key, price) in fallback_price.iter() {
+            price_map.insert((*key).clone(), (*price).clone());
+        }
+
+        FallbackPriceQuerier {
+            fallback_price: price_map,
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct PoolQuerier {
+    // pair contract => reserves
+    pools: HashMap<String, PoolResponse>,
+}
+
+impl PoolQuerier {
+    pub fn new(pools: &[(&String, &PoolResponse)]) -> Self {
+        let mut pool_map: HashMap<String, PoolResponse> = HashMap::new();
+        for (pair, pool) in pools.iter() {
+            pool_map.insert(pair.to_string(), (*pool).clone());
+        }
+
+        PoolQuerier { pools: pool_map }
+    }
+}
+
+impl Querier for WasmMockQuerier {
+    fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+        // MockQuerier doesn't support Custom, so we ignore it completely here
+        let request: QueryRequest<Empty> = match from_slice(bin_request) {
+            Ok(v) => v,
+            Err(e) => {
+                return SystemResult::Err(SystemError::InvalidRequest {
+                    error: format!("Parsing query request: {}", e),
+                    request: bin_request.into(),
+                })
+            }
+        };
+        self.handle_query(&request)
+    }
+}
+
+impl WasmMockQuerier {
+    pub fn handle_query(&self, request: &QueryRequest<Empty>) -> QuerierResult {
+        match &request {
+            QueryRequest::Wasm(WasmQuery::Smart { contract_addr, .. })
+                if self.pool_querier.pools.contains_key(contract_addr) =>
+            {
+                let pool = self.pool_querier.pools.get(contract_addr).unwrap();
+                SystemResult::Ok(ContractResult::from(to_binary(pool)))
+            }
+            QueryRequest::Wasm(WasmQuery::Smart { contract_addr, msg }) => {
+                match from_binary(msg).unwrap() {
+                    QueryMsg::Price { base, quote, .. } => {
+                        match self.fallback_price_querier.fallback_price.get(&(
+                            contract_addr.to_string(),
+                            base,
+                            quote,
+                        )) {
+                            Some(v) => SystemResult::Ok(ContractResult::from(to_binary(v))),
+                            None => SystemResult::Err(SystemError::InvalidRequest {
+                                error: "No oracle price exists".to_string(),
+                                request: msg.as_slice().into(),
+                            }),
+                        }
+                    }
+                    _ => panic!("DO NOT ENTER HERE"),
+                }
+            }
+            _ => self.base.handle_query(request),
+        }
+    }
+}
+
+impl WasmMockQuerier {
+    pub fn new(base: MockQuerier<Empty>) -> Self {
+        WasmMockQuerier {
+            base,
+            fallback_price_querier: FallbackPriceQuerier::default(),
+            pool_querier: PoolQuerier::default(),
+        }
+    }
+
+    // configure the secondary oracle prices
+    pub fn with_fallback_price(
+        &mut self,
+        fallback_price: &[(&(String, String, String), &PriceResponse)],
+    ) {
+        self.fallback_price_querier = FallbackPriceQuerier::new(fallback_price);
+    }
+
+    // configure the AMM pair reserves
+    pub fn with_pool(&mut self, pools: &[(&String, &PoolResponse)]) {
+        self.pool_querier = PoolQuerier::new(pools);
+    }
+}
@@ -0,0 +1,2991 @@
+use crate::contract::{execute, instantiate, migrate, query};
+use crate::error::ContractError;
+use crate::testing::mock_querier::mock_dependencies;
+use cosmwasm_bignumber::Decimal256;
+use cosmwasm_std::testing::{mock_env, mock_info};
+use cosmwasm_std::{attr, from_binary, Api, Binary, StdError, Storage, Uint128};
+use cosmwasm_storage::{to_length_prefixed, to_length_prefixed_nested};
+use k256::ecdsa::signature::Signer;
+use k256::ecdsa::{Signature, SigningKey};
+use moneymarket::oracle::{
+    AssetResponse, AssetsResponse, ConfigResponse, ExecuteMsg, FeederResponse, FeederStatsResponse,
+    FeederStatsResponseElem, FeedersResponse, FeedersResponseElem, InstantiateMsg, MigrateMsg,
+    PairSourceResponse, PriceResponse, PricesForResponse, PricesForResponseElem, PricesResponse,
+    PricesResponseElem, QueryMsg, SnapshotPriceResponse, StaleFeedResponse, StaleFeedsResponse,
+    StalePricesResponse, StalePricesResponseElem,
+};
+use std::str::FromStr;
+use terraswap::asset::{Asset, AssetInfo};
+use terraswap::pair::PoolResponse;
+
+#[test]
+fn proper_initialization() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        base_asset: "base0000".to_string(),
+        valid_period: 60,
+        fallback_oracle: None,
+        default_quote_denom: None,
+    };
+
+    let info = mock_info("addr0000", &[]);
+
+    // we can just call .unwrap() to assert this was a success
+    let res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert_eq!(0, res.messages.len());
+
+    // it worked, let's query the state
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    let value: ConfigResponse = from_binary(&res).unwrap();
+    assert_eq!("owner0000", value.owner.as_str());
+    assert_eq!("base0000", &value.base_asset);
+    assert_eq!(60, value.valid_period);
+    assert_eq!(None, value.fallback_oracle);
+}
+
+#[test]
+fn update_config() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        base_asset: "base0000".to_string(),
+        valid_period: 60,
+        fallback_oracle: None,
+        default_quote_denom: None,
+    };
+
+    let info = mock_info("addr0000", &[]);
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // update owner
+    let info = mock_info("owner0000", &[]);
+    let msg = ExecuteMsg::UpdateConfig {
+        owner: Some("owner0001".to_string()),
+        valid_period: Some(120),
+        fallback_oracle: Some("oracle0001".to_string()),
+        snapshotter: Some("overseer0000".to_string()),
+    };
+
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert_eq!(0, res.messages.len());
+
+    // it worked, let's query the state
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    let value: ConfigResponse = from_binary(&res).unwrap();
+    assert_eq!("owner0001", value.owner.as_str());
+    assert_eq!("base0000", &value.base_asset);
+    assert_eq!(120, value.valid_period);
+    assert_eq!(Some("oracle0001".to_string()), value.fallback_oracle);
+    assert_eq!(Some("overseer0000".to_string()), value.snapshotter);
+
+    // Unauthorized err
+    let info = mock_info("owner0000", &[]);
+    let msg = ExecuteMsg::UpdateConfig {
+        owner: None,
+        valid_period: None,
+        fallback_oracle: None,
+        snapshotter: None,
+    };
+
+    let res = execute(deps.as_mut(), mock_env(), info, msg);
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("Must return unauthorized error"),
+    }
+}
+
+#[test]
+fn register_feeder() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        base_asset: "base0000".to_string(),
+        valid_period: 60,
+        fallback_oracle: None,
+        default_quote_denom: None,
+    };
+
+    let info = mock_info("addr0000", &[]);
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let info = mock_info("addr0000", &[]);
+    let msg = ExecuteMsg::RegisterFeeder {
+        asset: "mAAPL".to_string(),
+        feeder: "feeder0000".to_string(),
+        quote: None,
+    };
+
+    let res = execute(deps.as_mut(), mock_env(), info, msg.clone());
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let info = mock_info("owner0000", &[]);
+    let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    let feeder_res: FeederResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::Feeder {
+                asset: "mAAPL".to_string(),
+                quote: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        feeder_res,
+        FeederResponse {
+            asset: "mAAPL".to_string(),
+            quote: "base0000".to_string(),
+            feeder: "feeder0000".to_string(),
+        }
+    );
+}
+
+#[test]
+fn feed_price() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        base_asset: "base0000".to_string(),
+        valid_period: 60,
+        fallback_oracle: None,
+        default_quote_denom: None,
+    };
+
+    let info = mock_info("addr0000", &[]);
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // Register mAAPL and mGOGL
+    let info = mock_info("owner0000", &[]);
+    for asset in ["mAAPL", "mGOGL"] {
+        let msg = ExecuteMsg::RegisterAsset {
+            asset: asset.to_string(),
+            decimals: 6,
+            description: asset.to_string(),
+            heartbeat: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+    }
+
+    // Register feeder for mAAPL
+    let msg = ExecuteMsg::RegisterFeeder {
+        asset: "mAAPL".to_string(),
+        feeder: "feeder0000".to_string(),
+        quote: None,
+    };
+    let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    // Register feeder for mGOGL
+    let msg = ExecuteMsg::RegisterFeeder {
+        asset: "mGOGL".to_string(),
+        feeder: "feeder0000".to_string(),
+        quote: None,
+    };
+    let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // Feed prices
+    let info = mock_info("feeder0000", &[]);
+    let env = mock_env();
+    let msg = ExecuteMsg::FeedPrice {
+        prices: vec![
+            ("mAAPL".to_string(), Decimal256::from_str("1.2").unwrap()),
+            ("mGOGL".to_string(), Decimal256::from_str("2.2").unwrap()),
+        ],
+        quote: None,
+    };
+    let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "feed_prices"),
+            attr("quote", "base0000"),
+            attr("asset", "mAAPL"),
+            attr("old_price", "none"),
+            attr("price", "1.2"),
+            attr("change", "none"),
+            attr("feeder", "feeder0000"),
+            attr("asset", "mGOGL"),
+            attr("old_price", "none"),
+            attr("price", "2.2"),
+            attr("change", "none"),
+            attr("feeder", "feeder0000"),
+        ]
+    );
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::Price {
+            base: "mAAPL".to_string(),
+            quote: "base0000".to_string(),
+            block_time: None,
+        },
+    )
+    .unwrap();
+    let value: PriceResponse = from_binary(&res).unwrap();
+
+    assert_eq!(
+        value,
+        PriceResponse {
+            rate: Decimal256::from_str("1.2").unwrap(),
+            last_updated_base: env.block.time.seconds(),
+            last_updated_quote: 9999999999,
+            decimals_base: 6,
+            decimals_quote: 6,
+            age_seconds: None,
+            feeder_count: 1,
+            derived: false,
+            frozen: false,
+            source: "primary".to_string(),
+            inverted: false,
+        }
+    );
+
+    // age is derived from the caller's block time
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::Price {
+            base: "mAAPL".to_string(),
+            quote: "base0000".to_string(),
+            block_time: Some(env.block.time.seconds() + 30),
+        },
+    )
+    .unwrap();
+    let value: PriceResponse = from_binary(&res).unwrap();
+    assert_eq!(value.age_seconds, Some(30));
+    assert_eq!(value.feeder_count, 1);
+    assert!(!value.derived);
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::Price {
+            base: "mGOGL".to_string(),
+            quote: "mAAPL".to_string(),
+            block_time: None,
+        },
+    )
+    .unwrap();
+    let value: PriceResponse = from_binary(&res).unwrap();
+
+    assert_eq!(
+        value,
+        PriceResponse {
+            rate: Decimal256::from_str("1.833333333333333333").unwrap(),
+            last_updated_base: env.block.time.seconds(),
+            last_updated_quote: env.block.time.seconds(),
+            decimals_base: 6,
+            decimals_quote: 6,
+            age_seconds: None,
+            feeder_count: 2,
+            derived: true,
+            frozen: false,
+            source: "primary".to_string(),
+            inverted: false,
+        }
+    );
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::Prices {
+            start_after: None,
+            limit: None,
+            quote: None,
+        },
+    )
+    .unwrap();
+    let value: PricesResponse = from_binary(&res).unwrap();
+
+    assert_eq!(
+        value,
+        PricesResponse {
+            prices: vec![
+                PricesResponseElem {
+                    asset: "mAAPL".to_string(),
+                    price: Decimal256::from_str("1.2").unwrap(),
+                    last_updated_time: env.block.time.seconds(),
+                },
+                PricesResponseElem {
+                    asset: "mGOGL".to_string(),
+                    price: Decimal256::from_str("2.2").unwrap(),
+                    last_updated_time: env.block.time.seconds(),
+                }
+            ],
+        }
+    );
+
+    // Unauthorized try
+    let info = mock_info("addr0001", &[]);
+    let msg = ExecuteMsg::FeedPrice {
+        prices: vec![("mAAPL".to_string(), Decimal256::from_str("1.2").unwrap())],
+        quote: None,
+    };
+
+    let res = execute(deps.as_mut(), mock_env(), info, msg);
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("Must return unauthorized error"),
+    }
+
+    // later feeds log the change from the previous price
+    let info = mock_info("feeder0000", &[]);
+    let msg = ExecuteMsg::FeedPrice {
+        prices: vec![
+            ("mAAPL".to_string(), Decimal256::from_str("1.5").unwrap()),
+            ("mGOGL".to_string(), Decimal256::from_str("1.1").unwrap()),
+        ],
+        quote: None,
+    };
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "feed_prices"),
+            attr("quote", "base0000"),
+            attr("asset", "mAAPL"),
+            attr("old_price", "1.2"),
+            attr("price", "1.5"),
+            attr("change", "0.25"),
+            attr("feeder", "feeder0000"),
+            attr("asset", "mGOGL"),
+            attr("old_price", "2.2"),
+            attr("price", "1.1"),
+            attr("change", "-0.5"),
+            attr("feeder", "feeder0000"),
+        ]
+    );
+}
+
+#[test]
+fn register_asset() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        base_asset: "base0000".to_string(),
+        valid_period: 60,
+        fallback_oracle: None,
+        default_quote_denom: None,
+    };
+
+    let info = mock_info("addr0000", &[]);
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::RegisterFeeder {
+        asset: "mAAPL".to_string(),
+        feeder: "feeder0000".to_string(),
+        quote: None,
+    };
+    let info = mock_info("owner0000", &[]);
+    let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // Feeding an unregistered asset is rejected
+    let feed_msg = ExecuteMsg::FeedPrice {
+        prices: vec![("mAAPL".to_string(), Decimal256::from_str("1.2").unwrap())],
+        quote: None,
+    };
+    let info = mock_info("feeder0000", &[]);
+    let res = execute(deps.as_mut(), mock_env(), info, feed_msg.clone());
+    match res {
+        Err(ContractError::AssetNotRegistered(asset)) => assert_eq!(asset, "mAAPL"),
+        _ => panic!("Must return asset not registered error"),
+    }
+
+    // Only the owner can register an asset
+    let msg = ExecuteMsg::RegisterAsset {
+        asset: "mAAPL".to_string(),
+        decimals: 8,
+        description: "Mirrored Apple".to_string(),
+        heartbeat: None,
+    };
+    let info = mock_info("addr0000", &[]);
+    let res = execute(deps.as_mut(), mock_env(), info, msg.clone());
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("Must return unauthorized error"),
+    }
+
+    let info = mock_info("owner0000", &[]);
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "register_asset"),
+            attr("asset", "mAAPL"),
+            attr("decimals", "8"),
+        ]
+    );
+
+    // Feed again after registration
+    let env = mock_env();
+    let info = mock_info("feeder0000", &[]);
+    let _res = execute(deps.as_mut(), env.clone(), info, feed_msg).unwrap();
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::Price {
+            base: "mAAPL".to_string(),
+            quote: "base0000".to_string(),
+            block_time: None,
+        },
+    )
+    .unwrap();
+    let value: PriceResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        value,
+        PriceResponse {
+            rate: Decimal256::from_str("1.2").unwrap(),
+            last_updated_base: env.block.time.seconds(),
+            last_updated_quote: 9999999999,
+            decimals_base: 8,
+            decimals_quote: 6,
+            age_seconds: None,
+            feeder_count: 1,
+            derived: false,
+            frozen: false,
+            source: "primary".to_string(),
+            inverted: false,
+        }
+    );
+
+    let msg = ExecuteMsg::RegisterAsset {
+        asset: "mGOGL".to_string(),
+        decimals: 6,
+        description: "Mirrored Google".to_string(),
+        heartbeat: None,
+    };
+    let info = mock_info("owner0000", &[]);
+    let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::Assets {
+            start_after: None,
+            limit: None,
+        },
+    )
+    .unwrap();
+    let value: AssetsResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        value,
+        AssetsResponse {
+            assets: vec![
+                AssetResponse {
+                    asset: "mAAPL".to_string(),
+                    decimals: 8,
+                    description: "Mirrored Apple".to_string(),
+                    heartbeat: None,
+                    min_price: None,
+                    max_price: None,
+                    frozen: false,
+                    deprecated: false,
+                },
+                AssetResponse {
+                    asset: "mGOGL".to_string(),
+                    decimals: 6,
+                    description: "Mirrored Google".to_string(),
+                    heartbeat: None,
+                    min_price: None,
+                    max_price: None,
+                    frozen: false,
+                    deprecated: false,
+                }
+            ],
+        }
+    );
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::Assets {
+            start_after: Some("mAAPL".to_string()),
+            limit: Some(1),
+        },
+    )
+    .unwrap();
+    let value: AssetsResponse = from_binary(&res).unwrap();
+    assert_eq!(value.assets.len(), 1);
+    assert_eq!(value.assets[0].asset, "mGOGL");
+}
+
+#[test]
+fn freeze_asset() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        base_asset: "base0000".to_string(),
+        valid_period: 60,
+        fallback_oracle: None,
+        default_quote_denom: None,
+    };
+
+    let info = mock_info("addr0000", &[]);
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let info = mock_info("owner0000", &[]);
+    let msg = ExecuteMsg::RegisterAsset {
+        asset: "mAAPL".to_string(),
+        decimals: 6,
+        description: "Mirrored Apple".to_string(),
+        heartbeat: None,
+    };
+    let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    let msg = ExecuteMsg::RegisterFeeder {
+        asset: "mAAPL".to_string(),
+        feeder: "feeder0000".to_string(),
+        quote: None,
+    };
+    let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let env = mock_env();
+    let info = mock_info("feeder0000", &[]);
+    let msg = ExecuteMsg::FeedPrice {
+        prices: vec![("mAAPL".to_string(), Decimal256::from_str("1.2").unwrap())],
+        quote: None,
+    };
+    let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+    // Only the owner can freeze
+    let msg = ExecuteMsg::FreezeAsset {
+        asset: "mAAPL".to_string(),
+    };
+    let info = mock_info("feeder0000", &[]);
+    let res = execute(deps.as_mut(), mock_env(), info, msg.clone());
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("Must return unauthorized error"),
+    }
+
+    // Unknown assets cannot be frozen
+    let info = mock_info("owner0000", &[]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        ExecuteMsg::FreezeAsset {
+            asset: "mGOGL".to_string(),
+        },
+    );
+    match res {
+        Err(ContractError::AssetNotRegistered(asset)) => assert_eq!(asset, "mGOGL"),
+        _ => panic!("Must return asset not registered error"),
+    }
+
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![attr("action", "freeze_asset"), attr("asset", "mAAPL")]
+    );
+
+    // Feeding a frozen asset is rejected
+    let mut new_env = mock_env();
+    new_env.block.time = new_env.block.time.plus_seconds(100);
+    let info = mock_info("feeder0000", &[]);
+    let msg = ExecuteMsg::FeedPrice {
+        prices: vec![("mAAPL".to_string(), Decimal256::from_str("0.01").unwrap())],
+        quote: None,
+    };
+    let res = execute(deps.as_mut(), new_env.clone(), info.clone(), msg.clone());
+    match res {
+        Err(ContractError::AssetFrozen(asset)) => assert_eq!(asset, "mAAPL"),
+        _ => panic!("Must return asset frozen error"),
+    }
+
+    // The last known price is served with the frozen flag
+    let price_query = QueryMsg::Price {
+        base: "mAAPL".to_string(),
+        quote: "base0000".to_string(),
+        block_time: None,
+    };
+    let value: PriceResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), price_query.clone()).unwrap()).unwrap();
+    assert_eq!(
+        value,
+        PriceResponse {
+            rate: Decimal256::from_str("1.2").unwrap(),
+            last_updated_base: env.block.time.seconds(),
+            last_updated_quote: 9999999999,
+            decimals_base: 6,
+            decimals_quote: 6,
+            age_seconds: None,
+            feeder_count: 1,
+            derived: false,
+            frozen: true,
+            source: "primary".to_string(),
+            inverted: false,
+        }
+    );
+
+    // Unfreeze and feed again
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner0000", &[]),
+        ExecuteMsg::UnfreezeAsset {
+            asset: "mAAPL".to_string(),
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![attr("action", "unfreeze_asset"), attr("asset", "mAAPL")]
+    );
+
+    let _res = execute(deps.as_mut(), new_env.clone(), info, msg).unwrap();
+    let value: PriceResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), price_query).unwrap()).unwrap();
+    assert_eq!(
+        value,
+        PriceResponse {
+            rate: Decimal256::from_str("0.01").unwrap(),
+            last_updated_base: new_env.block.time.seconds(),
+            last_updated_quote: 9999999999,
+            decimals_base: 6,
+            decimals_quote: 6,
+            age_seconds: None,
+            feeder_count: 1,
+            derived: false,
+            frozen: false,
+            source: "primary".to_string(),
+            inverted: false,
+        }
+    );
+}
+
+#[test]
+fn deprecate_asset() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        base_asset: "base0000".to_string(),
+        valid_period: 60,
+        fallback_oracle: None,
+        default_quote_denom: None,
+    };
+
+    let info = mock_info("addr0000", &[]);
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let info = mock_info("owner0000", &[]);
+    let msg = ExecuteMsg::RegisterAsset {
+        asset: "mAAPL".to_string(),
+        decimals: 6,
+        description: "Mirrored Apple".to_string(),
+        heartbeat: None,
+    };
+    let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    let msg = ExecuteMsg::RegisterFeeder {
+        asset: "mAAPL".to_string(),
+        feeder: "feeder0000".to_string(),
+        quote: None,
+    };
+    let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    let env = mock_env();
+    let feed_msg = ExecuteMsg::FeedPrice {
+        prices: vec![("mAAPL".to_string(), Decimal256::from_str("1.2").unwrap())],
+        quote: None,
+    };
+    let feeder = mock_info("feeder0000", &[]);
+    let _res = execute(deps.as_mut(), env.clone(), feeder.clone(), feed_msg.clone()).unwrap();
+
+    // Only the owner can deprecate
+    let msg = ExecuteMsg::DeprecateAsset {
+        asset: "mAAPL".to_string(),
+    };
+    let res = execute(deps.as_mut(), mock_env(), feeder.clone(), msg.clone());
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("Must return unauthorized error"),
+    }
+
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![attr("action", "deprecate_asset"), attr("asset", "mAAPL")]
+    );
+
+    // the price can no longer be consumed, in either direction
+    for (base, quote) in [("mAAPL", "base0000"), ("base0000", "mAAPL")] {
+        let res = query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Price {
+                base: base.to_string(),
+                quote: quote.to_string(),
+                block_time: None,
+            },
+        );
+        match res {
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "asset deprecated"),
+            _ => panic!("DO NOT ENTER HERE"),
+        }
+    }
+
+    let res: PricesForResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::PricesFor {
+                assets: vec!["mAAPL".to_string()],
+                quote: "base0000".to_string(),
+                block_time: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        res.prices[0].error,
+        Some("Generic error: asset deprecated".to_string())
+    );
+
+    // nor fed
+    let res = execute(deps.as_mut(), env.clone(), feeder, feed_msg);
+    match res {
+        Err(ContractError::AssetDeprecated(asset)) => assert_eq!(asset, "mAAPL"),
+        _ => panic!("Must return asset deprecated error"),
+    }
+
+    // while the stored price and asset stay readable
+    let res: PricesResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Prices {
+                quote: None,
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        res.prices,
+        vec![PricesResponseElem {
+            asset: "mAAPL".to_string(),
+            price: Decimal256::from_str("1.2").unwrap(),
+            last_updated_time: env.block.time.seconds(),
+        }]
+    );
+
+    let res: AssetsResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Assets {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert!(res.assets[0].deprecated);
+
+    // and it is not reported as a missing feed
+    let res: StaleFeedsResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::StaleFeeds {
+                block_time: env.block.time.seconds() + 1000,
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(res.feeds, vec![]);
+}
+
+#[test]
+fn fallback_oracle() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        base_asset: "base0000".to_string(),
+        valid_period: 60,
+        fallback_oracle: Some("oracle0001".to_string()),
+        default_quote_denom: None,
+    };
+
+    let info = mock_info("addr0000", &[]);
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let info = mock_info("owner0000", &[]);
+    let msg = ExecuteMsg::RegisterAsset {
+        asset: "mAAPL".to_string(),
+        decimals: 6,
+        description: "Mirrored Apple".to_string(),
+        heartbeat: None,
+    };
+    let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    let msg = ExecuteMsg::RegisterFeeder {
+        asset: "mAAPL".to_string(),
+        feeder: "feeder0000".to_string(),
+        quote: None,
+    };
+    let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let env = mock_env();
+    let info = mock_info("feeder0000", &[]);
+    let msg = ExecuteMsg::FeedPrice {
+        prices: vec![("mAAPL".to_string(), Decimal256::from_str("1.2").unwrap())],
+        quote: None,
+    };
+    let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+    let fallback_time = env.block.time.seconds() + 100;
+    let fallback_price = PriceResponse {
+        rate: Decimal256::from_str("1.3").unwrap(),
+        last_updated_base: fallback_time,
+        last_updated_quote: 9999999999,
+        decimals_base: 6,
+        decimals_quote: 6,
+        age_seconds: None,
+        feeder_count: 1,
+        derived: false,
+        frozen: false,
+        source: "primary".to_string(),
+        inverted: false,
+    };
+    deps.querier.with_fallback_price(&[(
+        &(
+            "oracle0001".to_string(),
+            "mAAPL".to_string(),
+            "base0000".to_string(),
+        ),
+        &fallback_price,
+    )]);
+
+    let price_query = QueryMsg::Price {
+        base: "mAAPL".to_string(),
+        quote: "base0000".to_string(),
+        block_time: None,
+    };
+
+    // fresh primary price
+    let value: PriceResponse =
+        from_binary(&query(deps.as_ref(), env.clone(), price_query.clone()).unwrap()).unwrap();
+    assert_eq!(
+        value,
+        PriceResponse {
+            rate: Decimal256::from_str("1.2").unwrap(),
+            last_updated_base: env.block.time.seconds(),
+            last_updated_quote: 9999999999,
+            decimals_base: 6,
+            decimals_quote: 6,
+            age_seconds: None,
+            feeder_count: 1,
+            derived: false,
+            frozen: false,
+            source: "primary".to_string(),
+            inverted: false,
+        }
+    );
+
+    // stale primary price, fresh fallback price
+    let mut stale_env = mock_env();
+    stale_env.block.time = stale_env.block.time.plus_seconds(120);
+    let value: PriceResponse =
+        from_binary(&query(deps.as_ref(), stale_env.clone(), price_query.clone()).unwrap())
+            .unwrap();
+    assert_eq!(
+        value,
+        PriceResponse {
+            source: "fallback".to_string(),
+            inverted: false,
+            ..fallback_price
+        }
+    );
+
+    // a frozen price is served as is, even stale with a fallback available
+    let owner_info = mock_info("owner0000", &[]);
+    let msg = ExecuteMsg::FreezeAsset {
+        asset: "mAAPL".to_string(),
+    };
+    let _res = execute(deps.as_mut(), mock_env(), owner_info.clone(), msg).unwrap();
+    let value: PriceResponse =
+        from_binary(&query(deps.as_ref(), stale_env.clone(), price_query.clone()).unwrap())
+            .unwrap();
+    assert_eq!(
+        value,
+        PriceResponse {
+            rate: Decimal256::from_str("1.2").unwrap(),
+            last_updated_base: env.block.time.seconds(),
+            last_updated_quote: 9999999999,
+            decimals_base: 6,
+            decimals_quote: 6,
+            age_seconds: None,
+            feeder_count: 1,
+            derived: false,
+            frozen: true,
+            source: "primary".to_string(),
+            inverted: false,
+        }
+    );
+    let msg = ExecuteMsg::UnfreezeAsset {
+        asset: "mAAPL".to_string(),
+    };
+    let _res = execute(deps.as_mut(), mock_env(), owner_info, msg).unwrap();
+
+    // both prices are stale
+    let mut stale_env = mock_env();
+    stale_env.block.time = stale_env.block.time.plus_seconds(200);
+    let res = query(deps.as_ref(), stale_env.clone(), price_query.clone());
+    match res {
+        Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Price is too old"),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // fallback oracle has no price for the pair
+    deps.querier.with_fallback_price(&[]);
+    let res = query(deps.as_ref(), stale_env, price_query);
+    match res {
+        Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Price is too old"),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // no local price at all, only the fallback oracle knows the asset
+    let fallback_price = PriceResponse {
+        rate: Decimal256::from_str("2.5").unwrap(),
+        last_updated_base: env.block.time.seconds(),
+        last_updated_quote: 9999999999,
+        decimals_base: 6,
+        decimals_quote: 6,
+        age_seconds: None,
+        feeder_count: 1,
+        derived: false,
+        frozen: false,
+        source: "primary".to_string(),
+        inverted: false,
+    };
+    deps.querier.with_fallback_price(&[(
+        &(
+            "oracle0001".to_string(),
+            "mGOGL".to_string(),
+            "base0000".to_string(),
+        ),
+        &fallback_price,
+    )]);
+    let price_query = QueryMsg::Price {
+        base: "mGOGL".to_string(),
+        quote: "base0000".to_string(),
+        block_time: None,
+    };
+    let value: PriceResponse =
+        from_binary(&query(deps.as_ref(), env.clone(), price_query.clone()).unwrap()).unwrap();
+    assert_eq!(
+        value,
+        PriceResponse {
+            source: "fallback".to_string(),
+            ..fallback_price
+        }
+    );
+
+    // when the fallback fails too, the local error is returned
+    deps.querier.with_fallback_price(&[]);
+    let res = query(deps.as_ref(), env, price_query);
+    match res {
+        Err(StdError::GenericErr { msg, .. }) => {
+            assert_eq!(msg, "No price data for the specified asset exist")
+        }
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+}
+
+#[test]
+fn stale_feeds() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        base_asset: "base0000".to_string(),
+        valid_period: 60,
+        fallback_oracle: None,
+        default_quote_denom: Some("uusd".to_string()),
+    };
+
+    let info = mock_info("addr0000", &[]);
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // mAAPL uses a custom heartbeat, mGOGL the default valid_period
+    let info = mock_info("owner0000", &[]);
+    for (asset, heartbeat) in [("mAAPL", Some(300u64)), ("mGOGL", None)] {
+        let msg = ExecuteMsg::RegisterAsset {
+            asset: asset.to_string(),
+            decimals: 6,
+            description: asset.to_string(),
+            heartbeat,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::RegisterFeeder {
+            asset: asset.to_string(),
+            feeder: "feeder0000".to_string(),
+            quote: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+    }
+
+    // feeds default to the uusd quote, which differs from the base asset
+    let env = mock_env();
+    let msg = ExecuteMsg::FeedPrice {
+        prices: vec![
+            ("mAAPL".to_string(), Decimal256::from_str("1.2").unwrap()),
+            ("mGOGL".to_string(), Decimal256::from_str("2.2").unwrap()),
+        ],
+        quote: None,
+    };
+    let _res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("feeder0000", &[]),
+        msg,
+    )
+    .unwrap();
+
+    // nothing is overdue right after the feed
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::StaleFeeds {
+            block_time: env.block.time.seconds() + 60,
+            start_after: None,
+            limit: None,
+        },
+    )
+    .unwrap();
+    let value: StaleFeedsResponse = from_binary(&res).unwrap();
+    assert_eq!(value.feeds, vec![]);
+
+    // only mGOGL has missed its heartbeat
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::StaleFeeds {
+            block_time: env.block.time.seconds() + 100,
+            start_after: None,
+            limit: None,
+        },
+    )
+    .unwrap();
+    let value: StaleFeedsResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        value,
+        StaleFeedsResponse {
+            feeds: vec![StaleFeedResponse {
+                asset: "mGOGL".to_string(),
+                heartbeat: 60,
+                last_updated_time: Some(env.block.time.seconds()),
+                age: Some(100),
+            }],
+        }
+    );
+
+    // both are overdue, paginated
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::StaleFeeds {
+            block_time: env.block.time.seconds() + 400,
+            start_after: None,
+            limit: Some(1),
+        },
+    )
+    .unwrap();
+    let value: StaleFeedsResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        value,
+        StaleFeedsResponse {
+            feeds: vec![StaleFeedResponse {
+                asset: "mAAPL".to_string(),
+                heartbeat: 300,
+                last_updated_time: Some(env.block.time.seconds()),
+                age: Some(400),
+            }],
+        }
+    );
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::StaleFeeds {
+            block_time: env.block.time.seconds() + 400,
+            start_after: Some("mAAPL".to_string()),
+            limit: Some(1),
+        },
+    )
+    .unwrap();
+    let value: StaleFeedsResponse = from_binary(&res).unwrap();
+    assert_eq!(value.feeds.len(), 1);
+    assert_eq!(value.feeds[0].asset, "mGOGL");
+}
+
+#[test]
+fn stale_prices() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        base_asset: "base0000".to_string(),
+        valid_period: 60,
+        fallback_oracle: None,
+        default_quote_denom: Some("uusd".to_string()),
+    };
+
+    let info = mock_info("addr0000", &[]);
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // each asset is fed 100 seconds after the previous one, in the uusd
+    // default quote which differs from the base asset
+    let env = mock_env();
+    let assets = ["mAAPL", "mGOGL", "mMSFT", "mTSLA"];
+    for (i, asset) in assets.iter().enumerate() {
+        let msg = ExecuteMsg::RegisterAsset {
+            asset: asset.to_string(),
+            decimals: 6,
+            description: asset.to_string(),
+            heartbeat: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg).unwrap();
+
+        let msg = ExecuteMsg::RegisterFeeder {
+            asset: asset.to_string(),
+            feeder: "feeder0000".to_string(),
+            quote: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg).unwrap();
+
+        let mut feed_env = env.clone();
+        feed_env.block.time = feed_env.block.time.plus_seconds(100 * i as u64);
+        let msg = ExecuteMsg::FeedPrice {
+            prices: vec![(asset.to_string(), Decimal256::from_str("1.2").unwrap())],
+            quote: None,
+        };
+        let _res = execute(deps.as_mut(), feed_env, mock_info("feeder0000", &[]), msg).unwrap();
+    }
+
+    let t0 = env.block.time.seconds();
+    let stale_prices = |start_after: Option<String>, limit: Option<u32>| {
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::StalePrices {
+                older_than: 100,
+                block_time: t0 + 300,
+                start_after,
+                limit,
+            },
+        )
+        .unwrap();
+        from_binary::<StalePricesResponse>(&res).unwrap()
+    };
+
+    // mMSFT is exactly 100 seconds old, which is not past the threshold
+    assert_eq!(
+        stale_prices(None, None),
+        StalePricesResponse {
+            prices: vec![
+                StalePricesResponseElem {
+                    asset: "mAAPL".to_string(),
+                    last_updated_time: t0,
+                    age: 300,
+                    feeder: Some("feeder0000".to_string()),
+                },
+                StalePricesResponseElem {
+                    asset: "mGOGL".to_string(),
+                    last_updated_time: t0 + 100,
+                    age: 200,
+                    feeder: Some("feeder0000".to_string()),
+                },
+            ],
+            next_start_after: None,
+        }
+    );
+
+    // the scan stops after `limit` prices, stale or not
+    let value = stale_prices(None, Some(1));
+    assert_eq!(value.prices.len(), 1);
+    assert_eq!(value.prices[0].asset, "mAAPL");
+    assert_eq!(value.next_start_after, Some("mAAPL".to_string()));
+
+    let value = stale_prices(Some("mGOGL".to_string()), Some(1));
+    assert_eq!(value.prices, vec![]);
+    assert_eq!(value.next_start_after, Some("mMSFT".to_string()));
+
+    // the last page reaches the end of the prices
+    let value = stale_prices(Some("mMSFT".to_string()), Some(1));
+    assert_eq!(value.prices, vec![]);
+    assert_eq!(value.next_start_after, None);
+}
+
+fn sign_price(signing_key: &SigningKey, asset: &str, price: Decimal256, timestamp: u64) -> Binary {
+    let message = format!("{}|{}|{}", asset, price, timestamp);
+    let signature: Signature = signing_key.sign(message.as_bytes());
+    Binary::from(signature.as_ref())
+}
+
+#[test]
+fn feed_price_signed() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        base_asset: "base0000".to_string(),
+        valid_period: 60,
+        fallback_oracle: None,
+        default_quote_denom: None,
+    };
+
+    let info = mock_info("addr0000", &[]);
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let info = mock_info("owner0000", &[]);
+    let msg = ExecuteMsg::RegisterAsset {
+        asset: "mAAPL".to_string(),
+        decimals: 6,
+        description: "Mirrored Apple".to_string(),
+        heartbeat: None,
+    };
+    let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let signing_key = SigningKey::from_bytes(&[7u8; 32]).unwrap();
+    let pubkey = Binary::from(signing_key.verifying_key().to_bytes().to_vec());
+
+    // Only the owner can register a feeder key
+    let msg = ExecuteMsg::RegisterFeederKey {
+        asset: "mAAPL".to_string(),
+        pubkey: pubkey.clone(),
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        msg.clone(),
+    );
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("Must return unauthorized error"),
+    }
+    let _res = execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg).unwrap();
+
+    let env = mock_env();
+    let timestamp = env.block.time.seconds();
+    let price = Decimal256::from_str("1.2").unwrap();
+
+    // signature over a different price
+    let msg = ExecuteMsg::FeedPriceSigned {
+        asset: "mAAPL".to_string(),
+        price,
+        timestamp,
+        signature: sign_price(
+            &signing_key,
+            "mAAPL",
+            Decimal256::from_str("1.3").unwrap(),
+            timestamp,
+        ),
+        pubkey: pubkey.clone(),
+    };
+    let res = execute(deps.as_mut(), env.clone(), mock_info("relayer", &[]), msg);
+    match res {
+        Err(ContractError::InvalidSignature {}) => (),
+        _ => panic!("Must return invalid signature error"),
+    }
+
+    // unregistered key
+    let other_key = SigningKey::from_bytes(&[8u8; 32]).unwrap();
+    let msg = ExecuteMsg::FeedPriceSigned {
+        asset: "mAAPL".to_string(),
+        price,
+        timestamp,
+        signature: sign_price(&other_key, "mAAPL", price, timestamp),
+        pubkey: Binary::from(other_key.verifying_key().to_bytes().to_vec()),
+    };
+    let res = execute(deps.as_mut(), env.clone(), mock_info("relayer", &[]), msg);
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("Must return unauthorized error"),
+    }
+
+    // future timestamp
+    let msg = ExecuteMsg::FeedPriceSigned {
+        asset: "mAAPL".to_string(),
+        price,
+        timestamp: timestamp + 61,
+        signature: sign_price(&signing_key, "mAAPL", price, timestamp + 61),
+        pubkey: pubkey.clone(),
+    };
+    let res = execute(deps.as_mut(), env.clone(), mock_info("relayer", &[]), msg);
+    match res {
+        Err(ContractError::PriceTimestampInFuture {}) => (),
+        _ => panic!("Must return timestamp in future error"),
+    }
+
+    // success, relayed by anyone
+    let msg = ExecuteMsg::FeedPriceSigned {
+        asset: "mAAPL".to_string(),
+        price,
+        timestamp,
+        signature: sign_price(&signing_key, "mAAPL", price, timestamp),
+        pubkey,
+    };
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("relayer", &[]),
+        msg.clone(),
+    )
+    .unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "feed_price_signed"),
+            attr("asset", "mAAPL"),
+            attr("price", "1.2"),
+            attr("timestamp", timestamp.to_string()),
+        ]
+    );
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::Price {
+            base: "mAAPL".to_string(),
+            quote: "base0000".to_string(),
+            block_time: None,
+        },
+    )
+    .unwrap();
+    let value: PriceResponse = from_binary(&res).unwrap();
+    assert_eq!(value.rate, price);
+    assert_eq!(value.last_updated_base, timestamp);
+
+    // replayed submission
+    let res = execute(deps.as_mut(), env, mock_info("relayer", &[]), msg);
+    match res {
+        Err(ContractError::PriceTimestampTooOld {}) => (),
+        _ => panic!("Must return timestamp too old error"),
+    }
+}
+
+#[test]
+fn prices_for() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        base_asset: "base0000".to_string(),
+        valid_period: 60,
+        fallback_oracle: None,
+        default_quote_denom: None,
+    };
+
+    let info = mock_info("addr0000", &[]);
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let info = mock_info("owner0000", &[]);
+    for asset in ["mAAPL", "mGOGL"] {
+        let msg = ExecuteMsg::RegisterAsset {
+            asset: asset.to_string(),
+            decimals: 6,
+            description: asset.to_string(),
+            heartbeat: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::RegisterFeeder {
+            asset: asset.to_string(),
+            feeder: "feeder0000".to_string(),
+            quote: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+    }
+
+    // only mAAPL and mGOGL are fed
+    let env = mock_env();
+    let msg = ExecuteMsg::FeedPrice {
+        prices: vec![
+            ("mAAPL".to_string(), Decimal256::from_str("1.2").unwrap()),
+            ("mGOGL".to_string(), Decimal256::from_str("2.2").unwrap()),
+        ],
+        quote: None,
+    };
+    let _res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("feeder0000", &[]),
+        msg,
+    )
+    .unwrap();
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::PricesFor {
+            assets: vec![
+                "mGOGL".to_string(),
+                "mMSFT".to_string(),
+                "mAAPL".to_string(),
+            ],
+            quote: "base0000".to_string(),
+            block_time: None,
+        },
+    )
+    .unwrap();
+    let value: PricesForResponse = from_binary(&res).unwrap();
+
+    // results keep the input order
+    assert_eq!(value.prices.len(), 3);
+    assert_eq!(value.prices[0].asset, "mGOGL");
+    assert_eq!(
+        value.prices[0].price.as_ref().unwrap().rate,
+        Decimal256::from_str("2.2").unwrap()
+    );
+    assert_eq!(value.prices[0].error, None);
+    assert_eq!(
+        value.prices[1],
+        PricesForResponseElem {
+            asset: "mMSFT".to_string(),
+            price: None,
+            error: Some("Generic error: No price data for the specified asset exist".to_string()),
+        }
+    );
+    assert_eq!(value.prices[2].asset, "mAAPL");
+    assert_eq!(
+        value.prices[2].price.as_ref().unwrap().rate,
+        Decimal256::from_str("1.2").unwrap()
+    );
+}
+
+#[test]
+fn feed_price_full_precision() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        base_asset: "base0000".to_string(),
+        valid_period: 60,
+        fallback_oracle: None,
+        default_quote_denom: None,
+    };
+
+    let info = mock_info("addr0000", &[]);
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let info = mock_info("owner0000", &[]);
+    let msg = ExecuteMsg::RegisterAsset {
+        asset: "mMICRO".to_string(),
+        decimals: 6,
+        description: "Micro priced asset".to_string(),
+        heartbeat: None,
+    };
+    let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    let msg = ExecuteMsg::RegisterFeeder {
+        asset: "mMICRO".to_string(),
+        feeder: "feeder0000".to_string(),
+        quote: None,
+    };
+    let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // 18 fractional digits survive storage and the query round trip
+    let price = Decimal256::from_str("0.000000001234567891").unwrap();
+    let msg = ExecuteMsg::FeedPrice {
+        prices: vec![("mMICRO".to_string(), price)],
+        quote: None,
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("feeder0000", &[]), msg).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "feed_prices"),
+            attr("quote", "base0000"),
+            attr("asset", "mMICRO"),
+            attr("old_price", "none"),
+            attr("price", "0.000000001234567891"),
+            attr("change", "none"),
+            attr("feeder", "feeder0000"),
+        ]
+    );
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::Price {
+            base: "mMICRO".to_string(),
+            quote: "base0000".to_string(),
+            block_time: None,
+        },
+    )
+    .unwrap();
+    let value: PriceResponse = from_binary(&res).unwrap();
+    assert_eq!(value.rate, price);
+    assert_eq!(value.rate.to_string(), "0.000000001234567891");
+}
+
+#[test]
+fn price_bounds() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        base_asset: "base0000".to_string(),
+        valid_period: 60,
+        fallback_oracle: None,
+        default_quote_denom: None,
+    };
+
+    let info = mock_info("addr0000", &[]);
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let info = mock_info("owner0000", &[]);
+    for asset in ["mAAPL", "mUST"] {
+        let msg = ExecuteMsg::RegisterAsset {
+            asset: asset.to_string(),
+            decimals: 6,
+            description: asset.to_string(),
+            heartbeat: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::RegisterFeeder {
+            asset: asset.to_string(),
+            feeder: "feeder0000".to_string(),
+            quote: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+    }
+
+    // min_price must not exceed max_price
+    let msg = ExecuteMsg::UpdateAssetBounds {
+        asset: "mUST".to_string(),
+        min_price: Some(Decimal256::from_str("1.1").unwrap()),
+        max_price: Some(Decimal256::from_str("0.9").unwrap()),
+    };
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), msg);
+    match res {
+        Err(ContractError::InvalidPriceBounds {}) => (),
+        _ => panic!("Must return invalid price bounds error"),
+    }
+
+    let msg = ExecuteMsg::UpdateAssetBounds {
+        asset: "mUST".to_string(),
+        min_price: Some(Decimal256::from_str("0.9").unwrap()),
+        max_price: Some(Decimal256::from_str("1.1").unwrap()),
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        msg.clone(),
+    );
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("Must return unauthorized error"),
+    }
+
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "update_asset_bounds"),
+            attr("asset", "mUST"),
+            attr("min_price", "0.9"),
+            attr("max_price", "1.1"),
+        ]
+    );
+
+    // out of bounds prices are skipped, the rest of the batch goes through
+    let feeder = mock_info("feeder0000", &[]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        feeder.clone(),
+        ExecuteMsg::FeedPrice {
+            prices: vec![
+                ("mAAPL".to_string(), Decimal256::one()),
+                ("mUST".to_string(), Decimal256::from_uint256(50u64)),
+            ],
+            quote: None,
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        res.attributes[res.attributes.len() - 2..],
+        [
+            attr("asset", "mUST"),
+            attr("rejected", "Price of mUST is above max_price: 50 > 1.1"),
+        ]
+    );
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        feeder.clone(),
+        ExecuteMsg::FeedPrice {
+            prices: vec![("mUST".to_string(), Decimal256::from_str("0.5").unwrap())],
+            quote: None,
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "feed_prices"),
+            attr("quote", "base0000"),
+            attr("asset", "mUST"),
+            attr("rejected", "Price of mUST is below min_price: 0.5 < 0.9"),
+        ]
+    );
+
+    // the bounds are in the base asset, other quotes cannot be checked against them
+    let msg = ExecuteMsg::RegisterFeeder {
+        asset: "mUST".to_string(),
+        feeder: "feeder0000".to_string(),
+        quote: Some("ukrw".to_string()),
+    };
+    let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        feeder.clone(),
+        ExecuteMsg::FeedPrice {
+            prices: vec![("mUST".to_string(), Decimal256::from_uint256(1200u64))],
+            quote: Some("ukrw".to_string()),
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        res.attributes[2..],
+        [
+            attr("asset", "mUST"),
+            attr(
+                "rejected",
+                "Price bounds of mUST are in the base asset, it cannot be fed in ukrw"
+            ),
+        ]
+    );
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::Price {
+            base: "mUST".to_string(),
+            quote: "base0000".to_string(),
+            block_time: None,
+        },
+    );
+    match res {
+        Err(StdError::GenericErr { msg, .. }) => {
+            assert_eq!(msg, "No price data for the specified asset exist")
+        }
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // in range, and unlimited for mAAPL
+    let _res = execute(
+        deps.as_mut(),
+        mock_env(),
+        feeder,
+        ExecuteMsg::FeedPrice {
+            prices: vec![
+                ("mUST".to_string(), Decimal256::one()),
+                ("mAAPL".to_string(), Decimal256::from_uint256(1000000u64)),
+            ],
+            quote: None,
+        },
+    )
+    .unwrap();
+
+    // narrowing the bounds below the stored price is allowed but logged
+    let msg = ExecuteMsg::UpdateAssetBounds {
+        asset: "mUST".to_string(),
+        min_price: None,
+        max_price: Some(Decimal256::from_str("0.99").unwrap()),
+    };
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "update_asset_bounds"),
+            attr("asset", "mUST"),
+            attr("min_price", "unlimited"),
+            attr("max_price", "0.99"),
+            attr("current_price_out_of_bounds", "1"),
+        ]
+    );
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::Assets {
+            start_after: None,
+            limit: None,
+        },
+    )
+    .unwrap();
+    let value: AssetsResponse = from_binary(&res).unwrap();
+    assert_eq!(value.assets[0].asset, "mAAPL");
+    assert_eq!(value.assets[0].min_price, None);
+    assert_eq!(value.assets[0].max_price, None);
+    assert_eq!(value.assets[1].asset, "mUST");
+    assert_eq!(value.assets[1].min_price, None);
+    assert_eq!(
+        value.assets[1].max_price,
+        Some(Decimal256::from_str("0.99").unwrap())
+    );
+}
+
+#[test]
+fn multi_quote() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        base_asset: "uusd".to_string(),
+        valid_period: 60,
+        fallback_oracle: Some("oracle0001".to_string()),
+        default_quote_denom: None,
+    };
+
+    let info = mock_info("addr0000", &[]);
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let info = mock_info("owner0000", &[]);
+    for (asset, description) in [("bluna", "Bonded Luna"), ("ukrw", "Korean won")] {
+        let msg = ExecuteMsg::RegisterAsset {
+            asset: asset.to_string(),
+            decimals: 6,
+            description: description.to_string(),
+            heartbeat: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+    }
+
+    let msg = ExecuteMsg::RegisterFeeder {
+        asset: "bluna".to_string(),
+        feeder: "feeder0000".to_string(),
+        quote: None,
+    };
+    let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    let msg = ExecuteMsg::RegisterFeeder {
+        asset: "bluna".to_string(),
+        feeder: "feeder0001".to_string(),
+        quote: Some("ukrw".to_string()),
+    };
+    let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let feeder_res: FeederResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::Feeder {
+                asset: "bluna".to_string(),
+                quote: Some("ukrw".to_string()),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        feeder_res,
+        FeederResponse {
+            asset: "bluna".to_string(),
+            quote: "ukrw".to_string(),
+            feeder: "feeder0001".to_string(),
+        }
+    );
+
+    let env = mock_env();
+    let msg = ExecuteMsg::FeedPrice {
+        prices: vec![("bluna".to_string(), Decimal256::from_str("5").unwrap())],
+        quote: None,
+    };
+    let _res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("feeder0000", &[]),
+        msg,
+    )
+    .unwrap();
+
+    // feeder authorization is per pair
+    let ukrw_msg = ExecuteMsg::FeedPrice {
+        prices: vec![("bluna".to_string(), Decimal256::from_str("6000").unwrap())],
+        quote: Some("ukrw".to_string()),
+    };
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("feeder0000", &[]),
+        ukrw_msg.clone(),
+    );
+    match res {
+        Err(ContractError::Unauthorized {}) => {}
+        _ => panic!("Must return unauthorized error"),
+    }
+
+    let mut later_env = env.clone();
+    later_env.block.time = later_env.block.time.plus_seconds(100);
+    let _res = execute(
+        deps.as_mut(),
+        later_env.clone(),
+        mock_info("feeder0001", &[]),
+        ukrw_msg,
+    )
+    .unwrap();
+
+    let uusd_query = QueryMsg::Price {
+        base: "bluna".to_string(),
+        quote: "uusd".to_string(),
+        block_time: None,
+    };
+    let ukrw_query = QueryMsg::Price {
+        base: "bluna".to_string(),
+        quote: "ukrw".to_string(),
+        block_time: None,
+    };
+
+    let value: PriceResponse =
+        from_binary(&query(deps.as_ref(), env.clone(), uusd_query.clone()).unwrap()).unwrap();
+    assert_eq!(value.rate, Decimal256::from_str("5").unwrap());
+    assert_eq!(value.last_updated_base, env.block.time.seconds());
+
+    // the pair price is used as is, not derived through uusd
+    let value: PriceResponse =
+        from_binary(&query(deps.as_ref(), later_env.clone(), ukrw_query.clone()).unwrap()).unwrap();
+    assert_eq!(
+        value,
+        PriceResponse {
+            rate: Decimal256::from_str("6000").unwrap(),
+            last_updated_base: later_env.block.time.seconds(),
+            last_updated_quote: 9999999999,
+            decimals_base: 6,
+            decimals_quote: 6,
+            age_seconds: None,
+            feeder_count: 1,
+            derived: false,
+            frozen: false,
+            source: "primary".to_string(),
+            inverted: false,
+        }
+    );
+
+    let value: PricesResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            later_env.clone(),
+            QueryMsg::Prices {
+                quote: Some("ukrw".to_string()),
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        value,
+        PricesResponse {
+            prices: vec![PricesResponseElem {
+                asset: "bluna".to_string(),
+                price: Decimal256::from_str("6000").unwrap(),
+                last_updated_time: later_env.block.time.seconds(),
+            }],
+        }
+    );
+
+    // each pair is stale-checked on its own update time
+    match query(deps.as_ref(), later_env.clone(), uusd_query) {
+        Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Price is too old"),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+    let _value: PriceResponse =
+        from_binary(&query(deps.as_ref(), later_env, ukrw_query).unwrap()).unwrap();
+}
+
+fn pool(bluna_reserve: u128, uusd_reserve: u128) -> PoolResponse {
+    PoolResponse {
+        assets: [
+            Asset {
+                info: AssetInfo::Token {
+                    contract_addr: "bluna".to_string(),
+                },
+                amount: Uint128::from(bluna_reserve),
+            },
+            Asset {
+                info: AssetInfo::NativeToken {
+                    denom: "uusd".to_string(),
+                },
+                amount: Uint128::from(uusd_reserve),
+            },
+        ],
+        total_share: Uint128::from(1000000u128),
+    }
+}
+
+#[test]
+fn inverted_price() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        base_asset: "base0000".to_string(),
+        valid_period: 60,
+        fallback_oracle: None,
+        default_quote_denom: Some("uusd".to_string()),
+    };
+
+    let info = mock_info("addr0000", &[]);
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let config: ConfigResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap()).unwrap();
+    assert_eq!("uusd", config.default_quote_denom);
+
+    let info = mock_info("owner0000", &[]);
+    for asset in ["bluna", "uusd"] {
+        let msg = ExecuteMsg::RegisterAsset {
+            asset: asset.to_string(),
+            decimals: 6,
+            description: asset.to_string(),
+            heartbeat: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+    }
+
+    // the pair is registered and fed against the default quote
+    let msg = ExecuteMsg::RegisterFeeder {
+        asset: "bluna".to_string(),
+        feeder: "feeder0000".to_string(),
+        quote: None,
+    };
+    let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let env = mock_env();
+    let msg = ExecuteMsg::FeedPrice {
+        prices: vec![("bluna".to_string(), Decimal256::from_str("3").unwrap())],
+        quote: None,
+    };
+    let _res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("feeder0000", &[]),
+        msg,
+    )
+    .unwrap();
+
+    let price: PriceResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Price {
+                base: "bluna".to_string(),
+                quote: "uusd".to_string(),
+                block_time: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(price.rate, Decimal256::from_str("3").unwrap());
+    assert!(!price.inverted);
+
+    let inverted_price: PriceResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Price {
+                base: "uusd".to_string(),
+                quote: "bluna".to_string(),
+                block_time: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        inverted_price,
+        PriceResponse {
+            rate: Decimal256::from_str("0.333333333333333333").unwrap(),
+            last_updated_base: 9999999999,
+            last_updated_quote: env.block.time.seconds(),
+            decimals_base: 6,
+            decimals_quote: 6,
+            age_seconds: None,
+            feeder_count: 1,
+            derived: false,
+            frozen: false,
+            source: "primary".to_string(),
+            inverted: true,
+        }
+    );
+
+    // both orientations agree within rounding
+    let product = price.rate * inverted_price.rate;
+    assert!(Decimal256::one() - product <= Decimal256::from_str("0.000000000000000001").unwrap());
+
+    // a zero price has no reciprocal
+    let msg = ExecuteMsg::FeedPrice {
+        prices: vec![("bluna".to_string(), Decimal256::zero())],
+        quote: None,
+    };
+    let _res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("feeder0000", &[]),
+        msg,
+    )
+    .unwrap();
+
+    let res = query(
+        deps.as_ref(),
+        env,
+        QueryMsg::Price {
+            base: "uusd".to_string(),
+            quote: "bluna".to_string(),
+            block_time: None,
+        },
+    );
+    match res {
+        Err(StdError::GenericErr { msg, .. }) => {
+            assert_eq!(msg, "Cannot invert zero price of bluna")
+        }
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+}
+
+#[test]
+fn update_price_from_pair() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        base_asset: "uusd".to_string(),
+        valid_period: 60,
+        fallback_oracle: None,
+        default_quote_denom: None,
+    };
+
+    let info = mock_info("addr0000", &[]);
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let info = mock_info("owner0000", &[]);
+    let msg = ExecuteMsg::RegisterAsset {
+        asset: "bluna".to_string(),
+        decimals: 6,
+        description: "Bonded Luna".to_string(),
+        heartbeat: None,
+    };
+    let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    let msg = ExecuteMsg::UpdatePriceFromPair {
+        asset: "bluna".to_string(),
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0001", &[]), msg);
+    match res {
+        Err(ContractError::PairSourceNotRegistered(asset)) => assert_eq!(asset, "bluna"),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let register_msg = |initial_price| ExecuteMsg::RegisterPairSource {
+        asset: "bluna".to_string(),
+        pair: "pair0000".to_string(),
+        max_deviation: Decimal256::percent(10),
+        twap_window: 600,
+        initial_price,
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        register_msg(None),
+    );
+    match res {
+        Err(ContractError::Unauthorized {}) => {}
+        _ => panic!("Must return unauthorized error"),
+    }
+
+    // without a fed price, the TWAP needs an initial price
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), register_msg(None));
+    match res {
+        Err(ContractError::PairSourceNotSeeded(asset)) => assert_eq!(asset, "bluna"),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        info,
+        register_msg(Some(Decimal256::from_uint256(5u64))),
+    )
+    .unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "register_pair_source"),
+            attr("asset", "bluna"),
+            attr("pair", "pair0000"),
+            attr("max_deviation", "0.1"),
+            attr("twap_window", "600"),
+            attr("twap", "5"),
+        ]
+    );
+
+    // the first update is already checked against the seeded TWAP,
+    // so a manipulated spot price cannot be stored
+    deps.querier
+        .with_pool(&[(&"pair0000".to_string(), &pool(1000000, 10000000))]);
+    let env = mock_env();
+    let update_msg = ExecuteMsg::UpdatePriceFromPair {
+        asset: "bluna".to_string(),
+    };
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0001", &[]),
+        update_msg.clone(),
+    );
+    match res {
+        Err(ContractError::PriceDeviationTooHigh(asset)) => assert_eq!(asset, "bluna"),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // anyone can pull the price
+    deps.querier
+        .with_pool(&[(&"pair0000".to_string(), &pool(1000000, 5000000))]);
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0001", &[]),
+        update_msg.clone(),
+    )
+    .unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "update_price_from_pair"),
+            attr("asset", "bluna"),
+            attr("price", "5"),
+            attr("twap", "5"),
+        ]
+    );
+
+    let price_query = QueryMsg::Price {
+        base: "bluna".to_string(),
+        quote: "uusd".to_string(),
+        block_time: None,
+    };
+    let value: PriceResponse =
+        from_binary(&query(deps.as_ref(), env.clone(), price_query.clone()).unwrap()).unwrap();
+    assert_eq!(value.rate, Decimal256::from_str("5").unwrap());
+    assert_eq!(value.source, "amm");
+
+    // a manipulated spot price is rejected and the stored price is kept
+    deps.querier
+        .with_pool(&[(&"pair0000".to_string(), &pool(1000000, 10000000))]);
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0001", &[]),
+        update_msg.clone(),
+    );
+    match res {
+        Err(ContractError::PriceDeviationTooHigh(asset)) => assert_eq!(asset, "bluna"),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+    let value: PriceResponse =
+        from_binary(&query(deps.as_ref(), env.clone(), price_query).unwrap()).unwrap();
+    assert_eq!(value.rate, Decimal256::from_str("5").unwrap());
+
+    // a move within the deviation is accepted and pulls the TWAP halfway
+    // after half the window
+    deps.querier
+        .with_pool(&[(&"pair0000".to_string(), &pool(1000000, 5400000))]);
+    let mut later_env = env.clone();
+    later_env.block.time = later_env.block.time.plus_seconds(300);
+    let _res = execute(
+        deps.as_mut(),
+        later_env.clone(),
+        mock_info("addr0001", &[]),
+        update_msg,
+    )
+    .unwrap();
+
+    let value: PairSourceResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            later_env.clone(),
+            QueryMsg::PairSource {
+                asset: "bluna".to_string(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        value,
+        PairSourceResponse {
+            asset: "bluna".to_string(),
+            pair: "pair0000".to_string(),
+            max_deviation: Decimal256::percent(10),
+            twap_window: 600,
+            twap: Some(Decimal256::from_str("5.2").unwrap()),
+            twap_updated_time: later_env.block.time.seconds(),
+        }
+    );
+}
+
+#[test]
+fn feeder_stats() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        base_asset: "base0000".to_string(),
+        valid_period: 60,
+        fallback_oracle: None,
+        default_quote_denom: None,
+    };
+
+    let info = mock_info("addr0000", &[]);
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let info = mock_info("owner0000", &[]);
+    for (asset, feeder) in [
+        ("mAAPL", "feeder0000"),
+        ("mGOGL", "feeder0000"),
+        ("mUST", "feeder0001"),
+    ] {
+        let msg = ExecuteMsg::RegisterAsset {
+            asset: asset.to_string(),
+            decimals: 6,
+            description: asset.to_string(),
+            heartbeat: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::RegisterFeeder {
+            asset: asset.to_string(),
+            feeder: feeder.to_string(),
+            quote: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+    }
+
+    let msg = ExecuteMsg::UpdateAssetBounds {
+        asset: "mGOGL".to_string(),
+        min_price: None,
+        max_price: Some(Decimal256::from_uint256(10u64)),
+    };
+    let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let env = mock_env();
+    let msg = ExecuteMsg::FeedPrice {
+        prices: vec![
+            ("mAAPL".to_string(), Decimal256::from_uint256(2u64)),
+            ("mGOGL".to_string(), Decimal256::from_uint256(5u64)),
+        ],
+        quote: None,
+    };
+    let _res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("feeder0000", &[]),
+        msg,
+    )
+    .unwrap();
+
+    // mGOGL is rejected by the bounds check, mAAPL still goes through
+    let mut later_env = env.clone();
+    later_env.block.time = later_env.block.time.plus_seconds(10);
+    let msg = ExecuteMsg::FeedPrice {
+        prices: vec![
+            ("mAAPL".to_string(), Decimal256::from_uint256(2u64)),
+            ("mGOGL".to_string(), Decimal256::from_uint256(50u64)),
+        ],
+        quote: None,
+    };
+    let res = execute(
+        deps.as_mut(),
+        later_env.clone(),
+        mock_info("feeder0000", &[]),
+        msg,
+    )
+    .unwrap();
+    assert_eq!(
+        res.attributes[res.attributes.len() - 2..],
+        [
+            attr("asset", "mGOGL"),
+            attr("rejected", "Price of mGOGL is above max_price: 50 > 10"),
+        ]
+    );
+
+    // a signed mGOGL price timestamped ahead of the block is newer than the
+    // feed, so mGOGL is rejected as stale while mAAPL still goes through
+    let signing_key = SigningKey::from_bytes(&[7u8; 32]).unwrap();
+    let msg = ExecuteMsg::RegisterFeederKey {
+        asset: "mGOGL".to_string(),
+        pubkey: Binary::from(signing_key.verifying_key().to_bytes().to_vec()),
+    };
+    let _res = execute(
+        deps.as_mut(),
+        later_env.clone(),
+        mock_info("owner0000", &[]),
+        msg,
+    )
+    .unwrap();
+    let price = Decimal256::from_uint256(6u64);
+    let timestamp = later_env.block.time.seconds() + 30;
+    let msg = ExecuteMsg::FeedPriceSigned {
+        asset: "mGOGL".to_string(),
+        price,
+        timestamp,
+        signature: sign_price(&signing_key, "mGOGL", price, timestamp),
+        pubkey: Binary::from(signing_key.verifying_key().to_bytes().to_vec()),
+    };
+    let _res = execute(
+        deps.as_mut(),
+        later_env.clone(),
+        mock_info("addr0000", &[]),
+        msg,
+    )
+    .unwrap();
+
+    let msg = ExecuteMsg::FeedPrice {
+        prices: vec![
+            ("mAAPL".to_string(), Decimal256::from_uint256(3u64)),
+            ("mGOGL".to_string(), Decimal256::from_uint256(7u64)),
+        ],
+        quote: None,
+    };
+    let res = execute(
+        deps.as_mut(),
+        later_env.clone(),
+        mock_info("feeder0000", &[]),
+        msg,
+    )
+    .unwrap();
+    assert_eq!(
+        res.attributes[res.attributes.len() - 2..],
+        [
+            attr("asset", "mGOGL"),
+            attr(
+                "rejected",
+                "Price timestamp is not newer than the stored price"
+            ),
+        ]
+    );
+
+    // unauthorized submissions fail the whole message and are not counted
+    let msg = ExecuteMsg::FeedPrice {
+        prices: vec![("mUST".to_string(), Decimal256::one())],
+        quote: None,
+    };
+    let res = execute(
+        deps.as_mut(),
+        later_env.clone(),
+        mock_info("feeder0000", &[]),
+        msg.clone(),
+    );
+    match res {
+        Err(ContractError::Unauthorized {}) => {}
+        _ => panic!("Must return unauthorized error"),
+    }
+    let _res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("feeder0001", &[]),
+        msg,
+    )
+    .unwrap();
+
+    let feeder0000 = FeederStatsResponseElem {
+        asset: String::new(),
+        feeder: "feeder0000".to_string(),
+        accepted: 3,
+        rejected: 0,
+        last_submission_time: later_env.block.time.seconds(),
+    };
+    let expected = vec![
+        FeederStatsResponseElem {
+            asset: "mUST".to_string(),
+            feeder: "feeder0001".to_string(),
+            accepted: 1,
+            rejected: 0,
+            last_submission_time: env.block.time.seconds(),
+        },
+        FeederStatsResponseElem {
+            asset: "mAAPL".to_string(),
+            ..feeder0000.clone()
+        },
+        FeederStatsResponseElem {
+            asset: "mGOGL".to_string(),
+            accepted: 1,
+            rejected: 2,
+            ..feeder0000
+        },
+    ];
+
+    let res: FeederStatsResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::FeederStats {
+                asset: None,
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(res.stats, expected);
+
+    let res: FeederStatsResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::FeederStats {
+                asset: None,
+                start_after: Some(("mAAPL".to_string(), "feeder0000".to_string())),
+                limit: Some(1),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(res.stats, expected[2..].to_vec());
+
+    let res: FeederStatsResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::FeederStats {
+                asset: Some("mAAPL".to_string()),
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(res.stats, expected[1..2].to_vec());
+}
+
+#[test]
+fn feeders() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        base_asset: "base0000".to_string(),
+        valid_period: 60,
+        fallback_oracle: None,
+        default_quote_denom: None,
+    };
+
+    let info = mock_info("addr0000", &[]);
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let info = mock_info("owner0000", &[]);
+    let mut env = mock_env();
+    for (asset, feeder, quote) in [
+        ("mAAPL", "feeder0000", None),
+        ("mGOGL", "feeder0000", None),
+        ("mGOGL", "feeder0000", Some("uusd")),
+        ("mNFLX", "feeder0001", None),
+        ("mNFLX", "feeder0002", Some("uusd")),
+    ] {
+        env.block.height += 1;
+        let msg = ExecuteMsg::RegisterFeeder {
+            asset: asset.to_string(),
+            feeder: feeder.to_string(),
+            quote: quote.map(|q| q.to_string()),
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+    }
+
+    let res: FeedersResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::Feeders {
+                start_after: None,
+                limit: Some(2),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        res,
+        FeedersResponse {
+            feeders: vec![
+                FeedersResponseElem {
+                    asset: "mAAPL".to_string(),
+                    feeder: "feeder0000".to_string(),
+                    quotes: vec!["base0000".to_string()],
+                    registered_at_height: 12346,
+                },
+                FeedersResponseElem {
+                    asset: "mGOGL".to_string(),
+                    feeder: "feeder0000".to_string(),
+                    quotes: vec!["base0000".to_string(), "uusd".to_string()],
+                    registered_at_height: 12348,
+                },
+            ],
+        }
+    );
+
+    // feeders of the same asset are ordered by canonical address
+    let res: FeedersResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::Feeders {
+                start_after: Some(("mGOGL".to_string(), "feeder0000".to_string())),
+                limit: Some(2),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        res,
+        FeedersResponse {
+            feeders: vec![
+                FeedersResponseElem {
+                    asset: "mNFLX".to_string(),
+                    feeder: "feeder0002".to_string(),
+                    quotes: vec!["uusd".to_string()],
+                    registered_at_height: 12350,
+                },
+                FeedersResponseElem {
+                    asset: "mNFLX".to_string(),
+                    feeder: "feeder0001".to_string(),
+                    quotes: vec!["base0000".to_string()],
+                    registered_at_height: 12349,
+                },
+            ],
+        }
+    );
+
+    // replacing the mAAPL feeder drops the old registration
+    env.block.height += 1;
+    let msg = ExecuteMsg::RegisterFeeder {
+        asset: "mAAPL".to_string(),
+        feeder: "feeder0001".to_string(),
+        quote: None,
+    };
+    let _res = execute(deps.as_mut(), env, info, msg).unwrap();
+
+    let res: FeedersResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::Feeders {
+                start_after: None,
+                limit: Some(1),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        res,
+        FeedersResponse {
+            feeders: vec![FeedersResponseElem {
+                asset: "mAAPL".to_string(),
+                feeder: "feeder0001".to_string(),
+                quotes: vec!["base0000".to_string()],
+                registered_at_height: 12351,
+            }],
+        }
+    );
+}
+
+#[test]
+fn snapshot_prices() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        base_asset: "base0000".to_string(),
+        valid_period: 60,
+        fallback_oracle: None,
+        default_quote_denom: None,
+    };
+
+    let info = mock_info("addr0000", &[]);
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let info = mock_info("owner0000", &[]);
+    let msg = ExecuteMsg::UpdateConfig {
+        owner: None,
+        valid_period: None,
+        fallback_oracle: None,
+        snapshotter: Some("overseer0000".to_string()),
+    };
+    let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    for asset in ["mAAPL", "mGOGL"] {
+        let msg = ExecuteMsg::RegisterAsset {
+            asset: asset.to_string(),
+            decimals: 6,
+            description: asset.to_string(),
+            heartbeat: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::RegisterFeeder {
+            asset: asset.to_string(),
+            feeder: "feeder0000".to_string(),
+            quote: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+    }
+
+    let env = mock_env();
+    let msg = ExecuteMsg::FeedPrice {
+        prices: vec![
+            ("mAAPL".to_string(), Decimal256::from_str("1.2").unwrap()),
+            ("mGOGL".to_string(), Decimal256::from_str("2.2").unwrap()),
+        ],
+        quote: None,
+    };
+    let _res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("feeder0000", &[]),
+        msg,
+    )
+    .unwrap();
+
+    // Only the snapshotter can take snapshots
+    let snapshot_msg = ExecuteMsg::SnapshotPrices {
+        assets: vec![
+            "mAAPL".to_string(),
+            "mGOGL".to_string(),
+            "mNFLX".to_string(),
+        ],
+    };
+    let res = execute(deps.as_mut(), env.clone(), info, snapshot_msg.clone());
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("Must return unauthorized error"),
+    }
+
+    let snapshotter = mock_info("overseer0000", &[]);
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        snapshotter.clone(),
+        snapshot_msg.clone(),
+    )
+    .unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "snapshot_prices"),
+            attr("asset", "mAAPL"),
+            attr("price", "1.2"),
+            attr("asset", "mGOGL"),
+            attr("price", "2.2"),
+            attr("skipped", "mNFLX"),
+            attr("height", env.block.height.to_string()),
+        ]
+    );
+
+    let snapshot_query = QueryMsg::SnapshotPrice {
+        asset: "mAAPL".to_string(),
+        height: env.block.height,
+    };
+    let live_price: PriceResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Price {
+                base: "mAAPL".to_string(),
+                quote: "base0000".to_string(),
+                block_time: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    let expected = SnapshotPriceResponse {
+        asset: "mAAPL".to_string(),
+        height: env.block.height,
+        rate: live_price.rate,
+        last_updated_time: live_price.last_updated_base,
+    };
+    let value: SnapshotPriceResponse =
+        from_binary(&query(deps.as_ref(), env.clone(), snapshot_query.clone()).unwrap()).unwrap();
+    assert_eq!(value, expected);
+
+    // later feeds do not change the snapshot
+    let mut later_env = env.clone();
+    later_env.block.height += 1;
+    later_env.block.time = later_env.block.time.plus_seconds(10);
+    let msg = ExecuteMsg::FeedPrice {
+        prices: vec![("mAAPL".to_string(), Decimal256::from_str("1.5").unwrap())],
+        quote: None,
+    };
+    let _res = execute(
+        deps.as_mut(),
+        later_env.clone(),
+        mock_info("feeder0000", &[]),
+        msg,
+    )
+    .unwrap();
+    let value: SnapshotPriceResponse =
+        from_binary(&query(deps.as_ref(), later_env, snapshot_query.clone()).unwrap()).unwrap();
+    assert_eq!(value, expected);
+
+    let res = query(
+        deps.as_ref(),
+        env.clone(),
+        QueryMsg::SnapshotPrice {
+            asset: "mNFLX".to_string(),
+            height: env.block.height,
+        },
+    );
+    match res {
+        Err(StdError::GenericErr { msg, .. }) => assert_eq!(
+            msg,
+            format!("No snapshot price for mNFLX at height {}", env.block.height)
+        ),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // only the last 30 snapshots are retained
+    for i in 1..=30 {
+        let mut snapshot_env = env.clone();
+        snapshot_env.block.height += i;
+        let _res = execute(
+            deps.as_mut(),
+            snapshot_env,
+            snapshotter.clone(),
+            snapshot_msg.clone(),
+        )
+        .unwrap();
+    }
+    let res = query(deps.as_ref(), env.clone(), snapshot_query);
+    match res {
+        Err(StdError::GenericErr { msg, .. }) => {
+            assert_eq!(msg, "No price snapshot at the given height")
+        }
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+    let _value: SnapshotPriceResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::SnapshotPrice {
+                asset: "mAAPL".to_string(),
+                height: env.block.height + 1,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+}
+
+#[test]
+fn legacy_price_info() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        base_asset: "base0000".to_string(),
+        valid_period: 60,
+        fallback_oracle: None,
+        default_quote_denom: None,
+    };
+
+    let info = mock_info("addr0000", &[]);
+    let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    let msg = ExecuteMsg::RegisterAsset {
+        asset: "mAAPL".to_string(),
+        decimals: 6,
+        description: "Mirrored Apple".to_string(),
+        heartbeat: None,
+    };
+    let _res = execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg).unwrap();
+
+    // prices stored before the source field was introduced
+    let env = mock_env();
+    let mut key = to_length_prefixed_nested(&[b"price", b"base0000"]);
+    key.extend_from_slice(b"mAAPL");
+    deps.storage.set(
+        &key,
+        format!(
+            r#"{{"price":"1.2","last_updated_time":{}}}"#,
+            env.block.time.seconds()
+        )
+        .as_bytes(),
+    );
+
+    let value: PriceResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Price {
+                base: "mAAPL".to_string(),
+                quote: "base0000".to_string(),
+                block_time: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(value.rate, Decimal256::from_str("1.2").unwrap());
+    assert_eq!(value.last_updated_base, env.block.time.seconds());
+    assert_eq!(value.source, "primary");
+}
+
+#[test]
+fn migrate_single_quote_layout() {
+    let mut deps = mock_dependencies(&[]);
+
+    // storage as written by the first release, keyed by asset only
+    let env = mock_env();
+    let owner = deps.api.addr_canonicalize("owner0000").unwrap();
+    let feeder = deps.api.addr_canonicalize("feeder0000").unwrap();
+    deps.storage.set(
+        &to_length_prefixed(b"config"),
+        format!(
+            r#"{{"owner":"{}","base_asset":"base0000"}}"#,
+            Binary::from(owner.as_slice()).to_base64()
+        )
+        .as_bytes(),
+    );
+    let mut legacy_price_key = to_length_prefixed(b"price");
+    legacy_price_key.extend_from_slice(b"mAAPL");
+    deps.storage.set(
+        &legacy_price_key,
+        format!(
+            r#"{{"price":"1.2","last_updated_time":{}}}"#,
+            env.block.time.seconds()
+        )
+        .as_bytes(),
+    );
+    let mut legacy_feeder_key = to_length_prefixed(b"feeder");
+    legacy_feeder_key.extend_from_slice(b"mAAPL");
+    deps.storage.set(
+        &legacy_feeder_key,
+        format!(r#""{}""#, Binary::from(feeder.as_slice()).to_base64()).as_bytes(),
+    );
+
+    let res = migrate(deps.as_mut(), env.clone(), MigrateMsg { valid_period: 60 }).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "migrate"),
+            attr("prices", "1"),
+            attr("feeders", "1"),
+        ]
+    );
+    assert_eq!(deps.storage.get(&legacy_price_key), None);
+    assert_eq!(deps.storage.get(&legacy_feeder_key), None);
+
+    let value: ConfigResponse =
+        from_binary(&query(deps.as_ref(), env.clone(), QueryMsg::Config {}).unwrap()).unwrap();
+    assert_eq!(
+        value,
+        ConfigResponse {
+            owner: "owner0000".to_string(),
+            base_asset: "base0000".to_string(),
+            valid_period: 60,
+            fallback_oracle: None,
+            snapshotter: None,
+            default_quote_denom: "base0000".to_string(),
+        }
+    );
+
+    let value: PriceResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Price {
+                base: "mAAPL".to_string(),
+                quote: "base0000".to_string(),
+                block_time: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(value.rate, Decimal256::from_str("1.2").unwrap());
+    assert_eq!(value.last_updated_base, env.block.time.seconds());
+
+    let value: FeederResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Feeder {
+                asset: "mAAPL".to_string(),
+                quote: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        value,
+        FeederResponse {
+            asset: "mAAPL".to_string(),
+            quote: "base0000".to_string(),
+            feeder: "feeder0000".to_string(),
+        }
+    );
+
+    let value: AssetsResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Assets {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(value.assets.len(), 1);
+    assert_eq!(value.assets[0].asset, "mAAPL");
+    assert_eq!(value.assets[0].decimals, 6);
+
+    // the migrated feeder keeps feeding
+    let msg = ExecuteMsg::FeedPrice {
+        prices: vec![("mAAPL".to_string(), Decimal256::from_str("1.3").unwrap())],
+        quote: None,
+    };
+    let res = execute(deps.as_mut(), env, mock_info("feeder0000", &[]), msg).unwrap();
+    assert_eq!(res.attributes[3], attr("old_price", "1.2"));
+}
@@ -4,10 +4,10 @@ use std::fs::create_dir_all;
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 
 use moneymarket::overseer::{
-    AllCollateralsResponse, BorrowLimitResponse, CollateralsResponse, ConfigResponse, ExecuteMsg,
-    InstantiateMsg, QueryMsg, WhitelistResponse,
+    AllCollateralsResponse, BorrowLimitResponse, BorrowLimitsResponse, CollateralValueResponse,
+    CollateralsBatchResponse, CollateralsResponse, ConfigResponse, DistributionParamsResponse,
+    EpochStateResponse, ExecuteMsg, InstantiateMsg, QueryMsg, WhitelistResponse,
 };
-use moneymarket_overseer::state::EpochState;
 
 fn main() {
     let mut out_dir = current_dir().unwrap();
@@ -20,8 +20,12 @@ fn main() {
     export_schema(&schema_for!(QueryMsg), &out_dir);
     export_schema(&schema_for!(AllCollateralsResponse), &out_dir);
     export_schema(&schema_for!(BorrowLimitResponse), &out_dir);
+    export_schema(&schema_for!(BorrowLimitsResponse), &out_dir);
+    export_schema(&schema_for!(CollateralValueResponse), &out_dir);
     export_schema(&schema_for!(CollateralsResponse), &out_dir);
+    export_schema(&schema_for!(CollateralsBatchResponse), &out_dir);
     export_schema(&schema_for!(ConfigResponse), &out_dir);
-    export_schema(&schema_for!(EpochState), &out_dir);
+    export_schema(&schema_for!(DistributionParamsResponse), &out_dir);
+    export_schema(&schema_for!(EpochStateResponse), &out_dir);
     export_schema(&schema_for!(WhitelistResponse), &out_dir);
 }
@@ -1,22 +1,27 @@
 use cosmwasm_bignumber::{Decimal256, Uint256};
 use cosmwasm_std::{
-    attr, to_binary, Addr, CosmosMsg, Deps, DepsMut, Env, MessageInfo, Response, StdResult, SubMsg,
-    WasmMsg,
+    attr, to_binary, Addr, Attribute, CanonicalAddr, CosmosMsg, Deps, DepsMut, Env, MessageInfo,
+    Response, StdError, StdResult, SubMsg, WasmMsg,
 };
 
 use crate::error::ContractError;
-use crate::querier::{query_borrower_info, query_liquidation_amount};
+use crate::querier::query_liquidation_amount;
 use crate::state::{
     read_all_collaterals, read_collaterals, read_config, read_whitelist_elem, store_collaterals,
     Config, WhitelistElem,
 };
 
 use moneymarket::custody::ExecuteMsg as CustodyExecuteMsg;
-use moneymarket::liquidation::LiquidationAmountResponse;
-use moneymarket::market::{BorrowerInfoResponse, ExecuteMsg as MarketExecuteMsg};
+use moneymarket::error::MoneyMarketError;
+use moneymarket::liquidation::{ExecuteMsg as LiquidationExecuteMsg, LiquidationAmountResponse};
+use moneymarket::math::{compute_value, uint_mul_decimal_256, STABLE_DECIMALS};
 use moneymarket::oracle::PriceResponse;
-use moneymarket::overseer::{AllCollateralsResponse, BorrowLimitResponse, CollateralsResponse};
-use moneymarket::querier::{query_balance, query_price, TimeConstraints};
+use moneymarket::overseer::{
+    AllCollateralsResponse, BorrowLimitResponse, BorrowLimitsResponse, CollateralValueResponse,
+    CollateralsBatchResponse, CollateralsResponse,
+};
+use moneymarket::pagination::{OrderBy, MAX_LIMIT};
+use moneymarket::querier::{query_loan_amount, query_prices_for, TimeConstraints};
 use moneymarket::tokens::{Tokens, TokensHuman, TokensMath, TokensToHuman, TokensToRaw};
 
 pub fn lock_collateral(
@@ -24,17 +29,26 @@ pub fn lock_collateral(
     info: MessageInfo,
     collaterals_human: TokensHuman,
 ) -> Result<Response, ContractError> {
+    // funds sent here would be stranded in the overseer
+    if !info.funds.is_empty() {
+        return Err(ContractError::FundsNotAccepted {});
+    }
+    assert_valid_collaterals(&collaterals_human)?;
+
     let borrower_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
     let mut cur_collaterals: Tokens = read_collaterals(deps.storage, &borrower_raw);
 
     let collaterals: Tokens = collaterals_human.to_raw(deps.as_ref())?;
 
-    cur_collaterals.add(collaterals.clone());
-    store_collaterals(deps.storage, &borrower_raw, &cur_collaterals)?;
-
     let mut messages: Vec<CosmosMsg> = vec![];
-    for collateral in collaterals {
+    for collateral in collaterals.clone() {
         let whitelist_elem: WhitelistElem = read_whitelist_elem(deps.storage, &collateral.0)?;
+        if whitelist_elem.paused {
+            return Err(ContractError::CollateralPaused(
+                deps.api.addr_humanize(&collateral.0)?.to_string(),
+            ));
+        }
+
         messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
             contract_addr: deps
                 .api
@@ -48,17 +62,16 @@ pub fn lock_collateral(
         }));
     }
 
-    // Logging stuff, so can be removed
-    let collateral_logs: Vec<String> = collaterals_human
-        .iter()
-        .map(|c| format!("{}{}", c.1, c.0.to_string()))
-        .collect();
+    cur_collaterals.add(collaterals);
+    store_collaterals(deps.storage, &borrower_raw, &cur_collaterals)?;
 
-    Ok(Response::new().add_messages(messages).add_attributes(vec![
-        attr("action", "lock_collateral"),
-        attr("borrower", info.sender),
-        attr("collaterals", collateral_logs.join(",")),
-    ]))
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attributes(vec![
+            attr("action", "lock_collateral"),
+            attr("borrower", info.sender),
+        ])
+        .add_attributes(collateral_attributes(&collaterals_human)))
 }
 
 pub fn unlock_collateral(
@@ -66,7 +79,10 @@ pub fn unlock_collateral(
     env: Env,
     info: MessageInfo,
     collaterals_human: TokensHuman,
+    withdraw: bool,
 ) -> Result<Response, ContractError> {
+    assert_valid_collaterals(&collaterals_human)?;
+
     let config: Config = read_config(deps.storage)?;
     let market = deps.api.addr_humanize(&config.market_contract)?;
 
@@ -75,9 +91,20 @@ pub fn unlock_collateral(
     let mut cur_collaterals: Tokens = read_collaterals(deps.storage, &borrower_raw);
     let collaterals: Tokens = collaterals_human.to_raw(deps.as_ref())?;
 
+    // The custody of each collateral receives the unlock message
+    let mut custody_contracts: Vec<String> = vec![];
+    for collateral in collaterals.iter() {
+        let whitelist_elem: WhitelistElem = read_whitelist_elem(deps.storage, &collateral.0)?;
+        custody_contracts.push(
+            deps.api
+                .addr_humanize(&whitelist_elem.custody_contract)?
+                .to_string(),
+        );
+    }
+
     // Underflow check is done in sub_collateral
     if cur_collaterals.sub(collaterals.clone()).is_err() {
-        return Err(ContractError::UnlockExceedsLocked {});
+        return Err(MoneyMarketError::UnlockExceedsLocked {}.into());
     }
 
     // Compute borrow limit with collaterals except unlock target collaterals
@@ -86,43 +113,83 @@ pub fn unlock_collateral(
         &cur_collaterals,
         Some(env.block.time.seconds()),
     )?;
-    let borrow_amount_res: BorrowerInfoResponse =
-        query_borrower_info(deps.as_ref(), market, borrower.clone(), env.block.height)?;
-    if borrow_limit < borrow_amount_res.loan_amount {
-        return Err(ContractError::UnlockTooLarge(borrow_limit.into()));
+    let borrow_amount =
+        query_loan_amount(deps.as_ref(), market, borrower.clone(), env.block.height)?;
+    if borrow_limit < borrow_amount {
+        return Err(MoneyMarketError::UnlockExceedsLtv(borrow_limit.into()).into());
     }
 
     store_collaterals(deps.storage, &borrower_raw, &cur_collaterals)?;
 
     let mut messages: Vec<SubMsg> = vec![];
-    for collateral in collaterals.clone() {
-        let whitelist_elem: WhitelistElem = read_whitelist_elem(deps.storage, &collateral.0)?;
+    for (collateral, custody_contract) in collaterals.into_iter().zip(custody_contracts) {
         messages.push(SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
-            contract_addr: deps
-                .api
-                .addr_humanize(&whitelist_elem.custody_contract)?
-                .to_string(),
+            contract_addr: custody_contract.clone(),
             funds: vec![],
             msg: to_binary(&CustodyExecuteMsg::UnlockCollateral {
                 borrower: borrower.to_string(),
                 amount: collateral.1,
             })?,
         })));
-    }
 
-    // Logging stuff, so can be removed
-    let collateral_logs: Vec<String> = collaterals_human
-        .iter()
-        .map(|c| format!("{}{}", c.1, c.0.to_string()))
-        .collect();
+        if withdraw {
+            messages.push(SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: custody_contract,
+                funds: vec![],
+                msg: to_binary(&CustodyExecuteMsg::WithdrawCollateral {
+                    borrower: Some(borrower.to_string()),
+                    amount: Some(collateral.1),
+                })?,
+            })));
+        }
+    }
 
     Ok(Response::new()
         .add_submessages(messages)
         .add_attributes(vec![
-            attr("action", "unlock_collateral"),
+            attr(
+                "action",
+                if withdraw {
+                    "unlock_and_withdraw_collateral"
+                } else {
+                    "unlock_collateral"
+                },
+            ),
             attr("borrower", borrower),
-            attr("collaterals", collateral_logs.join(",")),
-        ]))
+        ])
+        .add_attributes(collateral_attributes(&collaterals_human)))
+}
+
+/// The aggregate "collaterals" attribute, kept for the existing indexers,
+/// followed by a collateral_token / collateral_amount pair per collateral
+fn collateral_attributes(collaterals: &TokensHuman) -> Vec<Attribute> {
+    let collateral_logs: Vec<String> = collaterals
+        .iter()
+        .map(|c| format!("{}{}", c.1, c.0))
+        .collect();
+
+    let mut attributes = vec![attr("collaterals", collateral_logs.join(","))];
+    for collateral in collaterals.iter() {
+        attributes.push(attr("collateral_token", collateral.0.clone()));
+        attributes.push(attr("collateral_amount", collateral.1));
+    }
+
+    attributes
+}
+
+/// Every collateral must have a non-zero amount and appear only once
+fn assert_valid_collaterals(collaterals: &TokensHuman) -> Result<(), ContractError> {
+    for (i, collateral) in collaterals.iter().enumerate() {
+        if collateral.1.is_zero() {
+            return Err(ContractError::ZeroCollateralAmount(collateral.0.clone()));
+        }
+
+        if collaterals[..i].iter().any(|c| c.0 == collateral.0) {
+            return Err(ContractError::DuplicateCollateral(collateral.0.clone()));
+        }
+    }
+
+    Ok(())
 }
 
 pub fn liquidate_collateral(
@@ -137,15 +204,14 @@ pub fn liquidate_collateral(
     let borrower_raw = deps.api.addr_canonicalize(borrower.as_str())?;
     let mut cur_collaterals: Tokens = read_collaterals(deps.storage, &borrower_raw);
 
-    // Compute borrow limit with collaterals except unlock target collaterals
+    // Compute borrow limit with all the collaterals of the borrower
     let (borrow_limit, collateral_prices) = compute_borrow_limit(
         deps.as_ref(),
         &cur_collaterals,
         Some(env.block.time.seconds()),
     )?;
-    let borrow_amount_res: BorrowerInfoResponse =
-        query_borrower_info(deps.as_ref(), market, borrower.clone(), env.block.height)?;
-    let borrow_amount = borrow_amount_res.loan_amount;
+    let borrow_amount =
+        query_loan_amount(deps.as_ref(), market, borrower.clone(), env.block.height)?;
 
     // borrow limit is equal or bigger than loan amount
     // cannot liquidation collaterals
@@ -169,8 +235,6 @@ pub fn liquidate_collateral(
     store_collaterals(deps.storage, &borrower_raw, &cur_collaterals)?;
 
     let market_contract = deps.api.addr_humanize(&config.market_contract)?;
-    let prev_balance: Uint256 =
-        query_balance(deps.as_ref(), market_contract.clone(), config.stable_denom)?;
 
     let liquidation_messages: Vec<CosmosMsg> = liquidation_amount
         .iter()
@@ -190,19 +254,32 @@ pub fn liquidate_collateral(
                 })?,
             }))
         })
-        .filter(|msg| msg.is_ok())
         .collect::<StdResult<Vec<CosmosMsg>>>()?;
 
+    let attributes = collateral_attributes(&liquidation_amount_res.collaterals);
+
+    // the custody contracts leave the stable in the liquidation contract,
+    // which repays the market once for all the collaterals
     Ok(Response::new()
         .add_messages(liquidation_messages)
         .add_message(CosmosMsg::Wasm(WasmMsg::Execute {
-            contract_addr: market_contract.to_string(),
+            contract_addr: deps
+                .api
+                .addr_humanize(&config.liquidation_contract)?
+                .to_string(),
             funds: vec![],
-            msg: to_binary(&MarketExecuteMsg::RepayStableFromLiquidation {
+            msg: to_binary(&LiquidationExecuteMsg::SettleLiquidation {
                 borrower: borrower.to_string(),
-                prev_balance,
+                repay_address: Some(market_contract.to_string()),
+                collaterals: liquidation_amount_res.collaterals,
             })?,
-        })))
+        }))
+        .add_attributes(vec![
+            attr("action", "liquidate_collateral"),
+            attr("liquidator", info.sender),
+            attr("borrower", borrower),
+        ])
+        .add_attributes(attributes))
 }
 
 pub fn query_collaterals(deps: Deps, borrower: Addr) -> StdResult<CollateralsResponse> {
@@ -213,17 +290,35 @@ pub fn query_collaterals(deps: Deps, borrower: Addr) -> StdResult<CollateralsRes
 
     Ok(CollateralsResponse {
         borrower: borrower.to_string(),
-        collaterals: collaterals
-            .iter()
-            .map(|c| Ok((deps.api.addr_humanize(&c.0)?.to_string(), c.1)))
-            .collect::<StdResult<Vec<(String, Uint256)>>>()?,
+        collaterals: collaterals.to_human(deps)?,
     })
 }
 
+pub fn query_collaterals_batch(
+    deps: Deps,
+    borrowers: Vec<Addr>,
+) -> StdResult<CollateralsBatchResponse> {
+    if borrowers.len() > MAX_LIMIT as usize {
+        return Err(StdError::generic_err(format!(
+            "Cannot query more than {} borrowers at once",
+            MAX_LIMIT
+        )));
+    }
+
+    // borrowers without collaterals get an empty list
+    let collaterals = borrowers
+        .into_iter()
+        .map(|borrower| query_collaterals(deps, borrower))
+        .collect::<StdResult<Vec<CollateralsResponse>>>()?;
+
+    Ok(CollateralsBatchResponse { collaterals })
+}
+
 pub fn query_all_collaterals(
     deps: Deps,
     start_after: Option<Addr>,
     limit: Option<u32>,
+    order_by: Option<OrderBy>,
 ) -> StdResult<AllCollateralsResponse> {
     let start_after = if let Some(start_after) = start_after {
         Some(deps.api.addr_canonicalize(start_after.as_str())?)
@@ -231,7 +326,8 @@ pub fn query_all_collaterals(
         None
     };
 
-    let all_collaterals: Vec<CollateralsResponse> = read_all_collaterals(deps, start_after, limit)?;
+    let all_collaterals: Vec<CollateralsResponse> =
+        read_all_collaterals(deps, start_after, limit, order_by)?;
 
     Ok(AllCollateralsResponse { all_collaterals })
 }
@@ -241,37 +337,126 @@ pub(crate) fn compute_borrow_limit(
     deps: Deps,
     collaterals: &Tokens,
     block_time: Option<u64>,
-) -> StdResult<(Uint256, Vec<Decimal256>)> {
-    let config: Config = read_config(deps.storage)?;
-    let oracle_contract = deps.api.addr_humanize(&config.oracle_contract)?;
+) -> Result<(Uint256, Vec<Decimal256>), MoneyMarketError> {
+    let (borrow_limits, collateral_prices) = compute_borrow_limits(deps, collaterals, block_time)?;
 
+    // the total is the sum of the per collateral parts, so both always agree
     let mut borrow_limit: Uint256 = Uint256::zero();
-    let mut collateral_prices: Vec<Decimal256> = vec![];
-    for collateral in collaterals.iter() {
-        let collateral_token = collateral.0.clone();
-        let collateral_amount = collateral.1;
-
-        let price: PriceResponse = query_price(
-            deps,
-            oracle_contract.clone(),
-            (deps.api.addr_humanize(&collateral_token)?).to_string(),
-            config.stable_denom.to_string(),
-            block_time.map(|block_time| TimeConstraints {
-                block_time,
-                valid_timeframe: config.price_timeframe,
-            }),
-        )?;
-
-        let elem: WhitelistElem = read_whitelist_elem(deps.storage, &collateral.0)?;
-        let collateral_value = collateral_amount * price.rate;
-        borrow_limit += collateral_value * elem.max_ltv;
-        collateral_prices.push(price.rate);
+    for part in borrow_limits {
+        borrow_limit += part;
     }
 
     // returns borrow_limit with collaterals value in stable denom
     Ok((borrow_limit, collateral_prices))
 }
 
+/// Borrow limit contribution and price of each collateral, in the order of `collaterals`
+#[allow(clippy::ptr_arg)]
+fn compute_borrow_limits(
+    deps: Deps,
+    collaterals: &Tokens,
+    block_time: Option<u64>,
+) -> Result<(Vec<Uint256>, Vec<Decimal256>), MoneyMarketError> {
+    let tokens: Vec<CanonicalAddr> = collaterals.iter().map(|c| c.0.clone()).collect();
+    let prices = query_collateral_prices(deps, &tokens, block_time)?;
+    let borrow_limits = compute_borrow_limit_parts(deps, collaterals, &tokens, &prices)?;
+
+    Ok((borrow_limits, prices))
+}
+
+/// Prices of the given collateral tokens, queried in one round trip
+fn query_collateral_prices(
+    deps: Deps,
+    tokens: &[CanonicalAddr],
+    block_time: Option<u64>,
+) -> Result<Vec<Decimal256>, MoneyMarketError> {
+    if tokens.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let config: Config = read_config(deps.storage)?;
+    let oracle_contract = deps.api.addr_humanize(&config.oracle_contract)?;
+    let assets = tokens
+        .iter()
+        .map(|token| Ok(deps.api.addr_humanize(token)?.to_string()))
+        .collect::<StdResult<Vec<String>>>()?;
+
+    let prices: Vec<PriceResponse> = query_prices_for(
+        deps,
+        oracle_contract,
+        assets,
+        config.stable_denom.to_string(),
+        block_time.map(|block_time| TimeConstraints {
+            block_time,
+            valid_timeframe: config.price_timeframe,
+        }),
+    )?;
+
+    Ok(prices.into_iter().map(|price| price.rate).collect())
+}
+
+/// Stable denom value of each collateral along with its whitelist entry,
+/// pricing it with the entry of `prices` at the position of its token in `tokens`
+#[allow(clippy::ptr_arg)]
+fn compute_collateral_values(
+    deps: Deps,
+    collaterals: &Tokens,
+    tokens: &[CanonicalAddr],
+    prices: &[Decimal256],
+) -> StdResult<Vec<(Uint256, WhitelistElem)>> {
+    collaterals
+        .iter()
+        .map(|collateral| {
+            let price = tokens
+                .iter()
+                .position(|token| *token == collateral.0)
+                .map(|i| prices[i])
+                .ok_or_else(|| StdError::generic_err("Collateral price was not queried"))?;
+
+            let elem: WhitelistElem = read_whitelist_elem(deps.storage, &collateral.0)?;
+            let value = compute_value(collateral.1, price, elem.decimals, STABLE_DECIMALS)?;
+            Ok((value, elem))
+        })
+        .collect()
+}
+
+/// Borrow limit contribution of each collateral, see `compute_collateral_values`
+#[allow(clippy::ptr_arg)]
+fn compute_borrow_limit_parts(
+    deps: Deps,
+    collaterals: &Tokens,
+    tokens: &[CanonicalAddr],
+    prices: &[Decimal256],
+) -> StdResult<Vec<Uint256>> {
+    Ok(
+        compute_collateral_values(deps, collaterals, tokens, prices)?
+            .into_iter()
+            .map(|(value, elem)| uint_mul_decimal_256(value, elem.max_ltv))
+            .collect(),
+    )
+}
+
+#[allow(clippy::ptr_arg)]
+fn to_borrow_limit_response(
+    deps: Deps,
+    borrower: Addr,
+    collaterals: &Tokens,
+    borrow_limits: Vec<Uint256>,
+) -> StdResult<BorrowLimitResponse> {
+    let mut borrow_limit: Uint256 = Uint256::zero();
+    let mut parts: TokensHuman = vec![];
+    for (collateral, part) in collaterals.iter().zip(borrow_limits) {
+        borrow_limit += part;
+        parts.push((deps.api.addr_humanize(&collateral.0)?.to_string(), part));
+    }
+
+    Ok(BorrowLimitResponse {
+        borrower: borrower.to_string(),
+        borrow_limit,
+        collaterals: parts,
+    })
+}
+
 pub fn query_borrow_limit(
     deps: Deps,
     borrower: Addr,
@@ -283,10 +468,76 @@ pub fn query_borrow_limit(
     );
 
     // Compute borrow limit with collaterals
-    let (borrow_limit, _) = compute_borrow_limit(deps, &collaterals, block_time)?;
+    let (borrow_limits, _) = compute_borrow_limits(deps, &collaterals, block_time)?;
 
-    Ok(BorrowLimitResponse {
+    to_borrow_limit_response(deps, borrower, &collaterals, borrow_limits)
+}
+
+pub fn query_borrow_limits(
+    deps: Deps,
+    borrowers: Vec<Addr>,
+    block_time: Option<u64>,
+) -> StdResult<BorrowLimitsResponse> {
+    if borrowers.len() > MAX_LIMIT as usize {
+        return Err(StdError::generic_err(format!(
+            "Cannot query more than {} borrowers at once",
+            MAX_LIMIT
+        )));
+    }
+
+    let collaterals = borrowers
+        .iter()
+        .map(|borrower| {
+            Ok(read_collaterals(
+                deps.storage,
+                &deps.api.addr_canonicalize(borrower.as_str())?,
+            ))
+        })
+        .collect::<StdResult<Vec<Tokens>>>()?;
+
+    // query each collateral token price once for the whole batch
+    let mut tokens: Vec<CanonicalAddr> = vec![];
+    for collateral in collaterals.iter().flatten() {
+        if !tokens.contains(&collateral.0) {
+            tokens.push(collateral.0.clone());
+        }
+    }
+    let prices = query_collateral_prices(deps, &tokens, block_time)?;
+
+    // borrowers without collaterals get a zero borrow limit
+    let borrow_limits = borrowers
+        .into_iter()
+        .zip(collaterals.iter())
+        .map(|(borrower, collaterals)| {
+            let parts = compute_borrow_limit_parts(deps, collaterals, &tokens, &prices)?;
+            to_borrow_limit_response(deps, borrower, collaterals, parts)
+        })
+        .collect::<StdResult<Vec<BorrowLimitResponse>>>()?;
+
+    Ok(BorrowLimitsResponse { borrow_limits })
+}
+
+pub fn query_collateral_value(deps: Deps, borrower: Addr) -> StdResult<CollateralValueResponse> {
+    let collaterals = read_collaterals(
+        deps.storage,
+        &deps.api.addr_canonicalize(borrower.as_str())?,
+    );
+
+    // same price lookup as the borrow limit, without the time constraints
+    let tokens: Vec<CanonicalAddr> = collaterals.iter().map(|c| c.0.clone()).collect();
+    let prices = query_collateral_prices(deps, &tokens, None)?;
+    let values = compute_collateral_values(deps, &collaterals, &tokens, &prices)?;
+
+    let mut total_value: Uint256 = Uint256::zero();
+    let mut parts: TokensHuman = vec![];
+    for (collateral, (value, _)) in collaterals.iter().zip(values) {
+        total_value += value;
+        parts.push((deps.api.addr_humanize(&collateral.0)?.to_string(), value));
+    }
+
+    Ok(CollateralValueResponse {
         borrower: borrower.to_string(),
-        borrow_limit,
+        total_value,
+        collaterals: parts,
     })
 }
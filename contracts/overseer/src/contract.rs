@@ -2,29 +2,43 @@
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
     attr, to_binary, Addr, BankMsg, Binary, Coin, CosmosMsg, Deps, DepsMut, Env, MessageInfo,
-    Response, StdResult, WasmMsg,
+    Reply, Response, StdResult, SubMsg, Uint128, WasmMsg,
 };
 
 use crate::collateral::{
     liquidate_collateral, lock_collateral, query_all_collaterals, query_borrow_limit,
-    query_collaterals, unlock_collateral,
+    query_borrow_limits, query_collateral_value, query_collaterals, query_collaterals_batch,
+    unlock_collateral,
 };
 use crate::error::ContractError;
-use crate::querier::query_epoch_state;
+use crate::querier::{
+    query_anc_emission_rate, query_deposit_rate_bounds, query_distribution_model,
+};
 use crate::state::{
-    read_config, read_epoch_state, read_whitelist, read_whitelist_elem, store_config,
+    default_custody_batch_size, default_max_ltv, read_config, read_distribution_cursor,
+    read_epoch_state, read_whitelist, read_whitelist_elem, store_config, store_distribution_cursor,
     store_epoch_state, store_whitelist_elem, Config, EpochState, WhitelistElem,
 };
 
 use cosmwasm_bignumber::{Decimal256, Uint256};
-use moneymarket::common::optional_addr_validate;
+use moneymarket::common::{
+    assert_fraction, assert_max, assert_positive_fraction, optional_addr_validate,
+};
 use moneymarket::custody::ExecuteMsg as CustodyExecuteMsg;
-use moneymarket::market::EpochStateResponse;
+use moneymarket::distribution_model::ExecuteMsg as DistributionExecuteMsg;
+use moneymarket::error::MoneyMarketError;
+use moneymarket::market::EpochStateResponse as MarketEpochStateResponse;
 use moneymarket::market::ExecuteMsg as MarketExecuteMsg;
+use moneymarket::math::STABLE_DECIMALS;
+use moneymarket::oracle::ExecuteMsg as OracleExecuteMsg;
 use moneymarket::overseer::{
-    ConfigResponse, ExecuteMsg, InstantiateMsg, QueryMsg, WhitelistResponse, WhitelistResponseElem,
+    ConfigResponse, DistributionParamsResponse, EpochStateResponse, ExecuteMsg, InstantiateMsg,
+    QueryMsg, WhitelistResponse, WhitelistResponseElem,
 };
-use moneymarket::querier::{deduct_tax, query_balance};
+use moneymarket::pagination::{OrderBy, MAX_LIMIT};
+use moneymarket::querier::{deduct_tax_vec, query_balance, query_epoch_state};
+
+pub const UPDATE_EMISSION_RATE_OPERATION: u64 = 1u64;
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
@@ -32,24 +46,29 @@ pub fn instantiate(
     env: Env,
     _info: MessageInfo,
     msg: InstantiateMsg,
-) -> StdResult<Response> {
-    store_config(
-        deps.storage,
-        &Config {
-            owner_addr: deps.api.addr_canonicalize(&msg.owner_addr)?,
-            oracle_contract: deps.api.addr_canonicalize(&msg.oracle_contract)?,
-            market_contract: deps.api.addr_canonicalize(&msg.market_contract)?,
-            liquidation_contract: deps.api.addr_canonicalize(&msg.liquidation_contract)?,
-            collector_contract: deps.api.addr_canonicalize(&msg.collector_contract)?,
-            stable_denom: msg.stable_denom,
-            epoch_period: msg.epoch_period,
-            threshold_deposit_rate: msg.threshold_deposit_rate,
-            target_deposit_rate: msg.target_deposit_rate,
-            buffer_distribution_factor: msg.buffer_distribution_factor,
-            anc_purchase_factor: msg.anc_purchase_factor,
-            price_timeframe: msg.price_timeframe,
-        },
-    )?;
+) -> Result<Response, ContractError> {
+    let config = Config {
+        owner_addr: deps.api.addr_canonicalize(&msg.owner_addr)?,
+        oracle_contract: deps.api.addr_canonicalize(&msg.oracle_contract)?,
+        market_contract: deps.api.addr_canonicalize(&msg.market_contract)?,
+        liquidation_contract: deps.api.addr_canonicalize(&msg.liquidation_contract)?,
+        collector_contract: deps.api.addr_canonicalize(&msg.collector_contract)?,
+        stable_denom: msg.stable_denom,
+        epoch_period: msg.epoch_period,
+        threshold_deposit_rate: msg.threshold_deposit_rate,
+        target_deposit_rate: msg.target_deposit_rate,
+        buffer_distribution_factor: msg.buffer_distribution_factor,
+        anc_purchase_factor: msg.anc_purchase_factor,
+        price_timeframe: msg.price_timeframe,
+        max_ltv: default_max_ltv(),
+        guardian: None,
+        paused: false,
+        executor_incentive: Uint128::zero(),
+        max_buffer_distribution: None,
+        custody_batch_size: default_custody_batch_size(),
+    };
+    assert_valid_config(&config)?;
+    store_config(deps.storage, &config)?;
 
     store_epoch_state(
         deps.storage,
@@ -76,6 +95,7 @@ pub fn execute(
         ExecuteMsg::UpdateConfig {
             owner_addr,
             oracle_contract,
+            market_contract,
             liquidation_contract,
             threshold_deposit_rate,
             target_deposit_rate,
@@ -83,13 +103,20 @@ pub fn execute(
             anc_purchase_factor,
             epoch_period,
             price_timeframe,
+            max_ltv,
+            guardian,
+            executor_incentive,
+            max_buffer_distribution,
+            custody_batch_size,
         } => {
             let api = deps.api;
             update_config(
                 deps,
+                env,
                 info,
                 optional_addr_validate(api, owner_addr)?,
                 optional_addr_validate(api, oracle_contract)?,
+                optional_addr_validate(api, market_contract)?,
                 optional_addr_validate(api, liquidation_contract)?,
                 threshold_deposit_rate,
                 target_deposit_rate,
@@ -97,6 +124,11 @@ pub fn execute(
                 anc_purchase_factor,
                 epoch_period,
                 price_timeframe,
+                max_ltv,
+                optional_addr_validate(api, guardian)?,
+                executor_incentive,
+                max_buffer_distribution,
+                custody_batch_size,
             )
         }
         ExecuteMsg::Whitelist {
@@ -105,6 +137,7 @@ pub fn execute(
             collateral_token,
             custody_contract,
             max_ltv,
+            decimals,
         } => {
             let api = deps.api;
             register_whitelist(
@@ -115,12 +148,14 @@ pub fn execute(
                 api.addr_validate(&collateral_token)?,
                 api.addr_validate(&custody_contract)?,
                 max_ltv,
+                decimals.unwrap_or(STABLE_DECIMALS),
             )
         }
         ExecuteMsg::UpdateWhitelist {
             collateral_token,
             custody_contract,
             max_ltv,
+            decimals,
         } => {
             let api = deps.api;
             update_whitelist(
@@ -129,16 +164,50 @@ pub fn execute(
                 api.addr_validate(&collateral_token)?,
                 optional_addr_validate(api, custody_contract)?,
                 max_ltv,
+                decimals,
+            )
+        }
+        ExecuteMsg::PauseWhitelist { collateral_token } => {
+            let api = deps.api;
+            update_whitelist_paused(deps, info, api.addr_validate(&collateral_token)?, true)
+        }
+        ExecuteMsg::UnpauseWhitelist { collateral_token } => {
+            let api = deps.api;
+            update_whitelist_paused(deps, info, api.addr_validate(&collateral_token)?, false)
+        }
+        ExecuteMsg::MigrateCustody {
+            collateral_token,
+            new_custody_contract,
+        } => {
+            let api = deps.api;
+            migrate_custody(
+                deps,
+                info,
+                api.addr_validate(&collateral_token)?,
+                api.addr_validate(&new_custody_contract)?,
             )
         }
-        ExecuteMsg::ExecuteEpochOperations {} => execute_epoch_operations(deps, env),
+        ExecuteMsg::Pause {} => update_paused(deps, info, true),
+        ExecuteMsg::Unpause {} => update_paused(deps, info, false),
+        ExecuteMsg::ExecuteEpochOperations {} => {
+            assert_not_paused(deps.as_ref())?;
+            execute_epoch_operations(deps, env, info)
+        }
         ExecuteMsg::UpdateEpochState {
             interest_buffer,
             distributed_interest,
         } => update_epoch_state(deps, env, info, interest_buffer, distributed_interest),
-        ExecuteMsg::LockCollateral { collaterals } => lock_collateral(deps, info, collaterals),
+        ExecuteMsg::LockCollateral { collaterals } => {
+            assert_not_paused(deps.as_ref())?;
+            lock_collateral(deps, info, collaterals)
+        }
         ExecuteMsg::UnlockCollateral { collaterals } => {
-            unlock_collateral(deps, env, info, collaterals)
+            assert_not_paused(deps.as_ref())?;
+            unlock_collateral(deps, env, info, collaterals, false)
+        }
+        ExecuteMsg::UnlockAndWithdrawCollateral { collaterals } => {
+            assert_not_paused(deps.as_ref())?;
+            unlock_collateral(deps, env, info, collaterals, true)
         }
         ExecuteMsg::LiquidateCollateral { borrower } => {
             let api = deps.api;
@@ -150,9 +219,11 @@ pub fn execute(
 #[allow(clippy::too_many_arguments)]
 pub fn update_config(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     owner_addr: Option<Addr>,
     oracle_contract: Option<Addr>,
+    market_contract: Option<Addr>,
     liquidation_contract: Option<Addr>,
     threshold_deposit_rate: Option<Decimal256>,
     target_deposit_rate: Option<Decimal256>,
@@ -160,6 +231,11 @@ pub fn update_config(
     anc_purchase_factor: Option<Decimal256>,
     epoch_period: Option<u64>,
     price_timeframe: Option<u64>,
+    max_ltv: Option<Decimal256>,
+    guardian: Option<Addr>,
+    executor_incentive: Option<Uint128>,
+    max_buffer_distribution: Option<Uint256>,
+    custody_batch_size: Option<u32>,
 ) -> Result<Response, ContractError> {
     let mut config: Config = read_config(deps.storage)?;
 
@@ -171,11 +247,31 @@ pub fn update_config(
         config.owner_addr = deps.api.addr_canonicalize(&owner_addr.to_string())?;
     }
 
+    let mut attributes = vec![attr("action", "update_config")];
     if let Some(oracle_contract) = oracle_contract {
+        attributes.push(attr(
+            "prev_oracle_contract",
+            deps.api.addr_humanize(&config.oracle_contract)?,
+        ));
+        attributes.push(attr("oracle_contract", oracle_contract.as_str()));
         config.oracle_contract = deps.api.addr_canonicalize(&oracle_contract.to_string())?;
     }
 
+    if let Some(market_contract) = &market_contract {
+        attributes.push(attr(
+            "prev_market_contract",
+            deps.api.addr_humanize(&config.market_contract)?,
+        ));
+        attributes.push(attr("market_contract", market_contract.as_str()));
+        config.market_contract = deps.api.addr_canonicalize(market_contract.as_str())?;
+    }
     if let Some(liquidation_contract) = liquidation_contract {
+        // outstanding bids stay in the previous contract, so log the switch
+        attributes.push(attr(
+            "prev_liquidation_contract",
+            deps.api.addr_humanize(&config.liquidation_contract)?,
+        ));
+        attributes.push(attr("liquidation_contract", liquidation_contract.as_str()));
         config.liquidation_contract = deps
             .api
             .addr_canonicalize(&liquidation_contract.to_string())?;
@@ -205,11 +301,72 @@ pub fn update_config(
         config.price_timeframe = price_timeframe;
     }
 
+    if let Some(max_ltv) = max_ltv {
+        config.max_ltv = max_ltv;
+    }
+
+    if let Some(guardian) = guardian {
+        config.guardian = Some(deps.api.addr_canonicalize(guardian.as_str())?);
+    }
+
+    if let Some(executor_incentive) = executor_incentive {
+        config.executor_incentive = executor_incentive;
+    }
+
+    if let Some(max_buffer_distribution) = max_buffer_distribution {
+        config.max_buffer_distribution = Some(max_buffer_distribution);
+    }
+
+    if let Some(custody_batch_size) = custody_batch_size {
+        config.custody_batch_size = custody_batch_size;
+    }
+
+    assert_valid_config(&config)?;
+
+    // The exchange rate of the previous market is meaningless for the new one,
+    // so the deposit rate of the next epoch is measured from here
+    if let Some(market_contract) = market_contract {
+        let market_epoch_state: MarketEpochStateResponse =
+            query_epoch_state(deps.as_ref(), market_contract, env.block.height, None)?;
+        let mut epoch_state: EpochState = read_epoch_state(deps.storage)?;
+        epoch_state.last_executed_height = env.block.height;
+        epoch_state.prev_aterra_supply = market_epoch_state.aterra_supply;
+        epoch_state.prev_exchange_rate = market_epoch_state.exchange_rate;
+        store_epoch_state(deps.storage, &epoch_state)?;
+    }
+
     store_config(deps.storage, &config)?;
 
-    Ok(Response::new().add_attributes(vec![attr("action", "update_config")]))
+    Ok(Response::new().add_attributes(attributes))
 }
 
+fn assert_valid_config(config: &Config) -> Result<(), ContractError> {
+    if config.epoch_period == 0 {
+        return Err(ContractError::InvalidEpochPeriod {});
+    }
+
+    if config.custody_batch_size == 0 || config.custody_batch_size > MAX_LIMIT {
+        return Err(ContractError::InvalidCustodyBatchSize(MAX_LIMIT));
+    }
+
+    assert_fraction("threshold_deposit_rate", config.threshold_deposit_rate)?;
+    assert_fraction("target_deposit_rate", config.target_deposit_rate)?;
+    assert_max(
+        "threshold_deposit_rate",
+        config.threshold_deposit_rate,
+        config.target_deposit_rate,
+    )?;
+    assert_fraction(
+        "buffer_distribution_factor",
+        config.buffer_distribution_factor,
+    )?;
+    assert_fraction("anc_purchase_factor", config.anc_purchase_factor)?;
+    assert_positive_fraction("max_ltv", config.max_ltv)?;
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn register_whitelist(
     deps: DepsMut,
     info: MessageInfo,
@@ -218,12 +375,16 @@ pub fn register_whitelist(
     collateral_token: Addr,
     custody_contract: Addr,
     max_ltv: Decimal256,
+    decimals: u8,
 ) -> Result<Response, ContractError> {
     let config: Config = read_config(deps.storage)?;
     if deps.api.addr_canonicalize(info.sender.as_str())? != config.owner_addr {
         return Err(ContractError::Unauthorized {});
     }
 
+    assert_positive_fraction("max_ltv", max_ltv)?;
+    assert_max("max_ltv", max_ltv, config.max_ltv)?;
+
     let collateral_token_raw = deps.api.addr_canonicalize(collateral_token.as_str())?;
     if read_whitelist_elem(deps.storage, &collateral_token_raw).is_ok() {
         return Err(ContractError::TokenAlreadyRegistered {});
@@ -237,6 +398,8 @@ pub fn register_whitelist(
             symbol: symbol.to_string(),
             custody_contract: deps.api.addr_canonicalize(custody_contract.as_str())?,
             max_ltv,
+            decimals,
+            paused: false,
         },
     )?;
 
@@ -247,6 +410,7 @@ pub fn register_whitelist(
         attr("collateral_token", collateral_token),
         attr("custody_contract", custody_contract),
         attr("LTV", max_ltv.to_string()),
+        attr("decimals", decimals.to_string()),
     ]))
 }
 
@@ -256,6 +420,7 @@ pub fn update_whitelist(
     collateral_token: Addr,
     custody_contract: Option<Addr>,
     max_ltv: Option<Decimal256>,
+    decimals: Option<u8>,
 ) -> Result<Response, ContractError> {
     let config: Config = read_config(deps.storage)?;
     if deps.api.addr_canonicalize(info.sender.as_str())? != config.owner_addr {
@@ -265,15 +430,22 @@ pub fn update_whitelist(
     let collateral_token_raw = deps.api.addr_canonicalize(collateral_token.as_str())?;
     let mut whitelist_elem: WhitelistElem =
         read_whitelist_elem(deps.storage, &collateral_token_raw)?;
+    let prev_whitelist_elem = whitelist_elem.clone();
 
     if let Some(custody_contract) = custody_contract {
         whitelist_elem.custody_contract = deps.api.addr_canonicalize(custody_contract.as_str())?;
     }
 
     if let Some(max_ltv) = max_ltv {
+        assert_positive_fraction("max_ltv", max_ltv)?;
+        assert_max("max_ltv", max_ltv, config.max_ltv)?;
         whitelist_elem.max_ltv = max_ltv;
     }
 
+    if let Some(decimals) = decimals {
+        whitelist_elem.decimals = decimals;
+    }
+
     store_whitelist_elem(deps.storage, &collateral_token_raw, &whitelist_elem)?;
 
     Ok(Response::new().add_attributes(vec![
@@ -284,33 +456,179 @@ pub fn update_whitelist(
             deps.api.addr_humanize(&whitelist_elem.custody_contract)?,
         ),
         attr("LTV", whitelist_elem.max_ltv.to_string()),
+        attr("decimals", whitelist_elem.decimals.to_string()),
+        attr(
+            "prev_custody_contract",
+            deps.api
+                .addr_humanize(&prev_whitelist_elem.custody_contract)?,
+        ),
+        attr("prev_LTV", prev_whitelist_elem.max_ltv.to_string()),
+        attr("prev_decimals", prev_whitelist_elem.decimals.to_string()),
+    ]))
+}
+
+pub fn update_whitelist_paused(
+    deps: DepsMut,
+    info: MessageInfo,
+    collateral_token: Addr,
+    paused: bool,
+) -> Result<Response, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+    if deps.api.addr_canonicalize(info.sender.as_str())? != config.owner_addr {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let collateral_token_raw = deps.api.addr_canonicalize(collateral_token.as_str())?;
+    let mut whitelist_elem: WhitelistElem =
+        read_whitelist_elem(deps.storage, &collateral_token_raw)?;
+
+    whitelist_elem.paused = paused;
+    store_whitelist_elem(deps.storage, &collateral_token_raw, &whitelist_elem)?;
+
+    let action = if paused {
+        "pause_whitelist"
+    } else {
+        "unpause_whitelist"
+    };
+    Ok(Response::new().add_attributes(vec![
+        attr("action", action),
+        attr("collateral_token", collateral_token),
+    ]))
+}
+
+pub fn migrate_custody(
+    deps: DepsMut,
+    info: MessageInfo,
+    collateral_token: Addr,
+    new_custody_contract: Addr,
+) -> Result<Response, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+    if deps.api.addr_canonicalize(info.sender.as_str())? != config.owner_addr {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let collateral_token_raw = deps.api.addr_canonicalize(collateral_token.as_str())?;
+    let mut whitelist_elem: WhitelistElem =
+        read_whitelist_elem(deps.storage, &collateral_token_raw)?;
+    let prev_custody_contract = deps.api.addr_humanize(&whitelist_elem.custody_contract)?;
+
+    whitelist_elem.custody_contract = deps.api.addr_canonicalize(new_custody_contract.as_str())?;
+    store_whitelist_elem(deps.storage, &collateral_token_raw, &whitelist_elem)?;
+
+    // the new custody has to expect the balances before they are sent
+    Ok(Response::new()
+        .add_messages(vec![
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: new_custody_contract.to_string(),
+                funds: vec![],
+                msg: to_binary(&CustodyExecuteMsg::AcceptMigration {
+                    old_custody: prev_custody_contract.to_string(),
+                })?,
+            }),
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: prev_custody_contract.to_string(),
+                funds: vec![],
+                msg: to_binary(&CustodyExecuteMsg::MigrateBalances {
+                    new_custody: new_custody_contract.to_string(),
+                })?,
+            }),
+        ])
+        .add_attributes(vec![
+            attr("action", "migrate_custody"),
+            attr("collateral_token", collateral_token),
+            attr("prev_custody_contract", prev_custody_contract),
+            attr("custody_contract", new_custody_contract),
+        ]))
+}
+
+pub fn update_paused(
+    deps: DepsMut,
+    info: MessageInfo,
+    paused: bool,
+) -> Result<Response, ContractError> {
+    let mut config: Config = read_config(deps.storage)?;
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+
+    // the guardian can only pause, resuming is left to the owner
+    let is_guardian = paused && config.guardian.as_ref() == Some(&sender_raw);
+    if sender_raw != config.owner_addr && !is_guardian {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    config.paused = paused;
+    store_config(deps.storage, &config)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", if paused { "pause" } else { "unpause" }),
+        attr("sender", info.sender),
     ]))
 }
 
-pub fn execute_epoch_operations(deps: DepsMut, env: Env) -> Result<Response, ContractError> {
+fn assert_not_paused(deps: Deps) -> Result<(), ContractError> {
+    if read_config(deps.storage)?.paused {
+        return Err(ContractError::ContractPaused {});
+    }
+
+    Ok(())
+}
+
+pub fn execute_epoch_operations(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
     let config: Config = read_config(deps.storage)?;
     let state: EpochState = read_epoch_state(deps.storage)?;
-    if env.block.height < state.last_executed_height + config.epoch_period {
-        return Err(ContractError::EpochNotPassed(state.last_executed_height));
+    let next_epoch_height = state.last_executed_height + config.epoch_period;
+    if env.block.height < next_epoch_height {
+        return Err(MoneyMarketError::EpochNotElapsed {
+            remaining: next_epoch_height - env.block.height,
+        }
+        .into());
     }
 
+    // Continue the custody rewards distribution where the previous call
+    // of this epoch stopped; the epoch stays open while custodies are left
+    let start_after = read_distribution_cursor(deps.storage)?;
+    let whitelist: Vec<WhitelistResponseElem> = read_whitelist(
+        deps.as_ref(),
+        start_after,
+        Some(config.custody_batch_size),
+        None,
+    )?;
+    let rewards_messages: Vec<CosmosMsg> =
+        distribute_rewards_messages(deps.as_ref(), &config, &whitelist)?;
+
+    if let Some(last) = whitelist.last() {
+        let cursor = deps.api.addr_canonicalize(&last.collateral_token)?;
+        if whitelist.len() == config.custody_batch_size as usize
+            && !read_whitelist(deps.as_ref(), Some(cursor.clone()), Some(1), None)?.is_empty()
+        {
+            store_distribution_cursor(deps.storage, Some(&cursor))?;
+            return Ok(Response::new()
+                .add_messages(rewards_messages)
+                .add_attributes(vec![
+                    attr("action", "distribute_custody_rewards"),
+                    attr("last_collateral_token", last.collateral_token.clone()),
+                ]));
+        }
+    }
+    store_distribution_cursor(deps.storage, None)?;
+
     // # of blocks from the last executed height
     let blocks = Uint256::from(env.block.height - state.last_executed_height);
 
     // Compute next epoch state
     let market_contract = deps.api.addr_humanize(&config.market_contract)?;
-    let epoch_state: EpochStateResponse = query_epoch_state(
+    let epoch_state: MarketEpochStateResponse = query_epoch_state(
         deps.as_ref(),
         market_contract.clone(),
         env.block.height,
         None,
     )?;
 
-    // effective_deposit_rate = cur_exchange_rate / prev_exchange_rate
-    // deposit_rate = (effective_deposit_rate - 1) / blocks
-    let effective_deposit_rate = epoch_state.exchange_rate / state.prev_exchange_rate;
     let deposit_rate =
-        (effective_deposit_rate - Decimal256::one()) / Decimal256::from_uint256(blocks);
+        compute_deposit_rate(epoch_state.exchange_rate, state.prev_exchange_rate, blocks);
 
     let mut messages: Vec<CosmosMsg> = vec![];
     let mut interest_buffer = query_balance(
@@ -322,75 +640,111 @@ pub fn execute_epoch_operations(deps: DepsMut, env: Env) -> Result<Response, Con
     // Send accrued_buffer * config.anc_purchase_factor amount stable token to collector
     let accrued_buffer = interest_buffer - state.prev_interest_buffer;
     let anc_purchase_amount = accrued_buffer * config.anc_purchase_factor;
-    if !anc_purchase_amount.is_zero() {
+    let anc_purchase_coins = deduct_tax_vec(
+        deps.as_ref(),
+        vec![Coin {
+            denom: config.stable_denom.to_string(),
+            amount: anc_purchase_amount.into(),
+        }],
+    )?;
+    if !anc_purchase_coins.is_empty() {
         messages.push(CosmosMsg::Bank(BankMsg::Send {
             to_address: deps
                 .api
                 .addr_humanize(&config.collector_contract)?
                 .to_string(),
-            amount: vec![deduct_tax(
-                deps.as_ref(),
-                Coin {
-                    denom: config.stable_denom.to_string(),
-                    amount: anc_purchase_amount.into(),
-                },
-            )?],
+            amount: anc_purchase_coins,
         }));
     }
 
     // Deduct anc_purchase_amount from the interest_buffer
     interest_buffer = interest_buffer - anc_purchase_amount;
 
+    // Pay the executor before the buffer distribution, so the buffer
+    // is never overspent; skipped when the buffer cannot cover it
+    let mut executor_incentive: Uint128 = Uint128::zero();
+    if !config.executor_incentive.is_zero()
+        && interest_buffer >= Uint256::from(config.executor_incentive)
+    {
+        executor_incentive = config.executor_incentive;
+        interest_buffer -= Uint256::from(executor_incentive);
+
+        let incentive_coins = deduct_tax_vec(
+            deps.as_ref(),
+            vec![Coin {
+                denom: config.stable_denom.to_string(),
+                amount: executor_incentive,
+            }],
+        )?;
+        if !incentive_coins.is_empty() {
+            messages.push(CosmosMsg::Bank(BankMsg::Send {
+                to_address: info.sender.to_string(),
+                amount: incentive_coins,
+            }));
+        }
+    }
+
+    // The subsidy cannot imply a deposit rate
+    // outside the distribution model bounds
+    let distribution_model = query_distribution_model(deps.as_ref(), market_contract.clone())?;
+    let deposit_rate_bounds = query_deposit_rate_bounds(deps.as_ref(), distribution_model)?;
+    let subsidized_deposit_rate =
+        if config.threshold_deposit_rate > deposit_rate_bounds.deposit_rate_ceiling {
+            deposit_rate_bounds.deposit_rate_ceiling
+        } else if config.threshold_deposit_rate < deposit_rate_bounds.deposit_rate_floor {
+            deposit_rate_bounds.deposit_rate_floor
+        } else {
+            config.threshold_deposit_rate
+        };
+
     // Distribute Interest Buffer to depositor
-    // Only executed when deposit rate < threshold_deposit_rate
+    // Only executed when deposit rate < subsidized_deposit_rate
+    // distributed_interest is the amount the market receives after tax
     let mut distributed_interest: Uint256 = Uint256::zero();
-    if deposit_rate < config.threshold_deposit_rate {
+    let mut distributed_interest_gross: Uint256 = Uint256::zero();
+    if deposit_rate < subsidized_deposit_rate {
         // missing_deposit_rate(_per_block)
-        let missing_deposit_rate = config.threshold_deposit_rate - deposit_rate;
+        let missing_deposit_rate = subsidized_deposit_rate - deposit_rate;
         let prev_deposits = state.prev_aterra_supply * state.prev_exchange_rate;
 
         // missing_deposits = prev_deposits * missing_deposit_rate(_per_block) * blocks
         let missing_deposits = prev_deposits * blocks * missing_deposit_rate;
-        let distribution_buffer = interest_buffer * config.buffer_distribution_factor;
+        let mut distribution_buffer = interest_buffer * config.buffer_distribution_factor;
+        if let Some(max_buffer_distribution) = config.max_buffer_distribution {
+            distribution_buffer = std::cmp::min(distribution_buffer, max_buffer_distribution);
+        }
 
         // When there was not enough deposits happens,
         // distribute interest to market contract
-        distributed_interest = std::cmp::min(missing_deposits, distribution_buffer);
-        interest_buffer = interest_buffer - distributed_interest;
+        distributed_interest_gross = std::cmp::min(missing_deposits, distribution_buffer);
+        interest_buffer = interest_buffer - distributed_interest_gross;
 
-        if !distributed_interest.is_zero() {
+        if !distributed_interest_gross.is_zero() {
             // deduct tax
-            distributed_interest = Uint256::from(
-                deduct_tax(
-                    deps.as_ref(),
-                    Coin {
-                        denom: config.stable_denom.to_string(),
-                        amount: distributed_interest.into(),
-                    },
-                )?
-                .amount,
-            );
+            let distributed_coins = deduct_tax_vec(
+                deps.as_ref(),
+                vec![Coin {
+                    denom: config.stable_denom.to_string(),
+                    amount: distributed_interest_gross.into(),
+                }],
+            )?;
+            distributed_interest = distributed_coins
+                .first()
+                .map(|coin| Uint256::from(coin.amount))
+                .unwrap_or_else(Uint256::zero);
 
             // Send some portion of interest buffer to Market contract
-            messages.push(CosmosMsg::Bank(BankMsg::Send {
-                to_address: market_contract.to_string(),
-                amount: vec![Coin {
-                    denom: config.stable_denom,
-                    amount: distributed_interest.into(),
-                }],
-            }));
+            if !distributed_coins.is_empty() {
+                messages.push(CosmosMsg::Bank(BankMsg::Send {
+                    to_address: market_contract.to_string(),
+                    amount: distributed_coins,
+                }));
+            }
         }
     }
 
-    // Execute DistributeRewards
-    let whitelist: Vec<WhitelistResponseElem> = read_whitelist(deps.as_ref(), None, None)?;
-    for elem in whitelist.iter() {
-        messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
-            contract_addr: elem.custody_contract.clone(),
-            funds: vec![],
-            msg: to_binary(&CustodyExecuteMsg::DistributeRewards {})?,
-        }));
-    }
+    // Buffer transfers go ahead of the rewards distribution
+    messages.extend(rewards_messages);
 
     // TODO: Should this become a reply? If so which SubMsg to make reply_on?
     // Execute store epoch state operation
@@ -409,10 +763,65 @@ pub fn execute_epoch_operations(deps: DepsMut, env: Env) -> Result<Response, Con
         attr("exchange_rate", epoch_state.exchange_rate.to_string()),
         attr("aterra_supply", epoch_state.aterra_supply),
         attr("distributed_interest", distributed_interest),
+        attr("distributed_interest_gross", distributed_interest_gross),
+        attr(
+            "distributed_interest_tax",
+            distributed_interest_gross - distributed_interest,
+        ),
         attr("anc_purchase_amount", anc_purchase_amount),
+        attr("executor", info.sender),
+        attr("executor_incentive", executor_incentive),
     ]))
 }
 
+// Execute DistributeRewards, except for the custodies of paused collaterals,
+// and pin the collateral prices used for this epoch
+fn distribute_rewards_messages(
+    deps: Deps,
+    config: &Config,
+    whitelist: &[WhitelistResponseElem],
+) -> StdResult<Vec<CosmosMsg>> {
+    let mut messages: Vec<CosmosMsg> = vec![];
+    for elem in whitelist.iter().filter(|elem| !elem.paused) {
+        messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: elem.custody_contract.clone(),
+            funds: vec![],
+            msg: to_binary(&CustodyExecuteMsg::DistributeRewards {})?,
+        }));
+    }
+
+    if !whitelist.is_empty() {
+        messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: deps.api.addr_humanize(&config.oracle_contract)?.to_string(),
+            funds: vec![],
+            msg: to_binary(&OracleExecuteMsg::SnapshotPrices {
+                assets: whitelist
+                    .iter()
+                    .map(|elem| elem.collateral_token.clone())
+                    .collect(),
+            })?,
+        }));
+    }
+
+    Ok(messages)
+}
+
+// effective_deposit_rate = cur_exchange_rate / prev_exchange_rate
+// deposit_rate = (effective_deposit_rate - 1) / blocks
+// a shrinking exchange rate is recorded as a zero deposit rate
+fn compute_deposit_rate(
+    exchange_rate: Decimal256,
+    prev_exchange_rate: Decimal256,
+    blocks: Uint256,
+) -> Decimal256 {
+    let effective_deposit_rate = exchange_rate / prev_exchange_rate;
+    if effective_deposit_rate > Decimal256::one() {
+        (effective_deposit_rate - Decimal256::one()) / Decimal256::from_uint256(blocks)
+    } else {
+        Decimal256::zero()
+    }
+}
+
 pub fn update_epoch_state(
     deps: DepsMut,
     env: Env,
@@ -433,19 +842,30 @@ pub fn update_epoch_state(
 
     // Compute next epoch state
     let market_contract = deps.api.addr_humanize(&config.market_contract)?;
-    let market_epoch_state: EpochStateResponse = query_epoch_state(
+    let market_epoch_state: MarketEpochStateResponse = query_epoch_state(
         deps.as_ref(),
         market_contract.clone(),
         env.block.height,
         Some(distributed_interest),
     )?;
 
-    // effective_deposit_rate = cur_exchange_rate / prev_exchange_rate
-    // deposit_rate = (effective_deposit_rate - 1) / blocks
-    let effective_deposit_rate =
-        market_epoch_state.exchange_rate / overseer_epoch_state.prev_exchange_rate;
-    let deposit_rate =
-        (effective_deposit_rate - Decimal256::one()) / Decimal256::from_uint256(blocks);
+    let deposit_rate = compute_deposit_rate(
+        market_epoch_state.exchange_rate,
+        overseer_epoch_state.prev_exchange_rate,
+        blocks,
+    );
+
+    // Record the emission rate the market accepts for the governance history
+    let distribution_model = query_distribution_model(deps.as_ref(), market_contract.clone())?;
+    let anc_emission_rate = query_anc_emission_rate(
+        deps.as_ref(),
+        market_contract.clone(),
+        distribution_model.clone(),
+        deposit_rate,
+        config.target_deposit_rate,
+        config.threshold_deposit_rate,
+    )?
+    .emission_rate;
 
     // store updated epoch state
     store_epoch_state(
@@ -470,6 +890,21 @@ pub fn update_epoch_state(
                 distributed_interest,
             })?,
         }))
+        // the emission history is informational, so a distribution
+        // model that rejects it does not revert the epoch
+        .add_submessage(SubMsg::reply_on_error(
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: distribution_model.to_string(),
+                funds: vec![],
+                msg: to_binary(&DistributionExecuteMsg::UpdateEmissionRate {
+                    emission_rate: anc_emission_rate,
+                    deposit_rate,
+                    target_deposit_rate: config.target_deposit_rate,
+                    threshold_deposit_rate: config.threshold_deposit_rate,
+                })?,
+            }),
+            UPDATE_EMISSION_RATE_OPERATION,
+        ))
         .add_attributes(vec![
             attr("action", "update_epoch_state"),
             attr("deposit_rate", deposit_rate.to_string()),
@@ -479,32 +914,60 @@ pub fn update_epoch_state(
                 market_epoch_state.exchange_rate.to_string(),
             ),
             attr("interest_buffer", interest_buffer),
+            attr("anc_emission_rate", anc_emission_rate.to_string()),
         ]))
 }
 
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(_deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
+    match msg.id {
+        // UpdateEmissionRate failure callback
+        UPDATE_EMISSION_RATE_OPERATION => Ok(Response::new().add_attributes(vec![
+            attr("action", "update_emission_rate_failed"),
+            attr("error", msg.result.into_result().err().unwrap_or_default()),
+        ])),
+        _ => Err(ContractError::InvalidReplyId {}),
+    }
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::Config {} => to_binary(&query_config(deps)?),
         QueryMsg::EpochState {} => to_binary(&query_state(deps)?),
+        QueryMsg::DistributionParams {} => to_binary(&query_distribution_params(deps)?),
         QueryMsg::Whitelist {
             collateral_token,
             start_after,
             limit,
+            order_by,
         } => to_binary(&query_whitelist(
             deps,
             optional_addr_validate(deps.api, collateral_token)?,
             optional_addr_validate(deps.api, start_after)?,
             limit,
+            order_by,
         )?),
         QueryMsg::Collaterals { borrower } => to_binary(&query_collaterals(
             deps,
             deps.api.addr_validate(&borrower)?,
         )?),
-        QueryMsg::AllCollaterals { start_after, limit } => to_binary(&query_all_collaterals(
+        QueryMsg::CollateralsBatch { borrowers } => to_binary(&query_collaterals_batch(
+            deps,
+            borrowers
+                .iter()
+                .map(|borrower| deps.api.addr_validate(borrower))
+                .collect::<StdResult<Vec<Addr>>>()?,
+        )?),
+        QueryMsg::AllCollaterals {
+            start_after,
+            limit,
+            order_by,
+        } => to_binary(&query_all_collaterals(
             deps,
             optional_addr_validate(deps.api, start_after)?,
             limit,
+            order_by,
         )?),
         QueryMsg::BorrowLimit {
             borrower,
@@ -514,6 +977,21 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
             deps.api.addr_validate(&borrower)?,
             block_time,
         )?),
+        QueryMsg::BorrowLimits {
+            borrowers,
+            block_time,
+        } => to_binary(&query_borrow_limits(
+            deps,
+            borrowers
+                .iter()
+                .map(|borrower| deps.api.addr_validate(borrower))
+                .collect::<StdResult<Vec<Addr>>>()?,
+            block_time,
+        )?),
+        QueryMsg::CollateralValue { borrower } => to_binary(&query_collateral_value(
+            deps,
+            deps.api.addr_validate(&borrower)?,
+        )?),
     }
 }
 
@@ -538,11 +1016,39 @@ pub fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
         buffer_distribution_factor: config.buffer_distribution_factor,
         anc_purchase_factor: config.anc_purchase_factor,
         price_timeframe: config.price_timeframe,
+        max_ltv: config.max_ltv,
+        guardian: config
+            .guardian
+            .map(|guardian| deps.api.addr_humanize(&guardian))
+            .transpose()?
+            .map(|guardian| guardian.to_string()),
+        paused: config.paused,
+        executor_incentive: config.executor_incentive,
+        max_buffer_distribution: config.max_buffer_distribution,
+        custody_batch_size: config.custody_batch_size,
+    })
+}
+
+pub fn query_state(deps: Deps) -> StdResult<EpochStateResponse> {
+    let state: EpochState = read_epoch_state(deps.storage)?;
+    Ok(EpochStateResponse {
+        deposit_rate: state.deposit_rate,
+        prev_aterra_supply: state.prev_aterra_supply,
+        prev_exchange_rate: state.prev_exchange_rate,
+        prev_interest_buffer: state.prev_interest_buffer,
+        last_executed_height: state.last_executed_height,
     })
 }
 
-pub fn query_state(deps: Deps) -> StdResult<EpochState> {
-    read_epoch_state(deps.storage)
+pub fn query_distribution_params(deps: Deps) -> StdResult<DistributionParamsResponse> {
+    let config: Config = read_config(deps.storage)?;
+    let state: EpochState = read_epoch_state(deps.storage)?;
+    Ok(DistributionParamsResponse {
+        target_deposit_rate: config.target_deposit_rate,
+        threshold_deposit_rate: config.threshold_deposit_rate,
+        buffer_distribution_factor: config.buffer_distribution_factor,
+        deposit_rate: state.deposit_rate,
+    })
 }
 
 pub fn query_whitelist(
@@ -550,6 +1056,7 @@ pub fn query_whitelist(
     collateral_token: Option<Addr>,
     start_after: Option<Addr>,
     limit: Option<u32>,
+    order_by: Option<OrderBy>,
 ) -> StdResult<WhitelistResponse> {
     if let Some(collateral_token) = collateral_token {
         let whitelist_elem: WhitelistElem = read_whitelist_elem(
@@ -566,6 +1073,8 @@ pub fn query_whitelist(
                     .addr_humanize(&whitelist_elem.custody_contract)?
                     .to_string(),
                 collateral_token: collateral_token.to_string(),
+                decimals: whitelist_elem.decimals,
+                paused: whitelist_elem.paused,
             }],
         })
     } else {
@@ -575,7 +1084,8 @@ pub fn query_whitelist(
             None
         };
 
-        let whitelist: Vec<WhitelistResponseElem> = read_whitelist(deps, start_after, limit)?;
+        let whitelist: Vec<WhitelistResponseElem> =
+            read_whitelist(deps, start_after, limit, order_by)?;
         Ok(WhitelistResponse { elems: whitelist })
     }
 }
@@ -1,4 +1,5 @@
 use cosmwasm_std::{OverflowError, StdError};
+use moneymarket::error::MoneyMarketError;
 use thiserror::Error;
 
 #[derive(Error, Debug, PartialEq)]
@@ -9,21 +10,39 @@ pub enum ContractError {
     #[error("{0}")]
     OverflowError(#[from] OverflowError),
 
+    #[error("{0}")]
+    MoneyMarket(#[from] MoneyMarketError),
+
     #[error("Unauthorized")]
     Unauthorized {},
 
+    #[error("Contract is paused")]
+    ContractPaused {},
+
     #[error("Cannot liquidate safely collateralized loan")]
     CannotLiquidateSafeLoan {},
 
-    #[error("An epoch has not passed yet; last executed height: {0}")]
-    EpochNotPassed(u64),
+    #[error("Epoch period must be greater than 0")]
+    InvalidEpochPeriod {},
+
+    #[error("Custody batch size must be between 1 and {0}")]
+    InvalidCustodyBatchSize(u32),
 
     #[error("Token is already registered as collateral")]
     TokenAlreadyRegistered {},
 
-    #[error("Unlock amount cannot exceed locked amount")]
-    UnlockExceedsLocked {},
+    #[error("Collateral does not accept new locks: {0}")]
+    CollateralPaused(String),
+
+    #[error("Collateral amount must be greater than 0: {0}")]
+    ZeroCollateralAmount(String),
+
+    #[error("Collateral is listed more than once: {0}")]
+    DuplicateCollateral(String),
+
+    #[error("Invalid reply ID")]
+    InvalidReplyId {},
 
-    #[error("Unlock amount too high; Loan liability becomes greater than borrow limit: {0}")]
-    UnlockTooLarge(u128),
+    #[error("Native funds are not accepted when locking collaterals")]
+    FundsNotAccepted {},
 }
@@ -1,6 +1,7 @@
 pub mod collateral;
 pub mod contract;
 pub mod error;
+pub mod msg;
 pub mod querier;
 pub mod state;
 
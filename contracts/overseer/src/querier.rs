@@ -1,45 +1,69 @@
 use cosmwasm_bignumber::{Decimal256, Uint256};
 use cosmwasm_std::{to_binary, Addr, Deps, QueryRequest, StdResult, WasmQuery};
 
+use moneymarket::distribution_model::{
+    AncEmissionRateResponse, DepositRateBoundsResponse, QueryMsg as DistributionQueryMsg,
+};
 use moneymarket::liquidation::{LiquidationAmountResponse, QueryMsg as LiquidationQueryMsg};
-use moneymarket::market::{BorrowerInfoResponse, EpochStateResponse, QueryMsg as MarketQueryMsg};
+use moneymarket::market::{
+    ConfigResponse as MarketConfigResponse, QueryMsg as MarketQueryMsg,
+    StateResponse as MarketStateResponse,
+};
 use moneymarket::tokens::TokensHuman;
 
-pub fn query_epoch_state(
-    deps: Deps,
-    market_addr: Addr,
-    block_height: u64,
-    distributed_interest: Option<Uint256>,
-) -> StdResult<EpochStateResponse> {
-    let epoch_state: EpochStateResponse =
+/// Query the distribution model of the market contract
+pub fn query_distribution_model(deps: Deps, market_addr: Addr) -> StdResult<Addr> {
+    let market_config: MarketConfigResponse =
         deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
             contract_addr: market_addr.to_string(),
-            msg: to_binary(&MarketQueryMsg::EpochState {
-                block_height: Some(block_height),
-                distributed_interest,
-            })?,
+            msg: to_binary(&MarketQueryMsg::Config {})?,
+        }))?;
+
+    deps.api.addr_validate(&market_config.distribution_model)
+}
+
+/// Query the subsidized deposit rate bounds from the distribution model
+pub fn query_deposit_rate_bounds(
+    deps: Deps,
+    distribution_model: Addr,
+) -> StdResult<DepositRateBoundsResponse> {
+    let deposit_rate_bounds: DepositRateBoundsResponse =
+        deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+            contract_addr: distribution_model.to_string(),
+            msg: to_binary(&DistributionQueryMsg::DepositRateBounds {})?,
         }))?;
 
-    Ok(epoch_state)
+    Ok(deposit_rate_bounds)
 }
 
-/// Query borrow amount from the market contract
-pub fn query_borrower_info(
+/// Query the emission rate the market contract accepts at its
+/// epoch operations, computed from the same inputs
+pub fn query_anc_emission_rate(
     deps: Deps,
     market_addr: Addr,
-    borrower: Addr,
-    block_height: u64,
-) -> StdResult<BorrowerInfoResponse> {
-    let borrower_amount: BorrowerInfoResponse =
+    distribution_model: Addr,
+    deposit_rate: Decimal256,
+    target_deposit_rate: Decimal256,
+    threshold_deposit_rate: Decimal256,
+) -> StdResult<AncEmissionRateResponse> {
+    let market_state: MarketStateResponse =
         deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
             contract_addr: market_addr.to_string(),
-            msg: to_binary(&MarketQueryMsg::BorrowerInfo {
-                borrower: borrower.to_string(),
-                block_height: Some(block_height),
+            msg: to_binary(&MarketQueryMsg::State { block_height: None })?,
+        }))?;
+
+    let anc_emission_rate: AncEmissionRateResponse =
+        deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+            contract_addr: distribution_model.to_string(),
+            msg: to_binary(&DistributionQueryMsg::AncEmissionRate {
+                deposit_rate,
+                target_deposit_rate,
+                threshold_deposit_rate,
+                current_emission_rate: market_state.anc_emission_rate,
             })?,
         }))?;
 
-    Ok(borrower_amount)
+    Ok(anc_emission_rate)
 }
 
 #[allow(clippy::ptr_arg)]
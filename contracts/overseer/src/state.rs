@@ -2,14 +2,17 @@ use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use cosmwasm_bignumber::{Decimal256, Uint256};
-use cosmwasm_std::{CanonicalAddr, Deps, Order, StdError, StdResult, Storage};
+use cosmwasm_std::{CanonicalAddr, Deps, StdError, StdResult, Storage, Uint128};
 use cosmwasm_storage::{Bucket, ReadonlyBucket, ReadonlySingleton, Singleton};
 
+use moneymarket::math::STABLE_DECIMALS;
 use moneymarket::overseer::{CollateralsResponse, WhitelistResponseElem};
-use moneymarket::tokens::Tokens;
+use moneymarket::pagination::{calc_range_bounds, clamp_limit, OrderBy, DEFAULT_LIMIT, MAX_LIMIT};
+use moneymarket::tokens::{Tokens, TokensHuman, TokensToHuman};
 
 const KEY_CONFIG: &[u8] = b"config";
 const KEY_EPOCH_STATE: &[u8] = b"epoch_state";
+const KEY_DISTRIBUTION_CURSOR: &[u8] = b"distribution_cursor";
 
 const PREFIX_WHITELIST: &[u8] = b"whitelist";
 const PREFIX_COLLATERALS: &[u8] = b"collateral";
@@ -28,6 +31,32 @@ pub struct Config {
     pub buffer_distribution_factor: Decimal256,
     pub anc_purchase_factor: Decimal256,
     pub price_timeframe: u64,
+    /// Ceiling for the max_ltv of whitelisted collaterals
+    #[serde(default = "default_max_ltv")]
+    pub max_ltv: Decimal256,
+    /// Address allowed to pause the contract besides the owner
+    #[serde(default)]
+    pub guardian: Option<CanonicalAddr>,
+    #[serde(default)]
+    pub paused: bool,
+    /// Stable amount paid from the interest buffer to the epoch operations executor
+    #[serde(default)]
+    pub executor_incentive: Uint128,
+    /// Absolute cap of the interest buffer distributed in one epoch
+    #[serde(default)]
+    pub max_buffer_distribution: Option<Uint256>,
+    /// Custodies sent DistributeRewards per ExecuteEpochOperations call
+    #[serde(default = "default_custody_batch_size")]
+    pub custody_batch_size: u32,
+}
+
+pub fn default_max_ltv() -> Decimal256 {
+    Decimal256::percent(90)
+}
+
+/// The epoch operations used to reward a single whitelist page
+pub fn default_custody_batch_size() -> u32 {
+    DEFAULT_LIMIT
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -45,6 +74,16 @@ pub struct WhitelistElem {
     pub symbol: String,
     pub max_ltv: Decimal256,
     pub custody_contract: CanonicalAddr,
+    #[serde(default = "default_decimals")]
+    pub decimals: u8,
+    /// New locks are rejected while set
+    #[serde(default)]
+    pub paused: bool,
+}
+
+/// Whitelist entries stored before decimals were tracked are 6 decimal tokens
+fn default_decimals() -> u8 {
+    STABLE_DECIMALS
 }
 
 pub fn store_config(storage: &mut dyn Storage, data: &Config) -> StdResult<()> {
@@ -63,6 +102,27 @@ pub fn read_epoch_state(storage: &dyn Storage) -> StdResult<EpochState> {
     ReadonlySingleton::new(storage, KEY_EPOCH_STATE).load()
 }
 
+/// Last collateral token whose custody got DistributeRewards
+/// in the current epoch; None when no distribution is in progress
+pub fn store_distribution_cursor(
+    storage: &mut dyn Storage,
+    cursor: Option<&CanonicalAddr>,
+) -> StdResult<()> {
+    let mut cursor_singleton: Singleton<CanonicalAddr> =
+        Singleton::new(storage, KEY_DISTRIBUTION_CURSOR);
+    match cursor {
+        Some(cursor) => cursor_singleton.save(cursor),
+        None => {
+            cursor_singleton.remove();
+            Ok(())
+        }
+    }
+}
+
+pub fn read_distribution_cursor(storage: &dyn Storage) -> StdResult<Option<CanonicalAddr>> {
+    ReadonlySingleton::new(storage, KEY_DISTRIBUTION_CURSOR).may_load()
+}
+
 pub fn store_whitelist_elem(
     storage: &mut dyn Storage,
     collateral_token: &CanonicalAddr,
@@ -92,15 +152,18 @@ pub fn read_whitelist(
     deps: Deps,
     start_after: Option<CanonicalAddr>,
     limit: Option<u32>,
+    order_by: Option<OrderBy>,
 ) -> StdResult<Vec<WhitelistResponseElem>> {
     let whitelist_bucket: ReadonlyBucket<WhitelistElem> =
         ReadonlyBucket::new(deps.storage, PREFIX_WHITELIST);
 
-    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
-    let start = calc_range_start(start_after);
+    let limit = clamp_limit(limit, DEFAULT_LIMIT, MAX_LIMIT);
+    let order_by = order_by.unwrap_or(OrderBy::Ascending);
+    let (start, end) =
+        calc_range_bounds(start_after.map(|addr| addr.as_slice().to_vec()), order_by);
 
     whitelist_bucket
-        .range(start.as_deref(), None, Order::Ascending)
+        .range(start.as_deref(), end.as_deref(), order_by.into())
         .take(limit)
         .map(|elem| {
             let (k, v) = elem?;
@@ -112,6 +175,8 @@ pub fn read_whitelist(
                 collateral_token,
                 custody_contract,
                 max_ltv: v.max_ltv,
+                decimals: v.decimals,
+                paused: v.paused,
             })
         })
         .collect()
@@ -142,30 +207,27 @@ pub fn read_collaterals(storage: &dyn Storage, borrower: &CanonicalAddr) -> Toke
     }
 }
 
-// settings for pagination
-const MAX_LIMIT: u32 = 30;
-const DEFAULT_LIMIT: u32 = 10;
 pub fn read_all_collaterals(
     deps: Deps,
     start_after: Option<CanonicalAddr>,
     limit: Option<u32>,
+    order_by: Option<OrderBy>,
 ) -> StdResult<Vec<CollateralsResponse>> {
     let whitelist_bucket: ReadonlyBucket<Tokens> =
         ReadonlyBucket::new(deps.storage, PREFIX_COLLATERALS);
 
-    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
-    let start = calc_range_start(start_after);
+    let limit = clamp_limit(limit, DEFAULT_LIMIT, MAX_LIMIT);
+    let order_by = order_by.unwrap_or(OrderBy::Ascending);
+    let (start, end) =
+        calc_range_bounds(start_after.map(|addr| addr.as_slice().to_vec()), order_by);
 
     whitelist_bucket
-        .range(start.as_deref(), None, Order::Ascending)
+        .range(start.as_deref(), end.as_deref(), order_by.into())
         .take(limit)
         .map(|elem| {
             let (k, v) = elem?;
             let borrower = deps.api.addr_humanize(&CanonicalAddr::from(k))?.to_string();
-            let collaterals: Vec<(String, Uint256)> = v
-                .iter()
-                .map(|c| Ok((deps.api.addr_humanize(&c.0)?.to_string(), c.1)))
-                .collect::<StdResult<Vec<(String, Uint256)>>>()?;
+            let collaterals: TokensHuman = v.to_human(deps)?;
 
             Ok(CollateralsResponse {
                 borrower,
@@ -174,12 +236,3 @@ pub fn read_all_collaterals(
         })
         .collect()
 }
-
-// this will set the first key after the provided key, by appending a 1 byte
-fn calc_range_start(start_after: Option<CanonicalAddr>) -> Option<Vec<u8>> {
-    start_after.map(|addr| {
-        let mut v = addr.as_slice().to_vec();
-        v.push(1);
-        v
-    })
-}
@@ -1,13 +1,16 @@
 use crate::collateral::compute_borrow_limit;
 use crate::contract::{execute, instantiate};
-use crate::testing::mock_querier::mock_dependencies;
+use moneymarket_mocks::{mock_dependencies, PriceInfo};
 
 use cosmwasm_bignumber::{Decimal256, Uint256};
 use cosmwasm_std::testing::{mock_env, mock_info};
-use cosmwasm_std::Api;
+use cosmwasm_std::{Addr, Api, StdError};
 
+use moneymarket::error::MoneyMarketError;
 use moneymarket::overseer::{ExecuteMsg, InstantiateMsg};
+use moneymarket::querier::{query_price, TimeConstraints};
 use moneymarket::tokens::{Token, Tokens};
+use std::str::FromStr;
 
 #[test]
 fn proper_compute_borrow_limit() {
@@ -40,6 +43,7 @@ fn proper_compute_borrow_limit() {
         collateral_token: "bluna".to_string(),
         custody_contract: "custody_bluna".to_string(),
         max_ltv: Decimal256::percent(60),
+        decimals: None,
     };
 
     let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg);
@@ -50,6 +54,7 @@ fn proper_compute_borrow_limit() {
         collateral_token: "batom".to_string(),
         custody_contract: "custody_batom".to_string(),
         max_ltv: Decimal256::percent(60),
+        decimals: None,
     };
 
     let _res = execute(deps.as_mut(), env.clone(), info, msg);
@@ -57,19 +62,19 @@ fn proper_compute_borrow_limit() {
     deps.querier.with_oracle_price(&[
         (
             &("bluna".to_string(), "uusd".to_string()),
-            &(
-                Decimal256::from_uint256(1000u128),
-                env.block.time.seconds(),
-                env.block.time.seconds(),
-            ),
+            &PriceInfo {
+                rate: Decimal256::from_uint256(1000u128),
+                last_updated_base: env.block.time.seconds(),
+                last_updated_quote: env.block.time.seconds(),
+            },
         ),
         (
             &("batom".to_string(), "uusd".to_string()),
-            &(
-                Decimal256::from_uint256(2000u128),
-                env.block.time.seconds(),
-                env.block.time.seconds(),
-            ),
+            &PriceInfo {
+                rate: Decimal256::from_uint256(2000u128),
+                last_updated_base: env.block.time.seconds(),
+                last_updated_quote: env.block.time.seconds(),
+            },
         ),
     ]);
 
@@ -94,3 +99,244 @@ fn proper_compute_borrow_limit() {
     let res2 = (Uint256::from(1800000u128), vec);
     assert_eq!(res, res2);
 }
+
+#[test]
+fn collateral_price_metadata() {
+    let mut deps = mock_dependencies(&[]);
+
+    deps.querier.with_oracle_price(&[(
+        &("bluna".to_string(), "uusd".to_string()),
+        &PriceInfo {
+            rate: Decimal256::percent(1000),
+            last_updated_base: mock_env().block.time.seconds() - 20,
+            last_updated_quote: mock_env().block.time.seconds(),
+        },
+    )]);
+
+    let price = query_price(
+        deps.as_ref(),
+        Addr::unchecked("oracle"),
+        "bluna".to_string(),
+        "uusd".to_string(),
+        Some(TimeConstraints {
+            block_time: mock_env().block.time.seconds(),
+            valid_timeframe: 60u64,
+        }),
+    )
+    .unwrap();
+
+    assert_eq!(price.rate, Decimal256::percent(1000));
+    assert_eq!(price.age_seconds, Some(20));
+    assert_eq!(price.feeder_count, 1);
+    assert!(!price.derived);
+    assert!(!price.frozen);
+    assert_eq!(price.source, "primary");
+}
+
+#[test]
+fn compute_borrow_limit_missing_price() {
+    let mut deps = mock_dependencies(&[]);
+
+    let info = mock_info("owner", &[]);
+    let env = mock_env();
+    let msg = InstantiateMsg {
+        owner_addr: "owner".to_string(),
+        oracle_contract: "oracle".to_string(),
+        market_contract: "market".to_string(),
+        liquidation_contract: "liquidation".to_string(),
+        collector_contract: "collector".to_string(),
+        stable_denom: "uusd".to_string(),
+        epoch_period: 86400u64,
+        threshold_deposit_rate: Decimal256::permille(3),
+        target_deposit_rate: Decimal256::permille(5),
+        buffer_distribution_factor: Decimal256::percent(20),
+        anc_purchase_factor: Decimal256::percent(20),
+        price_timeframe: 60u64,
+    };
+    let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+    for token in ["bluna", "batom"] {
+        let msg = ExecuteMsg::Whitelist {
+            name: token.to_string(),
+            symbol: token.to_string(),
+            collateral_token: token.to_string(),
+            custody_contract: format!("custody_{}", token),
+            max_ltv: Decimal256::percent(60),
+            decimals: None,
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+    }
+
+    // only bluna is priced
+    deps.querier.with_oracle_price(&[(
+        &("bluna".to_string(), "uusd".to_string()),
+        &PriceInfo {
+            rate: Decimal256::percent(1000),
+            last_updated_base: env.block.time.seconds(),
+            last_updated_quote: env.block.time.seconds(),
+        },
+    )]);
+
+    let bluna_only: Tokens = vec![(
+        deps.api.addr_canonicalize("bluna").unwrap(),
+        Uint256::from(1000u128),
+    )];
+    let (borrow_limit, prices) =
+        compute_borrow_limit(deps.as_ref(), &bluna_only, Some(env.block.time.seconds())).unwrap();
+    assert_eq!(borrow_limit, Uint256::from(6000u128));
+    assert_eq!(prices, vec![Decimal256::percent(1000)]);
+
+    let collaterals: Tokens = vec![
+        (
+            deps.api.addr_canonicalize("bluna").unwrap(),
+            Uint256::from(1000u128),
+        ),
+        (
+            deps.api.addr_canonicalize("batom").unwrap(),
+            Uint256::from(1000u128),
+        ),
+    ];
+
+    let res = compute_borrow_limit(deps.as_ref(), &collaterals, Some(env.block.time.seconds()));
+    match res {
+        Err(MoneyMarketError::Std(StdError::GenericErr { msg, .. })) => {
+            assert_eq!(msg, "No price for batom: No oracle price exists")
+        }
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // positions holding a deprecated collateral cannot be priced anymore
+    deps.querier.with_deprecated_assets(&["bluna"]);
+    let res = compute_borrow_limit(deps.as_ref(), &bluna_only, Some(env.block.time.seconds()));
+    match res {
+        Err(MoneyMarketError::Std(StdError::GenericErr { msg, .. })) => {
+            assert_eq!(msg, "No price for bluna: asset deprecated")
+        }
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+}
+
+#[test]
+fn compute_borrow_limit_micro_price() {
+    let mut deps = mock_dependencies(&[]);
+
+    let info = mock_info("owner", &[]);
+    let env = mock_env();
+    let msg = InstantiateMsg {
+        owner_addr: "owner".to_string(),
+        oracle_contract: "oracle".to_string(),
+        market_contract: "market".to_string(),
+        liquidation_contract: "liquidation".to_string(),
+        collector_contract: "collector".to_string(),
+        stable_denom: "uusd".to_string(),
+        epoch_period: 86400u64,
+        threshold_deposit_rate: Decimal256::permille(3),
+        target_deposit_rate: Decimal256::permille(5),
+        buffer_distribution_factor: Decimal256::percent(20),
+        anc_purchase_factor: Decimal256::percent(20),
+        price_timeframe: 60u64,
+    };
+    let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+    let msg = ExecuteMsg::Whitelist {
+        name: "micro".to_string(),
+        symbol: "micro".to_string(),
+        collateral_token: "micro".to_string(),
+        custody_contract: "custody_micro".to_string(),
+        max_ltv: Decimal256::percent(50),
+        decimals: None,
+    };
+    let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+    deps.querier.with_oracle_price(&[(
+        &("micro".to_string(), "uusd".to_string()),
+        &PriceInfo {
+            rate: Decimal256::from_str("0.000000001234567891").unwrap(),
+            last_updated_base: env.block.time.seconds(),
+            last_updated_quote: env.block.time.seconds(),
+        },
+    )]);
+
+    // 10^24 units at 1.234567891e-9 each, at 50% LTV
+    let collaterals: Tokens = vec![(
+        deps.api.addr_canonicalize("micro").unwrap(),
+        Uint256::from_str("1000000000000000000000000").unwrap(),
+    )];
+    let (borrow_limit, prices) =
+        compute_borrow_limit(deps.as_ref(), &collaterals, Some(env.block.time.seconds())).unwrap();
+    assert_eq!(borrow_limit, Uint256::from(617283945500000u128));
+    assert_eq!(
+        prices,
+        vec![Decimal256::from_str("0.000000001234567891").unwrap()]
+    );
+}
+
+#[test]
+fn compute_borrow_limit_collateral_decimals() {
+    let mut deps = mock_dependencies(&[]);
+
+    let info = mock_info("owner", &[]);
+    let env = mock_env();
+    let msg = InstantiateMsg {
+        owner_addr: "owner".to_string(),
+        oracle_contract: "oracle".to_string(),
+        market_contract: "market".to_string(),
+        liquidation_contract: "liquidation".to_string(),
+        collector_contract: "collector".to_string(),
+        stable_denom: "uusd".to_string(),
+        epoch_period: 86400u64,
+        threshold_deposit_rate: Decimal256::permille(3),
+        target_deposit_rate: Decimal256::permille(5),
+        buffer_distribution_factor: Decimal256::percent(20),
+        anc_purchase_factor: Decimal256::percent(20),
+        price_timeframe: 60u64,
+    };
+    let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+    for (token, max_ltv, decimals) in [
+        ("bluna", Decimal256::percent(60), None),
+        ("beth", Decimal256::percent(50), Some(18u8)),
+        ("wbtc", Decimal256::percent(70), Some(8u8)),
+    ] {
+        let msg = ExecuteMsg::Whitelist {
+            name: token.to_string(),
+            symbol: token.to_string(),
+            collateral_token: token.to_string(),
+            custody_contract: format!("custody_{}", token),
+            max_ltv,
+            decimals,
+        };
+        execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+    }
+
+    let price = |rate: u64| PriceInfo {
+        rate: Decimal256::from_uint256(rate),
+        last_updated_base: env.block.time.seconds(),
+        last_updated_quote: env.block.time.seconds(),
+    };
+    deps.querier.with_oracle_price(&[
+        (&("bluna".to_string(), "uusd".to_string()), &price(10)),
+        (&("beth".to_string(), "uusd".to_string()), &price(3000)),
+        (&("wbtc".to_string(), "uusd".to_string()), &price(40000)),
+    ]);
+
+    // one whole token of each collateral
+    let collaterals: Tokens = vec![
+        (
+            deps.api.addr_canonicalize("bluna").unwrap(),
+            Uint256::from(1_000_000u64),
+        ),
+        (
+            deps.api.addr_canonicalize("beth").unwrap(),
+            Uint256::from(1_000_000_000_000_000_000u64),
+        ),
+        (
+            deps.api.addr_canonicalize("wbtc").unwrap(),
+            Uint256::from(100_000_000u64),
+        ),
+    ];
+
+    // 10 * 0.6 + 3000 * 0.5 + 40000 * 0.7 = 29506 UST
+    let (borrow_limit, _) = compute_borrow_limit(deps.as_ref(), &collaterals, None).unwrap();
+    assert_eq!(borrow_limit, Uint256::from(29_506_000_000u64));
+}
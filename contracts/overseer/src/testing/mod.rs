@@ -1,3 +1,2 @@
 mod collateral_ut;
-mod mock_querier;
 mod tests;
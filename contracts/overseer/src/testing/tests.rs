@@ -1,23 +1,29 @@
-use crate::contract::{execute, instantiate, query};
+use crate::contract::{execute, instantiate, query, reply, UPDATE_EMISSION_RATE_OPERATION};
 use crate::error::ContractError;
-use crate::querier::query_epoch_state;
 use crate::state::{read_epoch_state, store_epoch_state, EpochState};
-use crate::testing::mock_querier::mock_dependencies;
+use moneymarket_mocks::{mock_dependencies, PriceInfo};
 
 use cosmwasm_bignumber::{Decimal256, Uint256};
 use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
 use cosmwasm_std::{
-    attr, from_binary, to_binary, Addr, Api, BankMsg, CanonicalAddr, Coin, CosmosMsg, Decimal,
-    SubMsg, Uint128, WasmMsg,
+    attr, coins, from_binary, to_binary, Addr, Api, BankMsg, CanonicalAddr, Coin, ContractResult,
+    CosmosMsg, Decimal, Reply, StdError, SubMsg, Uint128, WasmMsg,
 };
 
 use moneymarket::custody::ExecuteMsg as CustodyExecuteMsg;
+use moneymarket::distribution_model::ExecuteMsg as DistributionExecuteMsg;
+use moneymarket::error::MoneyMarketError;
+use moneymarket::liquidation::ExecuteMsg as LiquidationExecuteMsg;
 use moneymarket::market::ExecuteMsg as MarketExecuteMsg;
+use moneymarket::oracle::ExecuteMsg as OracleExecuteMsg;
 use moneymarket::overseer::{
-    AllCollateralsResponse, BorrowLimitResponse, CollateralsResponse, ConfigResponse, ExecuteMsg,
-    InstantiateMsg, QueryMsg, WhitelistResponse, WhitelistResponseElem,
+    AllCollateralsResponse, BorrowLimitResponse, BorrowLimitsResponse, CollateralValueResponse,
+    CollateralsBatchResponse, CollateralsResponse, ConfigResponse, DistributionParamsResponse,
+    EpochStateResponse, ExecuteMsg, InstantiateMsg, QueryMsg, WhitelistResponse,
+    WhitelistResponseElem,
 };
-use moneymarket::querier::deduct_tax;
+use moneymarket::pagination::OrderBy;
+use moneymarket::querier::{deduct_tax, query_epoch_state};
 
 use std::str::FromStr;
 
@@ -42,6 +48,17 @@ fn proper_initialization() {
 
     let info = mock_info("addr0000", &[]);
 
+    // a zero epoch period would let operations run every block
+    let invalid_msg = InstantiateMsg {
+        epoch_period: 0u64,
+        ..msg.clone()
+    };
+    let res = instantiate(deps.as_mut(), mock_env(), info.clone(), invalid_msg);
+    match res {
+        Err(ContractError::InvalidEpochPeriod {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
     // we can just call .unwrap() to assert this was a success
     let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
 
@@ -62,14 +79,20 @@ fn proper_initialization() {
             buffer_distribution_factor: Decimal256::percent(20),
             anc_purchase_factor: Decimal256::percent(20),
             price_timeframe: 60u64,
+            max_ltv: Decimal256::percent(90),
+            guardian: None,
+            paused: false,
+            executor_incentive: Uint128::zero(),
+            max_buffer_distribution: None,
+            custody_batch_size: 10,
         }
     );
 
     let query_res = query(deps.as_ref(), mock_env(), QueryMsg::EpochState {}).unwrap();
-    let epoch_state: EpochState = from_binary(&query_res).unwrap();
+    let epoch_state: EpochStateResponse = from_binary(&query_res).unwrap();
     assert_eq!(
         epoch_state,
-        EpochState {
+        EpochStateResponse {
             deposit_rate: Decimal256::zero(),
             last_executed_height: mock_env().block.height,
             prev_aterra_supply: Uint256::zero(),
@@ -77,6 +100,18 @@ fn proper_initialization() {
             prev_interest_buffer: Uint256::zero(),
         }
     );
+
+    let query_res = query(deps.as_ref(), mock_env(), QueryMsg::DistributionParams {}).unwrap();
+    let distribution_params: DistributionParamsResponse = from_binary(&query_res).unwrap();
+    assert_eq!(
+        distribution_params,
+        DistributionParamsResponse {
+            target_deposit_rate: Decimal256::permille(5),
+            threshold_deposit_rate: Decimal256::permille(3),
+            buffer_distribution_factor: Decimal256::percent(20),
+            deposit_rate: Decimal256::zero(),
+        }
+    );
 }
 
 #[test]
@@ -107,6 +142,7 @@ fn update_config() {
     let msg = ExecuteMsg::UpdateConfig {
         owner_addr: Some("owner1".to_string()),
         oracle_contract: None,
+        market_contract: None,
         liquidation_contract: None,
         threshold_deposit_rate: None,
         target_deposit_rate: None,
@@ -114,6 +150,11 @@ fn update_config() {
         anc_purchase_factor: None,
         epoch_period: None,
         price_timeframe: None,
+        max_ltv: None,
+        guardian: None,
+        executor_incentive: None,
+        max_buffer_distribution: None,
+        custody_batch_size: None,
     };
 
     let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -129,6 +170,7 @@ fn update_config() {
     let msg = ExecuteMsg::UpdateConfig {
         owner_addr: None,
         oracle_contract: Some("oracle1".to_string()),
+        market_contract: None,
         liquidation_contract: Some("liquidation1".to_string()),
         threshold_deposit_rate: Some(Decimal256::permille(1)),
         target_deposit_rate: Some(Decimal256::permille(2)),
@@ -136,10 +178,25 @@ fn update_config() {
         anc_purchase_factor: Some(Decimal256::percent(10)),
         epoch_period: Some(100000u64),
         price_timeframe: Some(120u64),
+        max_ltv: None,
+        guardian: None,
+        executor_incentive: None,
+        max_buffer_distribution: None,
+        custody_batch_size: None,
     };
 
     let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
     assert_eq!(0, res.messages.len());
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "update_config"),
+            attr("prev_oracle_contract", "oracle"),
+            attr("oracle_contract", "oracle1"),
+            attr("prev_liquidation_contract", "liquidation"),
+            attr("liquidation_contract", "liquidation1"),
+        ]
+    );
 
     // it worked, let's query the state
     let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
@@ -157,11 +214,116 @@ fn update_config() {
     assert_eq!(100000u64, config_res.epoch_period);
     assert_eq!(120u64, config_res.price_timeframe);
 
+    // out of range parameters are rejected with their name and value
+    let info = mock_info("owner1", &[]);
+    let invalid_update =
+        |threshold_deposit_rate, buffer_distribution_factor| ExecuteMsg::UpdateConfig {
+            owner_addr: None,
+            oracle_contract: None,
+            market_contract: None,
+            liquidation_contract: None,
+            threshold_deposit_rate,
+            target_deposit_rate: None,
+            buffer_distribution_factor,
+            anc_purchase_factor: None,
+            epoch_period: None,
+            price_timeframe: None,
+            max_ltv: None,
+            guardian: None,
+            executor_incentive: None,
+            max_buffer_distribution: None,
+            custody_batch_size: None,
+        };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        invalid_update(None, Some(Decimal256::percent(101))),
+    );
+    match res {
+        Err(ContractError::Std(StdError::GenericErr { msg, .. })) => assert_eq!(
+            msg,
+            "buffer_distribution_factor must be within [0, 1], got 1.01"
+        ),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        invalid_update(Some(Decimal256::permille(3)), None),
+    );
+    match res {
+        Err(ContractError::Std(StdError::GenericErr { msg, .. })) => assert_eq!(
+            msg,
+            "threshold_deposit_rate must not exceed 0.002, got 0.003"
+        ),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        ExecuteMsg::UpdateConfig {
+            owner_addr: None,
+            oracle_contract: None,
+            market_contract: None,
+            liquidation_contract: None,
+            threshold_deposit_rate: None,
+            target_deposit_rate: None,
+            buffer_distribution_factor: None,
+            anc_purchase_factor: None,
+            epoch_period: None,
+            price_timeframe: None,
+            max_ltv: Some(Decimal256::zero()),
+            guardian: None,
+            executor_incentive: None,
+            max_buffer_distribution: None,
+            custody_batch_size: None,
+        },
+    );
+    match res {
+        Err(ContractError::Std(StdError::GenericErr { msg, .. })) => {
+            assert_eq!(msg, "max_ltv must be within (0, 1], got 0")
+        }
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        info,
+        ExecuteMsg::UpdateConfig {
+            owner_addr: None,
+            oracle_contract: None,
+            market_contract: None,
+            liquidation_contract: None,
+            threshold_deposit_rate: None,
+            target_deposit_rate: None,
+            buffer_distribution_factor: None,
+            anc_purchase_factor: None,
+            epoch_period: Some(0u64),
+            price_timeframe: None,
+            max_ltv: None,
+            guardian: None,
+            executor_incentive: None,
+            max_buffer_distribution: None,
+            custody_batch_size: None,
+        },
+    );
+    match res {
+        Err(ContractError::InvalidEpochPeriod {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
     // Unauthorized err
     let info = mock_info("owner", &[]);
     let msg = ExecuteMsg::UpdateConfig {
         owner_addr: None,
         oracle_contract: None,
+        market_contract: None,
         liquidation_contract: None,
         threshold_deposit_rate: None,
         target_deposit_rate: None,
@@ -169,6 +331,11 @@ fn update_config() {
         anc_purchase_factor: None,
         epoch_period: None,
         price_timeframe: None,
+        max_ltv: None,
+        guardian: None,
+        executor_incentive: None,
+        max_buffer_distribution: None,
+        custody_batch_size: None,
     };
 
     let res = execute(deps.as_mut(), mock_env(), info, msg);
@@ -207,6 +374,7 @@ fn whitelist() {
         collateral_token: "bluna".to_string(),
         custody_contract: "custody".to_string(),
         max_ltv: Decimal256::percent(60),
+        decimals: None,
     };
 
     let info = mock_info("addr0000", &[]);
@@ -217,6 +385,39 @@ fn whitelist() {
     };
 
     let info = mock_info("owner", &[]);
+    let invalid_msg = ExecuteMsg::Whitelist {
+        name: "bluna".to_string(),
+        symbol: "bluna".to_string(),
+        collateral_token: "bluna".to_string(),
+        custody_contract: "custody".to_string(),
+        max_ltv: Decimal256::zero(),
+        decimals: None,
+    };
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), invalid_msg);
+    match res {
+        Err(ContractError::Std(StdError::GenericErr { msg, .. })) => {
+            assert_eq!(msg, "max_ltv must be within (0, 1], got 0")
+        }
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // max_ltv must not exceed the configured ceiling
+    let invalid_msg = ExecuteMsg::Whitelist {
+        name: "bluna".to_string(),
+        symbol: "bluna".to_string(),
+        collateral_token: "bluna".to_string(),
+        custody_contract: "custody".to_string(),
+        max_ltv: Decimal256::percent(91),
+        decimals: None,
+    };
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), invalid_msg);
+    match res {
+        Err(ContractError::Std(StdError::GenericErr { msg, .. })) => {
+            assert_eq!(msg, "max_ltv must not exceed 0.9, got 0.91")
+        }
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
     let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
     assert_eq!(
         res.attributes,
@@ -227,6 +428,7 @@ fn whitelist() {
             attr("collateral_token", "bluna"),
             attr("custody_contract", "custody"),
             attr("LTV", "0.6"),
+            attr("decimals", "6"),
         ]
     );
 
@@ -237,6 +439,7 @@ fn whitelist() {
             collateral_token: Some("bluna".to_string()),
             start_after: None,
             limit: None,
+            order_by: None,
         },
     )
     .unwrap();
@@ -250,6 +453,8 @@ fn whitelist() {
                 collateral_token: "bluna".to_string(),
                 custody_contract: "custody".to_string(),
                 max_ltv: Decimal256::percent(60),
+                decimals: 6,
+                paused: false,
             }]
         }
     );
@@ -261,6 +466,7 @@ fn whitelist() {
         collateral_token: "bluna".to_string(),
         custody_contract: "custody".to_string(),
         max_ltv: Decimal256::percent(60),
+        decimals: None,
     };
 
     let info = mock_info("owner", &[]);
@@ -270,10 +476,67 @@ fn whitelist() {
         _ => panic!("DO NOT ENTER HERE"),
     }
 
+    // only registered collaterals can be updated
+    let invalid_msg = ExecuteMsg::UpdateWhitelist {
+        collateral_token: "batom".to_string(),
+        custody_contract: None,
+        max_ltv: Some(Decimal256::percent(30)),
+        decimals: None,
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        invalid_msg,
+    );
+    match res {
+        Err(ContractError::Std(StdError::GenericErr { msg, .. })) => {
+            assert_eq!(msg, "Token is not registered as collateral")
+        }
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let invalid_msg = ExecuteMsg::UpdateWhitelist {
+        collateral_token: "bluna".to_string(),
+        custody_contract: None,
+        max_ltv: Some(Decimal256::percent(101)),
+        decimals: None,
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        invalid_msg,
+    );
+    match res {
+        Err(ContractError::Std(StdError::GenericErr { msg, .. })) => {
+            assert_eq!(msg, "max_ltv must be within (0, 1], got 1.01")
+        }
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let invalid_msg = ExecuteMsg::UpdateWhitelist {
+        collateral_token: "bluna".to_string(),
+        custody_contract: Some("".to_string()),
+        max_ltv: None,
+        decimals: None,
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        invalid_msg,
+    );
+    match res {
+        Err(ContractError::Std(StdError::GenericErr { .. })) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
     let msg = ExecuteMsg::UpdateWhitelist {
         collateral_token: "bluna".to_string(),
         custody_contract: Some("custody2".to_string()),
         max_ltv: Some(Decimal256::percent(30)),
+        decimals: Some(8),
     };
 
     let info = mock_info("addr0000", &[]);
@@ -292,6 +555,10 @@ fn whitelist() {
             attr("collateral_token", "bluna"),
             attr("custody_contract", "custody2"),
             attr("LTV", "0.3"),
+            attr("decimals", "8"),
+            attr("prev_custody_contract", "custody"),
+            attr("prev_LTV", "0.6"),
+            attr("prev_decimals", "6"),
         ]
     );
 
@@ -302,6 +569,7 @@ fn whitelist() {
             collateral_token: Some("bluna".to_string()),
             start_after: None,
             limit: None,
+            order_by: None,
         },
     )
     .unwrap();
@@ -315,17 +583,72 @@ fn whitelist() {
                 collateral_token: "bluna".to_string(),
                 custody_contract: "custody2".to_string(),
                 max_ltv: Decimal256::percent(30),
+                decimals: 8,
+                paused: false,
             }]
         }
     );
+
+    // the ceiling itself is accepted, anything above it is not
+    let update_ltv = |max_ltv| ExecuteMsg::UpdateWhitelist {
+        collateral_token: "bluna".to_string(),
+        custody_contract: None,
+        max_ltv: Some(max_ltv),
+        decimals: None,
+    };
+    let info = mock_info("owner", &[]);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        update_ltv(Decimal256::percent(90)),
+    )
+    .unwrap();
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        update_ltv(Decimal256::percent(95)),
+    );
+    match res {
+        Err(ContractError::Std(StdError::GenericErr { msg, .. })) => {
+            assert_eq!(msg, "max_ltv must not exceed 0.9, got 0.95")
+        }
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // raising the ceiling through UpdateConfig allows higher LTVs
+    let msg = ExecuteMsg::UpdateConfig {
+        owner_addr: None,
+        oracle_contract: None,
+        market_contract: None,
+        liquidation_contract: None,
+        threshold_deposit_rate: None,
+        target_deposit_rate: None,
+        buffer_distribution_factor: None,
+        anc_purchase_factor: None,
+        epoch_period: None,
+        price_timeframe: None,
+        max_ltv: Some(Decimal256::percent(95)),
+        guardian: None,
+        executor_incentive: None,
+        max_buffer_distribution: None,
+        custody_batch_size: None,
+    };
+    execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        info,
+        update_ltv(Decimal256::percent(95)),
+    )
+    .unwrap();
 }
 
 #[test]
-fn execute_epoch_operations() {
-    let mut deps = mock_dependencies(&[Coin {
-        denom: "uusd".to_string(),
-        amount: Uint128::from(10000000000u128),
-    }]);
+fn update_oracle_and_market_contract() {
+    let mut deps = mock_dependencies(&[]);
 
     let mut env = mock_env();
     let info = mock_info("owner", &[]);
@@ -337,221 +660,245 @@ fn execute_epoch_operations() {
         collector_contract: "collector".to_string(),
         stable_denom: "uusd".to_string(),
         epoch_period: 86400u64,
-        threshold_deposit_rate: Decimal256::from_ratio(1u64, 1000000u64),
+        threshold_deposit_rate: Decimal256::permille(3),
         target_deposit_rate: Decimal256::permille(5),
         buffer_distribution_factor: Decimal256::percent(20),
         anc_purchase_factor: Decimal256::percent(20),
         price_timeframe: 60u64,
     };
-
-    // we can just call .unwrap() to assert this was a success
     let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
 
-    let batom_collat_token = deps
-        .api
-        .addr_humanize(&CanonicalAddr::from(vec![
-            1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-        ]))
-        .unwrap()
-        .to_string();
-
-    let bluna_collat_token = deps
-        .api
-        .addr_humanize(&CanonicalAddr::from(vec![
-            1, 1, 1, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-        ]))
-        .unwrap()
-        .to_string();
-
-    // store whitelist elems
     let msg = ExecuteMsg::Whitelist {
         name: "bluna".to_string(),
         symbol: "bluna".to_string(),
-        collateral_token: bluna_collat_token,
+        collateral_token: "bluna".to_string(),
         custody_contract: "custody_bluna".to_string(),
         max_ltv: Decimal256::percent(60),
+        decimals: None,
     };
+    let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
 
-    let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg);
-
-    let msg = ExecuteMsg::Whitelist {
-        name: "batom".to_string(),
-        symbol: "batom".to_string(),
-        collateral_token: batom_collat_token,
-        custody_contract: "custody_batom".to_string(),
-        max_ltv: Decimal256::percent(60),
+    let msg = ExecuteMsg::LockCollateral {
+        collaterals: vec![("bluna".to_string(), Uint256::from(1000u64))],
     };
+    let _res = execute(deps.as_mut(), env.clone(), mock_info("addr0000", &[]), msg).unwrap();
+
+    deps.querier.with_oracle_price(&[(
+        &("bluna".to_string(), "uusd".to_string()),
+        &PriceInfo {
+            rate: Decimal256::one(),
+            last_updated_base: env.block.time.seconds(),
+            last_updated_quote: env.block.time.seconds(),
+        },
+    )]);
+    deps.querier.with_oracle_contract("oracle1");
 
-    let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg);
-
-    let msg = ExecuteMsg::ExecuteEpochOperations {};
-    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone());
+    let borrow_limit_query = QueryMsg::BorrowLimit {
+        borrower: "addr0000".to_string(),
+        block_time: None,
+    };
+    let _res = query(deps.as_ref(), env.clone(), borrow_limit_query.clone()).unwrap_err();
+
+    let update_msg =
+        |oracle_contract: Option<&str>, market_contract: Option<&str>| ExecuteMsg::UpdateConfig {
+            owner_addr: None,
+            oracle_contract: oracle_contract.map(|addr| addr.to_string()),
+            market_contract: market_contract.map(|addr| addr.to_string()),
+            liquidation_contract: None,
+            threshold_deposit_rate: None,
+            target_deposit_rate: None,
+            buffer_distribution_factor: None,
+            anc_purchase_factor: None,
+            epoch_period: None,
+            price_timeframe: None,
+            max_ltv: None,
+            guardian: None,
+            executor_incentive: None,
+            max_buffer_distribution: None,
+            custody_batch_size: None,
+        };
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0000", &[]),
+        update_msg(Some("oracle1"), Some("market1")),
+    );
     match res {
-        Err(ContractError::EpochNotPassed(12345)) => (),
+        Err(ContractError::Unauthorized {}) => (),
         _ => panic!("DO NOT ENTER HERE"),
     }
 
-    env.block.height += 86400u64;
-
-    // If deposit_rate is bigger than threshold_deposit_rate
-    deps.querier.with_epoch_state(&[(
-        &"market".to_string(),
-        &(Uint256::from(1000000u64), Decimal256::percent(120)),
-    )]);
-
-    // (120 / 100 - 1) / 86400
-    // deposit rate = 0.000002314814814814
-    // accrued_buffer = 10,000,000,000
-    // anc_purchase_amount = accrued_buffer * 0.2 = 2,000,000,000
-    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone()).unwrap();
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        update_msg(Some("oracle1"), None),
+    )
+    .unwrap();
     assert_eq!(
-        res.messages,
+        res.attributes,
         vec![
-            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
-                to_address: "collector".to_string(),
-                amount: vec![deduct_tax(
-                    deps.as_ref(),
-                    Coin {
-                        denom: "uusd".to_string(),
-                        amount: Uint128::from(2_000_000_000u128),
-                    }
-                )
-                .unwrap()],
-            })),
-            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: "custody_batom".to_string(),
-                funds: vec![],
-                msg: to_binary(&CustodyExecuteMsg::DistributeRewards {}).unwrap(),
-            })),
-            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: "custody_bluna".to_string(),
-                funds: vec![],
-                msg: to_binary(&CustodyExecuteMsg::DistributeRewards {}).unwrap(),
-            })),
-            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: MOCK_CONTRACT_ADDR.to_string(),
-                funds: vec![],
-                msg: to_binary(&ExecuteMsg::UpdateEpochState {
-                    interest_buffer: Uint256::from(8_000_000_000u128),
-                    distributed_interest: Uint256::zero(),
-                })
-                .unwrap(),
-            }))
+            attr("action", "update_config"),
+            attr("prev_oracle_contract", "oracle"),
+            attr("oracle_contract", "oracle1"),
         ]
     );
 
+    // borrow limits are priced by the new oracle right away
+    let res = query(deps.as_ref(), env.clone(), borrow_limit_query).unwrap();
+    let borrow_limit_res: BorrowLimitResponse = from_binary(&res).unwrap();
+    assert_eq!(borrow_limit_res.borrow_limit, Uint256::from(600u64));
+
+    // switching the market restarts the epoch from the new market state
+    env.block.height += 100;
+    deps.querier.with_epoch_state(&[(
+        &"market1".to_string(),
+        &(Uint256::from(2000000u64), Decimal256::percent(150)),
+    )]);
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        info,
+        update_msg(None, Some("market1")),
+    )
+    .unwrap();
     assert_eq!(
         res.attributes,
         vec![
-            attr("action", "epoch_operations"),
-            attr("deposit_rate", "0.000002314814814814"),
-            attr("exchange_rate", "1.2"),
-            attr("aterra_supply", "1000000"),
-            attr("distributed_interest", "0"),
-            attr("anc_purchase_amount", "2000000000"),
+            attr("action", "update_config"),
+            attr("prev_market_contract", "market"),
+            attr("market_contract", "market1"),
         ]
     );
 
-    // store epoch state for test purpose
-    store_epoch_state(
-        deps.as_mut().storage,
-        &EpochState {
+    let res = query(deps.as_ref(), env.clone(), QueryMsg::Config {}).unwrap();
+    let config_res: ConfigResponse = from_binary(&res).unwrap();
+    assert_eq!(config_res.oracle_contract, "oracle1");
+    assert_eq!(config_res.market_contract, "market1");
+
+    let res = query(deps.as_ref(), env.clone(), QueryMsg::EpochState {}).unwrap();
+    let epoch_state_res: EpochStateResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        epoch_state_res,
+        EpochStateResponse {
+            deposit_rate: Decimal256::zero(),
+            prev_aterra_supply: Uint256::from(2000000u64),
+            prev_exchange_rate: Decimal256::percent(150),
+            prev_interest_buffer: Uint256::zero(),
             last_executed_height: env.block.height,
-            prev_exchange_rate: Decimal256::from_str("1.2").unwrap(),
-            prev_aterra_supply: Uint256::from_str("1000000").unwrap(),
-            prev_interest_buffer: Uint256::from_str("9999000000").unwrap(),
-            deposit_rate: Decimal256::from_str("0.000002314814814814").unwrap(),
-        },
-    )
-    .unwrap();
+        }
+    );
+}
 
-    // If deposit rate is bigger than threshold
-    deps.querier.with_epoch_state(&[(
-        &"market".to_string(),
-        &(Uint256::from(1000000u64), Decimal256::percent(125)),
-    )]);
+#[test]
+fn migrate_custody() {
+    let mut deps = mock_dependencies(&[]);
 
-    deps.querier.with_tax(
-        Decimal::percent(1),
-        &[(&"uusd".to_string(), &Uint128::from(1000000u128))],
-    );
+    let info = mock_info("owner", &[]);
+    let msg = InstantiateMsg {
+        owner_addr: "owner".to_string(),
+        oracle_contract: "oracle".to_string(),
+        market_contract: "market".to_string(),
+        liquidation_contract: "liquidation".to_string(),
+        collector_contract: "collector".to_string(),
+        stable_denom: "uusd".to_string(),
+        epoch_period: 86400u64,
+        threshold_deposit_rate: Decimal256::permille(3),
+        target_deposit_rate: Decimal256::permille(5),
+        buffer_distribution_factor: Decimal256::percent(20),
+        anc_purchase_factor: Decimal256::percent(20),
+        price_timeframe: 60u64,
+    };
+    let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
 
-    env.block.height += 86400u64;
+    let msg = ExecuteMsg::MigrateCustody {
+        collateral_token: "bluna".to_string(),
+        new_custody_contract: "custody_bluna2".to_string(),
+    };
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), msg.clone());
+    match res {
+        Err(ContractError::Std(StdError::GenericErr { msg, .. })) => {
+            assert_eq!(msg, "Token is not registered as collateral")
+        }
+        _ => panic!("DO NOT ENTER HERE"),
+    }
 
-    // accrued_buffer = 1,000,000
-    // interest_buffer = 9,999,000,000
-    // (125 / 120 - 1) / 86400
-    // deposit rate = 0.000000482253086419
-    let res = execute(deps.as_mut(), env, info, msg).unwrap();
+    let whitelist_msg = ExecuteMsg::Whitelist {
+        name: "bluna".to_string(),
+        symbol: "bluna".to_string(),
+        collateral_token: "bluna".to_string(),
+        custody_contract: "custody_bluna".to_string(),
+        max_ltv: Decimal256::percent(60),
+        decimals: None,
+    };
+    let _res = execute(deps.as_mut(), mock_env(), info.clone(), whitelist_msg).unwrap();
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        msg.clone(),
+    );
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
     assert_eq!(
         res.messages,
         vec![
-            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
-                to_address: "collector".to_string(),
-                amount: vec![deduct_tax(
-                    deps.as_ref(),
-                    Coin {
-                        denom: "uusd".to_string(),
-                        amount: Uint128::from(200_000u128),
-                    }
-                )
-                .unwrap()]
-            })),
-            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
-                to_address: "market".to_string(),
-                amount: vec![deduct_tax(
-                    deps.as_ref(),
-                    Coin {
-                        denom: "uusd".to_string(),
-                        amount: Uint128::from(53680u128),
-                    }
-                )
-                .unwrap()]
-            })),
             SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: "custody_batom".to_string(),
+                contract_addr: "custody_bluna2".to_string(),
                 funds: vec![],
-                msg: to_binary(&CustodyExecuteMsg::DistributeRewards {}).unwrap(),
+                msg: to_binary(&CustodyExecuteMsg::AcceptMigration {
+                    old_custody: "custody_bluna".to_string(),
+                })
+                .unwrap(),
             })),
             SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
                 contract_addr: "custody_bluna".to_string(),
                 funds: vec![],
-                msg: to_binary(&CustodyExecuteMsg::DistributeRewards {}).unwrap(),
-            })),
-            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: MOCK_CONTRACT_ADDR.to_string(),
-                funds: vec![],
-                msg: to_binary(&ExecuteMsg::UpdateEpochState {
-                    interest_buffer: Uint256::from(9999746320u128),
-                    distributed_interest: Uint256::from(53148u128),
+                msg: to_binary(&CustodyExecuteMsg::MigrateBalances {
+                    new_custody: "custody_bluna2".to_string(),
                 })
                 .unwrap(),
-            }))
+            })),
         ]
     );
-
     assert_eq!(
         res.attributes,
         vec![
-            attr("action", "epoch_operations"),
-            attr("deposit_rate", "0.000000482253086419"),
-            attr("exchange_rate", "1.25"),
-            attr("aterra_supply", "1000000"),
-            attr("distributed_interest", "53148"),
-            attr("anc_purchase_amount", "200000")
+            attr("action", "migrate_custody"),
+            attr("collateral_token", "bluna"),
+            attr("prev_custody_contract", "custody_bluna"),
+            attr("custody_contract", "custody_bluna2"),
         ]
     );
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::Whitelist {
+            collateral_token: Some("bluna".to_string()),
+            start_after: None,
+            limit: None,
+            order_by: None,
+        },
+    )
+    .unwrap();
+    let whitelist_res: WhitelistResponse = from_binary(&res).unwrap();
+    assert_eq!(whitelist_res.elems[0].custody_contract, "custody_bluna2");
 }
 
 #[test]
-fn update_epoch_state() {
+fn pause_whitelist() {
     let mut deps = mock_dependencies(&[Coin {
         denom: "uusd".to_string(),
         amount: Uint128::from(10000000000u128),
     }]);
 
+    let mut env = mock_env();
     let info = mock_info("owner", &[]);
     let msg = InstantiateMsg {
         owner_addr: "owner".to_string(),
@@ -567,137 +914,328 @@ fn update_epoch_state() {
         anc_purchase_factor: Decimal256::percent(20),
         price_timeframe: 60u64,
     };
+    let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
 
-    // we can just call .unwrap() to assert this was a success
-    let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
-
-    // store whitelist elems
-    let msg = ExecuteMsg::Whitelist {
-        name: "bluna".to_string(),
-        symbol: "bluna".to_string(),
-        collateral_token: "bluna".to_string(),
-        custody_contract: "custody_bluna".to_string(),
-        max_ltv: Decimal256::percent(60),
-    };
+    for token in ["bluna", "batom"] {
+        let msg = ExecuteMsg::Whitelist {
+            name: token.to_string(),
+            symbol: token.to_string(),
+            collateral_token: token.to_string(),
+            custody_contract: format!("custody_{}", token),
+            max_ltv: Decimal256::percent(60),
+            decimals: None,
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+    }
 
-    let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg);
+    deps.querier.with_oracle_price(&[
+        (
+            &("bluna".to_string(), "uusd".to_string()),
+            &PriceInfo {
+                rate: Decimal256::one(),
+                last_updated_base: env.block.time.seconds(),
+                last_updated_quote: env.block.time.seconds(),
+            },
+        ),
+        (
+            &("batom".to_string(), "uusd".to_string()),
+            &PriceInfo {
+                rate: Decimal256::one(),
+                last_updated_base: env.block.time.seconds(),
+                last_updated_quote: env.block.time.seconds(),
+            },
+        ),
+    ]);
+    deps.querier
+        .with_loan_amount(&[(&"addr0000".to_string(), &Uint256::zero())]);
 
-    let msg = ExecuteMsg::Whitelist {
-        name: "batom".to_string(),
-        symbol: "batom".to_string(),
-        collateral_token: "batom".to_string(),
-        custody_contract: "custody_batom".to_string(),
-        max_ltv: Decimal256::percent(60),
+    let borrower = mock_info("addr0000", &[]);
+    let lock_msg = |token: &str| ExecuteMsg::LockCollateral {
+        collaterals: vec![(token.to_string(), Uint256::from(1000u64))],
     };
+    let _res = execute(
+        deps.as_mut(),
+        env.clone(),
+        borrower.clone(),
+        lock_msg("batom"),
+    )
+    .unwrap();
 
-    let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg);
-
-    // only contract itself can execute update_epoch_state
-    let msg = ExecuteMsg::UpdateEpochState {
-        interest_buffer: Uint256::from(10000000000u128),
-        distributed_interest: Uint256::from(1000000u128),
+    let pause_msg = ExecuteMsg::PauseWhitelist {
+        collateral_token: "batom".to_string(),
     };
-    let res = execute(deps.as_mut(), mock_env(), info, msg.clone());
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        borrower.clone(),
+        pause_msg.clone(),
+    );
     match res {
         Err(ContractError::Unauthorized {}) => (),
         _ => panic!("DO NOT ENTER HERE"),
     }
 
-    // Assume execute epoch operation is executed
-    let mut env = mock_env();
-    let info = mock_info(MOCK_CONTRACT_ADDR, &[]);
-    env.block.height += 86400u64;
-
-    deps.querier.with_epoch_state(&[(
-        &"market".to_string(),
-        &(Uint256::from(1000000u64), Decimal256::percent(120)),
-    )]);
-
-    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone()).unwrap();
-    assert_eq!(
-        res.messages,
-        vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
-            contract_addr: "market".to_string(),
-            funds: vec![],
-            msg: to_binary(&MarketExecuteMsg::ExecuteEpochOperations {
-                deposit_rate: Decimal256::from_str("0.000002314814814814").unwrap(),
-                target_deposit_rate: Decimal256::permille(5),
-                threshold_deposit_rate: Decimal256::from_ratio(1u64, 1000000u64),
-                distributed_interest: Uint256::from(1000000u128),
-            })
-            .unwrap(),
-        }))]
-    );
+    let res = execute(deps.as_mut(), env.clone(), info.clone(), pause_msg).unwrap();
     assert_eq!(
         res.attributes,
         vec![
-            attr("action", "update_epoch_state"),
-            attr("deposit_rate", "0.000002314814814814"),
-            attr("aterra_supply", "1000000"),
-            attr("exchange_rate", "1.2"),
-            attr("interest_buffer", "10000000000"),
+            attr("action", "pause_whitelist"),
+            attr("collateral_token", "batom"),
         ]
     );
 
-    // Deposit rate increased
+    let res = query(
+        deps.as_ref(),
+        env.clone(),
+        QueryMsg::Whitelist {
+            collateral_token: Some("batom".to_string()),
+            start_after: None,
+            limit: None,
+            order_by: None,
+        },
+    )
+    .unwrap();
+    let whitelist_res: WhitelistResponse = from_binary(&res).unwrap();
+    assert!(whitelist_res.elems[0].paused);
+
+    // new locks of the paused collateral are rejected
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        borrower.clone(),
+        lock_msg("batom"),
+    );
+    match res {
+        Err(ContractError::CollateralPaused(token)) => assert_eq!(token, "batom"),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+    let _res = execute(
+        deps.as_mut(),
+        env.clone(),
+        borrower.clone(),
+        lock_msg("bluna"),
+    )
+    .unwrap();
+
+    // the locked position still counts towards the borrow limit
+    let res = query(
+        deps.as_ref(),
+        env.clone(),
+        QueryMsg::BorrowLimit {
+            borrower: "addr0000".to_string(),
+            block_time: None,
+        },
+    )
+    .unwrap();
+    let borrow_limit_res: BorrowLimitResponse = from_binary(&res).unwrap();
+    assert_eq!(borrow_limit_res.borrow_limit, Uint256::from(1200u64));
+
+    // the custody of the paused collateral gets no rewards distribution
+    env.block.height += 86400u64;
     deps.querier.with_epoch_state(&[(
         &"market".to_string(),
-        &(Uint256::from(1000000u64), Decimal256::percent(125)),
+        &(Uint256::from(1000000u64), Decimal256::percent(120)),
     )]);
-
-    env.block.height += 86400u64;
-    let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        ExecuteMsg::ExecuteEpochOperations {},
+    )
+    .unwrap();
+    let distribute_rewards_msg = |custody_contract: &str| {
+        SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: custody_contract.to_string(),
+            funds: vec![],
+            msg: to_binary(&CustodyExecuteMsg::DistributeRewards {}).unwrap(),
+        }))
+    };
+    assert!(res
+        .messages
+        .contains(&distribute_rewards_msg("custody_bluna")));
+    assert!(!res
+        .messages
+        .contains(&distribute_rewards_msg("custody_batom")));
+
+    // unlocking keeps working
+    let msg = ExecuteMsg::UnlockCollateral {
+        collaterals: vec![("batom".to_string(), Uint256::from(400u64))],
+    };
+    let res = execute(deps.as_mut(), env.clone(), borrower.clone(), msg).unwrap();
     assert_eq!(
         res.messages,
         vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
-            contract_addr: "market".to_string(),
+            contract_addr: "custody_batom".to_string(),
             funds: vec![],
-            msg: to_binary(&MarketExecuteMsg::ExecuteEpochOperations {
-                deposit_rate: Decimal256::from_str("0.000000482253086419").unwrap(),
-                target_deposit_rate: Decimal256::permille(5),
-                threshold_deposit_rate: Decimal256::from_ratio(1u64, 1000000u64),
-                distributed_interest: Uint256::from(1000000u128),
+            msg: to_binary(&CustodyExecuteMsg::UnlockCollateral {
+                borrower: "addr0000".to_string(),
+                amount: Uint256::from(400u64),
             })
             .unwrap(),
         }))]
     );
+
+    let msg = ExecuteMsg::UnpauseWhitelist {
+        collateral_token: "batom".to_string(),
+    };
+    let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
     assert_eq!(
         res.attributes,
         vec![
-            attr("action", "update_epoch_state"),
-            attr("deposit_rate", "0.000000482253086419"),
-            attr("aterra_supply", "1000000"),
-            attr("exchange_rate", "1.25"),
-            attr("interest_buffer", "10000000000"),
+            attr("action", "unpause_whitelist"),
+            attr("collateral_token", "batom"),
         ]
     );
+    let _res = execute(deps.as_mut(), env, borrower, lock_msg("batom")).unwrap();
+}
 
-    let epoch_state_response = query_epoch_state(
-        deps.as_ref(),
-        Addr::unchecked("market"),
-        env.block.height,
-        None,
-    )
-    .unwrap();
-    let epoch_state = read_epoch_state(deps.as_ref().storage).unwrap();
+#[test]
+fn pause_contract() {
+    let mut deps = mock_dependencies(&[]);
 
-    // deposit rate = 0.000000482253078703
-    assert_eq!(
-        epoch_state,
-        EpochState {
-            deposit_rate: Decimal256::from_ratio(482253086419u64, 1000000000000000000u64),
-            prev_aterra_supply: epoch_state_response.aterra_supply,
-            prev_exchange_rate: epoch_state_response.exchange_rate,
-            prev_interest_buffer: Uint256::from(10000000000u128),
-            last_executed_height: env.block.height,
-        }
+    let mut env = mock_env();
+    let owner = mock_info("owner", &[]);
+    let msg = InstantiateMsg {
+        owner_addr: "owner".to_string(),
+        oracle_contract: "oracle".to_string(),
+        market_contract: "market".to_string(),
+        liquidation_contract: "liquidation".to_string(),
+        collector_contract: "collector".to_string(),
+        stable_denom: "uusd".to_string(),
+        epoch_period: 86400u64,
+        threshold_deposit_rate: Decimal256::permille(3),
+        target_deposit_rate: Decimal256::permille(5),
+        buffer_distribution_factor: Decimal256::percent(20),
+        anc_purchase_factor: Decimal256::percent(20),
+        price_timeframe: 60u64,
+    };
+    let _res = instantiate(deps.as_mut(), env.clone(), owner.clone(), msg).unwrap();
+
+    let msg = ExecuteMsg::Whitelist {
+        name: "bluna".to_string(),
+        symbol: "bluna".to_string(),
+        collateral_token: "bluna".to_string(),
+        custody_contract: "custody_bluna".to_string(),
+        max_ltv: Decimal256::percent(60),
+        decimals: None,
+    };
+    let _res = execute(deps.as_mut(), env.clone(), owner.clone(), msg).unwrap();
+
+    let msg = ExecuteMsg::UpdateConfig {
+        owner_addr: None,
+        oracle_contract: None,
+        market_contract: None,
+        liquidation_contract: None,
+        threshold_deposit_rate: None,
+        target_deposit_rate: None,
+        buffer_distribution_factor: None,
+        anc_purchase_factor: None,
+        epoch_period: None,
+        price_timeframe: None,
+        max_ltv: None,
+        guardian: Some("guardian".to_string()),
+        executor_incentive: None,
+        max_buffer_distribution: None,
+        custody_batch_size: None,
+    };
+    let _res = execute(deps.as_mut(), env.clone(), owner.clone(), msg).unwrap();
+
+    // only the owner or the guardian can pause
+    let guardian = mock_info("guardian", &[]);
+    let borrower = mock_info("addr0000", &[]);
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        borrower.clone(),
+        ExecuteMsg::Pause {},
+    );
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        guardian.clone(),
+        ExecuteMsg::Pause {},
     )
+    .unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![attr("action", "pause"), attr("sender", "guardian")]
+    );
+
+    // collateral movements and epoch operations are frozen
+    let lock_msg = ExecuteMsg::LockCollateral {
+        collaterals: vec![("bluna".to_string(), Uint256::from(1000u64))],
+    };
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        borrower.clone(),
+        lock_msg.clone(),
+    );
+    match res {
+        Err(ContractError::ContractPaused {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        borrower.clone(),
+        ExecuteMsg::UnlockCollateral {
+            collaterals: vec![("bluna".to_string(), Uint256::one())],
+        },
+    );
+    match res {
+        Err(ContractError::ContractPaused {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    env.block.height += 86400;
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        borrower.clone(),
+        ExecuteMsg::ExecuteEpochOperations {},
+    );
+    match res {
+        Err(ContractError::ContractPaused {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // queries keep working
+    let res = query(deps.as_ref(), env.clone(), QueryMsg::Config {}).unwrap();
+    let config_res: ConfigResponse = from_binary(&res).unwrap();
+    assert_eq!(config_res.guardian, Some("guardian".to_string()));
+    assert!(config_res.paused);
+
+    // the guardian cannot unpause, the owner can
+    let res = execute(deps.as_mut(), env.clone(), guardian, ExecuteMsg::Unpause {});
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let res = execute(deps.as_mut(), env.clone(), owner, ExecuteMsg::Unpause {}).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![attr("action", "unpause"), attr("sender", "owner")]
+    );
+
+    let _res = execute(deps.as_mut(), env, borrower, lock_msg).unwrap();
 }
 
 #[test]
-fn lock_collateral() {
-    let mut deps = mock_dependencies(&[]);
+fn execute_epoch_operations() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::from(10000000000u128),
+    }]);
 
+    let mut env = mock_env();
     let info = mock_info("owner", &[]);
     let msg = InstantiateMsg {
         owner_addr: "owner".to_string(),
@@ -707,7 +1245,7 @@ fn lock_collateral() {
         collector_contract: "collector".to_string(),
         stable_denom: "uusd".to_string(),
         epoch_period: 86400u64,
-        threshold_deposit_rate: Decimal256::permille(3),
+        threshold_deposit_rate: Decimal256::from_ratio(1u64, 1000000u64),
         target_deposit_rate: Decimal256::permille(5),
         buffer_distribution_factor: Decimal256::percent(20),
         anc_purchase_factor: Decimal256::percent(20),
@@ -715,7 +1253,7 @@ fn lock_collateral() {
     };
 
     // we can just call .unwrap() to assert this was a success
-    let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+    let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
 
     let batom_collat_token = deps
         .api
@@ -742,9 +1280,10 @@ fn lock_collateral() {
         collateral_token: bluna_collat_token.clone(),
         custody_contract: "custody_bluna".to_string(),
         max_ltv: Decimal256::percent(60),
+        decimals: None,
     };
 
-    let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg);
+    let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg);
 
     let msg = ExecuteMsg::Whitelist {
         name: "batom".to_string(),
@@ -752,60 +1291,2033 @@ fn lock_collateral() {
         collateral_token: batom_collat_token.clone(),
         custody_contract: "custody_batom".to_string(),
         max_ltv: Decimal256::percent(60),
+        decimals: None,
+    };
+
+    let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg);
+
+    let msg = ExecuteMsg::ExecuteEpochOperations {};
+    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone());
+    match res {
+        Err(ContractError::MoneyMarket(MoneyMarketError::EpochNotElapsed { remaining })) => {
+            assert_eq!(remaining, 86400)
+        }
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    env.block.height += 86400u64;
+
+    // If deposit_rate is bigger than threshold_deposit_rate
+    deps.querier.with_epoch_state(&[(
+        &"market".to_string(),
+        &(Uint256::from(1000000u64), Decimal256::percent(120)),
+    )]);
+
+    // (120 / 100 - 1) / 86400
+    // deposit rate = 0.000002314814814814
+    // accrued_buffer = 10,000,000,000
+    // anc_purchase_amount = accrued_buffer * 0.2 = 2,000,000,000
+    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone()).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: "collector".to_string(),
+                amount: vec![deduct_tax(
+                    deps.as_ref(),
+                    Coin {
+                        denom: "uusd".to_string(),
+                        amount: Uint128::from(2_000_000_000u128),
+                    }
+                )
+                .unwrap()],
+            })),
+            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "custody_batom".to_string(),
+                funds: vec![],
+                msg: to_binary(&CustodyExecuteMsg::DistributeRewards {}).unwrap(),
+            })),
+            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "custody_bluna".to_string(),
+                funds: vec![],
+                msg: to_binary(&CustodyExecuteMsg::DistributeRewards {}).unwrap(),
+            })),
+            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "oracle".to_string(),
+                funds: vec![],
+                msg: to_binary(&OracleExecuteMsg::SnapshotPrices {
+                    assets: vec![batom_collat_token.clone(), bluna_collat_token.clone()],
+                })
+                .unwrap(),
+            })),
+            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: MOCK_CONTRACT_ADDR.to_string(),
+                funds: vec![],
+                msg: to_binary(&ExecuteMsg::UpdateEpochState {
+                    interest_buffer: Uint256::from(8_000_000_000u128),
+                    distributed_interest: Uint256::zero(),
+                })
+                .unwrap(),
+            }))
+        ]
+    );
+
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "epoch_operations"),
+            attr("deposit_rate", "0.000002314814814814"),
+            attr("exchange_rate", "1.2"),
+            attr("aterra_supply", "1000000"),
+            attr("distributed_interest", "0"),
+            attr("distributed_interest_gross", "0"),
+            attr("distributed_interest_tax", "0"),
+            attr("anc_purchase_amount", "2000000000"),
+            attr("executor", "owner"),
+            attr("executor_incentive", "0"),
+        ]
+    );
+
+    // store epoch state for test purpose
+    store_epoch_state(
+        deps.as_mut().storage,
+        &EpochState {
+            last_executed_height: env.block.height,
+            prev_exchange_rate: Decimal256::from_str("1.2").unwrap(),
+            prev_aterra_supply: Uint256::from_str("1000000").unwrap(),
+            prev_interest_buffer: Uint256::from_str("9999000000").unwrap(),
+            deposit_rate: Decimal256::from_str("0.000002314814814814").unwrap(),
+        },
+    )
+    .unwrap();
+
+    // If deposit rate is bigger than threshold
+    deps.querier.with_epoch_state(&[(
+        &"market".to_string(),
+        &(Uint256::from(1000000u64), Decimal256::percent(125)),
+    )]);
+
+    deps.querier.with_tax(
+        Decimal::percent(1),
+        &[(&"uusd".to_string(), &Uint128::from(1000000u128))],
+    );
+
+    env.block.height += 86400u64;
+
+    // accrued_buffer = 1,000,000
+    // interest_buffer = 9,999,000,000
+    // (125 / 120 - 1) / 86400
+    // deposit rate = 0.000000482253086419
+    let res = execute(deps.as_mut(), env, info, msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: "collector".to_string(),
+                amount: vec![deduct_tax(
+                    deps.as_ref(),
+                    Coin {
+                        denom: "uusd".to_string(),
+                        amount: Uint128::from(200_000u128),
+                    }
+                )
+                .unwrap()]
+            })),
+            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: "market".to_string(),
+                amount: vec![deduct_tax(
+                    deps.as_ref(),
+                    Coin {
+                        denom: "uusd".to_string(),
+                        amount: Uint128::from(53680u128),
+                    }
+                )
+                .unwrap()]
+            })),
+            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "custody_batom".to_string(),
+                funds: vec![],
+                msg: to_binary(&CustodyExecuteMsg::DistributeRewards {}).unwrap(),
+            })),
+            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "custody_bluna".to_string(),
+                funds: vec![],
+                msg: to_binary(&CustodyExecuteMsg::DistributeRewards {}).unwrap(),
+            })),
+            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "oracle".to_string(),
+                funds: vec![],
+                msg: to_binary(&OracleExecuteMsg::SnapshotPrices {
+                    assets: vec![batom_collat_token.clone(), bluna_collat_token.clone()],
+                })
+                .unwrap(),
+            })),
+            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: MOCK_CONTRACT_ADDR.to_string(),
+                funds: vec![],
+                msg: to_binary(&ExecuteMsg::UpdateEpochState {
+                    interest_buffer: Uint256::from(9999746320u128),
+                    distributed_interest: Uint256::from(53148u128),
+                })
+                .unwrap(),
+            }))
+        ]
+    );
+
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "epoch_operations"),
+            attr("deposit_rate", "0.000000482253086419"),
+            attr("exchange_rate", "1.25"),
+            attr("aterra_supply", "1000000"),
+            attr("distributed_interest", "53148"),
+            attr("distributed_interest_gross", "53680"),
+            attr("distributed_interest_tax", "532"),
+            attr("anc_purchase_amount", "200000"),
+            attr("executor", "owner"),
+            attr("executor_incentive", "0"),
+        ]
+    );
+}
+
+#[test]
+fn execute_epoch_operations_max_buffer_distribution() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::from(10000000000u128),
+    }]);
+
+    let mut env = mock_env();
+    let info = mock_info("owner", &[]);
+    let msg = InstantiateMsg {
+        owner_addr: "owner".to_string(),
+        oracle_contract: "oracle".to_string(),
+        market_contract: "market".to_string(),
+        liquidation_contract: "liquidation".to_string(),
+        collector_contract: "collector".to_string(),
+        stable_denom: "uusd".to_string(),
+        epoch_period: 86400u64,
+        threshold_deposit_rate: Decimal256::from_ratio(1u64, 1000000u64),
+        target_deposit_rate: Decimal256::permille(5),
+        buffer_distribution_factor: Decimal256::percent(20),
+        anc_purchase_factor: Decimal256::percent(20),
+        price_timeframe: 60u64,
+    };
+    let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+    let update_cap = |max_buffer_distribution| ExecuteMsg::UpdateConfig {
+        owner_addr: None,
+        oracle_contract: None,
+        market_contract: None,
+        liquidation_contract: None,
+        threshold_deposit_rate: None,
+        target_deposit_rate: None,
+        buffer_distribution_factor: None,
+        anc_purchase_factor: None,
+        epoch_period: None,
+        price_timeframe: None,
+        max_ltv: None,
+        guardian: None,
+        executor_incentive: None,
+        max_buffer_distribution: Some(max_buffer_distribution),
+        custody_batch_size: None,
+    };
+    let epoch_state = EpochState {
+        last_executed_height: env.block.height,
+        prev_exchange_rate: Decimal256::percent(120),
+        prev_aterra_supply: Uint256::from(1000000u64),
+        prev_interest_buffer: Uint256::from(9999000000u64),
+        deposit_rate: Decimal256::from_str("0.000002314814814814").unwrap(),
+    };
+
+    deps.querier.with_epoch_state(&[(
+        &"market".to_string(),
+        &(Uint256::from(1000000u64), Decimal256::percent(125)),
+    )]);
+    deps.querier.with_tax(
+        Decimal::percent(1),
+        &[(&"uusd".to_string(), &Uint128::from(1000000u128))],
+    );
+
+    // missing_deposits = 53,680 is above the cap
+    let _res = execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        update_cap(Uint256::from(10000u64)),
+    )
+    .unwrap();
+    store_epoch_state(deps.as_mut().storage, &epoch_state).unwrap();
+    env.block.height += 86400u64;
+
+    let msg = ExecuteMsg::ExecuteEpochOperations {};
+    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone()).unwrap();
+    assert_eq!(
+        res.messages[1],
+        SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: "market".to_string(),
+            amount: vec![Coin {
+                denom: "uusd".to_string(),
+                amount: Uint128::from(9900u128),
+            }],
+        }))
+    );
+    assert_eq!(
+        res.messages.last().unwrap(),
+        &SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: MOCK_CONTRACT_ADDR.to_string(),
+            funds: vec![],
+            msg: to_binary(&ExecuteMsg::UpdateEpochState {
+                interest_buffer: Uint256::from(9999790000u128),
+                distributed_interest: Uint256::from(9900u128),
+            })
+            .unwrap(),
+        }))
+    );
+    assert_eq!(
+        res.attributes[4..7],
+        [
+            attr("distributed_interest", "9900"),
+            attr("distributed_interest_gross", "10000"),
+            attr("distributed_interest_tax", "100"),
+        ]
+    );
+
+    // the cap does not bind, missing_deposits is distributed as before
+    let _res = execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        update_cap(Uint256::from(100000u64)),
+    )
+    .unwrap();
+    store_epoch_state(
+        deps.as_mut().storage,
+        &EpochState {
+            last_executed_height: env.block.height,
+            ..epoch_state
+        },
+    )
+    .unwrap();
+    env.block.height += 86400u64;
+
+    let res = execute(deps.as_mut(), env, info, msg).unwrap();
+    assert_eq!(
+        res.attributes[4..7],
+        [
+            attr("distributed_interest", "53148"),
+            attr("distributed_interest_gross", "53680"),
+            attr("distributed_interest_tax", "532"),
+        ]
+    );
+}
+
+#[test]
+fn execute_epoch_operations_paginated() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::from(10000000000u128),
+    }]);
+
+    let mut env = mock_env();
+    let info = mock_info("owner", &[]);
+    let msg = InstantiateMsg {
+        owner_addr: "owner".to_string(),
+        oracle_contract: "oracle".to_string(),
+        market_contract: "market".to_string(),
+        liquidation_contract: "liquidation".to_string(),
+        collector_contract: "collector".to_string(),
+        stable_denom: "uusd".to_string(),
+        epoch_period: 86400u64,
+        threshold_deposit_rate: Decimal256::from_ratio(1u64, 1000000u64),
+        target_deposit_rate: Decimal256::permille(5),
+        buffer_distribution_factor: Decimal256::percent(20),
+        anc_purchase_factor: Decimal256::percent(20),
+        price_timeframe: 60u64,
+    };
+    let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+    let update_batch_size = |custody_batch_size| ExecuteMsg::UpdateConfig {
+        owner_addr: None,
+        oracle_contract: None,
+        market_contract: None,
+        liquidation_contract: None,
+        threshold_deposit_rate: None,
+        target_deposit_rate: None,
+        buffer_distribution_factor: None,
+        anc_purchase_factor: None,
+        epoch_period: None,
+        price_timeframe: None,
+        max_ltv: None,
+        guardian: None,
+        executor_incentive: None,
+        max_buffer_distribution: None,
+        custody_batch_size: Some(custody_batch_size),
+    };
+    for custody_batch_size in [0u32, 31u32] {
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            info.clone(),
+            update_batch_size(custody_batch_size),
+        );
+        match res {
+            Err(ContractError::InvalidCustodyBatchSize(30)) => (),
+            _ => panic!("DO NOT ENTER HERE"),
+        }
+    }
+    let _res = execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        update_batch_size(2),
+    )
+    .unwrap();
+
+    // whitelist three collaterals, in the order of their canonical addresses
+    let mut collat_tokens: Vec<String> = vec![];
+    for (i, symbol) in ["batom", "bluna", "beth"].iter().enumerate() {
+        let mut canonical = vec![0u8; 54];
+        canonical[..4].copy_from_slice(&[1, 1, 1, i as u8 + 1]);
+        let collat_token = deps
+            .api
+            .addr_humanize(&CanonicalAddr::from(canonical))
+            .unwrap()
+            .to_string();
+
+        let msg = ExecuteMsg::Whitelist {
+            name: symbol.to_string(),
+            symbol: symbol.to_string(),
+            collateral_token: collat_token.clone(),
+            custody_contract: format!("custody_{}", symbol),
+            max_ltv: Decimal256::percent(60),
+            decimals: None,
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        collat_tokens.push(collat_token);
+    }
+
+    env.block.height += 86400u64;
+    deps.querier.with_epoch_state(&[(
+        &"market".to_string(),
+        &(Uint256::from(1000000u64), Decimal256::percent(120)),
+    )]);
+
+    let distribute_rewards = |custody_contract: &str| {
+        SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: custody_contract.to_string(),
+            funds: vec![],
+            msg: to_binary(&CustodyExecuteMsg::DistributeRewards {}).unwrap(),
+        }))
+    };
+    let snapshot_prices = |assets: &[String]| {
+        SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "oracle".to_string(),
+            funds: vec![],
+            msg: to_binary(&OracleExecuteMsg::SnapshotPrices {
+                assets: assets.to_vec(),
+            })
+            .unwrap(),
+        }))
+    };
+
+    // the first call only rewards the first two custodies
+    let msg = ExecuteMsg::ExecuteEpochOperations {};
+    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone()).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![
+            distribute_rewards("custody_batom"),
+            distribute_rewards("custody_bluna"),
+            snapshot_prices(&collat_tokens[..2]),
+        ]
+    );
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "distribute_custody_rewards"),
+            attr("last_collateral_token", collat_tokens[1].clone()),
+        ]
+    );
+
+    // the second call rewards the last custody and finishes the epoch
+    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone()).unwrap();
+    assert_eq!(
+        res.messages[1..],
+        [
+            distribute_rewards("custody_beth"),
+            snapshot_prices(&collat_tokens[2..]),
+            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: MOCK_CONTRACT_ADDR.to_string(),
+                funds: vec![],
+                msg: to_binary(&ExecuteMsg::UpdateEpochState {
+                    interest_buffer: Uint256::from(8_000_000_000u128),
+                    distributed_interest: Uint256::zero(),
+                })
+                .unwrap(),
+            })),
+        ]
+    );
+    assert_eq!(res.attributes[0], attr("action", "epoch_operations"));
+
+    // the cursor is cleared, so the next epoch starts from the first custody
+    let res = execute(deps.as_mut(), env, info, msg).unwrap();
+    assert_eq!(
+        res.attributes[1],
+        attr("last_collateral_token", collat_tokens[1].clone())
+    );
+}
+
+#[test]
+fn execute_epoch_operations_late_and_shrinking() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::from(10000000000u128),
+    }]);
+
+    let mut env = mock_env();
+    let info = mock_info("owner", &[]);
+    let msg = InstantiateMsg {
+        owner_addr: "owner".to_string(),
+        oracle_contract: "oracle".to_string(),
+        market_contract: "market".to_string(),
+        liquidation_contract: "liquidation".to_string(),
+        collector_contract: "collector".to_string(),
+        stable_denom: "uusd".to_string(),
+        epoch_period: 86400u64,
+        threshold_deposit_rate: Decimal256::from_ratio(1u64, 1000000u64),
+        target_deposit_rate: Decimal256::permille(5),
+        buffer_distribution_factor: Decimal256::percent(20),
+        anc_purchase_factor: Decimal256::percent(20),
+        price_timeframe: 60u64,
+    };
+    let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+    // executed one epoch late, the growth is spread over all elapsed blocks
+    env.block.height += 2 * 86400u64;
+    deps.querier.with_epoch_state(&[(
+        &"market".to_string(),
+        &(Uint256::from(1000000u64), Decimal256::percent(120)),
+    )]);
+
+    // (120 / 100 - 1) / 172800
+    // deposit rate = 0.000001157407407407
+    let msg = ExecuteMsg::ExecuteEpochOperations {};
+    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone()).unwrap();
+    assert_eq!(
+        res.attributes[..2],
+        [
+            attr("action", "epoch_operations"),
+            attr("deposit_rate", "0.000001157407407407"),
+        ]
+    );
+
+    store_epoch_state(
+        deps.as_mut().storage,
+        &EpochState {
+            last_executed_height: env.block.height,
+            prev_exchange_rate: Decimal256::percent(120),
+            prev_aterra_supply: Uint256::from(1000000u64),
+            prev_interest_buffer: Uint256::from(10000000000u64),
+            deposit_rate: Decimal256::from_str("0.000001157407407407").unwrap(),
+        },
+    )
+    .unwrap();
+
+    // a shrinking exchange rate gives a zero deposit rate instead of failing,
+    // so the interest buffer is distributed to the depositors
+    env.block.height += 86400u64;
+    deps.querier.with_epoch_state(&[(
+        &"market".to_string(),
+        &(Uint256::from(1000000u64), Decimal256::percent(110)),
+    )]);
+
+    let res = execute(deps.as_mut(), env, info, msg).unwrap();
+    assert_eq!(
+        res.attributes[..3],
+        [
+            attr("action", "epoch_operations"),
+            attr("deposit_rate", "0"),
+            attr("exchange_rate", "1.1"),
+        ]
+    );
+    assert_ne!(
+        res.attributes
+            .iter()
+            .find(|attr| attr.key == "distributed_interest")
+            .unwrap()
+            .value,
+        "0"
+    );
+}
+
+#[test]
+fn execute_epoch_operations_executor_incentive() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::from(10000000000u128),
+    }]);
+
+    let mut env = mock_env();
+    let info = mock_info("owner", &[]);
+    let msg = InstantiateMsg {
+        owner_addr: "owner".to_string(),
+        oracle_contract: "oracle".to_string(),
+        market_contract: "market".to_string(),
+        liquidation_contract: "liquidation".to_string(),
+        collector_contract: "collector".to_string(),
+        stable_denom: "uusd".to_string(),
+        epoch_period: 86400u64,
+        threshold_deposit_rate: Decimal256::from_ratio(1u64, 1000000u64),
+        target_deposit_rate: Decimal256::permille(5),
+        buffer_distribution_factor: Decimal256::percent(20),
+        anc_purchase_factor: Decimal256::percent(20),
+        price_timeframe: 60u64,
+    };
+    let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+    let update_incentive = |executor_incentive| ExecuteMsg::UpdateConfig {
+        owner_addr: None,
+        oracle_contract: None,
+        market_contract: None,
+        liquidation_contract: None,
+        threshold_deposit_rate: None,
+        target_deposit_rate: None,
+        buffer_distribution_factor: None,
+        anc_purchase_factor: None,
+        epoch_period: None,
+        price_timeframe: None,
+        max_ltv: None,
+        guardian: None,
+        executor_incentive: Some(executor_incentive),
+        max_buffer_distribution: None,
+        custody_batch_size: None,
+    };
+    let _res = execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        update_incentive(Uint128::from(1000000u64)),
+    )
+    .unwrap();
+
+    env.block.height += 86400u64;
+    deps.querier.with_epoch_state(&[(
+        &"market".to_string(),
+        &(Uint256::from(1000000u64), Decimal256::percent(120)),
+    )]);
+
+    // accrued_buffer = 10,000,000,000
+    // anc_purchase_amount = accrued_buffer * 0.2 = 2,000,000,000
+    // interest_buffer = 8,000,000,000 - 1,000,000 paid to the executor
+    let executor = mock_info("executor", &[]);
+    let msg = ExecuteMsg::ExecuteEpochOperations {};
+    let res = execute(deps.as_mut(), env.clone(), executor.clone(), msg.clone()).unwrap();
+    assert_eq!(
+        res.messages[1],
+        SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: "executor".to_string(),
+            amount: vec![deduct_tax(
+                deps.as_ref(),
+                Coin {
+                    denom: "uusd".to_string(),
+                    amount: Uint128::from(1_000_000u128),
+                }
+            )
+            .unwrap()],
+        }))
+    );
+    assert_eq!(
+        res.messages.last().unwrap(),
+        &SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: MOCK_CONTRACT_ADDR.to_string(),
+            funds: vec![],
+            msg: to_binary(&ExecuteMsg::UpdateEpochState {
+                interest_buffer: Uint256::from(7_999_000_000u128),
+                distributed_interest: Uint256::zero(),
+            })
+            .unwrap(),
+        }))
+    );
+    assert_eq!(
+        res.attributes[8..],
+        [
+            attr("executor", "executor"),
+            attr("executor_incentive", "1000000"),
+        ]
+    );
+
+    // the payment is skipped when the buffer cannot cover it
+    let _res = execute(
+        deps.as_mut(),
+        env.clone(),
+        info,
+        update_incentive(Uint128::from(10_000_000_001u128)),
+    )
+    .unwrap();
+    store_epoch_state(
+        deps.as_mut().storage,
+        &EpochState {
+            last_executed_height: env.block.height,
+            prev_exchange_rate: Decimal256::percent(120),
+            prev_aterra_supply: Uint256::from(1000000u64),
+            prev_interest_buffer: Uint256::from(10000000000u64),
+            deposit_rate: Decimal256::from_str("0.000002314814814814").unwrap(),
+        },
+    )
+    .unwrap();
+    env.block.height += 86400u64;
+
+    let res = execute(deps.as_mut(), env, executor, msg).unwrap();
+    assert!(!res.messages.iter().any(|msg| matches!(
+        &msg.msg,
+        CosmosMsg::Bank(BankMsg::Send { to_address, .. }) if to_address == "executor"
+    )));
+    assert_eq!(
+        res.attributes[8..],
+        [
+            attr("executor", "executor"),
+            attr("executor_incentive", "0"),
+        ]
+    );
+}
+
+#[test]
+fn execute_epoch_operations_deposit_rate_ceiling() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::from(10000000000u128),
+    }]);
+
+    let mut env = mock_env();
+    let info = mock_info("owner", &[]);
+    let msg = InstantiateMsg {
+        owner_addr: "owner".to_string(),
+        oracle_contract: "oracle".to_string(),
+        market_contract: "market".to_string(),
+        liquidation_contract: "liquidation".to_string(),
+        collector_contract: "collector".to_string(),
+        stable_denom: "uusd".to_string(),
+        epoch_period: 86400u64,
+        threshold_deposit_rate: Decimal256::from_ratio(1u64, 1000000u64),
+        target_deposit_rate: Decimal256::permille(5),
+        buffer_distribution_factor: Decimal256::percent(20),
+        anc_purchase_factor: Decimal256::percent(20),
+        price_timeframe: 60u64,
+    };
+    let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+    store_epoch_state(
+        deps.as_mut().storage,
+        &EpochState {
+            last_executed_height: env.block.height,
+            prev_exchange_rate: Decimal256::from_str("1.2").unwrap(),
+            prev_aterra_supply: Uint256::from_str("1000000").unwrap(),
+            prev_interest_buffer: Uint256::from_str("9999000000").unwrap(),
+            deposit_rate: Decimal256::from_str("0.000002314814814814").unwrap(),
+        },
+    )
+    .unwrap();
+
+    deps.querier.with_epoch_state(&[(
+        &"market".to_string(),
+        &(Uint256::from(1000000u64), Decimal256::percent(125)),
+    )]);
+
+    // the distribution model caps the subsidized rate below the threshold
+    deps.querier.with_deposit_rate_bounds(
+        Decimal256::zero(),
+        Decimal256::from_ratio(5u64, 10000000u64),
+    );
+
+    env.block.height += 86400u64;
+
+    // deposit rate = 0.000000482253086419
+    // missing_deposit_rate = 0.0000005 - 0.000000482253086419
+    // distributed_interest = 1,200,000 * missing_deposit_rate * 86400
+    // (53680 without the ceiling)
+    let res = execute(
+        deps.as_mut(),
+        env,
+        info,
+        ExecuteMsg::ExecuteEpochOperations {},
+    )
+    .unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "epoch_operations"),
+            attr("deposit_rate", "0.000000482253086419"),
+            attr("exchange_rate", "1.25"),
+            attr("aterra_supply", "1000000"),
+            attr("distributed_interest", "1840"),
+            attr("distributed_interest_gross", "1840"),
+            attr("distributed_interest_tax", "0"),
+            attr("anc_purchase_amount", "200000"),
+            attr("executor", "owner"),
+            attr("executor_incentive", "0"),
+        ]
+    );
+}
+
+#[test]
+fn update_epoch_state() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::from(10000000000u128),
+    }]);
+
+    let info = mock_info("owner", &[]);
+    let msg = InstantiateMsg {
+        owner_addr: "owner".to_string(),
+        oracle_contract: "oracle".to_string(),
+        market_contract: "market".to_string(),
+        liquidation_contract: "liquidation".to_string(),
+        collector_contract: "collector".to_string(),
+        stable_denom: "uusd".to_string(),
+        epoch_period: 86400u64,
+        threshold_deposit_rate: Decimal256::from_ratio(1u64, 1000000u64),
+        target_deposit_rate: Decimal256::permille(5),
+        buffer_distribution_factor: Decimal256::percent(20),
+        anc_purchase_factor: Decimal256::percent(20),
+        price_timeframe: 60u64,
+    };
+
+    // we can just call .unwrap() to assert this was a success
+    let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    // store whitelist elems
+    let msg = ExecuteMsg::Whitelist {
+        name: "bluna".to_string(),
+        symbol: "bluna".to_string(),
+        collateral_token: "bluna".to_string(),
+        custody_contract: "custody_bluna".to_string(),
+        max_ltv: Decimal256::percent(60),
+        decimals: None,
+    };
+
+    let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg);
+
+    let msg = ExecuteMsg::Whitelist {
+        name: "batom".to_string(),
+        symbol: "batom".to_string(),
+        collateral_token: "batom".to_string(),
+        custody_contract: "custody_batom".to_string(),
+        max_ltv: Decimal256::percent(60),
+        decimals: None,
+    };
+
+    let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg);
+
+    // only contract itself can execute update_epoch_state
+    let msg = ExecuteMsg::UpdateEpochState {
+        interest_buffer: Uint256::from(10000000000u128),
+        distributed_interest: Uint256::from(1000000u128),
+    };
+    let res = execute(deps.as_mut(), mock_env(), info, msg.clone());
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // Assume execute epoch operation is executed
+    let mut env = mock_env();
+    let info = mock_info(MOCK_CONTRACT_ADDR, &[]);
+    env.block.height += 86400u64;
+
+    deps.querier.with_epoch_state(&[(
+        &"market".to_string(),
+        &(Uint256::from(1000000u64), Decimal256::percent(120)),
+    )]);
+
+    // the emission rate accepted by the market is recorded
+    // in the distribution model history
+    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone()).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "market".to_string(),
+                funds: vec![],
+                msg: to_binary(&MarketExecuteMsg::ExecuteEpochOperations {
+                    deposit_rate: Decimal256::from_str("0.000002314814814814").unwrap(),
+                    target_deposit_rate: Decimal256::permille(5),
+                    threshold_deposit_rate: Decimal256::from_ratio(1u64, 1000000u64),
+                    distributed_interest: Uint256::from(1000000u128),
+                })
+                .unwrap(),
+            })),
+            SubMsg::reply_on_error(
+                CosmosMsg::Wasm(WasmMsg::Execute {
+                    contract_addr: "distribution".to_string(),
+                    funds: vec![],
+                    msg: to_binary(&DistributionExecuteMsg::UpdateEmissionRate {
+                        emission_rate: Decimal256::from_uint256(100u64),
+                        deposit_rate: Decimal256::from_str("0.000002314814814814").unwrap(),
+                        target_deposit_rate: Decimal256::permille(5),
+                        threshold_deposit_rate: Decimal256::from_ratio(1u64, 1000000u64),
+                    })
+                    .unwrap(),
+                }),
+                UPDATE_EMISSION_RATE_OPERATION,
+            ),
+        ]
+    );
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "update_epoch_state"),
+            attr("deposit_rate", "0.000002314814814814"),
+            attr("aterra_supply", "1000000"),
+            attr("exchange_rate", "1.2"),
+            attr("interest_buffer", "10000000000"),
+            attr("anc_emission_rate", "100"),
+        ]
+    );
+
+    // a distribution model that rejects the update, e.g. one without
+    // the overseer registered, leaves the epoch state update in place
+    let res = reply(
+        deps.as_mut(),
+        env.clone(),
+        Reply {
+            id: UPDATE_EMISSION_RATE_OPERATION,
+            result: ContractResult::Err("Unauthorized".to_string()),
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "update_emission_rate_failed"),
+            attr("error", "Unauthorized"),
+        ]
+    );
+    let res = reply(
+        deps.as_mut(),
+        env.clone(),
+        Reply {
+            id: 0,
+            result: ContractResult::Err("Unauthorized".to_string()),
+        },
+    );
+    match res {
+        Err(ContractError::InvalidReplyId {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // Deposit rate increased
+    deps.querier.with_epoch_state(&[(
+        &"market".to_string(),
+        &(Uint256::from(1000000u64), Decimal256::percent(125)),
+    )]);
+
+    env.block.height += 86400u64;
+    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone()).unwrap();
+    assert_eq!(res.messages.len(), 2);
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "market".to_string(),
+            funds: vec![],
+            msg: to_binary(&MarketExecuteMsg::ExecuteEpochOperations {
+                deposit_rate: Decimal256::from_str("0.000000482253086419").unwrap(),
+                target_deposit_rate: Decimal256::permille(5),
+                threshold_deposit_rate: Decimal256::from_ratio(1u64, 1000000u64),
+                distributed_interest: Uint256::from(1000000u128),
+            })
+            .unwrap(),
+        }))
+    );
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "update_epoch_state"),
+            attr("deposit_rate", "0.000000482253086419"),
+            attr("aterra_supply", "1000000"),
+            attr("exchange_rate", "1.25"),
+            attr("interest_buffer", "10000000000"),
+            attr("anc_emission_rate", "100"),
+        ]
+    );
+
+    let epoch_state_response = query_epoch_state(
+        deps.as_ref(),
+        Addr::unchecked("market"),
+        env.block.height,
+        None,
+    )
+    .unwrap();
+    let epoch_state = read_epoch_state(deps.as_ref().storage).unwrap();
+
+    // deposit rate = 0.000000482253078703
+    assert_eq!(
+        epoch_state,
+        EpochState {
+            deposit_rate: Decimal256::from_ratio(482253086419u64, 1000000000000000000u64),
+            prev_aterra_supply: epoch_state_response.aterra_supply,
+            prev_exchange_rate: epoch_state_response.exchange_rate,
+            prev_interest_buffer: Uint256::from(10000000000u128),
+            last_executed_height: env.block.height,
+        }
+    );
+
+    let res = query(deps.as_ref(), env.clone(), QueryMsg::DistributionParams {}).unwrap();
+    let distribution_params: DistributionParamsResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        distribution_params,
+        DistributionParamsResponse {
+            target_deposit_rate: Decimal256::permille(5),
+            threshold_deposit_rate: Decimal256::from_ratio(1u64, 1000000u64),
+            buffer_distribution_factor: Decimal256::percent(20),
+            deposit_rate: Decimal256::from_ratio(482253086419u64, 1000000000000000000u64),
+        }
+    );
+
+    // a shrinking exchange rate is recorded as a zero deposit rate
+    deps.querier.with_epoch_state(&[(
+        &"market".to_string(),
+        &(Uint256::from(1000000u64), Decimal256::percent(110)),
+    )]);
+
+    env.block.height += 86400u64;
+    let res = execute(deps.as_mut(), env, info, msg).unwrap();
+    assert_eq!(res.attributes[1], attr("deposit_rate", "0"));
+}
+
+#[test]
+fn lock_collateral() {
+    let mut deps = mock_dependencies(&[]);
+
+    let info = mock_info("owner", &[]);
+    let msg = InstantiateMsg {
+        owner_addr: "owner".to_string(),
+        oracle_contract: "oracle".to_string(),
+        market_contract: "market".to_string(),
+        liquidation_contract: "liquidation".to_string(),
+        collector_contract: "collector".to_string(),
+        stable_denom: "uusd".to_string(),
+        epoch_period: 86400u64,
+        threshold_deposit_rate: Decimal256::permille(3),
+        target_deposit_rate: Decimal256::permille(5),
+        buffer_distribution_factor: Decimal256::percent(20),
+        anc_purchase_factor: Decimal256::percent(20),
+        price_timeframe: 60u64,
+    };
+
+    // we can just call .unwrap() to assert this was a success
+    let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    let batom_collat_token = deps
+        .api
+        .addr_humanize(&CanonicalAddr::from(vec![
+            1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        ]))
+        .unwrap()
+        .to_string();
+
+    let bluna_collat_token = deps
+        .api
+        .addr_humanize(&CanonicalAddr::from(vec![
+            1, 1, 1, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        ]))
+        .unwrap()
+        .to_string();
+
+    // store whitelist elems
+    let msg = ExecuteMsg::Whitelist {
+        name: "bluna".to_string(),
+        symbol: "bluna".to_string(),
+        collateral_token: bluna_collat_token.clone(),
+        custody_contract: "custody_bluna".to_string(),
+        max_ltv: Decimal256::percent(60),
+        decimals: None,
+    };
+
+    let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg);
+
+    let msg = ExecuteMsg::Whitelist {
+        name: "batom".to_string(),
+        symbol: "batom".to_string(),
+        collateral_token: batom_collat_token.clone(),
+        custody_contract: "custody_batom".to_string(),
+        max_ltv: Decimal256::percent(60),
+        decimals: None,
+    };
+
+    let _res = execute(deps.as_mut(), mock_env(), info, msg);
+
+    // zero amounts, duplicated tokens and native funds are rejected
+    let info = mock_info("addr0000", &[]);
+    let invalid_msg = ExecuteMsg::LockCollateral {
+        collaterals: vec![
+            (bluna_collat_token.clone(), Uint256::from(1000000u64)),
+            (batom_collat_token.clone(), Uint256::zero()),
+        ],
+    };
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), invalid_msg).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "Collateral amount must be greater than 0: {}",
+            batom_collat_token
+        )
+    );
+
+    let invalid_msg = ExecuteMsg::LockCollateral {
+        collaterals: vec![
+            (bluna_collat_token.clone(), Uint256::from(1000000u64)),
+            (bluna_collat_token.clone(), Uint256::from(1000000u64)),
+        ],
+    };
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), invalid_msg).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "Collateral is listed more than once: {}",
+            bluna_collat_token
+        )
+    );
+
+    let invalid_msg = ExecuteMsg::LockCollateral {
+        collaterals: vec![(bluna_collat_token.clone(), Uint256::from(1000000u64))],
+    };
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &coins(100, "uusd")),
+        invalid_msg,
+    )
+    .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Native funds are not accepted when locking collaterals"
+    );
+
+    let msg = ExecuteMsg::LockCollateral {
+        collaterals: vec![
+            (bluna_collat_token.clone(), Uint256::from(1000000u64)),
+            (batom_collat_token.clone(), Uint256::from(10000000u64)),
+        ],
+    };
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "custody_bluna".to_string(),
+                funds: vec![],
+                msg: to_binary(&CustodyExecuteMsg::LockCollateral {
+                    borrower: "addr0000".to_string(),
+                    amount: Uint256::from(1000000u64),
+                })
+                .unwrap(),
+            })),
+            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "custody_batom".to_string(),
+                funds: vec![],
+                msg: to_binary(&CustodyExecuteMsg::LockCollateral {
+                    borrower: "addr0000".to_string(),
+                    amount: Uint256::from(10000000u64),
+                })
+                .unwrap(),
+            }))
+        ]
+    );
+
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "lock_collateral"),
+            attr("borrower", "addr0000"),
+            attr(
+                "collaterals",
+                format!(
+                    "1000000{},10000000{}",
+                    bluna_collat_token, batom_collat_token
+                )
+            ),
+            attr("collateral_token", bluna_collat_token.clone()),
+            attr("collateral_amount", "1000000"),
+            attr("collateral_token", batom_collat_token.clone()),
+            attr("collateral_amount", "10000000"),
+        ]
+    );
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::Collaterals {
+            borrower: "addr0000".to_string(),
+        },
+    )
+    .unwrap();
+    let collaterals_res: CollateralsResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        collaterals_res,
+        CollateralsResponse {
+            borrower: "addr0000".to_string(),
+            collaterals: vec![
+                (batom_collat_token.clone(), Uint256::from(10000000u64)),
+                (bluna_collat_token.clone(), Uint256::from(1000000u64)),
+            ]
+        }
+    );
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::AllCollaterals {
+            start_after: None,
+            limit: None,
+            order_by: None,
+        },
+    )
+    .unwrap();
+    let all_collaterals_res: AllCollateralsResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        all_collaterals_res,
+        AllCollateralsResponse {
+            all_collaterals: vec![CollateralsResponse {
+                borrower: "addr0000".to_string(),
+                collaterals: vec![
+                    (batom_collat_token, Uint256::from(10000000u64)),
+                    (bluna_collat_token, Uint256::from(1000000u64)),
+                ]
+            }]
+        }
+    );
+}
+
+#[test]
+fn paginate_whitelist_and_collaterals() {
+    let mut deps = mock_dependencies(&[]);
+
+    let info = mock_info("owner", &[]);
+    let msg = InstantiateMsg {
+        owner_addr: "owner".to_string(),
+        oracle_contract: "oracle".to_string(),
+        market_contract: "market".to_string(),
+        liquidation_contract: "liquidation".to_string(),
+        collector_contract: "collector".to_string(),
+        stable_denom: "uusd".to_string(),
+        epoch_period: 86400u64,
+        threshold_deposit_rate: Decimal256::permille(3),
+        target_deposit_rate: Decimal256::permille(5),
+        buffer_distribution_factor: Decimal256::percent(20),
+        anc_purchase_factor: Decimal256::percent(20),
+        price_timeframe: 60u64,
+    };
+    let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    // more entries than the maximum page size
+    for i in 0..31 {
+        let msg = ExecuteMsg::Whitelist {
+            name: format!("token{:04}", i),
+            symbol: format!("token{:04}", i),
+            collateral_token: format!("token{:04}", i),
+            custody_contract: format!("custody{:04}", i),
+            max_ltv: Decimal256::percent(60),
+            decimals: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+    }
+    for i in 0..5 {
+        let msg = ExecuteMsg::LockCollateral {
+            collaterals: vec![(format!("token{:04}", i), Uint256::from(100u64))],
+        };
+        let borrower = mock_info(&format!("addr{:04}", i), &[]);
+        let _res = execute(deps.as_mut(), mock_env(), borrower, msg).unwrap();
+    }
+
+    let whitelist_page =
+        |start_after: Option<String>, limit: Option<u32>, order_by: Option<OrderBy>| {
+            let res = query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::Whitelist {
+                    collateral_token: None,
+                    start_after,
+                    limit,
+                    order_by,
+                },
+            )
+            .unwrap();
+            from_binary::<WhitelistResponse>(&res)
+                .unwrap()
+                .elems
+                .into_iter()
+                .map(|elem| elem.collateral_token)
+                .collect::<Vec<String>>()
+        };
+
+    // limit defaults to 10 and is clamped to 30
+    assert_eq!(whitelist_page(None, None, None).len(), 10);
+    assert_eq!(whitelist_page(None, Some(10000), None).len(), 30);
+
+    // paging with an exclusive start_after visits every entry exactly once
+    let ascending = whitelist_page(None, Some(30), None);
+    let mut paged: Vec<String> = vec![];
+    loop {
+        let page = whitelist_page(paged.last().cloned(), Some(7), None);
+        if page.is_empty() {
+            break;
+        }
+        paged.extend(page);
+    }
+    assert_eq!(paged.len(), 31);
+    assert_eq!(paged[..30], ascending[..]);
+
+    let mut paged_desc: Vec<String> = vec![];
+    loop {
+        let page = whitelist_page(
+            paged_desc.last().cloned(),
+            Some(7),
+            Some(OrderBy::Descending),
+        );
+        if page.is_empty() {
+            break;
+        }
+        paged_desc.extend(page);
+    }
+    paged_desc.reverse();
+    assert_eq!(paged_desc, paged);
+
+    let collaterals_page = |start_after: Option<String>, order_by: Option<OrderBy>| {
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::AllCollaterals {
+                start_after,
+                limit: Some(2),
+                order_by,
+            },
+        )
+        .unwrap();
+        from_binary::<AllCollateralsResponse>(&res)
+            .unwrap()
+            .all_collaterals
+            .into_iter()
+            .map(|elem| elem.borrower)
+            .collect::<Vec<String>>()
+    };
+
+    let mut borrowers: Vec<String> = vec![];
+    loop {
+        let page = collaterals_page(borrowers.last().cloned(), None);
+        if page.is_empty() {
+            break;
+        }
+        borrowers.extend(page);
+    }
+    let mut sorted = borrowers.clone();
+    sorted.sort();
+    assert_eq!(
+        sorted,
+        (0..5)
+            .map(|i| format!("addr{:04}", i))
+            .collect::<Vec<String>>()
+    );
+
+    let mut borrowers_desc: Vec<String> = vec![];
+    loop {
+        let page = collaterals_page(borrowers_desc.last().cloned(), Some(OrderBy::Descending));
+        if page.is_empty() {
+            break;
+        }
+        borrowers_desc.extend(page);
+    }
+    borrowers_desc.reverse();
+    assert_eq!(borrowers_desc, borrowers);
+
+    // the order is stable between identical queries
+    assert_eq!(collaterals_page(None, None), borrowers[..2]);
+}
+
+#[test]
+fn query_collaterals_batch() {
+    let mut deps = mock_dependencies(&[]);
+
+    let info = mock_info("owner", &[]);
+    let msg = InstantiateMsg {
+        owner_addr: "owner".to_string(),
+        oracle_contract: "oracle".to_string(),
+        market_contract: "market".to_string(),
+        liquidation_contract: "liquidation".to_string(),
+        collector_contract: "collector".to_string(),
+        stable_denom: "uusd".to_string(),
+        epoch_period: 86400u64,
+        threshold_deposit_rate: Decimal256::permille(3),
+        target_deposit_rate: Decimal256::permille(5),
+        buffer_distribution_factor: Decimal256::percent(20),
+        anc_purchase_factor: Decimal256::percent(20),
+        price_timeframe: 60u64,
+    };
+
+    // we can just call .unwrap() to assert this was a success
+    let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    let msg = ExecuteMsg::Whitelist {
+        name: "bluna".to_string(),
+        symbol: "bluna".to_string(),
+        collateral_token: "bluna".to_string(),
+        custody_contract: "custody_bluna".to_string(),
+        max_ltv: Decimal256::percent(60),
+        decimals: None,
+    };
+    let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::LockCollateral {
+        collaterals: vec![("bluna".to_string(), Uint256::from(1000000u64))],
+    };
+    let _res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+    let msg = ExecuteMsg::LockCollateral {
+        collaterals: vec![("bluna".to_string(), Uint256::from(2000000u64))],
+    };
+    let _res = execute(deps.as_mut(), mock_env(), mock_info("addr0001", &[]), msg).unwrap();
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::CollateralsBatch {
+            borrowers: vec![
+                "addr0001".to_string(),
+                "addr0002".to_string(),
+                "addr0000".to_string(),
+            ],
+        },
+    )
+    .unwrap();
+    let batch_res: CollateralsBatchResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        batch_res,
+        CollateralsBatchResponse {
+            collaterals: vec![
+                CollateralsResponse {
+                    borrower: "addr0001".to_string(),
+                    collaterals: vec![("bluna".to_string(), Uint256::from(2000000u64))],
+                },
+                CollateralsResponse {
+                    borrower: "addr0002".to_string(),
+                    collaterals: vec![],
+                },
+                CollateralsResponse {
+                    borrower: "addr0000".to_string(),
+                    collaterals: vec![("bluna".to_string(), Uint256::from(1000000u64))],
+                },
+            ]
+        }
+    );
+
+    let borrowers = (0..31).map(|i| format!("addr{:04}", i)).collect();
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::CollateralsBatch { borrowers },
+    );
+    match res {
+        Err(StdError::GenericErr { msg, .. }) => {
+            assert_eq!(msg, "Cannot query more than 30 borrowers at once")
+        }
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+}
+
+#[test]
+fn lock_collateral_attributes() {
+    let mut deps = mock_dependencies(&[]);
+
+    let info = mock_info("owner", &[]);
+    let msg = InstantiateMsg {
+        owner_addr: "owner".to_string(),
+        oracle_contract: "oracle".to_string(),
+        market_contract: "market".to_string(),
+        liquidation_contract: "liquidation".to_string(),
+        collector_contract: "collector".to_string(),
+        stable_denom: "uusd".to_string(),
+        epoch_period: 86400u64,
+        threshold_deposit_rate: Decimal256::permille(3),
+        target_deposit_rate: Decimal256::permille(5),
+        buffer_distribution_factor: Decimal256::percent(20),
+        anc_purchase_factor: Decimal256::percent(20),
+        price_timeframe: 60u64,
+    };
+    let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    for collateral_token in ["bluna", "batom", "beth2"] {
+        let msg = ExecuteMsg::Whitelist {
+            name: collateral_token.to_string(),
+            symbol: collateral_token.to_string(),
+            collateral_token: collateral_token.to_string(),
+            custody_contract: format!("custody_{}", collateral_token),
+            max_ltv: Decimal256::percent(60),
+            decimals: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+    }
+
+    // a token name ending with digits cannot be told apart
+    // from the amount in the aggregate attribute
+    let msg = ExecuteMsg::LockCollateral {
+        collaterals: vec![
+            ("bluna".to_string(), Uint256::from(1000000u64)),
+            ("batom".to_string(), Uint256::from(10000000u64)),
+            ("beth2".to_string(), Uint256::from(20u64)),
+        ],
+    };
+    let info = mock_info("addr0000", &[]);
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "lock_collateral"),
+            attr("borrower", "addr0000"),
+            attr("collaterals", "1000000bluna,10000000batom,20beth2"),
+            attr("collateral_token", "bluna"),
+            attr("collateral_amount", "1000000"),
+            attr("collateral_token", "batom"),
+            attr("collateral_amount", "10000000"),
+            attr("collateral_token", "beth2"),
+            attr("collateral_amount", "20"),
+        ]
+    );
+}
+
+#[test]
+fn unlock_collateral() {
+    let mut deps = mock_dependencies(&[]);
+
+    let info = mock_info("owner", &[]);
+    let env = mock_env();
+    let msg = InstantiateMsg {
+        owner_addr: "owner".to_string(),
+        oracle_contract: "oracle".to_string(),
+        market_contract: "market".to_string(),
+        liquidation_contract: "liquidation".to_string(),
+        collector_contract: "collector".to_string(),
+        stable_denom: "uusd".to_string(),
+        epoch_period: 86400u64,
+        threshold_deposit_rate: Decimal256::permille(3),
+        target_deposit_rate: Decimal256::permille(5),
+        buffer_distribution_factor: Decimal256::percent(20),
+        anc_purchase_factor: Decimal256::percent(20),
+        price_timeframe: 60u64,
+    };
+
+    // we can just call .unwrap() to assert this was a success
+    let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+    // store whitelist elems
+    let msg = ExecuteMsg::Whitelist {
+        name: "bluna".to_string(),
+        symbol: "bluna".to_string(),
+        collateral_token: "bluna".to_string(),
+        custody_contract: "custody_bluna".to_string(),
+        max_ltv: Decimal256::percent(60),
+        decimals: None,
+    };
+
+    let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg);
+
+    let msg = ExecuteMsg::Whitelist {
+        name: "batom".to_string(),
+        symbol: "batom".to_string(),
+        collateral_token: "batom".to_string(),
+        custody_contract: "custody_batom".to_string(),
+        max_ltv: Decimal256::percent(60),
+        decimals: None,
+    };
+
+    let _res = execute(deps.as_mut(), env.clone(), info, msg);
+
+    let msg = ExecuteMsg::LockCollateral {
+        collaterals: vec![
+            ("bluna".to_string(), Uint256::from(1000000u64)),
+            ("batom".to_string(), Uint256::from(10000000u64)),
+        ],
+    };
+    let info = mock_info("addr0000", &[]);
+    let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+    // Failed to unlock more than locked amount
+    let msg = ExecuteMsg::UnlockCollateral {
+        collaterals: vec![
+            ("bluna".to_string(), Uint256::from(1000001u64)),
+            ("batom".to_string(), Uint256::from(10000001u64)),
+        ],
+    };
+    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg);
+    match res {
+        Err(ContractError::MoneyMarket(MoneyMarketError::UnlockExceedsLocked {})) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // Failed to unlock a token that was never whitelisted
+    let msg = ExecuteMsg::UnlockCollateral {
+        collaterals: vec![
+            ("bluna".to_string(), Uint256::one()),
+            ("beth".to_string(), Uint256::one()),
+        ],
+    };
+    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg);
+    match res {
+        Err(ContractError::Std(StdError::GenericErr { msg, .. })) => {
+            assert_eq!(msg, "Token is not registered as collateral")
+        }
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // Failed to unlock zero amounts or the same token twice
+    let msg = ExecuteMsg::UnlockCollateral {
+        collaterals: vec![("bluna".to_string(), Uint256::zero())],
+    };
+    let err = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Collateral amount must be greater than 0: bluna"
+    );
+
+    let msg = ExecuteMsg::UnlockCollateral {
+        collaterals: vec![
+            ("batom".to_string(), Uint256::one()),
+            ("batom".to_string(), Uint256::one()),
+        ],
+    };
+    let err = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Collateral is listed more than once: batom"
+    );
+
+    deps.querier.with_oracle_price(&[
+        (
+            &("bluna".to_string(), "uusd".to_string()),
+            &PriceInfo {
+                rate: Decimal256::from_ratio(1000u64, 1u64),
+                last_updated_base: env.block.time.seconds(),
+                last_updated_quote: env.block.time.seconds(),
+            },
+        ),
+        (
+            &("batom".to_string(), "uusd".to_string()),
+            &PriceInfo {
+                rate: Decimal256::from_ratio(2000u64, 1u64),
+                last_updated_base: env.block.time.seconds(),
+                last_updated_quote: env.block.time.seconds(),
+            },
+        ),
+    ]);
+
+    // borrow_limit = 1000 * 1000000 * 0.6 + 2000 * 10000000 * 0.6
+    // = 12,600,000,000 uusd
+    deps.querier
+        .with_loan_amount(&[(&"addr0000".to_string(), &Uint256::from(12600000000u64))]);
+
+    // cannot unlock any tokens
+    // Failed to unlock more than locked amount
+    let msg = ExecuteMsg::UnlockCollateral {
+        collaterals: vec![("bluna".to_string(), Uint256::one())],
+    };
+    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg);
+    match res {
+        Err(ContractError::MoneyMarket(MoneyMarketError::UnlockExceedsLtv(12599999400))) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let msg = ExecuteMsg::UnlockCollateral {
+        collaterals: vec![("batom".to_string(), Uint256::one())],
+    };
+    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg);
+    match res {
+        Err(ContractError::MoneyMarket(MoneyMarketError::UnlockExceedsLtv(12599998800))) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // borrow_limit = 1000 * 1000000 * 0.6 + 2000 * 10000000 * 0.6
+    // = 12,600,000,000 uusd
+    deps.querier
+        .with_loan_amount(&[(&"addr0000".to_string(), &Uint256::from(12599999400u64))]);
+    let res = query(
+        deps.as_ref(),
+        env.clone(),
+        QueryMsg::BorrowLimit {
+            borrower: "addr0000".to_string(),
+            block_time: None,
+        },
+    )
+    .unwrap();
+    let borrow_limit_res: BorrowLimitResponse = from_binary(&res).unwrap();
+    assert_eq!(borrow_limit_res.borrow_limit, Uint256::from(12600000000u64),);
+
+    // the breakdown follows the Collaterals ordering and adds up to the total
+    assert_eq!(
+        borrow_limit_res.collaterals,
+        vec![
+            ("bluna".to_string(), Uint256::from(600000000u64)),
+            ("batom".to_string(), Uint256::from(12000000000u64)),
+        ]
+    );
+
+    // Cannot unlock 2bluna
+    let msg = ExecuteMsg::UnlockCollateral {
+        collaterals: vec![("bluna".to_string(), Uint256::from(2u64))],
+    };
+    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg);
+    match res {
+        Err(ContractError::MoneyMarket(MoneyMarketError::UnlockExceedsLtv(12599998800))) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // Can unlock 1bluna
+    let msg = ExecuteMsg::UnlockCollateral {
+        collaterals: vec![("bluna".to_string(), Uint256::one())],
+    };
+    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "custody_bluna".to_string(),
+            funds: vec![],
+            msg: to_binary(&CustodyExecuteMsg::UnlockCollateral {
+                borrower: "addr0000".to_string(),
+                amount: Uint256::one(),
+            })
+            .unwrap(),
+        }))]
+    );
+
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "unlock_collateral"),
+            attr("borrower", "addr0000"),
+            attr("collaterals", "1bluna"),
+            attr("collateral_token", "bluna"),
+            attr("collateral_amount", "1"),
+        ]
+    );
+
+    //testing for unlocking more collaterals
+    deps.querier
+        .with_loan_amount(&[(&"addr0000".to_string(), &Uint256::from(125999900u128))]);
+
+    let msg = ExecuteMsg::UnlockCollateral {
+        collaterals: vec![
+            ("bluna".to_string(), Uint256::from(1u128)),
+            ("batom".to_string(), Uint256::from(1u128)),
+        ],
+    };
+    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "custody_bluna".to_string(),
+                funds: vec![],
+                msg: to_binary(&CustodyExecuteMsg::UnlockCollateral {
+                    borrower: "addr0000".to_string(),
+                    amount: Uint256::from(1u128),
+                })
+                .unwrap(),
+            })),
+            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "custody_batom".to_string(),
+                funds: vec![],
+                msg: to_binary(&CustodyExecuteMsg::UnlockCollateral {
+                    borrower: "addr0000".to_string(),
+                    amount: Uint256::from(1u128),
+                })
+                .unwrap(),
+            }))
+        ]
+    );
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "unlock_collateral"),
+            attr("borrower", "addr0000"),
+            attr("collaterals", "1bluna,1batom"),
+            attr("collateral_token", "bluna"),
+            attr("collateral_amount", "1"),
+            attr("collateral_token", "batom"),
+            attr("collateral_amount", "1"),
+        ]
+    );
+
+    // unlock and withdraw in one step
+    let msg = ExecuteMsg::UnlockAndWithdrawCollateral {
+        collaterals: vec![("batom".to_string(), Uint256::from(1u128))],
+    };
+    let res = execute(deps.as_mut(), env, info, msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "custody_batom".to_string(),
+                funds: vec![],
+                msg: to_binary(&CustodyExecuteMsg::UnlockCollateral {
+                    borrower: "addr0000".to_string(),
+                    amount: Uint256::from(1u128),
+                })
+                .unwrap(),
+            })),
+            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "custody_batom".to_string(),
+                funds: vec![],
+                msg: to_binary(&CustodyExecuteMsg::WithdrawCollateral {
+                    borrower: Some("addr0000".to_string()),
+                    amount: Some(Uint256::from(1u128)),
+                })
+                .unwrap(),
+            }))
+        ]
+    );
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "unlock_and_withdraw_collateral"),
+            attr("borrower", "addr0000"),
+            attr("collaterals", "1batom"),
+            attr("collateral_token", "batom"),
+            attr("collateral_amount", "1"),
+        ]
+    );
+}
+
+#[test]
+fn query_borrow_limits() {
+    let mut deps = mock_dependencies(&[]);
+
+    let info = mock_info("owner", &[]);
+    let env = mock_env();
+    let msg = InstantiateMsg {
+        owner_addr: "owner".to_string(),
+        oracle_contract: "oracle".to_string(),
+        market_contract: "market".to_string(),
+        liquidation_contract: "liquidation".to_string(),
+        collector_contract: "collector".to_string(),
+        stable_denom: "uusd".to_string(),
+        epoch_period: 86400u64,
+        threshold_deposit_rate: Decimal256::permille(3),
+        target_deposit_rate: Decimal256::permille(5),
+        buffer_distribution_factor: Decimal256::percent(20),
+        anc_purchase_factor: Decimal256::percent(20),
+        price_timeframe: 60u64,
+    };
+    let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+    for token in ["bluna", "batom"] {
+        let msg = ExecuteMsg::Whitelist {
+            name: token.to_string(),
+            symbol: token.to_string(),
+            collateral_token: token.to_string(),
+            custody_contract: format!("custody_{}", token),
+            max_ltv: Decimal256::percent(60),
+            decimals: None,
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+    }
+
+    deps.querier.with_oracle_price(&[
+        (
+            &("bluna".to_string(), "uusd".to_string()),
+            &PriceInfo {
+                rate: Decimal256::from_ratio(1000u64, 1u64),
+                last_updated_base: env.block.time.seconds(),
+                last_updated_quote: env.block.time.seconds(),
+            },
+        ),
+        (
+            &("batom".to_string(), "uusd".to_string()),
+            &PriceInfo {
+                rate: Decimal256::from_ratio(2000u64, 1u64),
+                last_updated_base: env.block.time.seconds(),
+                last_updated_quote: env.block.time.seconds(),
+            },
+        ),
+    ]);
+
+    let msg = ExecuteMsg::LockCollateral {
+        collaterals: vec![
+            ("bluna".to_string(), Uint256::from(1000000u64)),
+            ("batom".to_string(), Uint256::from(1000000u64)),
+        ],
+    };
+    let _res = execute(deps.as_mut(), env.clone(), mock_info("addr0000", &[]), msg).unwrap();
+    let msg = ExecuteMsg::LockCollateral {
+        collaterals: vec![("bluna".to_string(), Uint256::from(2000000u64))],
+    };
+    let _res = execute(deps.as_mut(), env.clone(), mock_info("addr0001", &[]), msg).unwrap();
+
+    // responses follow the request order, borrowers without collaterals get zero
+    let res = query(
+        deps.as_ref(),
+        env.clone(),
+        QueryMsg::BorrowLimits {
+            borrowers: vec![
+                "addr0001".to_string(),
+                "addr0002".to_string(),
+                "addr0000".to_string(),
+            ],
+            block_time: Some(env.block.time.seconds()),
+        },
+    )
+    .unwrap();
+    let borrow_limits_res: BorrowLimitsResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        borrow_limits_res,
+        BorrowLimitsResponse {
+            borrow_limits: vec![
+                BorrowLimitResponse {
+                    borrower: "addr0001".to_string(),
+                    borrow_limit: Uint256::from(1200000000u64),
+                    collaterals: vec![("bluna".to_string(), Uint256::from(1200000000u64))],
+                },
+                BorrowLimitResponse {
+                    borrower: "addr0002".to_string(),
+                    borrow_limit: Uint256::zero(),
+                    collaterals: vec![],
+                },
+                BorrowLimitResponse {
+                    borrower: "addr0000".to_string(),
+                    borrow_limit: Uint256::from(1800000000u64),
+                    collaterals: vec![
+                        ("bluna".to_string(), Uint256::from(600000000u64)),
+                        ("batom".to_string(), Uint256::from(1200000000u64)),
+                    ],
+                },
+            ]
+        }
+    );
+
+    // each entry matches the single borrower query
+    let res = query(
+        deps.as_ref(),
+        env.clone(),
+        QueryMsg::BorrowLimit {
+            borrower: "addr0000".to_string(),
+            block_time: Some(env.block.time.seconds()),
+        },
+    )
+    .unwrap();
+    let borrow_limit_res: BorrowLimitResponse = from_binary(&res).unwrap();
+    assert_eq!(borrow_limit_res, borrow_limits_res.borrow_limits[2]);
+
+    // raw collateral values, before applying the LTVs
+    let res = query(
+        deps.as_ref(),
+        env.clone(),
+        QueryMsg::CollateralValue {
+            borrower: "addr0000".to_string(),
+        },
+    )
+    .unwrap();
+    let collateral_value_res: CollateralValueResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        collateral_value_res,
+        CollateralValueResponse {
+            borrower: "addr0000".to_string(),
+            total_value: Uint256::from(3000000000u64),
+            collaterals: vec![
+                ("bluna".to_string(), Uint256::from(1000000000u64)),
+                ("batom".to_string(), Uint256::from(2000000000u64)),
+            ],
+        }
+    );
+
+    let res = query(
+        deps.as_ref(),
+        env.clone(),
+        QueryMsg::CollateralValue {
+            borrower: "addr0002".to_string(),
+        },
+    )
+    .unwrap();
+    let collateral_value_res: CollateralValueResponse = from_binary(&res).unwrap();
+    assert_eq!(collateral_value_res.total_value, Uint256::zero());
+    assert_eq!(collateral_value_res.collaterals, vec![]);
+
+    let borrowers = (0..31).map(|i| format!("addr{:04}", i)).collect();
+    let res = query(
+        deps.as_ref(),
+        env,
+        QueryMsg::BorrowLimits {
+            borrowers,
+            block_time: None,
+        },
+    );
+    match res {
+        Err(StdError::GenericErr { msg, .. }) => {
+            assert_eq!(msg, "Cannot query more than 30 borrowers at once")
+        }
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+}
+
+#[test]
+fn unlock_all_collateral() {
+    let mut deps = mock_dependencies(&[]);
+
+    let info = mock_info("owner", &[]);
+    let env = mock_env();
+    let msg = InstantiateMsg {
+        owner_addr: "owner".to_string(),
+        oracle_contract: "oracle".to_string(),
+        market_contract: "market".to_string(),
+        liquidation_contract: "liquidation".to_string(),
+        collector_contract: "collector".to_string(),
+        stable_denom: "uusd".to_string(),
+        epoch_period: 86400u64,
+        threshold_deposit_rate: Decimal256::permille(3),
+        target_deposit_rate: Decimal256::permille(5),
+        buffer_distribution_factor: Decimal256::percent(20),
+        anc_purchase_factor: Decimal256::percent(20),
+        price_timeframe: 60u64,
     };
+    let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
 
-    let _res = execute(deps.as_mut(), mock_env(), info, msg);
+    for token in ["bluna", "batom"] {
+        let msg = ExecuteMsg::Whitelist {
+            name: token.to_string(),
+            symbol: token.to_string(),
+            collateral_token: token.to_string(),
+            custody_contract: format!("custody_{}", token),
+            max_ltv: Decimal256::percent(60),
+            decimals: None,
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+    }
 
+    let info = mock_info("addr0000", &[]);
     let msg = ExecuteMsg::LockCollateral {
         collaterals: vec![
-            (bluna_collat_token.clone(), Uint256::from(1000000u64)),
-            (batom_collat_token.clone(), Uint256::from(10000000u64)),
+            ("bluna".to_string(), Uint256::from(1000u64)),
+            ("batom".to_string(), Uint256::from(2000u64)),
         ],
     };
-    let info = mock_info("addr0000", &[]);
-    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-    assert_eq!(
-        res.messages,
-        vec![
-            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: "custody_bluna".to_string(),
-                funds: vec![],
-                msg: to_binary(&CustodyExecuteMsg::LockCollateral {
-                    borrower: "addr0000".to_string(),
-                    amount: Uint256::from(1000000u64),
-                })
-                .unwrap(),
-            })),
-            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: "custody_batom".to_string(),
-                funds: vec![],
-                msg: to_binary(&CustodyExecuteMsg::LockCollateral {
-                    borrower: "addr0000".to_string(),
-                    amount: Uint256::from(10000000u64),
-                })
-                .unwrap(),
-            }))
-        ]
-    );
-
-    assert_eq!(
-        res.attributes,
-        vec![
-            attr("action", "lock_collateral"),
-            attr("borrower", "addr0000"),
-            attr(
-                "collaterals",
-                format!(
-                    "1000000{},10000000{}",
-                    bluna_collat_token, batom_collat_token
-                )
-            ),
-        ]
-    );
+    let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+    deps.querier
+        .with_loan_amount(&[(&"addr0000".to_string(), &Uint256::zero())]);
+    deps.querier.with_oracle_price(&[(
+        &("batom".to_string(), "uusd".to_string()),
+        &PriceInfo {
+            rate: Decimal256::one(),
+            last_updated_base: env.block.time.seconds(),
+            last_updated_quote: env.block.time.seconds(),
+        },
+    )]);
 
+    // fully unlocking one token drops its entry
+    let msg = ExecuteMsg::UnlockCollateral {
+        collaterals: vec![("bluna".to_string(), Uint256::from(1000u64))],
+    };
+    let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
     let res = query(
         deps.as_ref(),
-        mock_env(),
+        env.clone(),
         QueryMsg::Collaterals {
             borrower: "addr0000".to_string(),
         },
@@ -813,42 +3325,43 @@ fn lock_collateral() {
     .unwrap();
     let collaterals_res: CollateralsResponse = from_binary(&res).unwrap();
     assert_eq!(
-        collaterals_res,
-        CollateralsResponse {
-            borrower: "addr0000".to_string(),
-            collaterals: vec![
-                (batom_collat_token.clone(), Uint256::from(10000000u64)),
-                (bluna_collat_token.clone(), Uint256::from(1000000u64)),
-            ]
-        }
+        collaterals_res.collaterals,
+        vec![("batom".to_string(), Uint256::from(2000u64))]
     );
 
+    // and unlocking the rest removes the borrower
+    let msg = ExecuteMsg::UnlockCollateral {
+        collaterals: vec![("batom".to_string(), Uint256::from(2000u64))],
+    };
+    let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
     let res = query(
         deps.as_ref(),
-        mock_env(),
+        env.clone(),
         QueryMsg::AllCollaterals {
             start_after: None,
             limit: None,
+            order_by: None,
         },
     )
     .unwrap();
     let all_collaterals_res: AllCollateralsResponse = from_binary(&res).unwrap();
-    assert_eq!(
-        all_collaterals_res,
-        AllCollateralsResponse {
-            all_collaterals: vec![CollateralsResponse {
-                borrower: "addr0000".to_string(),
-                collaterals: vec![
-                    (batom_collat_token, Uint256::from(10000000u64)),
-                    (bluna_collat_token, Uint256::from(1000000u64)),
-                ]
-            }]
-        }
-    );
+    assert_eq!(all_collaterals_res.all_collaterals, vec![]);
+
+    let res = query(
+        deps.as_ref(),
+        env,
+        QueryMsg::Collaterals {
+            borrower: "addr0000".to_string(),
+        },
+    )
+    .unwrap();
+    let collaterals_res: CollateralsResponse = from_binary(&res).unwrap();
+    assert_eq!(collaterals_res.collaterals, vec![]);
 }
 
 #[test]
-fn unlock_collateral() {
+fn unlock_collateral_stale_price() {
     let mut deps = mock_dependencies(&[]);
 
     let info = mock_info("owner", &[]);
@@ -867,30 +3380,19 @@ fn unlock_collateral() {
         anc_purchase_factor: Decimal256::percent(20),
         price_timeframe: 60u64,
     };
-
-    // we can just call .unwrap() to assert this was a success
     let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
 
-    // store whitelist elems
-    let msg = ExecuteMsg::Whitelist {
-        name: "bluna".to_string(),
-        symbol: "bluna".to_string(),
-        collateral_token: "bluna".to_string(),
-        custody_contract: "custody_bluna".to_string(),
-        max_ltv: Decimal256::percent(60),
-    };
-
-    let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg);
-
-    let msg = ExecuteMsg::Whitelist {
-        name: "batom".to_string(),
-        symbol: "batom".to_string(),
-        collateral_token: "batom".to_string(),
-        custody_contract: "custody_batom".to_string(),
-        max_ltv: Decimal256::percent(60),
-    };
-
-    let _res = execute(deps.as_mut(), env.clone(), info, msg);
+    for token in ["bluna", "batom"] {
+        let msg = ExecuteMsg::Whitelist {
+            name: token.to_string(),
+            symbol: token.to_string(),
+            collateral_token: token.to_string(),
+            custody_contract: format!("custody_{}", token),
+            max_ltv: Decimal256::percent(60),
+            decimals: None,
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+    }
 
     let msg = ExecuteMsg::LockCollateral {
         collaterals: vec![
@@ -900,68 +3402,65 @@ fn unlock_collateral() {
     };
     let info = mock_info("addr0000", &[]);
     let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
-
-    // Failed to unlock more than locked amount
-    let msg = ExecuteMsg::UnlockCollateral {
-        collaterals: vec![
-            ("bluna".to_string(), Uint256::from(1000001u64)),
-            ("batom".to_string(), Uint256::from(10000001u64)),
-        ],
-    };
-    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg);
-    match res {
-        Err(ContractError::UnlockExceedsLocked {}) => (),
-        _ => panic!("DO NOT ENTER HERE"),
-    }
-
-    deps.querier.with_oracle_price(&[
-        (
-            &("bluna".to_string(), "uusd".to_string()),
-            &(
-                Decimal256::from_ratio(1000u64, 1u64),
-                env.block.time.seconds(),
-                env.block.time.seconds(),
+    deps.querier
+        .with_loan_amount(&[(&"addr0000".to_string(), &Uint256::zero())]);
+
+    // bluna is fresh, batom was last fed one second beyond the timeframe
+    let now = env.block.time.seconds();
+    let oracle_prices = |batom_updated: u64| {
+        [
+            (
+                ("bluna".to_string(), "uusd".to_string()),
+                PriceInfo {
+                    rate: Decimal256::from_ratio(1000u64, 1u64),
+                    last_updated_base: now,
+                    last_updated_quote: now,
+                },
             ),
-        ),
-        (
-            &("batom".to_string(), "uusd".to_string()),
-            &(
-                Decimal256::from_ratio(2000u64, 1u64),
-                env.block.time.seconds(),
-                env.block.time.seconds(),
+            (
+                ("batom".to_string(), "uusd".to_string()),
+                PriceInfo {
+                    rate: Decimal256::from_ratio(2000u64, 1u64),
+                    last_updated_base: batom_updated,
+                    last_updated_quote: now,
+                },
             ),
-        ),
-    ]);
-
-    // borrow_limit = 1000 * 1000000 * 0.6 + 2000 * 10000000 * 0.6
-    // = 12,600,000,000 uusd
+        ]
+    };
+    let prices = oracle_prices(now - 61);
     deps.querier
-        .with_loan_amount(&[(&"addr0000".to_string(), &Uint256::from(12600000000u64))]);
+        .with_oracle_price(&[(&prices[0].0, &prices[0].1), (&prices[1].0, &prices[1].1)]);
 
-    // cannot unlock any tokens
-    // Failed to unlock more than locked amount
+    // a single stale price in the basket blocks unlocking the fresh one
     let msg = ExecuteMsg::UnlockCollateral {
         collaterals: vec![("bluna".to_string(), Uint256::one())],
     };
-    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg);
+    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone());
     match res {
-        Err(ContractError::UnlockTooLarge(12599999400)) => (),
+        Err(ContractError::MoneyMarket(MoneyMarketError::PriceStale { asset, age })) => {
+            assert_eq!(asset, "batom");
+            assert_eq!(age, 61);
+        }
         _ => panic!("DO NOT ENTER HERE"),
     }
 
-    let msg = ExecuteMsg::UnlockCollateral {
-        collaterals: vec![("batom".to_string(), Uint256::one())],
-    };
-    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg);
+    // the query only validates prices when a block time is given
+    let res = query(
+        deps.as_ref(),
+        env.clone(),
+        QueryMsg::BorrowLimit {
+            borrower: "addr0000".to_string(),
+            block_time: Some(now),
+        },
+    );
     match res {
-        Err(ContractError::UnlockTooLarge(12599998800)) => (),
+        Err(StdError::GenericErr { msg, .. }) => assert_eq!(
+            msg,
+            "Price is too old; batom was last updated 61 seconds ago"
+        ),
         _ => panic!("DO NOT ENTER HERE"),
     }
 
-    // borrow_limit = 1000 * 1000000 * 0.6 + 2000 * 10000000 * 0.6
-    // = 12,600,000,000 uusd
-    deps.querier
-        .with_loan_amount(&[(&"addr0000".to_string(), &Uint256::from(12599999400u64))]);
     let res = query(
         deps.as_ref(),
         env.clone(),
@@ -972,87 +3471,13 @@ fn unlock_collateral() {
     )
     .unwrap();
     let borrow_limit_res: BorrowLimitResponse = from_binary(&res).unwrap();
-    assert_eq!(borrow_limit_res.borrow_limit, Uint256::from(12600000000u64),);
-
-    // Cannot unlock 2bluna
-    let msg = ExecuteMsg::UnlockCollateral {
-        collaterals: vec![("bluna".to_string(), Uint256::from(2u64))],
-    };
-    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg);
-    match res {
-        Err(ContractError::UnlockTooLarge(12599998800)) => (),
-        _ => panic!("DO NOT ENTER HERE"),
-    }
-
-    // Can unlock 1bluna
-    let msg = ExecuteMsg::UnlockCollateral {
-        collaterals: vec![("bluna".to_string(), Uint256::one())],
-    };
-    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
-    assert_eq!(
-        res.messages,
-        vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
-            contract_addr: "custody_bluna".to_string(),
-            funds: vec![],
-            msg: to_binary(&CustodyExecuteMsg::UnlockCollateral {
-                borrower: "addr0000".to_string(),
-                amount: Uint256::one(),
-            })
-            .unwrap(),
-        }))]
-    );
+    assert_eq!(borrow_limit_res.borrow_limit, Uint256::from(12600000000u64));
 
-    assert_eq!(
-        res.attributes,
-        vec![
-            attr("action", "unlock_collateral"),
-            attr("borrower", "addr0000"),
-            attr("collaterals", "1bluna"),
-        ]
-    );
-
-    //testing for unlocking more collaterals
+    // a price updated exactly at the edge of the timeframe is still valid
+    let prices = oracle_prices(now - 60);
     deps.querier
-        .with_loan_amount(&[(&"addr0000".to_string(), &Uint256::from(125999900u128))]);
-
-    let msg = ExecuteMsg::UnlockCollateral {
-        collaterals: vec![
-            ("bluna".to_string(), Uint256::from(1u128)),
-            ("batom".to_string(), Uint256::from(1u128)),
-        ],
-    };
-    let res = execute(deps.as_mut(), env, info, msg).unwrap();
-    assert_eq!(
-        res.messages,
-        vec![
-            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: "custody_bluna".to_string(),
-                funds: vec![],
-                msg: to_binary(&CustodyExecuteMsg::UnlockCollateral {
-                    borrower: "addr0000".to_string(),
-                    amount: Uint256::from(1u128),
-                })
-                .unwrap(),
-            })),
-            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: "custody_batom".to_string(),
-                funds: vec![],
-                msg: to_binary(&CustodyExecuteMsg::UnlockCollateral {
-                    borrower: "addr0000".to_string(),
-                    amount: Uint256::from(1u128),
-                })
-                .unwrap(),
-            }))
-        ]
-    );
-    assert_eq!(
-        res.attributes,
-        vec![
-            attr("action", "unlock_collateral"),
-            attr("borrower", "addr0000"),
-            attr("collaterals", "1bluna,1batom"),
-        ]
-    );
+        .with_oracle_price(&[(&prices[0].0, &prices[0].1), (&prices[1].0, &prices[1].1)]);
+    let _res = execute(deps.as_mut(), env, info, msg).unwrap();
 }
 
 #[test]
@@ -1106,6 +3531,7 @@ fn liquidate_collateral() {
         collateral_token: bluna_collat_token.clone(),
         custody_contract: "custody_bluna".to_string(),
         max_ltv: Decimal256::percent(60),
+        decimals: None,
     };
 
     let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg);
@@ -1116,6 +3542,7 @@ fn liquidate_collateral() {
         collateral_token: batom_collat_token.clone(),
         custody_contract: "custody_batom".to_string(),
         max_ltv: Decimal256::percent(60),
+        decimals: None,
     };
 
     let _res = execute(deps.as_mut(), env.clone(), info, msg);
@@ -1132,19 +3559,19 @@ fn liquidate_collateral() {
     deps.querier.with_oracle_price(&[
         (
             &(bluna_collat_token.clone(), "uusd".to_string()),
-            &(
-                Decimal256::from_ratio(1000u64, 1u64),
-                env.block.time.seconds(),
-                env.block.time.seconds(),
-            ),
+            &PriceInfo {
+                rate: Decimal256::from_ratio(1000u64, 1u64),
+                last_updated_base: env.block.time.seconds(),
+                last_updated_quote: env.block.time.seconds(),
+            },
         ),
         (
             &(batom_collat_token.clone(), "uusd".to_string()),
-            &(
-                Decimal256::from_ratio(2000u64, 1u64),
-                env.block.time.seconds(),
-                env.block.time.seconds(),
-            ),
+            &PriceInfo {
+                rate: Decimal256::from_ratio(2000u64, 1u64),
+                last_updated_base: env.block.time.seconds(),
+                last_updated_quote: env.block.time.seconds(),
+            },
         ),
     ]);
 
@@ -1190,14 +3617,34 @@ fn liquidate_collateral() {
                 .unwrap(),
             })),
             SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: "market".to_string(),
+                contract_addr: "liquidation".to_string(),
                 funds: vec![],
-                msg: to_binary(&MarketExecuteMsg::RepayStableFromLiquidation {
+                msg: to_binary(&LiquidationExecuteMsg::SettleLiquidation {
                     borrower: "addr0000".to_string(),
-                    prev_balance: Uint256::zero(),
+                    repay_address: Some("market".to_string()),
+                    collaterals: vec![
+                        (batom_collat_token.clone(), Uint256::from(100000u64)),
+                        (bluna_collat_token.clone(), Uint256::from(10000u64)),
+                    ],
                 })
                 .unwrap(),
-            }))
+            })),
+        ]
+    );
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "liquidate_collateral"),
+            attr("liquidator", "addr0001"),
+            attr("borrower", "addr0000"),
+            attr(
+                "collaterals",
+                format!("100000{},10000{}", batom_collat_token, bluna_collat_token)
+            ),
+            attr("collateral_token", batom_collat_token.clone()),
+            attr("collateral_amount", "100000"),
+            attr("collateral_token", bluna_collat_token.clone()),
+            attr("collateral_amount", "10000"),
         ]
     );
 
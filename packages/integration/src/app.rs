@@ -0,0 +1,508 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::ops::Bound;
+
+use cosmwasm_std::testing::{mock_env, MockApi};
+use cosmwasm_std::{
+    from_binary, from_slice, to_binary, Addr, AllBalanceResponse, Attribute, BalanceResponse,
+    BankMsg, BankQuery, Binary, BlockInfo, Coin, ContractInfo, ContractResult, CosmosMsg, Decimal,
+    Deps, DepsMut, Env, MessageInfo, Order, Pair, Querier, QuerierResult, QuerierWrapper,
+    QueryRequest, Reply, ReplyOn, Response, StdError, StdResult, Storage, SubMsg,
+    SubMsgExecutionResponse, SystemError, SystemResult, Uint128, WasmMsg, WasmQuery,
+};
+use terra_cosmwasm::{
+    TaxCapResponse, TaxRateResponse, TerraMsgWrapper, TerraQuery, TerraQueryWrapper,
+};
+
+/// Seconds between two blocks when the app advances
+const BLOCK_TIME: u64 = 6;
+
+/// Entry points the app dispatches to. Messages are passed serialized,
+/// contract errors are flattened to `StdError`s and responses carry
+/// Terra custom messages.
+pub trait Contract {
+    fn instantiate(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        msg: Binary,
+    ) -> StdResult<Response<TerraMsgWrapper>>;
+
+    fn execute(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        msg: Binary,
+    ) -> StdResult<Response<TerraMsgWrapper>>;
+
+    fn query(&self, deps: Deps, env: Env, msg: Binary) -> StdResult<Binary>;
+
+    fn reply(
+        &self,
+        _deps: DepsMut,
+        _env: Env,
+        _msg: Reply,
+    ) -> StdResult<Response<TerraMsgWrapper>> {
+        Err(StdError::generic_err("Reply is not supported"))
+    }
+}
+
+/// Attributes of every contract execution, in execution order,
+/// and the custom messages the app does not execute
+#[derive(Debug, Default)]
+pub struct AppResponse {
+    pub attributes: Vec<(String, Vec<Attribute>)>,
+    pub custom_msgs: Vec<(String, TerraMsgWrapper)>,
+}
+
+impl AppResponse {
+    pub fn has_action(&self, contract: &str, action: &str) -> bool {
+        self.attributes.iter().any(|(addr, attrs)| {
+            addr == contract
+                && attrs
+                    .iter()
+                    .any(|attr| attr.key == "action" && attr.value == action)
+        })
+    }
+}
+
+/// Unlike `MockStorage`, it can be cloned to roll back a failed execution
+#[derive(Clone, Default)]
+struct ContractStorage(BTreeMap<Vec<u8>, Vec<u8>>);
+
+impl Storage for ContractStorage {
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.0.get(key).cloned()
+    }
+
+    fn range<'a>(
+        &'a self,
+        start: Option<&[u8]>,
+        end: Option<&[u8]>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = Pair> + 'a> {
+        if let (Some(start), Some(end)) = (start, end) {
+            if start >= end {
+                return Box::new(std::iter::empty());
+            }
+        }
+
+        let start = start.map_or(Bound::Unbounded, |start| Bound::Included(start.to_vec()));
+        let end = end.map_or(Bound::Unbounded, |end| Bound::Excluded(end.to_vec()));
+        let iter = self
+            .0
+            .range((start, end))
+            .map(|(key, value)| (key.clone(), value.clone()));
+        match order {
+            Order::Ascending => Box::new(iter),
+            Order::Descending => Box::new(iter.rev()),
+        }
+    }
+
+    fn set(&mut self, key: &[u8], value: &[u8]) {
+        self.0.insert(key.to_vec(), value.to_vec());
+    }
+
+    fn remove(&mut self, key: &[u8]) {
+        self.0.remove(key);
+    }
+}
+
+/// Everything an execution may change
+#[derive(Clone, Default)]
+struct AppState {
+    storages: BTreeMap<String, ContractStorage>,
+    balances: BTreeMap<(String, String), Uint128>,
+}
+
+impl AppState {
+    fn balance(&self, address: &str, denom: &str) -> Uint128 {
+        self.balances
+            .get(&(address.to_string(), denom.to_string()))
+            .copied()
+            .unwrap_or_else(Uint128::zero)
+    }
+
+    fn transfer(&mut self, from: &str, to: &str, coins: &[Coin]) -> StdResult<()> {
+        for coin in coins {
+            let from_balance = self.balance(from, &coin.denom);
+            if from_balance < coin.amount {
+                return Err(StdError::generic_err(format!(
+                    "Insufficient funds: {} has {}{}, needs {}{}",
+                    from, from_balance, coin.denom, coin.amount, coin.denom
+                )));
+            }
+
+            self.balances.insert(
+                (from.to_string(), coin.denom.clone()),
+                from_balance - coin.amount,
+            );
+            let to_balance = self.balance(to, &coin.denom);
+            self.balances.insert(
+                (to.to_string(), coin.denom.clone()),
+                to_balance + coin.amount,
+            );
+        }
+
+        Ok(())
+    }
+}
+
+pub struct App {
+    pub block: BlockInfo,
+    api: MockApi,
+    contracts: BTreeMap<String, Box<dyn Contract>>,
+    state: AppState,
+}
+
+impl Default for App {
+    fn default() -> Self {
+        App::new()
+    }
+}
+
+impl App {
+    pub fn new() -> Self {
+        App {
+            block: mock_env().block,
+            api: MockApi::default(),
+            contracts: BTreeMap::new(),
+            state: AppState::default(),
+        }
+    }
+
+    pub fn advance_blocks(&mut self, blocks: u64) {
+        self.block.height += blocks;
+        self.block.time = self.block.time.plus_seconds(blocks * BLOCK_TIME);
+    }
+
+    pub fn balance(&self, address: &str, denom: &str) -> Uint128 {
+        self.state.balance(address, denom)
+    }
+
+    pub fn set_balance(&mut self, address: &str, coin: Coin) {
+        self.state
+            .balances
+            .insert((address.to_string(), coin.denom), coin.amount);
+    }
+
+    /// Registers the contract under `address` and instantiates it
+    pub fn instantiate<T: Serialize>(
+        &mut self,
+        address: &str,
+        contract: Box<dyn Contract>,
+        sender: &str,
+        msg: &T,
+    ) -> StdResult<AppResponse> {
+        if self.contracts.contains_key(address) {
+            return Err(StdError::generic_err(format!(
+                "Contract already exists: {}",
+                address
+            )));
+        }
+
+        self.contracts.insert(address.to_string(), contract);
+        let info = MessageInfo {
+            sender: Addr::unchecked(sender),
+            funds: vec![],
+        };
+        let msg = to_binary(msg)?;
+        let res = self.atomic(|app, res| {
+            let response = app.call(address, |contract, deps, env| {
+                contract.instantiate(deps, env, info, msg)
+            })?;
+            app.process_response(res, address, response)
+        });
+        if res.is_err() {
+            self.contracts.remove(address);
+        }
+
+        res
+    }
+
+    /// Executes `msg` and every message it results in;
+    /// nothing is changed when any of them fails
+    pub fn execute<T: Serialize>(
+        &mut self,
+        sender: &str,
+        contract: &str,
+        msg: &T,
+        funds: &[Coin],
+    ) -> StdResult<AppResponse> {
+        let msg = to_binary(msg)?;
+        self.atomic(|app, res| {
+            app.execute_contract(res, sender, contract, msg, funds.to_vec())
+                .map(|_| ())
+        })
+    }
+
+    pub fn query<T: DeserializeOwned, Q: Serialize>(
+        &self,
+        contract: &str,
+        msg: &Q,
+    ) -> StdResult<T> {
+        let querier = AppQuerier { app: self };
+        from_binary(&querier.query_contract(contract, to_binary(msg)?)?)
+    }
+
+    fn atomic<F>(&mut self, f: F) -> StdResult<AppResponse>
+    where
+        F: FnOnce(&mut App, &mut AppResponse) -> StdResult<()>,
+    {
+        let snapshot = self.state.clone();
+        let mut res = AppResponse::default();
+        match f(self, &mut res) {
+            Ok(()) => Ok(res),
+            Err(err) => {
+                self.state = snapshot;
+                Err(err)
+            }
+        }
+    }
+
+    fn env(&self, contract: &str) -> Env {
+        Env {
+            block: self.block.clone(),
+            contract: ContractInfo {
+                address: Addr::unchecked(contract),
+            },
+        }
+    }
+
+    /// Runs an entry point on the contract storage, taken out
+    /// of the state while the other contracts can be queried
+    fn call<F>(&mut self, contract: &str, f: F) -> StdResult<Response<TerraMsgWrapper>>
+    where
+        F: FnOnce(&dyn Contract, DepsMut, Env) -> StdResult<Response<TerraMsgWrapper>>,
+    {
+        let env = self.env(contract);
+        let mut storage = self.state.storages.remove(contract).unwrap_or_default();
+
+        let res = match self.contracts.get(contract) {
+            Some(code) => {
+                let querier = AppQuerier { app: self };
+                let deps = DepsMut {
+                    storage: &mut storage,
+                    api: &self.api,
+                    querier: QuerierWrapper::new(&querier),
+                };
+                f(code.as_ref(), deps, env)
+            }
+            None => Err(StdError::generic_err(format!(
+                "No such contract: {}",
+                contract
+            ))),
+        };
+
+        self.state.storages.insert(contract.to_string(), storage);
+        res
+    }
+
+    fn execute_contract(
+        &mut self,
+        res: &mut AppResponse,
+        sender: &str,
+        contract: &str,
+        msg: Binary,
+        funds: Vec<Coin>,
+    ) -> StdResult<Option<Binary>> {
+        self.state.transfer(sender, contract, &funds)?;
+
+        let info = MessageInfo {
+            sender: Addr::unchecked(sender),
+            funds,
+        };
+        let response = self.call(contract, |code, deps, env| {
+            code.execute(deps, env, info, msg)
+        })?;
+        let data = response.data.clone();
+        self.process_response(res, contract, response)?;
+
+        Ok(data)
+    }
+
+    /// Dispatches the messages in order, each with the messages
+    /// it results in, before the next one
+    fn process_response(
+        &mut self,
+        res: &mut AppResponse,
+        contract: &str,
+        response: Response<TerraMsgWrapper>,
+    ) -> StdResult<()> {
+        res.attributes
+            .push((contract.to_string(), response.attributes));
+
+        for SubMsg {
+            id, msg, reply_on, ..
+        } in response.messages
+        {
+            let catches_error = matches!(reply_on, ReplyOn::Always | ReplyOn::Error);
+            let snapshot = if catches_error {
+                Some(self.state.clone())
+            } else {
+                None
+            };
+
+            let result = match self.dispatch(res, contract, msg) {
+                Ok(data) => {
+                    if !matches!(reply_on, ReplyOn::Always | ReplyOn::Success) {
+                        continue;
+                    }
+                    ContractResult::Ok(SubMsgExecutionResponse {
+                        events: vec![],
+                        data,
+                    })
+                }
+                Err(err) => match snapshot {
+                    Some(snapshot) => {
+                        self.state = snapshot;
+                        ContractResult::Err(err.to_string())
+                    }
+                    None => return Err(err),
+                },
+            };
+
+            let response = self.call(contract, |code, deps, env| {
+                code.reply(deps, env, Reply { id, result })
+            })?;
+            self.process_response(res, contract, response)?;
+        }
+
+        Ok(())
+    }
+
+    fn dispatch(
+        &mut self,
+        res: &mut AppResponse,
+        sender: &str,
+        msg: CosmosMsg<TerraMsgWrapper>,
+    ) -> StdResult<Option<Binary>> {
+        match msg {
+            CosmosMsg::Bank(BankMsg::Send { to_address, amount }) => {
+                self.state.transfer(sender, &to_address, &amount)?;
+                Ok(None)
+            }
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr,
+                msg,
+                funds,
+            }) => self.execute_contract(res, sender, &contract_addr, msg, funds),
+            CosmosMsg::Custom(msg) => {
+                res.custom_msgs.push((sender.to_string(), msg));
+                Ok(None)
+            }
+            msg => Err(StdError::generic_err(format!(
+                "Unsupported message: {:?}",
+                msg
+            ))),
+        }
+    }
+}
+
+/// Serves the bank balances, the registered contracts and a tax-free treasury
+struct AppQuerier<'a> {
+    app: &'a App,
+}
+
+impl AppQuerier<'_> {
+    fn query_contract(&self, contract: &str, msg: Binary) -> StdResult<Binary> {
+        let code = self
+            .app
+            .contracts
+            .get(contract)
+            .ok_or_else(|| StdError::generic_err(format!("No such contract: {}", contract)))?;
+
+        // the storage is taken out while the contract executes
+        let storage = self.app.state.storages.get(contract).ok_or_else(|| {
+            StdError::generic_err(format!("Contract queried while executing: {}", contract))
+        })?;
+
+        let deps = Deps {
+            storage,
+            api: &self.app.api,
+            querier: QuerierWrapper::new(self),
+        };
+        code.query(deps, self.app.env(contract), msg)
+    }
+}
+
+impl Querier for AppQuerier<'_> {
+    fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+        let request: QueryRequest<TerraQueryWrapper> = match from_slice(bin_request) {
+            Ok(v) => v,
+            Err(e) => {
+                return SystemResult::Err(SystemError::InvalidRequest {
+                    error: format!("Parsing query request: {}", e),
+                    request: bin_request.into(),
+                })
+            }
+        };
+
+        let res = match request {
+            QueryRequest::Bank(BankQuery::Balance { address, denom }) => {
+                to_binary(&BalanceResponse {
+                    amount: Coin {
+                        amount: self.app.balance(&address, &denom),
+                        denom,
+                    },
+                })
+            }
+            QueryRequest::Bank(BankQuery::AllBalances { address }) => {
+                to_binary(&AllBalanceResponse {
+                    amount: self
+                        .app
+                        .state
+                        .balances
+                        .iter()
+                        .filter(|((owner, _), amount)| *owner == address && !amount.is_zero())
+                        .map(|((_, denom), amount)| Coin {
+                            denom: denom.clone(),
+                            amount: *amount,
+                        })
+                        .collect(),
+                })
+            }
+            QueryRequest::Wasm(WasmQuery::Smart { contract_addr, msg }) => {
+                if !self.app.contracts.contains_key(&contract_addr) {
+                    return SystemResult::Err(SystemError::NoSuchContract {
+                        addr: contract_addr,
+                    });
+                }
+                self.query_contract(&contract_addr, msg)
+            }
+            QueryRequest::Wasm(WasmQuery::Raw { contract_addr, key }) => {
+                match self.app.state.storages.get(&contract_addr) {
+                    Some(storage) => Ok(Binary(storage.get(&key).unwrap_or_default())),
+                    None => {
+                        return SystemResult::Err(SystemError::NoSuchContract {
+                            addr: contract_addr,
+                        })
+                    }
+                }
+            }
+            QueryRequest::Custom(TerraQueryWrapper { query_data, .. }) => match query_data {
+                TerraQuery::TaxRate {} => to_binary(&TaxRateResponse {
+                    rate: Decimal::zero(),
+                }),
+                TerraQuery::TaxCap { .. } => to_binary(&TaxCapResponse {
+                    cap: Uint128::zero(),
+                }),
+                _ => {
+                    return SystemResult::Err(SystemError::UnsupportedRequest {
+                        kind: "terra".to_string(),
+                    })
+                }
+            },
+            _ => {
+                return SystemResult::Err(SystemError::UnsupportedRequest {
+                    kind: "unknown".to_string(),
+                })
+            }
+        };
+
+        SystemResult::Ok(ContractResult::from(res))
+    }
+}
@@ -0,0 +1,534 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use cosmwasm_bignumber::{Decimal256, Uint256};
+use cosmwasm_std::{
+    attr, from_binary, to_binary, BankMsg, Binary, Coin, CosmosMsg, Deps, DepsMut, Env,
+    MessageInfo, Reply, Response, StdError, StdResult, SubMsg, Uint128, WasmMsg,
+};
+use cosmwasm_storage::{Bucket, ReadonlyBucket, ReadonlySingleton, Singleton};
+use cw20::{BalanceResponse, Cw20Coin, Cw20ExecuteMsg, Cw20QueryMsg, Cw20ReceiveMsg};
+use terra_cosmwasm::TerraMsgWrapper;
+
+use moneymarket::market::{
+    BorrowerInfoResponse, ConfigResponse as MarketConfigResponse, EpochStateResponse,
+    ExecuteMsg as MarketExecuteMsg, QueryMsg as MarketQueryMsg,
+    StateResponse as MarketStateResponse,
+};
+use moneymarket::oracle::{
+    ExecuteMsg as OracleExecuteMsg, PriceResponse, PricesForResponse, PricesForResponseElem,
+    QueryMsg as OracleQueryMsg,
+};
+use moneymarket::overseer::{BorrowLimitResponse, QueryMsg as OverseerQueryMsg};
+use moneymarket::rates::BLOCKS_PER_YEAR;
+
+use crate::app::Contract;
+
+fn to_std_err<E: ToString>(err: E) -> StdError {
+    StdError::generic_err(err.to_string())
+}
+
+fn unsupported(contract: &str) -> StdError {
+    StdError::generic_err(format!("Unsupported by the {} stub", contract))
+}
+
+/// Lifts a response without custom messages to the Terra ones
+fn into_terra(res: Response) -> StdResult<Response<TerraMsgWrapper>> {
+    let mut terra_res = Response::new()
+        .add_attributes(res.attributes)
+        .add_events(res.events);
+    terra_res.data = res.data;
+
+    for sub_msg in res.messages {
+        let msg = match sub_msg.msg {
+            CosmosMsg::Bank(msg) => CosmosMsg::Bank(msg),
+            CosmosMsg::Wasm(msg) => CosmosMsg::Wasm(msg),
+            msg => {
+                return Err(StdError::generic_err(format!(
+                    "Unsupported message: {:?}",
+                    msg
+                )))
+            }
+        };
+        terra_res.messages.push(SubMsg {
+            id: sub_msg.id,
+            msg,
+            gas_limit: sub_msg.gas_limit,
+            reply_on: sub_msg.reply_on,
+        });
+    }
+
+    Ok(terra_res)
+}
+
+pub struct Overseer;
+
+impl Contract for Overseer {
+    fn instantiate(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        msg: Binary,
+    ) -> StdResult<Response<TerraMsgWrapper>> {
+        moneymarket_overseer::contract::instantiate(deps, env, info, from_binary(&msg)?)
+            .map_err(to_std_err)
+            .and_then(into_terra)
+    }
+
+    fn execute(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        msg: Binary,
+    ) -> StdResult<Response<TerraMsgWrapper>> {
+        moneymarket_overseer::contract::execute(deps, env, info, from_binary(&msg)?)
+            .map_err(to_std_err)
+            .and_then(into_terra)
+    }
+
+    fn query(&self, deps: Deps, env: Env, msg: Binary) -> StdResult<Binary> {
+        moneymarket_overseer::contract::query(deps, env, from_binary(&msg)?)
+    }
+
+    fn reply(&self, deps: DepsMut, env: Env, msg: Reply) -> StdResult<Response<TerraMsgWrapper>> {
+        moneymarket_overseer::contract::reply(deps, env, msg)
+            .map_err(to_std_err)
+            .and_then(into_terra)
+    }
+}
+
+pub struct CustodyBLuna;
+
+impl Contract for CustodyBLuna {
+    fn instantiate(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        msg: Binary,
+    ) -> StdResult<Response<TerraMsgWrapper>> {
+        moneymarket_custody_bluna::contract::instantiate(deps, env, info, from_binary(&msg)?)
+            .and_then(into_terra)
+    }
+
+    fn execute(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        msg: Binary,
+    ) -> StdResult<Response<TerraMsgWrapper>> {
+        moneymarket_custody_bluna::contract::execute(deps, env, info, from_binary(&msg)?)
+            .map_err(to_std_err)
+    }
+
+    fn query(&self, deps: Deps, env: Env, msg: Binary) -> StdResult<Binary> {
+        moneymarket_custody_bluna::contract::query(deps, env, from_binary(&msg)?)
+    }
+
+    fn reply(&self, deps: DepsMut, env: Env, msg: Reply) -> StdResult<Response<TerraMsgWrapper>> {
+        moneymarket_custody_bluna::contract::reply(deps, env, msg).map_err(to_std_err)
+    }
+}
+
+pub struct DistributionModel;
+
+impl Contract for DistributionModel {
+    fn instantiate(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        msg: Binary,
+    ) -> StdResult<Response<TerraMsgWrapper>> {
+        moneymarket_distribution_model::contract::instantiate(deps, env, info, from_binary(&msg)?)
+            .map_err(to_std_err)
+            .and_then(into_terra)
+    }
+
+    fn execute(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        msg: Binary,
+    ) -> StdResult<Response<TerraMsgWrapper>> {
+        moneymarket_distribution_model::contract::execute(deps, env, info, from_binary(&msg)?)
+            .map_err(to_std_err)
+            .and_then(into_terra)
+    }
+
+    fn query(&self, deps: Deps, env: Env, msg: Binary) -> StdResult<Binary> {
+        moneymarket_distribution_model::contract::query(deps, env, from_binary(&msg)?)
+    }
+}
+
+static KEY_STUB_STATE: &[u8] = b"state";
+static PREFIX_STUB_ENTRY: &[u8] = b"entry";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StubMarketInstantiateMsg {
+    pub overseer_contract: String,
+    pub distribution_model: String,
+    pub stable_denom: String,
+    pub aterra_supply: Uint256,
+}
+
+/// Lends against the overseer borrow limit at a constant exchange rate
+/// of one, and logs the epoch operations of the overseer
+pub struct StubMarket;
+
+impl Contract for StubMarket {
+    fn instantiate(
+        &self,
+        deps: DepsMut,
+        _env: Env,
+        _info: MessageInfo,
+        msg: Binary,
+    ) -> StdResult<Response<TerraMsgWrapper>> {
+        let msg: StubMarketInstantiateMsg = from_binary(&msg)?;
+        Singleton::new(deps.storage, KEY_STUB_STATE).save(&msg)?;
+
+        Ok(Response::default())
+    }
+
+    fn execute(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        msg: Binary,
+    ) -> StdResult<Response<TerraMsgWrapper>> {
+        let config: StubMarketInstantiateMsg =
+            ReadonlySingleton::new(deps.storage, KEY_STUB_STATE).load()?;
+
+        match from_binary(&msg)? {
+            MarketExecuteMsg::BorrowStable { borrow_amount, to } => {
+                let borrower = info.sender;
+                let borrow_limit: BorrowLimitResponse = deps.querier.query_wasm_smart(
+                    config.overseer_contract,
+                    &OverseerQueryMsg::BorrowLimit {
+                        borrower: borrower.to_string(),
+                        block_time: Some(env.block.time.seconds()),
+                    },
+                )?;
+
+                let mut loans: Bucket<Uint256> = Bucket::new(deps.storage, PREFIX_STUB_ENTRY);
+                let loan_amount = loans
+                    .may_load(borrower.as_bytes())?
+                    .unwrap_or_else(Uint256::zero)
+                    + borrow_amount;
+                if loan_amount > borrow_limit.borrow_limit {
+                    return Err(StdError::generic_err("Borrow amount exceeds the limit"));
+                }
+                loans.save(borrower.as_bytes(), &loan_amount)?;
+
+                Ok(Response::new()
+                    .add_message(BankMsg::Send {
+                        to_address: to.unwrap_or_else(|| borrower.to_string()),
+                        amount: vec![Coin {
+                            denom: config.stable_denom,
+                            amount: borrow_amount.into(),
+                        }],
+                    })
+                    .add_attributes(vec![
+                        attr("action", "borrow_stable"),
+                        attr("borrower", borrower),
+                        attr("borrow_amount", borrow_amount),
+                    ]))
+            }
+            MarketExecuteMsg::ExecuteEpochOperations {
+                distributed_interest,
+                ..
+            } => {
+                if info.sender != config.overseer_contract {
+                    return Err(StdError::generic_err("Unauthorized"));
+                }
+
+                Ok(Response::new().add_attributes(vec![
+                    attr("action", "execute_epoch_operations"),
+                    attr("distributed_interest", distributed_interest),
+                ]))
+            }
+            _ => Err(unsupported("market")),
+        }
+    }
+
+    fn query(&self, deps: Deps, env: Env, msg: Binary) -> StdResult<Binary> {
+        let config: StubMarketInstantiateMsg =
+            ReadonlySingleton::new(deps.storage, KEY_STUB_STATE).load()?;
+
+        match from_binary(&msg)? {
+            MarketQueryMsg::Config {} => to_binary(&MarketConfigResponse {
+                owner_addr: "owner".to_string(),
+                aterra_contract: "aterra".to_string(),
+                interest_model: "interest_model".to_string(),
+                distribution_model: config.distribution_model,
+                overseer_contract: config.overseer_contract,
+                collector_contract: "collector".to_string(),
+                distributor_contract: "distributor".to_string(),
+                stable_denom: config.stable_denom,
+                max_borrow_factor: Decimal256::one(),
+                blocks_per_year: BLOCKS_PER_YEAR,
+            }),
+            MarketQueryMsg::State { .. } => to_binary(&MarketStateResponse {
+                total_liabilities: Decimal256::zero(),
+                total_reserves: Decimal256::zero(),
+                last_interest_updated: env.block.height,
+                last_reward_updated: env.block.height,
+                global_interest_index: Decimal256::one(),
+                global_reward_index: Decimal256::zero(),
+                anc_emission_rate: Decimal256::one(),
+                prev_aterra_supply: config.aterra_supply,
+                prev_exchange_rate: Decimal256::one(),
+            }),
+            MarketQueryMsg::EpochState { .. } => to_binary(&EpochStateResponse {
+                exchange_rate: Decimal256::one(),
+                aterra_supply: config.aterra_supply,
+            }),
+            MarketQueryMsg::BorrowerInfo { borrower, .. } => {
+                let loans: ReadonlyBucket<Uint256> =
+                    ReadonlyBucket::new(deps.storage, PREFIX_STUB_ENTRY);
+                to_binary(&BorrowerInfoResponse {
+                    loan_amount: loans
+                        .may_load(borrower.as_bytes())?
+                        .unwrap_or_else(Uint256::zero),
+                    borrower,
+                    interest_index: Decimal256::one(),
+                    reward_index: Decimal256::zero(),
+                    pending_rewards: Decimal256::zero(),
+                })
+            }
+            _ => Err(unsupported("market")),
+        }
+    }
+}
+
+/// Serves the last fed price of each asset, updated at the time it was fed
+pub struct StubOracle;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+struct FedPrice {
+    rate: Decimal256,
+    last_updated: u64,
+}
+
+impl StubOracle {
+    fn price(deps: Deps, asset: &str) -> StdResult<PriceResponse> {
+        let prices: ReadonlyBucket<FedPrice> = ReadonlyBucket::new(deps.storage, PREFIX_STUB_ENTRY);
+        let price = prices.load(asset.as_bytes())?;
+
+        Ok(PriceResponse {
+            rate: price.rate,
+            last_updated_base: price.last_updated,
+            last_updated_quote: price.last_updated,
+            decimals_base: 6,
+            decimals_quote: 6,
+            age_seconds: None,
+            feeder_count: 1,
+            derived: false,
+            frozen: false,
+            source: "primary".to_string(),
+            inverted: false,
+        })
+    }
+}
+
+impl Contract for StubOracle {
+    fn instantiate(
+        &self,
+        _deps: DepsMut,
+        _env: Env,
+        _info: MessageInfo,
+        _msg: Binary,
+    ) -> StdResult<Response<TerraMsgWrapper>> {
+        Ok(Response::default())
+    }
+
+    fn execute(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        _info: MessageInfo,
+        msg: Binary,
+    ) -> StdResult<Response<TerraMsgWrapper>> {
+        match from_binary(&msg)? {
+            OracleExecuteMsg::FeedPrice { prices, .. } => {
+                let mut bucket: Bucket<FedPrice> = Bucket::new(deps.storage, PREFIX_STUB_ENTRY);
+                for (asset, rate) in prices {
+                    bucket.save(
+                        asset.as_bytes(),
+                        &FedPrice {
+                            rate,
+                            last_updated: env.block.time.seconds(),
+                        },
+                    )?;
+                }
+
+                Ok(Response::new().add_attribute("action", "feed_prices"))
+            }
+            OracleExecuteMsg::SnapshotPrices { assets } => Ok(Response::new()
+                .add_attribute("action", "snapshot_prices")
+                .add_attribute("assets", assets.join(","))),
+            _ => Err(unsupported("oracle")),
+        }
+    }
+
+    fn query(&self, deps: Deps, _env: Env, msg: Binary) -> StdResult<Binary> {
+        match from_binary(&msg)? {
+            OracleQueryMsg::Price { base, .. } => to_binary(&StubOracle::price(deps, &base)?),
+            OracleQueryMsg::PricesFor { assets, .. } => to_binary(&PricesForResponse {
+                prices: assets
+                    .into_iter()
+                    .map(|asset| match StubOracle::price(deps, &asset) {
+                        Ok(price) => PricesForResponseElem {
+                            asset,
+                            price: Some(price),
+                            error: None,
+                        },
+                        Err(err) => PricesForResponseElem {
+                            asset,
+                            price: None,
+                            error: Some(err.to_string()),
+                        },
+                    })
+                    .collect(),
+            }),
+            _ => Err(unsupported("oracle")),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+struct AccruedRewardsResponse {
+    rewards: Uint128,
+}
+
+/// bAsset reward contract without accrued rewards
+pub struct StubRewards;
+
+impl Contract for StubRewards {
+    fn instantiate(
+        &self,
+        _deps: DepsMut,
+        _env: Env,
+        _info: MessageInfo,
+        _msg: Binary,
+    ) -> StdResult<Response<TerraMsgWrapper>> {
+        Ok(Response::default())
+    }
+
+    fn execute(
+        &self,
+        _deps: DepsMut,
+        _env: Env,
+        _info: MessageInfo,
+        _msg: Binary,
+    ) -> StdResult<Response<TerraMsgWrapper>> {
+        Err(unsupported("rewards"))
+    }
+
+    fn query(&self, _deps: Deps, _env: Env, _msg: Binary) -> StdResult<Binary> {
+        to_binary(&AccruedRewardsResponse {
+            rewards: Uint128::zero(),
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StubTokenInstantiateMsg {
+    pub initial_balances: Vec<Cw20Coin>,
+}
+
+/// cw20 token supporting transfers and sends
+pub struct StubToken;
+
+impl StubToken {
+    fn transfer(deps: DepsMut, from: &str, to: &str, amount: Uint128) -> StdResult<()> {
+        let mut balances: Bucket<Uint128> = Bucket::new(deps.storage, PREFIX_STUB_ENTRY);
+        let from_balance = balances.may_load(from.as_bytes())?.unwrap_or_default();
+        balances.save(from.as_bytes(), &from_balance.checked_sub(amount)?)?;
+        let to_balance = balances.may_load(to.as_bytes())?.unwrap_or_default();
+        balances.save(to.as_bytes(), &(to_balance + amount))?;
+
+        Ok(())
+    }
+}
+
+impl Contract for StubToken {
+    fn instantiate(
+        &self,
+        deps: DepsMut,
+        _env: Env,
+        _info: MessageInfo,
+        msg: Binary,
+    ) -> StdResult<Response<TerraMsgWrapper>> {
+        let msg: StubTokenInstantiateMsg = from_binary(&msg)?;
+        let mut balances: Bucket<Uint128> = Bucket::new(deps.storage, PREFIX_STUB_ENTRY);
+        for Cw20Coin { address, amount } in msg.initial_balances {
+            balances.save(address.as_bytes(), &amount)?;
+        }
+
+        Ok(Response::default())
+    }
+
+    fn execute(
+        &self,
+        deps: DepsMut,
+        _env: Env,
+        info: MessageInfo,
+        msg: Binary,
+    ) -> StdResult<Response<TerraMsgWrapper>> {
+        match from_binary(&msg)? {
+            Cw20ExecuteMsg::Transfer { recipient, amount } => {
+                StubToken::transfer(deps, info.sender.as_str(), &recipient, amount)?;
+
+                Ok(Response::new().add_attributes(vec![
+                    attr("action", "transfer"),
+                    attr("to", recipient),
+                    attr("amount", amount),
+                ]))
+            }
+            Cw20ExecuteMsg::Send {
+                contract,
+                amount,
+                msg,
+            } => {
+                StubToken::transfer(deps, info.sender.as_str(), &contract, amount)?;
+
+                Ok(Response::new()
+                    .add_message(WasmMsg::Execute {
+                        contract_addr: contract.clone(),
+                        msg: Cw20ReceiveMsg {
+                            sender: info.sender.to_string(),
+                            amount,
+                            msg,
+                        }
+                        .into_binary()?,
+                        funds: vec![],
+                    })
+                    .add_attributes(vec![
+                        attr("action", "send"),
+                        attr("to", contract),
+                        attr("amount", amount),
+                    ]))
+            }
+            _ => Err(unsupported("token")),
+        }
+    }
+
+    fn query(&self, deps: Deps, _env: Env, msg: Binary) -> StdResult<Binary> {
+        match from_binary(&msg)? {
+            Cw20QueryMsg::Balance { address } => {
+                let balances: ReadonlyBucket<Uint128> =
+                    ReadonlyBucket::new(deps.storage, PREFIX_STUB_ENTRY);
+                to_binary(&BalanceResponse {
+                    balance: balances.may_load(address.as_bytes())?.unwrap_or_default(),
+                })
+            }
+            _ => Err(unsupported("token")),
+        }
+    }
+}
@@ -0,0 +1,17 @@
+//! In-process harness for the money market message flows. Contracts are
+//! registered under fixed addresses and the messages their handlers return
+//! are dispatched to the target contracts, so the tests exercise the actual
+//! round-trips instead of per-contract mocks. Each top level execution is
+//! atomic, as on chain.
+
+mod app;
+mod contracts;
+
+pub use crate::app::{App, AppResponse, Contract};
+pub use crate::contracts::{
+    CustodyBLuna, DistributionModel, Overseer, StubMarket, StubMarketInstantiateMsg, StubOracle,
+    StubRewards, StubToken, StubTokenInstantiateMsg,
+};
+
+#[cfg(test)]
+mod testing;
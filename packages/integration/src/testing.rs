@@ -0,0 +1,558 @@
+use std::str::FromStr;
+
+use cosmwasm_bignumber::{Decimal256, Uint256};
+use cosmwasm_std::{to_binary, Coin, Uint128};
+use cw20::{BalanceResponse, Cw20Coin, Cw20ExecuteMsg, Cw20QueryMsg};
+
+use moneymarket::custody::{
+    BAssetInfo, BorrowerResponse, Cw20HookMsg as CustodyCw20HookMsg,
+    ExecuteMsg as CustodyExecuteMsg, InstantiateMsg as CustodyInstantiateMsg,
+    QueryMsg as CustodyQueryMsg,
+};
+use moneymarket::distribution_model::{
+    InstantiateMsg as DistributionInstantiateMsg, QueryMsg as DistributionQueryMsg,
+    StateResponse as DistributionStateResponse,
+};
+use moneymarket::market::ExecuteMsg as MarketExecuteMsg;
+use moneymarket::oracle::ExecuteMsg as OracleExecuteMsg;
+use moneymarket::overseer::{
+    CollateralsResponse, ExecuteMsg as OverseerExecuteMsg,
+    InstantiateMsg as OverseerInstantiateMsg, QueryMsg as OverseerQueryMsg,
+};
+
+use crate::{
+    App, CustodyBLuna, DistributionModel, Overseer, StubMarket, StubMarketInstantiateMsg,
+    StubOracle, StubRewards, StubToken, StubTokenInstantiateMsg,
+};
+
+const BORROWER: &str = "addr0000";
+
+/// `distribution_overseer` is the overseer registered in the
+/// distribution model, allowed to record the emission rate
+fn setup(
+    anc_purchase_factor: Decimal256,
+    buffer_distribution_factor: Decimal256,
+    distribution_overseer: Option<&str>,
+) -> App {
+    let mut app = App::new();
+
+    app.instantiate(
+        "bluna",
+        Box::new(StubToken),
+        "owner",
+        &StubTokenInstantiateMsg {
+            initial_balances: vec![Cw20Coin {
+                address: BORROWER.to_string(),
+                amount: Uint128::from(1_000_000u64),
+            }],
+        },
+    )
+    .unwrap();
+    app.instantiate("oracle", Box::new(StubOracle), "owner", &())
+        .unwrap();
+    app.instantiate("reward", Box::new(StubRewards), "owner", &())
+        .unwrap();
+    app.instantiate(
+        "distribution",
+        Box::new(DistributionModel),
+        "owner",
+        &DistributionInstantiateMsg {
+            owner: "owner".to_string(),
+            risk_admin: None,
+            emission_cap: Decimal256::from_uint256(100u64),
+            emission_floor: Decimal256::from_uint256(10u64),
+            increment_multiplier: Decimal256::percent(110),
+            decrement_multiplier: Decimal256::percent(90),
+            max_rate_change_per_epoch: Decimal256::percent(50),
+            deposit_rate_floor: Decimal256::zero(),
+            deposit_rate_ceiling: Decimal256::from_str("0.00001").unwrap(),
+            overseer_contract: distribution_overseer.map(|overseer| overseer.to_string()),
+        },
+    )
+    .unwrap();
+    app.instantiate(
+        "market",
+        Box::new(StubMarket),
+        "owner",
+        &StubMarketInstantiateMsg {
+            overseer_contract: "overseer".to_string(),
+            distribution_model: "distribution".to_string(),
+            stable_denom: "uusd".to_string(),
+            aterra_supply: Uint256::from(1_000_000_000_000u64),
+        },
+    )
+    .unwrap();
+    app.instantiate(
+        "overseer",
+        Box::new(Overseer),
+        "owner",
+        &OverseerInstantiateMsg {
+            owner_addr: "owner".to_string(),
+            oracle_contract: "oracle".to_string(),
+            market_contract: "market".to_string(),
+            liquidation_contract: "liquidation".to_string(),
+            collector_contract: "collector".to_string(),
+            stable_denom: "uusd".to_string(),
+            epoch_period: 100,
+            threshold_deposit_rate: Decimal256::from_str("0.000001").unwrap(),
+            target_deposit_rate: Decimal256::from_str("0.000002").unwrap(),
+            buffer_distribution_factor,
+            anc_purchase_factor,
+            price_timeframe: 60,
+        },
+    )
+    .unwrap();
+    app.instantiate(
+        "custody_bluna",
+        Box::new(CustodyBLuna),
+        "owner",
+        &custody_instantiate_msg(),
+    )
+    .unwrap();
+
+    app.execute(
+        "owner",
+        "overseer",
+        &OverseerExecuteMsg::Whitelist {
+            name: "bAsset".to_string(),
+            symbol: "bAsset".to_string(),
+            collateral_token: "bluna".to_string(),
+            custody_contract: "custody_bluna".to_string(),
+            max_ltv: Decimal256::percent(60),
+            decimals: None,
+        },
+        &[],
+    )
+    .unwrap();
+    feed_price(&mut app);
+
+    app
+}
+
+fn custody_instantiate_msg() -> CustodyInstantiateMsg {
+    CustodyInstantiateMsg {
+        owner: "owner".to_string(),
+        collateral_token: "bluna".to_string(),
+        overseer_contract: "overseer".to_string(),
+        market_contract: "market".to_string(),
+        reward_contract: "reward".to_string(),
+        liquidation_contract: "liquidation".to_string(),
+        stable_denom: "uusd".to_string(),
+        basset_info: BAssetInfo {
+            name: "bAsset".to_string(),
+            symbol: "bAsset".to_string(),
+            decimals: 6,
+        },
+    }
+}
+
+fn feed_price(app: &mut App) {
+    app.execute(
+        "owner",
+        "oracle",
+        &OracleExecuteMsg::FeedPrice {
+            prices: vec![("bluna".to_string(), Decimal256::from_uint256(10u64))],
+            quote: None,
+        },
+        &[],
+    )
+    .unwrap();
+}
+
+fn locked_collateral(app: &App) -> Uint256 {
+    let res: CollateralsResponse = app
+        .query(
+            "overseer",
+            &OverseerQueryMsg::Collaterals {
+                borrower: BORROWER.to_string(),
+            },
+        )
+        .unwrap();
+    res.collaterals
+        .iter()
+        .filter(|(token, _)| token == "bluna")
+        .map(|(_, amount)| *amount)
+        .fold(Uint256::zero(), |acc, amount| acc + amount)
+}
+
+fn custody_borrower(app: &App) -> BorrowerResponse {
+    custody_borrower_of(app, "custody_bluna")
+}
+
+fn custody_borrower_of(app: &App, custody: &str) -> BorrowerResponse {
+    app.query(
+        custody,
+        &CustodyQueryMsg::Borrower {
+            address: BORROWER.to_string(),
+        },
+    )
+    .unwrap()
+}
+
+fn token_balance(app: &App, address: &str) -> Uint128 {
+    let res: BalanceResponse = app
+        .query(
+            "bluna",
+            &Cw20QueryMsg::Balance {
+                address: address.to_string(),
+            },
+        )
+        .unwrap();
+    res.balance
+}
+
+/// The collateral the overseer records is exactly the one custody keeps
+/// unspendable, and custody holds every token it accounts for
+fn assert_collateral_invariants(app: &App) {
+    let borrower = custody_borrower(app);
+    assert_eq!(
+        locked_collateral(app),
+        borrower.balance - borrower.spendable
+    );
+    assert_eq!(
+        Uint256::from(token_balance(app, "custody_bluna")),
+        borrower.balance
+    );
+}
+
+#[test]
+fn lock_unlock_withdraw() {
+    let mut app = setup(Decimal256::zero(), Decimal256::zero(), Some("overseer"));
+    app.set_balance(
+        "market",
+        Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::from(10_000_000u64),
+        },
+    );
+
+    let res = app
+        .execute(
+            BORROWER,
+            "bluna",
+            &Cw20ExecuteMsg::Send {
+                contract: "custody_bluna".to_string(),
+                amount: Uint128::from(1_000_000u64),
+                msg: to_binary(&CustodyCw20HookMsg::DepositCollateral {}).unwrap(),
+            },
+            &[],
+        )
+        .unwrap();
+    assert!(res.has_action("custody_bluna", "deposit_collateral"));
+    assert_collateral_invariants(&app);
+
+    let res = app
+        .execute(
+            BORROWER,
+            "overseer",
+            &OverseerExecuteMsg::LockCollateral {
+                collaterals: vec![("bluna".to_string(), Uint256::from(1_000_000u64))],
+            },
+            &[],
+        )
+        .unwrap();
+    assert!(res.has_action("overseer", "lock_collateral"));
+    assert!(res.has_action("custody_bluna", "lock_collateral"));
+    assert_eq!(locked_collateral(&app), Uint256::from(1_000_000u64));
+    assert_collateral_invariants(&app);
+
+    // borrow limit = 1_000_000 * 10 * 0.6
+    let err = app
+        .execute(
+            BORROWER,
+            "market",
+            &MarketExecuteMsg::BorrowStable {
+                borrow_amount: Uint256::from(6_000_001u64),
+                to: None,
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Generic error: Borrow amount exceeds the limit"
+    );
+    app.execute(
+        BORROWER,
+        "market",
+        &MarketExecuteMsg::BorrowStable {
+            borrow_amount: Uint256::from(3_000_000u64),
+            to: None,
+        },
+        &[],
+    )
+    .unwrap();
+    assert_eq!(app.balance(BORROWER, "uusd"), Uint128::from(3_000_000u64));
+    assert_eq!(app.balance("market", "uusd"), Uint128::from(7_000_000u64));
+
+    // the remaining 400_000 only cover 2_400_000 of the loan
+    app.execute(
+        BORROWER,
+        "overseer",
+        &OverseerExecuteMsg::UnlockCollateral {
+            collaterals: vec![("bluna".to_string(), Uint256::from(600_000u64))],
+        },
+        &[],
+    )
+    .unwrap_err();
+    assert_eq!(locked_collateral(&app), Uint256::from(1_000_000u64));
+    assert_collateral_invariants(&app);
+
+    let res = app
+        .execute(
+            BORROWER,
+            "overseer",
+            &OverseerExecuteMsg::UnlockCollateral {
+                collaterals: vec![("bluna".to_string(), Uint256::from(400_000u64))],
+            },
+            &[],
+        )
+        .unwrap();
+    assert!(res.has_action("custody_bluna", "unlock_collateral"));
+    assert_eq!(locked_collateral(&app), Uint256::from(600_000u64));
+    assert_eq!(custody_borrower(&app).spendable, Uint256::from(400_000u64));
+    assert_collateral_invariants(&app);
+
+    let res = app
+        .execute(
+            BORROWER,
+            "custody_bluna",
+            &CustodyExecuteMsg::WithdrawCollateral {
+                borrower: None,
+                amount: Some(Uint256::from(300_000u64)),
+            },
+            &[],
+        )
+        .unwrap();
+    assert!(res.has_action("bluna", "transfer"));
+    assert_eq!(token_balance(&app, BORROWER), Uint128::from(300_000u64));
+    assert_collateral_invariants(&app);
+
+    // unlock and withdraw in one go; the last 100_000 spendable stay
+    app.execute(
+        BORROWER,
+        "overseer",
+        &OverseerExecuteMsg::UnlockAndWithdrawCollateral {
+            collaterals: vec![("bluna".to_string(), Uint256::from(100_000u64))],
+        },
+        &[],
+    )
+    .unwrap();
+    assert_eq!(locked_collateral(&app), Uint256::from(500_000u64));
+    assert_eq!(token_balance(&app, BORROWER), Uint128::from(400_000u64));
+    assert_eq!(custody_borrower(&app).spendable, Uint256::from(100_000u64));
+    assert_collateral_invariants(&app);
+
+    // locked collateral cannot be withdrawn
+    app.execute(
+        BORROWER,
+        "custody_bluna",
+        &CustodyExecuteMsg::WithdrawCollateral {
+            borrower: None,
+            amount: Some(Uint256::from(100_001u64)),
+        },
+        &[],
+    )
+    .unwrap_err();
+    assert_eq!(token_balance(&app, BORROWER), Uint128::from(400_000u64));
+    assert_collateral_invariants(&app);
+}
+
+#[test]
+fn epoch_operations() {
+    let mut app = setup(
+        Decimal256::percent(20),
+        Decimal256::percent(20),
+        Some("overseer"),
+    );
+    app.set_balance(
+        "overseer",
+        Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::from(1_000_000_000u64),
+        },
+    );
+
+    app.execute(
+        "addr0000",
+        "overseer",
+        &OverseerExecuteMsg::ExecuteEpochOperations {},
+        &[],
+    )
+    .unwrap_err();
+
+    // no deposits were recorded yet, so the whole
+    // buffer stays except the anc purchase
+    app.advance_blocks(100);
+    feed_price(&mut app);
+    let res = app
+        .execute(
+            "addr0000",
+            "overseer",
+            &OverseerExecuteMsg::ExecuteEpochOperations {},
+            &[],
+        )
+        .unwrap();
+    assert!(res.has_action("oracle", "snapshot_prices"));
+    assert!(res.has_action("overseer", "update_epoch_state"));
+    assert!(res.has_action("market", "execute_epoch_operations"));
+    assert_eq!(
+        app.balance("collector", "uusd"),
+        Uint128::from(200_000_000u64)
+    );
+    assert_eq!(app.balance("market", "uusd"), Uint128::zero());
+    assert_eq!(
+        app.balance("overseer", "uusd"),
+        Uint128::from(800_000_000u64)
+    );
+
+    let state: DistributionStateResponse = app
+        .query("distribution", &DistributionQueryMsg::State {})
+        .unwrap();
+    assert_eq!(state.last_updated, app.block.height);
+
+    // missing deposits = 1e12 * 100 blocks * 1e-6 = 1e8,
+    // below the distribution buffer of 8e8 * 0.2
+    app.advance_blocks(100);
+    feed_price(&mut app);
+    let res = app
+        .execute(
+            "addr0000",
+            "overseer",
+            &OverseerExecuteMsg::ExecuteEpochOperations {},
+            &[],
+        )
+        .unwrap();
+    assert_eq!(
+        app.balance("collector", "uusd"),
+        Uint128::from(200_000_000u64)
+    );
+    assert_eq!(app.balance("market", "uusd"), Uint128::from(100_000_000u64));
+    assert_eq!(
+        app.balance("overseer", "uusd"),
+        Uint128::from(700_000_000u64)
+    );
+    assert!(res
+        .attributes
+        .iter()
+        .filter(|(contract, _)| contract == "market")
+        .flat_map(|(_, attrs)| attrs.iter())
+        .any(|attr| attr.key == "distributed_interest" && attr.value == "100000000"));
+}
+
+#[test]
+fn epoch_operations_without_distribution_overseer() {
+    let mut app = setup(Decimal256::percent(20), Decimal256::percent(20), None);
+    app.set_balance(
+        "overseer",
+        Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::from(1_000_000_000u64),
+        },
+    );
+    let state: DistributionStateResponse = app
+        .query("distribution", &DistributionQueryMsg::State {})
+        .unwrap();
+
+    // the model refuses the emission rate, the epoch still goes through
+    app.advance_blocks(100);
+    feed_price(&mut app);
+    let res = app
+        .execute(
+            "addr0000",
+            "overseer",
+            &OverseerExecuteMsg::ExecuteEpochOperations {},
+            &[],
+        )
+        .unwrap();
+    assert!(res.has_action("overseer", "update_epoch_state"));
+    assert!(res.has_action("market", "execute_epoch_operations"));
+    assert!(res.has_action("overseer", "update_emission_rate_failed"));
+    assert_eq!(
+        app.balance("collector", "uusd"),
+        Uint128::from(200_000_000u64)
+    );
+
+    let new_state: DistributionStateResponse = app
+        .query("distribution", &DistributionQueryMsg::State {})
+        .unwrap();
+    assert_eq!(new_state.last_updated, state.last_updated);
+}
+
+#[test]
+fn migrate_custody() {
+    let mut app = setup(Decimal256::zero(), Decimal256::zero(), Some("overseer"));
+    app.instantiate(
+        "custody_bluna2",
+        Box::new(CustodyBLuna),
+        "owner",
+        &custody_instantiate_msg(),
+    )
+    .unwrap();
+
+    app.execute(
+        BORROWER,
+        "bluna",
+        &Cw20ExecuteMsg::Send {
+            contract: "custody_bluna".to_string(),
+            amount: Uint128::from(1_000_000u64),
+            msg: to_binary(&CustodyCw20HookMsg::DepositCollateral {}).unwrap(),
+        },
+        &[],
+    )
+    .unwrap();
+    app.execute(
+        BORROWER,
+        "overseer",
+        &OverseerExecuteMsg::LockCollateral {
+            collaterals: vec![("bluna".to_string(), Uint256::from(600_000u64))],
+        },
+        &[],
+    )
+    .unwrap();
+
+    // the borrower balances follow the tokens to the new custody
+    let res = app
+        .execute(
+            "owner",
+            "overseer",
+            &OverseerExecuteMsg::MigrateCustody {
+                collateral_token: "bluna".to_string(),
+                new_custody_contract: "custody_bluna2".to_string(),
+            },
+            &[],
+        )
+        .unwrap();
+    assert!(res.has_action("custody_bluna2", "accept_migration"));
+    assert!(res.has_action("custody_bluna", "migrate_balances"));
+    assert!(res.has_action("custody_bluna2", "migrate_borrowers"));
+
+    assert_eq!(token_balance(&app, "custody_bluna"), Uint128::zero());
+    assert_eq!(
+        token_balance(&app, "custody_bluna2"),
+        Uint128::from(1_000_000u64)
+    );
+    assert_eq!(custody_borrower(&app).balance, Uint256::zero());
+    let borrower = custody_borrower_of(&app, "custody_bluna2");
+    assert_eq!(borrower.balance, Uint256::from(1_000_000u64));
+    assert_eq!(borrower.spendable, Uint256::from(400_000u64));
+    assert_eq!(locked_collateral(&app), Uint256::from(600_000u64));
+
+    // the overseer unlocks from the new custody
+    let res = app
+        .execute(
+            BORROWER,
+            "overseer",
+            &OverseerExecuteMsg::UnlockAndWithdrawCollateral {
+                collaterals: vec![("bluna".to_string(), Uint256::from(600_000u64))],
+            },
+            &[],
+        )
+        .unwrap();
+    assert!(res.has_action("custody_bluna2", "unlock_collateral"));
+    assert_eq!(token_balance(&app, BORROWER), Uint128::from(600_000u64));
+    assert_eq!(
+        custody_borrower_of(&app, "custody_bluna2").spendable,
+        Uint256::from(400_000u64)
+    );
+}
@@ -0,0 +1,11 @@
+//! Mock querier shared by the contract test suites. It answers the cross
+//! contract queries of the money market (market, oracle, overseer, custody,
+//! liquidation, distribution model, cw20 tokens and the terra treasury),
+//! each configured through a `with_*` builder.
+
+mod mock_querier;
+
+pub use crate::mock_querier::{mock_dependencies, CustodyBorrower, PriceInfo, WasmMockQuerier};
+
+#[cfg(test)]
+mod testing;
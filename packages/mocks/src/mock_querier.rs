@@ -0,0 +1,754 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use cosmwasm_bignumber::{Decimal256, Uint256};
+use cosmwasm_std::testing::{MockApi, MockQuerier, MockStorage, MOCK_CONTRACT_ADDR};
+use cosmwasm_std::{
+    from_binary, from_slice, to_binary, Coin, ContractResult, Decimal, OwnedDeps, Querier,
+    QuerierResult, QueryRequest, SystemError, SystemResult, Uint128, WasmQuery,
+};
+use std::collections::HashMap;
+
+use cw20::{BalanceResponse, TokenInfoResponse};
+use moneymarket::custody::BorrowerResponse;
+use moneymarket::distribution_model::{AncEmissionRateResponse, DepositRateBoundsResponse};
+use moneymarket::liquidation::LiquidationAmountResponse;
+use moneymarket::market::{
+    BorrowerInfoResponse, ConfigResponse, EpochStateResponse, StateResponse,
+};
+use moneymarket::oracle::{PriceResponse, PricesForResponse, PricesForResponseElem};
+use moneymarket::overseer::{BorrowLimitResponse, WhitelistResponse, WhitelistResponseElem};
+use moneymarket::pagination::{clamp_limit, OrderBy, DEFAULT_LIMIT, MAX_LIMIT};
+use moneymarket::rates::BLOCKS_PER_YEAR;
+use moneymarket::tokens::TokensHuman;
+
+use terra_cosmwasm::{TaxCapResponse, TaxRateResponse, TerraQuery, TerraQueryWrapper, TerraRoute};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryMsg {
+    /// Query epoch state to market contract
+    EpochState {
+        block_height: Option<u64>,
+        distributed_interest: Option<Uint256>,
+    },
+    /// Query loan amount to market contract
+    BorrowerInfo {
+        borrower: String,
+        block_height: Option<u64>,
+    },
+    /// Query oracle price to oracle contract
+    Price {
+        base: String,
+        quote: String,
+        block_time: Option<u64>,
+    },
+    /// Query batched oracle prices to oracle contract
+    PricesFor {
+        assets: Vec<String>,
+        quote: String,
+        block_time: Option<u64>,
+    },
+    /// Query liquidation amount to liquidation model contract
+    LiquidationAmount {
+        borrow_amount: Uint256,
+        borrow_limit: Uint256,
+        collaterals: TokensHuman,
+        collateral_prices: Vec<Decimal256>,
+    },
+    /// Query config to market contract
+    Config {},
+    /// Query deposit rate bounds to distribution model contract
+    DepositRateBounds {},
+    /// Query state to market contract
+    State { block_height: Option<u64> },
+    /// Query anc emission rate to distribution model contract
+    AncEmissionRate {
+        deposit_rate: Decimal256,
+        target_deposit_rate: Decimal256,
+        threshold_deposit_rate: Decimal256,
+        current_emission_rate: Decimal256,
+    },
+    /// Query borrower info to custody contract
+    Borrower { address: String },
+    /// Query borrow limit to overseer contract
+    BorrowLimit {
+        borrower: String,
+        block_time: Option<u64>,
+    },
+    /// Query whitelist to overseer contract
+    Whitelist {
+        collateral_token: Option<String>,
+        start_after: Option<String>,
+        limit: Option<u32>,
+        order_by: Option<OrderBy>,
+    },
+    /// Query cw20 balance to token contract
+    Balance { address: String },
+    /// Query cw20 token info to token contract
+    TokenInfo {},
+}
+
+/// mock_dependencies is a drop-in replacement for cosmwasm_std::testing::mock_dependencies
+/// this uses our CustomQuerier.
+pub fn mock_dependencies(
+    contract_balance: &[Coin],
+) -> OwnedDeps<MockStorage, MockApi, WasmMockQuerier> {
+    let custom_querier: WasmMockQuerier =
+        WasmMockQuerier::new(MockQuerier::new(&[(MOCK_CONTRACT_ADDR, contract_balance)]));
+
+    OwnedDeps {
+        storage: MockStorage::default(),
+        api: MockApi::default(),
+        querier: custom_querier,
+    }
+}
+
+pub struct WasmMockQuerier {
+    base: MockQuerier<TerraQueryWrapper>,
+    tax_querier: TaxQuerier,
+    epoch_state_querier: EpochStateQuerier,
+    oracle_price_querier: OraclePriceQuerier,
+    loan_amount_querier: LoanAmountQuerier,
+    liquidation_percent_querier: LiquidationPercentQuerier,
+    deposit_rate_bounds: (Decimal256, Decimal256),
+    anc_emission_rate: Decimal256,
+    custody_borrower_querier: CustodyBorrowerQuerier,
+    borrow_limit_querier: BorrowLimitQuerier,
+    whitelist: Vec<WhitelistResponseElem>,
+    token_querier: TokenQuerier,
+}
+
+#[derive(Clone, Default)]
+pub struct TaxQuerier {
+    rate: Decimal,
+    // this lets us iterate over all pairs that match the first string
+    caps: HashMap<String, Uint128>,
+}
+
+impl TaxQuerier {
+    pub fn new(rate: Decimal, caps: &[(&String, &Uint128)]) -> Self {
+        TaxQuerier {
+            rate,
+            caps: caps_to_map(caps),
+        }
+    }
+}
+
+pub(crate) fn caps_to_map(caps: &[(&String, &Uint128)]) -> HashMap<String, Uint128> {
+    let mut owner_map: HashMap<String, Uint128> = HashMap::new();
+    for (denom, cap) in caps.iter() {
+        owner_map.insert(denom.to_string(), **cap);
+    }
+    owner_map
+}
+
+/// Price served by the mocked oracle
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PriceInfo {
+    pub rate: Decimal256,
+    pub last_updated_base: u64,
+    pub last_updated_quote: u64,
+}
+
+#[derive(Clone, Default)]
+pub struct OraclePriceQuerier {
+    // this lets us iterate over all pairs that match the first string
+    oracle_price: HashMap<(String, String), PriceInfo>,
+    deprecated_assets: Vec<String>,
+    // when set, other contracts do not answer price queries
+    oracle_contract: Option<String>,
+}
+
+impl OraclePriceQuerier {
+    pub fn price(
+        &self,
+        contract_addr: &str,
+        base: String,
+        quote: String,
+        block_time: Option<u64>,
+    ) -> Result<PriceResponse, String> {
+        if matches!(&self.oracle_contract, Some(oracle_contract) if oracle_contract != contract_addr)
+        {
+            return Err(format!("{} is not an oracle contract", contract_addr));
+        }
+
+        if self.deprecated_assets.contains(&base) {
+            return Err("asset deprecated".to_string());
+        }
+
+        self.oracle_price
+            .get(&(base, quote))
+            .map(|v| PriceResponse {
+                rate: v.rate,
+                last_updated_base: v.last_updated_base,
+                last_updated_quote: v.last_updated_quote,
+                decimals_base: 6,
+                decimals_quote: 6,
+                age_seconds: block_time.map(|block_time| {
+                    block_time
+                        .saturating_sub(std::cmp::min(v.last_updated_base, v.last_updated_quote))
+                }),
+                feeder_count: 1,
+                derived: false,
+                frozen: false,
+                source: "primary".to_string(),
+                inverted: false,
+            })
+            .ok_or_else(|| "No oracle price exists".to_string())
+    }
+
+    pub fn new(oracle_price: &[(&(String, String), &PriceInfo)]) -> Self {
+        OraclePriceQuerier {
+            oracle_price: oracle_price_to_map(oracle_price),
+            deprecated_assets: vec![],
+            oracle_contract: None,
+        }
+    }
+}
+
+pub(crate) fn oracle_price_to_map(
+    oracle_price: &[(&(String, String), &PriceInfo)],
+) -> HashMap<(String, String), PriceInfo> {
+    let mut oracle_price_map: HashMap<(String, String), PriceInfo> = HashMap::new();
+    for (base_quote, oracle_price) in oracle_price.iter() {
+        oracle_price_map.insert((*base_quote).clone(), **oracle_price);
+    }
+
+    oracle_price_map
+}
+
+#[derive(Clone, Default)]
+pub struct EpochStateQuerier {
+    // this lets us iterate over all pairs that match the first string
+    epoch_state: HashMap<String, (Uint256, Decimal256)>,
+}
+
+impl EpochStateQuerier {
+    pub fn new(epoch_state: &[(&String, &(Uint256, Decimal256))]) -> Self {
+        EpochStateQuerier {
+            epoch_state: epoch_state_to_map(epoch_state),
+        }
+    }
+}
+
+pub(crate) fn epoch_state_to_map(
+    epoch_state: &[(&String, &(Uint256, Decimal256))],
+) -> HashMap<String, (Uint256, Decimal256)> {
+    let mut epoch_state_map: HashMap<String, (Uint256, Decimal256)> = HashMap::new();
+    for (market_contract, epoch_state) in epoch_state.iter() {
+        epoch_state_map.insert((*market_contract).clone(), **epoch_state);
+    }
+    epoch_state_map
+}
+
+#[derive(Clone, Default)]
+pub struct LoanAmountQuerier {
+    // this lets us iterate over all pairs that match the first string
+    borrower_amount: HashMap<String, Uint256>,
+}
+
+impl LoanAmountQuerier {
+    pub fn new(borrower_amount: &[(&String, &Uint256)]) -> Self {
+        LoanAmountQuerier {
+            borrower_amount: borrower_amount_to_map(borrower_amount),
+        }
+    }
+}
+
+pub(crate) fn borrower_amount_to_map(
+    borrower_amount: &[(&String, &Uint256)],
+) -> HashMap<String, Uint256> {
+    let mut borrower_amount_map: HashMap<String, Uint256> = HashMap::new();
+    for (market_contract, borrower_amount) in borrower_amount.iter() {
+        borrower_amount_map.insert((*market_contract).clone(), **borrower_amount);
+    }
+    borrower_amount_map
+}
+
+/// Borrower address with its (balance, spendable) collateral
+pub type CustodyBorrower<'a> = (&'a String, &'a (Uint256, Uint256));
+
+#[derive(Clone, Default)]
+pub struct CustodyBorrowerQuerier {
+    // (balance, spendable) by custody contract and borrower
+    borrowers: HashMap<String, HashMap<String, (Uint256, Uint256)>>,
+}
+
+impl CustodyBorrowerQuerier {
+    pub fn new(borrowers: &[(&String, &[CustodyBorrower])]) -> Self {
+        let mut borrowers_map: HashMap<String, HashMap<String, (Uint256, Uint256)>> =
+            HashMap::new();
+        for (custody_contract, borrowers) in borrowers.iter() {
+            let mut custody_map: HashMap<String, (Uint256, Uint256)> = HashMap::new();
+            for (borrower, info) in borrowers.iter() {
+                custody_map.insert(borrower.to_string(), **info);
+            }
+            borrowers_map.insert(custody_contract.to_string(), custody_map);
+        }
+
+        CustodyBorrowerQuerier {
+            borrowers: borrowers_map,
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct BorrowLimitQuerier {
+    // this lets us iterate over all pairs that match the first string
+    borrow_limit: HashMap<String, Uint256>,
+}
+
+impl BorrowLimitQuerier {
+    pub fn new(borrow_limit: &[(&String, &Uint256)]) -> Self {
+        BorrowLimitQuerier {
+            borrow_limit: borrower_amount_to_map(borrow_limit),
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct TokenQuerier {
+    // this lets us iterate over all pairs that match the first string
+    balances: HashMap<String, HashMap<String, Uint128>>,
+}
+
+impl TokenQuerier {
+    pub fn new(balances: &[(&String, &[(&String, &Uint128)])]) -> Self {
+        TokenQuerier {
+            balances: balances_to_map(balances),
+        }
+    }
+}
+
+pub(crate) fn balances_to_map(
+    balances: &[(&String, &[(&String, &Uint128)])],
+) -> HashMap<String, HashMap<String, Uint128>> {
+    let mut balances_map: HashMap<String, HashMap<String, Uint128>> = HashMap::new();
+    for (contract_addr, balances) in balances.iter() {
+        let mut contract_balances_map: HashMap<String, Uint128> = HashMap::new();
+        for (addr, balance) in balances.iter() {
+            contract_balances_map.insert(addr.to_string(), **balance);
+        }
+
+        balances_map.insert(contract_addr.to_string(), contract_balances_map);
+    }
+    balances_map
+}
+
+impl Querier for WasmMockQuerier {
+    fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+        // MockQuerier doesn't support Custom, so we ignore it completely here
+        let request: QueryRequest<TerraQueryWrapper> = match from_slice(bin_request) {
+            Ok(v) => v,
+            Err(e) => {
+                return SystemResult::Err(SystemError::InvalidRequest {
+                    error: format!("Parsing query request: {}", e),
+                    request: bin_request.into(),
+                })
+            }
+        };
+        self.handle_query(&request)
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct LiquidationPercentQuerier {
+    // this lets us iterate over all pairs that match the first string
+    liquidation_percent: HashMap<String, Decimal256>,
+}
+
+impl LiquidationPercentQuerier {
+    pub fn new(liquidation_percent: &[(&String, &Decimal256)]) -> Self {
+        LiquidationPercentQuerier {
+            liquidation_percent: liquidation_percent_to_map(liquidation_percent),
+        }
+    }
+}
+
+pub(crate) fn liquidation_percent_to_map(
+    liquidation_percent: &[(&String, &Decimal256)],
+) -> HashMap<String, Decimal256> {
+    let mut liquidation_percent_map: HashMap<String, Decimal256> = HashMap::new();
+    for (liquidation_contract, liquidation_percent) in liquidation_percent.iter() {
+        liquidation_percent_map.insert((*liquidation_contract).clone(), **liquidation_percent);
+    }
+    liquidation_percent_map
+}
+
+impl WasmMockQuerier {
+    pub fn handle_query(&self, request: &QueryRequest<TerraQueryWrapper>) -> QuerierResult {
+        match &request {
+            QueryRequest::Custom(TerraQueryWrapper { route, query_data }) => {
+                if &TerraRoute::Treasury == route {
+                    match query_data {
+                        TerraQuery::TaxRate {} => {
+                            let res = TaxRateResponse {
+                                rate: self.tax_querier.rate,
+                            };
+                            SystemResult::Ok(ContractResult::from(to_binary(&res)))
+                        }
+                        TerraQuery::TaxCap { denom } => {
+                            let cap = self
+                                .tax_querier
+                                .caps
+                                .get(denom)
+                                .copied()
+                                .unwrap_or_default();
+                            let res = TaxCapResponse { cap };
+                            SystemResult::Ok(ContractResult::from(to_binary(&res)))
+                        }
+                        _ => panic!("DO NOT ENTER HERE"),
+                    }
+                } else {
+                    panic!("DO NOT ENTER HERE")
+                }
+            }
+            QueryRequest::Wasm(WasmQuery::Smart { contract_addr, msg }) => {
+                match from_binary(msg).unwrap() {
+                    QueryMsg::EpochState {
+                        block_height: _,
+                        distributed_interest: _,
+                    } => match self.epoch_state_querier.epoch_state.get(contract_addr) {
+                        Some(v) => {
+                            SystemResult::Ok(ContractResult::from(to_binary(&EpochStateResponse {
+                                aterra_supply: v.0,
+                                exchange_rate: v.1,
+                            })))
+                        }
+                        None => SystemResult::Err(SystemError::InvalidRequest {
+                            error: "No epoch state exists".to_string(),
+                            request: msg.as_slice().into(),
+                        }),
+                    },
+                    QueryMsg::BorrowerInfo {
+                        borrower,
+                        block_height: _,
+                    } => match self.loan_amount_querier.borrower_amount.get(&borrower) {
+                        Some(v) => SystemResult::Ok(ContractResult::from(to_binary(
+                            &BorrowerInfoResponse {
+                                borrower,
+                                interest_index: Decimal256::one(),
+                                reward_index: Decimal256::zero(),
+                                loan_amount: *v,
+                                pending_rewards: Decimal256::zero(),
+                            },
+                        ))),
+                        None => SystemResult::Err(SystemError::InvalidRequest {
+                            error: "No borrow amount exists".to_string(),
+                            request: msg.as_slice().into(),
+                        }),
+                    },
+                    QueryMsg::Price {
+                        base,
+                        quote,
+                        block_time,
+                    } => match self.oracle_price_querier.price(
+                        contract_addr,
+                        base,
+                        quote,
+                        block_time,
+                    ) {
+                        Ok(price) => SystemResult::Ok(ContractResult::from(to_binary(&price))),
+                        Err(error) => SystemResult::Err(SystemError::InvalidRequest {
+                            error,
+                            request: msg.as_slice().into(),
+                        }),
+                    },
+                    QueryMsg::PricesFor {
+                        assets,
+                        quote,
+                        block_time,
+                    } => SystemResult::Ok(ContractResult::from(to_binary(&PricesForResponse {
+                        prices: assets
+                            .into_iter()
+                            .map(|asset| {
+                                let price = self.oracle_price_querier.price(
+                                    contract_addr,
+                                    asset.clone(),
+                                    quote.clone(),
+                                    block_time,
+                                );
+                                match price {
+                                    Ok(price) => PricesForResponseElem {
+                                        asset,
+                                        price: Some(price),
+                                        error: None,
+                                    },
+                                    Err(error) => PricesForResponseElem {
+                                        asset,
+                                        price: None,
+                                        error: Some(error),
+                                    },
+                                }
+                            })
+                            .collect(),
+                    }))),
+                    QueryMsg::LiquidationAmount {
+                        borrow_amount,
+                        borrow_limit,
+                        collaterals,
+                        collateral_prices: _,
+                    } => {
+                        match self
+                            .liquidation_percent_querier
+                            .liquidation_percent
+                            .get(contract_addr)
+                        {
+                            Some(v) => {
+                                if borrow_amount > borrow_limit {
+                                    SystemResult::Ok(ContractResult::from(to_binary(
+                                        &LiquidationAmountResponse {
+                                            collaterals: collaterals
+                                                .iter()
+                                                .map(|x| (x.0.clone(), x.1 * *v))
+                                                .collect::<TokensHuman>()
+                                                .to_vec(),
+                                        },
+                                    )))
+                                } else {
+                                    SystemResult::Ok(ContractResult::from(to_binary(
+                                        &LiquidationAmountResponse {
+                                            collaterals: vec![],
+                                        },
+                                    )))
+                                }
+                            }
+                            None => SystemResult::Err(SystemError::InvalidRequest {
+                                error: "No liquidation percent exists".to_string(),
+                                request: msg.as_slice().into(),
+                            }),
+                        }
+                    }
+                    QueryMsg::Config {} => {
+                        SystemResult::Ok(ContractResult::from(to_binary(&ConfigResponse {
+                            owner_addr: "owner".to_string(),
+                            aterra_contract: "aterra".to_string(),
+                            interest_model: "interest".to_string(),
+                            distribution_model: "distribution".to_string(),
+                            overseer_contract: MOCK_CONTRACT_ADDR.to_string(),
+                            collector_contract: "collector".to_string(),
+                            distributor_contract: "distributor".to_string(),
+                            stable_denom: "uusd".to_string(),
+                            max_borrow_factor: Decimal256::one(),
+                            blocks_per_year: BLOCKS_PER_YEAR,
+                        })))
+                    }
+                    QueryMsg::DepositRateBounds {} => SystemResult::Ok(ContractResult::from(
+                        to_binary(&DepositRateBoundsResponse {
+                            deposit_rate_floor: self.deposit_rate_bounds.0,
+                            deposit_rate_ceiling: self.deposit_rate_bounds.1,
+                        }),
+                    )),
+                    QueryMsg::State { block_height: _ } => {
+                        SystemResult::Ok(ContractResult::from(to_binary(&StateResponse {
+                            total_liabilities: Decimal256::zero(),
+                            total_reserves: Decimal256::zero(),
+                            last_interest_updated: 0u64,
+                            last_reward_updated: 0u64,
+                            global_interest_index: Decimal256::one(),
+                            global_reward_index: Decimal256::zero(),
+                            anc_emission_rate: self.anc_emission_rate,
+                            prev_aterra_supply: Uint256::zero(),
+                            prev_exchange_rate: Decimal256::one(),
+                        })))
+                    }
+                    // the mocked model keeps the current rate
+                    QueryMsg::AncEmissionRate {
+                        deposit_rate: _,
+                        target_deposit_rate: _,
+                        threshold_deposit_rate: _,
+                        current_emission_rate,
+                    } => SystemResult::Ok(ContractResult::from(to_binary(
+                        &AncEmissionRateResponse {
+                            emission_rate: current_emission_rate,
+                            unclamped_emission_rate: current_emission_rate,
+                        },
+                    ))),
+                    QueryMsg::Borrower { address } => {
+                        let (balance, spendable) = self
+                            .custody_borrower_querier
+                            .borrowers
+                            .get(contract_addr)
+                            .and_then(|borrowers| borrowers.get(&address))
+                            .copied()
+                            .unwrap_or_default();
+                        SystemResult::Ok(ContractResult::from(to_binary(&BorrowerResponse {
+                            borrower: address,
+                            balance,
+                            spendable,
+                        })))
+                    }
+                    QueryMsg::BorrowLimit {
+                        borrower,
+                        block_time: _,
+                    } => match self.borrow_limit_querier.borrow_limit.get(&borrower) {
+                        Some(v) => SystemResult::Ok(ContractResult::from(to_binary(
+                            &BorrowLimitResponse {
+                                borrower,
+                                borrow_limit: *v,
+                                collaterals: vec![],
+                            },
+                        ))),
+                        None => SystemResult::Err(SystemError::InvalidRequest {
+                            error: "No borrow limit exists".to_string(),
+                            request: msg.as_slice().into(),
+                        }),
+                    },
+                    QueryMsg::Whitelist {
+                        collateral_token,
+                        start_after,
+                        limit,
+                        order_by,
+                    } => {
+                        let mut elems: Vec<WhitelistResponseElem> = self
+                            .whitelist
+                            .iter()
+                            .filter(|elem| match &collateral_token {
+                                Some(token) => &elem.collateral_token == token,
+                                None => true,
+                            })
+                            .cloned()
+                            .collect();
+                        elems.sort_by(|a, b| a.collateral_token.cmp(&b.collateral_token));
+                        if order_by == Some(OrderBy::Descending) {
+                            elems.reverse();
+                        }
+
+                        let elems = elems
+                            .into_iter()
+                            .filter(|elem| match (&start_after, order_by) {
+                                (Some(start), Some(OrderBy::Descending)) => {
+                                    &elem.collateral_token < start
+                                }
+                                (Some(start), _) => &elem.collateral_token > start,
+                                (None, _) => true,
+                            })
+                            .take(clamp_limit(limit, DEFAULT_LIMIT, MAX_LIMIT))
+                            .collect();
+                        SystemResult::Ok(ContractResult::from(to_binary(&WhitelistResponse {
+                            elems,
+                        })))
+                    }
+                    QueryMsg::Balance { address } => {
+                        let balance = self
+                            .token_querier
+                            .balances
+                            .get(contract_addr)
+                            .and_then(|balances| balances.get(&address))
+                            .copied()
+                            .unwrap_or_default();
+                        SystemResult::Ok(ContractResult::from(to_binary(&BalanceResponse {
+                            balance,
+                        })))
+                    }
+                    QueryMsg::TokenInfo {} => {
+                        let total_supply = self
+                            .token_querier
+                            .balances
+                            .get(contract_addr)
+                            .map(|balances| {
+                                balances
+                                    .values()
+                                    .fold(Uint128::zero(), |acc, balance| acc + *balance)
+                            })
+                            .unwrap_or_default();
+                        SystemResult::Ok(ContractResult::from(to_binary(&TokenInfoResponse {
+                            name: "mAPPL".to_string(),
+                            symbol: "mAPPL".to_string(),
+                            decimals: 6,
+                            total_supply,
+                        })))
+                    }
+                }
+            }
+            _ => self.base.handle_query(request),
+        }
+    }
+}
+
+impl WasmMockQuerier {
+    pub fn new(base: MockQuerier<TerraQueryWrapper>) -> Self {
+        WasmMockQuerier {
+            base,
+            tax_querier: TaxQuerier::default(),
+            epoch_state_querier: EpochStateQuerier::default(),
+            oracle_price_querier: OraclePriceQuerier::default(),
+            loan_amount_querier: LoanAmountQuerier::default(),
+            liquidation_percent_querier: LiquidationPercentQuerier::default(),
+            deposit_rate_bounds: (Decimal256::zero(), Decimal256::one()),
+            anc_emission_rate: Decimal256::from_uint256(100u64),
+            custody_borrower_querier: CustodyBorrowerQuerier::default(),
+            borrow_limit_querier: BorrowLimitQuerier::default(),
+            whitelist: vec![],
+            token_querier: TokenQuerier::default(),
+        }
+    }
+
+    // configure the tax mock querier
+    pub fn with_tax(&mut self, rate: Decimal, caps: &[(&String, &Uint128)]) {
+        self.tax_querier = TaxQuerier::new(rate, caps);
+    }
+
+    // set a new balance for the given address and return the old balance
+    pub fn update_balance<U: Into<String>>(
+        &mut self,
+        addr: U,
+        balance: Vec<Coin>,
+    ) -> Option<Vec<Coin>> {
+        self.base.update_balance(addr, balance)
+    }
+
+    pub fn with_epoch_state(&mut self, epoch_state: &[(&String, &(Uint256, Decimal256))]) {
+        self.epoch_state_querier = EpochStateQuerier::new(epoch_state);
+    }
+
+    pub fn with_oracle_price(&mut self, oracle_price: &[(&(String, String), &PriceInfo)]) {
+        self.oracle_price_querier = OraclePriceQuerier {
+            deprecated_assets: self.oracle_price_querier.deprecated_assets.clone(),
+            oracle_contract: self.oracle_price_querier.oracle_contract.clone(),
+            ..OraclePriceQuerier::new(oracle_price)
+        };
+    }
+
+    // only this contract answers price queries
+    pub fn with_oracle_contract(&mut self, oracle_contract: &str) {
+        self.oracle_price_querier.oracle_contract = Some(oracle_contract.to_string());
+    }
+
+    // prices of these assets are refused like the oracle does once deprecated
+    pub fn with_deprecated_assets(&mut self, assets: &[&str]) {
+        self.oracle_price_querier.deprecated_assets =
+            assets.iter().map(|asset| asset.to_string()).collect();
+    }
+
+    pub fn with_loan_amount(&mut self, loan_amount: &[(&String, &Uint256)]) {
+        self.loan_amount_querier = LoanAmountQuerier::new(loan_amount);
+    }
+
+    pub fn with_liquidation_percent(&mut self, liquidation_percent: &[(&String, &Decimal256)]) {
+        self.liquidation_percent_querier = LiquidationPercentQuerier::new(liquidation_percent);
+    }
+
+    pub fn with_deposit_rate_bounds(&mut self, floor: Decimal256, ceiling: Decimal256) {
+        self.deposit_rate_bounds = (floor, ceiling);
+    }
+
+    // configure the (balance, spendable) of borrowers in custody contracts
+    pub fn with_custody_borrowers(&mut self, borrowers: &[(&String, &[CustodyBorrower])]) {
+        self.custody_borrower_querier = CustodyBorrowerQuerier::new(borrowers);
+    }
+
+    pub fn with_borrow_limit(&mut self, borrow_limit: &[(&String, &Uint256)]) {
+        self.borrow_limit_querier = BorrowLimitQuerier::new(borrow_limit);
+    }
+
+    pub fn with_whitelist(&mut self, whitelist: &[WhitelistResponseElem]) {
+        self.whitelist = whitelist.to_vec();
+    }
+
+    // configure the cw20 token balances, the supply is their sum
+    pub fn with_token_balances(&mut self, balances: &[(&String, &[(&String, &Uint128)])]) {
+        self.token_querier = TokenQuerier::new(balances);
+    }
+}
@@ -0,0 +1,162 @@
+use crate::mock_dependencies;
+
+use cosmwasm_bignumber::{Decimal256, Uint256};
+use cosmwasm_std::{to_binary, Addr, QueryRequest, Uint128, WasmQuery};
+use moneymarket::custody::{BorrowerResponse, QueryMsg as CustodyQueryMsg};
+use moneymarket::overseer::{
+    QueryMsg as OverseerQueryMsg, WhitelistResponse, WhitelistResponseElem,
+};
+use moneymarket::pagination::OrderBy;
+use moneymarket::querier::{
+    query_borrow_limit, query_loan_amount, query_token_balance, query_token_supply,
+};
+
+fn whitelist_elem(collateral_token: &str) -> WhitelistResponseElem {
+    WhitelistResponseElem {
+        name: collateral_token.to_string(),
+        symbol: collateral_token.to_string(),
+        max_ltv: Decimal256::percent(60),
+        custody_contract: format!("custody_{}", collateral_token),
+        collateral_token: collateral_token.to_string(),
+        decimals: 6,
+        paused: false,
+    }
+}
+
+#[test]
+fn market_and_overseer_queries() {
+    let mut deps = mock_dependencies(&[]);
+    deps.querier
+        .with_loan_amount(&[(&"addr0000".to_string(), &Uint256::from(100u64))]);
+    deps.querier
+        .with_borrow_limit(&[(&"addr0000".to_string(), &Uint256::from(200u64))]);
+
+    assert_eq!(
+        query_loan_amount(
+            deps.as_ref(),
+            Addr::unchecked("market"),
+            Addr::unchecked("addr0000"),
+            1u64
+        )
+        .unwrap(),
+        Uint256::from(100u64)
+    );
+    assert_eq!(
+        query_borrow_limit(
+            deps.as_ref(),
+            Addr::unchecked("overseer"),
+            Addr::unchecked("addr0000"),
+            None
+        )
+        .unwrap()
+        .borrow_limit,
+        Uint256::from(200u64)
+    );
+
+    // unknown borrowers are errors, like the contracts
+    assert!(query_borrow_limit(
+        deps.as_ref(),
+        Addr::unchecked("overseer"),
+        Addr::unchecked("addr0001"),
+        None
+    )
+    .is_err());
+}
+
+#[test]
+fn overseer_whitelist_pagination() {
+    let mut deps = mock_dependencies(&[]);
+    deps.querier.with_whitelist(&[
+        whitelist_elem("bluna"),
+        whitelist_elem("batom"),
+        whitelist_elem("beth"),
+    ]);
+
+    let query_whitelist = |collateral_token: Option<&str>,
+                           start_after: Option<&str>,
+                           order_by: Option<OrderBy>|
+     -> Vec<String> {
+        let res: WhitelistResponse = deps
+            .as_ref()
+            .querier
+            .query(&QueryRequest::Wasm(WasmQuery::Smart {
+                contract_addr: "overseer".to_string(),
+                msg: to_binary(&OverseerQueryMsg::Whitelist {
+                    collateral_token: collateral_token.map(|v| v.to_string()),
+                    start_after: start_after.map(|v| v.to_string()),
+                    limit: None,
+                    order_by,
+                })
+                .unwrap(),
+            }))
+            .unwrap();
+        res.elems.into_iter().map(|e| e.collateral_token).collect()
+    };
+
+    assert_eq!(
+        query_whitelist(None, None, None),
+        vec!["batom", "beth", "bluna"]
+    );
+    assert_eq!(query_whitelist(Some("beth"), None, None), vec!["beth"]);
+    assert_eq!(
+        query_whitelist(None, Some("batom"), None),
+        vec!["beth", "bluna"]
+    );
+    assert_eq!(
+        query_whitelist(None, Some("bluna"), Some(OrderBy::Descending)),
+        vec!["beth", "batom"]
+    );
+}
+
+#[test]
+fn custody_and_token_queries() {
+    let mut deps = mock_dependencies(&[]);
+    deps.querier.with_custody_borrowers(&[(
+        &"custody_bluna".to_string(),
+        &[(
+            &"addr0000".to_string(),
+            &(Uint256::from(100u64), Uint256::from(40u64)),
+        )],
+    )]);
+    deps.querier.with_token_balances(&[(
+        &"bluna".to_string(),
+        &[
+            (&"custody_bluna".to_string(), &Uint128::from(100u128)),
+            (&"addr0000".to_string(), &Uint128::from(50u128)),
+        ],
+    )]);
+
+    let res: BorrowerResponse = deps
+        .as_ref()
+        .querier
+        .query(&QueryRequest::Wasm(WasmQuery::Smart {
+            contract_addr: "custody_bluna".to_string(),
+            msg: to_binary(&CustodyQueryMsg::Borrower {
+                address: "addr0000".to_string(),
+            })
+            .unwrap(),
+        }))
+        .unwrap();
+    assert_eq!(
+        res,
+        BorrowerResponse {
+            borrower: "addr0000".to_string(),
+            balance: Uint256::from(100u64),
+            spendable: Uint256::from(40u64),
+        }
+    );
+
+    assert_eq!(
+        query_token_balance(
+            deps.as_ref(),
+            Addr::unchecked("bluna"),
+            Addr::unchecked("custody_bluna")
+        )
+        .unwrap(),
+        Uint256::from(100u64)
+    );
+    assert_eq!(
+        query_token_supply(deps.as_ref(), Addr::unchecked("bluna")).unwrap(),
+        Uint256::from(150u64)
+    );
+}
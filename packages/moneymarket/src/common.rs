@@ -1,4 +1,5 @@
-use cosmwasm_std::{Addr, Api, StdResult};
+use cosmwasm_bignumber::Decimal256;
+use cosmwasm_std::{Addr, Api, StdError, StdResult};
 
 pub fn optional_addr_validate(api: &dyn Api, addr: Option<String>) -> StdResult<Option<Addr>> {
     let addr = if let Some(addr) = addr {
@@ -9,3 +10,39 @@ pub fn optional_addr_validate(api: &dyn Api, addr: Option<String>) -> StdResult<
 
     Ok(addr)
 }
+
+/// Errors unless `value` is within [0, 1]
+pub fn assert_fraction(name: &str, value: Decimal256) -> StdResult<()> {
+    if value > Decimal256::one() {
+        return Err(StdError::generic_err(format!(
+            "{} must be within [0, 1], got {}",
+            name, value
+        )));
+    }
+
+    Ok(())
+}
+
+/// Errors unless `value` is within (0, 1]
+pub fn assert_positive_fraction(name: &str, value: Decimal256) -> StdResult<()> {
+    if value.is_zero() || value > Decimal256::one() {
+        return Err(StdError::generic_err(format!(
+            "{} must be within (0, 1], got {}",
+            name, value
+        )));
+    }
+
+    Ok(())
+}
+
+/// Errors when `value` exceeds `max`
+pub fn assert_max(name: &str, value: Decimal256, max: Decimal256) -> StdResult<()> {
+    if value > max {
+        return Err(StdError::generic_err(format!(
+            "{} must not exceed {}, got {}",
+            name, max, value
+        )));
+    }
+
+    Ok(())
+}
@@ -54,6 +54,12 @@ pub enum ExecuteMsg {
         borrower: String,
         amount: Uint256,
     },
+    /// Send all the held collateral tokens along with the borrower
+    /// balances to the custody replacing this one, which is left empty
+    MigrateBalances { new_custody: String },
+    /// Accept the collateral tokens and borrower balances
+    /// the replaced `old_custody` sends next
+    AcceptMigration { old_custody: String },
 
     ////////////////////
     /// User operations
@@ -61,8 +67,22 @@ pub enum ExecuteMsg {
 
     /// Withdraw spendable collateral token.
     /// If the amount is not given,
-    /// return all spendable collateral
-    WithdrawCollateral { amount: Option<Uint256> },
+    /// return all spendable collateral.
+    /// The overseer may withdraw on behalf of a `borrower`,
+    /// the collateral is always sent to the borrower
+    WithdrawCollateral {
+        borrower: Option<String>,
+        amount: Option<Uint256>,
+    },
+
+    ////////////////////
+    /// Owner operations
+    ////////////////////
+
+    /// Credit collateral tokens held on top of the borrower balances
+    /// to the surplus, or cover a shortfall of at most `max_adjustment`
+    /// from the surplus. Borrower balances are never touched
+    ReconcileInvariant { max_adjustment: Uint256 },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -70,6 +90,9 @@ pub enum ExecuteMsg {
 pub enum Cw20HookMsg {
     /// Deposit collateral token
     DepositCollateral {},
+    /// Credit the borrower balances of the replaced custody,
+    /// sent along with its collateral tokens
+    MigrateBorrowers { borrowers: Vec<BorrowerResponse> },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -83,8 +106,13 @@ pub enum QueryMsg {
         start_after: Option<String>,
         limit: Option<u32>,
     },
+    /// Collateral token balance against the recorded balances
+    Invariant {},
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MigrateMsg {}
+
 // We define a custom struct for each query response
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct ConfigResponse {
@@ -112,6 +140,23 @@ pub struct BorrowersResponse {
     pub borrowers: Vec<BorrowerResponse>,
 }
 
+// We define a custom struct for each query response
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct InvariantResponse {
+    /// Collateral tokens held by the custody
+    pub token_balance: Uint256,
+    /// Sum of the borrower balances
+    pub total_balance: Uint256,
+    /// Reconciled tokens not owned by any borrower
+    pub surplus: Uint256,
+    /// The signed difference token_balance - (total_balance + surplus)
+    /// is split into `excess` and `shortfall`, at most one is non-zero.
+    /// Tokens held beyond the recorded balances
+    pub excess: Uint256,
+    /// Recorded balances not backed by held tokens
+    pub shortfall: Uint256,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct BAssetInfo {
     pub name: String,
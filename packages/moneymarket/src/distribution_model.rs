@@ -6,21 +6,49 @@ use cosmwasm_bignumber::Decimal256;
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct InstantiateMsg {
     pub owner: String,
+    /// May update the rate parameters, but not the owner or itself
+    pub risk_admin: Option<String>,
     pub emission_cap: Decimal256,
     pub emission_floor: Decimal256,
     pub increment_multiplier: Decimal256,
     pub decrement_multiplier: Decimal256,
+    /// Max relative change of the emission rate per epoch
+    pub max_rate_change_per_epoch: Decimal256,
+    /// Bounds of the per-block deposit rate the overseer subsidizes to
+    pub deposit_rate_floor: Decimal256,
+    pub deposit_rate_ceiling: Decimal256,
+    /// Only this contract may record emission rate updates
+    pub overseer_contract: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
+#[allow(clippy::large_enum_variant)]
 pub enum ExecuteMsg {
     UpdateConfig {
         owner: Option<String>,
+        risk_admin: Option<String>,
         emission_cap: Option<Decimal256>,
         emission_floor: Option<Decimal256>,
         increment_multiplier: Option<Decimal256>,
         decrement_multiplier: Option<Decimal256>,
+        max_rate_change_per_epoch: Option<Decimal256>,
+        deposit_rate_floor: Option<Decimal256>,
+        deposit_rate_ceiling: Option<Decimal256>,
+        overseer_contract: Option<String>,
+    },
+
+    ////////////////////
+    /// Overseer operations
+    ////////////////////
+
+    /// Records the emission rate accepted at the epoch
+    /// with the inputs it was computed from
+    UpdateEmissionRate {
+        emission_rate: Decimal256,
+        deposit_rate: Decimal256,
+        target_deposit_rate: Decimal256,
+        threshold_deposit_rate: Decimal256,
     },
 }
 
@@ -34,20 +62,64 @@ pub enum QueryMsg {
         threshold_deposit_rate: Decimal256,
         current_emission_rate: Decimal256,
     },
+    DepositRateBounds {},
+    /// Last recorded emission rate and its height
+    State {},
+    EmissionHistory {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
 }
 
 // We define a custom struct for each query response
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct ConfigResponse {
     pub owner: String,
+    pub risk_admin: Option<String>,
     pub emission_cap: Decimal256,
     pub emission_floor: Decimal256,
     pub increment_multiplier: Decimal256,
     pub decrement_multiplier: Decimal256,
+    pub max_rate_change_per_epoch: Decimal256,
+    pub deposit_rate_floor: Decimal256,
+    pub deposit_rate_ceiling: Decimal256,
+    pub overseer_contract: Option<String>,
+}
+
+// We define a custom struct for each query response
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DepositRateBoundsResponse {
+    pub deposit_rate_floor: Decimal256,
+    pub deposit_rate_ceiling: Decimal256,
 }
 
 // We define a custom struct for each query response
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct AncEmissionRateResponse {
     pub emission_rate: Decimal256,
+    /// Emission rate before the per-epoch change limit
+    pub unclamped_emission_rate: Decimal256,
+}
+
+// We define a custom struct for each query response
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StateResponse {
+    pub emission_rate: Decimal256,
+    pub last_updated: u64,
+}
+
+// We define a custom struct for each query response
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct EmissionHistoryElem {
+    pub height: u64,
+    pub emission_rate: Decimal256,
+    pub deposit_rate: Decimal256,
+    pub target_deposit_rate: Decimal256,
+    pub threshold_deposit_rate: Decimal256,
+}
+
+// We define a custom struct for each query response
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct EmissionHistoryResponse {
+    pub history: Vec<EmissionHistoryElem>,
 }
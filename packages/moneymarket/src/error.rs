@@ -0,0 +1,32 @@
+use cosmwasm_std::StdError;
+use thiserror::Error;
+
+/// Business-rule failures shared by the money market contracts. Contracts
+/// wrap it in their own `ContractError`, query handlers convert it into
+/// a `StdError` carrying the same message.
+#[derive(Error, Debug, PartialEq)]
+pub enum MoneyMarketError {
+    #[error("{0}")]
+    Std(#[from] StdError),
+
+    #[error("Unlock amount cannot exceed locked amount")]
+    UnlockExceedsLocked {},
+
+    #[error("Unlock amount too high; Loan liability becomes greater than borrow limit: {0}")]
+    UnlockExceedsLtv(u128),
+
+    #[error("An epoch has not passed yet; blocks remaining: {remaining}")]
+    EpochNotElapsed { remaining: u64 },
+
+    #[error("Price is too old; {asset} was last updated {age} seconds ago")]
+    PriceStale { asset: String, age: u64 },
+}
+
+impl From<MoneyMarketError> for StdError {
+    fn from(err: MoneyMarketError) -> Self {
+        match err {
+            MoneyMarketError::Std(err) => err,
+            err => StdError::generic_err(err.to_string()),
+        }
+    }
+}
@@ -2,45 +2,198 @@ use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use cosmwasm_bignumber::{Decimal256, Uint256};
+use std::fmt;
+
+/// Model type of the two-slope model kinked at optimal_utilization
+pub const MODEL_TYPE_KINKED_V1: &str = "kinked_v1";
+/// Model type of the model in the fixed rate mode
+pub const MODEL_TYPE_FIXED_V1: &str = "fixed_v1";
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct InstantiateMsg {
     pub owner: String,
+    /// May update the rate parameters, but not the owner or itself
+    pub risk_admin: Option<String>,
     pub base_rate: Decimal256,
-    pub interest_multiplier: Decimal256,
+    /// Utilization above which slope_2 applies, in (0, 1)
+    pub optimal_utilization: Decimal256,
+    /// Rate increase per unit of utilization up to optimal_utilization
+    pub slope_1: Decimal256,
+    /// Rate increase per unit of utilization above optimal_utilization
+    pub slope_2: Decimal256,
+    /// Yearly rate the base rate cannot exceed over blocks_per_year
+    pub max_apr: Decimal256,
+    pub blocks_per_year: u64,
+    /// Per-block rate the returned borrow rate is clamped to
+    pub max_borrow_rate: Decimal256,
+    /// Market allowed to record its utilization
+    pub market_contract: Option<String>,
+    /// Defaults to the spot utilization
+    pub utilization_mode: Option<UtilizationMode>,
+    /// Weight of a recorded utilization in the EMA, in (0, 1]
+    pub ema_smoothing_factor: Decimal256,
+    /// Defaults to the dynamic rate
+    pub rate_mode: Option<RateMode>,
+}
+
+/// Whether the borrow rate follows the utilization curve
+/// or is a fixed per-block rate
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RateMode {
+    Dynamic,
+    Fixed { rate: Decimal256 },
 }
 
+impl fmt::Display for RateMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RateMode::Dynamic => write!(f, "dynamic"),
+            RateMode::Fixed { rate } => write!(f, "fixed({})", rate),
+        }
+    }
+}
+
+/// Utilization the borrow rate is computed from
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
+pub enum UtilizationMode {
+    Spot,
+    MaxSpotEma,
+    Ema,
+}
+
+impl fmt::Display for UtilizationMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UtilizationMode::Spot => write!(f, "spot"),
+            UtilizationMode::MaxSpotEma => write!(f, "max_spot_ema"),
+            UtilizationMode::Ema => write!(f, "ema"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+#[allow(clippy::large_enum_variant)]
 pub enum ExecuteMsg {
     UpdateConfig {
         owner: Option<String>,
+        risk_admin: Option<String>,
         base_rate: Option<Decimal256>,
-        interest_multiplier: Option<Decimal256>,
+        optimal_utilization: Option<Decimal256>,
+        slope_1: Option<Decimal256>,
+        slope_2: Option<Decimal256>,
+        max_apr: Option<Decimal256>,
+        blocks_per_year: Option<u64>,
+        max_borrow_rate: Option<Decimal256>,
+        market_contract: Option<String>,
+        utilization_mode: Option<UtilizationMode>,
+        ema_smoothing_factor: Option<Decimal256>,
+        rate_mode: Option<RateMode>,
     },
+
+    ////////////////////
+    /// Market operations
+    ////////////////////
+
+    /// Folds the market utilization into the EMA
+    RecordUtilization { utilization: Decimal256 },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryMsg {
     Config {},
+    /// Model type, version and parameters, to check
+    /// the compatibility of a model
+    ModelInfo {},
     BorrowRate {
         market_balance: Uint256,
         total_liabilities: Decimal256,
         total_reserves: Decimal256,
     },
+    /// Borrow rate under the stored params and under the given
+    /// overrides, without changing the config
+    SimulateBorrowRate {
+        market_balance: Uint256,
+        total_liabilities: Decimal256,
+        total_reserves: Decimal256,
+        params: Option<ConfigOverride>,
+    },
+    /// Per-block and annualized borrow and deposit rates;
+    /// blocks_per_year defaults to the configured one
+    ProjectedRates {
+        market_balance: Uint256,
+        total_liabilities: Decimal256,
+        total_reserves: Decimal256,
+        reserve_factor: Decimal256,
+        blocks_per_year: Option<u64>,
+    },
+}
+
+/// Rate params replacing the stored ones for a simulation
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct ConfigOverride {
+    pub base_rate: Option<Decimal256>,
+    pub optimal_utilization: Option<Decimal256>,
+    pub slope_1: Option<Decimal256>,
+    pub slope_2: Option<Decimal256>,
+    pub max_borrow_rate: Option<Decimal256>,
 }
 
 // We define a custom struct for each query response
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct ConfigResponse {
     pub owner: String,
+    pub risk_admin: Option<String>,
     pub base_rate: Decimal256,
-    pub interest_multiplier: Decimal256,
+    pub optimal_utilization: Decimal256,
+    pub slope_1: Decimal256,
+    pub slope_2: Decimal256,
+    pub max_apr: Decimal256,
+    pub blocks_per_year: u64,
+    pub max_borrow_rate: Decimal256,
+    pub market_contract: Option<String>,
+    pub utilization_mode: UtilizationMode,
+    pub ema_smoothing_factor: Decimal256,
+    /// None until the market records its utilization
+    pub ema_utilization: Option<Decimal256>,
+    pub rate_mode: RateMode,
+}
+
+// We define a custom struct for each query response
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ModelInfoResponse {
+    pub model_type: String,
+    pub version: String,
+    pub params: ConfigResponse,
 }
 
 // We define a custom struct for each query response
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct BorrowRateResponse {
     pub rate: Decimal256,
+    /// Whether the rate of the curve exceeded max_borrow_rate
+    pub clamped: bool,
+}
+
+// We define a custom struct for each query response
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SimulateBorrowRateResponse {
+    pub current_rate: Decimal256,
+    pub simulated_rate: Decimal256,
+}
+
+// We define a custom struct for each query response
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ProjectedRatesResponse {
+    pub borrow_rate: Decimal256,
+    /// borrow_rate * utilization * (1 - reserve_factor)
+    pub deposit_rate: Decimal256,
+    pub borrow_apr: Decimal256,
+    pub deposit_apr: Decimal256,
+    /// Rates compounded every block
+    pub borrow_apy: Decimal256,
+    pub deposit_apy: Decimal256,
 }
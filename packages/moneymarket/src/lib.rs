@@ -1,12 +1,16 @@
 pub mod common;
 pub mod custody;
 pub mod distribution_model;
+pub mod error;
 pub mod interest_model;
 pub mod liquidation;
 pub mod market;
+pub mod math;
 pub mod oracle;
 pub mod overseer;
+pub mod pagination;
 pub mod querier;
+pub mod rates;
 pub mod tokens;
 
 #[cfg(test)]
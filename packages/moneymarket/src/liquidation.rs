@@ -6,6 +6,15 @@ use cw20::Cw20ReceiveMsg;
 
 use crate::tokens::TokensHuman;
 
+// Attributes logged for each bid pool fill of a bid execution,
+// in this order, after the summary of the execution
+pub const FILL_PREMIUM_SLOT: &str = "fill_premium_slot";
+pub const FILL_PREMIUM_RATE: &str = "fill_premium_rate";
+pub const FILL_COLLATERAL_AMOUNT: &str = "fill_collateral_amount";
+pub const FILL_REPAY_AMOUNT: &str = "fill_repay_amount";
+pub const FILL_PREMIUM_AMOUNT: &str = "fill_premium_amount";
+pub const FILL_LIQUIDATION_FEE: &str = "fill_liquidation_fee";
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct InstantiateMsg {
     pub owner: String,
@@ -26,10 +35,34 @@ pub struct InstantiateMsg {
     pub liquidation_threshold: Uint256,
     /// Valid oracle price timeframe
     pub price_timeframe: u64,
+    /// Seconds a bidder has to wait between requesting
+    /// a bid retraction and receiving the funds
+    pub bid_retract_wait: u64,
+    /// Protocol fee taken from the stable paid by bidders
+    pub liquidation_fee: Decimal256,
+    /// Receives the protocol liquidation fee
+    pub fee_address: String,
+    /// Seconds before a new bid can be activated
+    /// and matched against liquidations
+    pub bid_activation_delay: u64,
+    /// Seconds after which a bid untouched by its bidder can be swept
+    pub inactive_bid_ttl: Option<u64>,
+    /// Smallest amount of stable a bid can be submitted with
+    pub min_bid_amount: Uint256,
+    /// Bids and pools with less stable left are closed
+    pub dust_threshold: Uint256,
+    /// Overseer whose whitelist SyncWhitelist follows
+    pub overseer_contract: Option<String>,
+    /// Share of the repayment paid to the liquidator, zero when None
+    pub caller_fee: Option<Decimal256>,
+    /// Market repaid through RepayStableFromLiquidation when it is the
+    /// repay address of a liquidation
+    pub market_contract: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
+#[allow(clippy::large_enum_variant)]
 pub enum ExecuteMsg {
     Receive(Cw20ReceiveMsg),
     UpdateConfig {
@@ -41,15 +74,78 @@ pub enum ExecuteMsg {
         max_premium_rate: Option<Decimal256>,
         liquidation_threshold: Option<Uint256>,
         price_timeframe: Option<u64>,
+        bid_retract_wait: Option<u64>,
+        liquidation_fee: Option<Decimal256>,
+        fee_address: Option<String>,
+        bid_activation_delay: Option<u64>,
+        /// Zero disables the sweeping of inactive bids
+        inactive_bid_ttl: Option<u64>,
+        min_bid_amount: Option<Uint256>,
+        dust_threshold: Option<Uint256>,
+        overseer_contract: Option<String>,
+        caller_fee: Option<Decimal256>,
+        market_contract: Option<String>,
+    },
+    /// Owner operation to accept bids for a new collateral
+    WhitelistCollateral {
+        collateral_token: String,
+        /// Highest premium slot bids can be submitted to
+        max_slot: u8,
+        /// Premium rate added by each slot
+        premium_rate_per_slot: Decimal256,
     },
     SubmitBid {
         collateral_token: String,
-        premium_rate: Decimal256,
+        premium_slot: u8,
     },
     RetractBid {
-        collateral_token: String,
+        bid_idx: u64,
         amount: Option<Uint256>,
     },
+    /// Activates the sender's pending bids once their delay is over,
+    /// all of the ready ones for the collateral when bid_idxs is None
+    ActivateBids {
+        collateral_token: String,
+        bid_idxs: Option<Vec<u64>>,
+    },
+    /// Sends the sender the collateral bought by their bids,
+    /// all of their bids for the collateral when bids_idx is None
+    ClaimLiquidations {
+        collateral_token: String,
+        bids_idx: Option<Vec<u64>>,
+    },
+    /// Removes dust bids and bids left inactive beyond inactive_bid_ttl,
+    /// sending their funds back to the bidders. Anyone can sweep; each call
+    /// checks up to limit bids, continuing from where the last call stopped.
+    SweepBids {
+        collateral_token: String,
+        limit: Option<u32>,
+    },
+    /// Sends the stable accumulated for the borrower to the repay address,
+    /// the sender when None. The collaterals must match the (token, amount)
+    /// pairs received with AccumulateBid in this transaction.
+    SettleLiquidation {
+        borrower: String,
+        repay_address: Option<String>,
+        collaterals: TokensHuman,
+    },
+    /// Moves the remaining amount of a bid to another premium slot,
+    /// where it waits bid_activation_delay like a new bid
+    UpdateBidPremium {
+        bid_idx: u64,
+        premium_slot: u8,
+    },
+    /// Follows the overseer whitelist for the collaterals, or for every
+    /// collateral when None: collaterals new to this contract are added
+    /// with the default premium slots, and collaterals the overseer no
+    /// longer lists stop accepting bids until listed again
+    SyncWhitelist {
+        collateral_tokens: Option<Vec<String>>,
+    },
+    /// Owner operation that pauses new bids while the escrowed stable
+    /// does not match the contract balance, and resumes them once it does.
+    /// Retractions and executions keep working while paused.
+    CheckEscrowHealth {},
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -57,6 +153,17 @@ pub enum ExecuteMsg {
 pub enum Cw20HookMsg {
     ExecuteBid {
         liquidator: String,
+        /// owner of the liquidated collateral, receives the unsold part
+        borrower: String,
+        fee_address: Option<String>,
+        repay_address: Option<String>,
+    },
+    /// Executes the bids like ExecuteBid, but holds the stable paid until
+    /// SettleLiquidation, so that the collaterals liquidated for a borrower
+    /// in one transaction are repaid with a single transfer
+    AccumulateBid {
+        liquidator: String,
+        borrower: String,
         fee_address: Option<String>,
         repay_address: Option<String>,
     },
@@ -73,21 +180,49 @@ pub enum QueryMsg {
         collateral_prices: Vec<Decimal256>,
     },
     Bid {
-        collateral_token: String,
-        bidder: String,
+        bid_idx: u64,
     },
+    /// Bids of the user, for every collateral when collateral_token is None
     BidsByUser {
+        collateral_token: Option<String>,
         bidder: String,
-        start_after: Option<String>,
+        start_after: Option<u64>,
         limit: Option<u32>,
     },
     BidsByCollateral {
         collateral_token: String,
-        start_after: Option<String>,
+        start_after: Option<u64>,
         limit: Option<u32>,
     },
+    BidPool {
+        collateral_token: String,
+        premium_slot: u8,
+    },
+    BidPoolsByCollateral {
+        collateral_token: String,
+        start_after: Option<u8>,
+        limit: Option<u32>,
+    },
+    CollateralInfo {
+        collateral_token: String,
+    },
+    /// Outcome of executing the bids for the collateral amount
+    /// in the current state, without executing them
+    SimulateExecute {
+        collateral_token: String,
+        collateral_amount: Uint256,
+    },
+    /// Stable escrowed by the bids of the collateral, by premium slot
+    LiquidityDepth {
+        collateral_token: String,
+    },
+    /// Escrowed stable of every bid against the contract balance
+    EscrowHealth {},
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MigrateMsg {}
+
 // We define a custom struct for each query response
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct ConfigResponse {
@@ -99,6 +234,16 @@ pub struct ConfigResponse {
     pub max_premium_rate: Decimal256,
     pub liquidation_threshold: Uint256,
     pub price_timeframe: u64,
+    pub bid_retract_wait: u64,
+    pub liquidation_fee: Decimal256,
+    pub fee_address: String,
+    pub bid_activation_delay: u64,
+    pub inactive_bid_ttl: Option<u64>,
+    pub min_bid_amount: Uint256,
+    pub dust_threshold: Uint256,
+    pub overseer_contract: Option<String>,
+    pub caller_fee: Decimal256,
+    pub market_contract: Option<String>,
 }
 
 // We define a custom struct for each query response
@@ -110,10 +255,17 @@ pub struct LiquidationAmountResponse {
 // We define a custom struct for each query response
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct BidResponse {
+    pub idx: u64,
     pub collateral_token: String,
     pub bidder: String,
     pub amount: Uint256,
-    pub premium_rate: Decimal256,
+    pub premium_slot: u8,
+    /// Collateral bought by the bid that can be claimed
+    pub pending_liquidated_collateral: Uint256,
+    /// Set once a retraction was requested
+    pub wait_end: Option<u64>,
+    /// Set while the bid is pending activation
+    pub activation_time: Option<u64>,
 }
 
 // We define a custom struct for each query response
@@ -121,3 +273,84 @@ pub struct BidResponse {
 pub struct BidsResponse {
     pub bids: Vec<BidResponse>,
 }
+
+// We define a custom struct for each query response
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BidPoolResponse {
+    pub collateral_token: String,
+    pub premium_slot: u8,
+    pub premium_rate: Decimal256,
+    pub total_bid_amount: Uint256,
+}
+
+// We define a custom struct for each query response
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BidPoolsResponse {
+    pub bid_pools: Vec<BidPoolResponse>,
+}
+
+// We define a custom struct for each query response
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SimulateExecuteResponse {
+    /// Collateral sold to the bids
+    pub collateral_amount: Uint256,
+    /// Collateral the bids cannot absorb, returned to the borrower
+    pub returned_collateral_amount: Uint256,
+    pub repay_amount: Uint256,
+    pub bid_fee: Uint256,
+    pub caller_fee: Uint256,
+    pub liquidation_fee: Uint256,
+    /// Bid pools the collateral is sold to; their bids share each fill
+    pub filled_bid_pools: u32,
+}
+
+// We define a custom struct for each query response
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CollateralInfoResponse {
+    pub collateral_token: String,
+    pub max_slot: u8,
+    pub premium_rate_per_slot: Decimal256,
+    pub bids_enabled: bool,
+}
+
+// We define a custom struct for each query response
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LiquidityDepthResponse {
+    pub collateral_token: String,
+    /// Escrowed by the active and pending bids of the collateral
+    pub total_bid_amount: Uint256,
+    pub active_bids: u64,
+    pub pending_bids: u64,
+    /// Premium slots holding bids, lowest first
+    pub slots: Vec<SlotDepthResponse>,
+    /// Whether the stable balance of the contract covers the escrow
+    /// of every bid and the undistributed proceeds, see EscrowHealth
+    pub balance_covers_escrow: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SlotDepthResponse {
+    pub premium_slot: u8,
+    pub premium_rate: Decimal256,
+    pub active_bid_amount: Uint256,
+    pub pending_bid_amount: Uint256,
+    pub active_bids: u64,
+    pub pending_bids: u64,
+}
+
+// We define a custom struct for each query response
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct EscrowHealthResponse {
+    /// Stable of the pending and active bids
+    pub total_escrowed: Uint256,
+    /// Stable balance of the contract
+    pub balance: Uint256,
+    /// Liquidation proceeds and fees accumulated until SettleLiquidation
+    pub undistributed: Uint256,
+    /// balance >= total_escrowed + undistributed
+    pub consistent: bool,
+    /// Stable held beyond total_escrowed + undistributed, e.g. donations
+    pub surplus: Uint256,
+    /// New bids are rejected while set
+    pub bids_paused: bool,
+}
@@ -17,6 +17,9 @@ pub struct InstantiateMsg {
     pub anc_emission_rate: Decimal256,
     /// Maximum allowed borrow rate over deposited stable balance
     pub max_borrow_factor: Decimal256,
+    /// Blocks per year the rates are annualized over;
+    /// defaults to rates::BLOCKS_PER_YEAR
+    pub blocks_per_year: Option<u64>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -46,22 +49,28 @@ pub enum ExecuteMsg {
     UpdateConfig {
         owner_addr: Option<String>,
         max_borrow_factor: Option<Decimal256>,
+        blocks_per_year: Option<u64>,
         interest_model: Option<String>,
         distribution_model: Option<String>,
+        /// Skips the interest model type check
+        force: Option<bool>,
     },
 
     ////////////////////
     /// Overseer operations
     ////////////////////
-    /// Repay stable with liquidated collaterals
+    /// Repay stable with liquidated collaterals; sent by the
+    /// liquidation contract with the stable attached
     RepayStableFromLiquidation {
         borrower: String,
-        prev_balance: Uint256,
     },
 
     /// Execute epoch operations
     /// 1. send reserve to collector contract
     /// 2. update anc_emission_rate state
+    ///
+    /// Sent by the overseer UpdateEpochState, which runs after the
+    /// interest buffer transfers, so distributed_interest has arrived
     ExecuteEpochOperations {
         deposit_rate: Decimal256,
         target_deposit_rate: Decimal256,
@@ -117,6 +126,14 @@ pub enum QueryMsg {
         start_after: Option<String>,
         limit: Option<u32>,
     },
+    /// Splits the liability of the borrower into
+    /// the principal and the interest accrued on it
+    AccruedInterest {
+        borrower: String,
+        block_height: Option<u64>,
+    },
+    /// Current per-block and annualized borrow rates
+    Rates {},
 }
 
 // We define a custom struct for each query response
@@ -131,6 +148,7 @@ pub struct ConfigResponse {
     pub distributor_contract: String,
     pub stable_denom: String,
     pub max_borrow_factor: Decimal256,
+    pub blocks_per_year: u64,
 }
 
 // We define a custom struct for each query response
@@ -154,6 +172,15 @@ pub struct EpochStateResponse {
     pub aterra_supply: Uint256,
 }
 
+// We define a custom struct for each query response
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RatesResponse {
+    pub borrow_rate: Decimal256,
+    pub borrow_apr: Decimal256,
+    /// borrow_rate compounded every block
+    pub borrow_apy: Decimal256,
+}
+
 // We define a custom struct for each query response
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct BorrowerInfoResponse {
@@ -164,6 +191,15 @@ pub struct BorrowerInfoResponse {
     pub pending_rewards: Decimal256,
 }
 
+// We define a custom struct for each query response
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AccruedInterestResponse {
+    pub borrower: String,
+    pub principal: Uint256,
+    pub total_liability: Uint256,
+    pub accrued_interest: Uint256,
+}
+
 // We define a custom struct for each query response
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct BorrowerInfosResponse {
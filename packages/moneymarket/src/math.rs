@@ -0,0 +1,66 @@
+use cosmwasm_bignumber::{Decimal256, Uint256};
+use cosmwasm_std::{StdError, StdResult};
+
+/// Multiplies two decimals in 256 bits, accepting 128-bit `Decimal`s as well
+pub fn decimal_multiplication_256<A: Into<Decimal256>, B: Into<Decimal256>>(
+    a: A,
+    b: B,
+) -> Decimal256 {
+    a.into() * b.into()
+}
+
+/// Divides two decimals in 256 bits, accepting 128-bit `Decimal`s as well
+pub fn decimal_division_256<A: Into<Decimal256>, B: Into<Decimal256>>(
+    a: A,
+    b: B,
+) -> StdResult<Decimal256> {
+    let b: Decimal256 = b.into();
+    if b.is_zero() {
+        return Err(StdError::generic_err("Division by zero"));
+    }
+
+    Ok(a.into() / b)
+}
+
+/// Multiplies an amount by a decimal in 256 bits, accepting `Uint128` and
+/// `Decimal` as well; the result is floored
+pub fn uint_mul_decimal_256<A: Into<Uint256>, B: Into<Decimal256>>(a: A, b: B) -> Uint256 {
+    a.into() * b.into()
+}
+
+/// Value of `amount` at `price`, weighted by `ltv`. The collateral value is
+/// floored before the ltv is applied, as the borrow limit always did.
+pub fn value_of(amount: Uint256, price: Decimal256, ltv: Decimal256) -> Uint256 {
+    uint_mul_decimal_256(uint_mul_decimal_256(amount, price), ltv)
+}
+
+/// Keeps the scale within u64 and the scaled amount far from overflowing
+const MAX_DECIMALS_DIFF: u8 = 18;
+
+/// Decimals of the native stable denom (uusd)
+pub const STABLE_DECIMALS: u8 = 6;
+
+/// Value of `amount` of an asset with `collateral_decimals` at `price`,
+/// expressed in units of an asset with `base_decimals`. Rounds down.
+pub fn compute_value<A: Into<Uint256>>(
+    amount: A,
+    price: Decimal256,
+    collateral_decimals: u8,
+    base_decimals: u8,
+) -> StdResult<Uint256> {
+    let amount: Uint256 = amount.into();
+    let diff = collateral_decimals.abs_diff(base_decimals);
+    if diff > MAX_DECIMALS_DIFF {
+        return Err(StdError::generic_err(format!(
+            "Decimals cannot differ by more than {}: {} and {}",
+            MAX_DECIMALS_DIFF, collateral_decimals, base_decimals
+        )));
+    }
+
+    let scale = Uint256::from(10u64.pow(diff as u32));
+    if collateral_decimals > base_decimals {
+        Ok(uint_mul_decimal_256(amount, price) / scale)
+    } else {
+        Ok(uint_mul_decimal_256(amount * scale, price))
+    }
+}
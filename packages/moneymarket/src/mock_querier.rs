@@ -1,9 +1,11 @@
 use cosmwasm_bignumber::Decimal256;
 use cosmwasm_std::testing::{MockApi, MockQuerier, MockStorage, MOCK_CONTRACT_ADDR};
 use cosmwasm_std::{
-    from_binary, from_slice, to_binary, Coin, ContractResult, Decimal, OwnedDeps, Querier,
+    from_binary, from_slice, to_binary, Binary, Coin, ContractResult, Decimal, OwnedDeps, Querier,
     QuerierResult, QueryRequest, SystemError, SystemResult, Uint128, WasmQuery,
 };
+use cosmwasm_storage::to_length_prefixed;
+use cw20::{BalanceResponse, Cw20QueryMsg, TokenInfoResponse};
 use std::collections::HashMap;
 
 use crate::oracle::{PriceResponse, QueryMsg as OracleQueryMsg};
@@ -29,6 +31,49 @@ pub struct WasmMockQuerier {
     base: MockQuerier<TerraQueryWrapper>,
     tax_querier: TaxQuerier,
     oracle_price_querier: OraclePriceQuerier,
+    token_querier: TokenQuerier,
+}
+
+#[derive(Clone, Default)]
+pub struct TokenQuerier {
+    // this lets us iterate over all pairs that match the first string
+    balances: HashMap<String, HashMap<String, Uint128>>,
+}
+
+impl TokenQuerier {
+    pub fn new(balances: &[(&String, &[(&String, &Uint128)])]) -> Self {
+        TokenQuerier {
+            balances: balances_to_map(balances),
+        }
+    }
+
+    fn balances(&self, contract_addr: &str) -> HashMap<String, Uint128> {
+        self.balances
+            .get(contract_addr)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn total_supply(&self, contract_addr: &str) -> Uint128 {
+        self.balances(contract_addr)
+            .values()
+            .fold(Uint128::zero(), |acc, v| acc + *v)
+    }
+}
+
+pub(crate) fn balances_to_map(
+    balances: &[(&String, &[(&String, &Uint128)])],
+) -> HashMap<String, HashMap<String, Uint128>> {
+    let mut balances_map: HashMap<String, HashMap<String, Uint128>> = HashMap::new();
+    for (contract_addr, balances) in balances.iter() {
+        let mut contract_balances_map: HashMap<String, Uint128> = HashMap::new();
+        for (addr, balance) in balances.iter() {
+            contract_balances_map.insert(addr.to_string(), **balance);
+        }
+
+        balances_map.insert(contract_addr.to_string(), contract_balances_map);
+    }
+    balances_map
 }
 
 #[derive(Clone, Default)]
@@ -55,26 +100,32 @@ pub(crate) fn caps_to_map(caps: &[(&String, &Uint128)]) -> HashMap<String, Uint1
     owner_map
 }
 
+/// Price served by the mocked oracle
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PriceInfo {
+    pub rate: Decimal256,
+    pub last_updated_base: u64,
+    pub last_updated_quote: u64,
+}
+
 #[derive(Clone, Default)]
 pub struct OraclePriceQuerier {
     // this lets us iterate over all pairs that match the first string
-    oracle_price: HashMap<(String, String), (Decimal256, u64, u64)>,
+    oracle_price: HashMap<(String, String), PriceInfo>,
 }
 
-#[allow(clippy::type_complexity)]
 impl OraclePriceQuerier {
-    pub fn new(oracle_price: &[(&(String, String), &(Decimal256, u64, u64))]) -> Self {
+    pub fn new(oracle_price: &[(&(String, String), &PriceInfo)]) -> Self {
         OraclePriceQuerier {
             oracle_price: oracle_price_to_map(oracle_price),
         }
     }
 }
 
-#[allow(clippy::type_complexity)]
 pub(crate) fn oracle_price_to_map(
-    oracle_price: &[(&(String, String), &(Decimal256, u64, u64))],
-) -> HashMap<(String, String), (Decimal256, u64, u64)> {
-    let mut oracle_price_map: HashMap<(String, String), (Decimal256, u64, u64)> = HashMap::new();
+    oracle_price: &[(&(String, String), &PriceInfo)],
+) -> HashMap<(String, String), PriceInfo> {
+    let mut oracle_price_map: HashMap<(String, String), PriceInfo> = HashMap::new();
     for (base_quote, oracle_price) in oracle_price.iter() {
         oracle_price_map.insert((*base_quote).clone(), **oracle_price);
     }
@@ -126,25 +177,85 @@ impl WasmMockQuerier {
                     panic!("DO NOT ENTER HERE")
                 }
             }
+            QueryRequest::Wasm(WasmQuery::Smart { contract_addr, msg })
+                if self.token_querier.balances.contains_key(contract_addr) =>
+            {
+                match from_binary(msg).unwrap() {
+                    Cw20QueryMsg::TokenInfo {} => {
+                        SystemResult::Ok(ContractResult::from(to_binary(&TokenInfoResponse {
+                            name: "aTerra".to_string(),
+                            symbol: "aUST".to_string(),
+                            decimals: 6,
+                            total_supply: self.token_querier.total_supply(contract_addr),
+                        })))
+                    }
+                    Cw20QueryMsg::Balance { address } => {
+                        let balance = self
+                            .token_querier
+                            .balances(contract_addr)
+                            .get(&address)
+                            .copied()
+                            .unwrap_or_default();
+                        SystemResult::Ok(ContractResult::from(to_binary(&BalanceResponse {
+                            balance,
+                        })))
+                    }
+                    _ => panic!("DO NOT ENTER HERE"),
+                }
+            }
+            QueryRequest::Wasm(WasmQuery::Raw { contract_addr, key }) => {
+                // cw20-base storage layout
+                let prefix_balance = to_length_prefixed(b"balance");
+                if key.as_slice() == b"token_info" {
+                    SystemResult::Ok(ContractResult::from(to_binary(&TokenInfoResponse {
+                        name: "aTerra".to_string(),
+                        symbol: "aUST".to_string(),
+                        decimals: 6,
+                        total_supply: self.token_querier.total_supply(contract_addr),
+                    })))
+                } else if key.as_slice().starts_with(&prefix_balance) {
+                    let address =
+                        String::from_utf8(key.as_slice()[prefix_balance.len()..].to_vec()).unwrap();
+                    match self.token_querier.balances(contract_addr).get(&address) {
+                        Some(balance) => SystemResult::Ok(ContractResult::from(to_binary(balance))),
+                        None => SystemResult::Ok(ContractResult::Ok(Binary::default())),
+                    }
+                } else {
+                    panic!("DO NOT ENTER HERE")
+                }
+            }
             QueryRequest::Wasm(WasmQuery::Smart {
                 contract_addr: _,
                 msg,
             }) => match from_binary(msg).unwrap() {
-                OracleQueryMsg::Price { base, quote } => {
-                    match self.oracle_price_querier.oracle_price.get(&(base, quote)) {
-                        Some(v) => {
-                            SystemResult::Ok(ContractResult::from(to_binary(&PriceResponse {
-                                rate: v.0,
-                                last_updated_base: v.1,
-                                last_updated_quote: v.2,
-                            })))
-                        }
-                        None => SystemResult::Err(SystemError::InvalidRequest {
-                            error: "No oracle price exists".to_string(),
-                            request: msg.as_slice().into(),
+                OracleQueryMsg::Price {
+                    base,
+                    quote,
+                    block_time,
+                } => match self.oracle_price_querier.oracle_price.get(&(base, quote)) {
+                    Some(v) => SystemResult::Ok(ContractResult::from(to_binary(&PriceResponse {
+                        rate: v.rate,
+                        last_updated_base: v.last_updated_base,
+                        last_updated_quote: v.last_updated_quote,
+                        decimals_base: 6,
+                        decimals_quote: 6,
+                        age_seconds: block_time.map(|block_time| {
+                            block_time.saturating_sub(std::cmp::min(
+                                v.last_updated_base,
+                                v.last_updated_quote,
+                            ))
                         }),
-                    }
-                }
+                        feeder_count: 1,
+                        derived: false,
+                        frozen: false,
+                        source: "primary".to_string(),
+                        inverted: false,
+                    }))),
+                    None => SystemResult::Err(SystemError::InvalidRequest {
+                        error: "No oracle price exists".to_string(),
+                        request: msg.as_slice().into(),
+                    }),
+                },
                 _ => panic!("DO NOT ENTER HERE"),
             },
             _ => self.base.handle_query(request),
@@ -158,6 +269,7 @@ impl WasmMockQuerier {
             base,
             tax_querier: TaxQuerier::default(),
             oracle_price_querier: OraclePriceQuerier::default(),
+            token_querier: TokenQuerier::default(),
         }
     }
 
@@ -166,11 +278,12 @@ impl WasmMockQuerier {
         self.tax_querier = TaxQuerier::new(rate, caps);
     }
 
-    #[allow(clippy::type_complexity)]
-    pub fn with_oracle_price(
-        &mut self,
-        oracle_price: &[(&(String, String), &(Decimal256, u64, u64))],
-    ) {
+    // configure the cw20 token balances, the supply is their sum
+    pub fn with_token_balances(&mut self, balances: &[(&String, &[(&String, &Uint128)])]) {
+        self.token_querier = TokenQuerier::new(balances);
+    }
+
+    pub fn with_oracle_price(&mut self, oracle_price: &[(&(String, String), &PriceInfo)]) {
         self.oracle_price_querier = OraclePriceQuerier::new(oracle_price);
     }
 }
@@ -2,11 +2,16 @@ use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use cosmwasm_bignumber::Decimal256;
+use cosmwasm_std::Binary;
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct InstantiateMsg {
     pub owner: String,
     pub base_asset: String,
+    pub valid_period: u64,
+    pub fallback_oracle: Option<String>,
+    /// quote prices are fed and stored against, defaults to `base_asset`
+    pub default_quote_denom: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -14,13 +19,71 @@ pub struct InstantiateMsg {
 pub enum ExecuteMsg {
     UpdateConfig {
         owner: Option<String>,
+        valid_period: Option<u64>,
+        fallback_oracle: Option<String>,
+        snapshotter: Option<String>,
+    },
+    RegisterAsset {
+        asset: String,
+        decimals: u8,
+        description: String,
+        heartbeat: Option<u64>,
     },
     RegisterFeeder {
         asset: String,
         feeder: String,
+        quote: Option<String>, // defaults to the base asset
+    },
+    UpdateAssetBounds {
+        asset: String,
+        min_price: Option<Decimal256>,
+        max_price: Option<Decimal256>,
+    },
+    FreezeAsset {
+        asset: String,
+    },
+    UnfreezeAsset {
+        asset: String,
+    },
+    /// Stop serving and accepting prices for an asset being wound down
+    DeprecateAsset {
+        asset: String,
+    },
+    RegisterFeederKey {
+        asset: String,
+        pubkey: Binary, // secp256k1 public key
     },
     FeedPrice {
         prices: Vec<(String, Decimal256)>, // (asset, price)
+        quote: Option<String>,             // defaults to the base asset
+    },
+    /// Feed a price signed by the registered feeder key over
+    /// sha256("{asset}|{price}|{timestamp}")
+    FeedPriceSigned {
+        asset: String,
+        price: Decimal256,
+        timestamp: u64,
+        signature: Binary,
+        pubkey: Binary,
+    },
+    /// Register the AMM pair permissionless updates of `asset` are read from;
+    /// the pair must trade `asset` against the base asset
+    RegisterPairSource {
+        asset: String,
+        pair: String,
+        max_deviation: Decimal256, // allowed spot deviation from the TWAP
+        twap_window: u64,          // seconds
+        /// Seeds the TWAP, defaults to the currently fed price
+        initial_price: Option<Decimal256>,
+    },
+    /// Permissionless price update from the registered AMM pair reserves
+    UpdatePriceFromPair {
+        asset: String,
+    },
+    /// Pin the current prices of `assets` at the block height,
+    /// only callable by the snapshotter
+    SnapshotPrices {
+        assets: Vec<String>,
     },
 }
 
@@ -30,15 +93,64 @@ pub enum QueryMsg {
     Config {},
     Feeder {
         asset: String,
+        quote: Option<String>,
     },
     Price {
         base: String,
         quote: String,
+        block_time: Option<u64>,
     },
     Prices {
+        quote: Option<String>,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    PricesFor {
+        assets: Vec<String>,
+        quote: String,
+        block_time: Option<u64>,
+    },
+    Assets {
         start_after: Option<String>,
         limit: Option<u32>,
     },
+    /// Assets whose price in the default quote missed their heartbeat at `block_time`
+    StaleFeeds {
+        block_time: u64,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Prices in the default quote last updated more than `older_than` seconds before `block_time`;
+    /// at most `limit` prices are scanned per call
+    StalePrices {
+        older_than: u64,
+        block_time: u64,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    PairSource {
+        asset: String,
+    },
+    FeederStats {
+        asset: Option<String>,
+        start_after: Option<(String, String)>, // (asset, feeder)
+        limit: Option<u32>,
+    },
+    SnapshotPrice {
+        asset: String,
+        height: u64,
+    },
+    Feeders {
+        start_after: Option<(String, String)>, // (asset, feeder)
+        limit: Option<u32>,
+    },
+}
+
+/// Moves a contract from the single quote layout, where prices and feeders
+/// were only keyed by asset, to the per quote layout
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MigrateMsg {
+    pub valid_period: u64,
 }
 
 // We define a custom struct for each query response
@@ -46,12 +158,17 @@ pub enum QueryMsg {
 pub struct ConfigResponse {
     pub owner: String,
     pub base_asset: String,
+    pub valid_period: u64,
+    pub fallback_oracle: Option<String>,
+    pub snapshotter: Option<String>,
+    pub default_quote_denom: String,
 }
 
 // We define a custom struct for each query response
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct FeederResponse {
     pub asset: String,
+    pub quote: String,
     pub feeder: String,
 }
 
@@ -61,6 +178,19 @@ pub struct PriceResponse {
     pub rate: Decimal256,
     pub last_updated_base: u64,
     pub last_updated_quote: u64,
+    pub decimals_base: u8,
+    pub decimals_quote: u8,
+    /// seconds since the oldest side was updated, when `block_time` is given
+    pub age_seconds: Option<u64>,
+    /// number of fed prices the rate was computed from
+    pub feeder_count: u32,
+    /// true when the rate is a cross rate of two fed prices
+    pub derived: bool,
+    pub frozen: bool,
+    /// "primary", "amm" or "fallback"
+    pub source: String,
+    /// true when the rate is the reciprocal of the pair stored the other way round
+    pub inverted: bool,
 }
 
 // We define a custom struct for each query response
@@ -76,3 +206,125 @@ pub struct PricesResponseElem {
 pub struct PricesResponse {
     pub prices: Vec<PricesResponseElem>,
 }
+
+// We define a custom struct for each query response
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PricesForResponseElem {
+    pub asset: String,
+    pub price: Option<PriceResponse>,
+    /// set instead of `price` when the price could not be served
+    pub error: Option<String>,
+}
+
+// We define a custom struct for each query response
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PricesForResponse {
+    pub prices: Vec<PricesForResponseElem>,
+}
+
+// We define a custom struct for each query response
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AssetResponse {
+    pub asset: String,
+    pub decimals: u8,
+    pub description: String,
+    pub heartbeat: Option<u64>,
+    pub min_price: Option<Decimal256>,
+    pub max_price: Option<Decimal256>,
+    pub frozen: bool,
+    pub deprecated: bool,
+}
+
+// We define a custom struct for each query response
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AssetsResponse {
+    pub assets: Vec<AssetResponse>,
+}
+
+// We define a custom struct for each query response
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StaleFeedResponse {
+    pub asset: String,
+    pub heartbeat: u64,
+    /// None when the asset was never fed
+    pub last_updated_time: Option<u64>,
+    pub age: Option<u64>,
+}
+
+// We define a custom struct for each query response
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StaleFeedsResponse {
+    pub feeds: Vec<StaleFeedResponse>,
+}
+
+// We define a custom struct for each query response
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StalePricesResponseElem {
+    pub asset: String,
+    pub last_updated_time: u64,
+    pub age: u64,
+    /// feeder registered for the asset, None when it was never registered
+    pub feeder: Option<String>,
+}
+
+// We define a custom struct for each query response
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StalePricesResponse {
+    pub prices: Vec<StalePricesResponseElem>,
+    /// last scanned asset, None once the scan reached the end
+    pub next_start_after: Option<String>,
+}
+
+// We define a custom struct for each query response
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PairSourceResponse {
+    pub asset: String,
+    pub pair: String,
+    pub max_deviation: Decimal256,
+    pub twap_window: u64,
+    /// None until the first update from the pair
+    pub twap: Option<Decimal256>,
+    pub twap_updated_time: u64,
+}
+
+// We define a custom struct for each query response
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FeederStatsResponseElem {
+    pub asset: String,
+    pub feeder: String,
+    /// submissions stored as the current price
+    pub accepted: u64,
+    /// submissions skipped by the bounds and staleness checks
+    pub rejected: u64,
+    pub last_submission_time: u64,
+}
+
+// We define a custom struct for each query response
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FeederStatsResponse {
+    pub stats: Vec<FeederStatsResponseElem>,
+}
+
+// We define a custom struct for each query response
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SnapshotPriceResponse {
+    pub asset: String,
+    pub height: u64,
+    pub rate: Decimal256,
+    pub last_updated_time: u64,
+}
+
+// We define a custom struct for each query response
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FeedersResponseElem {
+    pub asset: String,
+    pub feeder: String,
+    pub quotes: Vec<String>,
+    pub registered_at_height: u64,
+}
+
+// We define a custom struct for each query response
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FeedersResponse {
+    pub feeders: Vec<FeedersResponseElem>,
+}
@@ -1,8 +1,10 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use crate::pagination::OrderBy;
 use crate::tokens::TokensHuman;
 use cosmwasm_bignumber::{Decimal256, Uint256};
+use cosmwasm_std::Uint128;
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
@@ -39,6 +41,7 @@ pub struct InstantiateMsg {
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
+#[allow(clippy::large_enum_variant)]
 pub enum ExecuteMsg {
     ////////////////////
     /// Owner operations
@@ -48,6 +51,7 @@ pub enum ExecuteMsg {
     UpdateConfig {
         owner_addr: Option<String>,
         oracle_contract: Option<String>,
+        market_contract: Option<String>, // resets the epoch state baseline
         liquidation_contract: Option<String>,
         threshold_deposit_rate: Option<Decimal256>,
         target_deposit_rate: Option<Decimal256>,
@@ -55,6 +59,11 @@ pub enum ExecuteMsg {
         anc_purchase_factor: Option<Decimal256>,
         epoch_period: Option<u64>,
         price_timeframe: Option<u64>,
+        max_ltv: Option<Decimal256>, // ceiling for the LTV of whitelisted collaterals
+        guardian: Option<String>,    // may pause the contract besides the owner
+        executor_incentive: Option<Uint128>, // paid to the ExecuteEpochOperations caller
+        max_buffer_distribution: Option<Uint256>, // cap of the buffer distributed per epoch
+        custody_batch_size: Option<u32>, // custodies rewarded per ExecuteEpochOperations call
     },
 
     /// Create new custody contract for the given collateral token
@@ -64,19 +73,44 @@ pub enum ExecuteMsg {
         collateral_token: String, // bAsset token contract
         custody_contract: String, // bAsset custody contract
         max_ltv: Decimal256,      // Loan To Value ratio
+        decimals: Option<u8>,     // bAsset token decimals, 6 if omitted
     },
     /// Update registered whitelist info
     UpdateWhitelist {
         collateral_token: String,         // bAsset token contract
         custody_contract: Option<String>, // bAsset custody contract
         max_ltv: Option<Decimal256>,      // Loan To Value ratio
+        decimals: Option<u8>,             // bAsset token decimals
     },
+    /// Stop accepting new locks of a collateral; locked positions
+    /// keep counting towards the borrow limit and can be unlocked
+    PauseWhitelist {
+        collateral_token: String,
+    },
+    UnpauseWhitelist {
+        collateral_token: String,
+    },
+    /// Replace the custody of a collateral and move
+    /// the tokens held by the previous custody to the new one
+    MigrateCustody {
+        collateral_token: String,
+        new_custody_contract: String,
+    },
+    /// Freeze collateral locks, unlocks and epoch operations;
+    /// callable by the owner or the guardian
+    Pause {},
+    /// Resume the frozen operations; callable by the owner only
+    Unpause {},
 
     /// Claims all staking rewards from the bAsset contracts
     /// and also do a epoch basis updates
     /// 1. Distribute interest buffers to depositors
     /// 2. Invoke [Custody] DistributeRewards
     /// 3. Update epoch state
+    ///
+    /// Custodies are processed custody_batch_size at a time; while some
+    /// are left, a call only distributes their rewards and must be repeated
+    /// until the last one runs steps 1 and 3
     ExecuteEpochOperations {},
     UpdateEpochState {
         interest_buffer: Uint256,
@@ -92,11 +126,17 @@ pub enum ExecuteMsg {
     UnlockCollateral {
         collaterals: TokensHuman, // <(Collateral Token, Amount)>
     },
+    /// Unlock the collaterals and withdraw them from the custody contracts
+    UnlockAndWithdrawCollateral {
+        collaterals: TokensHuman, // <(Collateral Token, Amount)>
+    },
 
     /////////////////////////////
     /// Permissionless operations
     /////////////////////////////
-    LiquidateCollateral { borrower: String },
+    LiquidateCollateral {
+        borrower: String,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -104,22 +144,38 @@ pub enum ExecuteMsg {
 pub enum QueryMsg {
     Config {},
     EpochState {},
+    /// Subsidy parameters with the deposit rate of the last epoch
+    DistributionParams {},
     Whitelist {
         collateral_token: Option<String>,
         start_after: Option<String>,
         limit: Option<u32>,
+        order_by: Option<OrderBy>,
     },
     Collaterals {
         borrower: String,
     },
+    /// Collaterals of each borrower, in the given order;
+    /// at most 30 borrowers per query
+    CollateralsBatch {
+        borrowers: Vec<String>,
+    },
     AllCollaterals {
         start_after: Option<String>,
         limit: Option<u32>,
+        order_by: Option<OrderBy>,
     },
     BorrowLimit {
         borrower: String,
         block_time: Option<u64>,
     },
+    BorrowLimits {
+        borrowers: Vec<String>,
+        block_time: Option<u64>,
+    },
+    CollateralValue {
+        borrower: String,
+    },
 }
 
 // We define a custom struct for each query response
@@ -137,6 +193,31 @@ pub struct ConfigResponse {
     pub stable_denom: String,
     pub epoch_period: u64,
     pub price_timeframe: u64,
+    pub max_ltv: Decimal256,
+    pub guardian: Option<String>,
+    pub paused: bool,
+    pub executor_incentive: Uint128,
+    pub max_buffer_distribution: Option<Uint256>,
+    pub custody_batch_size: u32,
+}
+
+// We define a custom struct for each query response
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct EpochStateResponse {
+    pub deposit_rate: Decimal256,
+    pub prev_aterra_supply: Uint256,
+    pub prev_exchange_rate: Decimal256,
+    pub prev_interest_buffer: Uint256,
+    pub last_executed_height: u64,
+}
+
+// We define a custom struct for each query response
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DistributionParamsResponse {
+    pub target_deposit_rate: Decimal256,
+    pub threshold_deposit_rate: Decimal256,
+    pub buffer_distribution_factor: Decimal256,
+    pub deposit_rate: Decimal256,
 }
 
 // We define a custom struct for each query response
@@ -147,6 +228,8 @@ pub struct WhitelistResponseElem {
     pub max_ltv: Decimal256,
     pub custody_contract: String,
     pub collateral_token: String,
+    pub decimals: u8,
+    pub paused: bool,
 }
 
 // We define a custom struct for each query response
@@ -162,6 +245,12 @@ pub struct CollateralsResponse {
     pub collaterals: TokensHuman, // <(Collateral Token, Amount)>
 }
 
+// We define a custom struct for each query response
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CollateralsBatchResponse {
+    pub collaterals: Vec<CollateralsResponse>,
+}
+
 // We define a custom struct for each query response
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct AllCollateralsResponse {
@@ -172,4 +261,19 @@ pub struct AllCollateralsResponse {
 pub struct BorrowLimitResponse {
     pub borrower: String,
     pub borrow_limit: Uint256,
+    pub collaterals: TokensHuman, // <(Collateral Token, Borrow Limit Contribution)>
+}
+
+// We define a custom struct for each query response
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BorrowLimitsResponse {
+    pub borrow_limits: Vec<BorrowLimitResponse>,
+}
+
+// We define a custom struct for each query response
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CollateralValueResponse {
+    pub borrower: String,
+    pub total_value: Uint256,
+    pub collaterals: TokensHuman, // <(Collateral Token, Value)>
 }
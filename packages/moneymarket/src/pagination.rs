@@ -0,0 +1,66 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use cosmwasm_std::{CanonicalAddr, Order};
+
+// settings for pagination
+pub const MAX_LIMIT: u32 = 30;
+pub const DEFAULT_LIMIT: u32 = 10;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderBy {
+    Ascending,
+    Descending,
+}
+
+impl From<OrderBy> for Order {
+    fn from(order_by: OrderBy) -> Order {
+        match order_by {
+            OrderBy::Ascending => Order::Ascending,
+            OrderBy::Descending => Order::Descending,
+        }
+    }
+}
+
+/// Number of items to return, `default` when not given and at most `max`
+pub fn clamp_limit(limit: Option<u32>, default: u32, max: u32) -> usize {
+    limit.unwrap_or(default).min(max) as usize
+}
+
+// this will set the first key after the provided key, by appending a 1 byte
+pub fn calc_range_start_human(start_after: Option<String>) -> Option<Vec<u8>> {
+    start_after.map(|key| {
+        let mut v = key.as_bytes().to_vec();
+        v.push(1);
+        v
+    })
+}
+
+// this will set the first key after the provided key, by appending a 1 byte
+pub fn calc_range_start_canonical(start_after: Option<CanonicalAddr>) -> Option<Vec<u8>> {
+    start_after.map(|addr| {
+        let mut v = addr.as_slice().to_vec();
+        v.push(1);
+        v
+    })
+}
+
+/// `(start, end)` bounds of a range query continuing after `start_after`.
+/// Ascending ranges start after the key, descending ranges end before it
+/// (the end bound is exclusive).
+pub fn calc_range_bounds(
+    start_after: Option<Vec<u8>>,
+    order_by: OrderBy,
+) -> (Option<Vec<u8>>, Option<Vec<u8>>) {
+    match order_by {
+        OrderBy::Ascending => (
+            start_after.map(|mut key| {
+                key.push(1);
+                key
+            }),
+            None,
+        ),
+        OrderBy::Descending => (None, start_after),
+    }
+}
@@ -3,13 +3,18 @@ use serde::{Deserialize, Serialize};
 
 use cosmwasm_bignumber::{Decimal256, Uint256};
 use cosmwasm_std::{
-    to_binary, Addr, AllBalanceResponse, BalanceResponse, BankQuery, Coin, Deps, QueryRequest,
-    StdError, StdResult, Uint128, WasmQuery,
+    to_binary, Addr, AllBalanceResponse, BalanceResponse, BankQuery, Binary, Coin, Deps,
+    QueryRequest, StdError, StdResult, Uint128, WasmQuery,
 };
-use cw20::{Cw20QueryMsg, TokenInfoResponse};
+use cosmwasm_storage::to_length_prefixed;
+use cw20::{BalanceResponse as Cw20BalanceResponse, Cw20QueryMsg, TokenInfoResponse};
+use std::collections::HashMap;
 use terra_cosmwasm::TerraQuerier;
 
-use crate::oracle::{PriceResponse, QueryMsg as OracleQueryMsg};
+use crate::error::MoneyMarketError;
+use crate::market::{BorrowerInfoResponse, EpochStateResponse, QueryMsg as MarketQueryMsg};
+use crate::oracle::{PriceResponse, PricesForResponse, QueryMsg as OracleQueryMsg};
+use crate::overseer::{BorrowLimitResponse, QueryMsg as OverseerQueryMsg};
 
 pub fn query_all_balances(deps: Deps, account_addr: Addr) -> StdResult<Vec<Coin>> {
     // load price form the oracle
@@ -30,34 +35,62 @@ pub fn query_balance(deps: Deps, account_addr: Addr, denom: String) -> StdResult
     Ok(balance.amount.amount.into())
 }
 
+/// CW20 balance of `account_addr`; missing balances are reported as zero
 pub fn query_token_balance(
     deps: Deps,
     contract_addr: Addr,
     account_addr: Addr,
 ) -> StdResult<Uint256> {
     // load balance form the token contract
-    let balance: Uint128 = deps
-        .querier
-        .query(&QueryRequest::Wasm(WasmQuery::Smart {
-            contract_addr: contract_addr.to_string(),
-            msg: to_binary(&Cw20QueryMsg::Balance {
-                address: account_addr.to_string(),
-            })?,
-        }))
-        .unwrap_or_else(|_| Uint128::zero());
+    let balance: Uint128 = if cfg!(feature = "raw-token-queries") {
+        // cw20-base keeps balances in the "balance" map keyed by address
+        let mut key = to_length_prefixed(b"balance");
+        key.extend_from_slice(account_addr.as_bytes());
+        deps.querier
+            .query(&QueryRequest::Wasm(WasmQuery::Raw {
+                contract_addr: contract_addr.to_string(),
+                key: Binary::from(key),
+            }))
+            .unwrap_or_else(|_| Uint128::zero())
+    } else {
+        deps.querier
+            .query(&QueryRequest::Wasm(WasmQuery::Smart {
+                contract_addr: contract_addr.to_string(),
+                msg: to_binary(&Cw20QueryMsg::Balance {
+                    address: account_addr.to_string(),
+                })?,
+            }))
+            .map(|res: Cw20BalanceResponse| res.balance)
+            .unwrap_or_else(|_| Uint128::zero())
+    };
 
     Ok(balance.into())
 }
 
-pub fn query_supply(deps: Deps, contract_addr: Addr) -> StdResult<Uint256> {
-    // load price form the oracle
-    let token_info: TokenInfoResponse =
-        deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+/// Subset of the cw20-base `token_info` item read by the raw query
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+struct TokenInfo {
+    pub total_supply: Uint128,
+}
+
+/// CW20 total supply of the token
+pub fn query_token_supply(deps: Deps, contract_addr: Addr) -> StdResult<Uint256> {
+    let total_supply: Uint128 = if cfg!(feature = "raw-token-queries") {
+        let token_info: TokenInfo = deps.querier.query(&QueryRequest::Wasm(WasmQuery::Raw {
             contract_addr: contract_addr.to_string(),
-            msg: to_binary(&Cw20QueryMsg::TokenInfo {})?,
+            key: Binary::from(b"token_info".to_vec()),
         }))?;
+        token_info.total_supply
+    } else {
+        let token_info: TokenInfoResponse =
+            deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+                contract_addr: contract_addr.to_string(),
+                msg: to_binary(&Cw20QueryMsg::TokenInfo {})?,
+            }))?;
+        token_info.total_supply
+    };
 
-    Ok(Uint256::from(token_info.total_supply))
+    Ok(Uint256::from(total_supply))
 }
 
 pub fn query_tax_rate(deps: Deps) -> StdResult<Decimal256> {
@@ -65,17 +98,26 @@ pub fn query_tax_rate(deps: Deps) -> StdResult<Decimal256> {
     Ok(terra_querier.query_tax_rate()?.rate.into())
 }
 
+/// Tax charged on sending the coin, `amount - amount / (1 + rate)` capped
+/// by the denom's cap. `deduct_tax` subtracts exactly this amount.
 pub fn compute_tax(deps: Deps, coin: &Coin) -> StdResult<Uint256> {
     let terra_querier = TerraQuerier::new(&deps.querier);
     let tax_rate = Decimal256::from((terra_querier.query_tax_rate()?).rate);
     let tax_cap = Uint256::from((terra_querier.query_tax_cap(coin.denom.to_string())?).cap);
-    let amount = Uint256::from(coin.amount);
-    Ok(std::cmp::min(
-        amount * Decimal256::one() - amount / (Decimal256::one() + tax_rate),
+    Ok(compute_tax_raw(
+        Uint256::from(coin.amount),
+        tax_rate,
         tax_cap,
     ))
 }
 
+fn compute_tax_raw(amount: Uint256, tax_rate: Decimal256, tax_cap: Uint256) -> Uint256 {
+    std::cmp::min(
+        amount * Decimal256::one() - amount / (Decimal256::one() + tax_rate),
+        tax_cap,
+    )
+}
+
 pub fn deduct_tax(deps: Deps, coin: Coin) -> StdResult<Coin> {
     let tax_amount = compute_tax(deps, &coin)?;
     Ok(Coin {
@@ -84,33 +126,196 @@ pub fn deduct_tax(deps: Deps, coin: Coin) -> StdResult<Coin> {
     })
 }
 
+/// Deducts the tax of each coin, querying the tax rate and each
+/// denom's cap once. Coins left with nothing are dropped.
+pub fn deduct_tax_vec(deps: Deps, coins: Vec<Coin>) -> StdResult<Vec<Coin>> {
+    let terra_querier = TerraQuerier::new(&deps.querier);
+    let tax_rate = Decimal256::from((terra_querier.query_tax_rate()?).rate);
+    let mut tax_caps: HashMap<String, Uint256> = HashMap::new();
+
+    let mut deducted_coins: Vec<Coin> = vec![];
+    for coin in coins {
+        let tax_cap = match tax_caps.get(&coin.denom) {
+            Some(tax_cap) => *tax_cap,
+            None => {
+                let tax_cap =
+                    Uint256::from((terra_querier.query_tax_cap(coin.denom.to_string())?).cap);
+                tax_caps.insert(coin.denom.clone(), tax_cap);
+                tax_cap
+            }
+        };
+
+        let amount = Uint256::from(coin.amount);
+        let amount = amount - compute_tax_raw(amount, tax_rate, tax_cap);
+        if !amount.is_zero() {
+            deducted_coins.push(Coin {
+                denom: coin.denom,
+                amount: amount.into(),
+            });
+        }
+    }
+
+    Ok(deducted_coins)
+}
+
+/// Query the epoch state from the market contract
+pub fn query_epoch_state(
+    deps: Deps,
+    market_addr: Addr,
+    block_height: u64,
+    distributed_interest: Option<Uint256>,
+) -> StdResult<EpochStateResponse> {
+    let epoch_state: EpochStateResponse =
+        deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+            contract_addr: market_addr.to_string(),
+            msg: to_binary(&MarketQueryMsg::EpochState {
+                block_height: Some(block_height),
+                distributed_interest,
+            })?,
+        }))?;
+
+    Ok(epoch_state)
+}
+
+/// Query the borrower info from the market contract
+pub fn query_borrower_info(
+    deps: Deps,
+    market_addr: Addr,
+    borrower: Addr,
+    block_height: u64,
+) -> StdResult<BorrowerInfoResponse> {
+    let borrower_info: BorrowerInfoResponse =
+        deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+            contract_addr: market_addr.to_string(),
+            msg: to_binary(&MarketQueryMsg::BorrowerInfo {
+                borrower: borrower.to_string(),
+                block_height: Some(block_height),
+            })?,
+        }))?;
+
+    Ok(borrower_info)
+}
+
+/// Query the loan amount of the borrower from the market contract
+pub fn query_loan_amount(
+    deps: Deps,
+    market_addr: Addr,
+    borrower: Addr,
+    block_height: u64,
+) -> StdResult<Uint256> {
+    Ok(query_borrower_info(deps, market_addr, borrower, block_height)?.loan_amount)
+}
+
+/// Query the borrow limit of the borrower from the overseer contract
+pub fn query_borrow_limit(
+    deps: Deps,
+    overseer_addr: Addr,
+    borrower: Addr,
+    block_time: Option<u64>,
+) -> StdResult<BorrowLimitResponse> {
+    let borrow_limit: BorrowLimitResponse =
+        deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+            contract_addr: overseer_addr.to_string(),
+            msg: to_binary(&OverseerQueryMsg::BorrowLimit {
+                borrower: borrower.to_string(),
+                block_time,
+            })?,
+        }))?;
+
+    Ok(borrow_limit)
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct TimeConstraints {
     pub block_time: u64,
     pub valid_timeframe: u64,
 }
 
+/// Oracle price of base in quote. With time constraints, fails with
+/// `MoneyMarketError::PriceStale` when either side was last updated before
+/// `block_time - valid_timeframe`.
 pub fn query_price(
     deps: Deps,
     oracle_addr: Addr,
     base: String,
     quote: String,
     time_contraints: Option<TimeConstraints>,
-) -> StdResult<PriceResponse> {
+) -> Result<PriceResponse, MoneyMarketError> {
     let oracle_price: PriceResponse =
         deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
             contract_addr: oracle_addr.to_string(),
-            msg: to_binary(&OracleQueryMsg::Price { base, quote })?,
+            msg: to_binary(&OracleQueryMsg::Price {
+                base: base.clone(),
+                quote: quote.clone(),
+                block_time: time_contraints.as_ref().map(|t| t.block_time),
+            })?,
         }))?;
 
+    assert_price_timeframe(&oracle_price, &base, &quote, &time_contraints)?;
+
+    Ok(oracle_price)
+}
+
+/// Batched form of `query_price`; fails when any of the assets cannot be priced
+pub fn query_prices_for(
+    deps: Deps,
+    oracle_addr: Addr,
+    assets: Vec<String>,
+    quote: String,
+    time_contraints: Option<TimeConstraints>,
+) -> Result<Vec<PriceResponse>, MoneyMarketError> {
+    let oracle_prices: PricesForResponse =
+        deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+            contract_addr: oracle_addr.to_string(),
+            msg: to_binary(&OracleQueryMsg::PricesFor {
+                assets,
+                quote: quote.clone(),
+                block_time: time_contraints.as_ref().map(|t| t.block_time),
+            })?,
+        }))?;
+
+    oracle_prices
+        .prices
+        .into_iter()
+        .map(|elem| match elem.price {
+            Some(oracle_price) => {
+                assert_price_timeframe(&oracle_price, &elem.asset, &quote, &time_contraints)?;
+                Ok(oracle_price)
+            }
+            None => Err(StdError::generic_err(format!(
+                "No price for {}: {}",
+                elem.asset,
+                elem.error.unwrap_or_default()
+            ))
+            .into()),
+        })
+        .collect()
+}
+
+/// Reports the older side of the price when it is out of the timeframe
+fn assert_price_timeframe(
+    oracle_price: &PriceResponse,
+    base: &str,
+    quote: &str,
+    time_contraints: &Option<TimeConstraints>,
+) -> Result<(), MoneyMarketError> {
     if let Some(time_contraints) = time_contraints {
-        let valid_update_time = time_contraints.block_time - time_contraints.valid_timeframe;
-        if oracle_price.last_updated_base < valid_update_time
-            || oracle_price.last_updated_quote < valid_update_time
-        {
-            return Err(StdError::generic_err("Price is too old"));
+        let valid_update_time = time_contraints
+            .block_time
+            .saturating_sub(time_contraints.valid_timeframe);
+        let (asset, last_updated_time) =
+            if oracle_price.last_updated_base <= oracle_price.last_updated_quote {
+                (base, oracle_price.last_updated_base)
+            } else {
+                (quote, oracle_price.last_updated_quote)
+            };
+        if last_updated_time < valid_update_time {
+            return Err(MoneyMarketError::PriceStale {
+                asset: asset.to_string(),
+                age: time_contraints.block_time.saturating_sub(last_updated_time),
+            });
         }
     }
 
-    Ok(oracle_price)
+    Ok(())
 }
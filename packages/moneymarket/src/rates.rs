@@ -0,0 +1,102 @@
+use cosmwasm_bignumber::{Decimal256, Uint256};
+use cosmwasm_std::{StdError, StdResult};
+
+/// Blocks per year at ~6.8s blocks, for configs stored before
+/// blocks_per_year was tracked
+pub const BLOCKS_PER_YEAR: u64 = 4_656_810;
+
+/// Operands above this cannot be multiplied without overflowing 256 bits
+const MAX_COMPOUNDING_OPERAND: u128 = 100_000_000_000_000_000_000;
+
+/// Iterations bisecting the compounded per-block rate; halves the
+/// search range down to the last decimal place
+const MAX_BISECTION_STEPS: usize = 128;
+
+/// Utilization is zero without liabilities. Reserves exceeding the
+/// balance plus liabilities saturate the market value to zero, and
+/// a market without value has zero utilization, i.e. the base rate.
+pub fn compute_utilization_ratio(
+    market_balance: Uint256,
+    total_liabilities: Decimal256,
+    total_reserves: Decimal256,
+) -> Decimal256 {
+    let market_value = Decimal256::from_uint256(market_balance) + total_liabilities;
+    if total_liabilities.is_zero() || market_value <= total_reserves {
+        return Decimal256::zero();
+    }
+
+    total_liabilities / (market_value - total_reserves)
+}
+
+/// Annualizes a per-block rate without compounding
+pub fn per_block_to_apr(rate: Decimal256, blocks_per_year: u64) -> Decimal256 {
+    rate * Decimal256::from_uint256(blocks_per_year)
+}
+
+/// Per-block rate that sums up to `apr` over a year; rounds down
+pub fn apr_to_per_block(apr: Decimal256, blocks_per_year: u64) -> StdResult<Decimal256> {
+    assert_blocks_per_year(blocks_per_year)?;
+    Ok(apr / Decimal256::from_uint256(blocks_per_year))
+}
+
+/// Annualizes a per-block rate compounded every block
+pub fn per_block_to_apy(rate: Decimal256, blocks_per_year: u64) -> StdResult<Decimal256> {
+    Ok(pow(Decimal256::one() + rate, blocks_per_year)? - Decimal256::one())
+}
+
+/// Per-block rate that compounds to `apy` over a year; rounds down
+pub fn apy_to_per_block(apy: Decimal256, blocks_per_year: u64) -> StdResult<Decimal256> {
+    // compounding never yields less than the simple rate,
+    // so the per-block rate is at most the one of the apr
+    let mut low = Decimal256::zero();
+    let mut high = apr_to_per_block(apy, blocks_per_year)?;
+    for _ in 0..MAX_BISECTION_STEPS {
+        let mid = (low + high) / Decimal256::from_uint256(2u64);
+        if mid == low {
+            break;
+        }
+
+        match per_block_to_apy(mid, blocks_per_year) {
+            Ok(mid_apy) if mid_apy <= apy => low = mid,
+            _ => high = mid,
+        }
+    }
+
+    Ok(low)
+}
+
+fn assert_blocks_per_year(blocks_per_year: u64) -> StdResult<()> {
+    if blocks_per_year == 0 {
+        return Err(StdError::generic_err("blocks_per_year must be positive"));
+    }
+
+    Ok(())
+}
+
+/// Exponentiation by squaring; errors instead of overflowing
+fn pow(base: Decimal256, exp: u64) -> StdResult<Decimal256> {
+    let max_operand = Decimal256::from_uint256(MAX_COMPOUNDING_OPERAND);
+    let overflow = || StdError::generic_err("Compounded rate overflows");
+
+    let mut base = base;
+    let mut exp = exp;
+    let mut result = Decimal256::one();
+    while exp > 0 {
+        if exp & 1 == 1 {
+            if result > max_operand || base > max_operand {
+                return Err(overflow());
+            }
+            result = result * base;
+        }
+
+        exp >>= 1;
+        if exp > 0 {
+            if base > max_operand {
+                return Err(overflow());
+            }
+            base = base * base;
+        }
+    }
+
+    Ok(result)
+}
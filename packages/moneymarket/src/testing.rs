@@ -1,10 +1,38 @@
-use crate::mock_querier::mock_dependencies;
+use crate::common::{assert_fraction, assert_max, assert_positive_fraction};
+use crate::custody::{Cw20HookMsg as CustodyCw20HookMsg, ExecuteMsg as CustodyExecuteMsg};
+use crate::error::MoneyMarketError;
+use crate::liquidation::Cw20HookMsg as LiquidationCw20HookMsg;
+use crate::market::Cw20HookMsg as MarketCw20HookMsg;
+use crate::math::{
+    compute_value, decimal_division_256, decimal_multiplication_256, uint_mul_decimal_256,
+    value_of, STABLE_DECIMALS,
+};
+use crate::mock_querier::{mock_dependencies, PriceInfo};
 use crate::oracle::PriceResponse;
-use crate::querier::{compute_tax, deduct_tax, query_price, query_tax_rate, TimeConstraints};
-use crate::tokens::{Tokens, TokensHuman, TokensMath, TokensToRaw};
+use crate::pagination::{
+    calc_range_bounds, calc_range_start_canonical, calc_range_start_human, clamp_limit, OrderBy,
+    DEFAULT_LIMIT, MAX_LIMIT,
+};
+use crate::querier::{
+    compute_tax, deduct_tax, deduct_tax_vec, query_price, query_tax_rate, query_token_balance,
+    query_token_supply, TimeConstraints,
+};
+use crate::rates::{
+    apr_to_per_block, apy_to_per_block, per_block_to_apr, per_block_to_apy, BLOCKS_PER_YEAR,
+};
+use crate::tokens::{Tokens, TokensHuman, TokensMath, TokensToHuman, TokensToRaw};
 
 use cosmwasm_bignumber::{Decimal256, Uint256};
-use cosmwasm_std::{Addr, Api, CanonicalAddr, Coin, Decimal, StdError, Uint128};
+use cosmwasm_std::{
+    from_binary, to_binary, Addr, Api, Binary, CanonicalAddr, Coin, Decimal, Order, StdError,
+    Uint128,
+};
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::ops::Bound;
+use std::str::FromStr;
 
 #[test]
 fn tax_rate_querier() {
@@ -37,6 +65,21 @@ fn test_compute_tax() {
         compute_tax(deps.as_ref(), &Coin::new(50000000u128, "uusd")).unwrap(),
         Uint256::from(495050u64)
     );
+
+    // 50 - 50 / 1.01 rounds the net down, so the tax is one unit
+    // more than the naive 50 * 0.01 and still matches deduct_tax
+    assert_eq!(
+        compute_tax(deps.as_ref(), &Coin::new(50u128, "uusd")).unwrap(),
+        Uint256::one()
+    );
+    assert_eq!(
+        deduct_tax(deps.as_ref(), Coin::new(50u128, "uusd")).unwrap(),
+        Coin::new(49u128, "uusd")
+    );
+    assert_eq!(
+        Uint256::from(50u64) * Decimal256::percent(1),
+        Uint256::zero()
+    );
 }
 
 #[test]
@@ -67,13 +110,56 @@ fn test_deduct_tax() {
     );
 }
 
+#[test]
+fn test_deduct_tax_vec() {
+    let mut deps = mock_dependencies(&[]);
+
+    deps.querier.with_tax(
+        Decimal::percent(1),
+        &[
+            (&"uusd".to_string(), &Uint128::from(1000000u128)),
+            (&"ukrw".to_string(), &Uint128::from(100u128)),
+        ],
+    );
+
+    assert_eq!(
+        deduct_tax_vec(
+            deps.as_ref(),
+            vec![
+                // cap to 1000000
+                Coin::new(10000000000u128, "uusd"),
+                // cap to 100
+                Coin::new(50000u128, "ukrw"),
+                // fully consumed by the tax
+                Coin::new(1u128, "uusd"),
+                // normal tax
+                Coin::new(50000000u128, "uusd"),
+                Coin::new(20u128, "ukrw"),
+            ],
+        )
+        .unwrap(),
+        vec![
+            Coin::new(9999000000u128, "uusd"),
+            Coin::new(49900u128, "ukrw"),
+            Coin::new(49504950u128, "uusd"),
+            Coin::new(19u128, "ukrw"),
+        ]
+    );
+
+    assert_eq!(deduct_tax_vec(deps.as_ref(), vec![]).unwrap(), vec![]);
+}
+
 #[test]
 fn oracle_price_querier() {
     let mut deps = mock_dependencies(&[]);
 
     deps.querier.with_oracle_price(&[(
         &("terra123123".to_string(), "uusd".to_string()),
-        &(Decimal256::from_ratio(131, 2), 123, 321),
+        &PriceInfo {
+            rate: Decimal256::from_ratio(131, 2),
+            last_updated_base: 123,
+            last_updated_quote: 321,
+        },
     )]);
 
     let oracle_price = query_price(
@@ -91,9 +177,31 @@ fn oracle_price_querier() {
             rate: Decimal256::from_ratio(131, 2),
             last_updated_base: 123,
             last_updated_quote: 321,
+            decimals_base: 6,
+            decimals_quote: 6,
+            age_seconds: None,
+            feeder_count: 1,
+            derived: false,
+            frozen: false,
+            source: "primary".to_string(),
+            inverted: false,
         }
     );
 
+    // the caller's block time is forwarded to derive the price age
+    let oracle_price = query_price(
+        deps.as_ref(),
+        Addr::unchecked("oracle"),
+        "terra123123".to_string(),
+        "uusd".to_string(),
+        Some(TimeConstraints {
+            block_time: 150u64,
+            valid_timeframe: 60u64,
+        }),
+    )
+    .unwrap();
+    assert_eq!(oracle_price.age_seconds, Some(27));
+
     query_price(
         deps.as_ref(),
         Addr::unchecked("oracle"),
@@ -115,9 +223,25 @@ fn oracle_price_querier() {
     );
 
     match res {
-        Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Price is too old"),
+        Err(MoneyMarketError::PriceStale { asset, age }) => {
+            assert_eq!(asset, "terra123123");
+            assert_eq!(age, 377);
+        }
         _ => panic!("DO NOT ENTER HERE"),
     }
+
+    // a timeframe longer than the chain history accepts any price
+    query_price(
+        deps.as_ref(),
+        Addr::unchecked("oracle"),
+        "terra123123".to_string(),
+        "uusd".to_string(),
+        Some(TimeConstraints {
+            block_time: 30u64,
+            valid_timeframe: 60u64,
+        }),
+    )
+    .unwrap();
 }
 
 #[test]
@@ -300,3 +424,662 @@ fn token_math_invalid_token_2() {
 
     let _ = tokens_1_raw.sub(tokens_2_raw);
 }
+
+#[test]
+fn tokens_sort_and_duplicates() {
+    let deps = mock_dependencies(&[]);
+
+    let tokens: TokensHuman = vec![
+        ("token3".to_string(), Uint256::from(3u64)),
+        ("token1".to_string(), Uint256::from(1u64)),
+        ("token2".to_string(), Uint256::from(2u64)),
+    ];
+
+    let mut tokens_raw: Tokens = tokens.to_raw(deps.as_ref()).unwrap();
+    tokens_raw.sort_by_addr();
+    assert!(tokens_raw
+        .windows(2)
+        .all(|w| w[0].0.as_slice() < w[1].0.as_slice()));
+    assert_eq!(tokens_raw.assert_no_duplicates(), Ok(()));
+
+    // round trip keeps the amounts attached to their tokens
+    let mut tokens_human = tokens_raw.to_human(deps.as_ref()).unwrap();
+    tokens_human.sort();
+    assert_eq!(
+        tokens_human,
+        vec![
+            ("token1".to_string(), Uint256::from(1u64)),
+            ("token2".to_string(), Uint256::from(2u64)),
+            ("token3".to_string(), Uint256::from(3u64)),
+        ]
+    );
+
+    let duplicated: TokensHuman = vec![
+        ("token1".to_string(), Uint256::from(1u64)),
+        ("token2".to_string(), Uint256::from(2u64)),
+        ("token1".to_string(), Uint256::from(3u64)),
+    ];
+    let mut duplicated_raw: Tokens = duplicated.to_raw(deps.as_ref()).unwrap();
+    duplicated_raw.sort_by_addr();
+
+    let token1 = deps.api.addr_canonicalize("token1").unwrap();
+    match duplicated_raw.assert_no_duplicates() {
+        Err(StdError::GenericErr { msg, .. }) => {
+            assert_eq!(msg, format!("Duplicate token address: {}", token1))
+        }
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+}
+
+#[test]
+fn token_math_underflow_names_token() {
+    let deps = mock_dependencies(&[]);
+
+    let tokens_1: TokensHuman = vec![
+        ("token1".to_string(), Uint256::from(1000000u64)),
+        ("token2".to_string(), Uint256::from(1000000u64)),
+    ];
+    let mut tokens_1_raw: Tokens = tokens_1.to_raw(deps.as_ref()).unwrap();
+
+    // amount larger than the current balance
+    let tokens_2: TokensHuman = vec![("token2".to_string(), Uint256::from(1000001u64))];
+    let tokens_2_raw: Tokens = tokens_2.to_raw(deps.as_ref()).unwrap();
+    let token2 = deps.api.addr_canonicalize("token2").unwrap();
+    match tokens_1_raw.clone().sub(tokens_2_raw) {
+        Err(StdError::GenericErr { msg, .. }) => {
+            assert_eq!(msg, format!("Subtraction underflow: {}", token2))
+        }
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // token not held at all
+    let tokens_3: TokensHuman = vec![("token3".to_string(), Uint256::from(1u64))];
+    let tokens_3_raw: Tokens = tokens_3.to_raw(deps.as_ref()).unwrap();
+    let token3 = deps.api.addr_canonicalize("token3").unwrap();
+    match tokens_1_raw.sub(tokens_3_raw) {
+        Err(StdError::GenericErr { msg, .. }) => {
+            assert_eq!(msg, format!("Subtraction underflow: {}", token3))
+        }
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+}
+
+const DECIMAL_FRACTIONAL: u128 = 1_000_000_000_000_000_000u128;
+
+// deterministic xorshift, so failures can be reproduced
+fn next_random(seed: &mut u64) -> u64 {
+    *seed ^= *seed << 13;
+    *seed ^= *seed >> 7;
+    *seed ^= *seed << 17;
+    *seed
+}
+
+fn decimal_from_atomics(atomics: u128) -> Decimal256 {
+    Decimal256::from_ratio(Uint256::from(atomics), Uint256::from(DECIMAL_FRACTIONAL))
+}
+
+#[test]
+fn math_256_matches_reference() {
+    // inputs are bounded to 64 bits, so the exact reference fits in u128
+    let mut seed = 0x2545f4914f6cdd1du64;
+    for _ in 0..1000 {
+        let a = next_random(&mut seed) as u128;
+        let b = next_random(&mut seed) as u128 | 1;
+        let amount = (next_random(&mut seed) >> 4) as u128;
+        let ltv = next_random(&mut seed) as u128 % DECIMAL_FRACTIONAL;
+
+        assert_eq!(
+            decimal_multiplication_256(decimal_from_atomics(a), decimal_from_atomics(b)),
+            decimal_from_atomics(a * b / DECIMAL_FRACTIONAL)
+        );
+        assert_eq!(
+            decimal_division_256(decimal_from_atomics(a), decimal_from_atomics(b)).unwrap(),
+            decimal_from_atomics(a * DECIMAL_FRACTIONAL / b)
+        );
+        assert_eq!(
+            uint_mul_decimal_256(Uint256::from(amount), decimal_from_atomics(b)),
+            Uint256::from(amount * b / DECIMAL_FRACTIONAL)
+        );
+        assert_eq!(
+            value_of(
+                Uint256::from(amount),
+                decimal_from_atomics(b),
+                decimal_from_atomics(ltv)
+            ),
+            Uint256::from(amount * b / DECIMAL_FRACTIONAL * ltv / DECIMAL_FRACTIONAL)
+        );
+    }
+}
+
+#[test]
+fn math_256_from_128_bit_types() {
+    let price = Decimal::from_ratio(123456789u128, 1000u128);
+    let ltv = Decimal::percent(60);
+
+    assert_eq!(
+        decimal_multiplication_256(price, ltv),
+        Decimal256::from_ratio(123456789u64 * 60, 1000u64 * 100)
+    );
+    assert_eq!(
+        uint_mul_decimal_256(Uint128::from(1000u128), price),
+        Uint256::from(123456789u64)
+    );
+
+    // values beyond the 128-bit range do not overflow
+    let large = Decimal256::from_uint256(Uint256::from(u128::MAX));
+    assert_eq!(
+        decimal_multiplication_256(large, Decimal256::from_uint256(2u64)),
+        Decimal256::from_uint256(Uint256::from(u128::MAX) + Uint256::from(u128::MAX))
+    );
+
+    match decimal_division_256(price, Decimal256::zero()) {
+        Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Division by zero"),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+}
+
+#[test]
+fn compute_value_normalizes_decimals() {
+    let price = Decimal256::from_ratio(1234u64, 100u64); // 12.34 uusd per unit
+
+    // 6 decimal collateral: 1.5 units -> 18.51 UST
+    assert_eq!(
+        compute_value(1_500_000u64, price, 6, STABLE_DECIMALS).unwrap(),
+        Uint256::from(18_510_000u64)
+    );
+
+    // 8 decimal collateral: 1.5 units -> 18.51 UST
+    assert_eq!(
+        compute_value(150_000_000u64, price, 8, STABLE_DECIMALS).unwrap(),
+        Uint256::from(18_510_000u64)
+    );
+
+    // 18 decimal collateral: 1.5 units -> 18.51 UST
+    assert_eq!(
+        compute_value(1_500_000_000_000_000_000u128, price, 18, STABLE_DECIMALS).unwrap(),
+        Uint256::from(18_510_000u64)
+    );
+
+    // 18 decimal collateral: dust below one uusd rounds down to zero
+    assert_eq!(
+        compute_value(80_000_000_000u64, price, 18, STABLE_DECIMALS).unwrap(),
+        Uint256::zero()
+    );
+
+    // 4 decimal collateral: 1.5 units -> 18.51 UST
+    assert_eq!(
+        compute_value(15_000u64, price, 4, STABLE_DECIMALS).unwrap(),
+        Uint256::from(18_510_000u64)
+    );
+
+    match compute_value(1u64, price, 25, STABLE_DECIMALS) {
+        Err(StdError::GenericErr { msg, .. }) => {
+            assert_eq!(msg, "Decimals cannot differ by more than 18: 25 and 6")
+        }
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+}
+
+fn assert_within(actual: Decimal256, expected: Decimal256, tolerance: Decimal256) {
+    let diff = if actual > expected {
+        actual - expected
+    } else {
+        expected - actual
+    };
+    assert!(
+        diff <= tolerance,
+        "{} differs from {} by more than {}",
+        actual,
+        expected,
+        tolerance
+    );
+}
+
+#[test]
+fn rates_round_trip() {
+    let tolerance = Decimal256::from_str("0.000000001").unwrap();
+    for apr in ["0.001", "0.035", "0.2", "0.5", "1"] {
+        let apr = Decimal256::from_str(apr).unwrap();
+
+        let rate = apr_to_per_block(apr, BLOCKS_PER_YEAR).unwrap();
+        assert_within(per_block_to_apr(rate, BLOCKS_PER_YEAR), apr, tolerance);
+
+        let rate = apy_to_per_block(apr, BLOCKS_PER_YEAR).unwrap();
+        assert_within(
+            per_block_to_apy(rate, BLOCKS_PER_YEAR).unwrap(),
+            apr,
+            tolerance,
+        );
+        // compounding reaches the same yearly rate with a lower per-block one
+        assert!(rate < apr_to_per_block(apr, BLOCKS_PER_YEAR).unwrap());
+    }
+
+    // compounding every block converges to e^apr - 1
+    let rate = apr_to_per_block(Decimal256::percent(20), BLOCKS_PER_YEAR).unwrap();
+    assert_within(
+        per_block_to_apy(rate, BLOCKS_PER_YEAR).unwrap(),
+        Decimal256::from_str("0.221402758").unwrap(),
+        Decimal256::from_str("0.000001").unwrap(),
+    );
+
+    // a year of one block does not compound
+    assert_eq!(
+        per_block_to_apy(Decimal256::percent(20), 1).unwrap(),
+        Decimal256::percent(20)
+    );
+
+    match apr_to_per_block(Decimal256::percent(20), 0) {
+        Err(StdError::GenericErr { msg, .. }) => {
+            assert_eq!(msg, "blocks_per_year must be positive")
+        }
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    match per_block_to_apy(Decimal256::one(), BLOCKS_PER_YEAR) {
+        Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Compounded rate overflows"),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+}
+
+#[test]
+fn token_supply_and_balance_querier() {
+    let mut deps = mock_dependencies(&[]);
+
+    deps.querier.with_token_balances(&[(
+        &"aterra".to_string(),
+        &[
+            (&"addr0000".to_string(), &Uint128::from(100u128)),
+            (&"addr0001".to_string(), &Uint128::from(250u128)),
+        ],
+    )]);
+
+    assert_eq!(
+        query_token_supply(deps.as_ref(), Addr::unchecked("aterra")).unwrap(),
+        Uint256::from(350u64)
+    );
+    assert_eq!(
+        query_token_balance(
+            deps.as_ref(),
+            Addr::unchecked("aterra"),
+            Addr::unchecked("addr0001")
+        )
+        .unwrap(),
+        Uint256::from(250u64)
+    );
+
+    // unknown holders have no balance
+    assert_eq!(
+        query_token_balance(
+            deps.as_ref(),
+            Addr::unchecked("aterra"),
+            Addr::unchecked("addr0002")
+        )
+        .unwrap(),
+        Uint256::zero()
+    );
+}
+
+#[test]
+fn custody_execute_msg_wire_format() {
+    // the overseer builds these messages and the custody contracts parse them
+    let msgs = vec![
+        (
+            CustodyExecuteMsg::LockCollateral {
+                borrower: "addr0000".to_string(),
+                amount: Uint256::from(100u64),
+            },
+            r#"{"lock_collateral":{"borrower":"addr0000","amount":"100"}}"#,
+        ),
+        (
+            CustodyExecuteMsg::UnlockCollateral {
+                borrower: "addr0000".to_string(),
+                amount: Uint256::from(100u64),
+            },
+            r#"{"unlock_collateral":{"borrower":"addr0000","amount":"100"}}"#,
+        ),
+        (
+            CustodyExecuteMsg::DistributeRewards {},
+            r#"{"distribute_rewards":{}}"#,
+        ),
+        (
+            CustodyExecuteMsg::LiquidateCollateral {
+                liquidator: "liquidator".to_string(),
+                borrower: "addr0000".to_string(),
+                amount: Uint256::from(100u64),
+            },
+            r#"{"liquidate_collateral":{"liquidator":"liquidator","borrower":"addr0000","amount":"100"}}"#,
+        ),
+        (
+            CustodyExecuteMsg::WithdrawCollateral {
+                borrower: Some("addr0000".to_string()),
+                amount: Some(Uint256::from(100u64)),
+            },
+            r#"{"withdraw_collateral":{"borrower":"addr0000","amount":"100"}}"#,
+        ),
+    ];
+
+    for (msg, json) in msgs {
+        assert_eq!(to_binary(&msg).unwrap(), Binary::from(json.as_bytes()));
+        assert_eq!(
+            from_binary::<CustodyExecuteMsg>(&Binary::from(json.as_bytes())).unwrap(),
+            msg
+        );
+    }
+
+    // borrowers withdrawing for themselves omit the borrower
+    assert_eq!(
+        from_binary::<CustodyExecuteMsg>(&Binary::from(
+            r#"{"withdraw_collateral":{"amount":"100"}}"#.as_bytes()
+        ))
+        .unwrap(),
+        CustodyExecuteMsg::WithdrawCollateral {
+            borrower: None,
+            amount: Some(Uint256::from(100u64)),
+        }
+    );
+}
+
+// same semantics as a storage range: inclusive start, exclusive end
+fn range_keys(
+    keys: &[&[u8]],
+    start: Option<Vec<u8>>,
+    end: Option<Vec<u8>>,
+    order: Order,
+) -> Vec<Vec<u8>> {
+    let map: BTreeMap<Vec<u8>, ()> = keys.iter().map(|k| (k.to_vec(), ())).collect();
+    let start = start.map_or(Bound::Unbounded, Bound::Included);
+    let end = end.map_or(Bound::Unbounded, Bound::Excluded);
+    let range = map.range((start, end)).map(|(k, _)| k.clone());
+    match order {
+        Order::Ascending => range.collect(),
+        Order::Descending => range.rev().collect(),
+    }
+}
+
+#[test]
+fn pagination_limit() {
+    assert_eq!(clamp_limit(None, DEFAULT_LIMIT, MAX_LIMIT), 10);
+    assert_eq!(clamp_limit(Some(0), DEFAULT_LIMIT, MAX_LIMIT), 0);
+    assert_eq!(clamp_limit(Some(25), DEFAULT_LIMIT, MAX_LIMIT), 25);
+    assert_eq!(clamp_limit(Some(31), DEFAULT_LIMIT, MAX_LIMIT), 30);
+    assert_eq!(clamp_limit(Some(u32::MAX), 5, 7), 7);
+
+    assert!(matches!(Order::from(OrderBy::Ascending), Order::Ascending));
+    assert!(matches!(
+        Order::from(OrderBy::Descending),
+        Order::Descending
+    ));
+    assert_eq!(
+        to_binary(&OrderBy::Descending).unwrap(),
+        Binary::from(br#""descending""#.to_vec())
+    );
+}
+
+#[test]
+fn pagination_range_start() {
+    let keys: Vec<&[u8]> = vec![b"", b"a", b"ab", b"b", b"ba"];
+
+    // no start_after returns everything
+    assert_eq!(calc_range_start_human(None), None);
+    assert_eq!(calc_range_start_canonical(None), None);
+    assert_eq!(range_keys(&keys, None, None, Order::Ascending).len(), 5);
+
+    // an empty key only skips itself
+    let start = calc_range_start_human(Some(String::new()));
+    assert_eq!(start, Some(vec![1u8]));
+    assert_eq!(
+        range_keys(&keys, start, None, Order::Ascending),
+        vec![b"a".to_vec(), b"ab".to_vec(), b"b".to_vec(), b"ba".to_vec()]
+    );
+
+    // an exact key is excluded, but keys it prefixes are not
+    let start = calc_range_start_human(Some("a".to_string()));
+    assert_eq!(
+        range_keys(&keys, start, None, Order::Ascending),
+        vec![b"ab".to_vec(), b"b".to_vec(), b"ba".to_vec()]
+    );
+
+    // a key that is not stored continues at the next one
+    let start = calc_range_start_human(Some("aa".to_string()));
+    assert_eq!(
+        range_keys(&keys, start, None, Order::Ascending),
+        vec![b"ab".to_vec(), b"b".to_vec(), b"ba".to_vec()]
+    );
+
+    // canonical addresses use their raw bytes
+    let start = calc_range_start_canonical(Some(CanonicalAddr::from(b"b".to_vec())));
+    assert_eq!(start, Some(vec![b'b', 1u8]));
+    assert_eq!(
+        range_keys(&keys, start, None, Order::Ascending),
+        vec![b"ba".to_vec()]
+    );
+}
+
+#[test]
+fn pagination_range_bounds() {
+    let keys: Vec<&[u8]> = vec![b"", b"a", b"ab", b"b", b"ba"];
+
+    // ascending matches calc_range_start
+    assert_eq!(
+        calc_range_bounds(Some(b"a".to_vec()), OrderBy::Ascending),
+        (calc_range_start_human(Some("a".to_string())), None)
+    );
+    assert_eq!(calc_range_bounds(None, OrderBy::Ascending), (None, None));
+    assert_eq!(calc_range_bounds(None, OrderBy::Descending), (None, None));
+
+    let range = |start_after: Option<&[u8]>, order_by: OrderBy| {
+        let (start, end) = calc_range_bounds(start_after.map(|k| k.to_vec()), order_by);
+        range_keys(&keys, start, end, order_by.into())
+    };
+
+    assert_eq!(
+        range(None, OrderBy::Descending),
+        vec![
+            b"ba".to_vec(),
+            b"b".to_vec(),
+            b"ab".to_vec(),
+            b"a".to_vec(),
+            b"".to_vec()
+        ]
+    );
+
+    // descending ranges continue before the exact key
+    assert_eq!(
+        range(Some(b"b"), OrderBy::Descending),
+        vec![b"ab".to_vec(), b"a".to_vec(), b"".to_vec()]
+    );
+    assert_eq!(
+        range(Some(b"ab"), OrderBy::Descending),
+        vec![b"a".to_vec(), b"".to_vec()]
+    );
+
+    // nothing comes before the empty key
+    assert_eq!(range(Some(b""), OrderBy::Descending), Vec::<Vec<u8>>::new());
+    assert_eq!(
+        range(Some(b"ba"), OrderBy::Ascending),
+        Vec::<Vec<u8>>::new()
+    );
+}
+
+#[test]
+fn money_market_error_into_std_error() {
+    let err: StdError = MoneyMarketError::EpochNotElapsed { remaining: 5 }.into();
+    assert_eq!(
+        err,
+        StdError::generic_err("An epoch has not passed yet; blocks remaining: 5")
+    );
+
+    // wrapped standard errors are passed through as is
+    let err: StdError = MoneyMarketError::Std(StdError::not_found("price")).into();
+    assert_eq!(err, StdError::not_found("price"));
+}
+
+#[test]
+fn decimal_parameter_validation() {
+    assert_eq!(assert_fraction("rate", Decimal256::zero()), Ok(()));
+    assert_eq!(assert_fraction("rate", Decimal256::one()), Ok(()));
+    assert_eq!(
+        assert_fraction("rate", Decimal256::percent(150)),
+        Err(StdError::generic_err("rate must be within [0, 1], got 1.5"))
+    );
+
+    assert_eq!(
+        assert_positive_fraction("ltv", Decimal256::percent(60)),
+        Ok(())
+    );
+    assert_eq!(assert_positive_fraction("ltv", Decimal256::one()), Ok(()));
+    assert_eq!(
+        assert_positive_fraction("ltv", Decimal256::zero()),
+        Err(StdError::generic_err("ltv must be within (0, 1], got 0"))
+    );
+    assert_eq!(
+        assert_positive_fraction("ltv", Decimal256::percent(101)),
+        Err(StdError::generic_err("ltv must be within (0, 1], got 1.01"))
+    );
+
+    let max = Decimal256::percent(5);
+    assert_eq!(assert_max("rate", Decimal256::percent(5), max), Ok(()));
+    assert_eq!(
+        assert_max("rate", Decimal256::percent(6), max),
+        Err(StdError::generic_err("rate must not exceed 0.05, got 0.06"))
+    );
+}
+
+// integrators send these as the `msg` of a cw20 `Send`
+fn assert_wire_format<T>(msg: T, json: &str)
+where
+    T: serde::Serialize + serde::de::DeserializeOwned + PartialEq + std::fmt::Debug,
+{
+    assert_eq!(
+        String::from_utf8(to_binary(&msg).unwrap().to_vec()).unwrap(),
+        json
+    );
+    assert_eq!(
+        from_binary::<T>(&Binary::from(json.as_bytes())).unwrap(),
+        msg
+    );
+}
+
+#[test]
+fn cw20_hook_msg_wire_format() {
+    assert_wire_format(
+        CustodyCw20HookMsg::DepositCollateral {},
+        r#"{"deposit_collateral":{}}"#,
+    );
+    assert_wire_format(
+        MarketCw20HookMsg::RedeemStable {},
+        r#"{"redeem_stable":{}}"#,
+    );
+    assert_wire_format(
+        LiquidationCw20HookMsg::ExecuteBid {
+            liquidator: "liquidator".to_string(),
+            borrower: "addr0000".to_string(),
+            fee_address: Some("fee".to_string()),
+            repay_address: Some("repay".to_string()),
+        },
+        r#"{"execute_bid":{"liquidator":"liquidator","borrower":"addr0000","fee_address":"fee","repay_address":"repay"}}"#,
+    );
+    assert_wire_format(
+        LiquidationCw20HookMsg::AccumulateBid {
+            liquidator: "liquidator".to_string(),
+            borrower: "addr0000".to_string(),
+            fee_address: None,
+            repay_address: None,
+        },
+        r#"{"accumulate_bid":{"liquidator":"liquidator","borrower":"addr0000","fee_address":null,"repay_address":null}}"#,
+    );
+}
+
+#[test]
+fn shared_msg_types() {
+    // every contract re-exports its module from msg.rs, so clients can build
+    // and parse all messages and query responses through this package alone
+    fn shared<T: Serialize + DeserializeOwned + JsonSchema>() {}
+
+    shared::<crate::custody::InstantiateMsg>();
+    shared::<crate::custody::ExecuteMsg>();
+    shared::<crate::custody::Cw20HookMsg>();
+    shared::<crate::custody::QueryMsg>();
+    shared::<crate::custody::BorrowerResponse>();
+    shared::<crate::custody::BorrowersResponse>();
+    shared::<crate::custody::ConfigResponse>();
+    shared::<crate::custody::InvariantResponse>();
+    shared::<crate::custody::MigrateMsg>();
+
+    shared::<crate::distribution_model::InstantiateMsg>();
+    shared::<crate::distribution_model::ExecuteMsg>();
+    shared::<crate::distribution_model::QueryMsg>();
+    shared::<crate::distribution_model::ConfigResponse>();
+    shared::<crate::distribution_model::AncEmissionRateResponse>();
+    shared::<crate::distribution_model::DepositRateBoundsResponse>();
+    shared::<crate::distribution_model::StateResponse>();
+    shared::<crate::distribution_model::EmissionHistoryResponse>();
+
+    shared::<crate::interest_model::InstantiateMsg>();
+    shared::<crate::interest_model::ExecuteMsg>();
+    shared::<crate::interest_model::QueryMsg>();
+    shared::<crate::interest_model::ConfigResponse>();
+    shared::<crate::interest_model::ModelInfoResponse>();
+    shared::<crate::interest_model::BorrowRateResponse>();
+    shared::<crate::interest_model::SimulateBorrowRateResponse>();
+    shared::<crate::interest_model::ProjectedRatesResponse>();
+
+    shared::<crate::liquidation::InstantiateMsg>();
+    shared::<crate::liquidation::ExecuteMsg>();
+    shared::<crate::liquidation::Cw20HookMsg>();
+    shared::<crate::liquidation::QueryMsg>();
+    shared::<crate::liquidation::ConfigResponse>();
+    shared::<crate::liquidation::BidResponse>();
+    shared::<crate::liquidation::BidsResponse>();
+    shared::<crate::liquidation::BidPoolResponse>();
+    shared::<crate::liquidation::BidPoolsResponse>();
+    shared::<crate::liquidation::CollateralInfoResponse>();
+    shared::<crate::liquidation::LiquidationAmountResponse>();
+    shared::<crate::liquidation::SimulateExecuteResponse>();
+    shared::<crate::liquidation::LiquidityDepthResponse>();
+    shared::<crate::liquidation::EscrowHealthResponse>();
+    shared::<crate::liquidation::MigrateMsg>();
+
+    shared::<crate::market::InstantiateMsg>();
+    shared::<crate::market::ExecuteMsg>();
+    shared::<crate::market::Cw20HookMsg>();
+    shared::<crate::market::QueryMsg>();
+    shared::<crate::market::ConfigResponse>();
+    shared::<crate::market::StateResponse>();
+    shared::<crate::market::EpochStateResponse>();
+    shared::<crate::market::BorrowerInfoResponse>();
+    shared::<crate::market::BorrowerInfosResponse>();
+    shared::<crate::market::AccruedInterestResponse>();
+    shared::<crate::market::RatesResponse>();
+
+    shared::<crate::oracle::InstantiateMsg>();
+    shared::<crate::oracle::ExecuteMsg>();
+    shared::<crate::oracle::QueryMsg>();
+    shared::<crate::oracle::ConfigResponse>();
+    shared::<crate::oracle::PriceResponse>();
+    shared::<crate::oracle::PricesResponse>();
+    shared::<crate::oracle::PricesForResponse>();
+    shared::<crate::oracle::AssetsResponse>();
+    shared::<crate::oracle::StaleFeedsResponse>();
+    shared::<crate::oracle::StalePricesResponse>();
+    shared::<crate::oracle::PairSourceResponse>();
+    shared::<crate::oracle::FeederResponse>();
+    shared::<crate::oracle::FeederStatsResponse>();
+    shared::<crate::oracle::FeedersResponse>();
+    shared::<crate::oracle::SnapshotPriceResponse>();
+    shared::<crate::oracle::MigrateMsg>();
+
+    shared::<crate::overseer::InstantiateMsg>();
+    shared::<crate::overseer::ExecuteMsg>();
+    shared::<crate::overseer::QueryMsg>();
+    shared::<crate::overseer::AllCollateralsResponse>();
+    shared::<crate::overseer::BorrowLimitResponse>();
+    shared::<crate::overseer::BorrowLimitsResponse>();
+    shared::<crate::overseer::CollateralValueResponse>();
+    shared::<crate::overseer::CollateralsResponse>();
+    shared::<crate::overseer::CollateralsBatchResponse>();
+    shared::<crate::overseer::ConfigResponse>();
+    shared::<crate::overseer::DistributionParamsResponse>();
+    shared::<crate::overseer::EpochStateResponse>();
+    shared::<crate::overseer::WhitelistResponse>();
+}
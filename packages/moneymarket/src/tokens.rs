@@ -11,6 +11,8 @@ pub trait TokensMath {
     fn sub(&mut self, collaterals: Tokens) -> StdResult<()>;
     fn add(&mut self, collaterals: Tokens);
     fn assert_duplicate_token(&self);
+    fn assert_no_duplicates(&self) -> StdResult<()>;
+    fn sort_by_addr(&mut self);
 }
 
 pub trait TokensToHuman {
@@ -23,11 +25,11 @@ pub trait TokensToRaw {
 
 impl TokensMath for Tokens {
     fn sub(&mut self, tokens: Tokens) -> StdResult<()> {
-        self.sort_by(|a, b| a.0.as_slice().cmp(b.0.as_slice()));
+        self.sort_by_addr();
         self.assert_duplicate_token();
 
         let mut tokens = tokens;
-        tokens.sort_by(|a, b| a.0.as_slice().cmp(b.0.as_slice()));
+        tokens.sort_by_addr();
         tokens.assert_duplicate_token();
 
         let mut i = 0;
@@ -35,7 +37,7 @@ impl TokensMath for Tokens {
         while i < self.len() && j < tokens.len() {
             if self[i].0 == tokens[j].0 {
                 if self[i].1 < tokens[j].1 {
-                    return Err(StdError::generic_err("Subtraction underflow"));
+                    return Err(underflow_err(&tokens[j].0));
                 }
 
                 self[i].1 = self[i].1 - tokens[j].1;
@@ -45,12 +47,12 @@ impl TokensMath for Tokens {
             } else if self[i].0.as_slice().cmp(tokens[j].0.as_slice()) == std::cmp::Ordering::Less {
                 i += 1;
             } else {
-                return Err(StdError::generic_err("Subtraction underflow"));
+                return Err(underflow_err(&tokens[j].0));
             }
         }
 
         if j != tokens.len() {
-            return Err(StdError::generic_err("Subtraction underflow"));
+            return Err(underflow_err(&tokens[j].0));
         }
 
         // remove zero tokens
@@ -60,11 +62,11 @@ impl TokensMath for Tokens {
     }
 
     fn add(&mut self, tokens: Tokens) {
-        self.sort_by(|a, b| a.0.as_slice().cmp(b.0.as_slice()));
+        self.sort_by_addr();
         self.assert_duplicate_token();
 
         let mut tokens = tokens;
-        tokens.sort_by(|a, b| a.0.as_slice().cmp(b.0.as_slice()));
+        tokens.sort_by_addr();
         tokens.assert_duplicate_token();
 
         let mut tmp_tokens: Tokens = vec![];
@@ -107,24 +109,45 @@ impl TokensMath for Tokens {
         self.extend(tmp_tokens);
     }
 
+    /// Panics when the (sorted) tokens contain the same address twice
     fn assert_duplicate_token(&self) {
+        if self.assert_no_duplicates().is_err() {
+            panic!("duplicate token address");
+        }
+    }
+
+    /// Errors naming the first address found twice in the (sorted) tokens
+    fn assert_no_duplicates(&self) -> StdResult<()> {
         if self.len() > 1 {
-            let mut before_token = self[0].0.as_slice();
+            let mut before_token = &self[0].0;
 
             let mut i = 1;
             while i < self.len() {
-                let next_token = self[i].0.as_slice();
+                let next_token = &self[i].0;
                 if before_token == next_token {
-                    panic!("duplicate token address");
+                    return Err(StdError::generic_err(format!(
+                        "Duplicate token address: {}",
+                        next_token
+                    )));
                 }
 
                 before_token = next_token;
                 i += 1;
             }
         }
+
+        Ok(())
+    }
+
+    fn sort_by_addr(&mut self) {
+        self.sort_by(|a, b| a.0.as_slice().cmp(b.0.as_slice()));
     }
 }
 
+fn underflow_err(token: &CanonicalAddr) -> StdError {
+    StdError::generic_err(format!("Subtraction underflow: {}", token))
+}
+
 impl TokensToHuman for Tokens {
     fn to_human(&self, deps: Deps) -> StdResult<TokensHuman> {
         let collaterals: TokensHuman = self